@@ -1,6 +1,9 @@
 use crate::AgentPanel;
+use crate::agent_configuration::RunContextServerToolModal;
 use crate::context::{AgentContextHandle, RULES_ICON};
 use crate::context_picker::{ContextPicker, MentionLink};
+use crate::context_server_tool_changes::ContextServerToolsChanged;
+use crate::context_server_tool_error::ContextServerToolError;
 use crate::context_store::ContextStore;
 use crate::context_strip::{ContextStrip, ContextStripEvent, SuggestContextKind};
 use crate::message_editor::insert_message_creases;
@@ -15,22 +18,24 @@ use crate::ui::{
 };
 use anyhow::Context as _;
 use assistant_settings::{AssistantSettings, NotifyWhenAgentWaiting};
-use assistant_tool::ToolUseStatus;
+use assistant_tool::{ToolSource, ToolSourceBadge, ToolUseStatus};
 use collections::{HashMap, HashSet};
+use context_server::ContextServerId;
 use editor::actions::{MoveUp, Paste};
 use editor::scroll::Autoscroll;
 use editor::{Editor, EditorElement, EditorEvent, EditorStyle, MultiBuffer};
 use gpui::{
     AbsoluteLength, Animation, AnimationExt, AnyElement, App, ClickEvent, ClipboardEntry,
-    ClipboardItem, DefiniteLength, EdgesRefinement, Empty, Entity, EventEmitter, Focusable, Hsla,
-    ListAlignment, ListState, MouseButton, PlatformDisplay, ScrollHandle, Stateful,
-    StyleRefinement, Subscription, Task, TextStyle, TextStyleRefinement, Transformation,
-    UnderlineStyle, WeakEntity, WindowHandle, linear_color_stop, linear_gradient, list, percentage,
-    pulsating_between,
+    ClipboardItem, DefiniteLength, EdgesRefinement, ElementId, Empty, Entity, EventEmitter,
+    Focusable, Hsla, ListAlignment, ListState, MouseButton, PlatformDisplay, ScrollHandle,
+    Stateful, StyleRefinement, Subscription, Task, TextStyle, TextStyleRefinement, Transformation,
+    UnderlineStyle, WeakEntity, WindowHandle, img, linear_color_stop, linear_gradient, list,
+    percentage, pulsating_between,
 };
 use language::{Buffer, Language, LanguageRegistry};
 use language_model::{
-    LanguageModelRequestMessage, LanguageModelToolUseId, MessageContent, Role, StopReason,
+    ConfiguredModel, LanguageModelRegistry, LanguageModelRequestMessage,
+    LanguageModelToolSchemaFormat, LanguageModelToolUseId, MessageContent, Role, StopReason,
 };
 use markdown::parser::{CodeBlockKind, CodeBlockMetadata};
 use markdown::{
@@ -39,6 +44,7 @@ use markdown::{
 use project::{ProjectEntryId, ProjectItem as _};
 use rope::Point;
 use settings::{Settings as _, SettingsStore, update_settings_file};
+use std::cell::RefCell;
 use std::ffi::OsStr;
 use std::path::Path;
 use std::rc::Rc;
@@ -47,11 +53,12 @@ use std::time::Duration;
 use text::ToPoint;
 use theme::ThemeSettings;
 use ui::{
-    Disclosure, IconButton, KeyBinding, PopoverMenuHandle, Scrollbar, ScrollbarState, TextSize,
-    Tooltip, prelude::*,
+    ContextMenu, Disclosure, IconButton, KeyBinding, PopoverMenu, PopoverMenuHandle, Scrollbar,
+    ScrollbarState, TextSize, Tooltip, prelude::*,
 };
 use util::ResultExt as _;
 use util::markdown::MarkdownCodeBlock;
+use workspace::notifications::{NotificationId, simple_message_notification::MessageNotification};
 use workspace::{CollaboratorId, Workspace};
 use zed_actions::assistant::OpenRulesLibrary;
 
@@ -72,6 +79,12 @@ pub struct ActiveThread {
     rendered_tool_uses: HashMap<LanguageModelToolUseId, RenderedToolUse>,
     editing_message: Option<(MessageId, EditingMessageState)>,
     expanded_tool_uses: HashMap<LanguageModelToolUseId, bool>,
+    /// The diffs shown in place of tool results, against the result of the previous identical
+    /// call, for the tool uses where the user asked for them.
+    tool_result_diffs: HashMap<LanguageModelToolUseId, Entity<Markdown>>,
+    /// The sources listed under each answer, kept until the thread's messages or tool uses
+    /// change, since finding them means searching every result the answer is based on.
+    citations: RefCell<HashMap<MessageId, Rc<[String]>>>,
     expanded_thinking_segments: HashMap<(MessageId, usize), bool>,
     expanded_code_blocks: HashMap<(MessageId, usize), bool>,
     last_error: Option<ThreadError>,
@@ -641,6 +654,23 @@ fn render_code_language(
         .into_any_element()
 }
 
+fn render_tool_source_badge(badge: &ToolSourceBadge) -> impl IntoElement {
+    h_flex()
+        .flex_none()
+        .gap_1()
+        .children(
+            badge
+                .icon
+                .clone()
+                .map(|icon| img(icon).size(rems_from_px(12.)).flex_none()),
+        )
+        .child(
+            Label::new(badge.name.clone())
+                .size(LabelSize::XSmall)
+                .color(Color::Muted),
+        )
+}
+
 fn open_markdown_link(
     text: SharedString,
     workspace: WeakEntity<Workspace>,
@@ -768,6 +798,7 @@ impl ActiveThread {
             cx.observe(&thread, |_, _, cx| cx.notify()),
             cx.subscribe_in(&thread, window, Self::handle_thread_event),
             cx.subscribe(&thread_store, Self::handle_rules_loading_error),
+            cx.subscribe(&thread_store, Self::handle_context_server_tools_changed),
             cx.observe_global::<SettingsStore>(|_, cx| cx.notify()),
         ];
 
@@ -790,6 +821,8 @@ impl ActiveThread {
             rendered_messages_by_id: HashMap::default(),
             rendered_tool_uses: HashMap::default(),
             expanded_tool_uses: HashMap::default(),
+            tool_result_diffs: HashMap::default(),
+            citations: RefCell::default(),
             expanded_thinking_segments: HashMap::default(),
             expanded_code_blocks: HashMap::default(),
             list_state: list_state.clone(),
@@ -962,6 +995,17 @@ impl ActiveThread {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if matches!(
+            event,
+            ThreadEvent::MessageAdded(_)
+                | ThreadEvent::MessageEdited(_)
+                | ThreadEvent::MessageDeleted(_)
+                | ThreadEvent::UsePendingTools { .. }
+                | ThreadEvent::StreamedToolUse { .. }
+                | ThreadEvent::ToolFinished { .. }
+        ) {
+            self.citations.get_mut().clear();
+        }
         match event {
             ThreadEvent::CancelEditing => {
                 if self.editing_message.is_some() {
@@ -1013,6 +1057,15 @@ impl ActiveThread {
             ThreadEvent::ToolConfirmationNeeded => {
                 self.show_notification("Waiting for tool confirmation", IconName::Info, window, cx);
             }
+            ThreadEvent::ToolCallBudgetExceeded => {
+                self.show_notification("Tool call budget exceeded", IconName::Warning, window, cx);
+            }
+            ThreadEvent::LongToolCallFinished {
+                tool_name,
+                succeeded,
+            } => {
+                self.notify_tool_call_finished(tool_name, *succeeded, window, cx);
+            }
             ThreadEvent::StreamedAssistantText(message_id, text) => {
                 if let Some(rendered_message) = self.rendered_messages_by_id.get_mut(&message_id) {
                     rendered_message.append_text(text, cx);
@@ -1147,6 +1200,101 @@ impl ActiveThread {
         cx.notify();
     }
 
+    fn handle_context_server_tools_changed(
+        &mut self,
+        _thread_store: Entity<ThreadStore>,
+        tools_changed: &ContextServerToolsChanged,
+        cx: &mut Context<Self>,
+    ) {
+        struct ContextServerToolsChangedNotification;
+
+        self.thread.update(cx, |thread, cx| {
+            thread.context_server_tools_changed(tools_changed, cx)
+        });
+        let notification_id = NotificationId::composite::<ContextServerToolsChangedNotification>(
+            SharedString::from(tools_changed.server_id.0.clone()),
+        );
+        let message = tools_changed.summary();
+        self.workspace
+            .update(cx, |workspace, cx| {
+                workspace.show_notification(notification_id, cx, |cx| {
+                    cx.new(|cx| MessageNotification::new(message, cx))
+                })
+            })
+            .ok();
+    }
+
+    /// Lets the user know that a long tool call finished, unless they're looking at the agent
+    /// panel. While Zed is in the background, that's a pop-up like the ones for the agent waiting
+    /// on the user, and otherwise a notification in the workspace.
+    fn notify_tool_call_finished(
+        &mut self,
+        tool_name: &str,
+        succeeded: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let (caption, icon) = if succeeded {
+            (format!("Tool call `{tool_name}` finished"), IconName::Check)
+        } else {
+            (format!("Tool call `{tool_name}` failed"), IconName::XCircle)
+        };
+        if !window.is_window_active() {
+            self.show_notification(caption, icon, window, cx);
+            return;
+        }
+
+        let Some(workspace) = self.workspace.upgrade() else {
+            return;
+        };
+        let is_panel_focused = workspace
+            .read(cx)
+            .panel::<AgentPanel>(cx)
+            .is_some_and(|panel| panel.focus_handle(cx).contains_focused(window, cx));
+        if is_panel_focused {
+            return;
+        }
+
+        struct ToolCallFinishedNotification;
+
+        let workspace_handle = self.workspace.clone();
+        let thread_id = self.thread.read(cx).id().clone();
+        workspace.update(cx, |workspace, cx| {
+            workspace.show_notification(
+                NotificationId::unique::<ToolCallFinishedNotification>(),
+                cx,
+                |cx| {
+                    cx.new(|cx| {
+                        MessageNotification::new(caption, cx)
+                            .primary_message("View Thread")
+                            .primary_on_click(move |window, cx| {
+                                workspace_handle
+                                    .update(cx, |workspace, cx| {
+                                        // The panel may have switched to another thread since
+                                        // the call started.
+                                        if let Some(panel) = workspace.panel::<AgentPanel>(cx) {
+                                            let is_open =
+                                                panel.read(cx).active_thread().is_some_and(
+                                                    |thread| thread.read(cx).id() == &thread_id,
+                                                );
+                                            if !is_open {
+                                                panel.update(cx, |panel, cx| {
+                                                    panel
+                                                        .open_thread_by_id(&thread_id, window, cx)
+                                                        .detach_and_log_err(cx)
+                                                });
+                                            }
+                                        }
+                                        workspace.focus_panel::<AgentPanel>(window, cx);
+                                    })
+                                    .ok();
+                            })
+                    })
+                },
+            )
+        });
+    }
+
     fn show_notification(
         &mut self,
         caption: impl Into<SharedString>,
@@ -1265,10 +1413,37 @@ impl ActiveThread {
         }
     }
 
+    fn replay_request(
+        &mut self,
+        message_id: MessageId,
+        configured_model: ConfiguredModel,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(replay) = self.thread.update(cx, |thread, cx| {
+            thread.replay_request(message_id, configured_model, cx)
+        }) else {
+            return;
+        };
+        // Opening the replay replaces this view, so it has to happen outside of this update.
+        let workspace = self.workspace.clone();
+        window.defer(cx, move |window, cx| {
+            if let Some(workspace) = workspace.upgrade() {
+                if let Some(panel) = workspace.read(cx).panel::<AgentPanel>(cx) {
+                    panel.update(cx, |panel, cx| panel.open_thread(replay, window, cx));
+                }
+            }
+        });
+    }
+
     /// Spawns a task to save the active thread.
     ///
     /// Only one task to save the thread will be in flight at a time.
     fn save_thread(&mut self, cx: &mut Context<Self>) {
+        // Replays are scratch threads for debugging, so they're kept out of the history.
+        if self.thread.read(cx).is_replay() {
+            return;
+        }
         let thread = self.thread.clone();
         self.save_thread_task = Some(cx.spawn(async move |this, cx| {
             let task = this
@@ -1786,6 +1961,12 @@ impl ActiveThread {
 
         let tool_uses = thread.tool_uses_for_message(message_id, cx);
         let has_tool_uses = !tool_uses.is_empty();
+        let citations = self
+            .citations
+            .borrow_mut()
+            .entry(message_id)
+            .or_insert_with(|| thread.message_citations(message_id, cx).into())
+            .clone();
         let is_generating = thread.is_generating();
         let is_generating_stale = thread.is_generation_stale().unwrap_or(false);
 
@@ -1819,6 +2000,52 @@ impl ActiveThread {
                 }
             });
 
+        let replay_request = thread.captured_request(message_id).is_some().then(|| {
+            let this = cx.entity().downgrade();
+            PopoverMenu::new(("replay-request-menu", ix))
+                .trigger_with_tooltip(
+                    IconButton::new(("replay-request", ix), IconName::Rerun)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(Color::Ignored),
+                    Tooltip::text("Replay Request with a Model…"),
+                )
+                .menu(move |window, cx| {
+                    let this = this.clone();
+                    Some(ContextMenu::build(
+                        window,
+                        cx,
+                        move |mut menu, _window, cx| {
+                            let providers = LanguageModelRegistry::read_global(cx).providers();
+                            for provider in providers {
+                                if !provider.is_authenticated(cx) {
+                                    continue;
+                                }
+                                menu = menu.header(provider.name().0);
+                                for model in provider.provided_models(cx) {
+                                    let this = this.clone();
+                                    let configured_model = ConfiguredModel {
+                                        provider: provider.clone(),
+                                        model: model.clone(),
+                                    };
+                                    menu = menu.entry(model.name().0, None, move |window, cx| {
+                                        this.update(cx, |this, cx| {
+                                            this.replay_request(
+                                                message_id,
+                                                configured_model.clone(),
+                                                window,
+                                                cx,
+                                            )
+                                        })
+                                        .ok();
+                                    });
+                                }
+                            }
+                            menu
+                        },
+                    ))
+                })
+        });
+
         // For all items that should be aligned with the LLM's response.
         const RESPONSE_PADDING_X: Pixels = px(19.);
 
@@ -1885,7 +2112,8 @@ impl ActiveThread {
                                     );
                                 })),
                         )
-                        .child(open_as_markdown),
+                        .child(open_as_markdown)
+                        .children(replay_request),
                 )
                 .into_any_element(),
             None if AssistantSettings::get_global(cx).enable_feedback =>
@@ -1929,11 +2157,12 @@ impl ActiveThread {
                                     );
                                 })),
                         )
-                        .child(open_as_markdown),
+                        .child(open_as_markdown)
+                        .children(replay_request),
                 )
                 .into_any_element(),
             None => feedback_container
-                .child(h_flex().child(open_as_markdown))
+                .child(h_flex().child(open_as_markdown).children(replay_request))
                 .into_any_element(),
         };
 
@@ -2080,6 +2309,9 @@ impl ActiveThread {
                     parent.children(tool_uses.into_iter().map(|tool_use| {
                         self.render_tool_use(tool_use, window, workspace.clone(), cx)
                     }))
+                })
+                .when(!citations.is_empty(), |parent| {
+                    parent.child(Self::render_citations(ix, citations))
                 }),
             Role::System => div().id(("message-container", ix)).py_1().px_2().child(
                 v_flex()
@@ -2647,6 +2879,229 @@ impl ActiveThread {
         })
     }
 
+    /// Renders a button that re-opens the arguments of a context server tool call for editing,
+    /// and runs it again at the end of the thread.
+    fn render_rerun_tool_button(
+        &self,
+        tool_use: &ToolUse,
+        cx: &Context<Self>,
+    ) -> Option<impl IntoElement + use<>> {
+        let ToolSource::ContextServer { id } = &tool_use.source else {
+            return None;
+        };
+        let server_id = ContextServerId(id.to_string().into());
+        let thread = self.thread.read(cx);
+        let tool = thread.tools().read(cx).tool(&tool_use.name, cx)?;
+        let schema_tool = context_server::types::Tool {
+            name: tool.name(),
+            description: Some(tool.description()),
+            input_schema: tool
+                .input_schema(LanguageModelToolSchemaFormat::JsonSchema)
+                .ok()?,
+            annotations: None,
+        };
+        let is_generating = thread.is_generating();
+        let input = tool_use.input.clone();
+        let thread = self.thread.clone();
+        let workspace = self.workspace.clone();
+        Some(
+            IconButton::new("rerun-tool", IconName::Rerun)
+                .icon_size(IconSize::XSmall)
+                .icon_color(Color::Muted)
+                .disabled(is_generating)
+                .tooltip(Tooltip::text("Edit Arguments and Run Again"))
+                .on_click(move |_, window, cx| {
+                    let Some(workspace) = workspace.upgrade() else {
+                        return;
+                    };
+                    let context_server_store =
+                        workspace.read(cx).project().read(cx).context_server_store();
+                    let window_handle = window.window_handle();
+                    let thread = thread.clone();
+                    let tool = tool.clone();
+                    workspace.update(cx, |workspace, cx| {
+                        workspace.toggle_modal(window, cx, |window, cx| {
+                            RunContextServerToolModal::edit_call(
+                                context_server_store,
+                                server_id.clone(),
+                                schema_tool.clone(),
+                                &input,
+                                move |input, _window, cx| {
+                                    thread
+                                        .update(cx, |thread, cx| {
+                                            thread.rerun_tool(tool, input, Some(window_handle), cx)
+                                        })
+                                        .log_err();
+                                },
+                                window,
+                                cx,
+                            )
+                        })
+                    });
+                }),
+        )
+    }
+
+    /// Renders buttons for getting a context server tool's output out of its card, since
+    /// large outputs are hard to read there.
+    fn render_tool_output_actions(
+        &self,
+        tool_use: &ToolUse,
+        cx: &Context<Self>,
+    ) -> Option<impl IntoElement + use<>> {
+        if !matches!(tool_use.source, ToolSource::ContextServer { .. }) {
+            return None;
+        }
+        self.thread.read(cx).output_for_tool(&tool_use.id)?;
+        let title = tool_use.name.clone();
+        let rerun = self.render_rerun_tool_button(tool_use, cx);
+        let diff = self.render_tool_result_diff_button(tool_use, cx);
+        // The output in the thread is cut short when it was too large for the model, so the
+        // buttons read the whole output.
+        let full_output = {
+            let thread = self.thread.clone();
+            let tool_use_id = tool_use.id.clone();
+            move |cx: &App| thread.read(cx).full_output_for_tool(&tool_use_id, cx)
+        };
+        Some(
+            h_flex()
+                .gap_0p5()
+                .children(rerun)
+                .children(diff)
+                .child(
+                    IconButton::new("copy-tool-output", IconName::Copy)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(Color::Muted)
+                        .tooltip(Tooltip::text("Copy Result"))
+                        .on_click({
+                            let full_output = full_output.clone();
+                            move |_, _, cx| {
+                                let output = full_output(cx);
+                                cx.spawn(async move |cx| {
+                                    let output = output.await?;
+                                    cx.update(|cx| {
+                                        cx.write_to_clipboard(ClipboardItem::new_string(
+                                            output.to_string(),
+                                        ))
+                                    })
+                                })
+                                .detach_and_log_err(cx);
+                            }
+                        }),
+                )
+                .child(
+                    IconButton::new("save-tool-output", IconName::Save)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(Color::Muted)
+                        .tooltip(Tooltip::text("Save Result to File"))
+                        .on_click({
+                            let full_output = full_output.clone();
+                            let workspace = self.workspace.clone();
+                            move |_, window, cx| {
+                                if let Some(workspace) = workspace.upgrade() {
+                                    save_tool_output(full_output(cx), workspace, window, cx)
+                                        .detach_and_log_err(cx);
+                                }
+                            }
+                        }),
+                )
+                .child(
+                    IconButton::new("open-tool-output", IconName::ArrowUpRight)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(Color::Muted)
+                        .tooltip(Tooltip::text("Open Result in New Buffer"))
+                        .on_click({
+                            let workspace = self.workspace.clone();
+                            move |_, window, cx| {
+                                if let Some(workspace) = workspace.upgrade() {
+                                    open_tool_output(
+                                        full_output(cx),
+                                        title.clone(),
+                                        workspace,
+                                        window,
+                                        cx,
+                                    )
+                                    .detach_and_log_err(cx);
+                                }
+                            }
+                        }),
+                ),
+        )
+    }
+
+    /// Renders links to the sources the tools found for the answer at `ix`, so that the user can
+    /// check the model's claims against them.
+    fn render_citations(ix: usize, citations: Rc<[String]>) -> impl IntoElement {
+        h_flex()
+            .flex_wrap()
+            .gap_1()
+            .child(
+                Label::new("Sources")
+                    .size(LabelSize::XSmall)
+                    .color(Color::Muted),
+            )
+            .children(
+                citations
+                    .iter()
+                    .cloned()
+                    .enumerate()
+                    .map(move |(citation_ix, citation)| {
+                        // The whole URL is shown, so that a link can't pass for another site.
+                        Button::new(
+                            ElementId::NamedInteger(
+                                format!("citation-{ix}").into(),
+                                citation_ix as u64,
+                            ),
+                            citation.clone(),
+                        )
+                        .label_size(LabelSize::XSmall)
+                        .icon(IconName::ArrowUpRight)
+                        .icon_size(IconSize::XSmall)
+                        .icon_position(IconPosition::End)
+                        .icon_color(Color::Muted)
+                        .tooltip(Tooltip::text(citation.clone()))
+                        .on_click(move |_, _, cx| cx.open_url(&citation))
+                    }),
+            )
+    }
+
+    /// Renders a button toggling a diff between the tool's result and the result of the previous
+    /// call with the same input, such as to see what changed between two polls of a build.
+    fn render_tool_result_diff_button(
+        &self,
+        tool_use: &ToolUse,
+        cx: &Context<Self>,
+    ) -> Option<impl IntoElement + use<>> {
+        let thread = self.thread.read(cx);
+        let previous_output = thread.previous_identical_tool_output(&tool_use.id)?.clone();
+        let output = thread.output_for_tool(&tool_use.id)?.clone();
+        let is_shown = self.tool_result_diffs.contains_key(&tool_use.id);
+        let tool_use_id = tool_use.id.clone();
+        Some(
+            IconButton::new("diff-tool-output", IconName::Diff)
+                .icon_size(IconSize::XSmall)
+                .icon_color(Color::Muted)
+                .toggle_state(is_shown)
+                .tooltip(Tooltip::text(if is_shown {
+                    "Show Result"
+                } else {
+                    "Compare with Previous Identical Call"
+                }))
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    if this.tool_result_diffs.remove(&tool_use_id).is_none() {
+                        let diff = MarkdownCodeBlock {
+                            tag: "diff",
+                            text: &language::unified_diff(&previous_output, &output),
+                        }
+                        .to_string();
+                        let diff = parse_markdown(diff.into(), this.language_registry.clone(), cx);
+                        this.tool_result_diffs.insert(tool_use_id.clone(), diff);
+                    }
+                    cx.notify();
+                })),
+        )
+    }
+
     fn render_tool_use(
         &self,
         tool_use: ToolUse,
@@ -2655,7 +3110,19 @@ impl ActiveThread {
         cx: &mut Context<Self>,
     ) -> impl IntoElement + use<> {
         if let Some(card) = self.thread.read(cx).card_for_tool(&tool_use.id) {
-            return card.render(&tool_use.status, window, workspace, cx);
+            let card = card.render(&tool_use.status, window, workspace, cx);
+            let output_actions = matches!(tool_use.status, ToolUseStatus::Finished(_))
+                .then(|| self.render_tool_output_actions(&tool_use, cx))
+                .flatten();
+            if tool_use.source_badge.is_none() && output_actions.is_none() {
+                return card;
+            }
+            return v_flex()
+                .gap_1()
+                .children(tool_use.source_badge.as_ref().map(render_tool_source_badge))
+                .child(card)
+                .children(output_actions.map(|actions| h_flex().justify_end().child(actions)))
+                .into_any_element();
         }
 
         let is_open = self
@@ -2665,6 +3132,12 @@ impl ActiveThread {
             .unwrap_or_default();
 
         let is_status_finished = matches!(&tool_use.status, ToolUseStatus::Finished(_));
+        let thread = self.thread.read(cx);
+        let tool_use_warning = thread
+            .stale_tool_use(&tool_use.id)
+            .or_else(|| thread.suppressed_tool_use(&tool_use.id))
+            .or_else(|| thread.dangerous_tool_use(&tool_use.id))
+            .cloned();
 
         let fs = self
             .workspace
@@ -2744,15 +3217,24 @@ impl ActiveThread {
                         .border_t_1()
                         .border_color(self.tool_card_border_color(cx))
                         .child(
-                            Label::new("Result")
-                                .size(LabelSize::XSmall)
-                                .color(Color::Muted)
-                                .buffer_font(cx),
+                            h_flex()
+                                .justify_between()
+                                .child(
+                                    Label::new("Result")
+                                        .size(LabelSize::XSmall)
+                                        .color(Color::Muted)
+                                        .buffer_font(cx),
+                                )
+                                .children(self.render_tool_output_actions(&tool_use, cx)),
                         )
                         .child(div().w_full().text_ui_sm(cx).children(
                             rendered_tool_use.as_ref().map(|rendered| {
+                                let output = self
+                                    .tool_result_diffs
+                                    .get(&tool_use.id)
+                                    .unwrap_or(&rendered.output);
                                 MarkdownElement::new(
-                                    rendered.output.clone(),
+                                    output.clone(),
                                     tool_use_markdown_style(window, cx),
                                 )
                                 .code_block_renderer(markdown::CodeBlockRenderer::Default {
@@ -2799,7 +3281,7 @@ impl ActiveThread {
                                 ),
                         ),
                 ),
-                ToolUseStatus::Error(_) => container.child(
+                ToolUseStatus::Error(error) => container.child(
                     results_content_container()
                         .border_t_1()
                         .border_color(self.tool_card_border_color(cx))
@@ -2809,10 +3291,16 @@ impl ActiveThread {
                                 .color(Color::Muted)
                                 .buffer_font(cx),
                         )
-                        .child(
-                            div()
-                                .text_ui_sm(cx)
-                                .children(rendered_tool_use.as_ref().map(|rendered| {
+                        .map(|this| match ContextServerToolError::parse(error) {
+                            Some(error) => this
+                                .child(Label::new(error.summary()).size(LabelSize::Small))
+                                .child(
+                                    Label::new(error.hint())
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted),
+                                ),
+                            None => this.child(div().text_ui_sm(cx).children(
+                                rendered_tool_use.as_ref().map(|rendered| {
                                     MarkdownElement::new(
                                         rendered.output.clone(),
                                         tool_use_markdown_style(window, cx),
@@ -2824,8 +3312,9 @@ impl ActiveThread {
                                         }
                                     })
                                     .into_any_element()
-                                })),
-                        ),
+                                }),
+                            )),
+                        }),
                 ),
                 ToolUseStatus::Pending => container,
                 ToolUseStatus::NeedsConfirmation => container.child(
@@ -2885,6 +3374,12 @@ impl ActiveThread {
                                                 .size(IconSize::XSmall)
                                                 .color(Color::Muted),
                                         )
+                                        .children(
+                                            tool_use
+                                                .source_badge
+                                                .as_ref()
+                                                .map(render_tool_source_badge),
+                                        )
                                         .child(
                                             h_flex().pr_8().text_size(rems(0.8125)).children(
                                                 rendered_tool_use.map(|rendered| MarkdownElement::new(rendered.label, tool_use_markdown_style(window, cx)).on_url_click({let workspace = self.workspace.clone(); move |text, window, cx| {
@@ -2976,6 +3471,12 @@ impl ActiveThread {
                                             .size(IconSize::XSmall)
                                             .color(Color::Muted),
                                     )
+                                    .children(
+                                        tool_use
+                                            .source_badge
+                                            .as_ref()
+                                            .map(render_tool_source_badge),
+                                    )
                                     .child(
                                         h_flex().pr_8().text_ui_sm(cx).children(
                                             rendered_tool_use.map(|rendered| MarkdownElement::new(rendered.label, tool_use_markdown_style(window, cx)).on_url_click({let workspace = self.workspace.clone(); move |text, window, cx| {
@@ -3130,7 +3631,24 @@ impl ActiveThread {
                         )
                     })
             }
-        }).into_any_element()
+        })
+        .when_some(tool_use_warning, |this, reason| {
+            this.child(
+                h_flex()
+                    .gap_1()
+                    .child(
+                        Icon::new(IconName::Warning)
+                            .size(IconSize::XSmall)
+                            .color(Color::Warning),
+                    )
+                    .child(
+                        Label::new(reason)
+                            .size(LabelSize::Small)
+                            .color(Color::Warning),
+                    ),
+            )
+        })
+        .into_any_element()
     }
 
     fn render_rules_item(&self, cx: &Context<Self>) -> AnyElement {
@@ -3256,6 +3774,68 @@ impl ActiveThread {
         _: &ClickEvent,
         window: &mut Window,
         cx: &mut Context<Self>,
+    ) {
+        self.allow_tool_use(tool_use_id, window, cx);
+    }
+
+    fn handle_deny_tool(
+        &mut self,
+        tool_use_id: LanguageModelToolUseId,
+        tool_name: Arc<str>,
+        _: &ClickEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.deny_tool_use(tool_use_id, tool_name, window, cx);
+    }
+
+    /// Allows the oldest tool call that is waiting for confirmation.
+    pub fn approve_tool_call(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(tool_use_id) = self
+            .thread
+            .read(cx)
+            .tools_needing_confirmation()
+            .next()
+            .map(|tool_use| tool_use.id.clone())
+        else {
+            return;
+        };
+        self.allow_tool_use(tool_use_id, window, cx);
+    }
+
+    /// Denies the oldest tool call that is waiting for confirmation.
+    pub fn deny_tool_call(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some((tool_use_id, tool_name)) = self
+            .thread
+            .read(cx)
+            .tools_needing_confirmation()
+            .next()
+            .map(|tool_use| (tool_use.id.clone(), tool_use.name.clone()))
+        else {
+            return;
+        };
+        self.deny_tool_use(tool_use_id, tool_name, window, cx);
+    }
+
+    /// Allows every call waiting for confirmation, except those whose input matched a
+    /// `dangerous_tool_arguments` rule, which have to be allowed one at a time.
+    pub fn approve_all_pending_tool_calls(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let thread = self.thread.read(cx);
+        let tool_use_ids = thread
+            .tools_needing_confirmation()
+            .filter(|tool_use| thread.dangerous_tool_use(&tool_use.id).is_none())
+            .map(|tool_use| tool_use.id.clone())
+            .collect::<Vec<_>>();
+        for tool_use_id in tool_use_ids {
+            self.allow_tool_use(tool_use_id, window, cx);
+        }
+    }
+
+    fn allow_tool_use(
+        &mut self,
+        tool_use_id: LanguageModelToolUseId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
     ) {
         if let Some(PendingToolUseStatus::NeedsConfirmation(c)) = self
             .thread
@@ -3280,11 +3860,10 @@ impl ActiveThread {
         }
     }
 
-    fn handle_deny_tool(
+    fn deny_tool_use(
         &mut self,
         tool_use_id: LanguageModelToolUseId,
         tool_name: Arc<str>,
-        _: &ClickEvent,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
@@ -3502,6 +4081,110 @@ pub(crate) fn open_active_thread_as_markdown(
     })
 }
 
+/// Guesses the language of a tool's output from its contents, for syntax highlighting.
+fn guess_tool_output_language(output: &str) -> Option<&'static str> {
+    let trimmed = output.trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(output).is_ok()
+    {
+        Some("JSON")
+    } else if trimmed.starts_with("diff --git") || trimmed.starts_with("--- ") {
+        Some("Diff")
+    } else if trimmed.starts_with("---\n") || trimmed.starts_with("%YAML") {
+        Some("YAML")
+    } else if trimmed.starts_with('#') || trimmed.contains("\n```") || trimmed.starts_with("```") {
+        Some("Markdown")
+    } else {
+        None
+    }
+}
+
+fn tool_output_buffer(
+    output: Arc<str>,
+    workspace: &Entity<Workspace>,
+    cx: &mut App,
+) -> Task<anyhow::Result<Entity<Buffer>>> {
+    let language = guess_tool_output_language(&output).map(|name| {
+        workspace
+            .read(cx)
+            .app_state()
+            .languages
+            .language_for_name(name)
+    });
+    let create_buffer = workspace
+        .read(cx)
+        .project()
+        .update(cx, |project, cx| project.create_buffer(cx));
+    cx.spawn(async move |cx| {
+        let buffer = create_buffer.await?;
+        let language = match language {
+            Some(language) => language.await.log_err(),
+            None => None,
+        };
+        buffer.update(cx, |buffer, cx| {
+            buffer.set_text(output.as_ref(), cx);
+            if let Some(language) = language {
+                buffer.set_language(Some(language), cx);
+            }
+        })?;
+        Ok(buffer)
+    })
+}
+
+fn save_tool_output(
+    output: Task<anyhow::Result<Arc<str>>>,
+    workspace: Entity<Workspace>,
+    window: &mut Window,
+    cx: &mut App,
+) -> Task<anyhow::Result<()>> {
+    let path = workspace.update(cx, |workspace, cx| {
+        workspace.prompt_for_new_path(window, cx)
+    });
+    window.spawn(cx, async move |cx| {
+        let Some(path) = path.await? else {
+            return Ok(());
+        };
+        let output = output.await?;
+        let buffer = cx
+            .update(|_, cx| tool_output_buffer(output, &workspace, cx))?
+            .await?;
+        workspace
+            .update(cx, |workspace, cx| {
+                workspace
+                    .project()
+                    .update(cx, |project, cx| project.save_buffer_as(buffer, path, cx))
+            })?
+            .await
+    })
+}
+
+fn open_tool_output(
+    output: Task<anyhow::Result<Arc<str>>>,
+    title: SharedString,
+    workspace: Entity<Workspace>,
+    window: &mut Window,
+    cx: &mut App,
+) -> Task<anyhow::Result<()>> {
+    window.spawn(cx, async move |cx| {
+        let output = output.await?;
+        let buffer = cx
+            .update(|_, cx| tool_output_buffer(output, &workspace, cx))?
+            .await?;
+        workspace.update_in(cx, |workspace, window, cx| {
+            let project = workspace.project().clone();
+            let buffer =
+                cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title.to_string()));
+            workspace.add_item_to_active_pane(
+                Box::new(cx.new(|cx| Editor::for_multibuffer(buffer, Some(project), window, cx))),
+                None,
+                true,
+                window,
+                cx,
+            );
+        })
+    })
+}
+
 pub(crate) fn open_context(
     context: &AgentContextHandle,
     workspace: Entity<Workspace>,
@@ -3626,6 +4309,21 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_guess_tool_output_language() {
+        assert_eq!(guess_tool_output_language("[{\"id\": 1}]"), Some("JSON"));
+        assert_eq!(
+            guess_tool_output_language("diff --git a/x b/x\n"),
+            Some("Diff")
+        );
+        assert_eq!(
+            guess_tool_output_language("# Results\n\n- one"),
+            Some("Markdown")
+        );
+        assert_eq!(guess_tool_output_language("{ not json"), None);
+        assert_eq!(guess_tool_output_language("plain text"), None);
+    }
+
     #[gpui::test]
     async fn test_agent_is_unfollowed_after_cancelling_completion(cx: &mut TestAppContext) {
         init_test_settings(cx);
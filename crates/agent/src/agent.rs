@@ -4,25 +4,46 @@ mod agent_diff;
 mod agent_model_selector;
 mod agent_panel;
 mod buffer_codegen;
+mod citations;
 mod context;
 mod context_picker;
+mod context_server_activity_indicator;
 mod context_server_configuration;
+mod context_server_suggestions;
+mod context_server_task;
+mod context_server_toggle;
 mod context_server_tool;
+mod context_server_tool_changes;
+mod context_server_tool_error;
 mod context_store;
 mod context_strip;
+mod dangerous_arguments;
 mod debug;
+mod headless_confirmation;
 mod history_store;
 mod inline_assistant;
 mod inline_prompt_editor;
+mod insert_tool_result_command;
+mod mcp_import;
 mod message_editor;
 mod profile_selector;
+mod prompt_injection;
+mod read_only_tools_button;
+mod report;
+mod secret_redaction;
 mod slash_command_settings;
 mod terminal_codegen;
 mod terminal_inline_assistant;
 mod thread;
 mod thread_history;
 mod thread_store;
+mod tool_call_budget;
 mod tool_compatibility;
+mod tool_permissions;
+mod tool_result_table;
+mod tool_schema_lint;
+mod tool_schema_snapshot;
+mod tool_tracing;
 mod tool_use;
 mod ui;
 
@@ -43,15 +64,20 @@ use settings::{Settings as _, SettingsStore};
 use thread::ThreadId;
 
 pub use crate::active_thread::ActiveThread;
-use crate::agent_configuration::{AddContextServerModal, ManageProfilesModal};
+use crate::agent_configuration::{
+    AddContextServerModal, ManageProfilesModal, RunContextServerToolModal,
+};
 pub use crate::agent_panel::{AgentPanel, ConcreteAssistantPanelDelegate};
 pub use crate::context::{ContextLoadResult, LoadedContext};
+pub use crate::context_server_activity_indicator::ContextServerActivityIndicator;
+pub use crate::headless_confirmation::{HeadlessToolDecision, HeadlessToolPolicy};
 pub use crate::inline_assistant::InlineAssistant;
 use crate::slash_command_settings::SlashCommandSettings;
 pub use crate::thread::{Message, MessageSegment, Thread, ThreadEvent};
 pub use crate::thread_store::{SerializedThread, TextThreadStore, ThreadStore};
 pub use agent_diff::{AgentDiffPane, AgentDiffToolbar};
 pub use context_store::ContextStore;
+pub use read_only_tools_button::ReadOnlyToolsButton;
 pub use ui::preview::{all_agent_previews, get_agent_preview};
 
 actions!(
@@ -86,6 +112,19 @@ actions!(
         Follow,
         ResetTrialUpsell,
         ResetTrialEndUpsell,
+        ToggleReadOnlyTools,
+        ToggleThreadReadOnlyTools,
+        SnapshotToolSchemas,
+        LintToolSchemas,
+        RunMcpTool,
+        ForceMcpTool,
+        AttachMcpServer,
+        ImportMcpServers,
+        ExportToolPermissions,
+        ImportToolPermissions,
+        ApproveToolCall,
+        DenyToolCall,
+        ApproveAllPending,
     ]
 );
 
@@ -134,6 +173,9 @@ pub fn init(
     thread_store::init(cx);
     agent_panel::init(cx);
     context_server_configuration::init(language_registry, cx);
+    context_server_suggestions::init(cx);
+    context_server_task::init(cx);
+    context_server_toggle::init(cx);
 
     register_slash_commands(cx);
     inline_assistant::init(
@@ -151,6 +193,7 @@ pub fn init(
     indexed_docs::init(cx);
     cx.observe_new(AddContextServerModal::register).detach();
     cx.observe_new(ManageProfilesModal::register).detach();
+    cx.observe_new(RunContextServerToolModal::register).detach();
 }
 
 fn init_language_model_settings(cx: &mut App) {
@@ -226,6 +269,10 @@ fn register_slash_commands(cx: &mut App) {
     slash_command_registry
         .register_command(assistant_slash_commands::DiagnosticsSlashCommand, true);
     slash_command_registry.register_command(assistant_slash_commands::FetchSlashCommand, true);
+    slash_command_registry.register_command(
+        insert_tool_result_command::InsertToolResultSlashCommand,
+        false,
+    );
 
     cx.observe_flag::<assistant_slash_commands::StreamingExampleSlashCommandFeatureFlag, _>({
         let slash_command_registry = slash_command_registry.clone();
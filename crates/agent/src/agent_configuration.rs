@@ -1,6 +1,8 @@
 mod add_context_server_modal;
+mod attach_context_server_modal;
 mod configure_context_server_modal;
 mod manage_profiles_modal;
+mod run_context_server_tool_modal;
 mod tool_picker;
 
 use std::{sync::Arc, time::Duration};
@@ -25,8 +27,10 @@ use util::ResultExt as _;
 use zed_actions::ExtensionCategoryFilter;
 
 pub(crate) use add_context_server_modal::AddContextServerModal;
+pub(crate) use attach_context_server_modal::AttachContextServerModal;
 pub(crate) use configure_context_server_modal::ConfigureContextServerModal;
 pub(crate) use manage_profiles_modal::ManageProfilesModal;
+pub(crate) use run_context_server_tool_modal::RunContextServerToolModal;
 
 use crate::AddContextServer;
 
@@ -423,6 +427,23 @@ impl AgentConfiguration {
             .unwrap_or(ContextServerStatus::Stopped);
 
         let is_running = matches!(server_status, ContextServerStatus::Running);
+        let installed_package = self
+            .context_server_store
+            .read(cx)
+            .installed_package(&context_server_id)
+            .cloned();
+        let server_instructions = self
+            .context_server_store
+            .read(cx)
+            .server_instructions(&context_server_id);
+        let duplicates = self
+            .context_server_store
+            .read(cx)
+            .duplicates_of(&context_server_id)
+            .into_iter()
+            .map(|id| id.0.as_ref())
+            .collect::<Vec<_>>()
+            .join(", ");
         let item_id = SharedString::from(context_server_id.0.clone());
 
         let error = if let ContextServerStatus::Error(error) = server_status.clone() {
@@ -446,8 +467,22 @@ impl AgentConfiguration {
 
         let border_color = cx.theme().colors().border.opacity(0.6);
         let success_color = Color::Success.color(cx);
+        let muted_color = Color::Muted.color(cx);
 
         let (status_indicator, tooltip_text) = match server_status {
+            ContextServerStatus::Installing => (
+                Indicator::dot()
+                    .color(Color::Muted)
+                    .with_animation(
+                        SharedString::from(format!("{}-installing", context_server_id.0.clone())),
+                        Animation::new(Duration::from_secs(2))
+                            .repeat()
+                            .with_easing(pulsating_between(0.4, 1.)),
+                        move |this, delta| this.color(muted_color.alpha(delta).into()),
+                    )
+                    .into_any_element(),
+                "Server's package is being installed.",
+            ),
             ContextServerStatus::Starting => (
                 Indicator::dot()
                     .color(Color::Success)
@@ -487,7 +522,9 @@ impl AgentConfiguration {
                     .p_1()
                     .justify_between()
                     .when(
-                        error.is_some() || are_tools_expanded && tool_count > 1,
+                        error.is_some()
+                            || are_tools_expanded
+                                && (tool_count > 1 || server_instructions.is_some()),
                         |element| element.border_b_1().border_color(border_color),
                     )
                     .child(
@@ -498,7 +535,7 @@ impl AgentConfiguration {
                                     "tool-list-disclosure",
                                     are_tools_expanded || error.is_some(),
                                 )
-                                .disabled(tool_count == 0)
+                                .disabled(tool_count == 0 && server_instructions.is_none())
                                 .on_click(cx.listener({
                                     let context_server_id = context_server_id.clone();
                                     move |this, _event, _window, _cx| {
@@ -518,6 +555,61 @@ impl AgentConfiguration {
                                     .child(status_indicator),
                             )
                             .child(Label::new(context_server_id.0.clone()).ml_0p5())
+                            .when(!duplicates.is_empty(), |this| {
+                                this.child(
+                                    div()
+                                        .id("duplicate-servers")
+                                        .tooltip(Tooltip::text(
+                                            "These servers launch the same thing, so only this one runs.",
+                                        ))
+                                        .child(
+                                            Label::new(format!("also configured as {duplicates}"))
+                                                .color(Color::Muted)
+                                                .size(LabelSize::Small),
+                                        ),
+                                )
+                            })
+                            .when_some(installed_package, |this, package| {
+                                this.child(
+                                    Label::new(format!("v{}", package.version))
+                                        .color(Color::Muted)
+                                        .size(LabelSize::Small),
+                                )
+                                .when_some(
+                                    package.latest_version,
+                                    |this, latest_version| {
+                                        this.child(
+                                            Button::new(
+                                                "update-package",
+                                                format!("Update to v{latest_version}"),
+                                            )
+                                            .label_size(LabelSize::Small)
+                                            .icon(IconName::Download)
+                                            .icon_size(IconSize::XSmall)
+                                            .icon_position(IconPosition::Start)
+                                            .icon_color(Color::Muted)
+                                            .on_click(
+                                                {
+                                                    let context_server_store =
+                                                        self.context_server_store.clone();
+                                                    let context_server_id =
+                                                        context_server_id.clone();
+                                                    move |_, _window, cx| {
+                                                        context_server_store
+                                                            .update(cx, |store, cx| {
+                                                                store.update_package(
+                                                                    &context_server_id,
+                                                                    cx,
+                                                                )
+                                                            })
+                                                            .detach_and_log_err(cx);
+                                                    }
+                                                },
+                                            ),
+                                        )
+                                    },
+                                )
+                            })
                             .when(is_running, |this| {
                                 this.child(
                                     Label::new(if tool_count == 1 {
@@ -584,32 +676,49 @@ impl AgentConfiguration {
                     );
                 }
 
-                if !are_tools_expanded || tools.is_empty() {
+                if !are_tools_expanded || tools.is_empty() && server_instructions.is_none() {
                     return parent;
                 }
 
-                parent.child(v_flex().py_1p5().px_1().gap_1().children(
-                    tools.into_iter().enumerate().map(|(ix, tool)| {
-                        h_flex()
-                            .id(("tool-item", ix))
-                            .px_1()
-                            .gap_2()
-                            .justify_between()
-                            .hover(|style| style.bg(cx.theme().colors().element_hover))
-                            .rounded_sm()
-                            .child(
-                                Label::new(tool.name())
-                                    .buffer_font(cx)
-                                    .size(LabelSize::Small),
-                            )
-                            .child(
-                                Icon::new(IconName::Info)
-                                    .size(IconSize::Small)
-                                    .color(Color::Ignored),
-                            )
-                            .tooltip(Tooltip::text(tool.description()))
-                    }),
-                ))
+                parent
+                    .when_some(server_instructions, |parent, instructions| {
+                        parent.child(
+                            v_flex()
+                                .pt_1p5()
+                                .px_2()
+                                .gap_0p5()
+                                .child(
+                                    Label::new("Instructions")
+                                        .color(Color::Muted)
+                                        .size(LabelSize::Small),
+                                )
+                                .child(Label::new(instructions).size(LabelSize::Small)),
+                        )
+                    })
+                    .when(!tools.is_empty(), |parent| {
+                        parent.child(v_flex().py_1p5().px_1().gap_1().children(
+                            tools.into_iter().enumerate().map(|(ix, tool)| {
+                                h_flex()
+                                    .id(("tool-item", ix))
+                                    .px_1()
+                                    .gap_2()
+                                    .justify_between()
+                                    .hover(|style| style.bg(cx.theme().colors().element_hover))
+                                    .rounded_sm()
+                                    .child(
+                                        Label::new(tool.name())
+                                            .buffer_font(cx)
+                                            .size(LabelSize::Small),
+                                    )
+                                    .child(
+                                        Icon::new(IconName::Info)
+                                            .size(IconSize::Small)
+                                            .color(Color::Ignored),
+                                    )
+                                    .tooltip(Tooltip::text(tool.description()))
+                            }),
+                        ))
+                    })
             })
     }
 }
@@ -86,8 +86,32 @@ impl AddContextServerModal {
                                 path,
                                 args,
                                 env: None,
+                                cwd: None,
                             }),
                             settings: Some(json!({})),
+                            replay: None,
+                            sandbox: None,
+                            confine_paths_to_worktrees: false,
+                            max_requests_per_minute: None,
+                            max_result_bytes: None,
+                            tool_call_timeout_seconds: None,
+                            keep_warm_seconds: None,
+                            tool_ui_text: Default::default(),
+                            tool_examples: Default::default(),
+                            tool_models: Default::default(),
+                            tool_env: Default::default(),
+                            display_name: None,
+                            icon: None,
+                            openapi: None,
+                            builtin: None,
+                            resolve_command_in_shell: None,
+                            package: None,
+                            confirm_tool_calls: None,
+                            features: Default::default(),
+                            trace_file: None,
+                            tool_name_prefix: None,
+                            instructions: None,
+                            use_server_instructions: None,
                         },
                     );
                 });
@@ -0,0 +1,151 @@
+use gpui::{DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, prelude::*};
+use ui::{KeyBinding, Modal, ModalFooter, ModalHeader, Section, prelude::*};
+use ui_input::SingleLineInput;
+use workspace::ModalView;
+
+use crate::Thread;
+
+/// Attaches an MCP server to a single thread without saving it to the settings, to try out a
+/// server before configuring it for good.
+pub struct AttachContextServerModal {
+    thread: Entity<Thread>,
+    command_editor: Entity<SingleLineInput>,
+    error: Option<SharedString>,
+}
+
+impl AttachContextServerModal {
+    pub fn new(thread: Entity<Thread>, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let command_editor = cx.new(|cx| {
+            SingleLineInput::new(window, cx, "npx -y @example/mcp-server")
+                .label("Command to run, or URL of an OpenAPI document")
+        });
+
+        Self {
+            thread,
+            command_editor,
+            error: None,
+        }
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, cx: &mut Context<Self>) {
+        let command_or_url = self
+            .command_editor
+            .read(cx)
+            .editor()
+            .read(cx)
+            .text(cx)
+            .trim()
+            .to_string();
+        if command_or_url.is_empty() {
+            return;
+        }
+
+        match self.thread.update(cx, |thread, cx| {
+            thread.attach_context_server(&command_or_url, cx)
+        }) {
+            Ok(_) => cx.emit(DismissEvent),
+            Err(error) => {
+                self.error = Some(format!("{error:#}").into());
+                cx.notify();
+            }
+        }
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, cx: &mut Context<Self>) {
+        cx.emit(DismissEvent);
+    }
+}
+
+impl ModalView for AttachContextServerModal {}
+
+impl Focusable for AttachContextServerModal {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.command_editor.focus_handle(cx).clone()
+    }
+}
+
+impl EventEmitter<DismissEvent> for AttachContextServerModal {}
+
+impl Render for AttachContextServerModal {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let is_command_empty = self.command_editor.read(cx).is_empty(cx);
+
+        let focus_handle = self.focus_handle(cx);
+
+        div()
+            .elevation_3(cx)
+            .w(rems(34.))
+            .key_context("AttachContextServerModal")
+            .on_action(
+                cx.listener(|this, _: &menu::Cancel, _window, cx| this.cancel(&menu::Cancel, cx)),
+            )
+            .on_action(
+                cx.listener(|this, _: &menu::Confirm, _window, cx| {
+                    this.confirm(&menu::Confirm, cx)
+                }),
+            )
+            .capture_any_mouse_down(cx.listener(|this, _, window, cx| {
+                this.focus_handle(cx).focus(window);
+            }))
+            .on_mouse_down_out(cx.listener(|_this, _, _, cx| cx.emit(DismissEvent)))
+            .child(
+                Modal::new("attach-context-server", None)
+                    .header(ModalHeader::new().headline("Attach MCP Server to This Thread"))
+                    .section(
+                        Section::new().child(
+                            v_flex()
+                                .gap_2()
+                                .child(self.command_editor.clone())
+                                .child(
+                                    Label::new(
+                                        "The server only runs for this thread, and isn't saved \
+                                        to your settings.",
+                                    )
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted),
+                                )
+                                .children(self.error.clone().map(|error| {
+                                    Label::new(error).size(LabelSize::Small).color(Color::Error)
+                                })),
+                        ),
+                    )
+                    .footer(
+                        ModalFooter::new().end_slot(
+                            h_flex()
+                                .gap_2()
+                                .child(
+                                    Button::new("cancel", "Cancel")
+                                        .key_binding(
+                                            KeyBinding::for_action_in(
+                                                &menu::Cancel,
+                                                &focus_handle,
+                                                window,
+                                                cx,
+                                            )
+                                            .map(|kb| kb.size(rems_from_px(12.))),
+                                        )
+                                        .on_click(cx.listener(|this, _event, _window, cx| {
+                                            this.cancel(&menu::Cancel, cx)
+                                        })),
+                                )
+                                .child(
+                                    Button::new("attach-server", "Attach Server")
+                                        .disabled(is_command_empty)
+                                        .key_binding(
+                                            KeyBinding::for_action_in(
+                                                &menu::Confirm,
+                                                &focus_handle,
+                                                window,
+                                                cx,
+                                            )
+                                            .map(|kb| kb.size(rems_from_px(12.))),
+                                        )
+                                        .on_click(cx.listener(|this, _event, _window, cx| {
+                                            this.confirm(&menu::Confirm, cx)
+                                        })),
+                                ),
+                        ),
+                    ),
+            )
+    }
+}
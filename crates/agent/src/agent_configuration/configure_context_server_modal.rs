@@ -6,6 +6,7 @@ use std::{
 use anyhow::Context as _;
 use context_server::ContextServerId;
 use editor::{Editor, EditorElement, EditorStyle};
+use extension::ContextServerSecretEntry;
 use gpui::{
     Animation, AnimationExt, App, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Task,
     TextStyle, TextStyleRefinement, Transformation, UnderlineStyle, WeakEntity, percentage,
@@ -19,7 +20,7 @@ use project::{
 };
 use settings::{Settings as _, update_settings_file};
 use theme::ThemeSettings;
-use ui::{KeyBinding, Modal, ModalFooter, ModalHeader, Section, Tooltip, prelude::*};
+use ui::{Checkbox, KeyBinding, Modal, ModalFooter, ModalHeader, Section, Tooltip, prelude::*};
 use util::ResultExt;
 use workspace::{ModalView, Workspace};
 
@@ -37,6 +38,13 @@ enum Configuration {
 
 struct ConfigurationRequiredState {
     installation_instructions: Entity<markdown::Markdown>,
+    /// What the extension says the server offers.
+    capabilities: Vec<String>,
+    /// An input for each secret the server needs, which is written to its settings.
+    secret_editors: Vec<(ContextServerSecretEntry, Entity<Editor>)>,
+    /// Whether the agent should ask before calling the server's tools, which starts as the
+    /// settings or the extension say and can be changed before saving.
+    confirm_tool_calls: bool,
     settings_validator: Option<jsonschema::Validator>,
     settings_editor: Entity<Editor>,
     last_error: Option<SharedString>,
@@ -73,6 +81,7 @@ impl ConfigureContextServerModal {
                     context_server_id,
                     repository_url,
                     config,
+                    manifest_entry,
                 ) => {
                     let jsonc_language = jsonc_language.clone();
                     let settings_validator = jsonschema::validator_for(&config.settings_schema)
@@ -87,6 +96,32 @@ impl ConfigureContextServerModal {
                                 cx,
                             )
                         }),
+                        capabilities: manifest_entry.capabilities,
+                        secret_editors: manifest_entry
+                            .secrets
+                            .into_iter()
+                            .map(|secret| {
+                                let editor = cx.new(|cx| {
+                                    let mut editor = Editor::single_line(window, cx);
+                                    editor.set_masked(true, cx);
+                                    editor.set_placeholder_text(
+                                        secret
+                                            .description
+                                            .clone()
+                                            .unwrap_or_else(|| secret.name.clone()),
+                                        cx,
+                                    );
+                                    editor
+                                });
+                                (secret, editor)
+                            })
+                            .collect(),
+                        confirm_tool_calls: ProjectSettings::get_global(cx)
+                            .context_servers
+                            .get(&context_server_id.0)
+                            .and_then(|config| config.confirm_tool_calls)
+                            .or(manifest_entry.confirm_tool_calls)
+                            .unwrap_or(true),
                         settings_validator,
                         settings_editor: cx.new(|cx| {
                             let mut editor = Editor::auto_height(16, window, cx);
@@ -152,7 +187,7 @@ impl ConfigureContextServerModal {
             return;
         }
 
-        let settings_value = match serde_json_lenient::from_str::<serde_json::Value>(
+        let mut settings_value = match serde_json_lenient::from_str::<serde_json::Value>(
             &configuration.settings_editor.read(cx).text(cx),
         ) {
             Ok(value) => value,
@@ -163,6 +198,25 @@ impl ConfigureContextServerModal {
             }
         };
 
+        for (secret, editor) in &configuration.secret_editors {
+            let value = editor.read(cx).text(cx).trim().to_string();
+            let error = match settings_value.as_object_mut() {
+                Some(settings) if !value.is_empty() => {
+                    settings.insert(secret.name.clone(), value.into());
+                    None
+                }
+                Some(settings) if settings.contains_key(&secret.name) => None,
+                Some(_) => Some(format!("Enter the {}.", secret.name)),
+                None => Some("The settings must be an object.".to_string()),
+            };
+            if let Some(error) = error {
+                configuration.last_error = Some(error.into());
+                cx.notify();
+                return;
+            }
+        }
+        let confirm_tool_calls = configuration.confirm_tool_calls;
+
         if let Some(validator) = configuration.settings_validator.as_ref() {
             if let Err(error) = validator.validate(&settings_value) {
                 configuration.last_error = Some(error.to_string().into());
@@ -223,11 +277,15 @@ impl ConfigureContextServerModal {
             |settings, _| {
                 if let Some(server_config) = settings.context_servers.get_mut(&id.0) {
                     server_config.settings = Some(settings_value);
+                    if !confirm_tool_calls || server_config.confirm_tool_calls.is_some() {
+                        server_config.confirm_tool_calls = Some(confirm_tool_calls);
+                    }
                 } else {
                     settings.context_servers.insert(
                         id.0,
                         ContextServerConfiguration {
                             settings: Some(settings_value),
+                            confirm_tool_calls: (!confirm_tool_calls).then_some(false),
                             ..Default::default()
                         },
                     );
@@ -304,6 +362,12 @@ fn wait_for_context_server(
                 _ => {}
             }
         }
+        project::context_server_store::Event::UntrustedProjectServers { .. }
+        | project::context_server_store::Event::UntrustedServers
+        | project::context_server_store::Event::PackageUpdateAvailable { .. }
+        | project::context_server_store::Event::ServerOfflineChanged { .. }
+        | project::context_server_store::Event::ServerRenamed { .. }
+        | project::context_server_store::Event::ToolCall { .. } => {}
     });
 
     cx.spawn(async move |_cx| {
@@ -346,6 +410,67 @@ impl Render for ConfigureContextServerModal {
                                 configuration.installation_instructions.clone(),
                                 default_markdown_style(window, cx),
                             )))
+                            .when(!configuration.capabilities.is_empty(), |this| {
+                                this.child(
+                                    div().pb_2().child(
+                                        Label::new(format!(
+                                            "Offers {}.",
+                                            configuration.capabilities.join(", ")
+                                        ))
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted),
+                                    ),
+                                )
+                            })
+                            .child(
+                                div().pb_2().child(
+                                    Checkbox::new(
+                                        "confirm-tool-calls",
+                                        configuration.confirm_tool_calls.into(),
+                                    )
+                                    .label("Ask before calling this server's tools")
+                                    .on_click(cx.listener(|this, state, _window, cx| {
+                                        if let Some(ContextServerSetup {
+                                            configuration: Configuration::Required(configuration),
+                                            ..
+                                        }) = this.context_servers_to_setup.first_mut()
+                                        {
+                                            configuration.confirm_tool_calls =
+                                                *state == ToggleState::Selected;
+                                            cx.notify();
+                                        }
+                                    })),
+                                ),
+                            )
+                            .children(configuration.secret_editors.iter().map(
+                                |(secret, editor)| {
+                                    v_flex()
+                                        .pb_2()
+                                        .gap_1()
+                                        .child(Label::new(secret.name.clone()).size(LabelSize::Small))
+                                        .child(
+                                            div()
+                                                .px_2()
+                                                .py_1()
+                                                .rounded_md()
+                                                .border_1()
+                                                .border_color(cx.theme().colors().border_variant)
+                                                .bg(cx.theme().colors().editor_background)
+                                                .child(EditorElement::new(
+                                                    editor,
+                                                    EditorStyle {
+                                                        background: cx
+                                                            .theme()
+                                                            .colors()
+                                                            .editor_background,
+                                                        local_player: cx.theme().players().local(),
+                                                        text: buffer_text_style(cx),
+                                                        ..Default::default()
+                                                    },
+                                                )),
+                                        )
+                                },
+                            ))
                             .child(
                                 div()
                                     .p_2()
@@ -354,30 +479,16 @@ impl Render for ConfigureContextServerModal {
                                     .border_color(cx.theme().colors().border_variant)
                                     .bg(cx.theme().colors().editor_background)
                                     .gap_1()
-                                    .child({
-                                        let settings = ThemeSettings::get_global(cx);
-                                        let text_style = TextStyle {
-                                            color: cx.theme().colors().text,
-                                            font_family: settings.buffer_font.family.clone(),
-                                            font_fallbacks: settings.buffer_font.fallbacks.clone(),
-                                            font_size: settings.buffer_font_size(cx).into(),
-                                            font_weight: settings.buffer_font.weight,
-                                            line_height: relative(
-                                                settings.buffer_line_height.value(),
-                                            ),
+                                    .child(EditorElement::new(
+                                        &configuration.settings_editor,
+                                        EditorStyle {
+                                            background: cx.theme().colors().editor_background,
+                                            local_player: cx.theme().players().local(),
+                                            text: buffer_text_style(cx),
+                                            syntax: cx.theme().syntax().clone(),
                                             ..Default::default()
-                                        };
-                                        EditorElement::new(
-                                            &configuration.settings_editor,
-                                            EditorStyle {
-                                                background: cx.theme().colors().editor_background,
-                                                local_player: cx.theme().players().local(),
-                                                text: text_style,
-                                                syntax: cx.theme().syntax().clone(),
-                                                ..Default::default()
-                                            },
-                                        )
-                                    })
+                                        },
+                                    ))
                                     .when_some(configuration.last_error.clone(), |this, error| {
                                         this.child(
                                             h_flex()
@@ -506,6 +617,19 @@ impl Render for ConfigureContextServerModal {
     }
 }
 
+fn buffer_text_style(cx: &App) -> TextStyle {
+    let settings = ThemeSettings::get_global(cx);
+    TextStyle {
+        color: cx.theme().colors().text,
+        font_family: settings.buffer_font.family.clone(),
+        font_fallbacks: settings.buffer_font.fallbacks.clone(),
+        font_size: settings.buffer_font_size(cx).into(),
+        font_weight: settings.buffer_font.weight,
+        line_height: relative(settings.buffer_line_height.value()),
+        ..Default::default()
+    }
+}
+
 pub(crate) fn default_markdown_style(window: &Window, cx: &App) -> MarkdownStyle {
     let theme_settings = ThemeSettings::get_global(cx);
     let colors = cx.theme().colors();
@@ -542,9 +666,12 @@ impl Focusable for ConfigureContextServerModal {
         if let Some(current) = self.context_servers_to_setup.first() {
             match &current.configuration {
                 Configuration::NotAvailable => self.focus_handle.clone(),
-                Configuration::Required(configuration) => {
-                    configuration.settings_editor.read(cx).focus_handle(cx)
-                }
+                Configuration::Required(configuration) => configuration
+                    .secret_editors
+                    .first()
+                    .map_or(&configuration.settings_editor, |(_, editor)| editor)
+                    .read(cx)
+                    .focus_handle(cx),
             }
         } else {
             self.focus_handle.clone()
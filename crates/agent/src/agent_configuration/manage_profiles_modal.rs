@@ -291,8 +291,14 @@ impl ManageProfilesModal {
                         .map(|profile| profile.enable_all_context_servers)
                         .unwrap_or_default(),
                     context_servers: base_profile
-                        .map(|profile| profile.context_servers)
+                        .as_ref()
+                        .map(|profile| profile.context_servers.clone())
                         .unwrap_or_default(),
+                    compact_tool_calls_above_tokens: base_profile
+                        .as_ref()
+                        .and_then(|profile| profile.compact_tool_calls_above_tokens),
+                    active_context_servers: base_profile
+                        .and_then(|profile| profile.active_context_servers),
                 };
 
                 self.create_profile(profile_id.clone(), profile, cx);
@@ -0,0 +1,717 @@
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result, anyhow};
+use collections::HashMap;
+use context_server::{ContextServerId, types};
+use gpui::{
+    DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Task, WeakEntity, prelude::*,
+};
+use picker::{Picker, PickerDelegate};
+use project::context_server_store::ContextServerStore;
+use serde_json::Value;
+use ui::{
+    KeyBinding, ListItem, ListItemSpacing, Modal, ModalFooter, ModalHeader, Section, prelude::*,
+};
+use ui_input::SingleLineInput;
+use util::ResultExt as _;
+use workspace::{ModalView, Workspace};
+
+use crate::RunMcpTool;
+
+/// Lets the user run a context server tool by hand, to check that a server works before the
+/// model is allowed to use it.
+pub struct RunContextServerToolModal {
+    context_server_store: Entity<ContextServerStore>,
+    state: State,
+    focus_handle: FocusHandle,
+    /// When set, picking a tool hands it to this instead of opening the form to run it.
+    on_pick: Option<Box<dyn FnOnce(ContextServerId, types::Tool, &mut Window, &mut App)>>,
+}
+
+enum State {
+    PickingTool(Entity<Picker<ContextServerToolPickerDelegate>>),
+    EditingArguments(ToolForm),
+}
+
+struct ToolForm {
+    server_id: ContextServerId,
+    tool: types::Tool,
+    fields: Vec<(ArgumentSpec, Entity<SingleLineInput>)>,
+    output: Option<Result<ToolOutput, SharedString>>,
+    /// When set, the form hands the arguments to this instead of running the tool itself.
+    on_submit: Option<Box<dyn FnOnce(Value, &mut Window, &mut App)>>,
+    _run: Task<()>,
+}
+
+struct ToolOutput {
+    text: String,
+    is_error: bool,
+}
+
+impl RunContextServerToolModal {
+    pub fn register(
+        workspace: &mut Workspace,
+        _window: Option<&mut Window>,
+        _cx: &mut Context<Workspace>,
+    ) {
+        workspace.register_action(|workspace, _: &RunMcpTool, window, cx| {
+            let context_server_store = workspace.project().read(cx).context_server_store();
+            workspace.toggle_modal(window, cx, |window, cx| {
+                Self::new(context_server_store, window, cx)
+            })
+        });
+    }
+
+    pub fn new(
+        context_server_store: Entity<ContextServerStore>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let delegate = ContextServerToolPickerDelegate {
+            modal: cx.entity().downgrade(),
+            tools: None,
+            matches: Vec::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+
+        let servers = context_server_store
+            .read(cx)
+            .running_servers()
+            .into_iter()
+            .filter(|server| {
+                context_server_store
+                    .read(cx)
+                    .capabilities(&server.id())
+                    .is_some_and(|capabilities| capabilities.tools)
+            })
+            .collect::<Vec<_>>();
+        cx.spawn_in(window, {
+            let picker = picker.downgrade();
+            async move |_, cx| {
+                let mut tools = Vec::new();
+                for server in servers {
+                    let Some(protocol) = server.client() else {
+                        continue;
+                    };
+                    if let Some(response) = protocol.list_tools().await.log_err() {
+                        tools.extend(response.tools.into_iter().map(|tool| (server.id(), tool)));
+                    }
+                }
+                picker
+                    .update_in(cx, |picker, window, cx| {
+                        picker.delegate.tools = Some(tools);
+                        picker.refresh(window, cx);
+                    })
+                    .ok();
+            }
+        })
+        .detach();
+
+        Self {
+            context_server_store,
+            state: State::PickingTool(picker),
+            focus_handle: cx.focus_handle(),
+            on_pick: None,
+        }
+    }
+
+    /// Lists the tools of the running servers for the user to pick one, which is passed to
+    /// `on_pick`.
+    pub fn pick_tool(
+        context_server_store: Entity<ContextServerStore>,
+        on_pick: impl FnOnce(ContextServerId, types::Tool, &mut Window, &mut App) + 'static,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut modal = Self::new(context_server_store, window, cx);
+        modal.on_pick = Some(Box::new(on_pick));
+        modal
+    }
+
+    /// Opens the form for a past call of `tool`, filled with its `arguments`, so that the user
+    /// can edit them and pass them to `on_submit`.
+    pub fn edit_call(
+        context_server_store: Entity<ContextServerStore>,
+        server_id: ContextServerId,
+        tool: types::Tool,
+        arguments: &Value,
+        on_submit: impl FnOnce(Value, &mut Window, &mut App) + 'static,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let focus_handle = cx.focus_handle();
+        let mut form = Self::tool_form(server_id, tool, Some(arguments), window, cx);
+        form.on_submit = Some(Box::new(on_submit));
+        Self::focus_form(&form, &focus_handle, window, cx);
+        Self {
+            context_server_store,
+            state: State::EditingArguments(form),
+            focus_handle,
+            on_pick: None,
+        }
+    }
+
+    fn edit_arguments(
+        &mut self,
+        server_id: ContextServerId,
+        tool: types::Tool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let form = Self::tool_form(server_id, tool, None, window, cx);
+        Self::focus_form(&form, &self.focus_handle, window, cx);
+        self.state = State::EditingArguments(form);
+        cx.notify();
+    }
+
+    fn tool_form(
+        server_id: ContextServerId,
+        tool: types::Tool,
+        arguments: Option<&Value>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> ToolForm {
+        let fields = argument_specs(&tool.input_schema)
+            .into_iter()
+            .map(|spec| {
+                let input = cx.new(|cx| {
+                    SingleLineInput::new(window, cx, spec.placeholder()).label(spec.label())
+                });
+                if let Some(value) = arguments.and_then(|arguments| arguments.get(&spec.name)) {
+                    let text = match value {
+                        Value::Null => String::new(),
+                        Value::String(text) => text.clone(),
+                        value => value.to_string(),
+                    };
+                    let editor = input.read(cx).editor().clone();
+                    editor.update(cx, |editor, cx| editor.set_text(text, window, cx));
+                }
+                (spec, input)
+            })
+            .collect::<Vec<_>>();
+        ToolForm {
+            server_id,
+            tool,
+            fields,
+            output: None,
+            on_submit: None,
+            _run: Task::ready(()),
+        }
+    }
+
+    fn focus_form(form: &ToolForm, focus_handle: &FocusHandle, window: &mut Window, cx: &App) {
+        match form.fields.first() {
+            Some((_, input)) => input.focus_handle(cx).focus(window),
+            None => focus_handle.focus(window),
+        }
+    }
+
+    fn run_tool(&mut self, _: &menu::Confirm, window: &mut Window, cx: &mut Context<Self>) {
+        let State::EditingArguments(form) = &mut self.state else {
+            return;
+        };
+        let arguments = arguments_from_inputs(form.fields.iter().map(|(spec, input)| {
+            let text = input.read(cx).editor().read(cx).text(cx);
+            (spec, text)
+        }));
+        let arguments = match arguments {
+            Ok(arguments) => arguments,
+            Err(error) => {
+                form.output = Some(Err(error.to_string().into()));
+                cx.notify();
+                return;
+            }
+        };
+        if let Some(on_submit) = form.on_submit.take() {
+            on_submit(Value::Object(arguments.into_iter().collect()), window, cx);
+            cx.emit(DismissEvent);
+            return;
+        }
+        let Some(protocol) = self
+            .context_server_store
+            .read(cx)
+            .get_running_server(&form.server_id)
+            .and_then(|server| server.client())
+        else {
+            form.output = Some(Err(format!("{} is not running", form.server_id).into()));
+            cx.notify();
+            return;
+        };
+
+        let tool_name = form.tool.name.clone();
+        form.output = Some(Ok(ToolOutput {
+            text: "Running…".into(),
+            is_error: false,
+        }));
+        form._run = cx.spawn(async move |this, cx| {
+            let response = protocol.run_tool(&tool_name, Some(arguments)).await;
+            this.update(cx, |this, cx| {
+                if let State::EditingArguments(form) = &mut this.state {
+                    form.output = Some(
+                        response
+                            .map(|response| ToolOutput {
+                                text: format_tool_response(&response),
+                                is_error: response.is_error.unwrap_or(false),
+                            })
+                            .map_err(|error| error.to_string().into()),
+                    );
+                    cx.notify();
+                }
+            })
+            .ok();
+        });
+        cx.notify();
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn render_form(
+        &self,
+        form: &ToolForm,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let focus_handle = self.focus_handle(cx);
+        let output = form.output.as_ref().map(|output| {
+            let (text, color) = match output {
+                Ok(output) if output.is_error => (output.text.clone(), Color::Error),
+                Ok(output) => (output.text.clone(), Color::Default),
+                Err(error) => (error.to_string(), Color::Error),
+            };
+            div()
+                .id("tool-output")
+                .max_h(rems(20.))
+                .overflow_y_scroll()
+                .p_2()
+                .rounded_sm()
+                .bg(cx.theme().colors().editor_background)
+                .child(
+                    Label::new(text)
+                        .size(LabelSize::Small)
+                        .color(color)
+                        .buffer_font(cx),
+                )
+        });
+
+        Modal::new("run-context-server-tool", None)
+            .header(
+                ModalHeader::new().headline(format!("Run {} ({})", form.tool.name, form.server_id)),
+            )
+            .section(
+                Section::new().child(
+                    v_flex()
+                        .gap_2()
+                        .when_some(form.tool.description.clone(), |this, description| {
+                            this.child(
+                                Label::new(description)
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted),
+                            )
+                        })
+                        .when(form.fields.is_empty(), |this| {
+                            this.child(
+                                Label::new("This tool takes no arguments").color(Color::Muted),
+                            )
+                        })
+                        .children(form.fields.iter().map(|(_, input)| input.clone()))
+                        .children(output),
+                ),
+            )
+            .footer(
+                ModalFooter::new().end_slot(
+                    h_flex()
+                        .gap_2()
+                        .child(
+                            Button::new("cancel", "Close")
+                                .key_binding(
+                                    KeyBinding::for_action_in(
+                                        &menu::Cancel,
+                                        &focus_handle,
+                                        window,
+                                        cx,
+                                    )
+                                    .map(|kb| kb.size(rems_from_px(12.))),
+                                )
+                                .on_click(cx.listener(|this, _, window, cx| {
+                                    this.cancel(&menu::Cancel, window, cx)
+                                })),
+                        )
+                        .child(
+                            Button::new(
+                                "run-tool",
+                                if form.on_submit.is_some() {
+                                    "Run in Thread"
+                                } else {
+                                    "Run"
+                                },
+                            )
+                            .key_binding(
+                                KeyBinding::for_action_in(
+                                    &menu::Confirm,
+                                    &focus_handle,
+                                    window,
+                                    cx,
+                                )
+                                .map(|kb| kb.size(rems_from_px(12.))),
+                            )
+                            .on_click(cx.listener(
+                                |this, _, window, cx| this.run_tool(&menu::Confirm, window, cx),
+                            )),
+                        ),
+                ),
+            )
+    }
+}
+
+impl ModalView for RunContextServerToolModal {}
+
+impl Focusable for RunContextServerToolModal {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        match &self.state {
+            State::PickingTool(picker) => picker.focus_handle(cx),
+            State::EditingArguments(form) => form
+                .fields
+                .first()
+                .map(|(_, input)| input.focus_handle(cx))
+                .unwrap_or_else(|| self.focus_handle.clone()),
+        }
+    }
+}
+
+impl EventEmitter<DismissEvent> for RunContextServerToolModal {}
+
+impl Render for RunContextServerToolModal {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let content = match &self.state {
+            State::PickingTool(picker) => picker.clone().into_any_element(),
+            State::EditingArguments(form) => div()
+                .elevation_3(cx)
+                .key_context("RunContextServerToolModal")
+                .on_action(cx.listener(Self::run_tool))
+                .on_action(cx.listener(Self::cancel))
+                .child(self.render_form(form, window, cx))
+                .into_any_element(),
+        };
+        v_flex()
+            .w(rems(34.))
+            .track_focus(&self.focus_handle)
+            .on_mouse_down_out(cx.listener(|_, _, _, cx| cx.emit(DismissEvent)))
+            .child(content)
+    }
+}
+
+pub struct ContextServerToolPickerDelegate {
+    modal: WeakEntity<RunContextServerToolModal>,
+    /// The tools of every running server, or `None` while they're being listed.
+    tools: Option<Vec<(ContextServerId, types::Tool)>>,
+    matches: Vec<usize>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for ContextServerToolPickerDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        "Search MCP tools…".into()
+    }
+
+    fn no_matches_text(&self, _window: &mut Window, _cx: &mut App) -> Option<SharedString> {
+        Some(match &self.tools {
+            None => "Loading tools…".into(),
+            Some(tools) if tools.is_empty() => "No running MCP server has any tools".into(),
+            Some(_) => "No matching tools".into(),
+        })
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let query = query.to_lowercase();
+        self.matches = self
+            .tools
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, (server_id, tool))| {
+                tool.name.to_lowercase().contains(&query)
+                    || server_id.0.to_lowercase().contains(&query)
+            })
+            .map(|(ix, _)| ix)
+            .collect();
+        self.selected_index = self
+            .selected_index
+            .min(self.matches.len().saturating_sub(1));
+        Task::ready(())
+    }
+
+    fn confirm(&mut self, _secondary: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        let Some((server_id, tool)) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|ix| self.tools.as_ref()?.get(*ix))
+            .cloned()
+        else {
+            return;
+        };
+        self.modal
+            .update(cx, |modal, cx| {
+                if let Some(on_pick) = modal.on_pick.take() {
+                    on_pick(server_id, tool, window, cx);
+                    cx.emit(DismissEvent);
+                } else {
+                    modal.edit_arguments(server_id, tool, window, cx)
+                }
+            })
+            .log_err();
+    }
+
+    fn dismissed(&mut self, _window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.modal
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let (server_id, tool) = self.tools.as_ref()?.get(*self.matches.get(ix)?)?;
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .toggle_state(selected)
+                .child(
+                    h_flex().gap_2().child(Label::new(tool.name.clone())).child(
+                        Label::new(server_id.to_string())
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    ),
+                ),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArgumentKind {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    /// Objects, arrays and anything else without a simpler input, entered as JSON.
+    Json,
+}
+
+/// A property of a tool's input schema, which gets its own input in the form.
+#[derive(Debug, Clone, PartialEq)]
+struct ArgumentSpec {
+    name: String,
+    kind: ArgumentKind,
+    description: Option<String>,
+    required: bool,
+}
+
+impl ArgumentSpec {
+    fn label(&self) -> String {
+        let kind = match self.kind {
+            ArgumentKind::String => "string",
+            ArgumentKind::Integer => "integer",
+            ArgumentKind::Number => "number",
+            ArgumentKind::Boolean => "true or false",
+            ArgumentKind::Json => "JSON",
+        };
+        let required = if self.required { ", required" } else { "" };
+        format!("{} ({kind}{required})", self.name)
+    }
+
+    fn placeholder(&self) -> String {
+        self.description.clone().unwrap_or_default()
+    }
+}
+
+fn argument_specs(input_schema: &Value) -> Vec<ArgumentSpec> {
+    let Some(properties) = input_schema.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let required = input_schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            // Nullable properties are typed as e.g. `["string", "null"]`.
+            let kind = match property.get("type") {
+                Some(Value::String(kind)) => Some(kind.as_str()),
+                Some(Value::Array(kinds)) => kinds
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find(|kind| *kind != "null"),
+                _ => None,
+            };
+            ArgumentSpec {
+                name: name.clone(),
+                kind: match kind {
+                    Some("string") => ArgumentKind::String,
+                    Some("integer") => ArgumentKind::Integer,
+                    Some("number") => ArgumentKind::Number,
+                    Some("boolean") => ArgumentKind::Boolean,
+                    _ => ArgumentKind::Json,
+                },
+                description: property
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .map(ToString::to_string),
+                required: required.contains(&name.as_str()),
+            }
+        })
+        .collect()
+}
+
+/// Converts the text entered for each argument into a value of its type. Empty optional
+/// arguments are left out.
+fn arguments_from_inputs<'a>(
+    inputs: impl IntoIterator<Item = (&'a ArgumentSpec, String)>,
+) -> Result<HashMap<String, Value>> {
+    let mut arguments = HashMap::default();
+    for (spec, text) in inputs {
+        if text.trim().is_empty() {
+            if spec.required {
+                return Err(anyhow!("{} is required", spec.name));
+            }
+            continue;
+        }
+
+        let value = match spec.kind {
+            ArgumentKind::String => Value::String(text),
+            ArgumentKind::Integer => text
+                .trim()
+                .parse::<i64>()
+                .map(Value::from)
+                .with_context(|| format!("{} must be an integer", spec.name))?,
+            ArgumentKind::Number => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .with_context(|| format!("{} must be a number", spec.name))?,
+            ArgumentKind::Boolean => match text.trim() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => return Err(anyhow!("{} must be true or false", spec.name)),
+            },
+            ArgumentKind::Json => serde_json::from_str(&text)
+                .with_context(|| format!("{} must be valid JSON", spec.name))?,
+        };
+        arguments.insert(spec.name.clone(), value);
+    }
+    Ok(arguments)
+}
+
+fn format_tool_response(response: &types::CallToolResponse) -> String {
+    let parts = response
+        .content
+        .iter()
+        .map(|content| match content {
+            types::ToolResponseContent::Text { text } => text.clone(),
+            types::ToolResponseContent::Image { data, mime_type } => {
+                format!("[{mime_type} image, {} bytes base64-encoded]", data.len())
+            }
+            types::ToolResponseContent::Resource { resource } => {
+                serde_json::to_string_pretty(resource).unwrap_or_default()
+            }
+        })
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "The tool returned no content".into()
+    } else {
+        parts.join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_arguments_from_inputs() {
+        let specs = argument_specs(&json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "The URL to fetch" },
+                "max_length": { "type": ["integer", "null"] },
+                "raw": { "type": "boolean" },
+                "headers": { "type": "object" },
+            },
+            "required": ["url"],
+        }));
+        let spec = |name: &str| specs.iter().find(|spec| spec.name == name).unwrap();
+        assert_eq!(
+            spec("url"),
+            &ArgumentSpec {
+                name: "url".into(),
+                kind: ArgumentKind::String,
+                description: Some("The URL to fetch".into()),
+                required: true,
+            }
+        );
+        assert_eq!(spec("max_length").kind, ArgumentKind::Integer);
+        assert_eq!(spec("headers").kind, ArgumentKind::Json);
+
+        let arguments = arguments_from_inputs([
+            (spec("url"), "https://zed.dev".to_string()),
+            (spec("max_length"), " 100 ".to_string()),
+            (spec("raw"), String::new()),
+            (spec("headers"), r#"{"accept": "text/html"}"#.to_string()),
+        ])
+        .unwrap();
+        assert_eq!(
+            Value::Object(arguments.into_iter().collect()),
+            json!({
+                "url": "https://zed.dev",
+                "max_length": 100,
+                "headers": { "accept": "text/html" },
+            })
+        );
+
+        let error = arguments_from_inputs([(spec("url"), " ".to_string())]).unwrap_err();
+        assert_eq!(error.to_string(), "url is required");
+        let error = arguments_from_inputs([(spec("raw"), "yes".to_string())]).unwrap_err();
+        assert_eq!(error.to_string(), "raw must be true or false");
+    }
+}
@@ -298,6 +298,9 @@ impl PickerDelegate for ToolPickerDelegate {
                                             )
                                         })
                                         .collect(),
+                                    compact_tool_calls_above_tokens: default_profile
+                                        .compact_tool_calls_above_tokens,
+                                    active_context_servers: default_profile.active_context_servers,
                                 });
 
                         if let Some(server_id) = server_id {
@@ -1372,6 +1372,8 @@ impl AgentDiff {
             | ThreadEvent::ToolFinished { .. }
             | ThreadEvent::CheckpointChanged
             | ThreadEvent::ToolConfirmationNeeded
+            | ThreadEvent::ToolCallBudgetExceeded
+            | ThreadEvent::LongToolCallFinished { .. }
             | ThreadEvent::CancelEditing => {}
         }
     }
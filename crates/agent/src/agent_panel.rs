@@ -18,6 +18,7 @@ use assistant_tool::ToolWorkingSet;
 
 use assistant_context_editor::language_model_selector::ToggleModelSelector;
 use client::{UserStore, zed_urls};
+use collections::IndexMap;
 use editor::{Anchor, AnchorRangeExt as _, Editor, EditorEvent, MultiBuffer};
 use fs::Fs;
 use gpui::{
@@ -46,7 +47,8 @@ use ui::{
 use util::{ResultExt as _, maybe};
 use workspace::dock::{DockPosition, Panel, PanelEvent};
 use workspace::{
-    CollaboratorId, DraggedSelection, DraggedTab, ToggleZoom, ToolbarItemView, Workspace,
+    CollaboratorId, DetachAndPromptErr as _, DraggedSelection, DraggedTab, ToggleZoom,
+    ToolbarItemView, Workspace,
 };
 use zed_actions::agent::{OpenConfiguration, OpenOnboardingModal, ResetOnboarding};
 use zed_actions::assistant::{OpenRulesLibrary, ToggleFocus};
@@ -54,20 +56,27 @@ use zed_actions::{DecreaseBufferFontSize, IncreaseBufferFontSize, ResetBufferFon
 use zed_llm_client::UsageLimit;
 
 use crate::active_thread::{self, ActiveThread, ActiveThreadEvent};
-use crate::agent_configuration::{AgentConfiguration, AssistantConfigurationEvent};
+use crate::agent_configuration::{
+    AgentConfiguration, AssistantConfigurationEvent, AttachContextServerModal,
+    RunContextServerToolModal,
+};
 use crate::agent_diff::AgentDiff;
 use crate::history_store::{HistoryStore, RecentEntry};
 use crate::message_editor::{MessageEditor, MessageEditorEvent};
-use crate::thread::{Thread, ThreadError, ThreadId, ThreadSummary, TokenUsageRatio};
+use crate::thread::{Thread, ThreadError, ThreadId, ThreadSummary, TokenUsageRatio, ToolTokenCost};
 use crate::thread_history::{HistoryEntryElement, ThreadHistory};
 use crate::thread_store::ThreadStore;
 use crate::ui::AgentOnboardingModal;
 use crate::{
-    AddContextServer, AgentDiffPane, ContextStore, DeleteRecentlyOpenThread, ExpandMessageEditor,
-    Follow, InlineAssistant, NewTextThread, NewThread, OpenActiveThreadAsMarkdown, OpenAgentDiff,
-    OpenHistory, ResetTrialEndUpsell, ResetTrialUpsell, TextThreadStore, ThreadEvent,
-    ToggleContextPicker, ToggleNavigationMenu, ToggleOptionsMenu,
+    AddContextServer, AgentDiffPane, ApproveAllPending, ApproveToolCall, AttachMcpServer,
+    ContextStore, DeleteRecentlyOpenThread, DenyToolCall, ExpandMessageEditor,
+    ExportToolPermissions, Follow, ForceMcpTool, ImportMcpServers, ImportToolPermissions,
+    InlineAssistant, LintToolSchemas, NewTextThread, NewThread, OpenActiveThreadAsMarkdown,
+    OpenAgentDiff, OpenHistory, ResetTrialEndUpsell, ResetTrialUpsell, SnapshotToolSchemas,
+    TextThreadStore, ThreadEvent, ToggleContextPicker, ToggleNavigationMenu, ToggleOptionsMenu,
+    ToggleReadOnlyTools,
 };
+use crate::{mcp_import, tool_permissions, tool_schema_lint, tool_schema_snapshot};
 
 const AGENT_PANEL_KEY: &str = "agent_panel";
 
@@ -160,6 +169,80 @@ pub fn init(cx: &mut App) {
                 })
                 .register_action(|_workspace, _: &ResetTrialEndUpsell, _window, cx| {
                     TrialEndUpsell::set_dismissed(false, cx);
+                })
+                .register_action(|workspace, _: &SnapshotToolSchemas, window, cx| {
+                    if let Some(panel) = workspace.panel::<AgentPanel>(cx) {
+                        let tools = panel.read(cx).thread_store.read(cx).tools();
+                        tool_schema_snapshot::snapshot_tool_schemas(tools, workspace, window, cx);
+                    }
+                })
+                .register_action(|workspace, _: &LintToolSchemas, window, cx| {
+                    if let Some(panel) = workspace.panel::<AgentPanel>(cx) {
+                        let tools = panel.read(cx).thread_store.read(cx).tools();
+                        tool_schema_lint::lint_tool_schemas(tools, workspace, window, cx);
+                    }
+                })
+                .register_action(|workspace, _: &ForceMcpTool, window, cx| {
+                    let Some(panel) = workspace.panel::<AgentPanel>(cx) else {
+                        return;
+                    };
+                    let thread = panel.read(cx).thread.read(cx).thread().clone();
+                    let context_server_store = workspace.project().read(cx).context_server_store();
+                    workspace.toggle_modal(window, cx, move |window, cx| {
+                        RunContextServerToolModal::pick_tool(
+                            context_server_store,
+                            move |server_id, tool, window, cx| {
+                                thread
+                                    .update(cx, |thread, cx| {
+                                        thread.force_context_server_tool(server_id, tool.name, cx)
+                                    })
+                                    .detach_and_prompt_err(
+                                        "Failed to force the tool",
+                                        window,
+                                        cx,
+                                        |_, _, _| None,
+                                    );
+                            },
+                            window,
+                            cx,
+                        )
+                    });
+                })
+                .register_action(|workspace, _: &AttachMcpServer, window, cx| {
+                    let Some(panel) = workspace.panel::<AgentPanel>(cx) else {
+                        return;
+                    };
+                    let thread = panel.read(cx).thread.read(cx).thread().clone();
+                    workspace.toggle_modal(window, cx, move |window, cx| {
+                        AttachContextServerModal::new(thread, window, cx)
+                    });
+                })
+                .register_action(|workspace, _: &ImportMcpServers, window, cx| {
+                    mcp_import::import_mcp_servers(workspace, window, cx);
+                })
+                .register_action(|workspace, _: &ExportToolPermissions, window, cx| {
+                    tool_permissions::export_tool_permissions(workspace, window, cx);
+                })
+                .register_action(|workspace, _: &ImportToolPermissions, window, cx| {
+                    tool_permissions::import_tool_permissions(workspace, window, cx);
+                })
+                .register_action(
+                    |workspace, _: &zed_actions::ShowEffectiveToolPermissions, window, cx| {
+                        if let Some(panel) = workspace.panel::<AgentPanel>(cx) {
+                            let tools = panel.read(cx).thread_store.read(cx).tools();
+                            tool_permissions::show_effective_tool_permissions(
+                                workspace, tools, window, cx,
+                            );
+                        }
+                    },
+                )
+                .register_action(|workspace, _: &ToggleReadOnlyTools, _window, cx| {
+                    let read_only_tools = !AssistantSettings::get_global(cx).read_only_tools;
+                    update_settings_file::<AssistantSettings>(
+                        workspace.app_state().fs.clone(),
+                        cx,
+                        move |settings, _| settings.set_read_only_tools(read_only_tools),
+                    );
                 });
         },
     )
@@ -1246,6 +1329,12 @@ impl AgentPanel {
         }
     }
 
+    /// Returns the agent thread that the panel shows, or that it showed last while another view
+    /// is open, such as a text thread.
+    pub(crate) fn last_thread(&self, cx: &App) -> Entity<Thread> {
+        self.thread.read(cx).thread().clone()
+    }
+
     pub(crate) fn delete_thread(
         &mut self,
         thread_id: &ThreadId,
@@ -1364,6 +1453,50 @@ fn agent_panel_dock_position(cx: &App) -> DockPosition {
     }
 }
 
+/// Summarizes what the MCP tools of a thread cost per server, naming the most expensive tools.
+fn describe_tool_token_costs(costs: &[ToolTokenCost]) -> String {
+    const TOOLS_PER_SERVER: usize = 3;
+
+    if costs.is_empty() {
+        return "No MCP tools are adding to this conversation.".to_string();
+    }
+    let mut servers = IndexMap::<_, Vec<&ToolTokenCost>>::default();
+    for cost in costs {
+        servers
+            .entry(cost.server_id.clone())
+            .or_default()
+            .push(cost);
+    }
+    let server_total =
+        |costs: &[&ToolTokenCost]| -> usize { costs.iter().map(|cost| cost.total()).sum() };
+    servers.sort_by(|_, a, _, b| server_total(b).cmp(&server_total(a)));
+
+    let total = costs.iter().map(|cost| cost.total()).sum();
+    let mut description = format!(
+        "MCP tools take up about {} tokens:",
+        humanize_token_count(total)
+    );
+    for (server_id, costs) in servers {
+        let definition_tokens = costs.iter().map(|cost| cost.definition_tokens).sum();
+        let result_tokens = costs.iter().map(|cost| cost.result_tokens).sum();
+        let mut tools = costs
+            .iter()
+            .take(TOOLS_PER_SERVER)
+            .map(|cost| format!("{} {}", cost.tool_name, humanize_token_count(cost.total())))
+            .collect::<Vec<_>>();
+        if costs.len() > TOOLS_PER_SERVER {
+            tools.push("…".to_string());
+        }
+        description.push_str(&format!(
+            "\n{server_id}: {} in tool definitions, {} in results ({})",
+            humanize_token_count(definition_tokens),
+            humanize_token_count(result_tokens),
+            tools.join(", ")
+        ));
+    }
+    description
+}
+
 impl EventEmitter<PanelEvent> for AgentPanel {}
 
 impl Panel for AgentPanel {
@@ -1706,6 +1839,9 @@ impl AgentPanel {
                             }),
                         )
                         .action("Add Custom Server…", Box::new(AddContextServer))
+                        .action("Import from Other Apps", Box::new(ImportMcpServers))
+                        .action("Force a Tool for the Next Turn…", Box::new(ForceMcpTool))
+                        .action("Attach a Server to This Thread…", Box::new(AttachMcpServer))
                         .separator();
 
                     if let Some(usage) = last_usage {
@@ -1853,6 +1989,25 @@ impl AgentPanel {
                     .id("token-count")
                     .flex_shrink_0()
                     .gap_0p5()
+                    .when(!is_estimating, |parent| {
+                        let thread = self.thread.read(cx).thread().clone();
+                        parent.tooltip(move |window, cx| {
+                            let thread = thread.read(cx);
+                            let costs = thread
+                                .configured_model()
+                                .map(|model| {
+                                    thread.context_server_tool_token_costs(&model.model, cx)
+                                })
+                                .unwrap_or_default();
+                            Tooltip::with_meta(
+                                "Conversation Tokens",
+                                None,
+                                describe_tool_token_costs(&costs),
+                                window,
+                                cx,
+                            )
+                        })
+                    })
                     .when(!is_generating && is_estimating, |parent| {
                         parent
                             .child(
@@ -2949,6 +3104,23 @@ impl Render for AgentPanel {
             .justify_between()
             .size_full()
             .on_action(cx.listener(Self::cancel))
+            .when(self.has_active_thread(), |this| {
+                this.on_action(cx.listener(|this, _: &ApproveToolCall, window, cx| {
+                    this.thread
+                        .update(cx, |thread, cx| thread.approve_tool_call(window, cx));
+                }))
+                .on_action(cx.listener(|this, _: &DenyToolCall, window, cx| {
+                    this.thread
+                        .update(cx, |thread, cx| thread.deny_tool_call(window, cx));
+                }))
+                .on_action(cx.listener(
+                    |this, _: &ApproveAllPending, window, cx| {
+                        this.thread.update(cx, |thread, cx| {
+                            thread.approve_all_pending_tool_calls(window, cx)
+                        });
+                    },
+                ))
+            })
             .on_action(cx.listener(|this, action: &NewThread, window, cx| {
                 this.new_thread(action, window, cx);
             }))
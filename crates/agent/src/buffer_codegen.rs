@@ -1,19 +1,31 @@
 use crate::context::ContextLoadResult;
+use crate::dangerous_arguments::ToolCallConfirmation;
 use crate::inline_prompt_editor::CodegenStatus;
+use crate::thread::is_read_only_tool;
 use crate::{context::load_context, context_store::ContextStore};
-use anyhow::{Context as _, Result};
+use anyhow::{Context as _, Result, anyhow, bail};
 use assistant_settings::AssistantSettings;
+use assistant_tool::{ActionLog, Tool, ToolSource};
 use client::telemetry::Telemetry;
 use collections::HashSet;
+use context_server::ContextServerId;
 use editor::{Anchor, AnchorRangeExt, MultiBuffer, MultiBufferSnapshot, ToOffset as _, ToPoint};
 use futures::{
-    SinkExt, Stream, StreamExt, TryStreamExt as _, channel::mpsc, future::LocalBoxFuture, join,
+    SinkExt, Stream, StreamExt, TryStreamExt as _,
+    channel::{mpsc, oneshot},
+    future::LocalBoxFuture,
+    join,
+};
+use gpui::{
+    App, AppContext as _, AsyncApp, Context, Entity, EventEmitter, SharedString, Subscription,
+    Task, WeakEntity,
 };
-use gpui::{App, AppContext as _, Context, Entity, EventEmitter, Subscription, Task, WeakEntity};
 use language::{Buffer, IndentKind, Point, TransactionId, line_diff};
 use language_model::{
-    LanguageModel, LanguageModelRegistry, LanguageModelRequest, LanguageModelRequestMessage,
-    LanguageModelTextStream, Role, report_assistant_event,
+    LanguageModel, LanguageModelCompletionEvent, LanguageModelRegistry, LanguageModelRequest,
+    LanguageModelRequestMessage, LanguageModelRequestTool, LanguageModelTextStream,
+    LanguageModelToolChoice, LanguageModelToolResult, LanguageModelToolUse, MessageContent, Role,
+    TokenUsage, report_assistant_event,
 };
 use multi_buffer::MultiBufferRow;
 use parking_lot::Mutex;
@@ -21,6 +33,7 @@ use project::Project;
 use prompt_store::PromptBuilder;
 use prompt_store::PromptStore;
 use rope::Rope;
+use settings::Settings as _;
 use smol::future::FutureExt;
 use std::{
     cmp,
@@ -35,11 +48,17 @@ use std::{
 use streaming_diff::{CharOperation, LineDiff, LineOperation, StreamingDiff};
 use telemetry_events::{AssistantEventData, AssistantKind, AssistantPhase};
 
+/// How many times the model can call tools before it has to answer with what it has, or the
+/// assist fails.
+const MAX_TOOL_ROUNDS: usize = 5;
+
 pub struct BufferCodegen {
     alternatives: Vec<Entity<CodegenAlternative>>,
     pub active_alternative: usize,
     seen_alternatives: HashSet<usize>,
     subscriptions: Vec<Subscription>,
+    /// Watches every alternative for tool calls to confirm, since only the active one shows them.
+    confirmation_subscriptions: Vec<Subscription>,
     buffer: Entity<MultiBuffer>,
     range: Range<Anchor>,
     initial_transaction_id: Option<TransactionId>,
@@ -82,6 +101,7 @@ impl BufferCodegen {
             active_alternative: 0,
             seen_alternatives: HashSet::default(),
             subscriptions: Vec::new(),
+            confirmation_subscriptions: Vec::new(),
             buffer,
             range,
             initial_transaction_id,
@@ -92,9 +112,37 @@ impl BufferCodegen {
             builder,
         };
         this.activate(0, cx);
+        this.observe_tool_confirmations(cx);
         this
     }
 
+    fn observe_tool_confirmations(&mut self, cx: &mut Context<Self>) {
+        self.confirmation_subscriptions = self
+            .alternatives
+            .iter()
+            .map(|alternative| {
+                cx.observe(alternative, |this, _, cx| {
+                    this.activate_alternative_awaiting_confirmation(cx)
+                })
+            })
+            .collect();
+    }
+
+    /// Switches to an alternative that waits for the user to confirm a tool call, unless the
+    /// active one does, so that the call isn't left waiting where the user can't see it.
+    fn activate_alternative_awaiting_confirmation(&mut self, cx: &mut Context<Self>) {
+        if self.tool_confirmation(cx).is_some() {
+            return;
+        }
+        if let Some(index) = self
+            .alternatives
+            .iter()
+            .position(|alternative| alternative.read(cx).tool_confirmation.is_some())
+        {
+            self.activate(index, cx);
+        }
+    }
+
     fn subscribe_to_alternative(&mut self, cx: &mut Context<Self>) {
         let codegen = self.active_alternative().clone();
         self.subscriptions.clear();
@@ -175,6 +223,8 @@ impl BufferCodegen {
             }));
         }
 
+        self.observe_tool_confirmations(cx);
+
         for (model, alternative) in iter::once(primary_model)
             .chain(alternative_models)
             .zip(&self.alternatives)
@@ -228,6 +278,28 @@ impl BufferCodegen {
     pub fn last_equal_ranges<'a>(&self, cx: &'a App) -> &'a [Range<Anchor>] {
         self.active_alternative().read(cx).last_equal_ranges()
     }
+
+    pub fn tool_confirmation<'a>(&self, cx: &'a App) -> Option<&'a ToolConfirmation> {
+        self.active_alternative()
+            .read(cx)
+            .tool_confirmation
+            .as_ref()
+    }
+
+    pub fn respond_to_tool_confirmation(&mut self, allow: bool, cx: &mut Context<Self>) {
+        self.active_alternative().update(cx, |codegen, cx| {
+            codegen.respond_to_tool_confirmation(allow, cx)
+        });
+        self.activate_alternative_awaiting_confirmation(cx);
+    }
+}
+
+/// An MCP tool call that waits for the user to allow it in the inline prompt.
+pub struct ToolConfirmation {
+    pub ui_text: SharedString,
+    /// The dangerous argument rules that the input matches, if any.
+    pub dangerous_arguments: Option<SharedString>,
+    response: oneshot::Sender<bool>,
 }
 
 impl EventEmitter<CodegenEvent> for BufferCodegen {}
@@ -254,6 +326,7 @@ pub struct CodegenAlternative {
     line_operations: Vec<LineOperation>,
     elapsed_time: Option<f64>,
     completion: Option<String>,
+    tool_confirmation: Option<ToolConfirmation>,
     pub message_id: Option<String>,
 }
 
@@ -319,6 +392,7 @@ impl CodegenAlternative {
             range,
             elapsed_time: None,
             completion: None,
+            tool_confirmation: None,
         }
     }
 
@@ -376,6 +450,7 @@ impl CodegenAlternative {
         }
 
         self.edit_position = Some(self.range.start.bias_right(&self.snapshot));
+        self.tool_confirmation = None;
 
         let api_key = model.api_key(cx);
         let telemetry_id = model.telemetry_id();
@@ -384,18 +459,267 @@ impl CodegenAlternative {
             if user_prompt.trim().to_lowercase() == "delete" {
                 async { Ok(LanguageModelTextStream::default()) }.boxed_local()
             } else {
-                let request = self.build_request(&model, user_prompt, cx)?;
-                cx.spawn(async move |_, cx| model.stream_completion_text(request.await, &cx).await)
+                let tools = self.mcp_tools(&model, cx);
+                let request = self.build_request(&model, user_prompt, &tools, cx)?;
+                if tools.is_empty() {
+                    cx.spawn(async move |_, cx| {
+                        model.stream_completion_text(request.await, &cx).await
+                    })
                     .boxed_local()
+                } else {
+                    cx.spawn(async move |codegen, cx| {
+                        Self::call_tools(codegen, request.await, tools, model, cx).await
+                    })
+                    .boxed_local()
+                }
             };
         self.handle_stream(telemetry_id, provider_id.to_string(), api_key, stream, cx);
         Ok(())
     }
 
+    /// Returns the MCP tools that the model can call before answering, when inline assists are
+    /// allowed to use them.
+    fn mcp_tools(&self, model: &Arc<dyn LanguageModel>, cx: &App) -> Vec<Arc<dyn Tool>> {
+        let settings = AssistantSettings::get_global(cx);
+        if !settings.inline_assist_mcp_tools || !model.supports_tools() {
+            return Vec::new();
+        }
+        let Some(thread_store) = self
+            .context_store
+            .as_ref()
+            .and_then(|context_store| context_store.read(cx).thread_store()?.upgrade())
+        else {
+            return Vec::new();
+        };
+        let Some(project) = self.project.upgrade() else {
+            return Vec::new();
+        };
+        let context_server_store = project.read(cx).context_server_store().read(cx);
+        thread_store
+            .read(cx)
+            .tools()
+            .read(cx)
+            .enabled_tools(cx)
+            .into_iter()
+            .filter(|tool| match tool.source() {
+                // Servers added for a single thread aren't meant for inline assists.
+                ToolSource::ContextServer { id } => {
+                    !context_server_store.is_temporary_server(&ContextServerId(id.into()))
+                }
+                ToolSource::Native => false,
+            })
+            .filter(|tool| {
+                !settings.read_only_tools
+                    || is_read_only_tool(tool.as_ref(), &settings.read_only_tool_names)
+            })
+            .filter(|tool| tool.is_available_to(model, cx))
+            .collect()
+    }
+
+    /// Lets the model call `tools` until it answers without calling any, and returns that answer.
+    /// Since the model can write some text before calling tools, only the text of the last
+    /// response is used, which is why the answer isn't streamed.
+    async fn call_tools(
+        codegen: WeakEntity<Self>,
+        mut request: LanguageModelRequest,
+        tools: Vec<Arc<dyn Tool>>,
+        model: Arc<dyn LanguageModel>,
+        cx: &mut AsyncApp,
+    ) -> Result<LanguageModelTextStream> {
+        let last_token_usage = Arc::new(Mutex::new(TokenUsage::default()));
+        let mut round = 0;
+        loop {
+            if round == MAX_TOOL_ROUNDS {
+                request.tool_choice = Some(LanguageModelToolChoice::None);
+            }
+            round += 1;
+
+            let mut events = model.stream_completion(request.clone(), cx).await?;
+            let mut message_id = None;
+            let mut text = String::new();
+            let mut tool_uses = Vec::<LanguageModelToolUse>::new();
+            while let Some(event) = events.next().await {
+                match event? {
+                    LanguageModelCompletionEvent::StartMessage { message_id: id } => {
+                        message_id = Some(id);
+                    }
+                    LanguageModelCompletionEvent::Text(chunk) => text.push_str(&chunk),
+                    LanguageModelCompletionEvent::ToolUse(tool_use) => {
+                        // The input of a tool use can be streamed, in which case the last event
+                        // has all of it.
+                        if let Some(existing) = tool_uses
+                            .iter_mut()
+                            .find(|existing| existing.id == tool_use.id)
+                        {
+                            *existing = tool_use;
+                        } else {
+                            tool_uses.push(tool_use);
+                        }
+                    }
+                    LanguageModelCompletionEvent::UsageUpdate(token_usage) => {
+                        *last_token_usage.lock() = token_usage;
+                    }
+                    LanguageModelCompletionEvent::StatusUpdate(_)
+                    | LanguageModelCompletionEvent::Thinking { .. }
+                    | LanguageModelCompletionEvent::Stop(_) => {}
+                }
+            }
+
+            if tool_uses.is_empty() {
+                return Ok(LanguageModelTextStream {
+                    message_id,
+                    stream: futures::stream::once(async move { Ok(text) }).boxed(),
+                    last_token_usage,
+                });
+            }
+            // Some providers ignore the tool choice, and the model would keep calling tools.
+            if round > MAX_TOOL_ROUNDS {
+                bail!("the model kept calling tools after {MAX_TOOL_ROUNDS} rounds");
+            }
+
+            let request_for_tools = Arc::new(request.clone());
+            let mut tool_use_message = LanguageModelRequestMessage {
+                role: Role::Assistant,
+                content: Vec::new(),
+                cache: false,
+            };
+            if !text.is_empty() {
+                tool_use_message.content.push(MessageContent::Text(text));
+            }
+            let mut tool_result_message = LanguageModelRequestMessage {
+                role: Role::User,
+                content: Vec::new(),
+                cache: false,
+            };
+            for tool_use in tool_uses {
+                let output = Self::call_tool(
+                    &codegen,
+                    &tool_use,
+                    &tools,
+                    request_for_tools.clone(),
+                    &model,
+                    cx,
+                )
+                .await;
+                tool_result_message.content.push(MessageContent::ToolResult(
+                    LanguageModelToolResult {
+                        tool_use_id: tool_use.id.clone(),
+                        tool_name: tool_use.name.clone(),
+                        is_error: output.is_err(),
+                        content: match output {
+                            Ok(output) => output.into(),
+                            Err(error) => format!("{error:#}").into(),
+                        },
+                        output: None,
+                    },
+                ));
+                tool_use_message
+                    .content
+                    .push(MessageContent::ToolUse(tool_use));
+            }
+            request.messages.push(tool_use_message);
+            request.messages.push(tool_result_message);
+        }
+    }
+
+    async fn call_tool(
+        codegen: &WeakEntity<Self>,
+        tool_use: &LanguageModelToolUse,
+        tools: &[Arc<dyn Tool>],
+        request: Arc<LanguageModelRequest>,
+        model: &Arc<dyn LanguageModel>,
+        cx: &mut AsyncApp,
+    ) -> Result<String> {
+        let tool = tools
+            .iter()
+            .find(|tool| tool.name() == tool_use.name.as_ref())
+            .cloned()
+            .ok_or_else(|| {
+                anyhow!(
+                    "The tool '{}' doesn't exist or is not enabled",
+                    tool_use.name
+                )
+            })?;
+        let prepare_input =
+            cx.update(|cx| tool.clone().prepare_input(tool_use.input.clone(), cx))?;
+        let input = match prepare_input {
+            Some(prepare_input) => prepare_input.await?,
+            None => tool_use.input.clone(),
+        };
+        let confirmation = codegen.update(cx, |codegen, cx| {
+            codegen.request_tool_confirmation(tool.as_ref(), &input, cx)
+        })?;
+        if let Some(confirmation) = confirmation {
+            if !confirmation.await.unwrap_or(false) {
+                return Err(anyhow!("Permission to run tool action denied by user"));
+            }
+        }
+
+        let output = codegen.update(cx, |codegen, cx| {
+            let project = codegen.project.upgrade().context("project was dropped")?;
+            let action_log = cx.new(|_| ActionLog::new(project.clone()));
+            anyhow::Ok(
+                tool.run(
+                    tool_use.id.clone(),
+                    input,
+                    request,
+                    project,
+                    action_log,
+                    model.clone(),
+                    None,
+                    cx,
+                )
+                .output,
+            )
+        })??;
+        let output = output.await?;
+        output
+            .content
+            .as_str()
+            .map(ToString::to_string)
+            .context("inline assists can only use text returned by tools")
+    }
+
+    /// Shows the tool call in the inline prompt, when the agent would have to ask before making
+    /// it too, and returns whether the user allowed it.
+    fn request_tool_confirmation(
+        &mut self,
+        tool: &dyn Tool,
+        input: &serde_json::Value,
+        cx: &mut Context<Self>,
+    ) -> Option<oneshot::Receiver<bool>> {
+        let read_only_tools = AssistantSettings::get_global(cx).read_only_tools;
+        let ToolCallConfirmation {
+            needs_confirmation,
+            dangerous_arguments,
+        } = ToolCallConfirmation::new(tool, input, read_only_tools, cx);
+        if !needs_confirmation {
+            return None;
+        }
+
+        let (response, confirmation) = oneshot::channel();
+        self.tool_confirmation = Some(ToolConfirmation {
+            ui_text: tool.ui_text(input).into(),
+            dangerous_arguments: (!dangerous_arguments.is_empty())
+                .then(|| dangerous_arguments.join(", ").into()),
+            response,
+        });
+        cx.notify();
+        Some(confirmation)
+    }
+
+    pub fn respond_to_tool_confirmation(&mut self, allow: bool, cx: &mut Context<Self>) {
+        if let Some(confirmation) = self.tool_confirmation.take() {
+            confirmation.response.send(allow).ok();
+            cx.notify();
+        }
+    }
+
     fn build_request(
         &self,
         model: &Arc<dyn LanguageModel>,
         user_prompt: String,
+        tools: &[Arc<dyn Tool>],
         cx: &mut App,
     ) -> Result<Task<LanguageModelRequest>> {
         let buffer = self.buffer.read(cx).snapshot(cx);
@@ -444,6 +768,16 @@ impl CodegenAlternative {
         });
 
         let temperature = AssistantSettings::temperature_for_model(&model, cx);
+        let tools = tools
+            .iter()
+            .filter_map(|tool| {
+                Some(LanguageModelRequestTool {
+                    name: tool.name(),
+                    description: tool.description(),
+                    input_schema: tool.shared_input_schema(model.tool_input_format()).ok()?,
+                })
+            })
+            .collect::<Vec<_>>();
 
         Ok(cx.spawn(async move |_cx| {
             let mut request_message = LanguageModelRequestMessage {
@@ -465,7 +799,7 @@ impl CodegenAlternative {
                 thread_id: None,
                 prompt_id: None,
                 mode: None,
-                tools: Vec::new(),
+                tools,
                 tool_choice: None,
                 stop: Vec::new(),
                 temperature,
@@ -758,6 +1092,7 @@ impl CodegenAlternative {
             self.status = CodegenStatus::Done;
         }
         self.generation = Task::ready(());
+        self.tool_confirmation = None;
         cx.emit(CodegenEvent::Finished);
         cx.notify();
     }
@@ -1076,22 +1411,29 @@ impl Diff {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assistant_tool::ToolResult;
     use fs::FakeFs;
     use futures::{
-        Stream,
+        Stream, TryStreamExt as _,
         stream::{self},
     };
+    use gpui::AnyWindowHandle;
     use gpui::TestAppContext;
     use indoc::indoc;
     use language::{
         Buffer, Language, LanguageConfig, LanguageMatcher, Point, language_settings,
         tree_sitter_rust,
     };
-    use language_model::{LanguageModelRegistry, TokenUsage};
+    use language_model::{
+        LanguageModelRegistry, LanguageModelToolResultContent, LanguageModelToolUseId, TokenUsage,
+        fake_provider::FakeLanguageModel,
+    };
     use rand::prelude::*;
     use serde::Serialize;
-    use settings::SettingsStore;
+    use serde_json::json;
+    use settings::{Settings, SettingsStore};
     use std::{future, sync::Arc};
+    use ui::IconName;
 
     #[derive(Serialize)]
     pub struct DummyCompletionRequest {
@@ -1469,6 +1811,214 @@ mod tests {
         }
     }
 
+    /// A tool that answers every call with its input.
+    struct EchoTool {
+        needs_confirmation: bool,
+    }
+
+    impl Tool for EchoTool {
+        fn name(&self) -> String {
+            "echo".into()
+        }
+
+        fn description(&self) -> String {
+            String::new()
+        }
+
+        fn icon(&self) -> IconName {
+            IconName::Cog
+        }
+
+        fn needs_confirmation(&self, _: &serde_json::Value, _: &App) -> bool {
+            self.needs_confirmation
+        }
+
+        fn may_perform_edits(&self) -> bool {
+            false
+        }
+
+        fn ui_text(&self, _: &serde_json::Value) -> String {
+            "Echo".into()
+        }
+
+        fn run(
+            self: Arc<Self>,
+            _: LanguageModelToolUseId,
+            input: serde_json::Value,
+            _: Arc<LanguageModelRequest>,
+            _: Entity<Project>,
+            _: Entity<ActionLog>,
+            _: Arc<dyn LanguageModel>,
+            _: Option<AnyWindowHandle>,
+            _: &mut App,
+        ) -> ToolResult {
+            Task::ready(Ok(input.to_string().into())).into()
+        }
+    }
+
+    #[gpui::test]
+    async fn test_call_tools(cx: &mut TestAppContext) {
+        let (_project, codegen, model) = init_tool_calls_test(cx).await;
+        let task = start_tool_calls(&codegen, &model, false, cx);
+
+        model.send_last_completion_stream_event(echo_tool_use("1"));
+        model.end_last_completion_stream();
+        cx.run_until_parked();
+        let result = last_tool_result(&model);
+        assert!(!result.is_error);
+        assert_eq!(
+            result.content,
+            LanguageModelToolResultContent::Text(json!({ "text": "1" }).to_string().into())
+        );
+
+        // The model answers once it stops calling tools.
+        model.stream_last_completion_response("Done".into());
+        model.end_last_completion_stream();
+        let answer = task.await.unwrap().stream.try_concat().await.unwrap();
+        assert_eq!(answer, "Done");
+    }
+
+    #[gpui::test]
+    async fn test_call_tools_denied(cx: &mut TestAppContext) {
+        let (_project, codegen, model) = init_tool_calls_test(cx).await;
+        let _task = start_tool_calls(&codegen, &model, true, cx);
+
+        model.send_last_completion_stream_event(echo_tool_use("1"));
+        model.end_last_completion_stream();
+        cx.run_until_parked();
+        codegen.update(cx, |codegen, cx| {
+            assert_eq!(
+                codegen.tool_confirmation.as_ref().unwrap().ui_text.as_ref(),
+                "Echo"
+            );
+            codegen.respond_to_tool_confirmation(false, cx);
+        });
+        cx.run_until_parked();
+
+        let result = last_tool_result(&model);
+        assert!(result.is_error);
+        assert_eq!(
+            result.content,
+            LanguageModelToolResultContent::Text(
+                "Permission to run tool action denied by user".into()
+            )
+        );
+    }
+
+    #[gpui::test]
+    async fn test_call_tools_stops_after_max_rounds(cx: &mut TestAppContext) {
+        let (_project, codegen, model) = init_tool_calls_test(cx).await;
+        let task = start_tool_calls(&codegen, &model, false, cx);
+
+        for round in 0..=MAX_TOOL_ROUNDS {
+            // The last round asks the model to answer instead of calling tools.
+            let tool_choice = model
+                .pending_completions()
+                .last()
+                .unwrap()
+                .tool_choice
+                .clone();
+            if round == MAX_TOOL_ROUNDS {
+                assert_eq!(tool_choice, Some(LanguageModelToolChoice::None));
+            } else {
+                assert_eq!(tool_choice, None);
+            }
+            model.send_last_completion_stream_event(echo_tool_use(&round.to_string()));
+            model.end_last_completion_stream();
+            cx.run_until_parked();
+        }
+
+        let error = task.await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("the model kept calling tools after {MAX_TOOL_ROUNDS} rounds")
+        );
+        assert_eq!(model.completion_count(), 0);
+    }
+
+    async fn init_tool_calls_test(
+        cx: &mut TestAppContext,
+    ) -> (
+        Entity<Project>,
+        Entity<CodegenAlternative>,
+        Arc<FakeLanguageModel>,
+    ) {
+        init_test(cx);
+        cx.update(AssistantSettings::register);
+        let buffer = cx.new(|cx| Buffer::local("", cx));
+        let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx));
+        let range = buffer.read_with(cx, |buffer, cx| {
+            let snapshot = buffer.snapshot(cx);
+            snapshot.anchor_before(Point::new(0, 0))..snapshot.anchor_after(Point::new(0, 0))
+        });
+        let prompt_builder = Arc::new(PromptBuilder::new(None).unwrap());
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, vec![], cx).await;
+        let codegen = cx.new(|cx| {
+            CodegenAlternative::new(
+                buffer.clone(),
+                range.clone(),
+                true,
+                None,
+                project.downgrade(),
+                None,
+                None,
+                prompt_builder,
+                cx,
+            )
+        });
+        (project, codegen, Arc::new(FakeLanguageModel::default()))
+    }
+
+    fn start_tool_calls(
+        codegen: &Entity<CodegenAlternative>,
+        model: &Arc<FakeLanguageModel>,
+        needs_confirmation: bool,
+        cx: &mut TestAppContext,
+    ) -> Task<Result<LanguageModelTextStream>> {
+        let codegen = codegen.downgrade();
+        let model: Arc<dyn LanguageModel> = model.clone();
+        let tools: Vec<Arc<dyn Tool>> = vec![Arc::new(EchoTool { needs_confirmation })];
+        let task = cx.spawn(|mut cx| async move {
+            CodegenAlternative::call_tools(
+                codegen,
+                LanguageModelRequest::default(),
+                tools,
+                model,
+                &mut cx,
+            )
+            .await
+        });
+        cx.run_until_parked();
+        task
+    }
+
+    fn echo_tool_use(id: &str) -> LanguageModelCompletionEvent {
+        let input = json!({ "text": id });
+        LanguageModelCompletionEvent::ToolUse(LanguageModelToolUse {
+            id: id.into(),
+            name: "echo".into(),
+            raw_input: input.to_string(),
+            input,
+            is_input_complete: true,
+        })
+    }
+
+    fn last_tool_result(model: &FakeLanguageModel) -> LanguageModelToolResult {
+        let request = model.pending_completions().pop().unwrap();
+        request
+            .messages
+            .last()
+            .unwrap()
+            .content
+            .iter()
+            .find_map(|content| match content {
+                MessageContent::ToolResult(result) => Some(result.clone()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
     fn init_test(cx: &mut TestAppContext) {
         cx.update(LanguageModelRegistry::test);
         cx.set_global(cx.update(SettingsStore::test));
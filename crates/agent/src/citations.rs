@@ -0,0 +1,55 @@
+use std::sync::LazyLock;
+
+use collections::HashSet;
+use regex::Regex;
+
+/// Web URLs, such as `https://example.com/page`. Other schemes, like `file://` or those of apps,
+/// are left out, since opening them can do more than show a page. Closing brackets are left out,
+/// so that links in Markdown and parenthesized URLs end where they should.
+static URI_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\bhttps?://[^\s<>"'`()\[\]{}]+"#).unwrap());
+
+/// Returns the web URLs in `texts`, in the order they first appear, so that the user can check
+/// where the model's claims came from.
+pub fn extract_citations<'a>(texts: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::default();
+    let mut citations = Vec::new();
+    for text in texts {
+        for uri in URI_PATTERN.find_iter(text) {
+            // Punctuation right after a URL usually ends the sentence around it.
+            let uri = uri
+                .as_str()
+                .trim_end_matches(|c| matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '*'));
+            if uri.ends_with("://") {
+                continue;
+            }
+            if seen.insert(uri.to_string()) {
+                citations.push(uri.to_string());
+            }
+        }
+    }
+    citations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_citations() {
+        assert_eq!(
+            extract_citations([
+                "1. [Rust](https://www.rust-lang.org/) - A language.\n\
+                 2. See https://doc.rust-lang.org/book/, or https://www.rust-lang.org/.",
+                r#"{"uri": "file:///home/user/notes.md", "mime_type": "text/markdown"}"#,
+                "Open vscode://command/run or HTTP://EXAMPLE.COM/docs.",
+                "No links here, just a ratio 3:1 and an empty https:// prefix.",
+            ]),
+            [
+                "https://www.rust-lang.org/",
+                "https://doc.rust-lang.org/book/",
+                "HTTP://EXAMPLE.COM/docs",
+            ]
+        );
+    }
+}
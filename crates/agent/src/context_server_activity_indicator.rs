@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use context_server::ContextServerId;
+use gpui::{Action as _, Entity, Subscription};
+use project::context_server_store::{self, ContextServerStatus, ContextServerStore, ToolCallEvent};
+use ui::{ContextMenu, PopoverMenu, Tooltip, prelude::*};
+use util::truncate_and_trailoff;
+use workspace::{ItemHandle, StatusItemView, Workspace};
+use zed_actions::dev::OpenContextServerInspector;
+
+const MAX_PROGRESS_LEN: usize = 60;
+
+/// A status bar item counting the MCP tool calls that are running and the servers that aren't
+/// healthy, so that long tool calls can be followed and cancelled outside of their thread.
+pub struct ContextServerActivityIndicator {
+    context_server_store: Entity<ContextServerStore>,
+    /// The running calls, in the order they started.
+    running_tool_calls: Vec<RunningToolCall>,
+    _subscription: Subscription,
+}
+
+struct RunningToolCall {
+    call_id: Arc<str>,
+    server_id: ContextServerId,
+    tool_name: Arc<str>,
+    progress: Option<String>,
+}
+
+impl ContextServerActivityIndicator {
+    pub fn new(workspace: &Workspace, cx: &mut Context<Self>) -> Self {
+        let context_server_store = workspace.project().read(cx).context_server_store();
+        let subscription = cx.subscribe(&context_server_store, |this, _, event, cx| {
+            match event {
+                context_server_store::Event::ToolCall {
+                    server_id,
+                    tool_name,
+                    call_id,
+                    event,
+                } => this.tool_call_changed(server_id, tool_name, call_id, event),
+                context_server_store::Event::ServerStatusChanged { .. }
+                | context_server_store::Event::ServerOfflineChanged { .. } => {}
+                context_server_store::Event::UntrustedProjectServers { .. }
+                | context_server_store::Event::UntrustedServers
+                | context_server_store::Event::PackageUpdateAvailable { .. }
+                | context_server_store::Event::ServerRenamed { .. } => return,
+            }
+            cx.notify();
+        });
+        Self {
+            context_server_store,
+            running_tool_calls: Vec::new(),
+            _subscription: subscription,
+        }
+    }
+
+    fn tool_call_changed(
+        &mut self,
+        server_id: &ContextServerId,
+        tool_name: &Arc<str>,
+        call_id: &Arc<str>,
+        event: &ToolCallEvent,
+    ) {
+        match event {
+            ToolCallEvent::Running { progress } => {
+                if let Some(call) = self
+                    .running_tool_calls
+                    .iter_mut()
+                    .find(|call| &call.call_id == call_id)
+                {
+                    if progress.is_some() {
+                        call.progress = progress.clone();
+                    }
+                } else {
+                    self.running_tool_calls.push(RunningToolCall {
+                        call_id: call_id.clone(),
+                        server_id: server_id.clone(),
+                        tool_name: tool_name.clone(),
+                        progress: progress.clone(),
+                    });
+                }
+            }
+            ToolCallEvent::Finished { .. } | ToolCallEvent::Failed { .. } => {
+                self.running_tool_calls
+                    .retain(|call| &call.call_id != call_id);
+            }
+            ToolCallEvent::Requested | ToolCallEvent::AwaitingConfirmation => {}
+        }
+    }
+
+    /// Returns the servers that failed or whose API can't be reached, with why.
+    fn unhealthy_servers(&self, cx: &App) -> Vec<(ContextServerId, SharedString)> {
+        let store = self.context_server_store.read(cx);
+        let mut servers = store
+            .all_server_ids()
+            .into_iter()
+            .filter_map(|server_id| {
+                let reason = match store.status_for_server(&server_id) {
+                    Some(ContextServerStatus::Error(error)) => SharedString::from(error),
+                    _ if store.is_server_offline(&server_id) => "Offline".into(),
+                    _ => return None,
+                };
+                Some((server_id, reason))
+            })
+            .collect::<Vec<_>>();
+        servers.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        servers
+    }
+
+    fn build_menu(&self, window: &mut Window, cx: &mut Context<Self>) -> Entity<ContextMenu> {
+        let unhealthy_servers = self.unhealthy_servers(cx);
+        let running_tool_calls = self
+            .running_tool_calls
+            .iter()
+            .map(|call| {
+                let mut label = format!("{} ({})", call.tool_name, call.server_id);
+                if let Some(progress) = &call.progress {
+                    label.push_str(": ");
+                    label.push_str(&truncate_and_trailoff(progress, MAX_PROGRESS_LEN));
+                }
+                (call.call_id.clone(), label)
+            })
+            .collect::<Vec<_>>();
+        let context_server_store = self.context_server_store.clone();
+        ContextMenu::build(window, cx, move |mut menu, _, _| {
+            let has_running_tool_calls = !running_tool_calls.is_empty();
+            if has_running_tool_calls {
+                menu = menu.header("Running Tool Calls");
+                for (call_id, label) in running_tool_calls {
+                    let context_server_store = context_server_store.clone();
+                    menu = menu.entry_with_end_slot(
+                        label,
+                        None,
+                        open_server_logs,
+                        IconName::XCircle,
+                        "Cancel Tool Call".into(),
+                        move |_, cx| {
+                            context_server_store
+                                .update(cx, |store, _| store.cancel_tool_call(&call_id));
+                        },
+                    );
+                }
+            }
+            if !unhealthy_servers.is_empty() {
+                if has_running_tool_calls {
+                    menu = menu.separator();
+                }
+                menu = menu.header("Unhealthy MCP Servers");
+                for (server_id, reason) in unhealthy_servers {
+                    menu = menu.entry(format!("{server_id}: {reason}"), None, open_server_logs);
+                }
+            }
+            menu.separator().entry(
+                "Open Server Logs",
+                Some(OpenContextServerInspector.boxed_clone()),
+                open_server_logs,
+            )
+        })
+    }
+}
+
+fn open_server_logs(window: &mut Window, cx: &mut App) {
+    window.dispatch_action(OpenContextServerInspector.boxed_clone(), cx);
+}
+
+impl Render for ContextServerActivityIndicator {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let running_count = self.running_tool_calls.len();
+        let unhealthy_count = self.unhealthy_servers(cx).len();
+        if running_count == 0 && unhealthy_count == 0 {
+            return div().into_any_element();
+        }
+
+        let tooltip = match (running_count, unhealthy_count) {
+            (0, _) => format!("{unhealthy_count} MCP server(s) unhealthy"),
+            (_, 0) => format!("{running_count} MCP tool call(s) running"),
+            _ => format!(
+                "{running_count} MCP tool call(s) running, {unhealthy_count} server(s) unhealthy"
+            ),
+        };
+        let trigger = ButtonLike::new("context-server-activity").child(
+            h_flex()
+                .gap_1()
+                .when(running_count > 0, |this| {
+                    this.child(
+                        Icon::new(IconName::Hammer)
+                            .size(IconSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .child(Label::new(running_count.to_string()).size(LabelSize::Small))
+                })
+                .when(unhealthy_count > 0, |this| {
+                    this.child(
+                        Icon::new(IconName::Warning)
+                            .size(IconSize::Small)
+                            .color(Color::Warning),
+                    )
+                    .child(Label::new(unhealthy_count.to_string()).size(LabelSize::Small))
+                }),
+        );
+
+        let this = cx.entity();
+        PopoverMenu::new("context-server-activity-menu")
+            .trigger_with_tooltip(trigger, Tooltip::text(tooltip))
+            .anchor(gpui::Corner::BottomRight)
+            .menu(move |window, cx| Some(this.update(cx, |this, cx| this.build_menu(window, cx))))
+            .into_any_element()
+    }
+}
+
+impl StatusItemView for ContextServerActivityIndicator {
+    fn set_active_pane_item(
+        &mut self,
+        _active_pane_item: Option<&dyn ItemHandle>,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) {
+    }
+}
@@ -1,23 +1,65 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::Context as _;
+use assistant_settings::AssistantSettings;
 use context_server::ContextServerId;
-use extension::{ContextServerConfiguration, ExtensionManifest};
-use gpui::Task;
+use db::kvp::KEY_VALUE_STORE;
+use extension::{ContextServerConfiguration, ContextServerManifestEntry, ExtensionManifest};
+use gpui::{Entity, PromptLevel, Task};
 use language::LanguageRegistry;
+use project::WorktreeId;
 use project::context_server_store::registry::ContextServerDescriptorRegistry;
+use project::context_server_store::{self, ContextServerStore, Event as ContextServerStoreEvent};
+use settings::{Settings as _, update_settings_file};
 use ui::prelude::*;
 use util::ResultExt;
-use workspace::Workspace;
+use workspace::{
+    Workspace,
+    notifications::{NotificationId, simple_message_notification::MessageNotification},
+};
 
 use crate::agent_configuration::ConfigureContextServerModal;
+use crate::context_server_tool_changes::rename_cached_tools;
 
 pub(crate) fn init(language_registry: Arc<LanguageRegistry>, cx: &mut App) {
-    cx.observe_new(move |_: &mut Workspace, window, cx| {
+    cx.observe_new(move |workspace: &mut Workspace, window, cx| {
         let Some(window) = window else {
             return;
         };
 
+        let context_server_store = workspace.project().read(cx).context_server_store();
+        cx.subscribe_in(
+            &context_server_store,
+            window,
+            |workspace, store, event, window, cx| match event {
+                ContextServerStoreEvent::UntrustedProjectServers { worktree_id } => {
+                    prompt_to_trust_project_servers(store.clone(), *worktree_id, window, cx);
+                }
+                ContextServerStoreEvent::UntrustedServers => {
+                    prompt_to_trust_servers(store.clone(), window, cx);
+                }
+                ContextServerStoreEvent::PackageUpdateAvailable { server_id, version } => {
+                    notify_package_update(workspace, store.clone(), server_id, version, cx);
+                }
+                ContextServerStoreEvent::ServerRenamed { old_id, new_id } => {
+                    rename_server_in_profiles(workspace, old_id, new_id, cx);
+                    cx.background_spawn(rename_cached_tools(old_id.clone(), new_id.clone()))
+                        .detach_and_log_err(cx);
+                }
+                ContextServerStoreEvent::ServerStatusChanged { .. }
+                | ContextServerStoreEvent::ServerOfflineChanged { .. }
+                | ContextServerStoreEvent::ToolCall { .. } => {}
+            },
+        )
+        .detach();
+        for worktree_id in context_server_store.read(cx).untrusted_project_servers() {
+            prompt_to_trust_project_servers(context_server_store.clone(), worktree_id, window, cx);
+        }
+        if !context_server_store.read(cx).untrusted_servers().is_empty() {
+            prompt_to_trust_servers(context_server_store.clone(), window, cx);
+        }
+
         if let Some(extension_events) = extension::ExtensionEvents::try_global(cx).as_ref() {
             cx.subscribe_in(extension_events, window, {
                 let language_registry = language_registry.clone();
@@ -55,12 +97,247 @@ pub(crate) fn init(language_registry: Arc<LanguageRegistry>, cx: &mut App) {
     .detach();
 }
 
+fn trusted_project_servers_key(abs_path: &Path) -> String {
+    format!("trusted_project_context_servers:{}", abs_path.display())
+}
+
+/// Asks the user whether to run the context servers a project declares, unless they already
+/// trusted the same contents of the same file.
+fn prompt_to_trust_project_servers(
+    context_server_store: Entity<ContextServerStore>,
+    worktree_id: WorktreeId,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let Some(project_servers) = context_server_store
+        .read(cx)
+        .project_servers(worktree_id)
+        .cloned()
+    else {
+        return;
+    };
+    let key = trusted_project_servers_key(&project_servers.abs_path);
+    cx.spawn_in(window, async move |_, cx| {
+        let trusted_digest = cx
+            .background_spawn({
+                let key = key.clone();
+                async move { KEY_VALUE_STORE.read_kvp(&key) }
+            })
+            .await?;
+        if trusted_digest.as_deref() != Some(project_servers.digest.as_ref()) {
+            let mut servers = project_servers
+                .servers
+                .iter()
+                .map(|(name, configuration)| {
+                    let summary = context_server_store::launch_summary(configuration);
+                    format!("{name}:\n    {}", summary.replace('\n', "\n    "))
+                })
+                .collect::<Vec<_>>();
+            servers.sort();
+            let sets_env = project_servers.servers.values().any(|configuration| {
+                let command_env = configuration
+                    .command
+                    .as_ref()
+                    .and_then(|command| command.env.as_ref());
+                let package_env = configuration
+                    .package
+                    .as_ref()
+                    .and_then(|package| package.env.as_ref());
+                command_env
+                    .or(package_env)
+                    .is_some_and(|env| !env.is_empty())
+            });
+            let mut detail = format!(
+                "{} declares these MCP servers:\n\n{}",
+                project_servers.abs_path.display(),
+                servers.join("\n")
+            );
+            // Variables such as `NODE_OPTIONS` or `LD_PRELOAD` can run code of their own.
+            if sets_env {
+                detail.push_str(
+                    "\n\nThe environment variables they set can change what the servers run.",
+                );
+            }
+            let answer = cx
+                .update(|window, cx| {
+                    window.prompt(
+                        PromptLevel::Warning,
+                        "Run this project's MCP servers?",
+                        Some(&detail),
+                        &["Trust and Run", "Don't Run"],
+                        cx,
+                    )
+                })?
+                .await?;
+            if answer != 0 {
+                return Ok(());
+            }
+            KEY_VALUE_STORE
+                .write_kvp(key, project_servers.digest.to_string())
+                .await?;
+        }
+        context_server_store.update(cx, |store, cx| {
+            store.trust_project_servers(worktree_id, &project_servers.digest, cx)
+        })?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Where trust used to be remembered, by the server's id, which renaming the server lost.
+fn legacy_trusted_server_key(server_id: &ContextServerId) -> String {
+    format!("trusted_context_server:{server_id}")
+}
+
+/// Trust is remembered by how a server is launched, so that it survives renaming the server.
+fn trusted_launch_key(digest: &str) -> String {
+    format!("trusted_context_server_launch:{digest}")
+}
+
+fn is_launch_trusted(server_id: &ContextServerId, digest: &str) -> anyhow::Result<bool> {
+    if KEY_VALUE_STORE
+        .read_kvp(&trusted_launch_key(digest))?
+        .is_some()
+    {
+        return Ok(true);
+    }
+    Ok(KEY_VALUE_STORE
+        .read_kvp(&legacy_trusted_server_key(server_id))?
+        .is_some_and(|trusted_digest| trusted_digest == digest))
+}
+
+/// Runs the servers the user already trusted to be launched the way they are now, and asks
+/// whether to run the others.
+fn prompt_to_trust_servers(
+    context_server_store: Entity<ContextServerStore>,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let untrusted_servers = context_server_store.read(cx).untrusted_servers().clone();
+    cx.spawn_in(window, async move |_, cx| {
+        let mut servers_to_review = Vec::new();
+        for (server_id, server) in untrusted_servers {
+            let is_trusted = cx
+                .background_spawn({
+                    let server_id = server_id.clone();
+                    let digest = server.digest.clone();
+                    async move { is_launch_trusted(&server_id, &digest) }
+                })
+                .await?;
+            if is_trusted {
+                context_server_store.update(cx, |store, cx| {
+                    store.trust_server(&server_id, &server.digest, cx)
+                })?;
+            } else {
+                servers_to_review.push((server_id, server));
+            }
+        }
+        if servers_to_review.is_empty() {
+            return Ok(());
+        }
+
+        servers_to_review.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        let detail = servers_to_review
+            .iter()
+            .map(|(server_id, server)| format!("{server_id}:\n{}", server.summary))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let answer = cx
+            .update(|window, cx| {
+                window.prompt(
+                    PromptLevel::Warning,
+                    "Run these new MCP servers?",
+                    Some(&detail),
+                    &["Trust and Run", "Don't Run"],
+                    cx,
+                )
+            })?
+            .await?;
+        if answer != 0 {
+            return Ok(());
+        }
+        for (server_id, server) in servers_to_review {
+            KEY_VALUE_STORE
+                .write_kvp(trusted_launch_key(&server.digest), server_id.to_string())
+                .await?;
+            context_server_store.update(cx, |store, cx| {
+                store.trust_server(&server_id, &server.digest, cx)
+            })?;
+        }
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Keeps the profiles' tool selections for a server that the user renamed in their settings.
+fn rename_server_in_profiles(
+    workspace: &Workspace,
+    old_id: &ContextServerId,
+    new_id: &ContextServerId,
+    cx: &mut Context<Workspace>,
+) {
+    let is_referenced = AssistantSettings::get_global(cx)
+        .profiles
+        .values()
+        .any(|profile| {
+            profile.context_servers.contains_key(&old_id.0)
+                || profile
+                    .active_context_servers
+                    .as_ref()
+                    .is_some_and(|servers| servers.contains(&old_id.0))
+        });
+    if !is_referenced {
+        return;
+    }
+    let old_id = old_id.0.clone();
+    let new_id = new_id.0.clone();
+    update_settings_file::<AssistantSettings>(
+        workspace.app_state().fs.clone(),
+        cx,
+        move |settings, _| settings.rename_context_server(&old_id, new_id),
+    );
+}
+
+/// Lets the user know that a newer version of a server's package is available, and update to it.
+fn notify_package_update(
+    workspace: &mut Workspace,
+    context_server_store: Entity<ContextServerStore>,
+    server_id: &ContextServerId,
+    version: &str,
+    cx: &mut Context<Workspace>,
+) {
+    struct PackageUpdateNotification;
+
+    let notification_id = NotificationId::composite::<PackageUpdateNotification>(
+        SharedString::from(server_id.0.clone()),
+    );
+    let message = format!(
+        "Version {version} of the {server_id} MCP server is available. \
+        Its tools may have changed since the installed version."
+    );
+    let server_id = server_id.clone();
+    workspace.show_notification(notification_id, cx, |cx| {
+        cx.new(move |cx| {
+            MessageNotification::new(message, cx)
+                .primary_message("Update")
+                .primary_icon(IconName::Download)
+                .primary_icon_color(Color::Muted)
+                .primary_on_click(move |_window, cx| {
+                    context_server_store
+                        .update(cx, |store, cx| store.update_package(&server_id, cx))
+                        .detach_and_log_err(cx);
+                })
+        })
+    });
+}
+
 pub enum Configuration {
     NotAvailable(ContextServerId, Option<SharedString>),
     Required(
         ContextServerId,
         Option<SharedString>,
         ContextServerConfiguration,
+        ContextServerManifestEntry,
     ),
 }
 
@@ -78,10 +355,10 @@ fn show_configure_mcp_modal(
     let worktree_store = workspace.project().read(cx).worktree_store();
     let configuration_tasks = manifest
         .context_servers
-        .keys()
-        .cloned()
+        .iter()
+        .map(|(key, entry)| (key.clone(), entry.clone()))
         .map({
-            |key| {
+            |(key, manifest_entry)| {
                 let Some(descriptor) = registry.context_server_descriptor(&key) else {
                     return Task::ready(Configuration::NotAvailable(
                         ContextServerId(key),
@@ -104,6 +381,7 @@ fn show_configure_mcp_modal(
                                 ContextServerId(key),
                                 repository_url,
                                 config,
+                                manifest_entry,
                             ),
                             None => {
                                 Configuration::NotAvailable(ContextServerId(key), repository_url)
@@ -0,0 +1,346 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use assistant_settings::AssistantSettings;
+use collections::{HashMap, HashSet};
+use db::kvp::KEY_VALUE_STORE;
+use fs::Fs;
+use project::project_settings::{
+    ContextServerConfiguration, ContextServerPackage, PackageRegistry, ProjectSettings,
+};
+use settings::{Settings as _, update_settings_file};
+use ui::prelude::*;
+use workspace::{
+    Workspace,
+    notifications::{NotificationId, simple_message_notification::MessageNotification},
+};
+
+/// A context server that looks useful in a project, and why.
+#[derive(Debug, Clone, PartialEq)]
+struct ContextServerSuggestion {
+    server_id: &'static str,
+    reason: String,
+    configuration: ContextServerConfiguration,
+}
+
+pub(crate) fn init(cx: &mut App) {
+    cx.observe_new(|workspace: &mut Workspace, window, cx| {
+        let Some(window) = window else {
+            return;
+        };
+
+        // Each server is suggested at most once per workspace, however many worktrees hint at it.
+        let offered = Rc::new(RefCell::new(HashSet::default()));
+        cx.subscribe_in(&workspace.project().clone(), window, {
+            let offered = offered.clone();
+            move |workspace, _, event, window, cx| {
+                if let project::Event::WorktreeAdded(_) = event {
+                    suggest_context_servers(workspace, offered.clone(), window, cx);
+                }
+            }
+        })
+        .detach();
+        suggest_context_servers(workspace, offered, window, cx);
+    })
+    .detach();
+}
+
+fn dismissed_suggestion_key(server_id: &str) -> String {
+    format!("dismissed_context_server_suggestion:{server_id}")
+}
+
+/// Looks for hints of the services a project uses in its worktrees, and suggests the servers for
+/// them that aren't configured yet.
+fn suggest_context_servers(
+    workspace: &mut Workspace,
+    offered: Rc<RefCell<HashSet<&'static str>>>,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let project = workspace.project().read(cx);
+    if !AssistantSettings::get_global(cx).suggest_context_servers || !project.is_local() {
+        return;
+    }
+    let fs = workspace.app_state().fs.clone();
+    let worktree_roots = project
+        .visible_worktrees(cx)
+        .map(|worktree| worktree.read(cx).abs_path())
+        .collect::<Vec<_>>();
+    cx.spawn_in(window, async move |workspace, cx| {
+        let mut suggestions = Vec::new();
+        for root in worktree_roots {
+            let git_config = load_git_config(fs.as_ref(), &root).await;
+            let env_file = fs.load(&root.join(".env")).await.unwrap_or_default();
+            suggestions.extend(project_suggestions(&git_config, &env_file));
+        }
+
+        for suggestion in suggestions {
+            let key = dismissed_suggestion_key(suggestion.server_id);
+            let dismissed = cx
+                .background_spawn(async move { KEY_VALUE_STORE.read_kvp(&key) })
+                .await?
+                .is_some();
+            if dismissed {
+                continue;
+            }
+            workspace.update(cx, |workspace, cx| {
+                if !is_configured(&suggestion, cx)
+                    && offered.borrow_mut().insert(suggestion.server_id)
+                {
+                    show_suggestion(workspace, suggestion, cx);
+                }
+            })?;
+        }
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Loads the git configuration of the repository at `root`, which is shared with the repository
+/// a linked worktree was added to when `.git` is a file pointing to the worktree's git directory.
+async fn load_git_config(fs: &dyn Fs, root: &Path) -> String {
+    let dot_git = root.join(".git");
+    if let Ok(config) = fs.load(&dot_git.join("config")).await {
+        return config;
+    }
+    let Some(git_dir) = fs.load(&dot_git).await.ok().and_then(|dot_git| {
+        let git_dir = dot_git.trim().strip_prefix("gitdir:")?.trim();
+        Some(root.join(git_dir))
+    }) else {
+        return String::new();
+    };
+    let common_dir = match fs.load(&git_dir.join("commondir")).await {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir,
+    };
+    fs.load(&common_dir.join("config"))
+        .await
+        .unwrap_or_default()
+}
+
+/// Whether a server for the same service is already configured, under any name.
+fn is_configured(suggestion: &ContextServerSuggestion, cx: &App) -> bool {
+    let package_name = |configuration: &ContextServerConfiguration| {
+        configuration
+            .package
+            .as_ref()
+            .map(|package| package.name.clone())
+    };
+    let suggested_package = package_name(&suggestion.configuration);
+    ProjectSettings::get_global(cx)
+        .context_servers
+        .iter()
+        .any(|(server_id, configuration)| {
+            server_id.as_ref() == suggestion.server_id
+                || package_name(configuration) == suggested_package
+                || configuration.command.as_ref().is_some_and(|command| {
+                    command
+                        .args
+                        .iter()
+                        .any(|arg| Some(arg) == suggested_package.as_ref())
+                })
+        })
+}
+
+fn show_suggestion(
+    workspace: &mut Workspace,
+    suggestion: ContextServerSuggestion,
+    cx: &mut Context<Workspace>,
+) {
+    struct ContextServerSuggestionNotification;
+
+    let notification_id = NotificationId::composite::<ContextServerSuggestionNotification>(
+        SharedString::new_static(suggestion.server_id),
+    );
+    let fs = workspace.app_state().fs.clone();
+    let message = format!(
+        "{} Add the {} MCP server to your settings?",
+        suggestion.reason, suggestion.server_id
+    );
+    workspace.show_notification(notification_id, cx, |cx| {
+        cx.new(move |cx| {
+            let server_id = suggestion.server_id;
+            let configuration = suggestion.configuration;
+            MessageNotification::new(message, cx)
+                .primary_message("Add Server")
+                .primary_icon(IconName::Plus)
+                .primary_icon_color(Color::Muted)
+                .primary_on_click(move |_window, cx| {
+                    let configuration = configuration.clone();
+                    update_settings_file::<ProjectSettings>(fs.clone(), cx, move |settings, _| {
+                        settings
+                            .context_servers
+                            .entry(server_id.into())
+                            .or_insert(configuration);
+                    });
+                })
+                .secondary_message("Don't Suggest Again")
+                .secondary_on_click(move |_window, cx| {
+                    cx.background_spawn(
+                        KEY_VALUE_STORE
+                            .write_kvp(dismissed_suggestion_key(server_id), "true".to_string()),
+                    )
+                    .detach_and_log_err(cx);
+                })
+        })
+    });
+}
+
+fn npm_package(
+    name: &str,
+    args: Vec<String>,
+    env: Option<HashMap<String, String>>,
+) -> ContextServerConfiguration {
+    ContextServerConfiguration {
+        package: Some(ContextServerPackage {
+            registry: PackageRegistry::Npm,
+            name: name.to_string(),
+            version: None,
+            bin: None,
+            args,
+            env,
+        }),
+        ..Default::default()
+    }
+}
+
+/// Suggests servers from a worktree's git configuration and `.env` file.
+fn project_suggestions(git_config: &str, env_file: &str) -> Vec<ContextServerSuggestion> {
+    let mut suggestions = Vec::new();
+
+    let remote_urls = git_config
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "url").then(|| value.trim())
+        })
+        .collect::<Vec<_>>();
+    let hosts = [
+        (
+            "github",
+            "github.com",
+            "GitHub",
+            "@modelcontextprotocol/server-github",
+            "GITHUB_PERSONAL_ACCESS_TOKEN",
+        ),
+        (
+            "gitlab",
+            "gitlab.com",
+            "GitLab",
+            "@modelcontextprotocol/server-gitlab",
+            "GITLAB_PERSONAL_ACCESS_TOKEN",
+        ),
+    ];
+    for (server_id, host, host_name, package, token_variable) in hosts {
+        if remote_urls.iter().any(|url| url.contains(host)) {
+            suggestions.push(ContextServerSuggestion {
+                server_id,
+                reason: format!(
+                    "This project has a {host_name} remote. Once the server is added, set \
+                    {token_variable} in its env to a personal access token."
+                ),
+                configuration: npm_package(
+                    package,
+                    Vec::new(),
+                    Some(HashMap::from_iter([(
+                        token_variable.to_string(),
+                        String::new(),
+                    )])),
+                ),
+            });
+        }
+    }
+
+    let database_url = env_file.lines().find_map(|line| {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "DATABASE_URL").then(|| value.trim().trim_matches(['"', '\'']))
+    });
+    if let Some(database_url) = database_url
+        .filter(|url| url.starts_with("postgres://") || url.starts_with("postgresql://"))
+    {
+        suggestions.push(ContextServerSuggestion {
+            server_id: "postgres",
+            reason: "This project's .env file sets a Postgres DATABASE_URL. The server is passed \
+                the DATABASE_URL of its environment, so the URL isn't written to your settings."
+                .to_string(),
+            // The URL usually includes a password, so it's referenced rather than copied.
+            configuration: npm_package(
+                "@modelcontextprotocol/server-postgres",
+                vec!["${env:DATABASE_URL}".to_string()],
+                None,
+            ),
+        });
+    }
+
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::path;
+
+    #[test]
+    fn test_project_suggestions() {
+        let git_config = r#"
+[core]
+	bare = false
+[remote "origin"]
+	url = git@github.com:zed-industries/zed.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+"#;
+        let env_file = "PORT=3000\nexport DATABASE_URL=\"postgres://localhost/app\"\n";
+
+        let suggestions = project_suggestions(git_config, env_file);
+        let server_ids = suggestions
+            .iter()
+            .map(|suggestion| suggestion.server_id)
+            .collect::<Vec<_>>();
+        assert_eq!(server_ids, ["github", "postgres"]);
+        let postgres = suggestions[1].configuration.package.as_ref().unwrap();
+        assert_eq!(postgres.name, "@modelcontextprotocol/server-postgres");
+        assert_eq!(postgres.args, ["${env:DATABASE_URL}"]);
+
+        assert_eq!(
+            project_suggestions("", "DATABASE_URL=sqlite://app.db"),
+            Vec::new()
+        );
+    }
+
+    #[gpui::test]
+    async fn test_load_git_config(cx: &mut gpui::TestAppContext) {
+        let fs = fs::FakeFs::new(cx.executor());
+        fs.insert_tree(
+            path!("/code"),
+            serde_json::json!({
+                "app": {
+                    ".git": {
+                        "config": "[remote \"origin\"]\n\turl = git@github.com:org/app.git\n",
+                        "worktrees": {
+                            "feature": {
+                                "commondir": "../..\n",
+                            },
+                        },
+                    },
+                },
+                "feature": {
+                    ".git": "gitdir: /code/app/.git/worktrees/feature\n",
+                },
+                "plain": {},
+            }),
+        )
+        .await;
+
+        for root in [path!("/code/app"), path!("/code/feature")] {
+            let git_config = load_git_config(fs.as_ref(), Path::new(root)).await;
+            assert!(git_config.contains("github.com"), "{root}: {git_config}");
+        }
+        assert_eq!(
+            load_git_config(fs.as_ref(), Path::new(path!("/code/plain"))).await,
+            ""
+        );
+    }
+}
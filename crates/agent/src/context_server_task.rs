@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use assistant_settings::AssistantSettings;
+use assistant_tool::Tool as _;
+use context_server::ContextServerId;
+use gpui::{Entity, PromptLevel, Task, WeakEntity};
+use language_model::LanguageModelToolUseId;
+use project::context_server_store::ContextServerStore;
+use settings::Settings as _;
+use task::McpToolCall;
+use ui::prelude::*;
+use util::truncate_and_trailoff;
+use uuid::Uuid;
+use workspace::{
+    McpToolProvider, Workspace,
+    notifications::{NotificationId, simple_message_notification::MessageNotification},
+};
+
+use crate::context_server_tool::{
+    ContextServerTool, SERVER_STARTUP_TIMEOUT, context_server_badge, offered_tool_name,
+};
+use crate::dangerous_arguments::ToolCallConfirmation;
+
+const MAX_RESULT_LEN: usize = 500;
+
+pub(crate) fn init(cx: &mut App) {
+    cx.observe_new(|workspace: &mut Workspace, _window, cx| {
+        workspace.set_mcp_tool_provider(ContextServerTaskRunner {
+            workspace: cx.entity().downgrade(),
+            context_server_store: workspace.project().read(cx).context_server_store(),
+        });
+    })
+    .detach();
+}
+
+/// Calls the MCP tools of tasks, asking first whenever the agent would have to ask before
+/// calling the tool with the same arguments.
+struct ContextServerTaskRunner {
+    workspace: WeakEntity<Workspace>,
+    context_server_store: Entity<ContextServerStore>,
+}
+
+impl McpToolProvider for ContextServerTaskRunner {
+    fn call_tool(
+        &self,
+        label: String,
+        call: McpToolCall,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<()>> {
+        let server_id = ContextServerId(call.server.into());
+        let input = serde_json::Value::Object(call.arguments);
+        let tool_name = call.tool;
+        let server = self.context_server_store.update(cx, |store, cx| {
+            store.wait_for_running_server(&server_id, SERVER_STARTUP_TIMEOUT, cx)
+        });
+        let context_server_store = self.context_server_store.clone();
+        let workspace = self.workspace.clone();
+        let window_handle = window.window_handle();
+        cx.spawn(async move |cx| {
+            let result = async {
+                let server = server.await?;
+                let protocol = server
+                    .client()
+                    .ok_or_else(|| anyhow!("{server_id} isn't initialized yet"))?;
+                let tool = protocol
+                    .list_tools()
+                    .await?
+                    .tools
+                    .into_iter()
+                    .find(|tool| tool.name == tool_name)
+                    .ok_or_else(|| anyhow!("{server_id} has no `{tool_name}` tool"))?;
+
+                // The call goes through the same tool, middleware and confirmation as the agent's
+                // calls of the tool, so a task can't do anything the agent would have to ask for.
+                let tool = context_server_store.read_with(cx, |store, _| {
+                    let configuration = store.configuration_for_server(&server_id);
+                    let name = offered_tool_name(
+                        store.tool_name_prefix(&server_id).as_deref(),
+                        &tool.name,
+                        |_| false,
+                    );
+                    let ui_text_template = configuration.as_ref().and_then(|configuration| {
+                        configuration.tool_ui_text.get(&tool.name).cloned()
+                    });
+                    let source_badge = context_server_badge(&server, configuration.as_deref());
+                    Arc::new(ContextServerTool::new(
+                        context_server_store.clone(),
+                        server_id.clone(),
+                        tool,
+                        name,
+                        ui_text_template,
+                        Vec::new(),
+                        source_badge,
+                    ))
+                })?;
+                let input = match cx.update(|cx| tool.clone().prepare_input(input.clone(), cx))? {
+                    Some(prepared_input) => prepared_input.await?,
+                    None => input,
+                };
+
+                let answer = cx.update_window(window_handle, |_, window, cx| {
+                    let read_only_tools = AssistantSettings::get_global(cx).read_only_tools;
+                    let ToolCallConfirmation {
+                        needs_confirmation,
+                        dangerous_arguments,
+                    } = ToolCallConfirmation::new(tool.as_ref(), &input, read_only_tools, cx);
+                    needs_confirmation.then(|| {
+                        let mut detail = serde_json::to_string_pretty(&input).unwrap_or_default();
+                        if !dangerous_arguments.is_empty() {
+                            detail = format!(
+                                "The input looks dangerous: {}\n\n{detail}",
+                                dangerous_arguments.join(", ")
+                            );
+                        }
+                        window.prompt(
+                            PromptLevel::Warning,
+                            &format!(
+                                "Call the `{tool_name}` tool of {server_id} for the task \"{label}\"?"
+                            ),
+                            Some(&detail),
+                            &["Call Tool", "Cancel"],
+                            cx,
+                        )
+                    })
+                })?;
+                if let Some(answer) = answer {
+                    if answer.await? != 0 {
+                        return Ok(None);
+                    }
+                }
+
+                let project = workspace.read_with(cx, |workspace, _| workspace.project().clone())?;
+                let output = cx
+                    .update(|cx| {
+                        let tool_use_id = LanguageModelToolUseId::from(format!(
+                            "task-{}",
+                            Uuid::new_v4()
+                        ));
+                        tool.call(
+                            tool_use_id,
+                            input,
+                            Arc::default(),
+                            project,
+                            Some(window_handle),
+                            cx,
+                        )
+                        .output
+                    })?
+                    .await?;
+                anyhow::Ok(Some(
+                    output
+                        .content
+                        .as_str()
+                        .map(ToString::to_string)
+                        .unwrap_or_default(),
+                ))
+            }
+            .await;
+
+            struct TaskToolCallNotification;
+
+            let caption = match &result {
+                Ok(None) => return Ok(()),
+                Ok(Some(text)) if text.is_empty() => format!("Task \"{label}\" finished"),
+                Ok(Some(text)) => format!(
+                    "Task \"{label}\" finished:\n{}",
+                    truncate_and_trailoff(text, MAX_RESULT_LEN)
+                ),
+                Err(error) => format!("Task \"{label}\" failed: {error:#}"),
+            };
+            workspace.update(cx, |workspace, cx| {
+                workspace.show_notification(
+                    NotificationId::unique::<TaskToolCallNotification>(),
+                    cx,
+                    |cx| cx.new(|cx| MessageNotification::new(caption, cx)),
+                );
+            })?;
+            result.map(|_| ())
+        })
+    }
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use context_server::ContextServerId;
+use gpui::{DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Task, WeakEntity};
+use picker::{Picker, PickerDelegate};
+use project::context_server_store::ContextServerStore;
+use ui::{ListItem, ListItemSpacing, prelude::*};
+use util::ResultExt as _;
+use workspace::{ModalView, Workspace};
+use zed_actions::agent::{DisableMcpServer, EnableMcpServer};
+
+pub(crate) fn init(cx: &mut App) {
+    cx.observe_new(|workspace: &mut Workspace, window, cx| {
+        if window.is_none() {
+            return;
+        }
+        workspace
+            .register_action(|workspace, action: &EnableMcpServer, window, cx| {
+                set_server_enabled(workspace, action.server_id.as_deref(), true, window, cx);
+            })
+            .register_action(|workspace, action: &DisableMcpServer, window, cx| {
+                set_server_enabled(workspace, action.server_id.as_deref(), false, window, cx);
+            });
+    })
+    .detach();
+}
+
+/// Turns the server on or off for the project, or lets the user pick one when `server_id` is
+/// `None`.
+fn set_server_enabled(
+    workspace: &mut Workspace,
+    server_id: Option<&str>,
+    enabled: bool,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let context_server_store = workspace.project().read(cx).context_server_store();
+    let Some(server_id) = server_id else {
+        let servers = servers_to_toggle(&context_server_store, enabled, cx);
+        let workspace_handle = cx.entity().downgrade();
+        workspace.toggle_modal(window, cx, |window, cx| {
+            ContextServerTogglePicker::new(workspace_handle, servers, enabled, window, cx)
+        });
+        return;
+    };
+
+    let server_id = ContextServerId(server_id.trim().into());
+    let store = context_server_store.read(cx);
+    let exists =
+        store.is_server_disabled(&server_id) || store.all_server_ids().contains(&server_id);
+    if !exists {
+        workspace.show_error(&anyhow!("No MCP server named {server_id}"), cx);
+        return;
+    }
+    context_server_store.update(cx, |store, cx| {
+        store.set_server_enabled(&server_id, enabled, cx)
+    });
+}
+
+/// Returns the servers that `enabled` would change, which are the disabled servers when enabling
+/// and the others when disabling.
+fn servers_to_toggle(
+    context_server_store: &Entity<ContextServerStore>,
+    enabled: bool,
+    cx: &App,
+) -> Vec<ContextServerId> {
+    let store = context_server_store.read(cx);
+    let mut servers = if enabled {
+        store.disabled_servers().iter().cloned().collect::<Vec<_>>()
+    } else {
+        store
+            .all_server_ids()
+            .into_iter()
+            .filter(|id| !store.is_server_disabled(id) && !store.is_temporary_server(id))
+            .collect()
+    };
+    servers.sort_by(|a, b| a.0.cmp(&b.0));
+    servers
+}
+
+struct ContextServerTogglePicker {
+    picker: Entity<Picker<ContextServerToggleDelegate>>,
+}
+
+impl ContextServerTogglePicker {
+    fn new(
+        workspace: WeakEntity<Workspace>,
+        servers: Vec<ContextServerId>,
+        enabled: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let delegate = ContextServerToggleDelegate {
+            toggle_picker: cx.entity().downgrade(),
+            workspace,
+            servers,
+            enabled,
+            matches: Vec::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+        Self { picker }
+    }
+}
+
+impl ModalView for ContextServerTogglePicker {}
+
+impl EventEmitter<DismissEvent> for ContextServerTogglePicker {}
+
+impl Focusable for ContextServerTogglePicker {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl Render for ContextServerTogglePicker {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+struct ContextServerToggleDelegate {
+    toggle_picker: WeakEntity<ContextServerTogglePicker>,
+    workspace: WeakEntity<Workspace>,
+    servers: Vec<ContextServerId>,
+    /// Whether picking a server enables it, rather than disabling it.
+    enabled: bool,
+    matches: Vec<usize>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for ContextServerToggleDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        if self.enabled {
+            "Enable MCP server…".into()
+        } else {
+            "Disable MCP server…".into()
+        }
+    }
+
+    fn no_matches_text(&self, _window: &mut Window, _cx: &mut App) -> Option<SharedString> {
+        Some(match (self.servers.is_empty(), self.enabled) {
+            (true, true) => "No MCP server is disabled in this project".into(),
+            (true, false) => "No MCP server to disable".into(),
+            (false, _) => "No matching servers".into(),
+        })
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let query = query.to_lowercase();
+        self.matches = self
+            .servers
+            .iter()
+            .enumerate()
+            .filter(|(_, server_id)| server_id.0.to_lowercase().contains(&query))
+            .map(|(ix, _)| ix)
+            .collect();
+        self.selected_index = self
+            .selected_index
+            .min(self.matches.len().saturating_sub(1));
+        Task::ready(())
+    }
+
+    fn confirm(&mut self, _secondary: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        let Some(server_id) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|ix| self.servers.get(*ix))
+        else {
+            return;
+        };
+        let enabled = self.enabled;
+        self.workspace
+            .update(cx, |workspace, cx| {
+                set_server_enabled(workspace, Some(&server_id.0), enabled, window, cx)
+            })
+            .log_err();
+        self.toggle_picker
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn dismissed(&mut self, _window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.toggle_picker
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let server_id = self.servers.get(*self.matches.get(ix)?)?;
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .toggle_state(selected)
+                .child(Label::new(server_id.to_string())),
+        )
+    }
+}
@@ -1,17 +1,76 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::{Result, anyhow, bail};
-use assistant_tool::{ActionLog, Tool, ToolResult, ToolSource};
-use context_server::{ContextServerId, types};
-use gpui::{AnyWindowHandle, App, Entity, Task};
-use language_model::{LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat};
-use project::{Project, context_server_store::ContextServerStore};
-use ui::IconName;
+use anyhow::{Context as _, Result, anyhow, bail};
+use assistant_settings::{AssistantSettings, PromptInjectionScan};
+use assistant_tool::{
+    ActionLog, AnyToolCard, Tool, ToolCard, ToolResult, ToolResultContent, ToolResultOutput,
+    ToolSource, ToolSourceBadge, ToolUseStatus,
+};
+use base64::Engine as _;
+use collections::HashMap;
+use context_server::{
+    ContextServer, ContextServerId,
+    client::RequestError,
+    middleware::{ToolCall, ToolMiddleware},
+    protocol::{TOOL_CALL_ENV_META_KEY, ToolCallTimedOut},
+    recording::{RecordedOutcome, RecordedToolCall, append_recorded_call},
+    types,
+};
+use fs::Fs;
+use futures::{FutureExt as _, StreamExt as _, channel::mpsc};
+use gpui::{
+    AnyWindowHandle, App, DevicePixels, Entity, Image, ImageFormat, ImageSource, PromptLevel, Size,
+    Task, WeakEntity, img, size,
+};
+use language_model::{
+    LanguageModel, LanguageModelImage, LanguageModelRequest, LanguageModelToolSchemaFormat,
+    LanguageModelToolUseId,
+};
+use parking_lot::Mutex;
+use project::{
+    Project,
+    context_server_store::{ContextServerStore, ToolCallEvent},
+    project_settings::ContextServerConfiguration,
+};
+use serde::{Deserialize, Serialize};
+use settings::Settings as _;
+use ui::prelude::*;
+use util::{ResultExt as _, truncate_and_trailoff};
+use workspace::Workspace;
+
+use crate::context_server_tool_error::ContextServerToolError;
+use crate::prompt_injection::scan_tool_output;
+use crate::secret_redaction::SecretRedactor;
+use crate::tool_result_table::{ToolResultTable, ToolResultTableCard};
+
+/// How long a tool call waits for its server to finish starting before failing.
+pub(crate) const SERVER_STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the context server tool calls of a thread are recorded when `record_tool_calls` is on.
+pub fn tool_call_recording_path(thread_id: &str) -> PathBuf {
+    paths::data_dir()
+        .join("tool_recordings")
+        .join(format!("{thread_id}.jsonl"))
+}
 
 pub struct ContextServerTool {
     store: Entity<ContextServerStore>,
     server_id: ContextServerId,
+    /// The name the tool is offered to the model under, which has the server's tool name prefix.
+    name: String,
     tool: types::Tool,
+    /// The user's template for the tool's UI text, which takes precedence over the server's.
+    ui_text_template: Option<String>,
+    /// The user's examples of how to use the tool, which are added to its description.
+    examples: Vec<String>,
+    source_badge: ToolSourceBadge,
+    /// The tool's input schema adapted to each format it was requested in. Refreshing a server's
+    /// tools creates new instances, which is what invalidates it.
+    adapted_schemas: Mutex<HashMap<LanguageModelToolSchemaFormat, Arc<serde_json::Value>>>,
 }
 
 impl ContextServerTool {
@@ -19,22 +78,522 @@ impl ContextServerTool {
         store: Entity<ContextServerStore>,
         server_id: ContextServerId,
         tool: types::Tool,
+        name: String,
+        ui_text_template: Option<String>,
+        examples: Vec<String>,
+        source_badge: ToolSourceBadge,
     ) -> Self {
         Self {
             store,
             server_id,
+            name,
             tool,
+            ui_text_template,
+            examples,
+            source_badge,
+            adapted_schemas: Mutex::default(),
+        }
+    }
+
+    /// Calls the tool on its server, for the agent as well as for tasks, which have no model.
+    pub(crate) fn call(
+        self: Arc<Self>,
+        tool_use_id: LanguageModelToolUseId,
+        input: serde_json::Value,
+        request: Arc<LanguageModelRequest>,
+        project: Entity<Project>,
+        window: Option<AnyWindowHandle>,
+        cx: &mut App,
+    ) -> ToolResult {
+        let tool_name = self.tool.name.clone();
+        let rate_limit_delay = self
+            .store
+            .read(cx)
+            .get_server(&self.server_id)
+            .map_or(Duration::ZERO, |server| server.rate_limit_delay());
+        let server = self.store.update(cx, |store, cx| {
+            store.wait_for_running_server(&self.server_id, SERVER_STARTUP_TIMEOUT, cx)
+        });
+        let settings = AssistantSettings::get_global(cx);
+        let max_image_dimension = settings.max_tool_image_dimension;
+        let recording_path = settings
+            .record_tool_calls
+            .then(|| request.thread_id.as_deref().map(tool_call_recording_path))
+            .flatten();
+        let redactor = SecretRedactor::from_settings(settings);
+        let prompt_injection_scan = settings.prompt_injection_scan;
+        let tool = self.tool.clone();
+        let server_id = self.server_id.clone();
+        let server_identity = self.store.read(cx).server_identity(&server_id);
+        let confine_paths_to_worktrees = self
+            .store
+            .read(cx)
+            .configuration_for_server(&server_id)
+            .is_some_and(|configuration| configuration.confine_paths_to_worktrees);
+        let meta = self
+            .store
+            .read(cx)
+            .configuration_for_server(&server_id)
+            .map(|configuration| tool_call_meta(&configuration.tool_env, &tool_name))
+            .unwrap_or_default();
+        let project = project.read(cx);
+        let worktree_roots = project
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+            .collect::<Vec<_>>();
+        let fs = project.fs().clone();
+        let working_directory = self.store.read(cx).server_working_directory(&server_id, cx);
+        let middleware = self.store.read(cx).tool_middleware(cx);
+
+        // The progress the server reports on the call is passed on to whoever follows tool
+        // activity when the thread reported the call running, and the user can cancel it then.
+        let call_id: Arc<str> = tool_use_id.to_string().into();
+        let (call_id, cancelled) = self.store.update(cx, |store, _| {
+            if store.is_tool_call_running(&call_id) {
+                let cancelled = store.tool_call_cancellation(call_id.clone());
+                (Some(call_id), Some(cancelled))
+            } else {
+                (None, None)
+            }
+        });
+        let (progress_tx, report_progress) = match call_id {
+            Some(call_id) => {
+                let (progress_tx, mut progress_rx) = mpsc::unbounded::<String>();
+                let store = self.store.downgrade();
+                let server_id = self.server_id.clone();
+                let tool_name: Arc<str> = self.name().into();
+                let report_progress = cx.spawn(async move |cx| {
+                    while let Some(progress) = progress_rx.next().await {
+                        store
+                            .update(cx, |store, cx| {
+                                store.report_tool_call(
+                                    &server_id,
+                                    tool_name.clone(),
+                                    call_id.clone(),
+                                    ToolCallEvent::Running {
+                                        progress: Some(progress),
+                                    },
+                                    cx,
+                                )
+                            })
+                            .ok();
+                    }
+                });
+                (Some(progress_tx), Some(report_progress))
+            }
+            None => (None, None),
+        };
+
+        // Calls over the server's rate limit wait in the protocol client, so say so while they do.
+        let rate_limited_card = (rate_limit_delay > Duration::ZERO).then(|| {
+            let send_at = Instant::now() + rate_limit_delay;
+            let tool_name = tool_name.clone().into();
+            cx.new(|cx| RateLimitedToolCard::new(tool_name, send_at, cx))
+        });
+
+        let output = cx.spawn(async move |cx| {
+            if confine_paths_to_worktrees {
+                for (name, path) in path_arguments(&tool.input_schema, &input) {
+                    let in_worktrees = is_path_in_worktrees(
+                        path,
+                        &worktree_roots,
+                        working_directory.as_deref(),
+                        fs.as_ref(),
+                    )
+                    .await;
+                    if !in_worktrees {
+                        bail!(
+                            "Refusing to run {tool_name}: the `{name}` argument points outside \
+                            of the project ({path})"
+                        );
+                    }
+                }
+            }
+
+            let server =
+                server
+                    .await
+                    .map_err(|error| ContextServerToolError::ServerNotRunning {
+                        server: server_id.to_string(),
+                        reason: format!("{error:#}"),
+                    })?;
+            let Some(protocol) = server.client() else {
+                return Err(ContextServerToolError::NotInitialized {
+                    server: server_id.to_string(),
+                }
+                .into());
+            };
+
+            let call = ToolCall {
+                server_id: &server_id,
+                tool_name: &tool_name,
+            };
+            // The input middleware already ran in `prepare_input`.
+            let arguments = if let serde_json::Value::Object(map) = input {
+                Some(map.into_iter().collect())
+            } else {
+                None
+            };
+
+            // The server gets the raw arguments, but logs and recordings only see redacted ones.
+            let mut redacted_arguments = serde_json::to_value(&arguments)?;
+            if let Some(redactor) = &redactor {
+                redactor.redact_json(&mut redacted_arguments);
+            }
+            log::trace!(
+                "Running tool: {} with arguments: {:?}",
+                tool_name,
+                redacted_arguments
+            );
+            let cancelled = async move {
+                if let Some(cancelled) = cancelled {
+                    if cancelled.await.is_ok() {
+                        return;
+                    }
+                }
+                futures::future::pending::<()>().await
+            };
+            let response = futures::select_biased! {
+                response = protocol
+                    .run_tool_with_progress(tool_name.clone(), arguments, meta, progress_tx)
+                    .fuse() => response,
+                _ = cancelled.fuse() => Err(anyhow!("The user cancelled the tool call")),
+            };
+            // The progress channel closed with the call, so this is done once the progress
+            // is reported, before the thread reports the call finished.
+            if let Some(report_progress) = report_progress {
+                report_progress.await;
+            }
+            // Middleware sees everything the server sent before it's recorded, so that what it
+            // strips isn't written to disk: the response, the message of an error, and what a
+            // call that timed out reported before it did.
+            let response = match response {
+                Ok(mut response) => {
+                    for middleware in &middleware {
+                        middleware.process_output(call, &mut response).await?;
+                    }
+                    Ok(response)
+                }
+                Err(error) => match error.downcast::<ToolCallTimedOut>() {
+                    Ok(timed_out) => {
+                        let progress =
+                            process_output_text(&middleware, call, timed_out.progress, false)
+                                .await?;
+                        Err(ToolCallFailure::TimedOut { progress })
+                    }
+                    Err(error) => {
+                        let mut error =
+                            ContextServerToolError::from_request_error(&server_id.0, error);
+                        process_error_output(&middleware, call, &mut error).await?;
+                        Err(ToolCallFailure::Error(error))
+                    }
+                },
+            };
+            if let Some(recording_path) = recording_path {
+                let outcome = match &response {
+                    Ok(response) => {
+                        let mut response = serde_json::to_value(response)?;
+                        if let Some(redactor) = &redactor {
+                            redactor.redact_json(&mut response);
+                        }
+                        RecordedOutcome::Response(response)
+                    }
+                    Err(failure) => {
+                        let error = failure.recorded_error();
+                        RecordedOutcome::Error(match &redactor {
+                            Some(redactor) => redactor.redact(&error).into_owned(),
+                            None => error,
+                        })
+                    }
+                };
+                let call = RecordedToolCall {
+                    server: server_id.0.to_string(),
+                    server_identity: server_identity.as_deref().map(str::to_string),
+                    tool,
+                    arguments: redacted_arguments,
+                    outcome,
+                };
+                append_recorded_call(&recording_path, &call)
+                    .await
+                    .context("recording tool call")
+                    .log_err();
+            }
+            let response = match response {
+                Ok(response) => response,
+                // What the server sent before timing out is better than nothing for long calls.
+                Err(ToolCallFailure::TimedOut { progress }) => {
+                    return Ok(partial_result(&progress).into());
+                }
+                Err(ToolCallFailure::Error(error)) => return Err(error.into()),
+            };
+
+            let structured_content = response.structured_content;
+            if response.is_error == Some(true) {
+                let message = response
+                    .content
+                    .into_iter()
+                    .filter_map(|content| match content {
+                        types::ToolResponseContent::Text { text } => Some(text),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return Err(ContextServerToolError::ToolError {
+                    code: None,
+                    message,
+                    data: structured_content,
+                }
+                .into());
+            }
+            let mut result = String::new();
+            let mut image = None;
+            let mut other_images = Vec::new();
+            for content in response.content {
+                match content {
+                    types::ToolResponseContent::Text { text } => {
+                        result.push_str(&text);
+                    }
+                    types::ToolResponseContent::Image { data, mime_type } => {
+                        if image.is_some() {
+                            other_images.push(ContextServerImage { mime_type, data });
+                        } else {
+                            image = Some((data, mime_type));
+                        }
+                    }
+                    // The URI is kept with the text, so that the model can refer to the resource.
+                    // Binary resources are left at their URI.
+                    types::ToolResponseContent::Resource { resource } => {
+                        if !result.is_empty() {
+                            result.push('\n');
+                        }
+                        result.push_str(&format!("Resource: {}", resource.uri));
+                        if let Some(text) = resource.text {
+                            result.push('\n');
+                            result.push_str(&text);
+                        }
+                    }
+                }
+            }
+
+            // Servers should send structured results as text too, but not all of them do.
+            if image.is_none() && result.is_empty() {
+                if let Some(structured_content) = structured_content.as_ref() {
+                    result = serde_json::to_string_pretty(structured_content)?;
+                }
+            }
+            // The text next to an image reaches the model too, so it's scanned either way.
+            let findings = match prompt_injection_scan {
+                PromptInjectionScan::Off => Vec::new(),
+                PromptInjectionScan::Warn | PromptInjectionScan::Confirm => {
+                    scan_tool_output(&result)
+                }
+            };
+            if !findings.is_empty() && prompt_injection_scan == PromptInjectionScan::Confirm {
+                let Some(window) = window else {
+                    bail!("The output of {tool_name} looks like a prompt injection");
+                };
+                let answer = cx.update_window(window, |_, window, cx| {
+                    window.prompt(
+                        PromptLevel::Warning,
+                        &format!("The output of {tool_name} may be trying to instruct the model"),
+                        Some(&findings.join("\n")),
+                        &["Send to Model", "Discard"],
+                        cx,
+                    )
+                })?;
+                if answer.await? != 0 {
+                    bail!(
+                        "The user discarded the output of {tool_name}, since it looked like a \
+                        prompt injection: {}",
+                        findings.join("; ")
+                    );
+                }
+            }
+
+            let Some((data, mime_type)) = image else {
+                if findings.is_empty() {
+                    let table = structured_content
+                        .as_ref()
+                        .and_then(ToolResultTable::from_json)
+                        .or_else(|| ToolResultTable::from_text(&result));
+                    let Some(table) = table else {
+                        return Ok(with_structured_content(result.into(), structured_content));
+                    };
+                    return Ok(with_structured_content(
+                        ToolResultOutput {
+                            content: ToolResultContent::Text(result.clone()),
+                            output: Some(serde_json::to_value(ContextServerTableOutput {
+                                table,
+                                raw: result,
+                            })?),
+                        },
+                        structured_content,
+                    ));
+                }
+                return Ok(with_structured_content(
+                    ToolResultOutput {
+                        content: ToolResultContent::Text(result),
+                        output: Some(serde_json::to_value(PromptInjectionWarning { findings })?),
+                    },
+                    structured_content,
+                ));
+            };
+            let Some(format) = ImageFormat::from_mime_type(&mime_type) else {
+                bail!("Unsupported image type in tool response: {mime_type}");
+            };
+            // The server's base64 is passed through, and only decoded when the image needs
+            // downscaling or converting to PNG.
+            let language_model_image = cx
+                .update(|cx| {
+                    LanguageModelImage::from_base64_with_max_dimension(
+                        SharedString::new(data.as_str()),
+                        format,
+                        max_image_dimension,
+                        cx,
+                    )
+                })?
+                .await
+                .context("processing image from tool response")?;
+            let sent_size = language_model_image.size();
+
+            Ok(with_structured_content(
+                ToolResultOutput {
+                    content: ToolResultContent::Image(language_model_image),
+                    output: Some(
+                        ContextServerImageOutput {
+                            mime_type,
+                            data,
+                            sent_width: sent_size.width.0,
+                            sent_height: sent_size.height.0,
+                            text: result,
+                            other_images,
+                            prompt_injection_findings: findings,
+                        }
+                        .into_json(),
+                    ),
+                },
+                structured_content,
+            ))
+        });
+
+        // The card of a call that was rate limited shows the output's own card once it's done,
+        // such as an image or a table.
+        let Some(rate_limited_card) = rate_limited_card else {
+            return ToolResult { output, card: None };
+        };
+        let output = cx.spawn({
+            let rate_limited_card = rate_limited_card.clone();
+            let tool_name: SharedString = self.tool.name.clone().into();
+            async move |cx| {
+                let output = output.await;
+                if let Ok(ToolResultOutput {
+                    output: Some(value),
+                    ..
+                }) = &output
+                {
+                    rate_limited_card
+                        .update(cx, |card, cx| {
+                            card.result_card = output_card(tool_name, value.clone(), cx);
+                            cx.notify();
+                        })
+                        .ok();
+                }
+                output
+            }
+        });
+        ToolResult {
+            output,
+            card: Some(rate_limited_card.into()),
+        }
+    }
+}
+
+/// The longest tool name that model providers accept.
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Returns the name to offer a server's tool to the model under: its name after `prefix`, cut to
+/// the length providers accept, with a numeric suffix when another tool already has that name.
+pub(crate) fn offered_tool_name(
+    prefix: Option<&str>,
+    name: &str,
+    is_taken: impl Fn(&str) -> bool,
+) -> String {
+    let full_name = format!("{}{name}", prefix.unwrap_or_default());
+    let truncated = |max_len: usize| {
+        let mut len = full_name.len().min(max_len);
+        while !full_name.is_char_boundary(len) {
+            len -= 1;
         }
+        &full_name[..len]
+    };
+    let offered_name = truncated(MAX_TOOL_NAME_LEN).to_string();
+    if !is_taken(&offered_name) {
+        return offered_name;
+    }
+    let mut ix = 2;
+    loop {
+        let suffix = format!("_{ix}");
+        let offered_name = format!("{}{suffix}", truncated(MAX_TOOL_NAME_LEN - suffix.len()));
+        if !is_taken(&offered_name) {
+            return offered_name;
+        }
+        ix += 1;
+    }
+}
+
+/// Returns the name and icon to show for `server` on the cards of its tool calls, preferring the
+/// ones in the user's configuration over the ones the server reports.
+pub fn context_server_badge(
+    server: &ContextServer,
+    configuration: Option<&ContextServerConfiguration>,
+) -> ToolSourceBadge {
+    let protocol = server.client();
+    let server_info = protocol
+        .as_ref()
+        .map(|protocol| &protocol.initialize.server_info);
+    let name = configuration
+        .and_then(|configuration| configuration.display_name.clone())
+        .or_else(|| server_info?.title.clone())
+        .unwrap_or_else(|| server.id().0.to_string());
+    let icon = configuration
+        .and_then(|configuration| configuration.icon.clone())
+        .map(ImageSource::from)
+        .or_else(|| {
+            server_info?
+                .icons
+                .as_ref()?
+                .iter()
+                .find_map(|icon| decode_data_uri_image(&icon.src).log_err().flatten())
+                .map(ImageSource::from)
+        });
+    ToolSourceBadge {
+        name: name.into(),
+        icon,
     }
 }
 
+/// Decodes an image from a base64 `data:` URI, returning `None` for other URIs.
+fn decode_data_uri_image(uri: &str) -> Result<Option<Arc<Image>>> {
+    let Some((mime_type, data)) = uri
+        .strip_prefix("data:")
+        .and_then(|uri| uri.split_once(";base64,"))
+    else {
+        return Ok(None);
+    };
+    decode_image(data, mime_type).map(Some)
+}
+
 impl Tool for ContextServerTool {
     fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn original_name(&self) -> String {
         self.tool.name.clone()
     }
 
     fn description(&self) -> String {
-        self.tool.description.clone().unwrap_or_default()
+        description_with_examples(self.tool.description.as_deref(), &self.examples)
     }
 
     fn icon(&self) -> IconName {
@@ -47,14 +606,61 @@ impl Tool for ContextServerTool {
         }
     }
 
-    fn needs_confirmation(&self, _: &serde_json::Value, _: &App) -> bool {
-        true
+    fn source_badge(&self) -> Option<ToolSourceBadge> {
+        Some(self.source_badge.clone())
+    }
+
+    fn needs_confirmation(&self, _: &serde_json::Value, cx: &App) -> bool {
+        self.store
+            .read(cx)
+            .tool_confirmation(&self.server_id, cx)
+            .confirm
+    }
+
+    fn may_perform_edits(&self) -> bool {
+        self.tool
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.read_only_hint)
+            != Some(true)
+    }
+
+    fn is_available_to(&self, model: &Arc<dyn LanguageModel>, cx: &App) -> bool {
+        let Some(configuration) = self
+            .store
+            .read(cx)
+            .configuration_for_server(&self.server_id)
+        else {
+            return true;
+        };
+        let Some(patterns) = configuration
+            .tool_models
+            .get(&self.tool.name)
+            .or_else(|| configuration.tool_models.get("*"))
+        else {
+            return true;
+        };
+        patterns
+            .iter()
+            .any(|pattern| model_matches(pattern, &model.provider_id().0, &model.id().0))
     }
 
     fn input_schema(&self, format: LanguageModelToolSchemaFormat) -> Result<serde_json::Value> {
+        self.shared_input_schema(format)
+            .map(|schema| serde_json::Value::clone(&schema))
+    }
+
+    fn shared_input_schema(
+        &self,
+        format: LanguageModelToolSchemaFormat,
+    ) -> Result<Arc<serde_json::Value>> {
+        let mut adapted_schemas = self.adapted_schemas.lock();
+        if let Some(schema) = adapted_schemas.get(&format) {
+            return Ok(schema.clone());
+        }
         let mut schema = self.tool.input_schema.clone();
         assistant_tool::adapt_schema_to_format(&mut schema, format)?;
-        Ok(match schema {
+        let schema = match schema {
             serde_json::Value::Null => {
                 serde_json::json!({ "type": "object", "properties": [] })
             }
@@ -62,65 +668,1253 @@ impl Tool for ContextServerTool {
                 serde_json::json!({ "type": "object", "properties": [] })
             }
             _ => schema,
-        })
+        };
+        let schema = Arc::new(schema);
+        adapted_schemas.insert(format, schema.clone());
+        Ok(schema)
     }
 
-    fn ui_text(&self, _input: &serde_json::Value) -> String {
-        format!("Run MCP tool `{}`", self.tool.name)
+    fn ui_text(&self, input: &serde_json::Value) -> String {
+        let template = self
+            .ui_text_template
+            .as_deref()
+            .or_else(|| self.tool.annotations.as_ref()?.ui_text_template.as_deref());
+        if let Some(text) = template.and_then(|template| fill_ui_text_template(template, input)) {
+            return text;
+        }
+        match argument_preview(&self.tool.input_schema, input) {
+            Some(preview) => format!("Run MCP tool `{}` with {preview}", self.tool.name),
+            None => format!("Run MCP tool `{}`", self.tool.name),
+        }
+    }
+
+    fn prepare_input(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        cx: &mut App,
+    ) -> Option<Task<Result<serde_json::Value>>> {
+        let middleware = self.store.read(cx).tool_middleware(cx);
+        if middleware.is_empty() {
+            return None;
+        }
+        let server_id = self.server_id.clone();
+        let tool_name = self.tool.name.clone();
+        Some(cx.spawn(async move |_| {
+            let serde_json::Value::Object(mut arguments) = input else {
+                return Ok(input);
+            };
+            let call = ToolCall {
+                server_id: &server_id,
+                tool_name: &tool_name,
+            };
+            for middleware in &middleware {
+                middleware.process_input(call, &mut arguments).await?;
+            }
+            Ok(serde_json::Value::Object(arguments))
+        }))
     }
 
     fn run(
         self: Arc<Self>,
+        tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
-        _request: Arc<LanguageModelRequest>,
-        _project: Entity<Project>,
+        request: Arc<LanguageModelRequest>,
+        project: Entity<Project>,
         _action_log: Entity<ActionLog>,
         _model: Arc<dyn LanguageModel>,
-        _window: Option<AnyWindowHandle>,
+        window: Option<AnyWindowHandle>,
         cx: &mut App,
     ) -> ToolResult {
-        if let Some(server) = self.store.read(cx).get_running_server(&self.server_id) {
-            let tool_name = self.tool.name.clone();
-            let server_clone = server.clone();
-            let input_clone = input.clone();
-
-            cx.spawn(async move |_cx| {
-                let Some(protocol) = server_clone.client() else {
-                    bail!("Context server not initialized");
-                };
+        self.call(tool_use_id, input, request, project, window, cx)
+    }
 
-                let arguments = if let serde_json::Value::Object(map) = input_clone {
-                    Some(map.into_iter().collect())
-                } else {
-                    None
-                };
+    fn deserialize_card(
+        self: Arc<Self>,
+        output: serde_json::Value,
+        _project: Entity<Project>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Option<AnyToolCard> {
+        output_card(self.tool.name.clone().into(), output, cx)
+    }
+}
 
-                log::trace!(
-                    "Running tool: {} with arguments: {:?}",
-                    tool_name,
-                    arguments
-                );
-                let response = protocol.run_tool(tool_name, arguments).await?;
-
-                let mut result = String::new();
-                for content in response.content {
-                    match content {
-                        types::ToolResponseContent::Text { text } => {
-                            result.push_str(&text);
-                        }
-                        types::ToolResponseContent::Image { .. } => {
-                            log::warn!("Ignoring image content from tool response");
-                        }
-                        types::ToolResponseContent::Resource { .. } => {
-                            log::warn!("Ignoring resource content from tool response");
-                        }
-                    }
+/// Returns the card that shows the output of a call, when it has more to show than text.
+fn output_card(
+    tool_name: SharedString,
+    output: serde_json::Value,
+    cx: &mut App,
+) -> Option<AnyToolCard> {
+    if let Ok(warning) = serde_json::from_value::<PromptInjectionWarning>(output.clone()) {
+        let card = cx.new(|_| PromptInjectionWarningCard {
+            tool_name,
+            findings: warning.findings,
+        });
+        return Some(card.into());
+    }
+    if let Ok(output) = serde_json::from_value::<ContextServerTableOutput>(output.clone()) {
+        let card = cx.new(|_| ToolResultTableCard::new(tool_name, output.table, output.raw.into()));
+        return Some(card.into());
+    }
+    let mut output = serde_json::from_value::<ContextServerImageOutput>(output).ok()?;
+    let card = cx.new(|cx| ContextServerImageCard {
+        tool_name,
+        prompt_injection_findings: std::mem::take(&mut output.prompt_injection_findings),
+        original: None,
+        sent_size: size(
+            DevicePixels(output.sent_width),
+            DevicePixels(output.sent_height),
+        ),
+        _decode: cx.spawn(async move |card, cx| {
+            let original = cx
+                .background_spawn(async move { decode_image(&output.data, &output.mime_type) })
+                .await
+                .log_err();
+            card.update(cx, |card, cx| {
+                card.original = original;
+                cx.notify();
+            })
+            .ok();
+        }),
+    });
+    Some(card.into())
+}
+
+/// The result for a call that timed out after the server reported progress, marked as incomplete
+/// so that the model doesn't take it for the whole result.
+/// How a call failed, once middleware has processed what the server sent.
+enum ToolCallFailure {
+    TimedOut { progress: Vec<String> },
+    Error(ContextServerToolError),
+}
+
+impl ToolCallFailure {
+    /// The error to record the call with, which replaying it fails the same way with.
+    fn recorded_error(&self) -> String {
+        match self {
+            Self::TimedOut { .. } => RequestError::Timeout.to_string(),
+            Self::Error(ContextServerToolError::ToolError { message, .. }) => message.clone(),
+            Self::Error(error) => error.summary(),
+        }
+    }
+}
+
+/// Runs the output middleware on texts the server sent outside of a response, as the text
+/// content of a response, so that middleware sees them the way it sees results.
+async fn process_output_text(
+    middleware: &[Arc<dyn ToolMiddleware>],
+    call: ToolCall<'_>,
+    texts: Vec<String>,
+    is_error: bool,
+) -> Result<Vec<String>> {
+    if middleware.is_empty() {
+        return Ok(texts);
+    }
+    let mut response = types::CallToolResponse {
+        content: texts
+            .into_iter()
+            .map(|text| types::ToolResponseContent::Text { text })
+            .collect(),
+        is_error: Some(is_error),
+        structured_content: None,
+        meta: None,
+    };
+    for middleware in middleware {
+        middleware.process_output(call, &mut response).await?;
+    }
+    Ok(response
+        .content
+        .into_iter()
+        .filter_map(|content| match content {
+            types::ToolResponseContent::Text { text } => Some(text),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Runs the output middleware on the parts of an error that came from the server.
+async fn process_error_output(
+    middleware: &[Arc<dyn ToolMiddleware>],
+    call: ToolCall<'_>,
+    error: &mut ContextServerToolError,
+) -> Result<()> {
+    if middleware.is_empty() {
+        return Ok(());
+    }
+    let (message, data) = match error {
+        ContextServerToolError::ToolError { message, data, .. } => (message, Some(data)),
+        ContextServerToolError::ProtocolError { message, .. } => (message, None),
+        _ => return Ok(()),
+    };
+    let mut response = types::CallToolResponse {
+        content: vec![types::ToolResponseContent::Text {
+            text: std::mem::take(message),
+        }],
+        is_error: Some(true),
+        structured_content: data.as_mut().and_then(|data| data.take()),
+        meta: None,
+    };
+    for middleware in middleware {
+        middleware.process_output(call, &mut response).await?;
+    }
+    *message = response
+        .content
+        .into_iter()
+        .filter_map(|content| match content {
+            types::ToolResponseContent::Text { text } => Some(text),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if let Some(data) = data {
+        *data = response.structured_content;
+    }
+    Ok(())
+}
+
+fn partial_result(progress: &[String]) -> String {
+    format!(
+        "The tool timed out before finishing, so this result is incomplete. \
+        It reported this progress before timing out:\n\n{}",
+        progress.join("\n")
+    )
+}
+
+/// Words that mark an argument as a path when its name ends with one of them.
+const PATH_ARGUMENT_WORDS: &[&str] = &[
+    "path",
+    "paths",
+    "file",
+    "files",
+    "filename",
+    "filepath",
+    "dir",
+    "dirs",
+    "directory",
+    "directories",
+    "folder",
+    "folders",
+    "cwd",
+    "root",
+];
+
+/// Returns the argument values that the tool's input schema or their names suggest are paths,
+/// along with the names of their arguments, such as `options.cwd` for nested ones.
+fn path_arguments<'a>(
+    input_schema: &serde_json::Value,
+    input: &'a serde_json::Value,
+) -> Vec<(String, &'a str)> {
+    let mut paths = Vec::new();
+    collect_path_arguments(input_schema, input, None, &mut paths);
+    paths
+}
+
+/// Collects the paths in an object's properties, descending into nested objects and arrays so
+/// that wrapping a path in one doesn't keep it from being checked.
+fn collect_path_arguments<'a>(
+    schema: &serde_json::Value,
+    input: &'a serde_json::Value,
+    parent_name: Option<&str>,
+    paths: &mut Vec<(String, &'a str)>,
+) {
+    let Some(arguments) = input.as_object() else {
+        return;
+    };
+    for (name, value) in arguments {
+        // Arguments the schema doesn't declare are still checked, by their names.
+        let property = &schema["properties"][name];
+        let describes_path = property["description"].as_str().is_some_and(|description| {
+            description
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|word| word.eq_ignore_ascii_case("path"))
+        });
+        let is_path = describes_path || is_path_like_name(name);
+        let name = match parent_name {
+            Some(parent_name) => format!("{parent_name}.{name}"),
+            None => name.clone(),
+        };
+        collect_path_values(property, value, &name, is_path, paths);
+    }
+}
+
+fn collect_path_values<'a>(
+    schema: &serde_json::Value,
+    value: &'a serde_json::Value,
+    name: &str,
+    is_path: bool,
+    paths: &mut Vec<(String, &'a str)>,
+) {
+    match value {
+        serde_json::Value::String(path) if is_path => paths.push((name.to_string(), path)),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_path_values(&schema["items"], item, name, is_path, paths);
+            }
+        }
+        serde_json::Value::Object(_) => collect_path_arguments(schema, value, Some(name), paths),
+        _ => {}
+    }
+}
+
+/// Whether a name such as `path`, `source_file` or `targetDir` names a path.
+fn is_path_like_name(name: &str) -> bool {
+    let mut last_word = String::new();
+    for (ix, c) in name.char_indices() {
+        if c == '_' || c == '-' || (ix > 0 && c.is_uppercase()) {
+            last_word.clear();
+        }
+        if c != '_' && c != '-' {
+            last_word.extend(c.to_lowercase());
+        }
+    }
+    PATH_ARGUMENT_WORDS.contains(&last_word.as_str())
+        || PATH_ARGUMENT_WORDS.contains(&name.to_lowercase().as_str())
+}
+
+/// Whether `path` resolves to a location inside one of the worktrees, following symlinks so that
+/// a link inside a worktree can't be used to reach outside of it. Relative paths are resolved
+/// against the server's `working_directory`, and refused when it isn't known.
+async fn is_path_in_worktrees(
+    path: &str,
+    worktree_roots: &[PathBuf],
+    working_directory: Option<&Path>,
+    fs: &dyn Fs,
+) -> bool {
+    if path.contains("://") && !path.starts_with("file://") {
+        return true;
+    }
+    let path = path.strip_prefix("file://").unwrap_or(path);
+    let path = if path == "~" {
+        util::paths::home_dir().clone()
+    } else if let Some(path) = path.strip_prefix("~/") {
+        util::paths::home_dir().join(path)
+    } else {
+        PathBuf::from(path)
+    };
+    let path = if path.is_relative() {
+        let Some(working_directory) = working_directory else {
+            return false;
+        };
+        working_directory.join(path)
+    } else {
+        path
+    };
+    let path = canonicalize_existing_ancestor(&fs::normalize_path(&path), fs).await;
+
+    for root in worktree_roots {
+        let root = fs.canonicalize(root).await.unwrap_or_else(|_| root.clone());
+        if path.starts_with(&root) {
+            return true;
+        }
+    }
+    false
+}
+
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// Canonicalizes the longest prefix of `path` that exists, since the tool may create the rest.
+/// Symlinks to paths that don't exist are followed too, as creating the file would follow them.
+async fn canonicalize_existing_ancestor(path: &Path, fs: &dyn Fs) -> PathBuf {
+    let mut path = path.to_path_buf();
+    // Links are only followed so many times, in case they form a loop.
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        let mut dangling_link = None;
+        for ancestor in path.ancestors() {
+            if let Ok(canonical) = fs.canonicalize(ancestor).await {
+                let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+                return canonical.join(rest);
+            }
+            if let Ok(target) = fs.read_link(ancestor).await {
+                dangling_link = Some((ancestor.to_path_buf(), target));
+                break;
+            }
+        }
+        let Some((link, target)) = dangling_link else {
+            break;
+        };
+        let rest = path.strip_prefix(&link).unwrap_or(Path::new(""));
+        let target = match link.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+        path = fs::normalize_path(&target.join(rest));
+    }
+    path
+}
+
+/// The tool output recorded for a tool response containing an image. The original image is kept
+/// since the model may have received a downscaled copy, along with the rest of the response,
+/// which the tool result can't hold next to the image.
+#[derive(Serialize, Deserialize)]
+struct ContextServerImageOutput {
+    mime_type: String,
+    /// The base64-encoded image, as returned by the context server.
+    data: String,
+    sent_width: i32,
+    sent_height: i32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    other_images: Vec<ContextServerImage>,
+    /// Named apart from `PromptInjectionWarning::findings`, which would otherwise match this too.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    prompt_injection_findings: Vec<String>,
+}
+
+/// Returns the rest of a tool response that contained an image, to send to the model next to
+/// the tool result, which only holds the image.
+pub(crate) fn text_beside_image(tool_name: &str, output: &serde_json::Value) -> Option<String> {
+    let output = ContextServerImageOutput::deserialize(output).ok()?;
+    let mut text = output.text;
+    if !output.other_images.is_empty() {
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(&format!(
+            "The tool also returned {} more images, which weren't sent.",
+            output.other_images.len()
+        ));
+    }
+    if text.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "The `{tool_name}` tool returned this along with its image:\n\n{text}"
+    ))
+}
+
+#[derive(Serialize, Deserialize)]
+struct ContextServerImage {
+    mime_type: String,
+    data: String,
+}
+
+impl ContextServerImageOutput {
+    /// Converts the output to JSON, moving the image data rather than copying it as
+    /// `serde_json::to_value` would.
+    fn into_json(self) -> serde_json::Value {
+        let mut fields = serde_json::Map::from_iter([
+            ("mime_type".to_string(), self.mime_type.into()),
+            ("data".to_string(), self.data.into()),
+            ("sent_width".to_string(), self.sent_width.into()),
+            ("sent_height".to_string(), self.sent_height.into()),
+        ]);
+        if !self.text.is_empty() {
+            fields.insert("text".to_string(), self.text.into());
+        }
+        if !self.other_images.is_empty() {
+            let other_images = self
+                .other_images
+                .into_iter()
+                .map(|image| {
+                    serde_json::Value::Object(serde_json::Map::from_iter([
+                        ("mime_type".to_string(), image.mime_type.into()),
+                        ("data".to_string(), image.data.into()),
+                    ]))
+                })
+                .collect();
+            fields.insert(
+                "other_images".to_string(),
+                serde_json::Value::Array(other_images),
+            );
+        }
+        if !self.prompt_injection_findings.is_empty() {
+            fields.insert(
+                "prompt_injection_findings".to_string(),
+                self.prompt_injection_findings.into(),
+            );
+        }
+        serde_json::Value::Object(fields)
+    }
+}
+
+/// Keeps a response's structured content in the output recorded for it, so that saved threads
+/// have it even when the text sent to the model was derived from it, or didn't include it.
+fn with_structured_content(
+    mut output: ToolResultOutput,
+    structured_content: Option<serde_json::Value>,
+) -> ToolResultOutput {
+    let Some(structured_content) = structured_content else {
+        return output;
+    };
+    match &mut output.output {
+        Some(serde_json::Value::Object(fields)) => {
+            fields.insert("structured_content".to_string(), structured_content);
+        }
+        Some(_) => {}
+        None => {
+            output.output = Some(serde_json::Value::Object(serde_json::Map::from_iter([(
+                "structured_content".to_string(),
+                structured_content,
+            )])));
+        }
+    }
+    output
+}
+
+/// Whether the model `model_id` of the provider `provider_id` matches a `provider/model` pattern
+/// from the `tool_models` setting, where each part may be `*` or end with `*`.
+fn model_matches(pattern: &str, provider_id: &str, model_id: &str) -> bool {
+    let part_matches = |pattern: &str, value: &str| match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    };
+    match pattern.split_once('/') {
+        Some((provider_pattern, model_pattern)) => {
+            part_matches(provider_pattern, provider_id) && part_matches(model_pattern, model_id)
+        }
+        None => part_matches(pattern, model_id),
+    }
+}
+
+/// Appends the user's examples of how to use a tool to its description.
+fn description_with_examples(description: Option<&str>, examples: &[String]) -> String {
+    let description = description.unwrap_or_default().trim_end();
+    if examples.is_empty() {
+        return description.to_string();
+    }
+    let mut text = String::from(description);
+    if !text.is_empty() {
+        text.push_str("\n\n");
+    }
+    text.push_str("Examples:");
+    for example in examples {
+        text.push_str("\n- ");
+        text.push_str(example.trim());
+    }
+    text
+}
+
+/// Returns the metadata to send with a call to `tool_name`, with the environment variables
+/// configured for all of the server's tools, overridden by the ones configured for this tool.
+pub(crate) fn tool_call_meta(
+    tool_env: &HashMap<String, HashMap<String, String>>,
+    tool_name: &str,
+) -> HashMap<String, serde_json::Value> {
+    let mut env = serde_json::Map::new();
+    for key in ["*", tool_name] {
+        for (name, value) in tool_env.get(key).into_iter().flatten() {
+            env.insert(name.clone(), value.clone().into());
+        }
+    }
+    if env.is_empty() {
+        return HashMap::default();
+    }
+    HashMap::from_iter([(TOOL_CALL_ENV_META_KEY.to_string(), env.into())])
+}
+
+/// Replaces the `{argument}` placeholders in `template` with the arguments in `input`, where
+/// `{a.b}` refers to a nested argument. Returns `None` when an argument is missing, so that
+/// the default text is shown rather than a misleading one.
+fn fill_ui_text_template(template: &str, input: &serde_json::Value) -> Option<String> {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let end = start + rest[start..].find('}')?;
+        let value = rest[start + 1..end]
+            .trim()
+            .split('.')
+            .try_fold(input, |value, key| value.get(key))?;
+        match value {
+            serde_json::Value::String(string) => text.push_str(string),
+            value => text.push_str(&value.to_string()),
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    Some(text)
+}
+
+const MAX_PREVIEW_ARGUMENTS: usize = 3;
+const MAX_PREVIEW_VALUE_CHARS: usize = 40;
+
+/// Summarizes the arguments of a tool call on one line, so that its confirmation shows what the
+/// call will do. The arguments the schema requires come first, as they're usually the key ones,
+/// and long values are elided.
+fn argument_preview(input_schema: &serde_json::Value, input: &serde_json::Value) -> Option<String> {
+    let arguments = input
+        .as_object()
+        .filter(|arguments| !arguments.is_empty())?;
+    let required = input_schema["required"]
+        .as_array()
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(|name| name.as_str())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut names = arguments.keys().map(String::as_str).collect::<Vec<_>>();
+    names.sort_by_key(|name| !required.contains(name));
+
+    let mut preview = names
+        .iter()
+        .take(MAX_PREVIEW_ARGUMENTS)
+        .map(|name| {
+            let value = match &arguments[*name] {
+                serde_json::Value::String(string) => {
+                    format!(
+                        "\"{}\"",
+                        truncate_and_trailoff(string, MAX_PREVIEW_VALUE_CHARS)
+                    )
+                }
+                value => truncate_and_trailoff(&value.to_string(), MAX_PREVIEW_VALUE_CHARS),
+            };
+            // Backticks would end the code span early.
+            format!("`{name}: {}`", value.replace('`', "'").replace('\n', " "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if names.len() > MAX_PREVIEW_ARGUMENTS {
+        preview.push_str(&format!(
+            " and {} more",
+            names.len() - MAX_PREVIEW_ARGUMENTS
+        ));
+    }
+    Some(preview)
+}
+
+/// The tool output recorded for a response that's a table, kept with the raw text so that the
+/// card can switch between them.
+#[derive(Serialize, Deserialize)]
+struct ContextServerTableOutput {
+    table: ToolResultTable,
+    raw: String,
+}
+
+/// The tool output recorded for a text response that looked like a prompt injection.
+#[derive(Serialize, Deserialize)]
+struct PromptInjectionWarning {
+    findings: Vec<String>,
+}
+
+fn decode_image(data: &str, mime_type: &str) -> Result<Arc<Image>> {
+    let Some(format) = ImageFormat::from_mime_type(mime_type) else {
+        bail!("Unsupported image type in tool response: {mime_type}");
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .context("decoding image from tool response")?;
+    Ok(Arc::new(Image::from_bytes(format, bytes)))
+}
+
+struct PromptInjectionWarningCard {
+    tool_name: SharedString,
+    findings: Vec<String>,
+}
+
+impl ToolCard for PromptInjectionWarningCard {
+    fn render(
+        &mut self,
+        _status: &ToolUseStatus,
+        _window: &mut Window,
+        _workspace: WeakEntity<Workspace>,
+        _cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        v_flex()
+            .mb_2()
+            .gap_1()
+            .child(
+                h_flex()
+                    .gap_1p5()
+                    .child(
+                        Icon::new(IconName::Warning)
+                            .size(IconSize::Small)
+                            .color(Color::Warning),
+                    )
+                    .child(
+                        Label::new(format!(
+                            "The output of {} may be trying to instruct the model",
+                            self.tool_name
+                        ))
+                        .size(LabelSize::Small),
+                    ),
+            )
+            .children(self.findings.iter().map(|finding| {
+                Label::new(finding.clone())
+                    .size(LabelSize::Small)
+                    .color(Color::Muted)
+            }))
+    }
+}
+
+struct RateLimitedToolCard {
+    tool_name: SharedString,
+    send_at: Instant,
+    /// The card of the call's output, once it's done.
+    result_card: Option<AnyToolCard>,
+    _update_countdown: Task<()>,
+}
+
+impl RateLimitedToolCard {
+    fn new(tool_name: SharedString, send_at: Instant, cx: &mut Context<Self>) -> Self {
+        let update_countdown = cx.spawn(async move |this, cx| {
+            while Instant::now() < send_at {
+                cx.background_executor().timer(Duration::from_secs(1)).await;
+                if this.update(cx, |_, cx| cx.notify()).is_err() {
+                    break;
                 }
-                Ok(result.into())
+            }
+        });
+        Self {
+            tool_name,
+            send_at,
+            result_card: None,
+            _update_countdown: update_countdown,
+        }
+    }
+}
+
+impl ToolCard for RateLimitedToolCard {
+    fn render(
+        &mut self,
+        status: &ToolUseStatus,
+        window: &mut Window,
+        workspace: WeakEntity<Workspace>,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        if let (ToolUseStatus::Finished(_), Some(result_card)) = (status, &self.result_card) {
+            return result_card.render(status, window, workspace, cx);
+        }
+        let remaining = self.send_at.saturating_duration_since(Instant::now());
+        let (icon, color, message) = match status {
+            ToolUseStatus::Finished(output) => (IconName::Check, Color::Success, output.clone()),
+            ToolUseStatus::Error(error) => (
+                IconName::Close,
+                Color::Error,
+                ContextServerToolError::parse(error)
+                    .map_or_else(|| error.clone(), |error| error.summary().into()),
+            ),
+            _ if remaining > Duration::ZERO => (
+                IconName::CountdownTimer,
+                Color::Warning,
+                format!(
+                    "Rate limited, retrying in {}s",
+                    remaining.as_secs_f32().ceil() as u64
+                )
+                .into(),
+            ),
+            _ => (IconName::ArrowCircle, Color::Muted, "Running…".into()),
+        };
+        v_flex()
+            .mb_2()
+            .gap_1()
+            .child(
+                h_flex()
+                    .gap_1p5()
+                    .child(Icon::new(icon).size(IconSize::Small).color(color))
+                    .child(
+                        Label::new(format!("Run MCP tool `{}`", self.tool_name))
+                            .size(LabelSize::Small),
+                    ),
+            )
+            .child(
+                Label::new(message)
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .into_any_element()
+    }
+}
+
+struct ContextServerImageCard {
+    tool_name: SharedString,
+    /// Why the text that came with the image looked like a prompt injection, if it did.
+    prompt_injection_findings: Vec<String>,
+    /// The image as returned by the server, once it's decoded.
+    original: Option<Arc<Image>>,
+    sent_size: Size<DevicePixels>,
+    _decode: Task<()>,
+}
+
+impl ToolCard for ContextServerImageCard {
+    fn render(
+        &mut self,
+        _status: &ToolUseStatus,
+        _window: &mut Window,
+        _workspace: WeakEntity<Workspace>,
+        _cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        v_flex()
+            .mb_2()
+            .gap_1()
+            .child(
+                h_flex()
+                    .gap_1p5()
+                    .child(
+                        Icon::new(IconName::Image)
+                            .size(IconSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .child(
+                        Label::new(format!("{} returned an image", self.tool_name))
+                            .size(LabelSize::Small),
+                    )
+                    .child(
+                        Label::new(format!(
+                            "Sent to the model at {}×{} pixels",
+                            self.sent_size.width.0, self.sent_size.height.0
+                        ))
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                    ),
+            )
+            .when(!self.prompt_injection_findings.is_empty(), |this| {
+                this.child(
+                    h_flex()
+                        .gap_1p5()
+                        .child(
+                            Icon::new(IconName::Warning)
+                                .size(IconSize::Small)
+                                .color(Color::Warning),
+                        )
+                        .child(
+                            Label::new(
+                                "The text along with it may be trying to instruct the model",
+                            )
+                            .size(LabelSize::Small),
+                        ),
+                )
+                .children(self.prompt_injection_findings.iter().map(|finding| {
+                    Label::new(finding.clone())
+                        .size(LabelSize::Small)
+                        .color(Color::Muted)
+                }))
             })
-            .into()
-        } else {
-            Task::ready(Err(anyhow!("Context server not found"))).into()
+            .children(
+                self.original
+                    .clone()
+                    .map(|original| img(original).max_w_full().max_h(px(480.))),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use serde_json::json;
+
+    #[test]
+    fn test_path_arguments() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "sourceFile": { "type": "string" },
+                "paths": { "type": "array", "items": { "type": "string" } },
+                "destination": { "type": "string", "description": "Path to move the file to" },
+                "pattern": { "type": "string" },
+                "profile": { "type": "string" },
+            },
+        });
+        let input = json!({
+            "path": "/a",
+            "sourceFile": "/b",
+            "paths": ["/c", "/d"],
+            "destination": "/e",
+            "pattern": "*.rs",
+            "profile": "default",
+        });
+        let mut paths = path_arguments(&schema, &input);
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                ("destination".to_string(), "/e"),
+                ("path".to_string(), "/a"),
+                ("paths".to_string(), "/c"),
+                ("paths".to_string(), "/d"),
+                ("sourceFile".to_string(), "/b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_path_arguments() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "options": {
+                    "type": "object",
+                    "properties": {
+                        "output": { "type": "string", "description": "Path of the report" },
+                    },
+                },
+                "edits": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "file": { "type": "string" },
+                            "text": { "type": "string" },
+                        },
+                    },
+                },
+            },
+        });
+        let input = json!({
+            "options": { "output": "/a", "cwd": "/b", "depth": 2 },
+            "edits": [
+                { "file": "/c", "text": "/not/a/path" },
+                { "file": "/d" },
+            ],
+            "extra": { "target_dir": ["/e"] },
+        });
+        let mut paths = path_arguments(&schema, &input);
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                ("edits.file".to_string(), "/c"),
+                ("edits.file".to_string(), "/d"),
+                ("extra.target_dir".to_string(), "/e"),
+                ("options.cwd".to_string(), "/b"),
+                ("options.output".to_string(), "/a"),
+            ]
+        );
+    }
+
+    struct RedactingMiddleware;
+
+    #[async_trait::async_trait]
+    impl ToolMiddleware for RedactingMiddleware {
+        async fn process_output(
+            &self,
+            _call: ToolCall<'_>,
+            response: &mut types::CallToolResponse,
+        ) -> Result<()> {
+            for content in &mut response.content {
+                if let types::ToolResponseContent::Text { text } = content {
+                    *text = text.replace("hunter2", "[redacted]");
+                }
+            }
+            if let Some(data) = response.structured_content.as_mut() {
+                *data = json!({ "redacted": true });
+            }
+            Ok(())
         }
     }
+
+    #[gpui::test]
+    async fn test_middleware_processes_errors_and_timeouts() {
+        let middleware: Vec<Arc<dyn ToolMiddleware>> = vec![Arc::new(RedactingMiddleware)];
+        let server_id = ContextServerId("test".into());
+        let call = ToolCall {
+            server_id: &server_id,
+            tool_name: "login",
+        };
+
+        let mut error = ContextServerToolError::ToolError {
+            code: Some(-32000),
+            message: "bad password hunter2".into(),
+            data: Some(json!({ "password": "hunter2" })),
+        };
+        process_error_output(&middleware, call, &mut error)
+            .await
+            .unwrap();
+        let ContextServerToolError::ToolError { message, data, .. } = error else {
+            panic!("expected a tool error");
+        };
+        assert_eq!(message, "bad password [redacted]");
+        assert_eq!(data, Some(json!({ "redacted": true })));
+
+        let progress = process_output_text(
+            &middleware,
+            call,
+            vec!["logged in with hunter2".into()],
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(progress, ["logged in with [redacted]"]);
+    }
+
+    #[gpui::test]
+    async fn test_small_png_is_passed_through(cx: &mut TestAppContext) {
+        let data = SharedString::new(
+            "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==",
+        );
+        let image = cx
+            .update(|cx| {
+                LanguageModelImage::from_base64_with_max_dimension(
+                    data.clone(),
+                    ImageFormat::Png,
+                    512,
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        assert_eq!(image.size(), size(DevicePixels(1), DevicePixels(1)));
+        assert_eq!(image.source, data);
+
+        // Anything without a PNG header is decoded, which fails for data that isn't base64.
+        let image = cx
+            .update(|cx| {
+                LanguageModelImage::from_base64_with_max_dimension(
+                    "not base64".into(),
+                    ImageFormat::Png,
+                    512,
+                    cx,
+                )
+            })
+            .await;
+        assert!(image.is_none());
+    }
+
+    #[test]
+    fn test_decode_data_uri_image() {
+        assert!(
+            decode_data_uri_image("https://example.com/icon.png")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            decode_data_uri_image("data:image/png;base64,iVBORw0KGgo=")
+                .unwrap()
+                .is_some()
+        );
+        assert!(decode_data_uri_image("data:image/x-unknown;base64,AAAA").is_err());
+    }
+
+    #[test]
+    fn test_image_output_into_json() {
+        let output = ContextServerImageOutput {
+            mime_type: "image/png".into(),
+            data: "iVBORw0KGgo=".into(),
+            sent_width: 640,
+            sent_height: 480,
+            text: String::new(),
+            other_images: Vec::new(),
+            prompt_injection_findings: Vec::new(),
+        };
+        let json = output.into_json();
+        assert_eq!(
+            json,
+            json!({
+                "mime_type": "image/png",
+                "data": "iVBORw0KGgo=",
+                "sent_width": 640,
+                "sent_height": 480,
+            })
+        );
+        let output = serde_json::from_value::<ContextServerImageOutput>(json).unwrap();
+        assert_eq!(output.data, "iVBORw0KGgo=");
+
+        let output = ContextServerImageOutput {
+            text: "A chart".into(),
+            other_images: vec![ContextServerImage {
+                mime_type: "image/jpeg".into(),
+                data: "/9j/4AAQ".into(),
+            }],
+            prompt_injection_findings: vec!["hidden Unicode characters".into()],
+            ..output
+        };
+        let output = with_structured_content(
+            ToolResultOutput {
+                content: ToolResultContent::Text(String::new()),
+                output: Some(output.into_json()),
+            },
+            Some(json!({ "points": 3 })),
+        );
+        assert_eq!(
+            output.output,
+            Some(json!({
+                "mime_type": "image/png",
+                "data": "iVBORw0KGgo=",
+                "sent_width": 640,
+                "sent_height": 480,
+                "text": "A chart",
+                "other_images": [{ "mime_type": "image/jpeg", "data": "/9j/4AAQ" }],
+                "prompt_injection_findings": ["hidden Unicode characters"],
+                "structured_content": { "points": 3 },
+            }))
+        );
+        let restored =
+            serde_json::from_value::<ContextServerImageOutput>(output.output.unwrap()).unwrap();
+        assert_eq!(
+            restored.prompt_injection_findings,
+            vec!["hidden Unicode characters".to_string()]
+        );
+        let output =
+            with_structured_content("42".to_string().into(), Some(json!({ "answer": 42 })));
+        assert_eq!(
+            output.output,
+            Some(json!({ "structured_content": { "answer": 42 } }))
+        );
+    }
+
+    #[test]
+    fn test_offered_tool_name() {
+        let taken = ["query", "prod_query", "prod_query_2"];
+        let is_taken = |name: &str| taken.contains(&name);
+        assert_eq!(offered_tool_name(None, "search", is_taken), "search");
+        assert_eq!(offered_tool_name(None, "query", is_taken), "query_2");
+        assert_eq!(
+            offered_tool_name(Some("prod_"), "query", is_taken),
+            "prod_query_3"
+        );
+
+        let long_name = "a".repeat(80);
+        let name = offered_tool_name(Some("prod_"), &long_name, |_| false);
+        assert_eq!(name.len(), MAX_TOOL_NAME_LEN);
+        assert!(name.starts_with("prod_a"));
+        let deduped = offered_tool_name(Some("prod_"), &long_name, |taken| taken == name);
+        assert_eq!(deduped.len(), MAX_TOOL_NAME_LEN);
+        assert!(deduped.ends_with("a_2"));
+    }
+
+    #[test]
+    fn test_model_matches() {
+        assert!(model_matches("anthropic/*", "anthropic", "claude-sonnet-4"));
+        assert!(!model_matches("anthropic/*", "openai", "gpt-4o"));
+        assert!(model_matches("openai/gpt-4*", "openai", "gpt-4o"));
+        assert!(!model_matches("openai/gpt-4*", "openai", "o3"));
+        assert!(model_matches("*/gpt-4o", "copilot_chat", "gpt-4o"));
+        assert!(model_matches("gpt-4o", "openai", "gpt-4o"));
+        assert!(!model_matches("gpt-4o", "openai", "gpt-4o-mini"));
+    }
+
+    #[test]
+    fn test_tool_call_meta() {
+        let tool_env = HashMap::from_iter([
+            (
+                "*".to_string(),
+                HashMap::from_iter([
+                    ("REGION".to_string(), "eu".to_string()),
+                    ("READ_ONLY".to_string(), "0".to_string()),
+                ]),
+            ),
+            (
+                "query".to_string(),
+                HashMap::from_iter([("READ_ONLY".to_string(), "1".to_string())]),
+            ),
+        ]);
+        assert_eq!(
+            tool_call_meta(&tool_env, "query")[TOOL_CALL_ENV_META_KEY],
+            json!({ "REGION": "eu", "READ_ONLY": "1" })
+        );
+        assert_eq!(
+            tool_call_meta(&tool_env, "migrate")[TOOL_CALL_ENV_META_KEY],
+            json!({ "REGION": "eu", "READ_ONLY": "0" })
+        );
+        assert!(tool_call_meta(&HashMap::default(), "query").is_empty());
+    }
+
+    #[test]
+    fn test_description_with_examples() {
+        assert_eq!(
+            description_with_examples(Some("Search issues."), &[]),
+            "Search issues."
+        );
+        assert_eq!(
+            description_with_examples(
+                Some("Search issues.\n"),
+                &[
+                    r#"Find open bugs: {"query": "is:open label:bug"}"#.into(),
+                    r#"Find my issues: {"query": "assignee:@me"}"#.into(),
+                ]
+            ),
+            "Search issues.\n\nExamples:\n\
+            - Find open bugs: {\"query\": \"is:open label:bug\"}\n\
+            - Find my issues: {\"query\": \"assignee:@me\"}"
+        );
+        assert_eq!(
+            description_with_examples(None, &["Search everything".into()]),
+            "Examples:\n- Search everything"
+        );
+    }
+
+    #[test]
+    fn test_fill_ui_text_template() {
+        let input = json!({
+            "database": "analytics",
+            "table": "events",
+            "options": { "limit": 10 },
+        });
+        assert_eq!(
+            fill_ui_text_template("Query {database} for {table}", &input).as_deref(),
+            Some("Query analytics for events")
+        );
+        assert_eq!(
+            fill_ui_text_template("Read { options.limit } rows", &input).as_deref(),
+            Some("Read 10 rows")
+        );
+        assert_eq!(fill_ui_text_template("Drop {schema}", &input), None);
+        assert_eq!(fill_ui_text_template("Unclosed {table", &input), None);
+    }
+
+    #[test]
+    fn test_argument_preview() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": { "type": "integer" },
+                "query": { "type": "string" },
+            },
+            "required": ["query"],
+        });
+        assert_eq!(
+            argument_preview(
+                &schema,
+                &json!({ "limit": 10, "query": "SELECT *\nFROM `events`" })
+            )
+            .as_deref(),
+            Some("`query: \"SELECT * FROM 'events'\"`, `limit: 10`")
+        );
+        assert_eq!(
+            argument_preview(
+                &schema,
+                &json!({ "a": 1, "b": [1, 2], "c": { "d": true }, "query": "x".repeat(50) })
+            )
+            .as_deref(),
+            Some(format!(
+                "`query: \"{}…\"`, `a: 1`, `b: [1,2]` and 1 more",
+                "x".repeat(40)
+            ))
+            .as_deref()
+        );
+        assert_eq!(argument_preview(&schema, &json!({})), None);
+    }
+
+    #[gpui::test]
+    async fn test_is_path_in_worktrees(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                "project": { "src": { "main.rs": "" } },
+                "home": { ".ssh": { "id_rsa": "" } },
+            }),
+        )
+        .await;
+        fs.insert_symlink("/root/project/keys", PathBuf::from("/root/home/.ssh"))
+            .await;
+        fs.insert_symlink(
+            "/root/project/notes.txt",
+            PathBuf::from("/root/home/.config/autostart.desktop"),
+        )
+        .await;
+        let roots = [PathBuf::from("/root/project")];
+        let cwd = Some(Path::new("/root/project"));
+        let fs = fs.as_ref() as &dyn Fs;
+
+        assert!(is_path_in_worktrees("/root/project/src/main.rs", &roots, cwd, fs).await);
+        assert!(is_path_in_worktrees("src/new_file.rs", &roots, cwd, fs).await);
+        assert!(is_path_in_worktrees("file:///root/project/src", &roots, cwd, fs).await);
+        assert!(!is_path_in_worktrees("/root/home/.ssh/id_rsa", &roots, cwd, fs).await);
+        assert!(!is_path_in_worktrees("../home/.ssh/id_rsa", &roots, cwd, fs).await);
+        assert!(!is_path_in_worktrees("/root/project/keys/id_rsa", &roots, cwd, fs).await);
+        assert!(!is_path_in_worktrees("/root/project/notes.txt", &roots, cwd, fs).await);
+        assert!(!is_path_in_worktrees("/root/project/src/main.rs", &[], cwd, fs).await);
+
+        // Relative paths are resolved where the server runs, which needn't be the project.
+        let home = Some(Path::new("/root/home"));
+        assert!(!is_path_in_worktrees(".ssh/id_rsa", &roots, home, fs).await);
+        assert!(is_path_in_worktrees("../project/src", &roots, home, fs).await);
+        assert!(!is_path_in_worktrees("src/main.rs", &roots, None, fs).await);
+    }
 }
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use context_server::{ContextServerId, types};
+use db::kvp::KEY_VALUE_STORE;
+
+/// How the tools of a context server differ from the ones it reported when it last ran.
+///
+/// Models tend to keep calling a tool the way they did earlier in a thread, so they misuse tools
+/// whose schema changed underneath them, such as after a server update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextServerToolsChanged {
+    pub server_id: ContextServerId,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Tools whose input schema changed.
+    pub changed: Vec<String>,
+}
+
+impl ContextServerToolsChanged {
+    fn diff(
+        server_id: ContextServerId,
+        previous: &[types::Tool],
+        current: &[types::Tool],
+    ) -> Option<Self> {
+        let find = |tools: &[types::Tool], name: &str| {
+            tools
+                .iter()
+                .find(|tool| tool.name == name)
+                .map(|tool| tool.input_schema.clone())
+        };
+        let mut changes = Self {
+            server_id,
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+        for tool in current {
+            match find(previous, &tool.name) {
+                None => changes.added.push(tool.name.clone()),
+                Some(schema) if schema != tool.input_schema => {
+                    changes.changed.push(tool.name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        for tool in previous {
+            if find(current, &tool.name).is_none() {
+                changes.removed.push(tool.name.clone());
+            }
+        }
+        (!changes.added.is_empty() || !changes.removed.is_empty() || !changes.changed.is_empty())
+            .then_some(changes)
+    }
+
+    /// Whether calls to the tool named `tool_name` were made against a schema that no longer
+    /// applies.
+    pub fn affects(&self, tool_name: &str) -> bool {
+        self.removed
+            .iter()
+            .chain(&self.changed)
+            .any(|name| name == tool_name)
+    }
+
+    pub fn summary(&self) -> String {
+        let list = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut changes = Vec::new();
+        if !self.changed.is_empty() {
+            changes.push(format!("changed {}", list(&self.changed)));
+        }
+        if !self.removed.is_empty() {
+            changes.push(format!("removed {}", list(&self.removed)));
+        }
+        if !self.added.is_empty() {
+            changes.push(format!("added {}", list(&self.added)));
+        }
+        format!(
+            "The tools of the {} MCP server changed since it last ran: {}.",
+            self.server_id,
+            changes.join("; ")
+        )
+    }
+}
+
+fn cached_tools_key(server_id: &ContextServerId) -> String {
+    format!("context_server_tools:{server_id}")
+}
+
+/// Returns the tools a server reported when it last ran, if it ran before.
+pub fn cached_tools(server_id: &ContextServerId) -> Result<Option<Vec<types::Tool>>> {
+    let Some(tools) = KEY_VALUE_STORE.read_kvp(&cached_tools_key(server_id))? else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::from_str(&tools)?))
+}
+
+/// Compares a server's tools to the ones it reported when it last ran, and remembers them for
+/// the next time.
+pub async fn update_cached_tools(
+    server_id: ContextServerId,
+    tools: Vec<types::Tool>,
+) -> Result<Option<ContextServerToolsChanged>> {
+    let previous = cached_tools(&server_id)?;
+    KEY_VALUE_STORE
+        .write_kvp(cached_tools_key(&server_id), serde_json::to_string(&tools)?)
+        .await?;
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+    Ok(ContextServerToolsChanged::diff(
+        server_id, &previous, &tools,
+    ))
+}
+
+/// Moves the tools cached for a server that was renamed in the settings to its new id.
+pub async fn rename_cached_tools(old_id: ContextServerId, new_id: ContextServerId) -> Result<()> {
+    let Some(tools) = KEY_VALUE_STORE.read_kvp(&cached_tools_key(&old_id))? else {
+        return Ok(());
+    };
+    KEY_VALUE_STORE
+        .write_kvp(cached_tools_key(&new_id), tools)
+        .await?;
+    KEY_VALUE_STORE.delete_kvp(cached_tools_key(&old_id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool(name: &str, input_schema: serde_json::Value) -> types::Tool {
+        types::Tool {
+            name: name.into(),
+            description: None,
+            input_schema,
+            annotations: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_tools() {
+        let server_id = ContextServerId("github".into());
+        let previous = [
+            tool("search", json!({ "type": "object" })),
+            tool("list_repos", json!({ "type": "object" })),
+            tool("get_issue", json!({ "type": "object" })),
+        ];
+        let current = [
+            tool("search", json!({ "type": "object", "required": ["query"] })),
+            tool("get_issue", json!({ "type": "object" })),
+            tool("create_issue", json!({ "type": "object" })),
+        ];
+
+        let changes =
+            ContextServerToolsChanged::diff(server_id.clone(), &previous, &current).unwrap();
+        assert_eq!(changes.changed, ["search"]);
+        assert_eq!(changes.removed, ["list_repos"]);
+        assert_eq!(changes.added, ["create_issue"]);
+        assert!(changes.affects("search"));
+        assert!(changes.affects("list_repos"));
+        assert!(!changes.affects("get_issue"));
+        assert_eq!(
+            changes.summary(),
+            "The tools of the github MCP server changed since it last ran: changed `search`; \
+            removed `list_repos`; added `create_issue`."
+        );
+
+        assert_eq!(
+            ContextServerToolsChanged::diff(server_id, &current, &current),
+            None
+        );
+    }
+}
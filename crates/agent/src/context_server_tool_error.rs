@@ -0,0 +1,157 @@
+use std::fmt;
+
+use context_server::client::RequestError;
+use context_server::protocol::ToolCallCancelled;
+use serde::{Deserialize, Serialize};
+
+/// Why a call to an MCP tool failed.
+///
+/// It's sent to the model as JSON, with a summary and a hint on how to recover, so that the
+/// model can tell a server that needs restarting from a call it should fix.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContextServerToolError {
+    /// The server isn't running, or failed to start.
+    ServerNotRunning { server: String, reason: String },
+    /// The server is running, but hasn't finished the protocol's handshake.
+    NotInitialized { server: String },
+    /// The server didn't respond in time.
+    Timeout { server: String },
+    /// The call was cancelled to restart the server with a new configuration.
+    ServerRestarted { server: String },
+    /// The call couldn't be sent, or the server's response couldn't be understood.
+    ProtocolError { server: String, message: String },
+    /// The tool ran, and reported that it failed.
+    ToolError {
+        /// The JSON-RPC error code, when the server responded with an error rather than a
+        /// result marked as one.
+        code: Option<i32>,
+        message: String,
+        data: Option<serde_json::Value>,
+    },
+}
+
+impl ContextServerToolError {
+    /// Categorizes an error returned by the protocol client for a tool call.
+    pub fn from_request_error(server: &str, error: anyhow::Error) -> Self {
+        if error.is::<ToolCallCancelled>() {
+            return Self::ServerRestarted {
+                server: server.to_string(),
+            };
+        }
+        match error.downcast::<RequestError>() {
+            Ok(RequestError::Timeout) => Self::Timeout {
+                server: server.to_string(),
+            },
+            Ok(RequestError::Response {
+                code,
+                message,
+                data,
+            }) => Self::ToolError {
+                code: Some(code),
+                message,
+                data,
+            },
+            Err(error) => Self::ProtocolError {
+                server: server.to_string(),
+                message: format!("{error:#}"),
+            },
+        }
+    }
+
+    /// Parses the error from the text of a tool result, if it's one.
+    pub fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str(text).ok()
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            Self::ServerNotRunning { server, reason } => {
+                format!("The {server} MCP server isn't running: {reason}")
+            }
+            Self::NotInitialized { server } => {
+                format!("The {server} MCP server hasn't finished starting")
+            }
+            Self::Timeout { server } => format!("The {server} MCP server didn't respond in time"),
+            Self::ServerRestarted { server } => format!(
+                "The {server} MCP server was restarted with a new configuration before the call finished"
+            ),
+            Self::ProtocolError { server, message } => {
+                format!("Couldn't communicate with the {server} MCP server: {message}")
+            }
+            Self::ToolError { message, .. } => message.clone(),
+        }
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::ServerNotRunning { .. } => {
+                "Ask the user to check the server's configuration and restart it. Retrying won't \
+                help until then."
+            }
+            Self::NotInitialized { .. } => "Wait for the server to finish starting, then retry.",
+            Self::Timeout { .. } => {
+                "The tool may still be running. Retry, ideally asking for less work at once."
+            }
+            Self::ServerRestarted { .. } => {
+                "The call may or may not have taken effect. Check before retrying it."
+            }
+            Self::ProtocolError { .. } => {
+                "This is likely a bug in the server. Retrying may not help."
+            }
+            Self::ToolError { .. } => "Check the call's arguments against the error, then retry.",
+        }
+    }
+}
+
+impl fmt::Display for ContextServerToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut report = serde_json::to_value(self).map_err(|_| fmt::Error)?;
+        report["summary"] = self.summary().into();
+        report["hint"] = self.hint().into();
+        write!(f, "{report}")
+    }
+}
+
+impl std::error::Error for ContextServerToolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_error_round_trip() {
+        let error = ContextServerToolError::from_request_error(
+            "github",
+            RequestError::Response {
+                code: -32602,
+                message: "Unknown repository".into(),
+                data: Some(json!({ "repository": "zed/zed" })),
+            }
+            .into(),
+        );
+        assert_eq!(
+            error,
+            ContextServerToolError::ToolError {
+                code: Some(-32602),
+                message: "Unknown repository".into(),
+                data: Some(json!({ "repository": "zed/zed" })),
+            }
+        );
+
+        let text = error.to_string();
+        let report = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+        assert_eq!(report["kind"], "tool_error");
+        assert_eq!(report["summary"], "Unknown repository");
+        assert_eq!(ContextServerToolError::parse(&text), Some(error));
+        assert_eq!(ContextServerToolError::parse("Unknown repository"), None);
+
+        assert_eq!(
+            ContextServerToolError::from_request_error("github", RequestError::Timeout.into()),
+            ContextServerToolError::Timeout {
+                server: "github".into()
+            }
+        );
+    }
+}
@@ -54,6 +54,10 @@ impl ContextStore {
         }
     }
 
+    pub fn thread_store(&self) -> Option<&WeakEntity<ThreadStore>> {
+        self.thread_store.as_ref()
+    }
+
     pub fn context(&self) -> impl Iterator<Item = &AgentContextHandle> {
         self.context_set.iter().map(|entry| entry.as_ref())
     }
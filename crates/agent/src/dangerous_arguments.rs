@@ -0,0 +1,129 @@
+use assistant_settings::AssistantSettings;
+use assistant_tool::Tool;
+use collections::IndexMap;
+use gpui::App;
+use regex::Regex;
+use settings::Settings as _;
+use util::ResultExt as _;
+
+/// The `dangerous_tool_arguments` rules, which make the agent ask before running a tool call
+/// whose input matches one of them, even when the tool's calls are otherwise allowed.
+pub struct DangerousArgumentRules {
+    rules: Vec<(String, Regex)>,
+}
+
+impl DangerousArgumentRules {
+    pub fn from_settings(settings: &AssistantSettings) -> Self {
+        Self::new(&settings.dangerous_tool_arguments)
+    }
+
+    /// Compiles the rules, skipping the ones turned off with an empty pattern.
+    pub fn new(rules: &IndexMap<String, String>) -> Self {
+        Self {
+            rules: rules
+                .iter()
+                .filter(|(_, pattern)| !pattern.is_empty())
+                .filter_map(|(name, pattern)| {
+                    let pattern = Regex::new(pattern).log_err()?;
+                    Some((name.clone(), pattern))
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the names of the rules that the string values in `input` match.
+    pub fn matching_rules(&self, input: &serde_json::Value) -> Vec<&str> {
+        let mut strings = Vec::new();
+        collect_strings(input, &mut strings);
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| strings.iter().any(|string| pattern.is_match(string)))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Whether a tool call has to be confirmed before it runs, which the agent, inline assists and
+/// tasks decide the same way.
+pub struct ToolCallConfirmation {
+    pub needs_confirmation: bool,
+    /// The names of the rules the call's input matches.
+    pub dangerous_arguments: Vec<String>,
+}
+
+impl ToolCallConfirmation {
+    /// Every call is confirmed while only read-only tools may run, and so is every call whose
+    /// input matches a rule, even when `always_allow_tool_actions` is on.
+    pub fn new(
+        tool: &dyn Tool,
+        input: &serde_json::Value,
+        read_only_tools: bool,
+        cx: &App,
+    ) -> Self {
+        let settings = AssistantSettings::get_global(cx);
+        let dangerous_arguments = DangerousArgumentRules::from_settings(settings)
+            .matching_rules(input)
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let needs_confirmation = read_only_tools
+            || !dangerous_arguments.is_empty()
+            || (tool.needs_confirmation(input, cx) && !settings.always_allow_tool_actions);
+        Self {
+            needs_confirmation,
+            dangerous_arguments,
+        }
+    }
+}
+
+fn collect_strings<'a>(value: &'a serde_json::Value, strings: &mut Vec<&'a str>) {
+    match value {
+        serde_json::Value::String(string) => strings.push(string),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_strings(item, strings);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values() {
+                collect_strings(field, strings);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_matching_rules() {
+        let rules = DangerousArgumentRules::new(&IndexMap::from_iter([
+            (
+                "rm -rf".to_string(),
+                r"\brm\s+(?:-[a-zA-Z]*[rR][a-zA-Z]*f|-[a-zA-Z]*f[a-zA-Z]*[rR])".to_string(),
+            ),
+            ("--force".to_string(), r"(?:^|\s)--force\b".to_string()),
+            ("DROP TABLE".to_string(), String::new()),
+        ]));
+
+        assert_eq!(
+            rules.matching_rules(&json!({ "command": "cargo build --release" })),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            rules.matching_rules(&json!({ "command": "rm -fr target && git push --force" })),
+            ["rm -rf", "--force"]
+        );
+        assert_eq!(
+            rules.matching_rules(&json!({ "args": ["push", "--force"] })),
+            ["--force"]
+        );
+        assert_eq!(
+            rules.matching_rules(&json!({ "query": "DROP TABLE users" })),
+            Vec::<&str>::new()
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::io::Write as _;
+use std::sync::{LazyLock, mpsc};
+
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use futures::channel::oneshot;
+use serde::Deserialize;
+use util::ResultExt as _;
+
+struct Prompt {
+    question: String,
+    answer: oneshot::Sender<bool>,
+}
+
+/// The questions waiting to be asked on the terminal. A thread of their own asks them one at a
+/// time, so that the prompts of threads running side by side don't interleave, and so that
+/// waiting for an answer doesn't hold up a thread of the executor.
+static PROMPTS: LazyLock<mpsc::Sender<Prompt>> = LazyLock::new(|| {
+    let (prompts_tx, prompts_rx) = mpsc::channel::<Prompt>();
+    std::thread::Builder::new()
+        .name("tool-confirmation-prompt".into())
+        .spawn(move || {
+            for prompt in prompts_rx {
+                prompt.answer.send(ask(&prompt.question)).ok();
+            }
+        })
+        .log_err();
+    prompts_tx
+});
+
+/// What to do with a tool call that needs confirmation when there's no window to ask in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadlessToolDecision {
+    Allow,
+    Deny,
+    /// Asks on stdin, when prompting is enabled, and denies the call otherwise.
+    Ask,
+}
+
+/// Resolves the tool calls that need confirmation when the agent runs headlessly, such as in
+/// evals, where nobody can click the buttons of a confirmation.
+///
+/// Calls of Zed's read-only tools, and of the tools in `read_only_tool_names`, are allowed and
+/// calls of other tools are denied, unless the policy file says otherwise. The annotations of MCP
+/// servers' tools aren't trusted to decide that a tool is read-only. For example:
+///
+/// ```json
+/// {
+///   "tools": {
+///     "fetch_url": "allow",
+///     "delete_path": "deny"
+///   },
+///   "default": "ask"
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct HeadlessToolPolicy {
+    /// Decisions for the calls of these tools, which take precedence over the rest of the policy.
+    #[serde(default)]
+    pub tools: HashMap<String, HeadlessToolDecision>,
+    /// The decision for calls of the other tools that can make changes.
+    #[serde(default)]
+    pub default: Option<HeadlessToolDecision>,
+    /// Whether calls without an `allow` or `deny` decision are asked about on stdin.
+    #[serde(skip)]
+    pub prompt_on_stdin: bool,
+}
+
+impl HeadlessToolPolicy {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json_lenient::from_str(json).context("parsing the tool policy")
+    }
+
+    /// Decides on a call of the tool named `tool_name`, where `read_only` is whether the tool is
+    /// known to be read-only. Calls whose input matches a `dangerous_tool_arguments` rule are
+    /// asked about even when the tool is read-only.
+    pub fn decision(
+        &self,
+        tool_name: &str,
+        read_only: bool,
+        dangerous_arguments: bool,
+    ) -> HeadlessToolDecision {
+        let decision = if let Some(decision) = self.tools.get(tool_name) {
+            *decision
+        } else if dangerous_arguments {
+            HeadlessToolDecision::Ask
+        } else if read_only {
+            HeadlessToolDecision::Allow
+        } else {
+            self.default.unwrap_or(HeadlessToolDecision::Ask)
+        };
+        if decision == HeadlessToolDecision::Ask && !self.prompt_on_stdin {
+            HeadlessToolDecision::Deny
+        } else {
+            decision
+        }
+    }
+}
+
+/// Asks `question` on the terminal, after the questions asked before it, and returns whether the
+/// answer was yes.
+pub async fn prompt_on_stdin(question: String) -> bool {
+    let (answer_tx, answer_rx) = oneshot::channel();
+    let prompt = Prompt {
+        question,
+        answer: answer_tx,
+    };
+    if PROMPTS.send(prompt).is_err() {
+        return false;
+    }
+    answer_rx.await.unwrap_or(false)
+}
+
+fn ask(question: &str) -> bool {
+    let mut stderr = std::io::stderr();
+    write!(stderr, "{question} [y/N] ").ok();
+    stderr.flush().ok();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_tool_decision() {
+        let policy = HeadlessToolPolicy::default();
+        assert_eq!(
+            policy.decision("read_file", true, false),
+            HeadlessToolDecision::Allow
+        );
+        assert_eq!(
+            policy.decision("edit_file", false, false),
+            HeadlessToolDecision::Deny
+        );
+        assert_eq!(
+            policy.decision("terminal", true, true),
+            HeadlessToolDecision::Deny
+        );
+
+        let mut policy = HeadlessToolPolicy::from_json(
+            r#"{
+                "tools": {
+                    "edit_file": "allow",
+                    "fetch_url": "deny",
+                    "terminal": "ask",
+                },
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy.decision("edit_file", false, true),
+            HeadlessToolDecision::Allow
+        );
+        assert_eq!(
+            policy.decision("fetch_url", true, false),
+            HeadlessToolDecision::Deny
+        );
+        assert_eq!(
+            policy.decision("terminal", false, false),
+            HeadlessToolDecision::Deny
+        );
+
+        policy.prompt_on_stdin = true;
+        assert_eq!(
+            policy.decision("terminal", false, false),
+            HeadlessToolDecision::Ask
+        );
+        assert_eq!(
+            policy.decision("delete_path", false, false),
+            HeadlessToolDecision::Ask
+        );
+        assert_eq!(
+            policy.decision("read_file", true, false),
+            HeadlessToolDecision::Allow
+        );
+    }
+}
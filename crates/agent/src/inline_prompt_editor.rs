@@ -96,6 +96,11 @@ impl<T: 'static> Render for PromptEditor<T> {
 
         buttons.extend(self.render_buttons(window, cx));
 
+        let tool_confirmation = match &self.mode {
+            PromptEditorMode::Buffer { codegen, .. } => Self::render_tool_confirmation(codegen, cx),
+            PromptEditorMode::Terminal { .. } => None,
+        };
+
         v_flex()
             .key_context("PromptEditor")
             .bg(cx.theme().colors().editor_background)
@@ -204,15 +209,23 @@ impl<T: 'static> Render for PromptEditor<T> {
                     .flex_row()
                     .items_center()
                     .child(h_flex().flex_shrink_0().w(left_gutter_width))
-                    .child(
-                        h_flex()
-                            .w_full()
-                            .pl_1()
-                            .items_start()
-                            .justify_between()
-                            .child(self.context_strip.clone())
-                            .child(self.model_selector.clone()),
-                    ),
+                    .map(|el| {
+                        // Tool calls are confirmed in place of the context, which keeps the height
+                        // of the prompt block the same.
+                        if let Some(tool_confirmation) = tool_confirmation {
+                            el.child(tool_confirmation)
+                        } else {
+                            el.child(
+                                h_flex()
+                                    .w_full()
+                                    .pl_1()
+                                    .items_start()
+                                    .justify_between()
+                                    .child(self.context_strip.clone())
+                                    .child(self.model_selector.clone()),
+                            )
+                        }
+                    }),
             )
     }
 }
@@ -302,6 +315,67 @@ impl<T: 'static> PromptEditor<T> {
         self.editor.read(cx).text(cx)
     }
 
+    fn render_tool_confirmation(codegen: &Entity<BufferCodegen>, cx: &App) -> Option<AnyElement> {
+        let confirmation = codegen.read(cx).tool_confirmation(cx)?;
+        let respond = |allow: bool| {
+            let codegen = codegen.clone();
+            move |_: &ClickEvent, _: &mut Window, cx: &mut App| {
+                codegen.update(cx, |codegen, cx| {
+                    codegen.respond_to_tool_confirmation(allow, cx)
+                });
+            }
+        };
+        Some(
+            h_flex()
+                .w_full()
+                .pl_1()
+                .gap_2()
+                .justify_between()
+                .child(
+                    h_flex()
+                        .min_w_0()
+                        .gap_1()
+                        .child(
+                            Icon::new(IconName::Hammer)
+                                .size(IconSize::Small)
+                                .color(Color::Muted),
+                        )
+                        .child(
+                            Label::new(format!("Run {}?", confirmation.ui_text))
+                                .size(LabelSize::Small)
+                                .truncate(),
+                        )
+                        .when_some(
+                            confirmation.dangerous_arguments.clone(),
+                            |el, dangerous_arguments| {
+                                el.child(
+                                    Label::new(format!("Looks dangerous: {dangerous_arguments}"))
+                                        .size(LabelSize::Small)
+                                        .color(Color::Warning),
+                                )
+                            },
+                        ),
+                )
+                .child(
+                    h_flex()
+                        .flex_shrink_0()
+                        .gap_1()
+                        .child(
+                            Button::new("deny-tool-call", "Deny")
+                                .label_size(LabelSize::Small)
+                                .on_click(respond(false)),
+                        )
+                        .child(
+                            Button::new("allow-tool-call", "Allow")
+                                .label_size(LabelSize::Small)
+                                .style(ButtonStyle::Filled)
+                                .on_click(respond(true)),
+                        ),
+                )
+                .into_any_element(),
+        )
+    }
+
     fn toggle_rate_limit_notice(
         &mut self,
         _: &ClickEvent,
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use anyhow::{Context as _, Result, anyhow};
+use assistant_slash_command::{
+    AfterCompletion, ArgumentCompletion, SlashCommand, SlashCommandOutput,
+    SlashCommandOutputSection, SlashCommandResult,
+};
+use editor::Editor;
+use gpui::{Entity, Task, WeakEntity};
+use language::{BufferSnapshot, CodeLabel, LspAdapterDelegate};
+use ui::prelude::*;
+use workspace::Workspace;
+
+use crate::{AgentPanel, Thread};
+
+/// How many of the latest results are offered, since older ones are rarely what's wanted.
+const MAX_COMPLETIONS: usize = 20;
+
+/// Inserts the result of one of the MCP tool calls in the agent thread into the active editor,
+/// at the cursor, so that query output can end up in a buffer rather than only in the thread.
+pub struct InsertToolResultSlashCommand;
+
+impl InsertToolResultSlashCommand {
+    fn agent_thread(workspace: &Workspace, cx: &App) -> Option<Entity<Thread>> {
+        Some(workspace.panel::<AgentPanel>(cx)?.read(cx).last_thread(cx))
+    }
+}
+
+impl SlashCommand for InsertToolResultSlashCommand {
+    fn name(&self) -> String {
+        "insert-tool-result".into()
+    }
+
+    fn icon(&self) -> IconName {
+        IconName::Hammer
+    }
+
+    fn description(&self) -> String {
+        "Insert an MCP tool result into the active editor".into()
+    }
+
+    fn menu_text(&self) -> String {
+        self.description()
+    }
+
+    fn requires_argument(&self) -> bool {
+        false
+    }
+
+    fn accepts_arguments(&self) -> bool {
+        true
+    }
+
+    fn complete_argument(
+        self: Arc<Self>,
+        _arguments: &[String],
+        _cancel: Arc<AtomicBool>,
+        workspace: Option<WeakEntity<Workspace>>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<Vec<ArgumentCompletion>>> {
+        let Some(thread) = workspace
+            .and_then(|workspace| workspace.upgrade())
+            .and_then(|workspace| Self::agent_thread(workspace.read(cx), cx))
+        else {
+            return Task::ready(Ok(Vec::new()));
+        };
+        let completions = thread
+            .read(cx)
+            .successful_context_server_tool_uses(cx)
+            .into_iter()
+            .take(MAX_COMPLETIONS)
+            .map(|tool_use| ArgumentCompletion {
+                label: CodeLabel::plain(tool_use.ui_text.to_string(), None),
+                new_text: tool_use.id.to_string(),
+                after_completion: AfterCompletion::Run,
+                replace_previous_arguments: true,
+            })
+            .collect();
+        Task::ready(Ok(completions))
+    }
+
+    fn run(
+        self: Arc<Self>,
+        arguments: &[String],
+        _context_slash_command_output_sections: &[SlashCommandOutputSection<language::Anchor>],
+        _context_buffer: BufferSnapshot,
+        workspace: WeakEntity<Workspace>,
+        _delegate: Option<Arc<dyn LspAdapterDelegate>>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Task<SlashCommandResult> {
+        let result = (|| {
+            let workspace = workspace.upgrade().context("workspace was dropped")?;
+            let workspace = workspace.read(cx);
+            let thread = Self::agent_thread(workspace, cx).context("no agent thread")?;
+            let thread = thread.read(cx);
+            let editor = workspace
+                .active_item(cx)
+                .and_then(|item| item.act_as::<Editor>(cx))
+                .context("no active editor to insert the result into")?;
+            // Without an argument, the latest result is inserted.
+            let tool_use_id = arguments.first().map(|argument| argument.trim());
+            let tool_use = thread
+                .successful_context_server_tool_uses(cx)
+                .into_iter()
+                .find(|tool_use| tool_use_id.is_none_or(|id| tool_use.id.to_string() == id))
+                .ok_or_else(|| anyhow!("no MCP tool result to insert"))?;
+            anyhow::Ok((editor, thread.full_output_for_tool(&tool_use.id, cx)))
+        })();
+        let (editor, output) = match result {
+            Ok(result) => result,
+            Err(error) => return Task::ready(Err(error)),
+        };
+
+        window.spawn(cx, async move |cx| {
+            let output = output.await?;
+            editor.update_in(cx, |editor, window, cx| editor.insert(&output, window, cx))?;
+            Ok(SlashCommandOutput {
+                text: String::new(),
+                sections: Vec::new(),
+                run_commands_in_text: false,
+            }
+            .to_event_stream())
+        })
+    }
+}
@@ -0,0 +1,612 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use context_server::ContextServerCommand;
+use gpui::{App, Context, Window};
+use project::project_settings::{ContextServerConfiguration, ProjectSettings};
+use serde_json::{Value, json};
+use settings::{Settings as _, SettingsLocation, SettingsStore, update_settings_file};
+use workspace::Workspace;
+
+use crate::report::open_read_only_report;
+
+/// An app whose MCP server configuration can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum McpConfigSource {
+    ClaudeDesktop,
+    VsCode,
+    Cursor,
+}
+
+impl McpConfigSource {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ClaudeDesktop => "Claude Desktop",
+            Self::VsCode => "VS Code",
+            Self::Cursor => "Cursor",
+        }
+    }
+
+    fn servers_key(&self) -> &'static str {
+        match self {
+            Self::VsCode => "servers",
+            Self::ClaudeDesktop | Self::Cursor => "mcpServers",
+        }
+    }
+}
+
+/// A file that may list MCP servers.
+struct McpConfigFile {
+    source: McpConfigSource,
+    path: PathBuf,
+    /// The worktree the file is in. Its servers are imported into that worktree's settings.
+    worktree_root: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq)]
+struct FoundServer {
+    name: String,
+    path: PathBuf,
+    /// The worktree whose settings the server is imported into, or `None` for the user's.
+    worktree_root: Option<PathBuf>,
+    command: ContextServerCommand,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct McpImport {
+    added: Vec<FoundServer>,
+    /// Servers that are already configured with the same command.
+    unchanged: Vec<String>,
+    conflicts: Vec<String>,
+    skipped: Vec<String>,
+    warnings: Vec<String>,
+}
+
+fn config_files(workspace: &Workspace, cx: &App) -> Vec<McpConfigFile> {
+    let mut files = vec![
+        McpConfigFile {
+            source: McpConfigSource::ClaudeDesktop,
+            path: paths::claude_desktop_config_file().clone(),
+            worktree_root: None,
+        },
+        McpConfigFile {
+            source: McpConfigSource::Cursor,
+            path: paths::cursor_mcp_file().clone(),
+            worktree_root: None,
+        },
+    ];
+    for worktree in workspace.project().read(cx).visible_worktrees(cx) {
+        let root = worktree.read(cx).abs_path().to_path_buf();
+        files.push(McpConfigFile {
+            source: McpConfigSource::VsCode,
+            path: root.join(paths::local_vscode_mcp_file_relative_path()),
+            worktree_root: Some(root.clone()),
+        });
+        files.push(McpConfigFile {
+            source: McpConfigSource::Cursor,
+            path: root.join(paths::local_cursor_mcp_file_relative_path()),
+            worktree_root: Some(root),
+        });
+    }
+    files
+}
+
+/// Reads the servers listed in one app's MCP configuration file.
+fn parse_config(
+    file: &McpConfigFile,
+    content: &str,
+    import: &mut McpImport,
+) -> anyhow::Result<Vec<FoundServer>> {
+    let config: Value = settings::parse_json_with_comments(content)?;
+    let Some(servers) = config
+        .get(file.source.servers_key())
+        .and_then(Value::as_object)
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut found = Vec::new();
+    for (name, server) in servers {
+        match parse_server(file, server) {
+            Ok(command) => {
+                if file.source == McpConfigSource::VsCode {
+                    if server.get("envFile").is_some() {
+                        import.warnings.push(format!(
+                            "`{name}` loads environment variables from an `envFile`, which isn't imported. Add them to its `env` instead."
+                        ));
+                    }
+                    if serde_json::to_string(server).is_ok_and(|json| json.contains("${input:")) {
+                        import.warnings.push(format!(
+                            "`{name}` refers to VS Code inputs (`${{input:…}}`), which Zed doesn't prompt for. Replace them with their values in your settings."
+                        ));
+                    }
+                }
+                found.push(FoundServer {
+                    name: name.clone(),
+                    path: file.path.clone(),
+                    worktree_root: file.worktree_root.clone(),
+                    command,
+                });
+            }
+            Err(reason) => import.skipped.push(format!(
+                "`{name}` from {} ({}): {reason}",
+                file.source.name(),
+                file.path.display()
+            )),
+        }
+    }
+    Ok(found)
+}
+
+fn parse_server(file: &McpConfigFile, server: &Value) -> Result<ContextServerCommand, String> {
+    let transport =
+        server
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or(if server.get("url").is_some() {
+                "http"
+            } else {
+                "stdio"
+            });
+    if transport != "stdio" {
+        return Err(format!(
+            "it uses the `{transport}` transport, and only servers that run as a local command can be imported"
+        ));
+    }
+
+    // Project files are imported into the project's settings, where Zed's own variable for the
+    // project's root keeps them working wherever the project is checked out.
+    let expand = |text: &str| match &file.worktree_root {
+        Some(_) => text.replace("${workspaceFolder}", "${worktree_root}"),
+        None => text.to_string(),
+    };
+
+    let path = server
+        .get("command")
+        .and_then(Value::as_str)
+        .filter(|command| !command.trim().is_empty())
+        .ok_or_else(|| "it has no `command`".to_string())?;
+    let args = match server.get("args") {
+        None => Vec::new(),
+        Some(Value::Array(args)) => args
+            .iter()
+            .map(|arg| match arg {
+                Value::String(arg) => Ok(expand(arg)),
+                Value::Number(arg) => Ok(arg.to_string()),
+                _ => Err("its `args` must be strings".to_string()),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("its `args` must be a list".to_string()),
+    };
+    let env = match server.get("env") {
+        None | Some(Value::Null) => None,
+        Some(Value::Object(env)) => Some(
+            env.iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(value) => expand(value),
+                        value => value.to_string(),
+                    };
+                    (key.clone(), value)
+                })
+                .collect::<HashMap<_, _>>(),
+        )
+        .filter(|env| !env.is_empty()),
+        Some(_) => return Err("its `env` must be an object".to_string()),
+    };
+    let cwd = match server.get("cwd") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(cwd)) => Some(expand(cwd)),
+        Some(_) => return Err("its `cwd` must be a string".to_string()),
+    };
+
+    Ok(ContextServerCommand {
+        path: expand(path),
+        args,
+        env,
+        cwd,
+    })
+}
+
+/// Decides which of the found servers to add, given the servers already in the settings that each
+/// would be imported into, keyed by their worktree's root.
+///
+/// Existing settings are never overwritten, and when several files define a server with the same
+/// name for the same settings, the first one wins.
+fn plan_import(
+    found: Vec<FoundServer>,
+    existing: &HashMap<Option<PathBuf>, HashMap<Arc<str>, ContextServerConfiguration>>,
+    import: &mut McpImport,
+) {
+    for server in found {
+        let configuration = existing
+            .get(&server.worktree_root)
+            .and_then(|existing| existing.get(server.name.as_str()));
+        if let Some(configuration) = configuration {
+            if configuration.command.as_ref() == Some(&server.command) {
+                if !import.unchanged.contains(&server.name) {
+                    import.unchanged.push(server.name);
+                }
+            } else {
+                import.conflicts.push(format!(
+                    "`{}` in {} differs from the server of the same name in your settings, which was kept.",
+                    server.name,
+                    server.path.display()
+                ));
+            }
+            continue;
+        }
+
+        match import
+            .added
+            .iter()
+            .find(|added| added.name == server.name && added.worktree_root == server.worktree_root)
+        {
+            Some(added) if added.command != server.command => {
+                import.conflicts.push(format!(
+                    "`{}` in {} differs from the one in {}, which was imported.",
+                    server.name,
+                    server.path.display(),
+                    added.path.display()
+                ));
+            }
+            Some(_) => {}
+            None => import.added.push(server),
+        }
+    }
+}
+
+fn report(import: &McpImport, files_read: &[PathBuf]) -> String {
+    let mut report = "# MCP Server Import\n\n".to_string();
+    if files_read.is_empty() {
+        report.push_str("No MCP configuration from Claude Desktop, VS Code or Cursor was found.\n");
+        return report;
+    }
+
+    report.push_str("Read the MCP servers from:\n\n");
+    for path in files_read {
+        writeln!(report, "- `{}`", path.display()).ok();
+    }
+
+    let sections = [
+        (
+            "Added",
+            import
+                .added
+                .iter()
+                .map(|server| match &server.worktree_root {
+                    Some(root) => format!(
+                        "`{}` from {}, into the settings of {}",
+                        server.name,
+                        server.path.display(),
+                        root.display()
+                    ),
+                    None => format!("`{}` from {}", server.name, server.path.display()),
+                })
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "Already Configured",
+            import
+                .unchanged
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect(),
+        ),
+        ("Conflicts", import.conflicts.clone()),
+        ("Skipped", import.skipped.clone()),
+        ("Needs Attention", import.warnings.clone()),
+    ];
+    for (title, items) in sections {
+        if items.is_empty() {
+            continue;
+        }
+        write!(report, "\n## {title}\n\n").ok();
+        for item in items {
+            writeln!(report, "- {item}").ok();
+        }
+    }
+    report
+}
+
+/// Adds the MCP servers configured for Claude Desktop, VS Code and Cursor to the
+/// `context_servers` settings, and opens a report of what was imported. Servers configured for a
+/// project are added to the project's settings.
+pub(crate) fn import_mcp_servers(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let fs = workspace.app_state().fs.clone();
+    let files = config_files(workspace, cx);
+    cx.spawn_in(window, async move |workspace, cx| {
+        let mut import = McpImport::default();
+        let mut found = Vec::new();
+        let mut files_read = Vec::new();
+        for file in &files {
+            let Ok(content) = fs.load(&file.path).await else {
+                continue;
+            };
+            match parse_config(file, &content, &mut import) {
+                Ok(servers) => {
+                    files_read.push(file.path.clone());
+                    found.extend(servers);
+                }
+                Err(error) => import.skipped.push(format!(
+                    "{} couldn't be parsed: {error}",
+                    file.path.display()
+                )),
+            }
+        }
+
+        let project_settings = workspace.update(cx, |workspace, cx| {
+            let project = workspace.project().read(cx);
+            let mut existing = HashMap::default();
+            existing.insert(
+                None,
+                ProjectSettings::get_global(cx).context_servers.clone(),
+            );
+            for worktree in project.visible_worktrees(cx) {
+                let worktree = worktree.read(cx);
+                let location = SettingsLocation {
+                    worktree_id: worktree.id(),
+                    path: Path::new(""),
+                };
+                existing.insert(
+                    Some(worktree.abs_path().to_path_buf()),
+                    ProjectSettings::get(Some(location), cx)
+                        .context_servers
+                        .clone(),
+                );
+            }
+            plan_import(found, &existing, &mut import);
+
+            let mut project_settings = HashMap::<PathBuf, Vec<_>>::default();
+            let mut user_settings = Vec::new();
+            for server in &import.added {
+                let entry = (server.name.clone(), server.command.clone());
+                match &server.worktree_root {
+                    Some(root) => project_settings
+                        .entry(root.clone())
+                        .or_default()
+                        .push(entry),
+                    None => user_settings.push(entry),
+                }
+            }
+            if !user_settings.is_empty() {
+                update_settings_file::<ProjectSettings>(fs.clone(), cx, move |settings, _| {
+                    add_servers(settings, user_settings)
+                });
+            }
+            project_settings
+        })?;
+
+        for (root, servers) in project_settings {
+            let settings_path = root.join(paths::local_settings_file_relative_path());
+            let old_text = fs.load(&settings_path).await.unwrap_or_default();
+            let new_text = cx.read_global(|store: &SettingsStore, _, _| {
+                store.new_text_for_update::<ProjectSettings>(old_text, |settings| {
+                    add_servers(settings, servers)
+                })
+            })?;
+            let result = async {
+                if let Some(settings_dir) = settings_path.parent() {
+                    fs.create_dir(settings_dir).await?;
+                }
+                fs.atomic_write(settings_path.clone(), new_text).await
+            };
+            if let Err(error) = result.await {
+                import.warnings.push(format!(
+                    "The servers for {} couldn't be saved to {}: {error}",
+                    root.display(),
+                    settings_path.display()
+                ));
+            }
+        }
+
+        workspace.update_in(cx, |workspace, window, cx| {
+            let report = report(&import, &files_read);
+            open_read_only_report(workspace, "MCP Server Import", &report, window, cx);
+        })?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+fn add_servers(settings: &mut ProjectSettings, servers: Vec<(String, ContextServerCommand)>) {
+    for (name, command) in servers {
+        settings.context_servers.insert(
+            name.into(),
+            ContextServerConfiguration {
+                command: Some(command),
+                settings: Some(json!({})),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(source: McpConfigSource, path: &str, worktree_root: Option<&str>) -> McpConfigFile {
+        McpConfigFile {
+            source,
+            path: PathBuf::from(path),
+            worktree_root: worktree_root.map(PathBuf::from),
+        }
+    }
+
+    fn command(path: &str, args: &[&str], env: &[(&str, &str)]) -> ContextServerCommand {
+        ContextServerCommand {
+            path: path.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: (!env.is_empty()).then(|| {
+                env.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect()
+            }),
+            cwd: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_claude_desktop_config() {
+        let mut import = McpImport::default();
+        let file = file(
+            McpConfigSource::ClaudeDesktop,
+            "/config/claude_desktop_config.json",
+            None,
+        );
+        let servers = parse_config(
+            &file,
+            r#"{
+                "mcpServers": {
+                    "github": {
+                        "command": "npx",
+                        "args": ["-y", "@modelcontextprotocol/server-github"],
+                        "env": { "GITHUB_PERSONAL_ACCESS_TOKEN": "token" }
+                    },
+                    "remote": { "url": "https://example.com/mcp" }
+                }
+            }"#,
+            &mut import,
+        )
+        .unwrap();
+        assert_eq!(
+            servers,
+            [FoundServer {
+                name: "github".into(),
+                path: file.path.clone(),
+                worktree_root: None,
+                command: command(
+                    "npx",
+                    &["-y", "@modelcontextprotocol/server-github"],
+                    &[("GITHUB_PERSONAL_ACCESS_TOKEN", "token")]
+                ),
+            }]
+        );
+        assert_eq!(
+            import.skipped,
+            [
+                "`remote` from Claude Desktop (/config/claude_desktop_config.json): it uses the `http` transport, and only servers that run as a local command can be imported"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_vscode_config() {
+        let mut import = McpImport::default();
+        let file = file(
+            McpConfigSource::VsCode,
+            "/project/.vscode/mcp.json",
+            Some("/project"),
+        );
+        let servers = parse_config(
+            &file,
+            r#"{
+                // Comments are allowed in VS Code's settings files.
+                "inputs": [{ "type": "promptString", "id": "api-key" }],
+                "servers": {
+                    "db": {
+                        "type": "stdio",
+                        "command": "${workspaceFolder}/bin/db-mcp",
+                        "args": ["--port", 5432],
+                        "cwd": "${workspaceFolder}/db",
+                        "env": { "API_KEY": "${input:api-key}" }
+                    },
+                    "docs": { "type": "sse", "url": "http://localhost:3000/sse" },
+                }
+            }"#,
+            &mut import,
+        )
+        .unwrap();
+        assert_eq!(
+            servers
+                .iter()
+                .map(|server| (&server.command, server.worktree_root.as_deref()))
+                .collect::<Vec<_>>(),
+            [(
+                &ContextServerCommand {
+                    cwd: Some("${worktree_root}/db".into()),
+                    ..command(
+                        "${worktree_root}/bin/db-mcp",
+                        &["--port", "5432"],
+                        &[("API_KEY", "${input:api-key}")]
+                    )
+                },
+                Some(Path::new("/project"))
+            )]
+        );
+        assert_eq!(import.skipped.len(), 1);
+        assert!(import.skipped[0].contains("`sse` transport"));
+        assert_eq!(import.warnings.len(), 1);
+        assert!(import.warnings[0].contains("VS Code inputs"));
+    }
+
+    #[test]
+    fn test_plan_import() {
+        let found = |name: &str, path: &str, program: &str| FoundServer {
+            name: name.into(),
+            path: PathBuf::from(path),
+            worktree_root: None,
+            command: command(program, &[], &[]),
+        };
+        let in_project = |name: &str, path: &str, program: &str| FoundServer {
+            worktree_root: Some(PathBuf::from("/project")),
+            ..found(name, path, program)
+        };
+        let user_settings = HashMap::from_iter([
+            (
+                Arc::from("same"),
+                ContextServerConfiguration {
+                    command: Some(command("same-mcp", &[], &[])),
+                    ..Default::default()
+                },
+            ),
+            (
+                Arc::from("mine"),
+                ContextServerConfiguration {
+                    command: Some(command("my-mcp", &[], &[])),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let existing = HashMap::from_iter([
+            (None, user_settings),
+            (Some(PathBuf::from("/project")), HashMap::default()),
+        ]);
+
+        let mut import = McpImport::default();
+        plan_import(
+            vec![
+                found("same", "/a.json", "same-mcp"),
+                found("mine", "/a.json", "their-mcp"),
+                found("new", "/a.json", "new-mcp"),
+                found("new", "/b.json", "new-mcp"),
+                found("new", "/c.json", "other-mcp"),
+                in_project("mine", "/project/.vscode/mcp.json", "their-mcp"),
+            ],
+            &existing,
+            &mut import,
+        );
+        assert_eq!(
+            import.added,
+            [
+                found("new", "/a.json", "new-mcp"),
+                in_project("mine", "/project/.vscode/mcp.json", "their-mcp"),
+            ]
+        );
+        assert_eq!(import.unchanged, ["same"]);
+        assert_eq!(
+            import.conflicts,
+            [
+                "`mine` in /a.json differs from the server of the same name in your settings, which was kept.",
+                "`new` in /c.json differs from the one in /a.json, which was imported.",
+            ]
+        );
+    }
+}
@@ -10,6 +10,7 @@ use crate::ui::{
 };
 use assistant_context_editor::language_model_selector::ToggleModelSelector;
 use assistant_settings::{AssistantSettings, CompletionMode};
+use assistant_tool::ToolSource;
 use buffer_diff::BufferDiff;
 use client::UserStore;
 use collections::{HashMap, HashSet};
@@ -33,6 +34,7 @@ use language_model::{
 };
 use multi_buffer;
 use project::Project;
+use project::context_server_store::ContextServerStatus;
 use prompt_store::PromptStore;
 use proto::Plan;
 use settings::Settings;
@@ -50,7 +52,8 @@ use crate::thread::{MessageCrease, Thread, TokenUsageRatio};
 use crate::thread_store::{TextThreadStore, ThreadStore};
 use crate::{
     ActiveThread, AgentDiffPane, Chat, ExpandMessageEditor, Follow, NewThread, OpenAgentDiff,
-    RemoveAllContext, ToggleContextPicker, ToggleProfileSelector, register_agent_preview,
+    RemoveAllContext, ToggleContextPicker, ToggleProfileSelector, ToggleThreadReadOnlyTools,
+    register_agent_preview,
 };
 
 #[derive(RegisterComponent)]
@@ -186,6 +189,11 @@ impl MessageEditor {
             cx.observe(&thread.read(cx).action_log().clone(), |_, _, cx| {
                 cx.notify()
             }),
+            cx.observe(&thread, |_, _, cx| cx.notify()),
+            cx.observe(
+                &thread.read(cx).project().read(cx).context_server_store(),
+                |_, _, cx| cx.notify(),
+            ),
         ];
 
         let model_selector = cx.new(|cx| {
@@ -489,6 +497,31 @@ impl MessageEditor {
         )
     }
 
+    fn render_forced_tool(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let forced_tool = self.thread.read(cx).forced_tool()?;
+        let tooltip = format!(
+            "The next response has to call {} of {}. Click to let the model choose.",
+            forced_tool.name, forced_tool.server_id
+        );
+
+        Some(
+            Button::new("forced-tool", forced_tool.name.clone())
+                .label_size(LabelSize::Small)
+                .color(Color::Muted)
+                .icon(IconName::Hammer)
+                .icon_size(IconSize::Small)
+                .icon_color(Color::Accent)
+                .icon_position(IconPosition::Start)
+                .toggle_state(true)
+                .tooltip(Tooltip::text(tooltip))
+                .on_click(cx.listener(|this, _event, _window, cx| {
+                    this.thread
+                        .update(cx, |thread, cx| thread.clear_forced_tool(cx));
+                }))
+                .into_any_element(),
+        )
+    }
+
     fn render_follow_toggle(&self, cx: &mut Context<Self>) -> impl IntoElement {
         let following = self
             .workspace
@@ -528,6 +561,53 @@ impl MessageEditor {
             }))
     }
 
+    fn render_read_only_tools_toggle(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let thread = self.thread.read(cx);
+        let read_only_everywhere = AssistantSettings::get_global(cx).read_only_tools;
+        let read_only_tools = thread.read_only_tools(cx);
+
+        IconButton::new("thread-read-only-tools", IconName::LockOutlined)
+            .icon_size(IconSize::Small)
+            .icon_color(Color::Muted)
+            .toggle_state(read_only_tools)
+            .selected_icon_color(Some(Color::Accent))
+            .disabled(read_only_everywhere)
+            .tooltip(move |window, cx| {
+                if read_only_everywhere {
+                    Tooltip::simple("Agent Tools Are Read-Only Everywhere", cx)
+                } else if read_only_tools {
+                    Tooltip::for_action(
+                        "Allow All Tools in This Thread",
+                        &ToggleThreadReadOnlyTools,
+                        window,
+                        cx,
+                    )
+                } else {
+                    Tooltip::with_meta(
+                        "Restrict Thread to Read-Only Tools",
+                        Some(&ToggleThreadReadOnlyTools),
+                        "Only offer the model tools that can't change the project.",
+                        window,
+                        cx,
+                    )
+                }
+            })
+            .on_click(cx.listener(|this, _, window, cx| {
+                this.toggle_thread_read_only_tools(&ToggleThreadReadOnlyTools, window, cx);
+            }))
+    }
+
+    fn toggle_thread_read_only_tools(
+        &mut self,
+        _: &ToggleThreadReadOnlyTools,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.thread.update(cx, |thread, cx| {
+            thread.set_read_only_tools(!thread.thread_read_only_tools(), cx);
+        });
+    }
+
     fn render_editor(&self, window: &mut Window, cx: &mut Context<Self>) -> Div {
         let thread = self.thread.read(cx);
         let model = thread.configured_model();
@@ -562,6 +642,7 @@ impl MessageEditor {
         v_flex()
             .key_context("MessageEditor")
             .on_action(cx.listener(Self::chat))
+            .on_action(cx.listener(Self::toggle_thread_read_only_tools))
             .on_action(cx.listener(|this, _: &ToggleProfileSelector, window, cx| {
                 this.profile_selector
                     .read(cx)
@@ -670,7 +751,9 @@ impl MessageEditor {
                                 h_flex()
                                     .gap_1()
                                     .child(self.render_follow_toggle(cx))
-                                    .children(self.render_max_mode_toggle(cx)),
+                                    .child(self.render_read_only_tools_toggle(cx))
+                                    .children(self.render_max_mode_toggle(cx))
+                                    .children(self.render_forced_tool(cx)),
                             )
                             .child(
                                 h_flex()
@@ -818,6 +901,389 @@ impl MessageEditor {
             )
     }
 
+    /// Renders the context server tool calls that failed in this thread, so that they can be
+    /// retried with the same arguments instead of asking the model to make them again.
+    fn render_failed_tool_calls(&self, window: &mut Window, cx: &mut Context<Self>) -> Option<Div> {
+        let failed_tool_calls = self.thread.read(cx).failed_tool_calls().to_vec();
+        if failed_tool_calls.is_empty() {
+            return None;
+        }
+        let window_handle = window.window_handle();
+        let title = if failed_tool_calls.len() == 1 {
+            "1 failed tool call".to_string()
+        } else {
+            format!("{} failed tool calls", failed_tool_calls.len())
+        };
+
+        Some(
+            v_flex()
+                .mt_1()
+                .mx_2()
+                .bg(cx.theme().colors().editor_background)
+                .border_1()
+                .border_b_0()
+                .border_color(cx.theme().colors().border)
+                .rounded_t_md()
+                .child(
+                    h_flex()
+                        .p_1p5()
+                        .justify_between()
+                        .border_b_1()
+                        .border_color(cx.theme().colors().border)
+                        .child(
+                            h_flex()
+                                .gap_1()
+                                .child(
+                                    Icon::new(IconName::XCircle)
+                                        .size(IconSize::Small)
+                                        .color(Color::Error),
+                                )
+                                .child(Label::new(title).size(LabelSize::Small)),
+                        )
+                        .child(
+                            h_flex()
+                                .gap_1()
+                                .child(
+                                    Button::new("dismiss-failed-tool-calls", "Dismiss")
+                                        .label_size(LabelSize::Small)
+                                        .on_click(cx.listener(|this, _, _, cx| {
+                                            this.thread.update(cx, |thread, cx| {
+                                                thread.dismiss_failed_tool_calls(cx)
+                                            });
+                                        })),
+                                )
+                                .child(
+                                    Button::new(
+                                        "retry-failed-tool-calls",
+                                        "Restart Servers and Retry All",
+                                    )
+                                    .label_size(LabelSize::Small)
+                                    .on_click(cx.listener(
+                                        move |this, _, _, cx| {
+                                            this.thread
+                                                .update(cx, |thread, cx| {
+                                                    thread.retry_failed_tool_calls_after_restart(
+                                                        Some(window_handle),
+                                                        cx,
+                                                    )
+                                                })
+                                                .detach_and_log_err(cx);
+                                        },
+                                    )),
+                                ),
+                        ),
+                )
+                .children(failed_tool_calls.iter().enumerate().map(|(ix, call)| {
+                    let tool_use_id = call.tool_use_id.clone();
+                    h_flex()
+                        .id(("failed-tool-call", ix))
+                        .px_1p5()
+                        .py_1()
+                        .gap_2()
+                        .justify_between()
+                        .child(
+                            h_flex()
+                                .gap_1()
+                                .min_w_0()
+                                .child(
+                                    Label::new(call.tool_name.to_string()).size(LabelSize::Small),
+                                )
+                                .child(
+                                    Label::new(call.error.clone())
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted)
+                                        .truncate(),
+                                ),
+                        )
+                        .child(
+                            Button::new(("retry-failed-tool-call", ix), "Retry")
+                                .label_size(LabelSize::Small)
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.thread
+                                        .update(cx, |thread, cx| {
+                                            thread.retry_failed_tool_call(
+                                                &tool_use_id,
+                                                Some(window_handle),
+                                                cx,
+                                            )
+                                        })
+                                        .log_err();
+                                })),
+                        )
+                })),
+        )
+    }
+
+    /// Renders the tool calls that were running when Zed quit, and lets the user run them again
+    /// or mark them as failed.
+    fn render_interrupted_tool_calls(
+        &self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<Div> {
+        let interrupted_tool_uses = self.thread.read(cx).interrupted_tool_uses(cx);
+        if interrupted_tool_uses.is_empty() {
+            return None;
+        }
+        let window_handle = window.window_handle();
+        let can_resume = interrupted_tool_uses
+            .iter()
+            .any(|tool_use| tool_use.can_resume);
+        let message = match interrupted_tool_uses.as_slice() {
+            [tool_use] => format!("{} was interrupted when Zed quit.", tool_use.ui_text),
+            tool_uses => format!(
+                "{} tool calls were interrupted when Zed quit.",
+                tool_uses.len()
+            ),
+        };
+
+        Some(
+            h_flex()
+                .mt_1()
+                .mx_2()
+                .p_1p5()
+                .gap_2()
+                .justify_between()
+                .bg(cx.theme().colors().editor_background)
+                .border_1()
+                .border_b_0()
+                .border_color(cx.theme().colors().border)
+                .rounded_t_md()
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .min_w_0()
+                        .child(
+                            Icon::new(IconName::Warning)
+                                .size(IconSize::Small)
+                                .color(Color::Warning),
+                        )
+                        .child(Label::new(message).size(LabelSize::Small).truncate()),
+                )
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .child(
+                            Button::new("fail-interrupted-tool-calls", "Mark as Failed")
+                                .label_size(LabelSize::Small)
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    this.thread.update(cx, |thread, cx| {
+                                        thread.fail_interrupted_tool_uses(cx)
+                                    });
+                                })),
+                        )
+                        .when(can_resume, |this| {
+                            this.child(
+                                Button::new("resume-interrupted-tool-calls", "Run Again")
+                                    .label_size(LabelSize::Small)
+                                    .tooltip(Tooltip::text(
+                                        "Runs the calls of trusted read-only tools again, \
+                                        and marks the others as failed",
+                                    ))
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        this.thread.update(cx, |thread, cx| {
+                                            thread.resume_interrupted_tool_uses(
+                                                Some(window_handle),
+                                                cx,
+                                            )
+                                        });
+                                    })),
+                            )
+                        }),
+                ),
+        )
+    }
+
+    /// Renders why the agent paused when its tool calls exceeded the `tool_call_budget` setting,
+    /// and lets the user decide whether it continues.
+    fn render_tool_call_budget_exceeded(
+        &self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<Div> {
+        let limit = self.thread.read(cx).tool_call_budget_exceeded()?;
+        let window_handle = window.window_handle();
+
+        Some(
+            h_flex()
+                .mt_1()
+                .mx_2()
+                .p_1p5()
+                .gap_2()
+                .justify_between()
+                .bg(cx.theme().colors().editor_background)
+                .border_1()
+                .border_b_0()
+                .border_color(cx.theme().colors().border)
+                .rounded_t_md()
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .min_w_0()
+                        .child(
+                            Icon::new(IconName::Warning)
+                                .size(IconSize::Small)
+                                .color(Color::Warning),
+                        )
+                        .child(
+                            Label::new(format!("{}. Continue?", limit.description()))
+                                .size(LabelSize::Small),
+                        ),
+                )
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .child(
+                            Button::new("stop-at-tool-call-budget", "Stop")
+                                .label_size(LabelSize::Small)
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.thread.update(cx, |thread, cx| {
+                                        thread.dismiss_tool_call_budget_exceeded(
+                                            Some(window_handle),
+                                            cx,
+                                        )
+                                    });
+                                })),
+                        )
+                        .child(
+                            Button::new("continue-past-tool-call-budget", "Continue")
+                                .label_size(LabelSize::Small)
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.thread.update(cx, |thread, cx| {
+                                        thread
+                                            .continue_past_tool_call_budget(Some(window_handle), cx)
+                                    });
+                                })),
+                        ),
+                ),
+        )
+    }
+
+    /// Renders which servers can't reach their APIs, and which of the thread's tools are
+    /// unavailable until they can again.
+    fn render_offline_context_servers(&self, cx: &mut Context<Self>) -> Option<Div> {
+        let context_server_store = self.project.read(cx).context_server_store();
+        let mut server_ids = context_server_store
+            .read(cx)
+            .offline_servers()
+            .cloned()
+            .collect::<Vec<_>>();
+        if server_ids.is_empty() {
+            return None;
+        }
+        server_ids.sort_by(|a, b| a.0.cmp(&b.0));
+        let enabled_tools = self.thread.read(cx).tools().read(cx).enabled_tools(cx);
+
+        Some(
+            v_flex()
+                .mt_1()
+                .mx_2()
+                .bg(cx.theme().colors().editor_background)
+                .border_1()
+                .border_b_0()
+                .border_color(cx.theme().colors().border)
+                .rounded_t_md()
+                .children(server_ids.into_iter().enumerate().map(|(ix, server_id)| {
+                    let tool_names = enabled_tools
+                        .iter()
+                        .filter(|tool| match tool.source() {
+                            ToolSource::ContextServer { id } => *id == *server_id.0,
+                            ToolSource::Native => false,
+                        })
+                        .map(|tool| tool.name())
+                        .collect::<Vec<_>>();
+                    let degraded = if tool_names.is_empty() {
+                        "No tools of this thread are affected".to_string()
+                    } else {
+                        format!("Unavailable: {}", tool_names.join(", "))
+                    };
+                    h_flex()
+                        .id(("offline-context-server", ix))
+                        .px_1p5()
+                        .py_1()
+                        .gap_1()
+                        .min_w_0()
+                        .child(
+                            Icon::new(IconName::Warning)
+                                .size(IconSize::Small)
+                                .color(Color::Warning),
+                        )
+                        .child(Label::new(format!("{server_id} is offline")).size(LabelSize::Small))
+                        .child(
+                            Label::new(degraded)
+                                .size(LabelSize::Small)
+                                .color(Color::Muted)
+                                .truncate(),
+                        )
+                        .tooltip(Tooltip::text(
+                            "Its API can't be reached, so calls to its tools fail right away. \
+                            They're tried again 30 seconds after the last failure.",
+                        ))
+                })),
+        )
+    }
+
+    fn render_temporary_context_servers(&self, cx: &mut Context<Self>) -> Option<Div> {
+        let server_ids = self.thread.read(cx).temporary_context_servers().to_vec();
+        if server_ids.is_empty() {
+            return None;
+        }
+        let context_server_store = self.project.read(cx).context_server_store();
+
+        Some(
+            v_flex()
+                .mt_1()
+                .mx_2()
+                .bg(cx.theme().colors().editor_background)
+                .border_1()
+                .border_b_0()
+                .border_color(cx.theme().colors().border)
+                .rounded_t_md()
+                .children(server_ids.into_iter().enumerate().map(|(ix, server_id)| {
+                    let status = match context_server_store.read(cx).status_for_server(&server_id) {
+                        Some(ContextServerStatus::Installing) => "Installing".into(),
+                        Some(ContextServerStatus::Starting) | None => "Starting".into(),
+                        Some(ContextServerStatus::Running) => "Running".into(),
+                        Some(ContextServerStatus::Stopped) => "Stopped".into(),
+                        Some(ContextServerStatus::Error(error)) => SharedString::from(error),
+                    };
+                    h_flex()
+                        .id(("temporary-context-server", ix))
+                        .px_1p5()
+                        .py_1()
+                        .gap_2()
+                        .justify_between()
+                        .child(
+                            h_flex()
+                                .gap_1()
+                                .min_w_0()
+                                .child(
+                                    Icon::new(IconName::Server)
+                                        .size(IconSize::Small)
+                                        .color(Color::Muted),
+                                )
+                                .child(Label::new(server_id.to_string()).size(LabelSize::Small))
+                                .child(
+                                    Label::new(status)
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted)
+                                        .truncate(),
+                                ),
+                        )
+                        .child(
+                            Button::new(("detach-context-server", ix), "Detach")
+                                .label_size(LabelSize::Small)
+                                .tooltip(Tooltip::text("Stop the server and remove its tools"))
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.thread.update(cx, |thread, cx| {
+                                        thread.detach_context_server(&server_id, cx)
+                                    });
+                                })),
+                        )
+                })),
+        )
+    }
+
     fn render_changed_buffers(
         &self,
         changed_buffers: &BTreeMap<Entity<Buffer>, Entity<BufferDiff>>,
@@ -1354,6 +1820,11 @@ impl Render for MessageEditor {
 
         v_flex()
             .size_full()
+            .children(self.render_temporary_context_servers(cx))
+            .children(self.render_offline_context_servers(cx))
+            .children(self.render_failed_tool_calls(window, cx))
+            .children(self.render_interrupted_tool_calls(window, cx))
+            .children(self.render_tool_call_budget_exceeded(window, cx))
             .when(changed_buffers.len() > 0, |parent| {
                 parent.child(self.render_changed_buffers(&changed_buffers, window, cx))
             })
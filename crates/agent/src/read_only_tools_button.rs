@@ -0,0 +1,59 @@
+use assistant_settings::AssistantSettings;
+use settings::Settings as _;
+use ui::{Tooltip, prelude::*};
+use workspace::{ItemHandle, StatusItemView};
+
+use crate::ToggleReadOnlyTools;
+
+/// A status bar button that restricts the agent to tools that can't modify the project.
+pub struct ReadOnlyToolsButton;
+
+impl ReadOnlyToolsButton {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        cx.observe_global::<settings::SettingsStore>(|_, cx| cx.notify())
+            .detach();
+        Self
+    }
+}
+
+impl Render for ReadOnlyToolsButton {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let settings = AssistantSettings::get_global(cx);
+        if !settings.enabled || !settings.button {
+            return div().into_any_element();
+        }
+
+        let read_only_tools = settings.read_only_tools;
+        let tooltip = if read_only_tools {
+            "Agent Tools Are Read-Only"
+        } else {
+            "Restrict Agent to Read-Only Tools"
+        };
+
+        IconButton::new("agent-read-only-tools", IconName::LockOutlined)
+            .icon_size(IconSize::Small)
+            .icon_color(if read_only_tools {
+                Color::Accent
+            } else {
+                Color::Muted
+            })
+            .toggle_state(read_only_tools)
+            .tooltip(move |window, cx| {
+                Tooltip::for_action(tooltip, &ToggleReadOnlyTools, window, cx)
+            })
+            .on_click(|_, window, cx| {
+                window.dispatch_action(Box::new(ToggleReadOnlyTools), cx);
+            })
+            .into_any_element()
+    }
+}
+
+impl StatusItemView for ReadOnlyToolsButton {
+    fn set_active_pane_item(
+        &mut self,
+        _active_pane_item: Option<&dyn ItemHandle>,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) {
+    }
+}
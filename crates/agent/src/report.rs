@@ -0,0 +1,26 @@
+use editor::Editor;
+use gpui::{AppContext as _, Context, Window};
+use multi_buffer::MultiBuffer;
+use workspace::Workspace;
+
+/// Opens `text` in a read-only editor in the active pane, titled `title`.
+pub(crate) fn open_read_only_report(
+    workspace: &mut Workspace,
+    title: &str,
+    text: &str,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let project = workspace.project().clone();
+    let buffer = project.update(cx, |project, cx| {
+        project.create_local_buffer(text, None, cx)
+    });
+    let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title.to_string()));
+    let editor = cx.new(|cx| {
+        let mut editor = Editor::for_multibuffer(buffer, Some(project), window, cx);
+        editor.set_read_only(true);
+        editor.set_breadcrumb_header(title.to_string());
+        editor
+    });
+    workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx);
+}
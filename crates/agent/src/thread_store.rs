@@ -1,15 +1,18 @@
 use std::borrow::Cow;
 use std::cell::{Ref, RefCell};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use anyhow::{Context as _, Result, anyhow};
 use assistant_settings::{AgentProfile, AgentProfileId, AssistantSettings, CompletionMode};
-use assistant_tool::{ToolId, ToolSource, ToolWorkingSet};
+use assistant_tool::{ToolId, ToolSource, ToolWorkingSet, tool_output_overflow_path};
 use chrono::{DateTime, Utc};
-use collections::HashMap;
-use context_server::ContextServerId;
+use collections::{HashMap, HashSet};
+use context_server::{ContextServerId, types};
+use fs::{Fs, RemoveOptions};
 use futures::channel::{mpsc, oneshot};
 use futures::future::{self, BoxFuture, Shared};
 use futures::{FutureExt as _, StreamExt as _};
@@ -19,7 +22,9 @@ use gpui::{
 };
 use heed::Database;
 use heed::types::SerdeBincode;
-use language_model::{LanguageModelToolResultContent, LanguageModelToolUseId, Role, TokenUsage};
+use language_model::{
+    LanguageModelToolResultContent, LanguageModelToolUseId, Role, TokenUsage, WrappedTextContent,
+};
 use project::context_server_store::{ContextServerStatus, ContextServerStore};
 use project::{Project, ProjectItem, ProjectPath, Worktree};
 use prompt_store::{
@@ -28,13 +33,25 @@ use prompt_store::{
 };
 use serde::{Deserialize, Serialize};
 use settings::{Settings as _, SettingsStore};
+use sha2::{Digest as _, Sha256};
 use ui::Window;
 use util::ResultExt as _;
 
-use crate::context_server_tool::ContextServerTool;
+use crate::context_server_tool::{ContextServerTool, context_server_badge, offered_tool_name};
+use crate::context_server_tool_changes::{
+    ContextServerToolsChanged, cached_tools, update_cached_tools,
+};
 use crate::thread::{
     DetailedSummaryState, ExceededWindowError, MessageId, ProjectSnapshot, Thread, ThreadId,
 };
+use crate::tool_call_budget::ToolCallBudgetUsage;
+use crate::tool_tracing;
+
+/// Strings in tool results at least this long, such as images, are stored apart from their
+/// thread, so that listing threads doesn't have to read them.
+const MIN_BLOB_LEN: usize = 16 * 1024;
+/// What a string stored apart from its thread is replaced with, followed by its hash.
+const BLOB_REFERENCE_PREFIX: &str = "zed-blob:sha256:";
 
 const RULES_FILE_NAMES: [&'static str; 6] = [
     ".rules",
@@ -80,6 +97,8 @@ pub struct RulesLoadingError {
 
 impl EventEmitter<RulesLoadingError> for ThreadStore {}
 
+impl EventEmitter<ContextServerToolsChanged> for ThreadStore {}
+
 impl ThreadStore {
     pub fn load(
         project: Entity<Project>,
@@ -457,9 +476,14 @@ impl ThreadStore {
     pub fn delete_thread(&mut self, id: &ThreadId, cx: &mut Context<Self>) -> Task<Result<()>> {
         let id = id.clone();
         let database_future = ThreadsDatabase::global_future(cx);
+        let fs = self.project.read(cx).fs().clone();
         cx.spawn(async move |this, cx| {
             let database = database_future.await.map_err(|err| anyhow!(err))?;
+            let thread = database.try_find_thread(id.clone()).await?;
             database.delete_thread(id.clone()).await?;
+            if let Some(thread) = thread {
+                remove_overflowed_tool_outputs(&thread, fs.as_ref()).await;
+            }
 
             this.update(cx, |this, cx| {
                 this.threads.retain(|thread| thread.id != id);
@@ -499,6 +523,15 @@ impl ThreadStore {
     }
 
     pub fn load_profile(&self, profile: AgentProfile, cx: &mut Context<Self>) {
+        let active_servers = profile
+            .active_context_servers
+            .clone()
+            .map(|ids| ids.into_iter().collect::<HashSet<_>>());
+        self.project
+            .read(cx)
+            .context_server_store()
+            .update(cx, |store, cx| store.set_active_servers(active_servers, cx));
+
         self.tools.update(cx, |tools, cx| {
             tools.disable_all_tools(cx);
             tools.enable(
@@ -562,6 +595,27 @@ impl ThreadStore {
                 })
             }
         }
+
+        // Temporary servers were attached to a thread by hand, so their tools are offered
+        // whichever profile is selected.
+        let temporary_server_ids = self
+            .project
+            .read(cx)
+            .context_server_store()
+            .read(cx)
+            .temporary_server_ids()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.tools.update(cx, |tools, cx| {
+            for server_id in temporary_server_ids {
+                tools.enable_source(
+                    ToolSource::ContextServer {
+                        id: server_id.0.into(),
+                    },
+                    cx,
+                );
+            }
+        });
     }
 
     fn register_context_server_handlers(&self, cx: &mut Context<Self>) {
@@ -572,61 +626,170 @@ impl ThreadStore {
         .detach();
     }
 
+    /// Offers `tools` from a context server, replacing the ones it offered before, so that
+    /// there's no moment in which the server has no tools.
+    fn register_context_server_tools(
+        &mut self,
+        context_server_store: &Entity<ContextServerStore>,
+        server_id: &ContextServerId,
+        tools: Vec<types::Tool>,
+        cx: &mut Context<Self>,
+    ) {
+        let store = context_server_store.read(cx);
+        let Some(server) = store.get_server(server_id) else {
+            return;
+        };
+        let configuration = store.configuration_for_server(server_id);
+        let source_badge = context_server_badge(&server, configuration.as_deref());
+        let name_prefix = store.tool_name_prefix(server_id);
+        let previous_tool_ids = self
+            .context_server_tool_ids
+            .remove(server_id)
+            .unwrap_or_default();
+        let tool_ids = self.tools.update(cx, |tool_working_set, cx| {
+            tool_working_set.remove(&previous_tool_ids);
+            tools
+                .into_iter()
+                .map(|tool| {
+                    log::info!("registering context server tool: {:?}", tool.name);
+                    let ui_text_template = configuration.as_ref().and_then(|configuration| {
+                        configuration.tool_ui_text.get(&tool.name).cloned()
+                    });
+                    let examples = configuration
+                        .as_ref()
+                        .and_then(|configuration| {
+                            configuration.tool_examples.get(&tool.name).cloned()
+                        })
+                        .unwrap_or_default();
+                    let name = offered_tool_name(name_prefix.as_deref(), &tool.name, |name| {
+                        tool_working_set.tool(name, cx).is_some()
+                    });
+                    tool_working_set.insert(Arc::new(ContextServerTool::new(
+                        context_server_store.clone(),
+                        server.id(),
+                        tool,
+                        name,
+                        ui_text_template,
+                        examples,
+                        source_badge.clone(),
+                    )))
+                })
+                .collect::<Vec<_>>()
+        });
+        self.context_server_tool_ids
+            .insert(server_id.clone(), tool_ids);
+        self.load_default_profile(cx);
+    }
+
+    fn unregister_context_server_tools(
+        &mut self,
+        server_id: &ContextServerId,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(tool_ids) = self.context_server_tool_ids.remove(server_id) {
+            self.tools.update(cx, |tool_working_set, _| {
+                tool_working_set.remove(&tool_ids);
+            });
+            self.load_default_profile(cx);
+        }
+    }
+
     fn handle_context_server_event(
         &mut self,
         context_server_store: Entity<ContextServerStore>,
         event: &project::context_server_store::Event,
         cx: &mut Context<Self>,
     ) {
-        let tool_working_set = self.tools.clone();
         match event {
             project::context_server_store::Event::ServerStatusChanged { server_id, status } => {
                 match status {
+                    // Until the server lists its tools, the ones it had when it last ran are
+                    // offered, so that a turn starting meanwhile doesn't go without them. Calls
+                    // to them wait for the server to start.
+                    ContextServerStatus::Starting => {
+                        if self.context_server_tool_ids.contains_key(server_id) {
+                            return;
+                        }
+                        let server_id = server_id.clone();
+                        cx.spawn(async move |this, cx| {
+                            let tools = cx
+                                .background_spawn({
+                                    let server_id = server_id.clone();
+                                    async move { cached_tools(&server_id) }
+                                })
+                                .await
+                                .log_err()
+                                .flatten();
+                            let Some(tools) = tools else {
+                                return;
+                            };
+                            this.update(cx, |this, cx| {
+                                if !this.context_server_tool_ids.contains_key(&server_id) {
+                                    this.register_context_server_tools(
+                                        &context_server_store,
+                                        &server_id,
+                                        tools,
+                                        cx,
+                                    );
+                                }
+                            })
+                            .log_err();
+                        })
+                        .detach();
+                    }
                     ContextServerStatus::Running => {
                         if let Some(server) =
                             context_server_store.read(cx).get_running_server(server_id)
                         {
-                            let context_server_manager = context_server_store.clone();
+                            let identity = context_server_store.read(cx).server_identity(server_id);
+                            let has_tools = context_server_store
+                                .read(cx)
+                                .capabilities(server_id)
+                                .is_some_and(|capabilities| capabilities.tools);
                             cx.spawn({
-                                let server = server.clone();
                                 let server_id = server_id.clone();
                                 async move |this, cx| {
                                     let Some(protocol) = server.client() else {
                                         return;
                                     };
 
-                                    if protocol.capable(context_server::protocol::ServerCapability::Tools) {
-                                        if let Some(tools) = protocol.list_tools().await.log_err() {
-                                            let tool_ids = tool_working_set
-                                                .update(cx, |tool_working_set, _| {
-                                                    tools
-                                                        .tools
-                                                        .into_iter()
-                                                        .map(|tool| {
-                                                            log::info!(
-                                                                "registering context server tool: {:?}",
-                                                                tool.name
-                                                            );
-                                                            tool_working_set.insert(Arc::new(
-                                                                ContextServerTool::new(
-                                                                    context_server_manager.clone(),
-                                                                    server.id(),
-                                                                    tool,
-                                                                ),
-                                                            ))
-                                                        })
-                                                        .collect::<Vec<_>>()
-                                                })
-                                                .log_err();
-
-                                            if let Some(tool_ids) = tool_ids {
-                                                this.update(cx, |this, cx| {
-                                                    this.context_server_tool_ids
-                                                        .insert(server_id, tool_ids);
-                                                    this.load_default_profile(cx);
-                                                })
-                                                .log_err();
-                                            }
+                                    if has_tools {
+                                        let discovery_start = SystemTime::now();
+                                        let tools = protocol.list_tools().await;
+                                        cx.update(|cx| {
+                                            tool_tracing::record_discovery(
+                                                &server_id,
+                                                identity.as_deref(),
+                                                discovery_start,
+                                                tools
+                                                    .as_ref()
+                                                    .map(|tools| tools.tools.len())
+                                                    .map_err(|error| error.to_string()),
+                                                cx,
+                                            )
+                                        })
+                                        .ok();
+                                        if let Some(tools) = tools.log_err() {
+                                            let tools_changed = cx
+                                                .background_spawn(update_cached_tools(
+                                                    server_id.clone(),
+                                                    tools.tools.clone(),
+                                                ))
+                                                .await
+                                                .log_err()
+                                                .flatten();
+                                            this.update(cx, |this, cx| {
+                                                this.register_context_server_tools(
+                                                    &context_server_store,
+                                                    &server_id,
+                                                    tools.tools,
+                                                    cx,
+                                                );
+                                                if let Some(tools_changed) = tools_changed {
+                                                    cx.emit(tools_changed);
+                                                }
+                                            })
+                                            .log_err();
                                         }
                                     }
                                 }
@@ -635,16 +798,36 @@ impl ThreadStore {
                         }
                     }
                     ContextServerStatus::Stopped | ContextServerStatus::Error(_) => {
-                        if let Some(tool_ids) = self.context_server_tool_ids.remove(server_id) {
-                            tool_working_set.update(cx, |tool_working_set, _| {
-                                tool_working_set.remove(&tool_ids);
-                            });
-                            self.load_default_profile(cx);
-                        }
+                        // A server that's restarting is stopped and started in the same update,
+                        // so its tools are only removed if it's still stopped after it.
+                        let server_id = server_id.clone();
+                        cx.spawn(async move |this, cx| {
+                            this.update(cx, |this, cx| {
+                                let status =
+                                    context_server_store.read(cx).status_for_server(&server_id);
+                                if !matches!(
+                                    status,
+                                    Some(
+                                        ContextServerStatus::Starting
+                                            | ContextServerStatus::Running
+                                    )
+                                ) {
+                                    this.unregister_context_server_tools(&server_id, cx);
+                                }
+                            })
+                            .log_err();
+                        })
+                        .detach();
                     }
-                    _ => {}
+                    ContextServerStatus::Installing => {}
                 }
             }
+            project::context_server_store::Event::UntrustedProjectServers { .. }
+            | project::context_server_store::Event::UntrustedServers
+            | project::context_server_store::Event::PackageUpdateAvailable { .. }
+            | project::context_server_store::Event::ServerOfflineChanged { .. }
+            | project::context_server_store::Event::ServerRenamed { .. }
+            | project::context_server_store::Event::ToolCall { .. } => {}
         }
     }
 }
@@ -676,6 +859,10 @@ pub struct SerializedThread {
     pub model: Option<SerializedLanguageModel>,
     #[serde(default)]
     pub completion_mode: Option<CompletionMode>,
+    #[serde(default)]
+    pub read_only_tools: bool,
+    #[serde(default)]
+    pub tool_call_budget_usage: ToolCallBudgetUsage,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -709,6 +896,95 @@ impl SerializedThread {
             version => anyhow::bail!("unrecognized serialized thread version: {version:?}"),
         }
     }
+
+    /// Moves the long strings of tool results to the returned map, keyed by their hash, and
+    /// leaves references to them in their place.
+    fn extract_blobs(&mut self) -> BTreeMap<String, String> {
+        let mut blobs = BTreeMap::new();
+        self.replace_tool_result_strings(&mut |string| {
+            if string.len() < MIN_BLOB_LEN {
+                return None;
+            }
+            let hash = format!("{:x}", Sha256::digest(string.as_bytes()));
+            let reference = format!("{BLOB_REFERENCE_PREFIX}{hash}");
+            blobs.insert(hash, string.to_string());
+            Some(reference)
+        });
+        blobs
+    }
+
+    /// Puts the strings moved out by [`Self::extract_blobs`] back in place of their references.
+    fn restore_blobs(&mut self, blobs: &BTreeMap<String, String>) {
+        self.replace_tool_result_strings(&mut |string| {
+            let hash = string.strip_prefix(BLOB_REFERENCE_PREFIX)?;
+            let blob = blobs.get(hash);
+            if blob.is_none() {
+                log::error!("missing tool result blob {hash}");
+            }
+            blob.cloned()
+        });
+    }
+
+    fn replace_tool_result_strings(&mut self, replace: &mut impl FnMut(&str) -> Option<String>) {
+        fn replace_in_content(
+            content: &mut LanguageModelToolResultContent,
+            replace: &mut impl FnMut(&str) -> Option<String>,
+        ) {
+            match content {
+                LanguageModelToolResultContent::Text(text)
+                | LanguageModelToolResultContent::WrappedText(WrappedTextContent {
+                    text, ..
+                }) => {
+                    if let Some(replacement) = replace(text) {
+                        *text = replacement.into();
+                    }
+                }
+                LanguageModelToolResultContent::Image(image) => {
+                    if let Some(replacement) = replace(&image.source) {
+                        image.source = replacement.into();
+                    }
+                }
+            }
+        }
+
+        fn replace_in_json(
+            value: &mut serde_json::Value,
+            replace: &mut impl FnMut(&str) -> Option<String>,
+        ) {
+            match value {
+                serde_json::Value::String(string) => {
+                    if let Some(replacement) = replace(string) {
+                        *string = replacement;
+                    }
+                }
+                serde_json::Value::Array(items) => {
+                    for item in items {
+                        replace_in_json(item, replace);
+                    }
+                }
+                serde_json::Value::Object(fields) => {
+                    for field in fields.values_mut() {
+                        replace_in_json(field, replace);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for tool_result in self
+            .messages
+            .iter_mut()
+            .flat_map(|message| &mut message.tool_results)
+        {
+            replace_in_content(&mut tool_result.content, replace);
+            if let Some(summary) = tool_result.summary.as_mut() {
+                replace_in_content(summary, replace);
+            }
+            if let Some(output) = tool_result.output.as_mut() {
+                replace_in_json(output, replace);
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -782,6 +1058,12 @@ pub struct SerializedToolUse {
     pub id: LanguageModelToolUseId,
     pub name: SharedString,
     pub input: serde_json::Value,
+    /// The input as the model wrote it, when it isn't what serializing `input` gives.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_input: Option<String>,
+    /// The MCP server that provided the tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_id: Option<SharedString>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -790,6 +1072,9 @@ pub struct SerializedToolResult {
     pub is_error: bool,
     pub content: LanguageModelToolResultContent,
     pub output: Option<serde_json::Value>,
+    /// What was sent to the model in place of `content`, when it was summarized.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<LanguageModelToolResultContent>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -815,6 +1100,8 @@ impl LegacySerializedThread {
             exceeded_window_error: None,
             model: None,
             completion_mode: None,
+            read_only_tools: false,
+            tool_call_budget_usage: ToolCallBudgetUsage::default(),
         }
     }
 }
@@ -862,6 +1149,8 @@ pub(crate) struct ThreadsDatabase {
     executor: BackgroundExecutor,
     env: heed::Env,
     threads: Database<SerdeBincode<ThreadId>, SerializedThread>,
+    /// The long strings of each thread's tool results, keyed by their hash.
+    blobs: Database<SerdeBincode<ThreadId>, SerdeBincode<BTreeMap<String, String>>>,
 }
 
 impl heed::BytesEncode<'_> for SerializedThread {
@@ -911,18 +1200,20 @@ impl ThreadsDatabase {
         let env = unsafe {
             heed::EnvOpenOptions::new()
                 .map_size(ONE_GB_IN_BYTES)
-                .max_dbs(1)
+                .max_dbs(2)
                 .open(path)?
         };
 
         let mut txn = env.write_txn()?;
         let threads = env.create_database(&mut txn, Some("threads"))?;
+        let blobs = env.create_database(&mut txn, Some("blobs"))?;
         txn.commit()?;
 
         Ok(Self {
             executor,
             env,
             threads,
+            blobs,
         })
     }
 
@@ -949,21 +1240,34 @@ impl ThreadsDatabase {
     pub fn try_find_thread(&self, id: ThreadId) -> Task<Result<Option<SerializedThread>>> {
         let env = self.env.clone();
         let threads = self.threads;
+        let blobs = self.blobs;
 
         self.executor.spawn(async move {
             let txn = env.read_txn()?;
-            let thread = threads.get(&txn, &id)?;
-            Ok(thread)
+            let Some(mut thread) = threads.get(&txn, &id)? else {
+                return Ok(None);
+            };
+            if let Some(blobs) = blobs.get(&txn, &id)? {
+                thread.restore_blobs(&blobs);
+            }
+            Ok(Some(thread))
         })
     }
 
-    pub fn save_thread(&self, id: ThreadId, thread: SerializedThread) -> Task<Result<()>> {
+    pub fn save_thread(&self, id: ThreadId, mut thread: SerializedThread) -> Task<Result<()>> {
         let env = self.env.clone();
         let threads = self.threads;
+        let blobs = self.blobs;
 
         self.executor.spawn(async move {
+            let thread_blobs = thread.extract_blobs();
             let mut txn = env.write_txn()?;
             threads.put(&mut txn, &id, &thread)?;
+            if thread_blobs.is_empty() {
+                blobs.delete(&mut txn, &id)?;
+            } else {
+                blobs.put(&mut txn, &id, &thread_blobs)?;
+            }
             txn.commit()?;
             Ok(())
         })
@@ -972,12 +1276,117 @@ impl ThreadsDatabase {
     pub fn delete_thread(&self, id: ThreadId) -> Task<Result<()>> {
         let env = self.env.clone();
         let threads = self.threads;
+        let blobs = self.blobs;
 
         self.executor.spawn(async move {
             let mut txn = env.write_txn()?;
             threads.delete(&mut txn, &id)?;
+            blobs.delete(&mut txn, &id)?;
             txn.commit()?;
             Ok(())
         })
     }
 }
+
+/// Removes the files holding the full outputs of the thread's tool calls that were too large to
+/// send to the model, which nothing reads once the thread is gone.
+async fn remove_overflowed_tool_outputs(thread: &SerializedThread, fs: &dyn Fs) {
+    for tool_use in thread
+        .messages
+        .iter()
+        .flat_map(|message| &message.tool_uses)
+    {
+        fs.remove_file(
+            &tool_output_overflow_path(&tool_use.id),
+            RemoveOptions {
+                ignore_if_not_exists: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .log_err();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_use::overflow_large_tool_output;
+    use gpui::TestAppContext;
+    use project::FakeFs;
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_removing_overflowed_tool_outputs(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        let output = "line\n".repeat(100);
+        overflow_large_tool_output(output.into(), &"tool_1".into(), 64, None, fs.clone()).await;
+        let path = tool_output_overflow_path(&"tool_1".into());
+        assert!(fs.is_file(&path).await);
+
+        let json = json!({
+            "version": SerializedThread::VERSION,
+            "summary": "Logs",
+            "updated_at": "2025-05-01T12:00:00Z",
+            "messages": [{
+                "id": 0,
+                "role": "assistant",
+                "segments": [],
+                "tool_uses": [
+                    { "id": "tool_1", "name": "logs", "input": {} },
+                    { "id": "tool_2", "name": "logs", "input": {} },
+                ],
+            }],
+        });
+        let thread = SerializedThread::from_json(json.to_string().as_bytes()).unwrap();
+        remove_overflowed_tool_outputs(&thread, fs.as_ref()).await;
+        assert!(!fs.is_file(&path).await);
+    }
+
+    #[test]
+    fn test_tool_result_blobs() {
+        let image = "A".repeat(MIN_BLOB_LEN);
+        let json = json!({
+            "version": SerializedThread::VERSION,
+            "summary": "Screenshots",
+            "updated_at": "2025-05-01T12:00:00Z",
+            "messages": [{
+                "id": 0,
+                "role": "assistant",
+                "segments": [],
+                "tool_uses": [{
+                    "id": "tool_1",
+                    "name": "screenshot",
+                    "input": { "url": "https://zed.dev" },
+                    "raw_input": "{\"url\": \"https://zed.dev\"}",
+                    "server_id": "browser",
+                }],
+                "tool_results": [{
+                    "tool_use_id": "tool_1",
+                    "is_error": false,
+                    "content": "Took a screenshot",
+                    "output": {
+                        "mime_type": "image/png",
+                        "data": image,
+                        "structured_content": { "title": "Zed" },
+                    },
+                }],
+            }],
+        });
+        let mut thread = SerializedThread::from_json(json.to_string().as_bytes()).unwrap();
+        let saved = serde_json::to_value(&thread).unwrap();
+        for key in ["tool_uses", "tool_results"] {
+            assert_eq!(saved["messages"][0][key], json["messages"][0][key]);
+        }
+
+        let blobs = thread.extract_blobs();
+        assert_eq!(blobs.len(), 1);
+        let output = thread.messages[0].tool_results[0].output.clone().unwrap();
+        let reference = output["data"].as_str().unwrap();
+        assert!(reference.starts_with(BLOB_REFERENCE_PREFIX));
+        assert_eq!(output["structured_content"], json!({ "title": "Zed" }));
+
+        thread.restore_blobs(&blobs);
+        assert_eq!(serde_json::to_value(&thread).unwrap(), saved);
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use assistant_settings::ToolCallBudget;
+use serde::{Deserialize, Serialize};
+
+/// What the context server tool calls of a thread have used of its [`ToolCallBudget`] since
+/// the user last decided whether it continues.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallBudgetUsage {
+    pub calls: u32,
+    pub duration: Duration,
+    pub result_tokens: u64,
+}
+
+/// The limit of a [`ToolCallBudget`] that a thread reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolCallBudgetLimit {
+    Calls(u32),
+    Duration(Duration),
+    ResultTokens(u64),
+}
+
+impl ToolCallBudgetUsage {
+    /// Counts a call when it starts, so that the calls the model requests at once can't
+    /// overshoot the budget together.
+    pub fn start_call(&mut self) {
+        self.calls += 1;
+    }
+
+    pub fn record(&mut self, duration: Duration, result_tokens: u64) {
+        self.duration += duration;
+        self.result_tokens += result_tokens;
+    }
+
+    /// Returns the first limit of `budget` this usage has reached, if any, in which case no
+    /// more calls may start.
+    pub fn reached_limit(&self, budget: &ToolCallBudget) -> Option<ToolCallBudgetLimit> {
+        if let Some(max_calls) = budget.max_calls {
+            if self.calls >= max_calls {
+                return Some(ToolCallBudgetLimit::Calls(max_calls));
+            }
+        }
+        if let Some(max_seconds) = budget.max_seconds {
+            let max_duration = Duration::from_secs(max_seconds);
+            if self.duration >= max_duration {
+                return Some(ToolCallBudgetLimit::Duration(max_duration));
+            }
+        }
+        if let Some(max_result_tokens) = budget.max_result_tokens {
+            if self.result_tokens >= max_result_tokens {
+                return Some(ToolCallBudgetLimit::ResultTokens(max_result_tokens));
+            }
+        }
+        None
+    }
+}
+
+impl ToolCallBudgetLimit {
+    pub fn description(&self) -> String {
+        match self {
+            Self::Calls(max_calls) => format!("The agent made {max_calls} tool calls"),
+            Self::Duration(max_duration) => format!(
+                "The agent's tool calls took {} seconds",
+                max_duration.as_secs()
+            ),
+            Self::ResultTokens(max_result_tokens) => {
+                format!("The outputs of the agent's tool calls took up {max_result_tokens} tokens")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reached_limit() {
+        let budget = ToolCallBudget {
+            max_calls: Some(2),
+            max_seconds: Some(10),
+            max_result_tokens: None,
+        };
+        let mut usage = ToolCallBudgetUsage::default();
+        usage.start_call();
+        assert_eq!(usage.reached_limit(&budget), None);
+
+        usage.start_call();
+        assert_eq!(
+            usage.reached_limit(&budget),
+            Some(ToolCallBudgetLimit::Calls(2))
+        );
+
+        let mut usage = ToolCallBudgetUsage::default();
+        usage.start_call();
+        usage.record(Duration::from_secs(4), 1000);
+        assert_eq!(usage.reached_limit(&budget), None);
+
+        usage.record(Duration::from_secs(6), 1000);
+        assert_eq!(
+            usage.reached_limit(&budget),
+            Some(ToolCallBudgetLimit::Duration(Duration::from_secs(10)))
+        );
+        assert_eq!(usage.reached_limit(&ToolCallBudget::default()), None);
+    }
+}
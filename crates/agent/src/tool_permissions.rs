@@ -0,0 +1,346 @@
+use std::fmt::Write as _;
+
+use anyhow::Context as _;
+use assistant_settings::{AgentProfileContent, AgentProfileId, AssistantSettings, ToolPermissions};
+use assistant_tool::{ToolSource, ToolWorkingSet};
+use context_server::ContextServerId;
+use gpui::{Context, Entity, PathPromptOptions, PromptLevel, Window};
+use project::DirectoryLister;
+use project::context_server_store::{ConfirmationSource, ToolConfirmation};
+use settings::{Settings as _, update_settings_file};
+use util::ResultExt as _;
+use workspace::{DetachAndPromptErr as _, Workspace};
+
+use crate::report::open_read_only_report;
+
+/// Describes what importing the permissions would change, for the user to confirm.
+fn import_summary(permissions: &ToolPermissions, current: &ToolPermissions) -> String {
+    let mut summary = Vec::new();
+    summary.push(if permissions.always_allow_tool_actions {
+        "Tool actions will run without asking for confirmation.".to_string()
+    } else {
+        "Tool actions will ask for confirmation.".to_string()
+    });
+    if permissions.read_only_tools {
+        summary.push("Only tools that don't make changes will be available.".to_string());
+    }
+    let (replaced, added): (Vec<_>, Vec<_>) = permissions
+        .profiles
+        .iter()
+        .partition(|(id, _)| current.profiles.contains_key(*id));
+    let names = |profiles: Vec<(&AgentProfileId, &AgentProfileContent)>| {
+        profiles
+            .into_iter()
+            .map(|(_, profile)| profile.name.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if !replaced.is_empty() {
+        summary.push(format!("Replaces the profiles: {}", names(replaced)));
+    }
+    if !added.is_empty() {
+        summary.push(format!("Adds the profiles: {}", names(added)));
+    }
+    summary.join("\n")
+}
+
+/// Saves the tool permissions in effect to a file that can be shared with other users.
+pub(crate) fn export_tool_permissions(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let permissions = AssistantSettings::get_global(cx).tool_permissions();
+    let fs = workspace.app_state().fs.clone();
+    let directory = workspace
+        .visible_worktrees(cx)
+        .next()
+        .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+        .unwrap_or_else(|| paths::home_dir().clone());
+    let path = cx.prompt_for_new_path(&directory);
+    cx.spawn_in(window, async move |_, _| {
+        let Some(path) = path.await?? else {
+            return Ok(());
+        };
+        fs.atomic_write(path, serde_json::to_string_pretty(&permissions)?)
+            .await
+    })
+    .detach_and_prompt_err(
+        "Failed to export tool permissions",
+        window,
+        cx,
+        |_, _, _| None,
+    );
+}
+
+/// Applies tool permissions from a file exported with [`export_tool_permissions`], once the
+/// user confirms what they change.
+pub(crate) fn import_tool_permissions(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let fs = workspace.app_state().fs.clone();
+    let paths = workspace.prompt_for_open_path(
+        PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        },
+        DirectoryLister::Local(fs.clone()),
+        window,
+        cx,
+    );
+    cx.spawn_in(window, async move |workspace, cx| {
+        let Some(path) = paths.await?.and_then(|paths| paths.into_iter().next()) else {
+            return Ok(());
+        };
+        let content = fs.load(&path).await?;
+        let permissions = serde_json::from_str::<ToolPermissions>(&content)
+            .with_context(|| format!("parsing tool permissions from {path:?}"))?;
+
+        let answer = workspace.update_in(cx, |_, window, cx| {
+            let current = AssistantSettings::get_global(cx).tool_permissions();
+            window.prompt(
+                PromptLevel::Warning,
+                "Import these tool permissions?",
+                Some(&import_summary(&permissions, &current)),
+                &["Import", "Cancel"],
+                cx,
+            )
+        })?;
+        if answer.await? != 0 {
+            return Ok(());
+        }
+
+        workspace.update(cx, |_, cx| {
+            update_settings_file::<AssistantSettings>(fs, cx, move |settings, _| {
+                settings.set_tool_permissions(permissions).log_err();
+            });
+        })
+    })
+    .detach_and_prompt_err(
+        "Failed to import tool permissions",
+        window,
+        cx,
+        |_, _, _| None,
+    );
+}
+
+/// How the agent treats the calls to a context server's tools, with the settings in effect.
+struct EffectiveServerPermissions {
+    server_id: ContextServerId,
+    confirmation: ToolConfirmation,
+    /// The server's tools, and whether the selected profile enables each of them.
+    tools: Vec<(String, bool)>,
+}
+
+fn effective_permissions_report(
+    always_allow_tool_actions: bool,
+    read_only_tools: bool,
+    profile_name: &str,
+    servers: &[EffectiveServerPermissions],
+) -> String {
+    let mut report = "# Effective Tool Permissions\n\n".to_string();
+    writeln!(report, "- Profile: {profile_name}").ok();
+    writeln!(
+        report,
+        "- `always_allow_tool_actions`: {}",
+        if always_allow_tool_actions {
+            "on, so tool calls run without asking unless `read_only_tools` is on"
+        } else {
+            "off"
+        }
+    )
+    .ok();
+    writeln!(
+        report,
+        "- `read_only_tools`: {}",
+        if read_only_tools {
+            "on, so only tools that don't make changes are offered, and every call is confirmed"
+        } else {
+            "off"
+        }
+    )
+    .ok();
+    if servers.is_empty() {
+        report.push_str("\nNo MCP servers are configured.\n");
+        return report;
+    }
+
+    for server in servers {
+        let source = match server.confirmation.source {
+            ConfirmationSource::Default => "by default",
+            ConfirmationSource::User => "by your settings",
+            ConfirmationSource::ProjectServers => "by the project's `.mcp.json`",
+            ConfirmationSource::WorktreeSettings => "by the project's `.zed/settings.json`",
+        };
+        let calls = if read_only_tools {
+            "Calls are confirmed, since `read_only_tools` is on".to_string()
+        } else if always_allow_tool_actions {
+            "Calls run without asking, since `always_allow_tool_actions` is on".to_string()
+        } else if server.confirmation.confirm {
+            format!("Calls are confirmed, as set {source}")
+        } else {
+            format!("Calls run without asking, as set {source}")
+        };
+        write!(report, "\n## {}\n\n{calls}.\n", server.server_id).ok();
+        if server.tools.is_empty() {
+            continue;
+        }
+        report.push('\n');
+        for (name, enabled) in &server.tools {
+            let state = if *enabled {
+                "enabled"
+            } else {
+                "disabled in the profile"
+            };
+            writeln!(report, "- `{name}`: {state}").ok();
+        }
+    }
+    report
+}
+
+/// Opens a report of how the agent treats each context server's tools, after merging the
+/// user's, the project's and the worktree's settings.
+pub(crate) fn show_effective_tool_permissions(
+    workspace: &mut Workspace,
+    tools: Entity<ToolWorkingSet>,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let settings = AssistantSettings::get_global(cx);
+    let always_allow_tool_actions = settings.always_allow_tool_actions;
+    let read_only_tools = settings.read_only_tools;
+    let profile_name = settings
+        .profiles
+        .get(&settings.default_profile)
+        .map_or_else(
+            || settings.default_profile.0.to_string(),
+            |profile| profile.name.to_string(),
+        );
+
+    let context_server_store = workspace.project().read(cx).context_server_store();
+    let context_server_store = context_server_store.read(cx);
+    let tools = tools.read(cx);
+    let tools_by_source = tools.tools_by_source(cx);
+    let mut servers = context_server_store
+        .all_server_ids()
+        .into_iter()
+        .map(|server_id| {
+            let source = ToolSource::ContextServer {
+                id: server_id.0.clone().into(),
+            };
+            let server_tools = tools_by_source
+                .get(&source)
+                .map(|server_tools| {
+                    server_tools
+                        .iter()
+                        .map(|tool| {
+                            let name = tool.name();
+                            let enabled = tools.is_enabled(&source, &name.as_str().into());
+                            (name, enabled)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            EffectiveServerPermissions {
+                confirmation: context_server_store.tool_confirmation(&server_id, cx),
+                server_id,
+                tools: server_tools,
+            }
+        })
+        .collect::<Vec<_>>();
+    servers.sort_by(|a, b| a.server_id.0.cmp(&b.server_id.0));
+
+    let report = effective_permissions_report(
+        always_allow_tool_actions,
+        read_only_tools,
+        &profile_name,
+        &servers,
+    );
+    open_read_only_report(workspace, "Effective Tool Permissions", &report, window, cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collections::IndexMap;
+
+    fn profile(name: &str) -> AgentProfileContent {
+        AgentProfileContent {
+            name: name.into(),
+            tools: IndexMap::default(),
+            enable_all_context_servers: Some(false),
+            context_servers: IndexMap::default(),
+            compact_tool_calls_above_tokens: None,
+            active_context_servers: None,
+        }
+    }
+
+    #[test]
+    fn test_import_summary() {
+        let current = ToolPermissions {
+            always_allow_tool_actions: true,
+            read_only_tools: false,
+            profiles: IndexMap::from_iter([(AgentProfileId("write".into()), profile("Write"))]),
+        };
+        let permissions = ToolPermissions {
+            always_allow_tool_actions: false,
+            read_only_tools: true,
+            profiles: IndexMap::from_iter([
+                (AgentProfileId("write".into()), profile("Write")),
+                (AgentProfileId("vetted".into()), profile("Vetted")),
+            ]),
+        };
+        assert_eq!(
+            import_summary(&permissions, &current),
+            "Tool actions will ask for confirmation.\n\
+            Only tools that don't make changes will be available.\n\
+            Replaces the profiles: Write\n\
+            Adds the profiles: Vetted"
+        );
+    }
+
+    #[test]
+    fn test_effective_permissions_report() {
+        let servers = [
+            EffectiveServerPermissions {
+                server_id: ContextServerId("github".into()),
+                confirmation: ToolConfirmation {
+                    confirm: true,
+                    source: ConfirmationSource::WorktreeSettings,
+                },
+                tools: vec![
+                    ("search_issues".into(), true),
+                    ("create_issue".into(), false),
+                ],
+            },
+            EffectiveServerPermissions {
+                server_id: ContextServerId("postgres".into()),
+                confirmation: ToolConfirmation {
+                    confirm: false,
+                    source: ConfirmationSource::User,
+                },
+                tools: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            effective_permissions_report(false, false, "Write", &servers),
+            "# Effective Tool Permissions\n\n\
+            - Profile: Write\n\
+            - `always_allow_tool_actions`: off\n\
+            - `read_only_tools`: off\n\
+            \n## github\n\n\
+            Calls are confirmed, as set by the project's `.zed/settings.json`.\n\n\
+            - `search_issues`: enabled\n\
+            - `create_issue`: disabled in the profile\n\
+            \n## postgres\n\n\
+            Calls run without asking, as set by your settings.\n"
+        );
+        assert!(
+            effective_permissions_report(true, true, "Write", &servers)
+                .contains("Calls are confirmed, since `read_only_tools` is on.")
+        );
+    }
+}
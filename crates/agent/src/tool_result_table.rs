@@ -0,0 +1,380 @@
+use std::cmp::Ordering;
+
+use assistant_tool::{ToolCard, ToolUseStatus};
+use gpui::WeakEntity;
+use serde::{Deserialize, Serialize};
+use ui::prelude::*;
+use workspace::Workspace;
+
+/// Header fields with more words than this are taken to be prose rather than column names.
+const MAX_COLUMN_NAME_WORDS: usize = 3;
+
+/// Tool results with more rows than this are shown as they are, since a table that long isn't
+/// easier to read than the raw output.
+const MAX_ROWS: usize = 1000;
+
+/// A tool result that's a list of records, such as the rows returned by a database query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolResultTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ToolResultTable {
+    /// Builds a table from a JSON array of objects, with a column for each of their fields.
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        let items = value.as_array()?;
+        if items.is_empty() || items.len() > MAX_ROWS {
+            return None;
+        }
+        let mut columns = Vec::<String>::new();
+        for item in items {
+            for key in item.as_object()?.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+        let rows = items
+            .iter()
+            .filter_map(|item| item.as_object())
+            .map(|item| {
+                columns
+                    .iter()
+                    .map(|column| match item.get(column) {
+                        None | Some(serde_json::Value::Null) => String::new(),
+                        Some(serde_json::Value::String(string)) => string.clone(),
+                        Some(value) => value.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(Self { columns, rows })
+    }
+
+    /// Builds a table from text that's either a JSON array of objects, or CSV or TSV with a
+    /// header row.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.starts_with('[') {
+            return Self::from_json(&serde_json::from_str(text).ok()?);
+        }
+
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next()?;
+        let delimiter = if header.contains('\t') { '\t' } else { ',' };
+        let columns = parse_delimited_line(header, delimiter);
+        if columns.len() < 2 || !columns.iter().all(|column| is_column_name(column)) {
+            return None;
+        }
+        if columns
+            .iter()
+            .enumerate()
+            .any(|(ix, column)| columns[..ix].contains(column))
+        {
+            return None;
+        }
+        let rows = lines
+            .map(|line| parse_delimited_line(line, delimiter))
+            .collect::<Vec<_>>();
+        // Requiring every row to have as many fields as the header keeps prose with commas in
+        // it from being shown as a table.
+        if rows.is_empty()
+            || rows.len() > MAX_ROWS
+            || rows.iter().any(|row| row.len() != columns.len())
+        {
+            return None;
+        }
+        Some(Self { columns, rows })
+    }
+}
+
+/// Whether a header field looks like a column name, such as `id` or `created at`, rather
+/// than part of a sentence.
+fn is_column_name(field: &str) -> bool {
+    let field = field.trim();
+    !field.is_empty()
+        && field.split_whitespace().count() <= MAX_COLUMN_NAME_WORDS
+        && !field.ends_with(['.', '!', '?', ':', ';'])
+}
+
+/// Splits a line of CSV or TSV into fields, where fields may be quoted with `"` and quotes
+/// within them are escaped by doubling them.
+fn parse_delimited_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes || field.is_empty() => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Compares cells numerically when both are numbers, so that `10` sorts after `9`.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+pub struct ToolResultTableCard {
+    tool_name: SharedString,
+    table: ToolResultTable,
+    raw: SharedString,
+    sort_column: Option<usize>,
+    sort_descending: bool,
+    show_raw: bool,
+}
+
+impl ToolResultTableCard {
+    pub fn new(tool_name: SharedString, table: ToolResultTable, raw: SharedString) -> Self {
+        Self {
+            tool_name,
+            table,
+            raw,
+            sort_column: None,
+            sort_descending: false,
+            show_raw: false,
+        }
+    }
+
+    fn sort_by(&mut self, column: usize) {
+        if self.sort_column == Some(column) {
+            self.sort_descending = !self.sort_descending;
+        } else {
+            self.sort_column = Some(column);
+            self.sort_descending = false;
+        }
+    }
+
+    fn sorted_rows(&self) -> Vec<&Vec<String>> {
+        let mut rows = self.table.rows.iter().collect::<Vec<_>>();
+        if let Some(column) = self.sort_column {
+            rows.sort_by(|a, b| {
+                let ordering = compare_cells(&a[column], &b[column]);
+                if self.sort_descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        rows
+    }
+
+    fn render_table(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let border_color = cx.theme().colors().border;
+        let header = h_flex()
+            .w_full()
+            .py_1()
+            .border_b_1()
+            .border_color(border_color)
+            .children(self.table.columns.iter().enumerate().map(|(ix, column)| {
+                let sort_icon = (self.sort_column == Some(ix)).then(|| {
+                    let icon = if self.sort_descending {
+                        IconName::ChevronDown
+                    } else {
+                        IconName::ChevronUp
+                    };
+                    Icon::new(icon).size(IconSize::XSmall).color(Color::Muted)
+                });
+                h_flex()
+                    .id(("column", ix))
+                    .flex_1()
+                    .min_w_0()
+                    .px_1p5()
+                    .gap_0p5()
+                    .cursor_pointer()
+                    .child(
+                        Label::new(column.clone())
+                            .size(LabelSize::Small)
+                            .weight(gpui::FontWeight::SEMIBOLD)
+                            .truncate(),
+                    )
+                    .children(sort_icon)
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        this.sort_by(ix);
+                        cx.notify();
+                    }))
+            }));
+
+        let rows = self.sorted_rows();
+        let row_count = rows.len();
+        let rows = rows.into_iter().enumerate().map(|(ix, row)| {
+            h_flex()
+                .w_full()
+                .py_1()
+                .when(ix + 1 < row_count, |this| {
+                    this.border_b_1().border_color(border_color)
+                })
+                .children(row.iter().map(|cell| {
+                    div().flex_1().min_w_0().px_1p5().child(
+                        Label::new(cell.clone())
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                            .truncate(),
+                    )
+                }))
+        });
+
+        v_flex()
+            .id("tool-result-table")
+            .max_h(px(400.))
+            .overflow_y_scroll()
+            .rounded_md()
+            .border_1()
+            .border_color(border_color)
+            .bg(cx.theme().colors().editor_background)
+            .child(header)
+            .children(rows)
+    }
+}
+
+impl ToolCard for ToolResultTableCard {
+    fn render(
+        &mut self,
+        _status: &ToolUseStatus,
+        _window: &mut Window,
+        _workspace: WeakEntity<Workspace>,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let row_count = self.table.rows.len();
+        let toggle_label = if self.show_raw {
+            "Show Table"
+        } else {
+            "Show Raw"
+        };
+        v_flex()
+            .mb_2()
+            .gap_1()
+            .child(
+                h_flex()
+                    .justify_between()
+                    .child(
+                        h_flex()
+                            .gap_1p5()
+                            .child(
+                                Icon::new(IconName::Cog)
+                                    .size(IconSize::XSmall)
+                                    .color(Color::Muted),
+                            )
+                            .child(
+                                Label::new(format!(
+                                    "{} returned {row_count} row{}",
+                                    self.tool_name,
+                                    if row_count == 1 { "" } else { "s" }
+                                ))
+                                .size(LabelSize::Small),
+                            ),
+                    )
+                    .child(
+                        Button::new("toggle-raw-output", toggle_label)
+                            .label_size(LabelSize::Small)
+                            .on_click(cx.listener(|this, _, _, cx| {
+                                this.show_raw = !this.show_raw;
+                                cx.notify();
+                            })),
+                    ),
+            )
+            .map(|this| {
+                if self.show_raw {
+                    this.child(
+                        div()
+                            .id("tool-result-raw")
+                            .max_h(px(400.))
+                            .overflow_y_scroll()
+                            .p_2()
+                            .rounded_md()
+                            .border_1()
+                            .border_color(cx.theme().colors().border)
+                            .bg(cx.theme().colors().editor_background)
+                            .child(
+                                Label::new(self.raw.clone())
+                                    .size(LabelSize::Small)
+                                    .buffer_font(cx),
+                            ),
+                    )
+                } else {
+                    this.child(self.render_table(cx))
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_table_from_json() {
+        let table = ToolResultTable::from_json(&json!([
+            { "id": 1, "name": "Ada" },
+            { "id": 2, "name": "Grace", "email": null },
+            { "id": 3, "email": "linus@example.com" },
+        ]))
+        .unwrap();
+        assert_eq!(table.columns, ["id", "name", "email"]);
+        assert_eq!(
+            table.rows,
+            [
+                vec!["1", "Ada", ""],
+                vec!["2", "Grace", ""],
+                vec!["3", "", "linus@example.com"],
+            ]
+        );
+
+        assert_eq!(ToolResultTable::from_json(&json!([1, 2, 3])), None);
+        assert_eq!(ToolResultTable::from_json(&json!({ "id": 1 })), None);
+    }
+
+    #[test]
+    fn test_table_from_text() {
+        let table =
+            ToolResultTable::from_text("name,notes\nAda,\"first, and \"\"only\"\"\"\nGrace,\n")
+                .unwrap();
+        assert_eq!(table.columns, ["name", "notes"]);
+        assert_eq!(
+            table.rows,
+            [vec!["Ada", "first, and \"only\""], vec!["Grace", ""]]
+        );
+
+        let table = ToolResultTable::from_text("a\tb\n1\t2").unwrap();
+        assert_eq!(table.rows, [vec!["1", "2"]]);
+
+        assert_eq!(
+            ToolResultTable::from_text("Found 2 files, in src.\nBoth are new."),
+            None
+        );
+        assert_eq!(ToolResultTable::from_text("No results"), None);
+        assert_eq!(
+            ToolResultTable::from_text(
+                "I checked the config, the logs, and the cache.\nNothing stood out, so far, anyway."
+            ),
+            None
+        );
+        assert_eq!(
+            ToolResultTable::from_text("Note: this is slow, really\nslow, right"),
+            None
+        );
+        assert_eq!(ToolResultTable::from_text("id,id\n1,2"), None);
+        assert!(ToolResultTable::from_text("id,created at\n1,today").is_some());
+    }
+
+    #[test]
+    fn test_compare_cells() {
+        assert_eq!(compare_cells("9", "10"), Ordering::Less);
+        assert_eq!(compare_cells("b", "a"), Ordering::Greater);
+    }
+}
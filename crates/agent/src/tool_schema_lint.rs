@@ -0,0 +1,306 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use assistant_tool::{Tool, ToolSource, ToolWorkingSet};
+use gpui::{Context, Entity, Window};
+use serde_json::Value;
+use workspace::Workspace;
+
+use crate::report::open_read_only_report;
+use crate::tool_schema_snapshot::{SCHEMA_FORMATS, tool_key};
+
+/// Schemas larger than this take up a noticeable part of every request, and a few large schemas
+/// can crowd out the other tools when the model picks one.
+const MAX_SCHEMA_LEN: usize = 8 * 1024;
+
+#[derive(Debug, PartialEq)]
+enum SchemaDiagnostic {
+    /// The schema can't be adapted to the format, so the tool is left out for models using it.
+    Unsupported {
+        format: String,
+        error: String,
+    },
+    /// Keywords that adapting the schema to the format removed or rewrote, by path.
+    RewrittenKeywords {
+        format: String,
+        paths: Vec<String>,
+    },
+    TooLarge {
+        format: String,
+        len: usize,
+    },
+    MissingToolDescription,
+    /// Properties without a description, by path.
+    MissingPropertyDescriptions {
+        paths: Vec<String>,
+    },
+}
+
+impl SchemaDiagnostic {
+    fn to_markdown(&self) -> String {
+        let code_list = |paths: &[String]| {
+            paths
+                .iter()
+                .map(|path| format!("`{path}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Self::Unsupported { format, error } => {
+                format!(
+                    "**Unsupported** ({format}): {error}. Models using this format can't call the tool."
+                )
+            }
+            Self::RewrittenKeywords { format, paths } => format!(
+                "**Rewritten keywords** ({format}): {}. Models using this format don't see them as written.",
+                code_list(paths)
+            ),
+            Self::TooLarge { format, len } => {
+                format!("**Large schema** ({format}): {len} bytes, more than {MAX_SCHEMA_LEN}.")
+            }
+            Self::MissingToolDescription => {
+                "**Missing description**: the model has only the tool's name to go by.".to_string()
+            }
+            Self::MissingPropertyDescriptions { paths } => {
+                format!("**Undescribed properties**: {}.", code_list(paths))
+            }
+        }
+    }
+}
+
+/// Checks a tool's description and its input schema in each format, where `schemas` starts with
+/// the schema in the format closest to what the server reported.
+fn lint(description: &str, schemas: &[(&str, Result<Value>)]) -> Vec<SchemaDiagnostic> {
+    let mut diagnostics = Vec::new();
+    if description.trim().is_empty() {
+        diagnostics.push(SchemaDiagnostic::MissingToolDescription);
+    }
+
+    let original = schemas.first().and_then(|(_, schema)| schema.as_ref().ok());
+    for (format, schema) in schemas {
+        let schema = match schema {
+            Ok(schema) => schema,
+            Err(error) => {
+                diagnostics.push(SchemaDiagnostic::Unsupported {
+                    format: format.to_string(),
+                    error: error.to_string(),
+                });
+                continue;
+            }
+        };
+
+        if let Some(original) = original {
+            let mut paths = Vec::new();
+            removed_keys(original, schema, "", &mut paths);
+            if !paths.is_empty() {
+                diagnostics.push(SchemaDiagnostic::RewrittenKeywords {
+                    format: format.to_string(),
+                    paths,
+                });
+            }
+        }
+
+        let len = schema.to_string().len();
+        if len > MAX_SCHEMA_LEN {
+            diagnostics.push(SchemaDiagnostic::TooLarge {
+                format: format.to_string(),
+                len,
+            });
+        }
+    }
+
+    if let Some(original) = original {
+        let mut paths = Vec::new();
+        undescribed_properties(original, "", &mut paths);
+        if !paths.is_empty() {
+            diagnostics.push(SchemaDiagnostic::MissingPropertyDescriptions { paths });
+        }
+    }
+    diagnostics
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+/// Collects the paths of the keys in `before` that are missing from `after`.
+fn removed_keys(before: &Value, after: &Value, prefix: &str, paths: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, value) in before {
+                let path = join_path(prefix, key);
+                match after.get(key) {
+                    Some(adapted) => removed_keys(value, adapted, &path, paths),
+                    None => paths.push(path),
+                }
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for (ix, (value, adapted)) in before.iter().zip(after).enumerate() {
+                removed_keys(value, adapted, &format!("{prefix}[{ix}]"), paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collects the paths of the properties, including those of nested objects and array items, that
+/// have no description.
+fn undescribed_properties(schema: &Value, prefix: &str, paths: &mut Vec<String>) {
+    if let Some(items) = schema.get("items") {
+        undescribed_properties(items, &format!("{prefix}[]"), paths);
+    }
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+    for (name, property) in properties {
+        let path = join_path(prefix, name);
+        let described = property
+            .get("description")
+            .and_then(Value::as_str)
+            .is_some_and(|description| !description.trim().is_empty());
+        if !described {
+            paths.push(path.clone());
+        }
+        undescribed_properties(property, &path, paths);
+    }
+}
+
+fn report(tools: &[Arc<dyn Tool>]) -> String {
+    let formats = SCHEMA_FORMATS
+        .iter()
+        .map(|(name, _)| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut findings = tools
+        .iter()
+        .filter_map(|tool| {
+            let schemas = SCHEMA_FORMATS
+                .iter()
+                .map(|(name, format)| (*name, tool.input_schema(*format)))
+                .collect::<Vec<_>>();
+            let diagnostics = lint(&tool.description(), &schemas);
+            (!diagnostics.is_empty()).then(|| (tool_key(tool.as_ref()), diagnostics))
+        })
+        .collect::<Vec<_>>();
+    findings.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut report = format!(
+        "# Tool Schema Lint\n\nChecked the schemas of {} MCP tools in the {formats} formats.\n",
+        tools.len()
+    );
+    if findings.is_empty() {
+        report.push_str("\nNo problems found.\n");
+        return report;
+    }
+    report.push_str(&format!(
+        "\n{} tools have problems that can make models pick or call them wrongly.\n",
+        findings.len()
+    ));
+    for (tool, diagnostics) in findings {
+        report.push_str(&format!("\n## `{tool}`\n\n"));
+        for diagnostic in diagnostics {
+            report.push_str(&format!("- {}\n", diagnostic.to_markdown()));
+        }
+    }
+    report
+}
+
+/// Opens a report of the problems in the tool schemas of the configured MCP servers, such as
+/// keywords a schema format doesn't support, oversized schemas, and missing descriptions.
+pub(crate) fn lint_tool_schemas(
+    tools: Entity<ToolWorkingSet>,
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let tools = tools
+        .read(cx)
+        .tools(cx)
+        .into_iter()
+        .filter(|tool| matches!(tool.source(), ToolSource::ContextServer { .. }))
+        .collect::<Vec<_>>();
+    let report = report(&tools);
+    open_read_only_report(workspace, "Tool Schema Lint", &report, window, cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn adapted_schemas(schema: Value) -> Vec<(&'static str, Result<Value>)> {
+        SCHEMA_FORMATS
+            .iter()
+            .map(|(name, format)| {
+                let mut schema = schema.clone();
+                let result = assistant_tool::adapt_schema_to_format(&mut schema, *format);
+                (*name, result.map(|_| schema))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_tool_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "format": "uri", "description": "The page to fetch" },
+                "headers": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "name": { "type": "string" } },
+                        "additionalProperties": false,
+                    },
+                },
+            },
+        });
+        assert_eq!(
+            lint("Fetches a page", &adapted_schemas(schema)),
+            vec![
+                SchemaDiagnostic::RewrittenKeywords {
+                    format: "json_schema_subset".into(),
+                    paths: vec![
+                        "properties.url.format".into(),
+                        "properties.headers.items.additionalProperties".into(),
+                    ],
+                },
+                SchemaDiagnostic::MissingPropertyDescriptions {
+                    paths: vec!["headers".into(), "headers[].name".into()],
+                },
+            ]
+        );
+
+        let schema = json!({
+            "type": "object",
+            "properties": { "id": { "$ref": "#/definitions/id", "description": "The record" } },
+        });
+        assert_eq!(
+            lint(" ", &adapted_schemas(schema)),
+            vec![
+                SchemaDiagnostic::MissingToolDescription,
+                SchemaDiagnostic::Unsupported {
+                    format: "json_schema_subset".into(),
+                    error: "Schema cannot be made compatible because it contains \"$ref\"".into(),
+                },
+            ]
+        );
+
+        let schema = json!({
+            "type": "object",
+            "properties": { "query": { "type": "string", "description": "x".repeat(MAX_SCHEMA_LEN) } },
+        });
+        let diagnostics = lint("Searches", &adapted_schemas(schema));
+        assert_eq!(diagnostics.len(), SCHEMA_FORMATS.len());
+        assert!(
+            diagnostics
+                .iter()
+                .all(|diagnostic| matches!(diagnostic, SchemaDiagnostic::TooLarge { .. }))
+        );
+    }
+}
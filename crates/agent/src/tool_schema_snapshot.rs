@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use assistant_tool::{Tool, ToolSource, ToolWorkingSet};
+use gpui::{Context, Entity, Window};
+use language_model::LanguageModelToolSchemaFormat;
+use util::ResultExt as _;
+use workspace::Workspace;
+
+use crate::report::open_read_only_report;
+
+pub(crate) const SCHEMA_FORMATS: &[(&str, LanguageModelToolSchemaFormat)] = &[
+    ("json_schema", LanguageModelToolSchemaFormat::JsonSchema),
+    (
+        "json_schema_subset",
+        LanguageModelToolSchemaFormat::JsonSchemaSubset,
+    ),
+];
+
+/// The input schemas of a set of tools, keyed by schema format and then by tool.
+type ToolSchemaSnapshot = BTreeMap<String, BTreeMap<String, serde_json::Value>>;
+
+#[derive(Debug, PartialEq)]
+enum ToolSchemaChange {
+    Added { format: String, tool: String },
+    Removed { format: String, tool: String },
+    Changed { format: String, tool: String },
+}
+
+fn snapshot_path() -> PathBuf {
+    paths::data_dir().join("tool_schema_snapshot.json")
+}
+
+/// Identifies tools by source as well as name, since context servers can provide tools with the
+/// same name.
+pub(crate) fn tool_key(tool: &dyn Tool) -> String {
+    match tool.source() {
+        ToolSource::Native => tool.name(),
+        ToolSource::ContextServer { id } => format!("{id}/{}", tool.name()),
+    }
+}
+
+fn snapshot(tools: &[Arc<dyn Tool>]) -> ToolSchemaSnapshot {
+    SCHEMA_FORMATS
+        .iter()
+        .map(|(format_name, format)| {
+            let schemas = tools
+                .iter()
+                .map(|tool| {
+                    let schema = tool
+                        .input_schema(*format)
+                        .unwrap_or_else(|error| serde_json::json!({ "error": error.to_string() }));
+                    (tool_key(tool.as_ref()), schema)
+                })
+                .collect();
+            (format_name.to_string(), schemas)
+        })
+        .collect()
+}
+
+fn diff(old: &ToolSchemaSnapshot, new: &ToolSchemaSnapshot) -> Vec<ToolSchemaChange> {
+    let empty = BTreeMap::new();
+    let mut changes = Vec::new();
+    for (format, new_schemas) in new {
+        let old_schemas = old.get(format).unwrap_or(&empty);
+        for (tool, new_schema) in new_schemas {
+            match old_schemas.get(tool) {
+                None => changes.push(ToolSchemaChange::Added {
+                    format: format.clone(),
+                    tool: tool.clone(),
+                }),
+                Some(old_schema) if old_schema != new_schema => {
+                    changes.push(ToolSchemaChange::Changed {
+                        format: format.clone(),
+                        tool: tool.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for tool in old_schemas.keys() {
+            if !new_schemas.contains_key(tool) {
+                changes.push(ToolSchemaChange::Removed {
+                    format: format.clone(),
+                    tool: tool.clone(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+fn report(previous: Option<&ToolSchemaSnapshot>, current: &ToolSchemaSnapshot) -> String {
+    let tool_count = current.values().map(BTreeMap::len).max().unwrap_or(0);
+    let mut report = format!(
+        "# Tool Schema Snapshot\n\nSaved the schemas of {tool_count} tools to `{}`.\n\n",
+        snapshot_path().display()
+    );
+
+    let Some(previous) = previous else {
+        report.push_str("There was no previous snapshot to compare against.\n");
+        return report;
+    };
+    let changes = diff(previous, current);
+    if changes.is_empty() {
+        report.push_str("No schemas changed since the previous snapshot.\n");
+        return report;
+    }
+
+    report.push_str(
+        "The following schemas changed since the previous snapshot. \
+        Models may call these tools differently.\n",
+    );
+    let pretty = |schema: Option<&serde_json::Value>| {
+        schema
+            .and_then(|schema| serde_json::to_string_pretty(schema).log_err())
+            .unwrap_or_default()
+    };
+    for change in changes {
+        match change {
+            ToolSchemaChange::Added { format, tool } => {
+                report.push_str(&format!("\n## Added `{tool}` ({format})\n"));
+            }
+            ToolSchemaChange::Removed { format, tool } => {
+                report.push_str(&format!("\n## Removed `{tool}` ({format})\n"));
+            }
+            ToolSchemaChange::Changed { format, tool } => {
+                let old = pretty(previous.get(&format).and_then(|schemas| schemas.get(&tool)));
+                let new = pretty(current.get(&format).and_then(|schemas| schemas.get(&tool)));
+                report.push_str(&format!(
+                    "\n## Changed `{tool}` ({format})\n\nBefore:\n\n```json\n{old}\n```\n\nAfter:\n\n```json\n{new}\n```\n"
+                ));
+            }
+        }
+    }
+    report
+}
+
+/// Saves the schemas of all registered tools, and opens a report of the schemas that changed
+/// since the last time this was run.
+pub(crate) fn snapshot_tool_schemas(
+    tools: Entity<ToolWorkingSet>,
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let current = snapshot(&tools.read(cx).tools(cx));
+    let fs = workspace.app_state().fs.clone();
+    cx.spawn_in(window, async move |workspace, cx| {
+        let path = snapshot_path();
+        let previous = match fs.load(&path).await {
+            Ok(content) => serde_json::from_str::<ToolSchemaSnapshot>(&content).log_err(),
+            Err(_) => None,
+        };
+
+        if let Some(parent) = path.parent() {
+            fs.create_dir(parent).await?;
+        }
+        fs.atomic_write(path, serde_json::to_string_pretty(&current)?)
+            .await?;
+
+        let report = report(previous.as_ref(), &current);
+        workspace.update_in(cx, |workspace, window, cx| {
+            open_read_only_report(workspace, "Tool Schema Snapshot", &report, window, cx);
+        })?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_tool_schemas() {
+        let old: ToolSchemaSnapshot = serde_json::from_value(json!({
+            "json_schema": {
+                "read_file": { "type": "object" },
+                "server/search": { "type": "object" },
+                "grep": { "type": "object" },
+            }
+        }))
+        .unwrap();
+        let new: ToolSchemaSnapshot = serde_json::from_value(json!({
+            "json_schema": {
+                "read_file": { "type": "object" },
+                "server/search": { "type": "object", "required": ["query"] },
+                "terminal": { "type": "object" },
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                ToolSchemaChange::Changed {
+                    format: "json_schema".into(),
+                    tool: "server/search".into(),
+                },
+                ToolSchemaChange::Added {
+                    format: "json_schema".into(),
+                    tool: "terminal".into(),
+                },
+                ToolSchemaChange::Removed {
+                    format: "json_schema".into(),
+                    tool: "grep".into(),
+                },
+            ]
+        );
+        assert!(diff(&new, &new).is_empty());
+    }
+}
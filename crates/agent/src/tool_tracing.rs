@@ -0,0 +1,269 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use assistant_settings::AssistantSettings;
+use assistant_tool::ToolSource;
+use context_server::ContextServerId;
+use gpui::App;
+use http_client::{AsyncBody, HttpClient as _};
+use language_model::LanguageModelToolUseId;
+use serde_json::json;
+use settings::Settings as _;
+use util::ResultExt as _;
+
+const SCOPE_NAME: &str = "zed.agent";
+
+/// A finished span, ready to be exported.
+#[derive(Debug, Clone)]
+struct Span {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, String)>,
+    error: Option<String>,
+}
+
+/// Collects the phases of a single tool call into one trace, which is exported when it finishes.
+pub struct ToolCallTrace {
+    trace_id: [u8; 16],
+    root_span_id: [u8; 8],
+    start: SystemTime,
+    attributes: Vec<(&'static str, String)>,
+    spans: Vec<Span>,
+    open_phase: Option<(&'static str, SystemTime)>,
+}
+
+impl ToolCallTrace {
+    /// Starts a trace if an export endpoint is configured.
+    pub fn start(
+        tool_use_id: &LanguageModelToolUseId,
+        tool_name: &str,
+        source: &ToolSource,
+        cx: &App,
+    ) -> Option<Self> {
+        AssistantSettings::get_global(cx)
+            .tool_tracing_endpoint
+            .as_ref()?;
+
+        let source = match source {
+            ToolSource::Native => "native".to_string(),
+            ToolSource::ContextServer { id } => format!("mcp:{id}"),
+        };
+        Some(Self {
+            trace_id: rand::random(),
+            root_span_id: rand::random(),
+            start: SystemTime::now(),
+            attributes: vec![
+                ("gen_ai.tool.name", tool_name.to_string()),
+                ("gen_ai.tool.call.id", tool_use_id.to_string()),
+                ("zed.tool.source", source),
+            ],
+            spans: Vec::new(),
+            open_phase: None,
+        })
+    }
+
+    /// Starts a phase that lasts until the next phase starts or the trace finishes.
+    pub fn begin_phase(&mut self, name: &'static str) {
+        self.end_phase();
+        self.open_phase = Some((name, SystemTime::now()));
+    }
+
+    pub fn end_phase(&mut self) {
+        if let Some((name, start)) = self.open_phase.take() {
+            self.record_phase(name, start, SystemTime::now());
+        }
+    }
+
+    pub fn record_phase(&mut self, name: &'static str, start: SystemTime, end: SystemTime) {
+        self.spans.push(Span {
+            trace_id: self.trace_id,
+            span_id: rand::random(),
+            parent_span_id: Some(self.root_span_id),
+            name,
+            start,
+            end,
+            attributes: Vec::new(),
+            error: None,
+        });
+    }
+
+    pub fn finish(mut self, error: Option<String>, cx: &App) {
+        self.end_phase();
+        self.spans.push(Span {
+            trace_id: self.trace_id,
+            span_id: self.root_span_id,
+            parent_span_id: None,
+            name: "tool.call",
+            start: self.start,
+            end: SystemTime::now(),
+            attributes: self.attributes,
+            error,
+        });
+        export(self.spans, cx);
+    }
+}
+
+/// Records how long it took to list the tools of a context server.
+pub fn record_discovery(
+    server_id: &ContextServerId,
+    server_identity: Option<&str>,
+    start: SystemTime,
+    result: Result<usize, String>,
+    cx: &App,
+) {
+    if AssistantSettings::get_global(cx)
+        .tool_tracing_endpoint
+        .is_none()
+    {
+        return;
+    }
+
+    let mut attributes = vec![("zed.context_server.id", server_id.0.to_string())];
+    if let Some(server_identity) = server_identity {
+        attributes.push(("zed.context_server.identity", server_identity.to_string()));
+    }
+    let error = match result {
+        Ok(tool_count) => {
+            attributes.push(("zed.tool.count", tool_count.to_string()));
+            None
+        }
+        Err(error) => Some(error),
+    };
+    export(
+        vec![Span {
+            trace_id: rand::random(),
+            span_id: rand::random(),
+            parent_span_id: None,
+            name: "tool.discovery",
+            start,
+            end: SystemTime::now(),
+            attributes,
+            error,
+        }],
+        cx,
+    );
+}
+
+fn export(spans: Vec<Span>, cx: &App) {
+    let Some(endpoint) = AssistantSettings::get_global(cx)
+        .tool_tracing_endpoint
+        .clone()
+    else {
+        return;
+    };
+    let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    let body = otlp_body(&spans).to_string();
+    let http_client = cx.http_client();
+    cx.background_spawn(async move {
+        let response = http_client.post_json(&url, AsyncBody::from(body)).await?;
+        anyhow::ensure!(
+            response.status().is_success(),
+            "exporting tool traces to {url} failed with status {}",
+            response.status()
+        );
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Encodes spans using the OTLP/HTTP JSON encoding.
+fn otlp_body(spans: &[Span]) -> serde_json::Value {
+    let spans = spans
+        .iter()
+        .map(|span| {
+            let mut value = json!({
+                "traceId": hex(&span.trace_id),
+                "spanId": hex(&span.span_id),
+                "name": span.name,
+                // SPAN_KIND_INTERNAL
+                "kind": 1,
+                "startTimeUnixNano": unix_nanos(span.start).to_string(),
+                "endTimeUnixNano": unix_nanos(span.end).to_string(),
+                "attributes": span
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+                    .collect::<Vec<_>>(),
+                "status": match &span.error {
+                    // STATUS_CODE_OK
+                    None => json!({ "code": 1 }),
+                    // STATUS_CODE_ERROR
+                    Some(message) => json!({ "code": 2, "message": message }),
+                },
+            });
+            if let Some(parent_span_id) = &span.parent_span_id {
+                value["parentSpanId"] = hex(parent_span_id).into();
+            }
+            value
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [{ "key": "service.name", "value": { "stringValue": "zed" } }],
+            },
+            "scopeSpans": [{
+                "scope": { "name": SCOPE_NAME },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .log_err()
+        .map_or(0, |duration| duration.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_otlp_body() {
+        let start = UNIX_EPOCH + Duration::from_secs(1);
+        let spans = [
+            Span {
+                trace_id: [1; 16],
+                span_id: [2; 8],
+                parent_span_id: None,
+                name: "tool.call",
+                start,
+                end: start + Duration::from_millis(5),
+                attributes: vec![("gen_ai.tool.name", "grep".to_string())],
+                error: None,
+            },
+            Span {
+                trace_id: [1; 16],
+                span_id: [3; 8],
+                parent_span_id: Some([2; 8]),
+                name: "tool.execute",
+                start,
+                end: start + Duration::from_millis(3),
+                attributes: Vec::new(),
+                error: Some("timed out".to_string()),
+            },
+        ];
+
+        let body = otlp_body(&spans);
+        let spans = &body["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans[0]["traceId"], "01".repeat(16));
+        assert_eq!(spans[0]["startTimeUnixNano"], "1000000000");
+        assert_eq!(spans[0]["endTimeUnixNano"], "1005000000");
+        assert_eq!(spans[0]["attributes"][0]["value"]["stringValue"], "grep");
+        assert!(spans[0].get("parentSpanId").is_none());
+        assert_eq!(spans[1]["parentSpanId"], "02".repeat(8));
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert_eq!(spans[1]["status"]["message"], "timed out");
+    }
+}
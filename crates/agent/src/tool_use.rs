@@ -1,21 +1,29 @@
+use std::process::Stdio;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context as _, Result, bail};
+use assistant_settings::{ImageDescriptionFallback, ToolResultSummarization};
 use assistant_tool::{
-    AnyToolCard, Tool, ToolResultContent, ToolResultOutput, ToolUseStatus, ToolWorkingSet,
+    AnyToolCard, Tool, ToolResultContent, ToolResultOutput, ToolSource, ToolSourceBadge,
+    ToolUseStatus, ToolWorkingSet,
 };
-use collections::HashMap;
-use futures::FutureExt as _;
+use base64::Engine as _;
+use collections::{HashMap, HashSet};
+use fs::Fs;
 use futures::future::Shared;
-use gpui::{App, Entity, SharedString, Task};
+use futures::{AsyncWriteExt as _, FutureExt as _, StreamExt as _};
+use gpui::{App, AsyncApp, Entity, SharedString, Task};
 use language_model::{
-    ConfiguredModel, LanguageModel, LanguageModelRequest, LanguageModelToolResult,
-    LanguageModelToolResultContent, LanguageModelToolUse, LanguageModelToolUseId, Role,
+    ConfiguredModel, LanguageModel, LanguageModelId, LanguageModelImage, LanguageModelProviderId,
+    LanguageModelRegistry, LanguageModelRequest, LanguageModelRequestMessage,
+    LanguageModelToolResult, LanguageModelToolResultContent, LanguageModelToolUse,
+    LanguageModelToolUseId, MessageContent, Role, SelectedModel, estimate_text_tokens,
 };
 use project::Project;
 use ui::{IconName, Window};
-use util::truncate_lines_to_byte_limit;
+use util::{ResultExt as _, truncate_lines_to_byte_limit};
 
+use crate::secret_redaction::SecretRedactor;
 use crate::thread::{MessageId, PromptId, ThreadId};
 use crate::thread_store::SerializedMessage;
 
@@ -27,6 +35,8 @@ pub struct ToolUse {
     pub status: ToolUseStatus,
     pub input: serde_json::Value,
     pub icon: ui::IconName,
+    pub source: ToolSource,
+    pub source_badge: Option<ToolSourceBadge>,
     pub needs_confirmation: bool,
 }
 
@@ -37,6 +47,17 @@ pub struct ToolUseState {
     pending_tool_uses_by_id: HashMap<LanguageModelToolUseId, PendingToolUse>,
     tool_result_cards: HashMap<LanguageModelToolUseId, AnyToolCard>,
     tool_use_metadata_by_id: HashMap<LanguageModelToolUseId, ToolUseMetadata>,
+    /// Summaries of long tool results, which are sent to the model in place of the results.
+    tool_result_summaries: HashMap<LanguageModelToolUseId, LanguageModelToolResultContent>,
+    /// The servers of the MCP tools that were called, which restored threads still show when
+    /// the servers aren't running.
+    context_server_ids: HashMap<LanguageModelToolUseId, SharedString>,
+    /// The names that the servers of MCP tools know them by, for the calls made in this session,
+    /// since tools can be offered to the model under a different name.
+    context_server_tool_names: HashMap<LanguageModelToolUseId, String>,
+    /// The tool uses that were restored without a result, because they were running or waiting
+    /// to when the thread was saved before Zed quit.
+    interrupted_tool_use_ids: HashSet<LanguageModelToolUseId>,
 }
 
 impl ToolUseState {
@@ -48,6 +69,10 @@ impl ToolUseState {
             pending_tool_uses_by_id: HashMap::default(),
             tool_result_cards: HashMap::default(),
             tool_use_metadata_by_id: HashMap::default(),
+            tool_result_summaries: HashMap::default(),
+            context_server_ids: HashMap::default(),
+            context_server_tool_names: HashMap::default(),
+            interrupted_tool_use_ids: HashSet::default(),
         }
     }
 
@@ -78,11 +103,18 @@ impl ToolUseState {
                             .map(|tool_use| LanguageModelToolUse {
                                 id: tool_use.id.clone(),
                                 name: tool_use.name.clone().into(),
-                                raw_input: tool_use.input.to_string(),
+                                raw_input: tool_use
+                                    .raw_input
+                                    .clone()
+                                    .unwrap_or_else(|| tool_use.input.to_string()),
                                 input: tool_use.input.clone(),
                                 is_input_complete: true,
                             })
                             .collect::<Vec<_>>();
+                        this.context_server_ids
+                            .extend(message.tool_uses.iter().filter_map(|tool_use| {
+                                Some((tool_use.id.clone(), tool_use.server_id.clone()?))
+                            }));
 
                         tool_names_by_id.extend(
                             tool_uses
@@ -110,6 +142,10 @@ impl ToolUseState {
                                     output: tool_result.output.clone(),
                                 },
                             );
+                            if let Some(summary) = tool_result.summary.clone() {
+                                this.tool_result_summaries
+                                    .insert(tool_use_id.clone(), summary);
+                            }
 
                             if let Some(window) = &mut window {
                                 if let Some(tool) = this.tools.read(cx).tool(tool_use, cx) {
@@ -132,6 +168,13 @@ impl ToolUseState {
             }
         }
 
+        this.interrupted_tool_use_ids = this
+            .tool_uses_by_assistant_message
+            .values()
+            .flatten()
+            .filter(|tool_use| !this.tool_results.contains_key(&tool_use.id))
+            .map(|tool_use| tool_use.id.clone())
+            .collect();
         this
     }
 
@@ -160,10 +203,36 @@ impl ToolUseState {
         cancelled_tool_uses
     }
 
+    /// Returns the server of an MCP tool call, and the name the server knows the tool by.
+    pub fn context_server_tool(&self, id: &LanguageModelToolUseId) -> Option<(&str, &str)> {
+        Some((
+            self.context_server_ids.get(id)?.as_ref(),
+            self.context_server_tool_names.get(id)?.as_str(),
+        ))
+    }
+
     pub fn pending_tool_uses(&self) -> Vec<&PendingToolUse> {
         self.pending_tool_uses_by_id.values().collect()
     }
 
+    /// Returns the tool uses that are waiting for the user's confirmation, in the order that the
+    /// model requested them.
+    pub fn tool_uses_needing_confirmation(&self) -> Vec<&PendingToolUse> {
+        let mut tool_uses = self
+            .pending_tool_uses_by_id
+            .values()
+            .filter(|tool_use| tool_use.status.needs_confirmation())
+            .collect::<Vec<_>>();
+        tool_uses.sort_by_key(|tool_use| {
+            let index = self
+                .tool_uses_by_assistant_message
+                .get(&tool_use.assistant_message_id)
+                .and_then(|tool_uses| tool_uses.iter().position(|t| t.id == tool_use.id));
+            (tool_use.assistant_message_id, index)
+        });
+        tool_uses
+    }
+
     pub fn tool_uses_for_message(&self, id: MessageId, cx: &App) -> Vec<ToolUse> {
         let Some(tool_uses_for_message) = &self.tool_uses_by_assistant_message.get(&id) else {
             return Vec::new();
@@ -191,7 +260,9 @@ impl ToolUseState {
 
                 if let Some(pending_tool_use) = self.pending_tool_uses_by_id.get(&tool_use.id) {
                     match pending_tool_use.status {
-                        PendingToolUseStatus::Idle => ToolUseStatus::Pending,
+                        PendingToolUseStatus::Idle | PendingToolUseStatus::Preparing { .. } => {
+                            ToolUseStatus::Pending
+                        }
                         PendingToolUseStatus::NeedsConfirmation { .. } => {
                             ToolUseStatus::NeedsConfirmation
                         }
@@ -203,16 +274,32 @@ impl ToolUseState {
                             ToolUseStatus::InputStillStreaming
                         }
                     }
+                } else if self.interrupted_tool_use_ids.contains(&tool_use.id) {
+                    ToolUseStatus::Error("Interrupted when Zed quit".into())
                 } else {
                     ToolUseStatus::Pending
                 }
             })();
 
-            let (icon, needs_confirmation) =
+            let (icon, source, source_badge, needs_confirmation) =
                 if let Some(tool) = self.tools.read(cx).tool(&tool_use.name, cx) {
-                    (tool.icon(), tool.needs_confirmation(&tool_use.input, cx))
+                    (
+                        tool.icon(),
+                        tool.source(),
+                        tool.source_badge(),
+                        tool.needs_confirmation(&tool_use.input, cx),
+                    )
+                } else if let Some(server_id) = self.context_server_ids.get(&tool_use.id) {
+                    (
+                        IconName::Cog,
+                        ToolSource::ContextServer {
+                            id: server_id.clone(),
+                        },
+                        None,
+                        false,
+                    )
                 } else {
-                    (IconName::Cog, false)
+                    (IconName::Cog, ToolSource::Native, None, false)
                 };
 
             tool_uses.push(ToolUse {
@@ -227,6 +314,8 @@ impl ToolUseState {
                 input: tool_use.input.clone(),
                 status,
                 icon,
+                source,
+                source_badge,
                 needs_confirmation,
             })
         }
@@ -234,6 +323,27 @@ impl ToolUseState {
         tool_uses
     }
 
+    /// Returns the tool uses that were running, or waiting to, when the thread was last saved
+    /// before Zed quit, and that haven't been run again or marked as failed since.
+    pub fn interrupted_tool_uses(&self) -> Vec<(MessageId, &LanguageModelToolUse)> {
+        let mut interrupted_tool_uses = self
+            .tool_uses_by_assistant_message
+            .iter()
+            .flat_map(|(message_id, tool_uses)| {
+                tool_uses
+                    .iter()
+                    .map(move |tool_use| (*message_id, tool_use))
+            })
+            .filter(|(_, tool_use)| {
+                self.interrupted_tool_use_ids.contains(&tool_use.id)
+                    && !self.tool_results.contains_key(&tool_use.id)
+                    && !self.pending_tool_uses_by_id.contains_key(&tool_use.id)
+            })
+            .collect::<Vec<_>>();
+        interrupted_tool_uses.sort_by_key(|(message_id, _)| *message_id);
+        interrupted_tool_uses
+    }
+
     pub fn tool_ui_label(
         &self,
         tool_name: &str,
@@ -269,6 +379,13 @@ impl ToolUseState {
             .collect()
     }
 
+    /// The tool uses the model requested in a message, in the order it requested them.
+    pub fn requested_tool_uses(&self, assistant_message_id: MessageId) -> &[LanguageModelToolUse] {
+        self.tool_uses_by_assistant_message
+            .get(&assistant_message_id)
+            .map_or(&[], |tool_uses| tool_uses.as_slice())
+    }
+
     pub fn message_has_tool_results(&self, assistant_message_id: MessageId) -> bool {
         self.tool_uses_by_assistant_message
             .get(&assistant_message_id)
@@ -282,6 +399,24 @@ impl ToolUseState {
         self.tool_results.get(tool_use_id)
     }
 
+    pub fn tool_result_summary(
+        &self,
+        tool_use_id: &LanguageModelToolUseId,
+    ) -> Option<&LanguageModelToolResultContent> {
+        self.tool_result_summaries.get(tool_use_id)
+    }
+
+    pub fn insert_tool_result_summary(
+        &mut self,
+        tool_use_id: LanguageModelToolUseId,
+        summary: String,
+    ) {
+        self.tool_result_summaries.insert(
+            tool_use_id,
+            LanguageModelToolResultContent::Text(summary.into()),
+        );
+    }
+
     pub fn tool_result_card(&self, tool_use_id: &LanguageModelToolUseId) -> Option<&AnyToolCard> {
         self.tool_result_cards.get(tool_use_id)
     }
@@ -306,6 +441,7 @@ impl ToolUseState {
             .entry(assistant_message_id)
             .or_default();
 
+        self.interrupted_tool_use_ids.remove(&tool_use.id);
         let mut existing_tool_use_found = false;
 
         for existing_tool_use in tool_uses.iter_mut() {
@@ -322,6 +458,13 @@ impl ToolUseState {
         let status = if tool_use.is_input_complete {
             self.tool_use_metadata_by_id
                 .insert(tool_use.id.clone(), metadata);
+            if let Some(tool) = self.tools.read(cx).tool(&tool_use.name, cx) {
+                if let ToolSource::ContextServer { id } = tool.source() {
+                    self.context_server_ids.insert(tool_use.id.clone(), id);
+                    self.context_server_tool_names
+                        .insert(tool_use.id.clone(), tool.original_name());
+                }
+            }
 
             PendingToolUseStatus::Idle
         } else {
@@ -352,6 +495,28 @@ impl ToolUseState {
         ui_text
     }
 
+    /// Marks a tool use as waiting for `task`, which lets its tool rewrite its input.
+    pub fn prepare_pending_tool(&mut self, tool_use_id: &LanguageModelToolUseId, task: Task<()>) {
+        if let Some(tool_use) = self.pending_tool_uses_by_id.get_mut(tool_use_id) {
+            tool_use.status = PendingToolUseStatus::Preparing {
+                _task: task.shared(),
+            };
+        }
+    }
+
+    /// Replaces the input of a tool use with the one its tool rewrote it to, and returns the tool
+    /// use, unless it was cancelled in the meantime.
+    pub fn set_prepared_input(
+        &mut self,
+        tool_use_id: &LanguageModelToolUseId,
+        input: serde_json::Value,
+    ) -> Option<PendingToolUse> {
+        let tool_use = self.pending_tool_uses_by_id.get_mut(tool_use_id)?;
+        tool_use.input = input;
+        tool_use.status = PendingToolUseStatus::Idle;
+        Some(tool_use.clone())
+    }
+
     pub fn run_pending_tool(
         &mut self,
         tool_use_id: LanguageModelToolUseId,
@@ -396,6 +561,7 @@ impl ToolUseState {
         configured_model: Option<&ConfiguredModel>,
     ) -> Option<PendingToolUse> {
         let metadata = self.tool_use_metadata_by_id.remove(&tool_use_id);
+        self.interrupted_tool_use_ids.remove(&tool_use_id);
 
         telemetry::event!(
             "Agent Tool Finished",
@@ -512,7 +678,6 @@ impl ToolUseState {
 pub struct PendingToolUse {
     pub id: LanguageModelToolUseId,
     /// The ID of the Assistant message in which the tool use was requested.
-    #[allow(unused)]
     pub assistant_message_id: MessageId,
     pub name: Arc<str>,
     pub ui_text: Arc<str>,
@@ -533,8 +698,14 @@ pub struct Confirmation {
 pub enum PendingToolUseStatus {
     InputStillStreaming,
     Idle,
+    /// The tool is rewriting the input, before the call is confirmed or run.
+    Preparing {
+        _task: Shared<Task<()>>,
+    },
     NeedsConfirmation(Arc<Confirmation>),
-    Running { _task: Shared<Task<()>> },
+    Running {
+        _task: Shared<Task<()>>,
+    },
     Error(#[allow(unused)] Arc<str>),
 }
 
@@ -558,3 +729,355 @@ pub struct ToolUseMetadata {
     pub thread_id: ThreadId,
     pub prompt_id: PromptId,
 }
+
+/// Truncates text output larger than `max_bytes` so that it doesn't blow the context window.
+///
+/// The full output is written to a temporary file, and its path is mentioned in the truncated
+/// result so that the model (or the user) can page through it with other tools. The file outlives
+/// the thread, so it's redacted with `redactor` first.
+pub async fn overflow_large_tool_output(
+    output: ToolResultOutput,
+    tool_use_id: &LanguageModelToolUseId,
+    max_bytes: usize,
+    redactor: Option<Arc<SecretRedactor>>,
+    fs: Arc<dyn Fs>,
+) -> ToolResultOutput {
+    let ToolResultOutput {
+        content: ToolResultContent::Text(text),
+        output,
+    } = output
+    else {
+        return output;
+    };
+    if text.len() <= max_bytes {
+        return ToolResultOutput {
+            content: ToolResultContent::Text(text),
+            output,
+        };
+    }
+
+    let truncated = truncate_lines_to_byte_limit(&text, max_bytes);
+    let overflow_path = assistant_tool::tool_output_overflow_path(tool_use_id);
+    let saved = async {
+        if let Some(parent) = overflow_path.parent() {
+            fs.create_dir(parent).await?;
+        }
+        let saved_text = match &redactor {
+            Some(redactor) => redactor.redact(&text).into_owned(),
+            None => text.clone(),
+        };
+        fs.atomic_write(overflow_path.clone(), saved_text).await
+    }
+    .await
+    .log_err()
+    .is_some();
+
+    let mut content = format!(
+        "Tool output was {} bytes, which exceeds the limit of {} bytes. ",
+        text.len(),
+        max_bytes
+    );
+    if saved {
+        content.push_str(&format!(
+            "The full output was saved to `{}`; use the `read_tool_output` tool with the \
+            tool use ID `{}` to read the rest. ",
+            overflow_path.display(),
+            tool_use_id
+        ));
+    }
+    content.push_str(&format!(
+        "The first {} bytes:\n\n{}",
+        truncated.len(),
+        truncated
+    ));
+
+    ToolResultOutput {
+        content: ToolResultContent::Text(content),
+        output,
+    }
+}
+
+/// Replaces an image returned by a tool with a text description when `model` can't view images.
+pub async fn describe_image_tool_output(
+    output: ToolResultOutput,
+    model: &Arc<dyn LanguageModel>,
+    fallback: ImageDescriptionFallback,
+    cx: &mut AsyncApp,
+) -> Result<ToolResultOutput> {
+    let ToolResultOutput {
+        content: ToolResultContent::Image(image),
+        output,
+    } = output
+    else {
+        return Ok(output);
+    };
+    if model.supports_images() {
+        return Ok(ToolResultOutput {
+            content: ToolResultContent::Image(image),
+            output,
+        });
+    }
+
+    let description = match fallback {
+        ImageDescriptionFallback::None => bail!(
+            "The tool returned an image, but {} can't view images. \
+            Set `agent.image_description_fallback` to have images described instead.",
+            model.name().0
+        ),
+        ImageDescriptionFallback::Model(selection) => {
+            let selected_model = SelectedModel {
+                provider: LanguageModelProviderId::from(selection.provider.0),
+                model: LanguageModelId::from(selection.model),
+            };
+            let describer = cx
+                .update(|cx| {
+                    LanguageModelRegistry::global(cx).update(cx, |registry, cx| {
+                        registry.select_model(&selected_model, cx)
+                    })
+                })?
+                .context("image description model is not available")?
+                .model;
+            describe_image_with_model(image, describer, cx).await?
+        }
+        ImageDescriptionFallback::Command { command, args } => {
+            describe_image_with_command(&image, &command, &args).await?
+        }
+    };
+
+    Ok(ToolResultOutput {
+        content: ToolResultContent::Text(format!(
+            "The tool returned an image, which was replaced with the following description \
+            because {} can't view images:\n\n{}",
+            model.name().0,
+            description.trim()
+        )),
+        output,
+    })
+}
+
+/// Asks the configured model to summarize the text `output` of a tool when it's long, returning
+/// the summary to send to the model of the thread in place of the output.
+pub async fn summarize_tool_output(
+    output: &ToolResultOutput,
+    tool_name: &str,
+    input: &serde_json::Value,
+    summarization: &ToolResultSummarization,
+    cx: &mut AsyncApp,
+) -> Result<Option<String>> {
+    let ToolResultContent::Text(text) = &output.content else {
+        return Ok(None);
+    };
+    let tokens = estimate_text_tokens(text);
+    if tokens <= summarization.above_tokens {
+        return Ok(None);
+    }
+
+    let selected_model = SelectedModel {
+        provider: LanguageModelProviderId::from(summarization.model.provider.0.clone()),
+        model: LanguageModelId::from(summarization.model.model.clone()),
+    };
+    let summarizer = cx
+        .update(|cx| {
+            LanguageModelRegistry::global(cx).update(cx, |registry, cx| {
+                registry.select_model(&selected_model, cx)
+            })
+        })?
+        .context("tool result summarization model is not available")?
+        .model;
+    let request = LanguageModelRequest {
+        messages: vec![LanguageModelRequestMessage {
+            role: Role::User,
+            content: vec![
+                format!(
+                    "An AI agent called the `{tool_name}` tool with the input {input}, and it \
+                    returned the output below. Summarize the output for the agent. Keep every \
+                    detail it may need to act on, such as identifiers, names, numbers, paths, \
+                    URLs and errors, and drop repetition and boilerplate. Reply with the \
+                    summary only.\n\n{text}"
+                )
+                .into(),
+            ],
+            cache: false,
+        }],
+        ..Default::default()
+    };
+
+    let mut stream = summarizer.stream_completion_text(request, cx).await?.stream;
+    let mut summary = String::new();
+    while let Some(chunk) = stream.next().await {
+        summary.push_str(&chunk?);
+    }
+    Ok(Some(format!(
+        "The tool's output was about {tokens} tokens long, so it was replaced with this \
+        summary:\n\n{}",
+        summary.trim()
+    )))
+}
+
+async fn describe_image_with_model(
+    image: LanguageModelImage,
+    model: Arc<dyn LanguageModel>,
+    cx: &AsyncApp,
+) -> Result<String> {
+    if !model.supports_images() {
+        bail!(
+            "{} was configured to describe images, but it can't view images either",
+            model.name().0
+        );
+    }
+
+    let request = LanguageModelRequest {
+        messages: vec![LanguageModelRequestMessage {
+            role: Role::User,
+            content: vec![
+                MessageContent::Image(image),
+                "Describe this image in detail for someone who can't see it. \
+                Transcribe any text it contains verbatim."
+                    .into(),
+            ],
+            cache: false,
+        }],
+        ..Default::default()
+    };
+
+    let mut stream = model.stream_completion_text(request, cx).await?.stream;
+    let mut description = String::new();
+    while let Some(chunk) = stream.next().await {
+        description.push_str(&chunk?);
+    }
+    Ok(description)
+}
+
+async fn describe_image_with_command(
+    image: &LanguageModelImage,
+    command: &str,
+    args: &[String],
+) -> Result<String> {
+    let png = base64::engine::general_purpose::STANDARD
+        .decode(image.source.as_bytes())
+        .context("decoding image")?;
+    let mut child = util::command::new_smol_command(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running image description command `{command}`"))?;
+
+    // Write stdin while reading output so that large images can't deadlock on a full pipe.
+    let stdin = child.stdin.take();
+    let write_stdin = async move {
+        if let Some(mut stdin) = stdin {
+            stdin.write_all(&png).await?;
+        }
+        anyhow::Ok(())
+    };
+    let (write_result, output) = futures::join!(write_stdin, child.output());
+    let output = output?;
+    if !output.status.success() {
+        bail!(
+            "image description command `{command}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    write_result?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assistant_settings::LanguageModelSelection;
+    use gpui::TestAppContext;
+    use language_model::fake_provider::FakeLanguageModel;
+    use project::FakeFs;
+
+    #[gpui::test]
+    async fn test_overflow_large_tool_output(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        let tool_use_id = LanguageModelToolUseId::from("tool_1");
+
+        let small = overflow_large_tool_output(
+            "short".to_string().into(),
+            &tool_use_id,
+            16,
+            None,
+            fs.clone(),
+        )
+        .await;
+        assert_eq!(small.content, ToolResultContent::Text("short".into()));
+
+        let text = "line one\nline two\nline three\n".to_string();
+        let large =
+            overflow_large_tool_output(text.clone().into(), &tool_use_id, 16, None, fs.clone())
+                .await;
+        let content = large.content.as_str().unwrap();
+        assert!(content.contains("exceeds the limit of 16 bytes"));
+        assert!(content.ends_with("line one\n"));
+
+        let overflow_path = paths::temp_dir().join("tool_output").join("tool_1.txt");
+        assert!(content.contains(&overflow_path.display().to_string()));
+        assert_eq!(fs.load(&overflow_path).await.unwrap(), text);
+
+        let text = "export API_KEY=abcdef123456\nline two\n".to_string();
+        let redactor = Arc::new(SecretRedactor::new(&[]));
+        overflow_large_tool_output(text.into(), &tool_use_id, 16, Some(redactor), fs.clone()).await;
+        assert_eq!(
+            fs.load(&overflow_path).await.unwrap(),
+            "export API_KEY=[REDACTED]\nline two\n"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_describe_image_tool_output_without_fallback(cx: &mut TestAppContext) {
+        let model: Arc<dyn LanguageModel> = Arc::new(FakeLanguageModel::default());
+        let mut cx = cx.to_async();
+
+        let text = describe_image_tool_output(
+            "text".to_string().into(),
+            &model,
+            ImageDescriptionFallback::None,
+            &mut cx,
+        )
+        .await
+        .unwrap();
+        assert_eq!(text.content, ToolResultContent::Text("text".into()));
+
+        let image = ToolResultOutput {
+            content: ToolResultContent::Image(LanguageModelImage::empty()),
+            output: None,
+        };
+        let error =
+            describe_image_tool_output(image, &model, ImageDescriptionFallback::None, &mut cx)
+                .await
+                .unwrap_err();
+        assert!(error.to_string().contains("can't view images"));
+    }
+
+    #[gpui::test]
+    async fn test_summarize_tool_output(cx: &mut TestAppContext) {
+        cx.update(|cx| LanguageModelRegistry::test(cx));
+        let summarization = ToolResultSummarization {
+            model: LanguageModelSelection {
+                provider: "fake".to_string().into(),
+                model: "missing".to_string(),
+            },
+            above_tokens: 10,
+        };
+        let input = serde_json::json!({ "query": "zed" });
+        let mut cx = cx.to_async();
+
+        let short = "x".repeat(40).into();
+        let summary = summarize_tool_output(&short, "search", &input, &summarization, &mut cx)
+            .await
+            .unwrap();
+        assert_eq!(summary, None);
+
+        let long = "x".repeat(41).into();
+        let error = summarize_tool_output(&long, "search", &input, &summarization, &mut cx)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("model is not available"));
+    }
+}
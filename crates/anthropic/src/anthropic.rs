@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::sync::Arc;
 
 use anyhow::{Context as _, Result, anyhow};
 use chrono::{DateTime, Utc};
@@ -635,7 +636,7 @@ pub struct ImageSource {
 pub struct Tool {
     pub name: String,
     pub description: String,
-    pub input_schema: serde_json::Value,
+    pub input_schema: Arc<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
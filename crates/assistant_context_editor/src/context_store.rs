@@ -831,6 +831,10 @@ impl ContextStore {
                             .read(cx)
                             .get_running_server(server_id)
                         {
+                            let has_prompts = context_server_manager
+                                .read(cx)
+                                .capabilities(server_id)
+                                .is_some_and(|capabilities| capabilities.prompts);
                             let context_server_manager = context_server_manager.clone();
                             cx.spawn({
                                 let server = server.clone();
@@ -840,7 +844,7 @@ impl ContextStore {
                                         return;
                                     };
 
-                                    if protocol.capable(context_server::protocol::ServerCapability::Prompts) {
+                                    if has_prompts {
                                         if let Some(prompts) = protocol.list_prompts().await.log_err() {
                                             let slash_command_ids = prompts
                                                 .into_iter()
@@ -882,6 +886,12 @@ impl ContextStore {
                     _ => {}
                 }
             }
+            project::context_server_store::Event::UntrustedProjectServers { .. }
+            | project::context_server_store::Event::UntrustedServers
+            | project::context_server_store::Event::PackageUpdateAvailable { .. }
+            | project::context_server_store::Event::ServerOfflineChanged { .. }
+            | project::context_server_store::Event::ServerRenamed { .. }
+            | project::context_server_store::Event::ToolCall { .. } => {}
         }
     }
 }
@@ -69,6 +69,12 @@ pub struct AgentProfile {
     pub tools: IndexMap<Arc<str>, bool>,
     pub enable_all_context_servers: bool,
     pub context_servers: IndexMap<Arc<str>, ContextServerPreset>,
+    /// Once a thread's requests grow past this many tokens, the results of its oldest context
+    /// server tool calls are replaced with short notes in the requests, until they're back under
+    /// it. The current turn's results are always kept.
+    pub compact_tool_calls_above_tokens: Option<usize>,
+    /// The context servers that run while this profile is selected, or `None` for all of them.
+    pub active_context_servers: Option<Vec<Arc<str>>>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -40,6 +40,67 @@ pub enum NotifyWhenAgentWaiting {
     Never,
 }
 
+/// What to do when the output of a context server tool looks like it's trying to instruct the
+/// model.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptInjectionScan {
+    /// Don't scan tool output.
+    #[default]
+    Off,
+    /// Send the output to the model, and show a warning in the tool's card.
+    Warn,
+    /// Ask before sending the output to the model.
+    Confirm,
+}
+
+/// How to turn an image returned by a tool into text for models that can't view images.
+#[derive(Clone, Default, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageDescriptionFallback {
+    /// Fail the tool call.
+    #[default]
+    None,
+    /// Ask a vision-capable model to describe the image.
+    Model(LanguageModelSelection),
+    /// Run a local command, such as `tesseract stdin stdout`, and use its output.
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// Which model summarizes long outputs of context server tools, and from what length.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ToolResultSummarization {
+    /// The model that writes the summaries, ideally a fast and cheap one.
+    pub model: LanguageModelSelection,
+    /// Outputs estimated to be longer than this many tokens are summarized.
+    #[serde(default = "default_summarize_above_tokens")]
+    pub above_tokens: usize,
+}
+
+fn default_summarize_above_tokens() -> usize {
+    2000
+}
+
+/// Limits on the context server tool calls of a thread. When a limit is reached, the agent
+/// pauses and asks whether to continue, which allows another budget of the same size.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ToolCallBudget {
+    /// The most tool calls the agent makes.
+    #[serde(default)]
+    pub max_calls: Option<u32>,
+    /// The most seconds the agent's tool calls take, added up.
+    #[serde(default)]
+    pub max_seconds: Option<u64>,
+    /// The most tokens the outputs of the agent's tool calls take up, added up and estimated
+    /// from their length.
+    #[serde(default)]
+    pub max_result_tokens: Option<u64>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(tag = "name", rename_all = "snake_case")]
 #[schemars(deny_unknown_fields)]
@@ -101,6 +162,25 @@ pub struct AssistantSettings {
     pub model_parameters: Vec<LanguageModelParameters>,
     pub preferred_completion_mode: CompletionMode,
     pub enable_feedback: bool,
+    pub max_tool_output_bytes: usize,
+    pub read_only_tools: bool,
+    pub max_tool_image_dimension: u32,
+    pub image_description_fallback: ImageDescriptionFallback,
+    pub tool_tracing_endpoint: Option<String>,
+    pub record_tool_calls: bool,
+    pub capture_requests: bool,
+    pub redact_secrets: bool,
+    pub secret_patterns: Vec<String>,
+    pub prompt_injection_scan: PromptInjectionScan,
+    pub summarize_tool_results: Option<ToolResultSummarization>,
+    pub suggest_context_servers: bool,
+    pub tool_call_budget: Option<ToolCallBudget>,
+    pub max_identical_tool_calls: usize,
+    pub send_tool_result_deltas: bool,
+    pub dangerous_tool_arguments: IndexMap<String, String>,
+    pub read_only_tool_names: Vec<String>,
+    pub tool_call_notification_seconds: u64,
+    pub inline_assist_mcp_tools: bool,
 }
 
 impl AssistantSettings {
@@ -133,6 +213,30 @@ impl AssistantSettings {
             model,
         });
     }
+
+    /// Returns the tool permissions in effect, after merging every settings file.
+    pub fn tool_permissions(&self) -> ToolPermissions {
+        ToolPermissions {
+            always_allow_tool_actions: self.always_allow_tool_actions,
+            read_only_tools: self.read_only_tools,
+            profiles: self
+                .profiles
+                .iter()
+                .map(|(id, profile)| (id.clone(), profile.clone().into()))
+                .collect(),
+        }
+    }
+}
+
+/// The settings that decide which tools the agent can use and whether it asks before using
+/// them, in a form that can be shared as a file.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ToolPermissions {
+    pub always_allow_tool_actions: bool,
+    pub read_only_tools: bool,
+    /// The profiles, with the tools each one enables, including the tools of each context
+    /// server.
+    pub profiles: IndexMap<AgentProfileId, AgentProfileContent>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
@@ -275,6 +379,25 @@ impl AssistantSettingsContent {
                     model_parameters: Vec::new(),
                     preferred_completion_mode: None,
                     enable_feedback: None,
+                    max_tool_output_bytes: None,
+                    read_only_tools: None,
+                    max_tool_image_dimension: None,
+                    image_description_fallback: None,
+                    tool_tracing_endpoint: None,
+                    record_tool_calls: None,
+                    capture_requests: None,
+                    redact_secrets: None,
+                    secret_patterns: None,
+                    prompt_injection_scan: None,
+                    summarize_tool_results: None,
+                    suggest_context_servers: None,
+                    tool_call_budget: None,
+                    max_identical_tool_calls: None,
+                    send_tool_result_deltas: None,
+                    dangerous_tool_arguments: None,
+                    read_only_tool_names: None,
+                    tool_call_notification_seconds: None,
+                    inline_assist_mcp_tools: None,
                 },
                 VersionedAssistantSettingsContent::V2(ref settings) => settings.clone(),
             },
@@ -306,6 +429,25 @@ impl AssistantSettingsContent {
                 model_parameters: Vec::new(),
                 preferred_completion_mode: None,
                 enable_feedback: None,
+                max_tool_output_bytes: None,
+                read_only_tools: None,
+                max_tool_image_dimension: None,
+                image_description_fallback: None,
+                tool_tracing_endpoint: None,
+                record_tool_calls: None,
+                capture_requests: None,
+                redact_secrets: None,
+                secret_patterns: None,
+                prompt_injection_scan: None,
+                summarize_tool_results: None,
+                suggest_context_servers: None,
+                tool_call_budget: None,
+                max_identical_tool_calls: None,
+                send_tool_result_deltas: None,
+                dangerous_tool_arguments: None,
+                read_only_tool_names: None,
+                tool_call_notification_seconds: None,
+                inline_assist_mcp_tools: None,
             },
             None => AssistantSettingsContentV2::default(),
         }
@@ -504,6 +646,14 @@ impl AssistantSettingsContent {
         .ok();
     }
 
+    pub fn set_read_only_tools(&mut self, read_only: bool) {
+        self.v2_setting(|setting| {
+            setting.read_only_tools = Some(read_only);
+            Ok(())
+        })
+        .ok();
+    }
+
     pub fn set_single_file_review(&mut self, allow: bool) {
         self.v2_setting(|setting| {
             setting.single_file_review = Some(allow);
@@ -531,30 +681,61 @@ impl AssistantSettingsContent {
                 bail!("profile with ID '{profile_id}' already exists");
             }
 
-            profiles.insert(
-                profile_id,
-                AgentProfileContent {
-                    name: profile.name.into(),
-                    tools: profile.tools,
-                    enable_all_context_servers: Some(profile.enable_all_context_servers),
-                    context_servers: profile
-                        .context_servers
-                        .into_iter()
-                        .map(|(server_id, preset)| {
-                            (
-                                server_id,
-                                ContextServerPresetContent {
-                                    tools: preset.tools,
-                                },
-                            )
-                        })
-                        .collect(),
-                },
-            );
+            profiles.insert(profile_id, profile.into());
 
             Ok(())
         })
     }
+
+    /// Applies shared tool permissions, replacing any profiles that have the same IDs.
+    pub fn set_tool_permissions(&mut self, permissions: ToolPermissions) -> Result<()> {
+        let mut applied = false;
+        self.v2_setting(|settings| {
+            settings.always_allow_tool_actions = Some(permissions.always_allow_tool_actions);
+            settings.read_only_tools = Some(permissions.read_only_tools);
+            settings
+                .profiles
+                .get_or_insert_default()
+                .extend(permissions.profiles);
+            applied = true;
+            Ok(())
+        })?;
+        if !applied {
+            bail!("tool permissions can only be imported into version 2 of the agent settings");
+        }
+        Ok(())
+    }
+
+    /// Moves what the profiles say about a context server over to its new id, after the user
+    /// renamed it.
+    pub fn rename_context_server(&mut self, old_id: &str, new_id: Arc<str>) {
+        self.v2_setting(|settings| {
+            for profile in settings
+                .profiles
+                .iter_mut()
+                .flat_map(|profiles| profiles.values_mut())
+            {
+                if !profile.context_servers.contains_key(&new_id) {
+                    if let Some((index, _, preset)) =
+                        profile.context_servers.shift_remove_full(old_id)
+                    {
+                        profile
+                            .context_servers
+                            .shift_insert(index, new_id.clone(), preset);
+                    }
+                }
+                if let Some(active_servers) = &mut profile.active_context_servers {
+                    for id in active_servers.iter_mut() {
+                        if id.as_ref() == old_id {
+                            *id = new_id.clone();
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+        .ok();
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, JsonSchema, Debug)]
@@ -589,6 +770,25 @@ impl Default for VersionedAssistantSettingsContent {
             model_parameters: Vec::new(),
             preferred_completion_mode: None,
             enable_feedback: None,
+            max_tool_output_bytes: None,
+            read_only_tools: None,
+            max_tool_image_dimension: None,
+            image_description_fallback: None,
+            tool_tracing_endpoint: None,
+            record_tool_calls: None,
+            capture_requests: None,
+            redact_secrets: None,
+            secret_patterns: None,
+            prompt_injection_scan: None,
+            summarize_tool_results: None,
+            suggest_context_servers: None,
+            tool_call_budget: None,
+            max_identical_tool_calls: None,
+            send_tool_result_deltas: None,
+            dangerous_tool_arguments: None,
+            read_only_tool_names: None,
+            tool_call_notification_seconds: None,
+            inline_assist_mcp_tools: None,
         })
     }
 }
@@ -667,6 +867,115 @@ pub struct AssistantSettingsContentV2 {
     ///
     /// Default: true
     enable_feedback: Option<bool>,
+    /// The maximum size, in bytes, of a tool's text output that is sent to the model.
+    ///
+    /// Larger outputs are truncated, and the full output is written to a temporary
+    /// file whose path is included in the result.
+    ///
+    /// Default: 65536
+    max_tool_output_bytes: Option<usize>,
+    /// Whether the agent is restricted to tools that don't modify the project or the system.
+    ///
+    /// When enabled, tools that may perform edits are hidden from the model, and every
+    /// other tool requires confirmation before running.
+    ///
+    /// Default: false
+    read_only_tools: Option<bool>,
+    /// The maximum width and height, in pixels, of images returned by context server tools.
+    ///
+    /// Larger images are downscaled before being sent to the model to reduce token usage.
+    ///
+    /// Default: 1024
+    max_tool_image_dimension: Option<u32>,
+    /// How to handle images returned by tools when the selected model can't view images.
+    ///
+    /// The image can be described by a vision-capable model, or by a local command that
+    /// receives the PNG-encoded image on stdin and prints a description (e.g. an OCR tool).
+    ///
+    /// Default: none
+    image_description_fallback: Option<ImageDescriptionFallback>,
+    /// The base URL of an OpenTelemetry collector, such as `http://localhost:4318`, to export
+    /// spans for tool discovery, confirmation, execution and result processing to over OTLP/HTTP.
+    ///
+    /// Default: null
+    tool_tracing_endpoint: Option<String>,
+    /// Whether to record the calls made to context server tools, and their responses, to a file
+    /// for each thread, so that they can be replayed with a context server's `replay` setting.
+    ///
+    /// Default: false
+    record_tool_calls: Option<bool>,
+    /// Whether to keep the most recent requests sent to the model in each thread, so that they
+    /// can be replayed in a scratch thread.
+    ///
+    /// Default: false
+    capture_requests: Option<bool>,
+    /// Whether to redact secrets, such as API keys, tokens and private keys, from the inputs and
+    /// outputs of context server tools before they're saved in threads, logged or recorded. The
+    /// unredacted inputs are still sent to the server.
+    ///
+    /// Default: true
+    redact_secrets: Option<bool>,
+    /// Regular expressions matching additional secrets to redact when `redact_secrets` is on.
+    ///
+    /// Default: []
+    secret_patterns: Option<Vec<String>>,
+    /// What to do when the output of a context server tool contains text that looks like a prompt
+    /// injection, such as instructions to ignore previous instructions, hidden Unicode characters or
+    /// embedded data URIs.
+    ///
+    /// Default: off
+    prompt_injection_scan: Option<PromptInjectionScan>,
+    /// Summarizes long outputs of context server tools with another model before they're sent
+    /// to the model of the thread. The full outputs are still shown in the thread.
+    ///
+    /// Default: null
+    summarize_tool_results: Option<ToolResultSummarization>,
+    /// Whether to suggest context servers that look useful for a project, such as the GitHub
+    /// server when the project has a GitHub remote.
+    ///
+    /// Default: false
+    suggest_context_servers: Option<bool>,
+    /// Limits on the number, duration and output size of the context server tool calls in each
+    /// thread, to stop runaway loops against paid APIs.
+    ///
+    /// Default: null
+    tool_call_budget: Option<ToolCallBudget>,
+    /// The most times in a row the agent can call a tool with the same input. Further identical
+    /// calls aren't run, and the model is told that they were suppressed. Set to 0 to run every
+    /// call, since calling a read-only tool again, such as to poll a build's status, is often
+    /// what the model means to do.
+    ///
+    /// Default: 0
+    max_identical_tool_calls: Option<usize>,
+    /// Whether to send the model only what changed in the result of a context server tool call,
+    /// when it made the same call earlier in the thread, such as to poll a build's status. The
+    /// full results are still shown in the thread.
+    ///
+    /// Default: false
+    send_tool_result_deltas: Option<bool>,
+    /// Regular expressions that make the agent ask before running a tool call whose input
+    /// matches them, even when the tool's calls are otherwise allowed, keyed by the name shown
+    /// when asking. Rules are merged with the default ones, and a rule with an empty pattern is
+    /// turned off.
+    ///
+    /// Default: rules for `rm -rf`, `DROP TABLE`, `--force` and credentials
+    dangerous_tool_arguments: Option<IndexMap<String, String>>,
+    /// Tools that stay available when the agent is restricted to read-only tools, even though
+    /// they don't say that they're read-only, by the name the model sees.
+    ///
+    /// Default: []
+    read_only_tool_names: Option<Vec<String>>,
+    /// Show a notification when a context server tool call that took at least this many seconds
+    /// finishes while the agent panel isn't focused, with where it's shown following
+    /// `notify_when_agent_waiting`. Set to 0 to never show one.
+    ///
+    /// Default: 30
+    tool_call_notification_seconds: Option<u64>,
+    /// Whether inline assists can call the tools of MCP servers, asking in the inline prompt
+    /// whenever the agent would have to ask before calling them.
+    ///
+    /// Default: false
+    inline_assist_mcp_tools: Option<bool>,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
@@ -750,6 +1059,17 @@ pub struct AgentProfileContent {
     pub enable_all_context_servers: Option<bool>,
     #[serde(default)]
     pub context_servers: IndexMap<Arc<str>, ContextServerPresetContent>,
+    /// Once a thread's requests grow past this many tokens, the results of its oldest context
+    /// server tool calls are replaced with short notes in the requests, until they're back under
+    /// it. The current turn's results are always kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_tool_calls_above_tokens: Option<usize>,
+    /// The context servers that run while this profile is selected. Servers that aren't listed
+    /// are stopped when switching to the profile.
+    ///
+    /// Default: all configured context servers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_context_servers: Option<Vec<Arc<str>>>,
 }
 
 #[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize, JsonSchema)]
@@ -757,6 +1077,30 @@ pub struct ContextServerPresetContent {
     pub tools: IndexMap<Arc<str>, bool>,
 }
 
+impl From<AgentProfile> for AgentProfileContent {
+    fn from(profile: AgentProfile) -> Self {
+        Self {
+            name: profile.name.into(),
+            tools: profile.tools,
+            enable_all_context_servers: Some(profile.enable_all_context_servers),
+            context_servers: profile
+                .context_servers
+                .into_iter()
+                .map(|(server_id, preset)| {
+                    (
+                        server_id,
+                        ContextServerPresetContent {
+                            tools: preset.tools,
+                        },
+                    )
+                })
+                .collect(),
+            compact_tool_calls_above_tokens: profile.compact_tool_calls_above_tokens,
+            active_context_servers: profile.active_context_servers,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, JsonSchema, Debug)]
 #[schemars(deny_unknown_fields)]
 pub struct AssistantSettingsContentV1 {
@@ -875,6 +1219,63 @@ impl Settings for AssistantSettings {
                 value.preferred_completion_mode,
             );
             merge(&mut settings.enable_feedback, value.enable_feedback);
+            merge(
+                &mut settings.max_tool_output_bytes,
+                value.max_tool_output_bytes,
+            );
+            merge(&mut settings.read_only_tools, value.read_only_tools);
+            merge(
+                &mut settings.max_tool_image_dimension,
+                value.max_tool_image_dimension,
+            );
+            merge(
+                &mut settings.image_description_fallback,
+                value.image_description_fallback,
+            );
+            settings.tool_tracing_endpoint = value
+                .tool_tracing_endpoint
+                .or(settings.tool_tracing_endpoint.take());
+            merge(&mut settings.record_tool_calls, value.record_tool_calls);
+            merge(&mut settings.capture_requests, value.capture_requests);
+            merge(&mut settings.redact_secrets, value.redact_secrets);
+            merge(&mut settings.secret_patterns, value.secret_patterns);
+            merge(
+                &mut settings.prompt_injection_scan,
+                value.prompt_injection_scan,
+            );
+            settings.summarize_tool_results = value
+                .summarize_tool_results
+                .or(settings.summarize_tool_results.take());
+            merge(
+                &mut settings.suggest_context_servers,
+                value.suggest_context_servers,
+            );
+            settings.tool_call_budget = value.tool_call_budget.or(settings.tool_call_budget.take());
+            merge(
+                &mut settings.max_identical_tool_calls,
+                value.max_identical_tool_calls,
+            );
+            merge(
+                &mut settings.send_tool_result_deltas,
+                value.send_tool_result_deltas,
+            );
+            if let Some(dangerous_tool_arguments) = value.dangerous_tool_arguments {
+                settings
+                    .dangerous_tool_arguments
+                    .extend(dangerous_tool_arguments);
+            }
+            merge(
+                &mut settings.read_only_tool_names,
+                value.read_only_tool_names,
+            );
+            merge(
+                &mut settings.tool_call_notification_seconds,
+                value.tool_call_notification_seconds,
+            );
+            merge(
+                &mut settings.inline_assist_mcp_tools,
+                value.inline_assist_mcp_tools,
+            );
 
             settings
                 .model_parameters
@@ -904,6 +1305,9 @@ impl Settings for AssistantSettings {
                                         )
                                     })
                                     .collect(),
+                                compact_tool_calls_above_tokens: profile
+                                    .compact_tool_calls_above_tokens,
+                                active_context_servers: profile.active_context_servers,
                             },
                         )
                     }));
@@ -1012,6 +1416,25 @@ mod tests {
                                 stream_edits: None,
                                 single_file_review: None,
                                 enable_feedback: None,
+                                max_tool_output_bytes: None,
+                                read_only_tools: None,
+                                max_tool_image_dimension: None,
+                                image_description_fallback: None,
+                                tool_tracing_endpoint: None,
+                                record_tool_calls: None,
+                                capture_requests: None,
+                                redact_secrets: None,
+                                secret_patterns: None,
+                                prompt_injection_scan: None,
+                                summarize_tool_results: None,
+                                suggest_context_servers: None,
+                                tool_call_budget: None,
+                                max_identical_tool_calls: None,
+                                send_tool_result_deltas: None,
+                                dangerous_tool_arguments: None,
+                                read_only_tool_names: None,
+                                tool_call_notification_seconds: None,
+                                inline_assist_mcp_tools: None,
                                 model_parameters: Vec::new(),
                                 preferred_completion_mode: None,
                             },
@@ -1037,6 +1460,56 @@ mod tests {
         assert!(!assistant_settings.agent.is_version_outdated());
     }
 
+    #[test]
+    fn test_tool_permissions() {
+        let mut settings = AssistantSettings::default();
+        settings.always_allow_tool_actions = true;
+        settings.profiles.insert(
+            AgentProfileId("reviewed".into()),
+            AgentProfile {
+                name: "Reviewed".into(),
+                tools: IndexMap::from_iter([(Arc::from("terminal"), false)]),
+                enable_all_context_servers: false,
+                context_servers: IndexMap::from_iter([(
+                    Arc::from("github"),
+                    ContextServerPreset {
+                        tools: IndexMap::from_iter([(Arc::from("create_issue"), true)]),
+                    },
+                )]),
+                compact_tool_calls_above_tokens: None,
+                active_context_servers: None,
+            },
+        );
+        let permissions = settings.tool_permissions();
+        let json = serde_json::to_string(&permissions).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ToolPermissions>(&json).unwrap(),
+            permissions
+        );
+
+        let mut content = AssistantSettingsContent::default();
+        content.set_tool_permissions(permissions.clone()).unwrap();
+        let AssistantSettingsContent {
+            inner: Some(AssistantSettingsContentInner::Versioned(versioned)),
+        } = content
+        else {
+            panic!("expected versioned settings");
+        };
+        let VersionedAssistantSettingsContent::V2(content) = *versioned else {
+            panic!("expected version 2 settings");
+        };
+        assert_eq!(content.always_allow_tool_actions, Some(true));
+        assert_eq!(content.read_only_tools, Some(false));
+        assert_eq!(content.profiles, Some(permissions.profiles.clone()));
+
+        let mut content = AssistantSettingsContent {
+            inner: Some(AssistantSettingsContentInner::Versioned(Box::new(
+                VersionedAssistantSettingsContent::V1(serde_json::from_str("{}").unwrap()),
+            ))),
+        };
+        assert!(content.set_tool_permissions(permissions).is_err());
+    }
+
     #[gpui::test]
     async fn test_load_settings_from_old_key(cx: &mut TestAppContext) {
         let fs = fs::FakeFs::new(cx.executor().clone());
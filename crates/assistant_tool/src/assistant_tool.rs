@@ -8,12 +8,14 @@ use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
 use gpui::AnyElement;
 use gpui::AnyWindowHandle;
 use gpui::Context;
+use gpui::ImageSource;
 use gpui::IntoElement;
 use gpui::Window;
 use gpui::{App, Entity, SharedString, Task, WeakEntity};
@@ -22,6 +24,7 @@ use language_model::LanguageModel;
 use language_model::LanguageModelImage;
 use language_model::LanguageModelRequest;
 use language_model::LanguageModelToolSchemaFormat;
+use language_model::LanguageModelToolUseId;
 use project::Project;
 use workspace::Workspace;
 
@@ -198,11 +201,41 @@ pub enum ToolSource {
     ContextServer { id: SharedString },
 }
 
+/// The name and icon of a tool's source, shown on the cards of its calls.
+#[derive(Clone)]
+pub struct ToolSourceBadge {
+    pub name: SharedString,
+    pub icon: Option<ImageSource>,
+}
+
+impl Debug for ToolSourceBadge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolSourceBadge")
+            .field("name", &self.name)
+            .field("has_icon", &self.icon.is_some())
+            .finish()
+    }
+}
+
+/// Returns the path where the full output of a tool use is saved when it's too large to be
+/// sent to the model.
+pub fn tool_output_overflow_path(tool_use_id: &LanguageModelToolUseId) -> PathBuf {
+    paths::temp_dir()
+        .join("tool_output")
+        .join(format!("{tool_use_id}.txt"))
+}
+
 /// A tool that can be used by a language model.
 pub trait Tool: 'static + Send + Sync {
     /// Returns the name of the tool.
     fn name(&self) -> String;
 
+    /// Returns the name the tool's source gives it, which differs from `name` when the tool is
+    /// offered to the model under another name.
+    fn original_name(&self) -> String {
+        self.name()
+    }
+
     /// Returns the description of the tool.
     fn description(&self) -> String;
 
@@ -214,15 +247,45 @@ pub trait Tool: 'static + Send + Sync {
         ToolSource::Native
     }
 
+    /// Returns how to show the tool's source on the cards of its calls, so that tools with the
+    /// same name from different sources can be told apart.
+    fn source_badge(&self) -> Option<ToolSourceBadge> {
+        None
+    }
+
     /// Returns true iff the tool needs the users's confirmation
     /// before having permission to run.
     fn needs_confirmation(&self, input: &serde_json::Value, cx: &App) -> bool;
 
+    /// Returns true if the tool may modify the project or the system it's running on,
+    /// e.g. by editing files or running commands.
+    fn may_perform_edits(&self) -> bool;
+
+    /// Returns true if running the tool again with the same input has no further effect, so
+    /// that a call that was interrupted can safely be run again.
+    fn is_idempotent(&self) -> bool {
+        !self.may_perform_edits()
+    }
+
+    /// Returns whether the tool may be offered to `model`.
+    fn is_available_to(&self, _model: &Arc<dyn LanguageModel>, _cx: &App) -> bool {
+        true
+    }
+
     /// Returns the JSON schema that describes the tool's input.
     fn input_schema(&self, _: LanguageModelToolSchemaFormat) -> Result<serde_json::Value> {
         Ok(serde_json::Value::Object(serde_json::Map::default()))
     }
 
+    /// Returns the same schema as [`Tool::input_schema`], for tools that keep their schemas
+    /// and can hand them out to every request without copying them.
+    fn shared_input_schema(
+        &self,
+        format: LanguageModelToolSchemaFormat,
+    ) -> Result<Arc<serde_json::Value>> {
+        self.input_schema(format).map(Arc::new)
+    }
+
     /// Returns markdown to be displayed in the UI for this tool.
     fn ui_text(&self, input: &serde_json::Value) -> String;
 
@@ -232,9 +295,22 @@ pub trait Tool: 'static + Send + Sync {
         self.ui_text(input)
     }
 
-    /// Runs the tool with the provided input.
+    /// Rewrites the input of a call before it's confirmed and run, so that the confirmation
+    /// shows what will run. Returns `None` when the tool doesn't rewrite its input, and callers
+    /// that get a task run the tool with the input it resolves to.
+    fn prepare_input(
+        self: Arc<Self>,
+        _input: serde_json::Value,
+        _cx: &mut App,
+    ) -> Option<Task<Result<serde_json::Value>>> {
+        None
+    }
+
+    /// Runs the tool with the provided input. The id of the tool use identifies the call, for
+    /// example to report the call's progress under.
     fn run(
         self: Arc<Self>,
+        tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
@@ -12,6 +12,7 @@ mod move_path_tool;
 mod now_tool;
 mod open_tool;
 mod read_file_tool;
+mod read_tool_output_tool;
 mod schema;
 mod templates;
 mod terminal_tool;
@@ -40,6 +41,7 @@ use crate::find_path_tool::FindPathTool;
 use crate::grep_tool::GrepTool;
 use crate::list_directory_tool::ListDirectoryTool;
 use crate::now_tool::NowTool;
+use crate::read_tool_output_tool::ReadToolOutputTool;
 use crate::thinking_tool::ThinkingTool;
 
 pub use edit_file_tool::{EditFileMode, EditFileToolInput};
@@ -63,6 +65,7 @@ pub fn init(http_client: Arc<HttpClientWithUrl>, cx: &mut App) {
     registry.register_tool(OpenTool);
     registry.register_tool(FindPathTool);
     registry.register_tool(ReadFileTool);
+    registry.register_tool(ReadToolOutputTool);
     registry.register_tool(GrepTool);
     registry.register_tool(ThinkingTool);
     registry.register_tool(FetchTool::new(http_client));
@@ -4,7 +4,7 @@ use assistant_tool::{ActionLog, Tool, ToolResult};
 use gpui::AnyWindowHandle;
 use gpui::{App, AppContext, Entity, Task};
 use language_model::LanguageModel;
-use language_model::{LanguageModelRequest, LanguageModelToolSchemaFormat};
+use language_model::{LanguageModelRequest, LanguageModelToolSchemaFormat, LanguageModelToolUseId};
 use project::Project;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -48,6 +48,10 @@ impl Tool for CopyPathTool {
         false
     }
 
+    fn may_perform_edits(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> String {
         include_str!("./copy_path_tool/description.md").into()
     }
@@ -73,6 +77,7 @@ impl Tool for CopyPathTool {
 
     fn run(
         self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         _request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
@@ -3,7 +3,9 @@ use anyhow::{Context as _, Result, anyhow};
 use assistant_tool::{ActionLog, Tool, ToolResult};
 use gpui::AnyWindowHandle;
 use gpui::{App, Entity, Task};
-use language_model::{LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat};
+use language_model::{
+    LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat, LanguageModelToolUseId,
+};
 use project::Project;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -37,6 +39,10 @@ impl Tool for CreateDirectoryTool {
         false
     }
 
+    fn may_perform_edits(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> String {
         include_str!("./create_directory_tool/description.md").into()
     }
@@ -60,6 +66,7 @@ impl Tool for CreateDirectoryTool {
 
     fn run(
         self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         _request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
@@ -3,7 +3,9 @@ use anyhow::{Context as _, Result, anyhow};
 use assistant_tool::{ActionLog, Tool, ToolResult};
 use futures::{SinkExt, StreamExt, channel::mpsc};
 use gpui::{AnyWindowHandle, App, AppContext, Entity, Task};
-use language_model::{LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat};
+use language_model::{
+    LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat, LanguageModelToolUseId,
+};
 use project::{Project, ProjectPath};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -37,6 +39,10 @@ impl Tool for DeletePathTool {
         false
     }
 
+    fn may_perform_edits(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> String {
         include_str!("./delete_path_tool/description.md").into()
     }
@@ -58,6 +64,7 @@ impl Tool for DeletePathTool {
 
     fn run(
         self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         _request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
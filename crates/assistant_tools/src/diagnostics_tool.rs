@@ -3,7 +3,9 @@ use anyhow::{Result, anyhow};
 use assistant_tool::{ActionLog, Tool, ToolResult};
 use gpui::{AnyWindowHandle, App, Entity, Task};
 use language::{DiagnosticSeverity, OffsetRangeExt};
-use language_model::{LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat};
+use language_model::{
+    LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat, LanguageModelToolUseId,
+};
 use project::Project;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -50,6 +52,10 @@ impl Tool for DiagnosticsTool {
         false
     }
 
+    fn may_perform_edits(&self) -> bool {
+        false
+    }
+
     fn description(&self) -> String {
         include_str!("./diagnostics_tool/description.md").into()
     }
@@ -78,6 +84,7 @@ impl Tool for DiagnosticsTool {
 
     fn run(
         self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         _request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
@@ -1456,7 +1456,7 @@ impl EditAgentTest {
                 .into_iter()
                 .filter_map(|tool| {
                     let input_schema = tool
-                        .input_schema(self.agent.model.tool_input_format())
+                        .shared_input_schema(self.agent.model.tool_input_format())
                         .ok()?;
                     Some(LanguageModelRequestTool {
                         name: tool.name(),
@@ -1480,6 +1480,7 @@ impl EditAgentTest {
             &project_context,
             &ModelContext {
                 available_tools: tool_names,
+                context_server_instructions: Vec::new(),
             },
         )?;
 
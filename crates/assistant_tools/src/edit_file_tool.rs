@@ -24,7 +24,9 @@ use language::{
     Anchor, Buffer, Capability, LanguageRegistry, LineEnding, OffsetRangeExt, Rope, TextBuffer,
     language_settings::SoftWrap,
 };
-use language_model::{LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat};
+use language_model::{
+    LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat, LanguageModelToolUseId,
+};
 use markdown::{Markdown, MarkdownElement, MarkdownStyle};
 use project::{Project, ProjectPath};
 use schemars::JsonSchema;
@@ -125,6 +127,10 @@ impl Tool for EditFileTool {
         false
     }
 
+    fn may_perform_edits(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> String {
         include_str!("edit_file_tool/description.md").to_string()
     }
@@ -162,6 +168,7 @@ impl Tool for EditFileTool {
 
     fn run(
         self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
@@ -986,6 +993,7 @@ mod tests {
                 .unwrap();
                 Arc::new(EditFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -1215,6 +1223,7 @@ mod tests {
                 .unwrap();
                 Arc::new(EditFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -1269,6 +1278,7 @@ mod tests {
                 .unwrap();
                 Arc::new(EditFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -1401,6 +1411,7 @@ mod tests {
                 .unwrap();
                 Arc::new(EditFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -1453,6 +1464,7 @@ mod tests {
                 .unwrap();
                 Arc::new(EditFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -1509,6 +1521,7 @@ mod tests {
                 .unwrap();
                 Arc::new(EditFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -4,7 +4,9 @@ use assistant_tool::{ActionLog, Tool, ToolResult};
 use futures::StreamExt;
 use gpui::{AnyWindowHandle, App, Entity, Task};
 use language::{OffsetRangeExt, ParseStatus, Point};
-use language_model::{LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat};
+use language_model::{
+    LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat, LanguageModelToolUseId,
+};
 use project::{
     Project,
     search::{SearchQuery, SearchResult},
@@ -60,6 +62,10 @@ impl Tool for GrepTool {
         false
     }
 
+    fn may_perform_edits(&self) -> bool {
+        false
+    }
+
     fn description(&self) -> String {
         include_str!("./grep_tool/description.md").into()
     }
@@ -95,6 +101,7 @@ impl Tool for GrepTool {
 
     fn run(
         self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         _request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
@@ -746,8 +753,18 @@ mod tests {
         let tool = Arc::new(GrepTool);
         let action_log = cx.new(|_cx| ActionLog::new(project.clone()));
         let model = Arc::new(FakeLanguageModel::default());
-        let task =
-            cx.update(|cx| tool.run(input, Arc::default(), project, action_log, model, None, cx));
+        let task = cx.update(|cx| {
+            tool.run(
+                LanguageModelToolUseId::from("test"),
+                input,
+                Arc::default(),
+                project,
+                action_log,
+                model,
+                None,
+                cx,
+            )
+        });
 
         match task.output.await {
             Ok(result) => {
@@ -11,6 +11,7 @@ use itertools::Itertools;
 use language::{Anchor, Point};
 use language_model::{
     LanguageModel, LanguageModelImage, LanguageModelRequest, LanguageModelToolSchemaFormat,
+    LanguageModelToolUseId,
 };
 use project::{AgentLocation, Project};
 use schemars::JsonSchema;
@@ -58,6 +59,10 @@ impl Tool for ReadFileTool {
         false
     }
 
+    fn may_perform_edits(&self) -> bool {
+        false
+    }
+
     fn description(&self) -> String {
         include_str!("./read_file_tool/description.md").into()
     }
@@ -86,11 +91,12 @@ impl Tool for ReadFileTool {
 
     fn run(
         self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
         input: serde_json::Value,
         _request: Arc<LanguageModelRequest>,
         project: Entity<Project>,
         action_log: Entity<ActionLog>,
-        model: Arc<dyn LanguageModel>,
+        _model: Arc<dyn LanguageModel>,
         _window: Option<AnyWindowHandle>,
         cx: &mut App,
     ) -> ToolResult {
@@ -106,14 +112,6 @@ impl Tool for ReadFileTool {
         let file_path = input.path.clone();
 
         if image_store::is_image_file(&project, &project_path, cx) {
-            if !model.supports_images() {
-                return Task::ready(Err(anyhow!(
-                    "Attempted to read an image, but Zed doesn't currently sending images to {}.",
-                    model.name().0
-                )))
-                .into();
-            }
-
             let task = cx.spawn(async move |cx| -> Result<ToolResultOutput> {
                 let image_entity: Entity<ImageItem> = cx
                     .update(|cx| {
@@ -272,6 +270,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -311,6 +310,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -353,6 +353,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -386,6 +387,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -443,6 +445,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -486,6 +489,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -509,6 +513,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
@@ -532,6 +537,7 @@ mod test {
                 });
                 Arc::new(ReadFileTool)
                     .run(
+                        LanguageModelToolUseId::from("test"),
                         input,
                         Arc::default(),
                         project.clone(),
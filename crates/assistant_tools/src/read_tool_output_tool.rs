@@ -0,0 +1,263 @@
+use std::sync::Arc;
+
+use crate::schema::json_schema_for;
+use anyhow::{Result, anyhow};
+use assistant_tool::{ActionLog, Tool, ToolResult};
+use gpui::{AnyWindowHandle, App, Entity, Task};
+use language_model::{
+    LanguageModel, LanguageModelRequest, LanguageModelToolSchemaFormat, LanguageModelToolUseId,
+};
+use project::Project;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ui::IconName;
+use util::markdown::MarkdownInlineCode;
+
+/// The maximum number of bytes returned by a single call, kept well below the limit at which
+/// tool output is truncated so that reading a chunk never overflows again.
+const MAX_CHUNK_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReadToolOutputToolInput {
+    /// The ID of the tool call whose full output was saved.
+    pub tool_use_id: String,
+
+    /// Optional line number to start reading on (1-based index)
+    #[serde(default)]
+    pub start_line: Option<u32>,
+
+    /// Optional line number to end reading on (1-based index, inclusive)
+    #[serde(default)]
+    pub end_line: Option<u32>,
+
+    /// Optional byte offset in `start_line` to start reading from, to continue reading a line
+    /// that was too long to be returned in one chunk
+    #[serde(default)]
+    pub start_byte: Option<u32>,
+}
+
+pub struct ReadToolOutputTool;
+
+impl Tool for ReadToolOutputTool {
+    fn name(&self) -> String {
+        "read_tool_output".into()
+    }
+
+    fn needs_confirmation(&self, _: &serde_json::Value, _: &App) -> bool {
+        false
+    }
+
+    fn may_perform_edits(&self) -> bool {
+        false
+    }
+
+    fn description(&self) -> String {
+        include_str!("./read_tool_output_tool/description.md").into()
+    }
+
+    fn icon(&self) -> IconName {
+        IconName::FileText
+    }
+
+    fn input_schema(&self, format: LanguageModelToolSchemaFormat) -> Result<serde_json::Value> {
+        json_schema_for::<ReadToolOutputToolInput>(format)
+    }
+
+    fn ui_text(&self, input: &serde_json::Value) -> String {
+        match serde_json::from_value::<ReadToolOutputToolInput>(input.clone()) {
+            Ok(input) => {
+                let id = MarkdownInlineCode(&input.tool_use_id);
+                match (input.start_line, input.end_line) {
+                    (Some(start), None) => format!("Read output of {id} (from line {start})"),
+                    (Some(start), Some(end)) => {
+                        format!("Read output of {id} (lines {start}-{end})")
+                    }
+                    _ => format!("Read output of {id}"),
+                }
+            }
+            Err(_) => "Read tool output".to_string(),
+        }
+    }
+
+    fn run(
+        self: Arc<Self>,
+        _tool_use_id: LanguageModelToolUseId,
+        input: serde_json::Value,
+        _request: Arc<LanguageModelRequest>,
+        project: Entity<Project>,
+        _action_log: Entity<ActionLog>,
+        _model: Arc<dyn LanguageModel>,
+        _window: Option<AnyWindowHandle>,
+        cx: &mut App,
+    ) -> ToolResult {
+        let input = match serde_json::from_value::<ReadToolOutputToolInput>(input) {
+            Ok(input) => input,
+            Err(err) => return Task::ready(Err(anyhow!(err))).into(),
+        };
+
+        // The ID becomes part of a path, so it mustn't be able to point outside of the directory
+        // where tool output is saved.
+        if input.tool_use_id.is_empty()
+            || !input
+                .tool_use_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Task::ready(Err(anyhow!("Invalid tool use ID: {}", input.tool_use_id))).into();
+        }
+
+        let tool_use_id = LanguageModelToolUseId::from(input.tool_use_id);
+        let path = assistant_tool::tool_output_overflow_path(&tool_use_id);
+        let fs = project.read(cx).fs().clone();
+        cx.background_spawn(async move {
+            let text = fs
+                .load(&path)
+                .await
+                .map_err(|_| anyhow!("No saved output found for tool use {tool_use_id}"))?;
+            Ok(read_lines(&text, input.start_line, input.end_line, input.start_byte).into())
+        })
+        .into()
+    }
+}
+
+fn read_lines(
+    text: &str,
+    start_line: Option<u32>,
+    end_line: Option<u32>,
+    start_byte: Option<u32>,
+) -> String {
+    let total_lines = text.lines().count();
+    // .max(1) because despite instructions to be 1-indexed, sometimes the model passes 0.
+    let start = start_line.unwrap_or(1).max(1) as usize;
+    let end = end_line.map_or(total_lines, |end| (end as usize).min(total_lines));
+    if start > end {
+        return format!(
+            "The output has {total_lines} lines; there is nothing to read from line {start}."
+        );
+    }
+
+    // A byte offset into the middle of a character is moved back to the character's start.
+    let mut start_offset = 0;
+    if let Some(line) = text.lines().nth(start - 1) {
+        start_offset = (start_byte.unwrap_or(0) as usize).min(line.len());
+        while !line.is_char_boundary(start_offset) {
+            start_offset -= 1;
+        }
+    }
+
+    let mut chunk = String::new();
+    let mut last_line = start - 1;
+    // The line that was too long to fit in the chunk, with the offset to continue reading it from.
+    let mut continued_line = None;
+    for (ix, line) in text
+        .lines()
+        .enumerate()
+        .skip(start - 1)
+        .take(end + 1 - start)
+    {
+        let line_number = ix + 1;
+        let offset = if line_number == start {
+            start_offset
+        } else {
+            0
+        };
+        let rest = &line[offset..];
+        if chunk.len() + rest.len() + 1 > MAX_CHUNK_BYTES {
+            if chunk.is_empty() {
+                let shown = util::truncate_to_byte_limit(rest, MAX_CHUNK_BYTES);
+                chunk.push_str(shown);
+                chunk.push('\n');
+                last_line = line_number;
+                continued_line = Some((line_number, line.len(), offset + shown.len()));
+            }
+            break;
+        }
+        chunk.push_str(rest);
+        chunk.push('\n');
+        last_line = line_number;
+    }
+
+    let mut result = format!("Lines {start}-{last_line} of {total_lines}");
+    if start_offset > 0 {
+        result.push_str(&format!(
+            ", starting at byte {start_offset} of line {start}"
+        ));
+    }
+    result.push_str(&format!(":\n\n{chunk}"));
+    if let Some((line_number, line_len, next_byte)) = continued_line {
+        result.push_str(&format!(
+            "\nLine {line_number} is {line_len} bytes long and continues from byte {next_byte}. \
+            Continue reading it with a `start_line` of {line_number} and a `start_byte` of \
+            {next_byte}."
+        ));
+    } else if last_line < end {
+        result.push_str(&format!(
+            "\nThe chunk was limited to {MAX_CHUNK_BYTES} bytes. Continue reading from line {}.",
+            last_line + 1
+        ));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_lines() {
+        let text = "one\ntwo\nthree\nfour\n";
+        assert_eq!(
+            read_lines(text, None, None, None),
+            "Lines 1-4 of 4:\n\none\ntwo\nthree\nfour\n"
+        );
+        assert_eq!(
+            read_lines(text, Some(2), Some(3), None),
+            "Lines 2-3 of 4:\n\ntwo\nthree\n"
+        );
+        assert_eq!(
+            read_lines(text, Some(0), Some(1), None),
+            "Lines 1-1 of 4:\n\none\n"
+        );
+        assert_eq!(
+            read_lines(text, Some(3), Some(100), None),
+            "Lines 3-4 of 4:\n\nthree\nfour\n"
+        );
+        assert_eq!(
+            read_lines(text, Some(5), None, None),
+            "The output has 4 lines; there is nothing to read from line 5."
+        );
+        assert_eq!(
+            read_lines(text, Some(3), Some(4), Some(2)),
+            "Lines 3-4 of 4, starting at byte 2 of line 3:\n\nree\nfour\n"
+        );
+
+        let long_line = "x".repeat(MAX_CHUNK_BYTES - 1);
+        let text = format!("{long_line}\n{long_line}\n");
+        let result = read_lines(&text, None, None, None);
+        assert!(result.starts_with("Lines 1-1 of 2:"));
+        assert!(result.ends_with("Continue reading from line 2."));
+    }
+
+    #[test]
+    fn test_read_long_line_in_chunks() {
+        let long_line = "yé".repeat(MAX_CHUNK_BYTES);
+        let text = format!("{long_line}\nshort\n");
+
+        let mut read_line = String::new();
+        let mut start_byte = None;
+        loop {
+            let result = read_lines(&text, Some(1), None, start_byte);
+            assert!(result.len() < MAX_CHUNK_BYTES + 300);
+            let (_, rest) = result.split_once(":\n\n").unwrap();
+            let (piece, note) = rest.split_once('\n').unwrap();
+            read_line.push_str(piece);
+            match note.rsplit_once("a `start_byte` of ") {
+                Some((_, next_byte)) => {
+                    start_byte = Some(next_byte.trim_end_matches('.').parse().unwrap());
+                }
+                None => break,
+            }
+        }
+        assert_eq!(read_line, long_line);
+    }
+}
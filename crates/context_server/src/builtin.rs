@@ -0,0 +1,67 @@
+//! Context servers that Zed implements itself, so that basic tools work without installing a
+//! server for them. They're served over a [`BuiltinTransport`](crate::transport::BuiltinTransport)
+//! and go through the same protocol, settings and confirmations as any other server.
+
+mod fetch;
+mod filesystem;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::types;
+
+pub use fetch::*;
+pub use filesystem::*;
+
+/// A context server built into Zed.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltinContextServer {
+    /// Fetches web pages and other HTTP resources, converting HTML to Markdown.
+    Fetch,
+    /// Reads, writes and lists files in the project's worktrees.
+    Filesystem,
+}
+
+impl BuiltinContextServer {
+    /// The built-in servers that run unless the settings configure a server with the same id.
+    ///
+    /// None do: `fetch` reaches the network on the model's behalf, so it only runs once it's
+    /// configured.
+    pub const DEFAULT: &[Self] = &[];
+
+    /// The id the server runs under by default.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Fetch => "fetch",
+            Self::Filesystem => "filesystem",
+        }
+    }
+}
+
+/// The tools of a built-in server.
+#[async_trait]
+pub trait BuiltinServer: Send + Sync {
+    async fn tools(&self) -> Result<Vec<types::Tool>>;
+
+    /// Runs the tool named `name`. Failures the model can act on, such as an HTTP error, are
+    /// returned as error responses rather than as errors.
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Map<String, Value>,
+    ) -> Result<types::CallToolResponse>;
+}
+
+/// A response containing `text`, which is an error when `is_error` is set.
+pub fn text_response(text: String, is_error: bool) -> types::CallToolResponse {
+    types::CallToolResponse {
+        content: vec![types::ToolResponseContent::Text { text }],
+        is_error: Some(is_error),
+        structured_content: None,
+        meta: None,
+    }
+}
@@ -0,0 +1,331 @@
+use std::cell::RefCell;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures::AsyncReadExt as _;
+use html_to_markdown::{TagHandler, convert_html_to_markdown, markdown};
+use http_client::{AsyncBody, HttpClient};
+use serde_json::{Map, Value, json};
+use url::{Host, Url};
+
+use crate::builtin::{BuiltinServer, text_response};
+use crate::types;
+
+const FETCH_TOOL_NAME: &str = "fetch_url";
+
+/// How much of a response is returned when the call doesn't ask for a length.
+const DEFAULT_MAX_LENGTH: usize = 50_000;
+
+/// The most bytes of a response that are read, however much of it the call asks for.
+const MAX_BODY_BYTES: u64 = 5 * 1024 * 1024;
+
+const MAX_REDIRECTS: usize = 10;
+
+/// Fetches URLs over HTTP, returning HTML pages as Markdown.
+pub struct FetchServer {
+    http_client: Arc<dyn HttpClient>,
+}
+
+impl FetchServer {
+    pub fn new(http_client: Arc<dyn HttpClient>) -> Self {
+        Self { http_client }
+    }
+
+    async fn fetch(&self, url: &str, max_length: usize) -> Result<types::CallToolResponse> {
+        let url = if url.starts_with("https://") || url.starts_with("http://") {
+            url.to_string()
+        } else {
+            format!("https://{url}")
+        };
+        let mut url = Url::parse(&url).with_context(|| format!("parsing {url}"))?;
+
+        // Redirects are followed here rather than by the client, so that every hop is checked
+        // before it's requested: a public page could otherwise redirect to a private address.
+        let mut redirects = 0;
+        let mut response = loop {
+            if let Err(message) = check_url(&url).await {
+                return Ok(text_response(message, true));
+            }
+            let response = self
+                .http_client
+                .get(url.as_str(), AsyncBody::default(), false)
+                .await
+                .with_context(|| format!("fetching {url}"))?;
+            if !response.status().is_redirection() {
+                break response;
+            }
+            let Some(location) = response
+                .headers()
+                .get("location")
+                .and_then(|location| location.to_str().ok())
+            else {
+                break response;
+            };
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Ok(text_response(
+                    format!("{url} redirected more than {MAX_REDIRECTS} times"),
+                    true,
+                ));
+            }
+            url = url
+                .join(location)
+                .with_context(|| format!("parsing the redirect to {location}"))?;
+        };
+
+        let mut body = Vec::new();
+        (&mut *response.body_mut())
+            .take(MAX_BODY_BYTES + 1)
+            .read_to_end(&mut body)
+            .await
+            .context("reading the response")?;
+        let body_truncated = body.len() as u64 > MAX_BODY_BYTES;
+        body.truncate(MAX_BODY_BYTES as usize);
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = String::from_utf8_lossy(&body);
+            return Ok(text_response(
+                format!("HTTP {}: {}", status.as_u16(), truncate(&body, max_length)),
+                true,
+            ));
+        }
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|content_type| content_type.to_str().ok())
+            .unwrap_or_default();
+        let text = response_text(content_type, &body)?;
+        if text.trim().is_empty() {
+            return Ok(text_response(format!("{url} has no textual content"), true));
+        }
+        let mut text = truncate(&text, max_length);
+        if body_truncated {
+            text.push_str(&format!(
+                "\n\n[The response is larger than {MAX_BODY_BYTES} bytes, so only its start was read.]"
+            ));
+        }
+        Ok(text_response(text, false))
+    }
+}
+
+/// Checks that `url` is an HTTP URL whose host only resolves to public addresses, so that the
+/// model can't use the server to reach the user's machine, network or cloud metadata services.
+async fn check_url(url: &Url) -> Result<(), String> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("{url} isn't an HTTP URL"));
+    }
+    let Some(port) = url.port_or_known_default() else {
+        return Err(format!("{url} has no port"));
+    };
+    let addresses = match url.host() {
+        Some(Host::Ipv4(address)) => vec![IpAddr::V4(address)],
+        Some(Host::Ipv6(address)) => vec![IpAddr::V6(address)],
+        Some(Host::Domain(domain)) => smol::net::resolve((domain, port))
+            .await
+            .map_err(|error| format!("resolving {domain}: {error}"))?
+            .into_iter()
+            .map(|address| address.ip())
+            .collect(),
+        None => return Err(format!("{url} has no host")),
+    };
+    if addresses.is_empty() {
+        return Err(format!("{url} doesn't resolve to any address"));
+    }
+    if let Some(address) = addresses.into_iter().find(|address| !is_public(*address)) {
+        return Err(format!(
+            "{url} resolves to {address}, which isn't a public address"
+        ));
+    }
+    Ok(())
+}
+
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_public_v4(address),
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(address) => is_public_v4(address),
+            None => is_public_v6(address),
+        },
+    }
+}
+
+fn is_public_v4(address: Ipv4Addr) -> bool {
+    let [a, b, ..] = address.octets();
+    !(address.is_unspecified()
+        || address.is_loopback()
+        || address.is_private()
+        || address.is_link_local()
+        || address.is_broadcast()
+        || address.is_multicast()
+        || address.is_documentation()
+        || a == 0
+        || a >= 240
+        // Shared address space, used by carrier-grade NAT.
+        || (a == 100 && (b & 0xc0) == 64))
+}
+
+fn is_public_v6(address: Ipv6Addr) -> bool {
+    let first = address.segments()[0];
+    !(address.is_unspecified()
+        || address.is_loopback()
+        || address.is_multicast()
+        // Unique local addresses.
+        || (first & 0xfe00) == 0xfc00
+        // Link-local addresses.
+        || (first & 0xffc0) == 0xfe80)
+}
+
+#[async_trait]
+impl BuiltinServer for FetchServer {
+    async fn tools(&self) -> Result<Vec<types::Tool>> {
+        Ok(vec![types::Tool {
+            name: FETCH_TOOL_NAME.to_string(),
+            description: Some(
+                "Fetches a URL and returns its content. HTML pages are converted to Markdown."
+                    .to_string(),
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The URL to fetch.",
+                    },
+                    "max_length": {
+                        "type": "integer",
+                        "description": "The most characters of the content to return.",
+                        "default": DEFAULT_MAX_LENGTH,
+                    },
+                },
+                "required": ["url"],
+            }),
+            annotations: Some(types::ToolAnnotations {
+                title: Some("Fetch URL".to_string()),
+                read_only_hint: Some(false),
+                open_world_hint: Some(true),
+                ui_text_template: Some("Fetch {url}".to_string()),
+                ..Default::default()
+            }),
+        }])
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Map<String, Value>,
+    ) -> Result<types::CallToolResponse> {
+        anyhow::ensure!(name == FETCH_TOOL_NAME, "unknown tool {name:?}");
+        let Some(url) = arguments.get("url").and_then(Value::as_str) else {
+            return Ok(text_response(
+                "The `url` argument is required".to_string(),
+                true,
+            ));
+        };
+        let max_length = arguments
+            .get("max_length")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_MAX_LENGTH, |max_length| max_length as usize);
+        self.fetch(url, max_length).await
+    }
+}
+
+/// Converts a response's body to text the model can read, based on its content type.
+fn response_text(content_type: &str, body: &[u8]) -> Result<String> {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if media_type == "application/json" || media_type.ends_with("+json") {
+        if let Ok(json) = serde_json::from_slice::<Value>(body) {
+            return Ok(format!(
+                "```json\n{}\n```",
+                serde_json::to_string_pretty(&json)?
+            ));
+        }
+    }
+    if media_type.is_empty() || media_type == "text/html" || media_type == "application/xhtml+xml" {
+        let mut handlers: Vec<TagHandler> = vec![
+            Rc::new(RefCell::new(markdown::WebpageChromeRemover)),
+            Rc::new(RefCell::new(markdown::ParagraphHandler)),
+            Rc::new(RefCell::new(markdown::HeadingHandler)),
+            Rc::new(RefCell::new(markdown::ListHandler)),
+            Rc::new(RefCell::new(markdown::TableHandler::new())),
+            Rc::new(RefCell::new(markdown::StyledTextHandler)),
+            Rc::new(RefCell::new(markdown::CodeHandler)),
+        ];
+        return convert_html_to_markdown(body, &mut handlers);
+    }
+    Ok(String::from_utf8_lossy(body).into_owned())
+}
+
+fn truncate(text: &str, max_length: usize) -> String {
+    match text.char_indices().nth(max_length) {
+        Some((end, _)) => format!(
+            "{}\n\n[The content was truncated to {max_length} characters. Fetch again with a \
+            larger `max_length` to see more.]",
+            &text[..end]
+        ),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_text() {
+        assert_eq!(
+            response_text("application/json; charset=utf-8", br#"{"a":1}"#).unwrap(),
+            "```json\n{\n  \"a\": 1\n}\n```"
+        );
+        assert_eq!(
+            response_text("text/plain", b"plain text").unwrap(),
+            "plain text"
+        );
+        let markdown = response_text(
+            "text/html; charset=utf-8",
+            b"<html><body><h1>Title</h1></body></html>",
+        )
+        .unwrap();
+        assert!(markdown.contains("# Title"), "{markdown}");
+
+        assert_eq!(truncate("abcdef", 10), "abcdef");
+        assert!(truncate("abcdef", 3).starts_with("abc\n\n[The content was truncated"));
+    }
+
+    #[test]
+    fn test_is_public() {
+        for address in [
+            "93.184.216.34",
+            "2606:2800:220:1:248:1893:25c8:1946",
+            "8.8.8.8",
+        ] {
+            assert!(is_public(address.parse().unwrap()), "{address}");
+        }
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public(address.parse().unwrap()), "{address}");
+        }
+    }
+}
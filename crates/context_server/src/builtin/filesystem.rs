@@ -0,0 +1,330 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use fs::Fs;
+use futures::StreamExt as _;
+use serde_json::{Map, Value, json};
+
+use crate::builtin::{BuiltinServer, text_response};
+use crate::types;
+
+const LIST_DIRECTORY_TOOL_NAME: &str = "list_directory";
+const READ_FILE_TOOL_NAME: &str = "read_file";
+const WRITE_FILE_TOOL_NAME: &str = "write_file";
+
+/// Reads, writes and lists files in the project's worktrees, refusing paths outside of them.
+pub struct FilesystemServer {
+    fs: Arc<dyn Fs>,
+    roots: Arc<[PathBuf]>,
+}
+
+impl FilesystemServer {
+    /// Serves the files under `roots`. Relative paths are resolved against the first root.
+    pub fn new(fs: Arc<dyn Fs>, roots: Arc<[PathBuf]>) -> Self {
+        Self { fs, roots }
+    }
+
+    /// Resolves `path` to a path under one of the roots, following symlinks so that a link
+    /// can't lead outside of them. The path itself doesn't need to exist, but its parent does,
+    /// and a path that doesn't exist can't be a symlink, as writing to it would follow the link.
+    async fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let Some(first_root) = self.roots.first() else {
+            return Err("No folders are open in the project".to_string());
+        };
+        let path = Path::new(path);
+        let path = normalize(&if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            first_root.join(path)
+        });
+        let outside_roots = || {
+            Err(format!(
+                "{} is outside of the project's folders: {}",
+                path.display(),
+                self.roots
+                    .iter()
+                    .map(|root| root.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        };
+
+        let mut roots = Vec::new();
+        for root in self.roots.iter() {
+            roots.push(
+                self.fs
+                    .canonicalize(root)
+                    .await
+                    .unwrap_or_else(|_| root.clone()),
+            );
+        }
+        let resolved = match self.fs.canonicalize(&path).await {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                if self.fs.read_link(&path).await.is_ok() {
+                    return Err(format!(
+                        "{} is a symlink to a path that doesn't exist",
+                        path.display()
+                    ));
+                }
+                let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+                    return outside_roots();
+                };
+                let parent = self
+                    .fs
+                    .canonicalize(parent)
+                    .await
+                    .map_err(|_| format!("The directory {} doesn't exist", parent.display()))?;
+                parent.join(file_name)
+            }
+        };
+        if roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            outside_roots()
+        }
+    }
+
+    async fn list_directory(&self, path: &Path) -> Result<String> {
+        let mut entries = self
+            .fs
+            .read_dir(path)
+            .await
+            .with_context(|| format!("listing {}", path.display()))?;
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+            let Some(file_name) = entry.file_name() else {
+                continue;
+            };
+            let mut name = file_name.to_string_lossy().into_owned();
+            if self.fs.is_dir(&entry).await {
+                name.push('/');
+            }
+            names.push(name);
+        }
+        names.sort();
+        if names.is_empty() {
+            return Ok(format!("{} is empty", path.display()));
+        }
+        Ok(names.join("\n"))
+    }
+}
+
+#[async_trait]
+impl BuiltinServer for FilesystemServer {
+    async fn tools(&self) -> Result<Vec<types::Tool>> {
+        let path_schema = json!({
+            "type": "string",
+            "description": "The path of the file or directory, either absolute or relative to \
+                the project's first folder.",
+        });
+        Ok(vec![
+            types::Tool {
+                name: LIST_DIRECTORY_TOOL_NAME.to_string(),
+                description: Some(
+                    "Lists the entries of a directory in the project. Directories end with `/`."
+                        .to_string(),
+                ),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": { "path": path_schema },
+                    "required": ["path"],
+                }),
+                annotations: Some(types::ToolAnnotations {
+                    read_only_hint: Some(true),
+                    ui_text_template: Some("List {path}".to_string()),
+                    ..Default::default()
+                }),
+            },
+            types::Tool {
+                name: READ_FILE_TOOL_NAME.to_string(),
+                description: Some("Reads a text file in the project.".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": { "path": path_schema },
+                    "required": ["path"],
+                }),
+                annotations: Some(types::ToolAnnotations {
+                    read_only_hint: Some(true),
+                    ui_text_template: Some("Read {path}".to_string()),
+                    ..Default::default()
+                }),
+            },
+            types::Tool {
+                name: WRITE_FILE_TOOL_NAME.to_string(),
+                description: Some(
+                    "Writes a text file in the project, replacing its content if it exists. The \
+                    file's directory must already exist."
+                        .to_string(),
+                ),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": path_schema,
+                        "content": {
+                            "type": "string",
+                            "description": "The file's new content.",
+                        },
+                    },
+                    "required": ["path", "content"],
+                }),
+                annotations: Some(types::ToolAnnotations {
+                    destructive_hint: Some(true),
+                    ui_text_template: Some("Write {path}".to_string()),
+                    ..Default::default()
+                }),
+            },
+        ])
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Map<String, Value>,
+    ) -> Result<types::CallToolResponse> {
+        let Some(path) = arguments.get("path").and_then(Value::as_str) else {
+            return Ok(text_response(
+                "The `path` argument is required".to_string(),
+                true,
+            ));
+        };
+        let path = match self.resolve(path).await {
+            Ok(path) => path,
+            Err(message) => return Ok(text_response(message, true)),
+        };
+        let result = match name {
+            LIST_DIRECTORY_TOOL_NAME => self.list_directory(&path).await,
+            READ_FILE_TOOL_NAME => self
+                .fs
+                .load(&path)
+                .await
+                .with_context(|| format!("reading {}", path.display())),
+            WRITE_FILE_TOOL_NAME => {
+                let Some(content) = arguments.get("content").and_then(Value::as_str) else {
+                    return Ok(text_response(
+                        "The `content` argument is required".to_string(),
+                        true,
+                    ));
+                };
+                self.fs
+                    .atomic_write(path.clone(), content.to_string())
+                    .await
+                    .with_context(|| format!("writing {}", path.display()))
+                    .map(|()| format!("Wrote {} bytes to {}", content.len(), path.display()))
+            }
+            _ => anyhow::bail!("unknown tool {name:?}"),
+        };
+        Ok(match result {
+            Ok(text) => text_response(text, false),
+            Err(error) => text_response(format!("{error:#}"), true),
+        })
+    }
+}
+
+/// Removes `.` and `..` components without touching the filesystem, so that paths that don't
+/// exist yet can still be checked against the roots.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use util::path;
+
+    fn text(response: &types::CallToolResponse) -> &str {
+        match &response.content[..] {
+            [types::ToolResponseContent::Text { text }] => text,
+            _ => panic!("expected a single text content"),
+        }
+    }
+
+    #[gpui::test]
+    async fn test_filesystem_server(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            path!("/project"),
+            json!({ "src": { "main.rs": "fn main() {}" } }),
+        )
+        .await;
+        fs.insert_tree(path!("/outside"), json!({ "secret.txt": "secret" }))
+            .await;
+        let server =
+            FilesystemServer::new(fs.clone(), Arc::from([PathBuf::from(path!("/project"))]));
+        let call = async |name: &str, arguments: Value| {
+            server
+                .call_tool(name, arguments.as_object().unwrap().clone())
+                .await
+                .unwrap()
+        };
+
+        let response = call("list_directory", json!({ "path": "." })).await;
+        assert_eq!(text(&response), "src/");
+        let response = call("read_file", json!({ "path": "src/main.rs" })).await;
+        assert_eq!(
+            (text(&response), response.is_error),
+            ("fn main() {}", Some(false))
+        );
+
+        let response = call(
+            "write_file",
+            json!({ "path": "src/lib.rs", "content": "pub fn lib() {}" }),
+        )
+        .await;
+        assert_eq!(response.is_error, Some(false));
+        assert_eq!(
+            fs.load(Path::new(path!("/project/src/lib.rs")))
+                .await
+                .unwrap(),
+            "pub fn lib() {}"
+        );
+
+        let response = call("read_file", json!({ "path": path!("/outside/secret.txt") })).await;
+        assert_eq!(response.is_error, Some(true));
+        assert!(text(&response).contains("outside of the project's folders"));
+        let response = call("read_file", json!({ "path": "../outside/secret.txt" })).await;
+        assert_eq!(response.is_error, Some(true));
+
+        fs.insert_symlink(
+            path!("/project/link.txt"),
+            PathBuf::from(path!("/outside/secret.txt")),
+        )
+        .await;
+        let response = call("read_file", json!({ "path": "link.txt" })).await;
+        assert_eq!(response.is_error, Some(true));
+        assert!(text(&response).contains("outside of the project's folders"));
+
+        // A link to a file that doesn't exist yet would otherwise create it outside the project.
+        fs.insert_symlink(
+            path!("/project/notes.txt"),
+            PathBuf::from(path!("/outside/autostart.desktop")),
+        )
+        .await;
+        let response = call(
+            "write_file",
+            json!({ "path": "notes.txt", "content": "[Desktop Entry]" }),
+        )
+        .await;
+        assert_eq!(response.is_error, Some(true));
+        assert!(text(&response).contains("symlink"));
+        assert!(
+            !fs.is_file(Path::new(path!("/outside/autostart.desktop")))
+                .await
+        );
+    }
+}
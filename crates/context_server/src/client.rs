@@ -1,6 +1,6 @@
-use anyhow::{Context as _, Result, anyhow};
+use anyhow::{Context as _, Result};
 use collections::HashMap;
-use futures::{FutureExt, StreamExt, channel::oneshot, select};
+use futures::{FutureExt, StreamExt, channel::oneshot, future, select};
 use gpui::{AppContext as _, AsyncApp, BackgroundExecutor, Task};
 use parking_lot::Mutex;
 use postage::barrier;
@@ -21,7 +21,7 @@ use util::TryFutureExt;
 use crate::transport::{StdioTransport, Transport};
 
 const JSON_RPC_VERSION: &str = "2.0";
-const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
 
 // Standard JSON-RPC error codes
 pub const PARSE_ERROR: i32 = -32700;
@@ -29,9 +29,24 @@ pub const INVALID_REQUEST: i32 = -32600;
 pub const METHOD_NOT_FOUND: i32 = -32601;
 pub const INVALID_PARAMS: i32 = -32602;
 pub const INTERNAL_ERROR: i32 = -32603;
+/// Returned in place of a response that was larger than the server's `max_result_bytes`.
+pub const RESPONSE_TOO_LARGE: i32 = -32001;
+
+/// How much of the start and end of an oversized message is kept, to find the request it answers.
+pub(crate) const OVERSIZED_MESSAGE_CONTEXT_BYTES: usize = 1024;
 
 type ResponseHandler = Box<dyn Send + FnOnce(Result<String, Error>)>;
 type NotificationHandler = Box<dyn Send + FnMut(Value, AsyncApp)>;
+type RequestHandler = Box<dyn Send + FnMut(Value) -> Result<Value, ResponseError>>;
+type IoHandler = Box<dyn Send + FnMut(IoKind, &str)>;
+
+/// Kind of context server traffic given to an IO handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoKind {
+    Send,
+    Receive,
+    StdErr,
+}
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -46,7 +61,9 @@ pub(crate) struct Client {
     outbound_tx: channel::Sender<String>,
     name: Arc<str>,
     notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
+    request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
     response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
+    io_handlers: Arc<Mutex<Vec<IoHandler>>>,
     #[allow(clippy::type_complexity)]
     #[allow(dead_code)]
     io_tasks: Mutex<Option<(Task<Option<()>>, Task<Option<()>>)>>,
@@ -78,6 +95,36 @@ struct Request<'a, T> {
     params: T,
 }
 
+/// A request sent by the server to the client.
+#[derive(Deserialize)]
+struct AnyRequest<'a> {
+    #[allow(dead_code)]
+    jsonrpc: &'a str,
+    id: RequestId,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+/// A response written by Zed, to a request sent by the server or in place of a response that was
+/// too large.
+#[derive(Serialize)]
+struct OutgoingResponse {
+    jsonrpc: &'static str,
+    id: RequestId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ResponseError>,
+}
+
+/// An error returned to the server for a request it sent.
+#[derive(Debug, Serialize)]
+pub(crate) struct ResponseError {
+    pub code: i32,
+    pub message: String,
+}
+
 #[derive(Serialize, Deserialize)]
 struct AnyResponse<'a> {
     jsonrpc: &'a str,
@@ -124,14 +171,43 @@ struct AnyNotification<'a> {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Error {
+    #[serde(default)]
+    code: i32,
     message: String,
+    #[serde(default)]
+    data: Option<Value>,
+}
+
+/// Why a request to the server failed, when the server is reachable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestError {
+    /// The server didn't respond in time.
+    Timeout,
+    /// The server responded with an error.
+    Response {
+        code: i32,
+        message: String,
+        data: Option<Value>,
+    },
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Timeout => write!(f, "Context server request timeout"),
+            RequestError::Response { message, .. } => write!(f, "{message}"),
+        }
+    }
 }
 
+impl std::error::Error for RequestError {}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ModelContextServerBinary {
     pub executable: PathBuf,
     pub args: Vec<String>,
     pub env: Option<HashMap<String, String>>,
+    pub cwd: Option<PathBuf>,
 }
 
 impl Client {
@@ -143,6 +219,7 @@ impl Client {
     pub fn stdio(
         server_id: ContextServerId,
         binary: ModelContextServerBinary,
+        max_message_bytes: Option<usize>,
         cx: AsyncApp,
     ) -> Result<Self> {
         log::info!(
@@ -157,15 +234,23 @@ impl Client {
             .map(|name| name.to_string_lossy().to_string())
             .unwrap_or_else(String::new);
 
-        let transport = Arc::new(StdioTransport::new(binary, &cx)?);
-        Self::new(server_id, server_name.into(), transport, cx)
+        let transport = Arc::new(StdioTransport::new(binary, max_message_bytes, &cx)?);
+        Self::new(
+            server_id,
+            server_name.into(),
+            transport,
+            max_message_bytes,
+            cx,
+        )
     }
 
-    /// Creates a new Client instance for a context server.
+    /// Creates a new Client instance for a context server, which answers the messages from the
+    /// server larger than `max_message_bytes` with an error.
     pub fn new(
         server_id: ContextServerId,
         server_name: Arc<str>,
         transport: Arc<dyn Transport>,
+        max_message_bytes: Option<usize>,
         cx: AsyncApp,
     ) -> Result<Self> {
         let (outbound_tx, outbound_rx) = channel::unbounded::<String>();
@@ -173,22 +258,40 @@ impl Client {
 
         let notification_handlers =
             Arc::new(Mutex::new(HashMap::<_, NotificationHandler>::default()));
+        let request_handlers = Arc::new(Mutex::new(HashMap::<_, RequestHandler>::from_iter([(
+            "ping",
+            Box::new(|_| Ok(Value::Object(Default::default()))) as RequestHandler,
+        )])));
         let response_handlers =
             Arc::new(Mutex::new(Some(HashMap::<_, ResponseHandler>::default())));
+        let io_handlers = Arc::new(Mutex::new(Vec::<IoHandler>::new()));
 
         let receive_input_task = cx.spawn({
             let notification_handlers = notification_handlers.clone();
+            let request_handlers = request_handlers.clone();
             let response_handlers = response_handlers.clone();
+            let io_handlers = io_handlers.clone();
             let transport = transport.clone();
+            let outbound_tx = outbound_tx.clone();
             async move |cx| {
-                Self::handle_input(transport, notification_handlers, response_handlers, cx)
-                    .log_err()
-                    .await
+                Self::handle_input(
+                    transport,
+                    notification_handlers,
+                    request_handlers,
+                    outbound_tx,
+                    response_handlers,
+                    io_handlers,
+                    max_message_bytes,
+                    cx,
+                )
+                .log_err()
+                .await
             }
         });
         let receive_err_task = cx.spawn({
+            let io_handlers = io_handlers.clone();
             let transport = transport.clone();
-            async move |_| Self::handle_err(transport).log_err().await
+            async move |_| Self::handle_err(transport, io_handlers).log_err().await
         });
         let input_task = cx.spawn(async move |_| {
             let (input, err) = futures::join!(receive_input_task, receive_err_task);
@@ -202,6 +305,7 @@ impl Client {
                 outbound_rx,
                 output_done_tx,
                 response_handlers.clone(),
+                io_handlers.clone(),
             )
             .log_err()
         });
@@ -209,7 +313,9 @@ impl Client {
         Ok(Self {
             server_id,
             notification_handlers,
+            request_handlers,
             response_handlers,
+            io_handlers,
             name: server_name,
             next_id: Default::default(),
             outbound_tx,
@@ -223,19 +329,61 @@ impl Client {
     /// Handles input from the server's stdout.
     ///
     /// This function continuously reads lines from the provided stdout stream,
-    /// parses them as JSON-RPC responses or notifications, and dispatches them
-    /// to the appropriate handlers. It processes both responses (which are matched
-    /// to pending requests) and notifications (which trigger registered handlers).
+    /// parses them as JSON-RPC requests, responses or notifications, and dispatches them
+    /// to the appropriate handlers. Requests from the server are answered by the handler
+    /// registered for their method, or refused when there is none. Responses are matched
+    /// to pending requests, and notifications trigger registered handlers.
     async fn handle_input(
         transport: Arc<dyn Transport>,
         notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
+        request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
+        outbound_tx: channel::Sender<String>,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
+        io_handlers: Arc<Mutex<Vec<IoHandler>>>,
+        max_message_bytes: Option<usize>,
         cx: &mut AsyncApp,
     ) -> anyhow::Result<()> {
         let mut receiver = transport.receive();
 
         while let Some(message) = receiver.next().await {
-            if let Ok(response) = serde_json::from_str::<AnyResponse>(&message) {
+            let message = match max_message_bytes {
+                Some(max_bytes) if message.len() > max_bytes => {
+                    let (head, tail) = message_context(&message);
+                    let Some(error) = oversized_message_error(head, tail, max_bytes) else {
+                        log::warn!(
+                            "discarded a {} byte message from a context server, which is over \
+                            its limit of {max_bytes} bytes",
+                            message.len(),
+                        );
+                        continue;
+                    };
+                    error
+                }
+                _ => message,
+            };
+            for handler in io_handlers.lock().iter_mut() {
+                handler(IoKind::Receive, &message);
+            }
+            if let Ok(request) = serde_json::from_str::<AnyRequest>(&message) {
+                let result = match request_handlers.lock().get_mut(request.method.as_str()) {
+                    Some(handler) => handler(request.params.unwrap_or(Value::Null)),
+                    None => Err(ResponseError {
+                        code: METHOD_NOT_FOUND,
+                        message: format!("{} isn't supported", request.method),
+                    }),
+                };
+                let (result, error) = match result {
+                    Ok(result) => (Some(result), None),
+                    Err(error) => (None, Some(error)),
+                };
+                let response = serde_json::to_string(&OutgoingResponse {
+                    jsonrpc: JSON_RPC_VERSION,
+                    id: request.id,
+                    result,
+                    error,
+                })?;
+                outbound_tx.try_send(response)?;
+            } else if let Ok(response) = serde_json::from_str::<AnyResponse>(&message) {
                 if let Some(handlers) = response_handlers.lock().as_mut() {
                     if let Some(handler) = handlers.remove(&response.id) {
                         handler(Ok(message.to_string()));
@@ -256,9 +404,15 @@ impl Client {
 
     /// Handles the stderr output from the context server.
     /// Continuously reads and logs any error messages from the server.
-    async fn handle_err(transport: Arc<dyn Transport>) -> anyhow::Result<()> {
+    async fn handle_err(
+        transport: Arc<dyn Transport>,
+        io_handlers: Arc<Mutex<Vec<IoHandler>>>,
+    ) -> anyhow::Result<()> {
         while let Some(err) = transport.receive_err().next().await {
             log::warn!("context server stderr: {}", err.trim());
+            for handler in io_handlers.lock().iter_mut() {
+                handler(IoKind::StdErr, &err);
+            }
         }
 
         Ok(())
@@ -272,6 +426,7 @@ impl Client {
         outbound_rx: channel::Receiver<String>,
         output_done_tx: barrier::Sender,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
+        io_handlers: Arc<Mutex<Vec<IoHandler>>>,
     ) -> anyhow::Result<()> {
         let _clear_response_handlers = util::defer({
             let response_handlers = response_handlers.clone();
@@ -281,18 +436,37 @@ impl Client {
         });
         while let Ok(message) = outbound_rx.recv().await {
             log::trace!("outgoing message: {}", message);
+            for handler in io_handlers.lock().iter_mut() {
+                handler(IoKind::Send, &message);
+            }
             transport.send(message).await?;
         }
         drop(output_done_tx);
         Ok(())
     }
 
+    pub fn executor(&self) -> &BackgroundExecutor {
+        &self.executor
+    }
+
     /// Sends a JSON-RPC request to the context server and waits for a response.
     /// This function handles serialization, deserialization, timeout, and error handling.
     pub async fn request<T: DeserializeOwned>(
         &self,
         method: &str,
         params: impl Serialize,
+    ) -> Result<T> {
+        self.request_with_timeout(method, params, Some(REQUEST_TIMEOUT))
+            .await
+    }
+
+    /// Like [`Self::request`], but gives up waiting for the response after `timeout`, or waits
+    /// for as long as it takes when there's no timeout.
+    pub async fn request_with_timeout<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+        timeout: Option<Duration>,
     ) -> Result<T> {
         let id = self.next_id.fetch_add(1, SeqCst);
         let request = serde_json::to_string(&Request {
@@ -328,7 +502,11 @@ impl Client {
         handle_response?;
         send?;
 
-        let mut timeout = executor.timer(REQUEST_TIMEOUT).fuse();
+        let mut timer = match timeout {
+            Some(timeout) => executor.timer(timeout).boxed(),
+            None => future::pending().boxed(),
+        }
+        .fuse();
         select! {
             response = rx.fuse() => {
                 let elapsed = started.elapsed();
@@ -337,7 +515,12 @@ impl Client {
                     Ok(response) => {
                         let parsed: AnyResponse = serde_json::from_str(&response)?;
                         if let Some(error) = parsed.error {
-                            Err(anyhow!(error.message))
+                            Err(RequestError::Response {
+                                code: error.code,
+                                message: error.message,
+                                data: error.data,
+                            }
+                            .into())
                         } else if let Some(result) = parsed.result {
                             Ok(serde_json::from_str(result.get())?)
                         } else {
@@ -347,9 +530,9 @@ impl Client {
                     Err(_) => anyhow::bail!("cancelled")
                 }
             }
-            _ = timeout => {
-                log::error!("cancelled csp request task for {method:?} id {id} which took over {:?}", REQUEST_TIMEOUT);
-                anyhow::bail!("Context server request timeout");
+            _ = timer => {
+                log::error!("cancelled csp request task for {method:?} id {id} which took over {:?}", timeout.unwrap_or_default());
+                Err(RequestError::Timeout.into())
             }
         }
     }
@@ -367,6 +550,22 @@ impl Client {
         Ok(())
     }
 
+    /// Registers a handler to inspect all traffic with the context server.
+    pub fn on_io<F>(&self, f: F)
+    where
+        F: 'static + Send + FnMut(IoKind, &str),
+    {
+        self.io_handlers.lock().push(Box::new(f));
+    }
+
+    /// Registers a handler to answer requests the server sends with the given method.
+    pub fn on_request<F>(&self, method: &'static str, f: F)
+    where
+        F: 'static + Send + FnMut(Value) -> Result<Value, ResponseError>,
+    {
+        self.request_handlers.lock().insert(method, Box::new(f));
+    }
+
     #[allow(unused)]
     pub fn on_notification<F>(&self, method: &'static str, f: F)
     where
@@ -378,6 +577,99 @@ impl Client {
     }
 }
 
+/// Returns the start and end of a message, to find the request it answers when it's too large
+/// to parse.
+fn message_context(message: &str) -> (&str, &str) {
+    let mut head_end = OVERSIZED_MESSAGE_CONTEXT_BYTES.min(message.len());
+    while !message.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    let mut tail_start = message
+        .len()
+        .saturating_sub(OVERSIZED_MESSAGE_CONTEXT_BYTES);
+    while !message.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    (&message[..head_end], &message[tail_start..])
+}
+
+/// Builds the error response that replaces a message larger than `max_bytes`, given its start and
+/// end. Returns `None` when the request it answers can't be found, such as when the message is a
+/// notification.
+pub(crate) fn oversized_message_error(head: &str, tail: &str, max_bytes: usize) -> Option<String> {
+    let id = top_level_id_in_head(head).or_else(|| top_level_id_in_tail(tail))?;
+    serde_json::to_string(&OutgoingResponse {
+        jsonrpc: JSON_RPC_VERSION,
+        id,
+        result: None,
+        error: Some(ResponseError {
+            code: RESPONSE_TOO_LARGE,
+            message: format!(
+                "The response was larger than {max_bytes} bytes, the most this server may send. \
+                Ask for less data, such as with a narrower query or a lower limit."
+            ),
+        }),
+    })
+    .ok()
+}
+
+/// Finds the `id` of a JSON-RPC message from its start, for servers that write it before the
+/// result.
+fn top_level_id_in_head(head: &str) -> Option<RequestId> {
+    let mut depth = 0;
+    let mut string_start = None;
+    let mut last_string = None;
+    let mut escaped = false;
+    for (ix, character) in head.char_indices() {
+        if let Some(start) = string_start {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => {
+                    string_start = None;
+                    last_string = Some(&head[start..ix]);
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match character {
+            '"' => string_start = Some(ix + 1),
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ':' if depth == 1 && last_string == Some("id") => {
+                let value = head[ix + 1..].trim_start();
+                let end = match value.strip_prefix('"') {
+                    Some(string) => string.find('"')? + 2,
+                    None => value.find([',', '}'])?,
+                };
+                return serde_json::from_str(value[..end].trim_end()).ok();
+            }
+            _ if character.is_whitespace() => continue,
+            _ => {}
+        }
+        last_string = None;
+    }
+    None
+}
+
+/// Finds the `id` of a JSON-RPC message from its end, for servers that write it after the result.
+fn top_level_id_in_tail(tail: &str) -> Option<RequestId> {
+    let rest = tail.trim_end().strip_suffix('}')?.trim_end();
+    let value_start = if rest.ends_with('"') {
+        rest[..rest.len() - 1].rfind('"')?
+    } else {
+        rest.rfind(|character: char| !(character.is_ascii_digit() || character == '-'))? + 1
+    };
+    let (rest, value) = rest.split_at(value_start);
+    rest.trim_end()
+        .strip_suffix(':')?
+        .trim_end()
+        .ends_with("\"id\"")
+        .then(|| serde_json::from_str(value).ok())
+        .flatten()
+}
+
 impl fmt::Display for ContextServerId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
@@ -392,3 +684,43 @@ impl fmt::Debug for Client {
             .finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oversized_message_error() {
+        let error = |message: &str| {
+            let (head, tail) = message_context(message);
+            let response = oversized_message_error(head, tail, 10)?;
+            let response = serde_json::from_str::<Value>(&response).unwrap();
+            assert_eq!(response["error"]["code"], RESPONSE_TOO_LARGE);
+            Some(response["id"].clone())
+        };
+
+        let result = format!("\"{}\"", "x".repeat(4 * OVERSIZED_MESSAGE_CONTEXT_BYTES));
+        assert_eq!(
+            error(&format!(r#"{{"jsonrpc":"2.0","id":7,"result":{result}}}"#)),
+            Some(7.into())
+        );
+        assert_eq!(
+            error(&format!(
+                r#"{{"result":{{"content":[{{"id":1,"text":{result}}}]}},"jsonrpc":"2.0","id":"a"}}"#
+            )),
+            Some("a".into())
+        );
+        assert_eq!(
+            error(&format!(
+                r#"{{"result":{{"id":3,"text":{result}}},"jsonrpc":"2.0", "id" : 8 }}"#
+            )),
+            Some(8.into())
+        );
+        assert_eq!(
+            error(&format!(
+                r#"{{"jsonrpc":"2.0","method":"notifications/message","params":{result}}}"#
+            )),
+            None
+        );
+    }
+}
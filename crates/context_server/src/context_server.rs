@@ -1,19 +1,35 @@
+pub mod builtin;
 pub mod client;
+pub mod egress_proxy;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fake_server;
+pub mod message_log;
+pub mod middleware;
+pub mod openapi;
 pub mod protocol;
+pub mod rate_limiter;
+pub mod recording;
+pub mod sandbox;
+pub mod trace_file;
 pub mod transport;
 pub mod types;
 
 use std::fmt::Display;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context as _, Result, bail};
 use client::Client;
 use collections::HashMap;
 use gpui::AsyncApp;
-use parking_lot::RwLock;
+use message_log::MessageLog;
+use parking_lot::{Mutex, RwLock};
+use protocol::ContextServerFeatures;
+use rate_limiter::RateLimiter;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use trace_file::TraceFile;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ContextServerId(pub Arc<str>);
@@ -24,13 +40,110 @@ impl Display for ContextServerId {
     }
 }
 
+/// The command that runs a context server.
+///
+/// `${worktree_root}` in any of its fields is replaced with the root of the project's first
+/// worktree.
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
 pub struct ContextServerCommand {
     pub path: String,
     pub args: Vec<String>,
     pub env: Option<HashMap<String, String>>,
+    /// The directory to start the server in, instead of Zed's working directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
 }
 
+impl ContextServerCommand {
+    /// Returns whether the command depends on the project's worktrees.
+    pub fn uses_worktree_root(&self) -> bool {
+        let uses = |text: &str| text.contains(WORKTREE_ROOT_VARIABLE);
+        uses(&self.path)
+            || self.args.iter().any(|arg| uses(arg))
+            || self.env.iter().flatten().any(|(_, value)| uses(value))
+            || self.cwd.as_deref().is_some_and(uses)
+    }
+
+    /// Replaces `${worktree_root}` with `worktree_root` everywhere in the command, and resolves
+    /// its working directory.
+    pub fn expand_worktree_root(&mut self, worktree_root: Option<&Path>) -> Result<()> {
+        self.cwd = self
+            .working_directory(worktree_root)?
+            .map(|cwd| cwd.to_string_lossy().into_owned());
+        if !self.uses_worktree_root() {
+            return Ok(());
+        }
+        let worktree_root = worktree_root
+            .with_context(|| {
+                format!("{WORKTREE_ROOT_VARIABLE} can't be used without an open project")
+            })?
+            .to_string_lossy();
+        let expand =
+            |text: &mut String| *text = text.replace(WORKTREE_ROOT_VARIABLE, &worktree_root);
+        expand(&mut self.path);
+        self.args.iter_mut().for_each(expand);
+        self.env
+            .iter_mut()
+            .flatten()
+            .for_each(|(_, value)| expand(value));
+        Ok(())
+    }
+
+    /// Returns the directory to start the server in, with `${worktree_root}` replaced by
+    /// `worktree_root`.
+    pub fn working_directory(&self, worktree_root: Option<&Path>) -> Result<Option<PathBuf>> {
+        let Some(cwd) = self.cwd.as_deref() else {
+            return Ok(None);
+        };
+        let cwd = if cwd.contains(WORKTREE_ROOT_VARIABLE) {
+            let worktree_root = worktree_root.with_context(|| {
+                format!("{WORKTREE_ROOT_VARIABLE} can't be used without an open project")
+            })?;
+            cwd.replace(WORKTREE_ROOT_VARIABLE, &worktree_root.to_string_lossy())
+        } else {
+            cwd.to_string()
+        };
+        let cwd = PathBuf::from(cwd);
+        if !cwd.is_absolute() {
+            bail!("the working directory {cwd:?} must be an absolute path");
+        }
+        Ok(Some(cwd))
+    }
+
+    /// Replaces `${env:NAME}` in the arguments with the value of the environment variable `NAME`
+    /// in `env`, or in Zed's environment when `env` doesn't set it. This lets secrets that a
+    /// server only accepts as arguments stay out of the settings.
+    pub fn expand_env_variables(&mut self) {
+        for arg in &mut self.args {
+            let mut expanded = String::new();
+            let mut rest = arg.as_str();
+            while let Some(start) = rest.find(ENV_VARIABLE_PREFIX) {
+                let name_start = start + ENV_VARIABLE_PREFIX.len();
+                let Some(name_len) = rest[name_start..].find('}') else {
+                    break;
+                };
+                let name = &rest[name_start..name_start + name_len];
+                let value = self
+                    .env
+                    .as_ref()
+                    .and_then(|env| env.get(name).cloned())
+                    .or_else(|| std::env::var(name).ok());
+                if value.is_none() {
+                    log::warn!("the environment variable {name} isn't set");
+                }
+                expanded.push_str(&rest[..start]);
+                expanded.push_str(value.as_deref().unwrap_or_default());
+                rest = &rest[name_start + name_len + 1..];
+            }
+            expanded.push_str(rest);
+            *arg = expanded;
+        }
+    }
+}
+
+const WORKTREE_ROOT_VARIABLE: &str = "${worktree_root}";
+const ENV_VARIABLE_PREFIX: &str = "${env:";
+
 enum ContextServerTransport {
     Stdio(ContextServerCommand),
     Custom(Arc<dyn crate::transport::Transport>),
@@ -40,6 +153,13 @@ pub struct ContextServer {
     id: ContextServerId,
     client: RwLock<Option<Arc<crate::protocol::InitializedContextServerProtocol>>>,
     configuration: ContextServerTransport,
+    message_log: Arc<Mutex<MessageLog>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    max_message_bytes: Option<usize>,
+    tool_call_timeout: Option<Duration>,
+    features: ContextServerFeatures,
+    worktree_roots: Arc<[PathBuf]>,
+    trace_file: Arc<Mutex<Option<TraceFile>>>,
 }
 
 impl ContextServer {
@@ -47,6 +167,13 @@ impl ContextServer {
         Self {
             id,
             client: RwLock::new(None),
+            message_log: Arc::default(),
+            rate_limiter: None,
+            max_message_bytes: None,
+            tool_call_timeout: None,
+            features: ContextServerFeatures::default(),
+            worktree_roots: Arc::default(),
+            trace_file: Arc::default(),
             configuration: ContextServerTransport::Stdio(command),
         }
     }
@@ -55,10 +182,83 @@ impl ContextServer {
         Self {
             id,
             client: RwLock::new(None),
+            message_log: Arc::default(),
+            rate_limiter: None,
+            max_message_bytes: None,
+            tool_call_timeout: None,
+            features: ContextServerFeatures::default(),
+            worktree_roots: Arc::default(),
+            trace_file: Arc::default(),
             configuration: ContextServerTransport::Custom(transport),
         }
     }
 
+    /// Limits how many requests can be sent to this server per minute, across restarts and
+    /// projects.
+    pub fn with_max_requests_per_minute(mut self, max_requests_per_minute: Option<u32>) -> Self {
+        self.rate_limiter =
+            max_requests_per_minute.map(|max| RateLimiter::for_server(&self.id, max));
+        self
+    }
+
+    /// Limits the size of the messages the server may send, so that a runaway server can't
+    /// flood memory. Responses over the limit are answered with an error instead.
+    pub fn with_max_result_bytes(mut self, max_result_bytes: Option<u64>) -> Self {
+        self.max_message_bytes =
+            max_result_bytes.map(|max| usize::try_from(max).unwrap_or(usize::MAX));
+        self
+    }
+
+    /// Limits how long a tool call may take, which is also passed to the server in the call's
+    /// metadata so that it can bound its own work. Zero seconds means no limit.
+    pub fn with_tool_call_timeout(mut self, tool_call_timeout_seconds: Option<u64>) -> Self {
+        self.tool_call_timeout = tool_call_timeout_seconds.map(Duration::from_secs);
+        self
+    }
+
+    /// Limits the protocol features the server may use, where `worktree_roots` are the project's
+    /// worktrees that resources may be read from.
+    pub fn with_features(
+        mut self,
+        features: ContextServerFeatures,
+        worktree_roots: Vec<PathBuf>,
+    ) -> Self {
+        self.features = features;
+        self.worktree_roots = worktree_roots.into();
+        self
+    }
+
+    /// Appends the server's JSON-RPC messages to the file at `path`, if given.
+    pub fn with_trace_file(self, path: Option<PathBuf>) -> Self {
+        if let Err(error) = self.set_trace_file(path) {
+            log::error!("failed to trace context server {}: {error:#}", self.id);
+        }
+        self
+    }
+
+    /// Starts or stops appending the server's JSON-RPC messages to a file, while it's running.
+    pub fn set_trace_file(&self, path: Option<PathBuf>) -> Result<()> {
+        *self.trace_file.lock() = path.map(TraceFile::open).transpose()?;
+        Ok(())
+    }
+
+    /// Returns the file the server's JSON-RPC messages are appended to, if any.
+    pub fn trace_file_path(&self) -> Option<PathBuf> {
+        self.trace_file
+            .lock()
+            .as_ref()
+            .map(|trace_file| trace_file.path().to_path_buf())
+    }
+
+    /// Returns how long a request made now would be queued by the server's rate limit.
+    pub fn rate_limit_delay(&self) -> Duration {
+        self.rate_limiter
+            .as_ref()
+            .map_or(Duration::ZERO, |rate_limiter| {
+                rate_limiter.delay(Instant::now())
+            })
+    }
+
     pub fn id(&self) -> ContextServerId {
         self.id.clone()
     }
@@ -67,6 +267,12 @@ impl ContextServer {
         self.client.read().clone()
     }
 
+    /// Returns the recent traffic with this server, which is kept across restarts and only
+    /// logged while the log is subscribed to.
+    pub fn message_log(&self) -> Arc<Mutex<MessageLog>> {
+        self.message_log.clone()
+    }
+
     pub async fn start(self: Arc<Self>, cx: &AsyncApp) -> Result<()> {
         let client = match &self.configuration {
             ContextServerTransport::Stdio(command) => Client::stdio(
@@ -75,25 +281,44 @@ impl ContextServer {
                     executable: Path::new(&command.path).to_path_buf(),
                     args: command.args.clone(),
                     env: command.env.clone(),
+                    cwd: command.cwd.as_ref().map(PathBuf::from),
                 },
+                self.max_message_bytes,
                 cx.clone(),
             )?,
             ContextServerTransport::Custom(transport) => Client::new(
                 client::ContextServerId(self.id.0.clone()),
                 self.id().0,
                 transport.clone(),
+                self.max_message_bytes,
                 cx.clone(),
             )?,
         };
+        let message_log = self.message_log.clone();
+        client.on_io(move |kind, message| message_log.lock().push(kind, message));
+        let trace_file = self.trace_file.clone();
+        client.on_io(move |kind, message| {
+            if let Some(trace_file) = trace_file.lock().as_ref() {
+                trace_file.append(kind, message);
+            }
+        });
         self.initialize(client).await
     }
 
     async fn initialize(&self, client: Client) -> Result<()> {
         log::info!("starting context server {}", self.id);
-        let protocol = crate::protocol::ModelContextProtocol::new(client);
+        let protocol = crate::protocol::ModelContextProtocol::new(
+            client,
+            self.rate_limiter.clone(),
+            self.tool_call_timeout,
+            self.features,
+            self.worktree_roots.clone(),
+        );
         let client_info = types::Implementation {
             name: "Zed".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            title: None,
+            icons: None,
         };
         let initialized_protocol = protocol.initialize(client_info).await?;
 
@@ -115,3 +340,98 @@ impl ContextServer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_working_directory() {
+        let command = |cwd: Option<&str>| ContextServerCommand {
+            path: "server".into(),
+            args: Vec::new(),
+            env: None,
+            cwd: cwd.map(str::to_string),
+        };
+        let worktree_root = Path::new("/projects/app");
+
+        assert_eq!(
+            command(None)
+                .working_directory(Some(worktree_root))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            command(Some("${worktree_root}/packages/api"))
+                .working_directory(Some(worktree_root))
+                .unwrap(),
+            Some(PathBuf::from("/projects/app/packages/api"))
+        );
+        assert_eq!(
+            command(Some("/srv/tools")).working_directory(None).unwrap(),
+            Some(PathBuf::from("/srv/tools"))
+        );
+        assert!(
+            command(Some("${worktree_root}"))
+                .working_directory(None)
+                .is_err()
+        );
+        assert!(
+            command(Some("packages/api"))
+                .working_directory(Some(worktree_root))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_expand_worktree_root() {
+        let mut command = ContextServerCommand {
+            path: "${worktree_root}/bin/server".into(),
+            args: vec!["--config".into(), "${worktree_root}/server.json".into()],
+            env: Some(HashMap::from_iter([(
+                "DATA_DIR".into(),
+                "${worktree_root}/data".into(),
+            )])),
+            cwd: Some("${worktree_root}".into()),
+        };
+        assert!(command.uses_worktree_root());
+        assert!(command.clone().expand_worktree_root(None).is_err());
+
+        command
+            .expand_worktree_root(Some(Path::new("/projects/app")))
+            .unwrap();
+        assert_eq!(command.path, "/projects/app/bin/server");
+        assert_eq!(command.args, ["--config", "/projects/app/server.json"]);
+        assert_eq!(command.env.unwrap()["DATA_DIR"], "/projects/app/data");
+        assert_eq!(command.cwd.as_deref(), Some("/projects/app"));
+    }
+
+    #[test]
+    fn test_expand_env_variables() {
+        let mut command = ContextServerCommand {
+            path: "server".into(),
+            args: vec![
+                "${env:DATABASE_URL}".into(),
+                "--user=${env:DB_USER}@${env:DB_HOST}".into(),
+                "${env:ZED_TEST_UNSET_VARIABLE}".into(),
+                "${env:UNTERMINATED".into(),
+            ],
+            env: Some(HashMap::from_iter([
+                ("DATABASE_URL".into(), "postgres://localhost/app".into()),
+                ("DB_USER".into(), "admin".into()),
+                ("DB_HOST".into(), "db".into()),
+            ])),
+            cwd: None,
+        };
+        command.expand_env_variables();
+        assert_eq!(
+            command.args,
+            [
+                "postgres://localhost/app",
+                "--user=admin@db",
+                "",
+                "${env:UNTERMINATED"
+            ]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! A proxy that lets a sandboxed context server reach an allowlist of hosts.
+//!
+//! The sandbox only lets the server connect to this proxy, which accepts HTTP `CONNECT` requests
+//! for the allowed hosts and tunnels them through, so HTTPS traffic to those hosts keeps working
+//! while connections anywhere else are refused.
+
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result, anyhow, bail};
+use futures::{
+    AsyncBufRead, AsyncBufReadExt as _, AsyncRead, AsyncReadExt as _, AsyncWrite,
+    AsyncWriteExt as _, io::BufReader,
+};
+use gpui::{App, BackgroundExecutor, Task};
+use smol::net::TcpStream;
+
+/// Clients only send a request line and a few headers before tunneling, so longer requests are
+/// refused instead of being buffered without bound.
+const MAX_HEADER_LINE_BYTES: usize = 8 * 1024;
+const MAX_HEADER_LINES: usize = 100;
+
+/// Where a sandboxed server reaches the proxy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EgressProxyAddress {
+    /// A port on the loopback interface, which the macOS sandbox shares with Zed.
+    Port(u16),
+    /// A Unix socket, since a Linux sandbox's network namespace has a loopback interface of its
+    /// own. The socket is bound into the sandbox, where a port is forwarded to it.
+    Socket(PathBuf),
+}
+
+pub struct EgressProxy {
+    address: EgressProxyAddress,
+    _accept_connections: Task<()>,
+}
+
+impl EgressProxy {
+    #[cfg(not(target_os = "linux"))]
+    pub fn start(allowed_hosts: Vec<String>, cx: &App) -> Result<Self> {
+        use std::net::{Ipv4Addr, SocketAddr};
+
+        let listener = std::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .context("binding egress proxy")?;
+        let port = listener.local_addr()?.port();
+        let listener = smol::net::TcpListener::try_from(listener)?;
+        let executor = cx.background_executor().clone();
+        let accept_connections = cx.background_spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                spawn_tunnel(stream, allowed_hosts.clone(), &executor);
+            }
+        });
+        Ok(Self {
+            address: EgressProxyAddress::Port(port),
+            _accept_connections: accept_connections,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn start(allowed_hosts: Vec<String>, cx: &App) -> Result<Self> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static NEXT_SOCKET_ID: AtomicUsize = AtomicUsize::new(0);
+        // Socket paths are limited to around 100 bytes, so the socket goes in the temporary
+        // directory rather than in the server's scratch directory.
+        let socket_path = std::env::temp_dir().join(format!(
+            "zed-egress-proxy-{}-{}.sock",
+            std::process::id(),
+            NEXT_SOCKET_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::remove_file(&socket_path).ok();
+        let listener = std::os::unix::net::UnixListener::bind(&socket_path)
+            .with_context(|| format!("binding egress proxy to {socket_path:?}"))?;
+        let listener = smol::net::unix::UnixListener::try_from(listener)?;
+        let executor = cx.background_executor().clone();
+        let accept_connections = cx.background_spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                spawn_tunnel(stream, allowed_hosts.clone(), &executor);
+            }
+        });
+        Ok(Self {
+            address: EgressProxyAddress::Socket(socket_path),
+            _accept_connections: accept_connections,
+        })
+    }
+
+    pub fn address(&self) -> &EgressProxyAddress {
+        &self.address
+    }
+}
+
+impl Drop for EgressProxy {
+    fn drop(&mut self) {
+        if let EgressProxyAddress::Socket(socket_path) = &self.address {
+            std::fs::remove_file(socket_path).ok();
+        }
+    }
+}
+
+fn spawn_tunnel<S>(stream: S, allowed_hosts: Vec<String>, executor: &BackgroundExecutor)
+where
+    S: AsyncRead + AsyncWrite + Clone + Unpin + Send + 'static,
+{
+    executor
+        .spawn(async move {
+            if let Err(error) = tunnel(stream, &allowed_hosts).await {
+                log::debug!("egress proxy connection failed: {error:#}");
+            }
+        })
+        .detach();
+}
+
+async fn tunnel<S>(stream: S, allowed_hosts: &[String]) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Clone + Unpin,
+{
+    let mut reader = BufReader::new(stream.clone());
+    let mut request_line = String::new();
+    read_header_line(&mut reader, &mut request_line).await?;
+    // Skip the headers, which the tunnel doesn't need.
+    let mut header_lines = 0;
+    loop {
+        let mut header = String::new();
+        if read_header_line(&mut reader, &mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        header_lines += 1;
+        if header_lines > MAX_HEADER_LINES {
+            bail!("the request has too many headers");
+        }
+    }
+
+    let mut client = stream;
+    let target = match parse_connect_request(&request_line) {
+        Some((host, port)) if is_host_allowed(host, allowed_hosts) => {
+            if host.contains(':') {
+                format!("[{host}]:{port}")
+            } else {
+                format!("{host}:{port}")
+            }
+        }
+        Some((host, _)) => {
+            client.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n").await?;
+            return Err(anyhow!("denied connection to {host}"));
+        }
+        None => {
+            client
+                .write_all(b"HTTP/1.1 405 Method Not Allowed\r\n\r\n")
+                .await?;
+            return Err(anyhow!("unsupported request {:?}", request_line.trim()));
+        }
+    };
+
+    let upstream = TcpStream::connect(target.as_str())
+        .await
+        .with_context(|| format!("connecting to {target}"))?;
+    client
+        .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+        .await?;
+    // Read from the buffered reader, since it may hold bytes sent right after the request.
+    let mut upstream_writer = upstream.clone();
+    futures::future::try_join(
+        futures::io::copy(reader, &mut upstream_writer),
+        futures::io::copy(upstream, &mut client),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Reads a line of the request, failing when it's longer than [`MAX_HEADER_LINE_BYTES`].
+async fn read_header_line(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    line: &mut String,
+) -> Result<usize> {
+    let read = reader
+        .take(MAX_HEADER_LINE_BYTES as u64)
+        .read_line(line)
+        .await?;
+    if read == MAX_HEADER_LINE_BYTES && !line.ends_with('\n') {
+        bail!("a line of the request is too long");
+    }
+    Ok(read)
+}
+
+/// Parses a request line of the form `CONNECT host:port HTTP/1.1`.
+fn parse_connect_request(request_line: &str) -> Option<(&str, u16)> {
+    let mut parts = request_line.split_whitespace();
+    if parts.next()? != "CONNECT" {
+        return None;
+    }
+    let (host, port) = parts.next()?.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host, port.parse().ok()?))
+}
+
+/// Returns whether `host` is in the allowlist, where `*.example.com` allows any subdomain of
+/// `example.com`.
+fn is_host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    let host = host.to_ascii_lowercase();
+    allowed_hosts.iter().any(|allowed| {
+        let allowed = allowed.to_ascii_lowercase();
+        match allowed.strip_prefix("*.") {
+            Some(domain) => host
+                .strip_suffix(domain)
+                .is_some_and(|subdomain| subdomain.ends_with('.')),
+            None => host == allowed,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    #[gpui::test]
+    async fn test_read_header_line() {
+        let mut reader = Cursor::new(b"CONNECT api.github.com:443 HTTP/1.1\r\n".to_vec());
+        let mut line = String::new();
+        read_header_line(&mut reader, &mut line).await.unwrap();
+        assert_eq!(line, "CONNECT api.github.com:443 HTTP/1.1\r\n");
+
+        let mut reader = Cursor::new(vec![b'a'; MAX_HEADER_LINE_BYTES * 2]);
+        let mut line = String::new();
+        assert!(read_header_line(&mut reader, &mut line).await.is_err());
+        assert_eq!(line.len(), MAX_HEADER_LINE_BYTES);
+    }
+
+    #[test]
+    fn test_parse_connect_request() {
+        assert_eq!(
+            parse_connect_request("CONNECT api.github.com:443 HTTP/1.1\r\n"),
+            Some(("api.github.com", 443))
+        );
+        assert_eq!(
+            parse_connect_request("CONNECT [::1]:8080 HTTP/1.1\r\n"),
+            Some(("::1", 8080))
+        );
+        assert_eq!(
+            parse_connect_request("GET http://example.com/ HTTP/1.1\r\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_host_allowed() {
+        let allowed_hosts = vec!["api.github.com".to_string(), "*.example.com".to_string()];
+        assert!(is_host_allowed("api.github.com", &allowed_hosts));
+        assert!(is_host_allowed("API.GitHub.com", &allowed_hosts));
+        assert!(is_host_allowed("a.b.example.com", &allowed_hosts));
+        assert!(!is_host_allowed("example.com", &allowed_hosts));
+        assert!(!is_host_allowed("notexample.com", &allowed_hosts));
+        assert!(!is_host_allowed("github.com", &allowed_hosts));
+    }
+}
@@ -0,0 +1,363 @@
+//! An in-memory context server for tests, with scriptable tools and responses.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use collections::HashMap;
+use futures::channel::mpsc;
+use futures::lock::Mutex as AsyncMutex;
+use futures::{Stream, StreamExt as _};
+use gpui::BackgroundExecutor;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+
+use crate::client::{INTERNAL_ERROR, METHOD_NOT_FOUND};
+use crate::transport::Transport;
+use crate::types::{self, RequestType};
+use crate::{ContextServer, ContextServerId};
+
+type RequestHandler = Arc<dyn Fn(Value) -> Result<Value, String> + Send + Sync>;
+
+/// A request that was sent to a [`FakeContextServer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeRequest {
+    pub method: String,
+    pub params: Value,
+}
+
+/// A context server that runs in memory, for testing code that talks to context servers.
+///
+/// It answers `initialize`, `ping`, `tools/list` and `tools/call` on its own, based on the tools
+/// registered with [`FakeContextServer::add_tool`]. Any other method can be scripted with
+/// [`FakeContextServer::on_request`], and every request is recorded so tests can assert on it.
+#[derive(Clone)]
+pub struct FakeContextServer {
+    state: Arc<Mutex<FakeServerState>>,
+    executor: BackgroundExecutor,
+    tx: mpsc::UnboundedSender<String>,
+    rx: Arc<AsyncMutex<mpsc::UnboundedReceiver<String>>>,
+}
+
+struct FakeServerState {
+    name: String,
+    instructions: Option<String>,
+    /// Serialized tool definitions, or `None` if the server doesn't support tools.
+    tools: Option<Vec<Value>>,
+    tool_handlers: HashMap<String, RequestHandler>,
+    request_handlers: HashMap<String, RequestHandler>,
+    pending_failures: HashMap<String, VecDeque<String>>,
+    latency: Duration,
+    requests: Vec<FakeRequest>,
+}
+
+impl FakeContextServer {
+    pub fn new(name: impl Into<String>, executor: BackgroundExecutor) -> Self {
+        let (tx, rx) = mpsc::unbounded();
+        Self {
+            state: Arc::new(Mutex::new(FakeServerState {
+                name: name.into(),
+                instructions: None,
+                tools: None,
+                tool_handlers: HashMap::default(),
+                request_handlers: HashMap::default(),
+                pending_failures: HashMap::default(),
+                latency: Duration::ZERO,
+                requests: Vec::new(),
+            })),
+            executor,
+            tx,
+            rx: Arc::new(AsyncMutex::new(rx)),
+        }
+    }
+
+    /// Creates a [`ContextServer`] that talks to this fake server.
+    pub fn context_server(&self, id: ContextServerId) -> Arc<ContextServer> {
+        Arc::new(ContextServer::new(id, Arc::new(self.clone())))
+    }
+
+    /// Sets the instructions the server returns when it's initialized.
+    pub fn set_instructions(&self, instructions: impl Into<String>) {
+        self.state.lock().instructions = Some(instructions.into());
+    }
+
+    /// Adds a tool to the ones listed by `tools/list`, and makes the server advertise support
+    /// for tools when it's initialized.
+    pub fn add_tool(&self, tool: types::Tool) {
+        let tool = serde_json::to_value(tool).expect("tools are serializable");
+        self.state.lock().tools.get_or_insert_default().push(tool);
+    }
+
+    /// Answers every call to the tool named `name` with `response`.
+    pub fn set_tool_response(&self, name: impl Into<String>, response: types::CallToolResponse) {
+        let response = serde_json::to_value(response).expect("responses are serializable");
+        self.state
+            .lock()
+            .tool_handlers
+            .insert(name.into(), Arc::new(move |_| Ok(response.clone())));
+    }
+
+    /// Answers calls to the tool named `name` by passing their arguments to `handler`.
+    pub fn on_tool_call(
+        &self,
+        name: impl Into<String>,
+        handler: impl Fn(Value) -> types::CallToolResponse + Send + Sync + 'static,
+    ) {
+        self.state.lock().tool_handlers.insert(
+            name.into(),
+            Arc::new(move |arguments| {
+                serde_json::to_value(handler(arguments)).map_err(|error| error.to_string())
+            }),
+        );
+    }
+
+    /// Answers requests for `method` by passing their params to `handler`, overriding the
+    /// built-in behavior for that method. Returning an error sends a JSON-RPC error.
+    pub fn on_request(
+        &self,
+        method: impl Into<String>,
+        handler: impl Fn(Value) -> Result<Value, String> + Send + Sync + 'static,
+    ) {
+        self.state
+            .lock()
+            .request_handlers
+            .insert(method.into(), Arc::new(handler));
+    }
+
+    /// Makes the next request for `method` fail with a JSON-RPC error containing `message`.
+    pub fn fail_next_request(&self, method: impl Into<String>, message: impl Into<String>) {
+        self.state
+            .lock()
+            .pending_failures
+            .entry(method.into())
+            .or_default()
+            .push_back(message.into());
+    }
+
+    /// Delays every response by `latency`. With a test executor, advance the clock to deliver
+    /// delayed responses.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().latency = latency;
+    }
+
+    /// Sends a notification to the client, such as the progress of a request.
+    pub fn send_notification(&self, method: &str, params: Value) {
+        let notification = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        self.tx.unbounded_send(notification.to_string()).ok();
+    }
+
+    /// Returns every request received so far, in order.
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.state.lock().requests.clone()
+    }
+
+    /// Returns the params of every request received so far for `method`.
+    pub fn requests_for(&self, method: &str) -> Vec<Value> {
+        self.state
+            .lock()
+            .requests
+            .iter()
+            .filter(|request| request.method == method)
+            .map(|request| request.params.clone())
+            .collect()
+    }
+
+    /// Returns the name and arguments of every tool call received so far.
+    pub fn tool_calls(&self) -> Vec<(String, Value)> {
+        self.requests_for(RequestType::CallTool.as_str())
+            .into_iter()
+            .map(|params| {
+                let name = params["name"].as_str().unwrap_or_default().to_string();
+                (name, params["arguments"].clone())
+            })
+            .collect()
+    }
+
+    fn respond(&self, method: &str, params: Value) -> Result<Value, (i32, String)> {
+        let handler = {
+            let mut state = self.state.lock();
+            state.requests.push(FakeRequest {
+                method: method.to_string(),
+                params: params.clone(),
+            });
+            if let Some(message) = state
+                .pending_failures
+                .get_mut(method)
+                .and_then(|failures| failures.pop_front())
+            {
+                return Err((INTERNAL_ERROR, message));
+            }
+            state.request_handlers.get(method).cloned()
+        };
+        // Handlers run without the state locked, so they can script the server further.
+        if let Some(handler) = handler {
+            return handler(params).map_err(|message| (INTERNAL_ERROR, message));
+        }
+
+        match RequestType::try_from(method) {
+            Ok(RequestType::Initialize) => {
+                let state = self.state.lock();
+                Ok(json!({
+                    "protocolVersion": types::LATEST_PROTOCOL_VERSION,
+                    "serverInfo": { "name": state.name, "version": "1.0.0" },
+                    "instructions": state.instructions,
+                    "capabilities": if state.tools.is_some() {
+                        json!({ "tools": {} })
+                    } else {
+                        json!({})
+                    },
+                }))
+            }
+            Ok(RequestType::Ping) => Ok(json!({})),
+            Ok(RequestType::ListTools) => Ok(json!({
+                "tools": self.state.lock().tools.clone().unwrap_or_default(),
+            })),
+            Ok(RequestType::CallTool) => {
+                let name = params["name"].as_str().unwrap_or_default();
+                let handler = self.state.lock().tool_handlers.get(name).cloned();
+                let handler =
+                    handler.ok_or_else(|| (INTERNAL_ERROR, format!("unknown tool: {name}")))?;
+                handler(params["arguments"].clone()).map_err(|message| (INTERNAL_ERROR, message))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for FakeContextServer {
+    async fn send(&self, message: String) -> Result<()> {
+        let message = serde_json::from_str::<Value>(&message).context("parsing a message")?;
+        // Notifications don't get a response.
+        let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) else {
+            return Ok(());
+        };
+
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let response = match self.respond(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+
+        let latency = self.state.lock().latency;
+        if latency.is_zero() {
+            self.tx
+                .unbounded_send(response.to_string())
+                .context("sending a response")?;
+        } else {
+            let tx = self.tx.clone();
+            let executor = self.executor.clone();
+            self.executor
+                .spawn(async move {
+                    executor.timer(latency).await;
+                    tx.unbounded_send(response.to_string()).ok();
+                })
+                .detach();
+        }
+        Ok(())
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        let rx = self.rx.clone();
+        let executor = self.executor.clone();
+        Box::pin(futures::stream::unfold(rx, move |rx| {
+            let executor = executor.clone();
+            async move {
+                let mut rx_guard = rx.lock().await;
+                executor.simulate_random_delay().await;
+                let message = rx_guard.next().await?;
+                drop(rx_guard);
+                Some((message, rx))
+            }
+        }))
+    }
+
+    fn receive_err(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        Box::pin(futures::stream::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+
+    #[gpui::test]
+    async fn test_fake_context_server(cx: &mut TestAppContext) {
+        let fake = FakeContextServer::new("fake", cx.executor());
+        fake.add_tool(types::Tool {
+            name: "echo".into(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            annotations: None,
+        });
+        fake.on_tool_call("echo", |arguments| types::CallToolResponse {
+            content: vec![types::ToolResponseContent::Text {
+                text: arguments["text"].as_str().unwrap_or_default().to_string(),
+            }],
+            is_error: None,
+            structured_content: None,
+            meta: None,
+        });
+
+        let server = fake.context_server(ContextServerId("fake".into()));
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        let tools = protocol.list_tools().await.unwrap();
+        assert_eq!(tools.tools.len(), 1);
+        assert_eq!(tools.tools[0].name, "echo");
+
+        let arguments = HashMap::from_iter([("text".to_string(), json!("hello"))]);
+        let response = protocol
+            .run_tool("echo", Some(arguments.clone()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            &response.content[..],
+            [types::ToolResponseContent::Text { text }] if text == "hello"
+        ));
+        assert_eq!(
+            fake.tool_calls(),
+            vec![("echo".to_string(), json!({ "text": "hello" }))]
+        );
+
+        fake.fail_next_request("tools/call", "server exploded");
+        let error = protocol
+            .run_tool("echo", Some(arguments.clone()))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("server exploded"));
+
+        fake.set_latency(Duration::from_secs(1));
+        let response = cx.background_spawn({
+            let protocol = protocol.clone();
+            async move { protocol.run_tool("echo", Some(arguments)).await }
+        });
+        cx.run_until_parked();
+        assert_eq!(fake.tool_calls().len(), 3);
+        cx.executor().advance_clock(Duration::from_secs(1));
+        assert!(response.await.is_ok());
+
+        assert_eq!(
+            fake.requests()
+                .iter()
+                .map(|request| request.method.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "initialize",
+                "tools/list",
+                "tools/call",
+                "tools/call",
+                "tools/call"
+            ]
+        );
+    }
+}
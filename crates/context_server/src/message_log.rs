@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use postage::{stream::Stream as _, watch};
+use serde_json::Value;
+
+use crate::client::IoKind;
+
+/// How many messages are kept for each context server.
+const MAX_LOGGED_MESSAGES: usize = 1000;
+
+/// Object keys whose values are replaced before a message is logged, matched case-insensitively
+/// against any part of the key.
+const SENSITIVE_KEY_FRAGMENTS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "authorization",
+    "credential",
+    "password",
+    "secret",
+    "token",
+];
+
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone)]
+pub struct LoggedMessage {
+    pub kind: IoKind,
+    pub message: String,
+}
+
+/// The most recent JSON-RPC traffic with a context server, with sensitive values redacted.
+///
+/// Messages are only logged while something is subscribed to the log, so that servers nobody
+/// inspects don't pay for redacting every message.
+pub struct MessageLog {
+    messages: VecDeque<LoggedMessage>,
+    changed_tx: watch::Sender<()>,
+    changed_rx: watch::Receiver<()>,
+    subscribers: Arc<AtomicUsize>,
+}
+
+/// Keeps a [`MessageLog`] logging messages until it's dropped.
+pub struct MessageLogSubscription {
+    changed_rx: watch::Receiver<()>,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl MessageLogSubscription {
+    /// Waits until messages are logged or cleared.
+    pub async fn recv(&mut self) -> Option<()> {
+        self.changed_rx.recv().await
+    }
+}
+
+impl Drop for MessageLogSubscription {
+    fn drop(&mut self) {
+        self.subscribers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Default for MessageLog {
+    fn default() -> Self {
+        let (changed_tx, changed_rx) = watch::channel();
+        Self {
+            messages: VecDeque::new(),
+            changed_tx,
+            changed_rx,
+            subscribers: Arc::default(),
+        }
+    }
+}
+
+impl MessageLog {
+    pub fn push(&mut self, kind: IoKind, message: &str) {
+        if self.subscribers.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        if self.messages.len() == MAX_LOGGED_MESSAGES {
+            self.messages.pop_front();
+        }
+        let message = match kind {
+            IoKind::Send | IoKind::Receive => redact_message(message),
+            IoKind::StdErr => message.trim_end().to_string(),
+        };
+        self.messages.push_back(LoggedMessage { kind, message });
+        *self.changed_tx.borrow_mut() = ();
+    }
+
+    pub fn messages(&self) -> impl DoubleEndedIterator<Item = &LoggedMessage> {
+        self.messages.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        *self.changed_tx.borrow_mut() = ();
+    }
+
+    /// Starts logging messages, and returns a subscription that is notified whenever messages
+    /// are logged or cleared.
+    pub fn subscribe(&self) -> MessageLogSubscription {
+        self.subscribers.fetch_add(1, Ordering::SeqCst);
+        MessageLogSubscription {
+            changed_rx: self.changed_rx.clone(),
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+/// Replaces the values of sensitive-looking object keys in a JSON message. Messages that aren't
+/// valid JSON are logged as-is.
+pub fn redact_message(message: &str) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(message) else {
+        return message.to_string();
+    };
+    redact_value(&mut value);
+    value.to_string()
+}
+
+/// Returns whether a key, such as an object key or the name of an environment variable, looks
+/// like it holds a credential.
+pub fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_KEY_FRAGMENTS
+        .iter()
+        .any(|fragment| key.contains(fragment))
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if is_sensitive_key(key) && !value.is_object() && !value.is_array() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_message() {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {
+                "name": "fetch",
+                "arguments": {
+                    "url": "https://example.com",
+                    "headers": [{ "Authorization": "Bearer abc" }],
+                    "githubToken": "ghp_123",
+                    "tokens": { "count": 3 },
+                },
+            },
+        });
+
+        let redacted = redact_message(&message.to_string());
+        let redacted = serde_json::from_str::<Value>(&redacted).unwrap();
+        let arguments = &redacted["params"]["arguments"];
+        assert_eq!(arguments["url"], "https://example.com");
+        assert_eq!(arguments["headers"][0]["Authorization"], REDACTED);
+        assert_eq!(arguments["githubToken"], REDACTED);
+        assert_eq!(arguments["tokens"]["count"], 3);
+
+        assert_eq!(redact_message("not json"), "not json");
+    }
+
+    #[test]
+    fn test_message_log_is_bounded() {
+        let mut log = MessageLog::default();
+        let _subscription = log.subscribe();
+        for ix in 0..MAX_LOGGED_MESSAGES + 10 {
+            log.push(IoKind::Send, &ix.to_string());
+        }
+        assert_eq!(log.messages().count(), MAX_LOGGED_MESSAGES);
+        assert_eq!(log.messages().next().unwrap().message, "10");
+    }
+
+    #[test]
+    fn test_message_log_only_logs_while_subscribed() {
+        let mut log = MessageLog::default();
+        log.push(IoKind::Send, "before");
+        let subscription = log.subscribe();
+        log.push(IoKind::Send, "during");
+        drop(subscription);
+        log.push(IoKind::Send, "after");
+        assert_eq!(
+            log.messages()
+                .map(|message| message.message.as_str())
+                .collect::<Vec<_>>(),
+            ["during"]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Middleware that runs around every context server tool call, whichever server the tool
+//! belongs to. It can observe calls, for example to trace them, or rewrite them, for example to
+//! add credentials to a call's input or to strip personal data from its output.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use crate::{ContextServerId, types};
+
+/// The tool call a [`ToolMiddleware`] is running around.
+#[derive(Clone, Copy, Debug)]
+pub struct ToolCall<'a> {
+    pub server_id: &'a ContextServerId,
+    /// The name of the tool on its server, without the prefix it's offered to the model under.
+    pub tool_name: &'a str,
+}
+
+/// Observes or rewrites the input and output of tool calls.
+///
+/// Middleware runs in the order it was registered. An error from either method fails the call,
+/// and when it's returned from [`ToolMiddleware::process_input`], the call isn't sent.
+#[async_trait]
+pub trait ToolMiddleware: Send + Sync {
+    /// Runs before the user is asked to confirm the call, with the arguments the model gave it,
+    /// so the confirmation shows what will actually be sent.
+    async fn process_input(
+        &self,
+        _call: ToolCall<'_>,
+        _arguments: &mut Map<String, Value>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs on the server's response, before anything else sees it. Errors and timeouts are
+    /// passed through as a response with `is_error` set, so they can be redacted too.
+    async fn process_output(
+        &self,
+        _call: ToolCall<'_>,
+        _response: &mut types::CallToolResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
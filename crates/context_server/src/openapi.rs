@@ -0,0 +1,735 @@
+//! Offers the operations of an HTTP API described by an OpenAPI document as tools, so that
+//! simple REST APIs can be used without running a context server for them.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context as _, Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use url::Url;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::types;
+
+/// The HTTP methods an OpenAPI path item can describe operations for.
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// How many `$ref`s to follow when inlining schemas, so that recursive schemas terminate.
+const MAX_REF_DEPTH: usize = 8;
+
+/// Models reject tool names longer than this.
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// An HTTP API whose operations are offered as tools.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+pub struct ContextServerOpenApi {
+    /// The URL, or local path, of the API's OpenAPI 3 document, in JSON or YAML.
+    pub spec_url: String,
+    /// The URL that the operations' paths are relative to.
+    ///
+    /// Defaults to the first of the document's `servers`.
+    pub base_url: Option<String>,
+    /// Headers to send with every request, such as `Authorization`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParameterLocation {
+    Path,
+    Query,
+    Header,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct OpenApiParameter {
+    name: String,
+    location: ParameterLocation,
+}
+
+/// An operation of the API, and the tool that runs it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiOperation {
+    pub tool: types::Tool,
+    method: String,
+    path: String,
+    parameters: Vec<OpenApiParameter>,
+    body: Option<OpenApiBody>,
+}
+
+/// The request body of an operation, and the argument it's passed in.
+#[derive(Debug, Clone, PartialEq)]
+struct OpenApiBody {
+    argument: String,
+    content_type: String,
+}
+
+/// A request to make for a call to an operation's tool.
+#[derive(Debug, PartialEq)]
+pub struct OpenApiRequest {
+    pub method: String,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+/// Parses an OpenAPI document, which can be written in JSON or YAML.
+pub fn parse_spec(content: &str) -> Result<Value> {
+    if let Ok(spec) = serde_json::from_str(content) {
+        return Ok(spec);
+    }
+    let mut documents = YamlLoader::load_from_str(content).context("parsing the document")?;
+    if documents.is_empty() {
+        return Err(anyhow!("the document is empty"));
+    }
+    Ok(yaml_to_json(documents.swap_remove(0)))
+}
+
+fn yaml_to_json(yaml: Yaml) -> Value {
+    match yaml {
+        Yaml::Real(real) => real
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map_or(Value::String(real), Value::Number),
+        Yaml::Integer(integer) => integer.into(),
+        Yaml::String(string) => string.into(),
+        Yaml::Boolean(boolean) => boolean.into(),
+        Yaml::Array(items) => items.into_iter().map(yaml_to_json).collect(),
+        // Keys such as response status codes can be numbers, but JSON keys are always strings.
+        Yaml::Hash(entries) => Value::Object(
+            entries
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let key = match yaml_to_json(key) {
+                        Value::String(key) => key,
+                        Value::Null => return None,
+                        key => key.to_string(),
+                    };
+                    Some((key, yaml_to_json(value)))
+                })
+                .collect(),
+        ),
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Value::Null,
+    }
+}
+
+/// Returns the URL that operation paths are relative to, resolving a relative server URL in the
+/// document against the document's own URL.
+pub fn base_url(settings: &ContextServerOpenApi, spec: &Value) -> Result<String> {
+    if let Some(base_url) = &settings.base_url {
+        return Ok(base_url.clone());
+    }
+    let server_url = spec["servers"][0]["url"]
+        .as_str()
+        .context("the OpenAPI document has no `servers`, so `base_url` must be set")?;
+    match Url::parse(server_url) {
+        Ok(url) => Ok(url.to_string()),
+        Err(_) => Ok(Url::parse(&settings.spec_url)
+            .and_then(|spec_url| spec_url.join(server_url))
+            .with_context(|| format!("resolving the server URL {server_url:?}"))?
+            .to_string()),
+    }
+}
+
+/// Builds a tool for each operation in an OpenAPI 3 document.
+pub fn operations(spec: &Value) -> Result<Vec<OpenApiOperation>> {
+    let paths = spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .context("the OpenAPI document has no `paths`")?;
+
+    let mut operations = Vec::<OpenApiOperation>::new();
+    for (path, item) in paths {
+        let item = resolve(spec, item);
+        for method in METHODS {
+            let Some(operation) = item.get(*method) else {
+                continue;
+            };
+            let operation = resolve(spec, operation);
+
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            let mut parameters = Vec::<OpenApiParameter>::new();
+            // Parameters of the operation override those shared by the path's operations.
+            let declared_parameters = item["parameters"]
+                .as_array()
+                .into_iter()
+                .chain(operation["parameters"].as_array())
+                .flatten()
+                .map(|parameter| resolve(spec, parameter));
+            for parameter in declared_parameters {
+                let Some(name) = parameter["name"].as_str() else {
+                    continue;
+                };
+                let location = match parameter["in"].as_str() {
+                    Some("path") => ParameterLocation::Path,
+                    Some("query") => ParameterLocation::Query,
+                    Some("header") => ParameterLocation::Header,
+                    _ => continue,
+                };
+                let mut schema = inline_refs(spec, &parameter["schema"], 0);
+                if !schema.is_object() {
+                    schema = json!({ "type": "string" });
+                }
+                if let Some(description) = parameter["description"].as_str() {
+                    schema["description"] = description.into();
+                }
+                properties.insert(name.to_string(), schema);
+                required.retain(|required: &String| required != name);
+                if location == ParameterLocation::Path || parameter["required"] == true {
+                    required.push(name.to_string());
+                }
+                parameters.retain(|parameter| parameter.name != name);
+                parameters.push(OpenApiParameter {
+                    name: name.to_string(),
+                    location,
+                });
+            }
+
+            let request_body = resolve(spec, &operation["requestBody"]);
+            let mut body = None;
+            if let Some((content_type, media_type)) = request_body["content"]
+                .as_object()
+                .and_then(body_media_type)
+            {
+                let mut body_schema = inline_refs(spec, &media_type["schema"], 0);
+                if !body_schema.is_object() {
+                    body_schema = json!({});
+                }
+                if let Some(description) = request_body["description"].as_str() {
+                    body_schema["description"] = description.into();
+                }
+                // A parameter can be called `body` too, so the body's argument is renamed then.
+                let mut argument = "body".to_string();
+                while properties.contains_key(&argument) {
+                    argument.insert_str(0, "request_");
+                }
+                properties.insert(argument.clone(), body_schema);
+                if request_body["required"] == true {
+                    required.push(argument.clone());
+                }
+                body = Some(OpenApiBody {
+                    argument,
+                    content_type: content_type.clone(),
+                });
+            }
+
+            let name = unique_tool_name(
+                &tool_name(operation["operationId"].as_str(), method, path),
+                &operations,
+            );
+
+            let summary = [&operation["summary"], &operation["description"]]
+                .into_iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let description = if summary.is_empty() {
+                format!("{} {path}", method.to_uppercase())
+            } else {
+                format!("{summary}\n\n{} {path}", method.to_uppercase())
+            };
+
+            operations.push(OpenApiOperation {
+                tool: types::Tool {
+                    name,
+                    description: Some(description),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": properties,
+                        "required": required,
+                    }),
+                    annotations: None,
+                },
+                method: method.to_uppercase(),
+                path: path.clone(),
+                parameters,
+                body,
+            });
+        }
+    }
+    Ok(operations)
+}
+
+impl OpenApiOperation {
+    /// Builds the request for a call to this operation's tool.
+    pub fn request(
+        &self,
+        base_url: &str,
+        headers: &BTreeMap<String, String>,
+        arguments: &Map<String, Value>,
+    ) -> Result<OpenApiRequest> {
+        let mut path = self.path.clone();
+        for parameter in &self.parameters {
+            if parameter.location == ParameterLocation::Path {
+                let value = arguments
+                    .get(&parameter.name)
+                    .with_context(|| format!("missing the `{}` argument", parameter.name))?;
+                path = path.replace(
+                    &format!("{{{}}}", parameter.name),
+                    &encode_path_segment(&argument_text(value)),
+                );
+            }
+        }
+
+        let mut url = Url::parse(&format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        ))
+        .with_context(|| format!("building the URL for {path}"))?;
+        let mut request_headers = headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        for parameter in &self.parameters {
+            let Some(value) = arguments.get(&parameter.name) else {
+                continue;
+            };
+            match parameter.location {
+                ParameterLocation::Path => {}
+                ParameterLocation::Query => {
+                    let values = match value {
+                        Value::Array(values) => values.iter().collect(),
+                        value => vec![value],
+                    };
+                    for value in values {
+                        url.query_pairs_mut()
+                            .append_pair(&parameter.name, &argument_text(value));
+                    }
+                }
+                ParameterLocation::Header => {
+                    request_headers.push((parameter.name.clone(), argument_text(value)));
+                }
+            }
+        }
+
+        let body = match &self.body {
+            Some(body) => match arguments.get(&body.argument) {
+                Some(value) => {
+                    request_headers.push(("Content-Type".into(), body.content_type.clone()));
+                    Some(encode_body(&body.content_type, value)?)
+                }
+                None => None,
+            },
+            None => None,
+        };
+
+        Ok(OpenApiRequest {
+            method: self.method.clone(),
+            url,
+            headers: request_headers,
+            body,
+        })
+    }
+}
+
+/// Follows `$ref`s to other parts of the same document.
+fn resolve<'a>(spec: &'a Value, mut value: &'a Value) -> &'a Value {
+    for _ in 0..MAX_REF_DEPTH {
+        let Some(pointer) = value["$ref"]
+            .as_str()
+            .and_then(|reference| reference.strip_prefix('#'))
+        else {
+            break;
+        };
+        match spec.pointer(pointer) {
+            Some(target) => value = target,
+            None => break,
+        }
+    }
+    value
+}
+
+/// Replaces the `$ref`s in a schema with what they refer to, since tool schemas have to be
+/// self-contained.
+fn inline_refs(spec: &Value, schema: &Value, depth: usize) -> Value {
+    match schema {
+        Value::Object(fields) if fields.contains_key("$ref") => {
+            if depth >= MAX_REF_DEPTH {
+                return json!({});
+            }
+            let target = resolve(spec, schema);
+            if std::ptr::eq(target, schema) {
+                json!({})
+            } else {
+                inline_refs(spec, target, depth + 1)
+            }
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), inline_refs(spec, value, depth)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| inline_refs(spec, item, depth))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Picks the media type to send an operation's body as, preferring JSON, then forms, since
+/// those are what the model's arguments can be encoded as.
+fn body_media_type(content: &Map<String, Value>) -> Option<(&String, &Value)> {
+    content
+        .iter()
+        .find(|(content_type, _)| is_json(content_type))
+        .or_else(|| {
+            content
+                .iter()
+                .find(|(content_type, _)| is_form(content_type))
+        })
+        .or_else(|| content.iter().next())
+}
+
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn is_json(content_type: &str) -> bool {
+    let essence = essence(content_type);
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+fn is_form(content_type: &str) -> bool {
+    essence(content_type) == "application/x-www-form-urlencoded"
+}
+
+/// Encodes a body argument as the operation's content type.
+fn encode_body(content_type: &str, value: &Value) -> Result<String> {
+    if is_json(content_type) {
+        Ok(value.to_string())
+    } else if is_form(content_type) {
+        let fields = value
+            .as_object()
+            .context("a form body has to be an object")?;
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in fields {
+            let values = match value {
+                Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+            for value in values {
+                form.append_pair(name, &argument_text(value));
+            }
+        }
+        Ok(form.finish())
+    } else {
+        Ok(argument_text(value))
+    }
+}
+
+/// Suffixes a name that another operation's tool already has, shortening it so that the
+/// suffixed name still fits.
+fn unique_tool_name(name: &str, operations: &[OpenApiOperation]) -> String {
+    let is_taken = |candidate: &str| {
+        operations
+            .iter()
+            .any(|operation| operation.tool.name == candidate)
+    };
+    let mut candidate = name.chars().take(MAX_TOOL_NAME_LEN).collect::<String>();
+    let mut suffix = 2;
+    while is_taken(&candidate) {
+        let suffix_text = format!("_{suffix}");
+        candidate = name
+            .chars()
+            .take(MAX_TOOL_NAME_LEN - suffix_text.len())
+            .collect::<String>();
+        candidate.push_str(&suffix_text);
+        suffix += 1;
+    }
+    candidate
+}
+
+/// Returns a name for an operation's tool, which may be longer than models accept.
+fn tool_name(operation_id: Option<&str>, method: &str, path: &str) -> String {
+    let name = operation_id.map_or_else(|| format!("{method}_{path}"), str::to_string);
+    let mut sanitized = String::new();
+    for c in name.chars() {
+        let c = if c.is_ascii_alphanumeric() || c == '-' {
+            c
+        } else {
+            '_'
+        };
+        if !(c == '_' && (sanitized.is_empty() || sanitized.ends_with('_'))) {
+            sanitized.push(c);
+        }
+    }
+    sanitized.trim_end_matches('_').to_string()
+}
+
+fn argument_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn encode_path_segment(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Describes a response from the API as a tool result.
+pub fn tool_response(status: u16, body: String) -> Value {
+    let is_error = status >= 400;
+    let text = if is_error {
+        format!("HTTP {status}\n\n{body}")
+    } else {
+        body
+    };
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+impl std::fmt::Display for OpenApiRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.url)
+    }
+}
+
+pub(crate) fn missing_operation(name: &str) -> anyhow::Error {
+    anyhow!("the API has no operation for the tool {name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn petstore() -> Value {
+        json!({
+            "openapi": "3.0.0",
+            "servers": [{ "url": "/v1" }],
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [
+                        { "name": "petId", "in": "path", "schema": { "type": "integer" } }
+                    ],
+                    "get": {
+                        "operationId": "showPetById",
+                        "summary": "Info for a specific pet",
+                        "parameters": [
+                            { "name": "fields", "in": "query", "schema": { "type": "array", "items": { "type": "string" } } },
+                            { "$ref": "#/components/parameters/ApiVersion" }
+                        ]
+                    }
+                },
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "required": true,
+                            "content": {
+                                "application/json": { "schema": { "$ref": "#/components/schemas/Pet" } }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "ApiVersion": { "name": "X-Api-Version", "in": "header", "required": true }
+                },
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "parent": { "$ref": "#/components/schemas/Pet" }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_operations() {
+        let spec = petstore();
+        let operations = operations(&spec).unwrap();
+        let names = operations
+            .iter()
+            .map(|operation| operation.tool.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["showPetById", "post_pets"]);
+
+        let show_pet = &operations[0].tool;
+        assert_eq!(
+            show_pet.description.as_deref(),
+            Some("Info for a specific pet\n\nGET /pets/{petId}")
+        );
+        assert_eq!(
+            show_pet.input_schema["required"],
+            json!(["petId", "X-Api-Version"])
+        );
+        assert_eq!(
+            show_pet.input_schema["properties"]["X-Api-Version"],
+            json!({ "type": "string" })
+        );
+
+        // Referenced schemas are inlined, up to a depth that ends recursive ones.
+        let add_pet = &operations[1].tool;
+        assert_eq!(add_pet.input_schema["required"], json!(["body"]));
+        let body = &add_pet.input_schema["properties"]["body"];
+        assert_eq!(body["properties"]["name"], json!({ "type": "string" }));
+        assert_eq!(body["properties"]["parent"]["type"], "object");
+    }
+
+    #[test]
+    fn test_request() {
+        let spec = petstore();
+        let settings = ContextServerOpenApi {
+            spec_url: "https://pets.example.com/openapi.json".into(),
+            base_url: None,
+            headers: BTreeMap::from_iter([("Authorization".into(), "Bearer token".into())]),
+        };
+        let base_url = base_url(&settings, &spec).unwrap();
+        assert_eq!(base_url, "https://pets.example.com/v1");
+
+        let operations = operations(&spec).unwrap();
+        let arguments = json!({
+            "petId": "a b/c",
+            "fields": ["name", "age"],
+            "X-Api-Version": 2,
+        });
+        let request = operations[0]
+            .request(&base_url, &settings.headers, arguments.as_object().unwrap())
+            .unwrap();
+        assert_eq!(
+            request.to_string(),
+            "GET https://pets.example.com/v1/pets/a%20b%2Fc?fields=name&fields=age"
+        );
+        assert_eq!(
+            request.headers,
+            [
+                ("Authorization".to_string(), "Bearer token".to_string()),
+                ("X-Api-Version".to_string(), "2".to_string()),
+            ]
+        );
+        assert_eq!(request.body, None);
+
+        let arguments = json!({ "body": { "name": "Rex" } });
+        let request = operations[1]
+            .request(&base_url, &BTreeMap::new(), arguments.as_object().unwrap())
+            .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.body.as_deref(), Some(r#"{"name":"Rex"}"#));
+
+        let error = operations[0]
+            .request(&base_url, &BTreeMap::new(), &Map::new())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "missing the `petId` argument");
+    }
+
+    #[test]
+    fn test_yaml_spec() {
+        let spec = parse_spec(
+            "openapi: 3.0.0\n\
+            servers:\n  - url: https://api.example.com\n\
+            paths:\n  /items:\n    get:\n      operationId: listItems\n      \
+            responses:\n        200:\n          description: OK\n",
+        )
+        .unwrap();
+        assert_eq!(spec["servers"][0]["url"], "https://api.example.com");
+        assert_eq!(
+            spec["paths"]["/items"]["get"]["responses"]["200"]["description"],
+            "OK"
+        );
+        assert_eq!(operations(&spec).unwrap()[0].tool.name, "listItems");
+    }
+
+    #[test]
+    fn test_bodies() {
+        let spec = json!({
+            "paths": {
+                "/search": {
+                    "post": {
+                        "parameters": [{ "name": "body", "in": "query" }],
+                        "requestBody": {
+                            "content": {
+                                "text/plain": { "schema": { "type": "string" } },
+                                "application/x-www-form-urlencoded": {
+                                    "schema": { "type": "object" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let operations = operations(&spec).unwrap();
+        let properties = &operations[0].tool.input_schema["properties"];
+        assert_eq!(properties["body"], json!({ "type": "string" }));
+        assert_eq!(properties["request_body"], json!({ "type": "object" }));
+
+        let arguments = json!({
+            "body": "query text",
+            "request_body": { "q": "a&b", "tags": ["x", "y"] },
+        });
+        let request = operations[0]
+            .request(
+                "https://api.example.com",
+                &BTreeMap::new(),
+                arguments.as_object().unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.to_string(),
+            "POST https://api.example.com/search?body=query+text"
+        );
+        assert_eq!(
+            request.headers,
+            [(
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded".to_string()
+            )]
+        );
+        assert_eq!(request.body.as_deref(), Some("q=a%26b&tags=x&tags=y"));
+    }
+
+    #[test]
+    fn test_long_tool_names_stay_unique() {
+        let long_name = "a".repeat(MAX_TOOL_NAME_LEN + 10);
+        let spec = json!({
+            "paths": {
+                "/one": { "get": { "operationId": long_name } },
+                "/two": { "get": { "operationId": long_name } },
+                "/three": { "get": { "operationId": format!("{long_name}b") } },
+            }
+        });
+        let names = operations(&spec)
+            .unwrap()
+            .into_iter()
+            .map(|operation| operation.tool.name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "a".repeat(MAX_TOOL_NAME_LEN),
+                format!("{}_2", "a".repeat(MAX_TOOL_NAME_LEN - 2)),
+                format!("{}_3", "a".repeat(MAX_TOOL_NAME_LEN - 2)),
+            ]
+        );
+    }
+}
@@ -5,19 +5,146 @@
 //! read/write messages and the types from types.rs for serialization/deserialization
 //! of messages.
 
-use anyhow::Result;
-use collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::client::Client;
+use anyhow::{Context as _, Result, bail};
+use collections::HashMap;
+use futures::channel::{mpsc, oneshot};
+use futures::future::Shared;
+use futures::{FutureExt as _, select};
+use parking_lot::Mutex;
+use postage::stream::Stream as _;
+use postage::watch;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::client::{Client, METHOD_NOT_FOUND, REQUEST_TIMEOUT, RequestError, ResponseError};
+use crate::rate_limiter::RateLimiter;
 use crate::types;
 
+/// Protocol features a server may use, which can be turned off to give a server that isn't
+/// fully trusted as little access as possible.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, JsonSchema, Debug)]
+#[serde(default)]
+pub struct ContextServerFeatures {
+    /// Whether the server may ask for completions from the language model. Zed doesn't support
+    /// sampling yet, so these requests are refused either way.
+    ///
+    /// Default: false
+    pub sampling: bool,
+    /// Whether the server may ask the user for input. Zed doesn't support elicitation yet, so
+    /// these requests are refused either way.
+    ///
+    /// Default: false
+    pub elicitation: bool,
+    /// Whether resources with `file://` URIs outside of the project's worktrees may be read from
+    /// the server.
+    ///
+    /// Default: true
+    pub resources_outside_worktrees: bool,
+}
+
+impl Default for ContextServerFeatures {
+    fn default() -> Self {
+        Self {
+            sampling: false,
+            elicitation: false,
+            resources_outside_worktrees: true,
+        }
+    }
+}
+
+/// Returns whether `uri` is a `file://` URI outside of the given worktrees.
+fn is_outside_worktrees(uri: &Url, worktree_roots: &[PathBuf]) -> bool {
+    if uri.scheme() != "file" {
+        return false;
+    }
+    match uri.to_file_path() {
+        Ok(path) => !worktree_roots.iter().any(|root| path.starts_with(root)),
+        Err(()) => true,
+    }
+}
+
+/// The metadata key of a tool call's time budget, in milliseconds.
+pub const TOOL_CALL_TIMEOUT_META_KEY: &str = "dev.zed/timeoutMs";
+
+/// The metadata key of the time after which a tool call's result is discarded, in milliseconds
+/// since the Unix epoch.
+pub const TOOL_CALL_DEADLINE_META_KEY: &str = "dev.zed/deadlineMs";
+
+/// The metadata key of the environment variables configured for a tool call, for servers that
+/// let each call set its own, such as a database server running a query read-only.
+pub const TOOL_CALL_ENV_META_KEY: &str = "dev.zed/env";
+
+/// A tool call that timed out after the server reported progress on it.
+#[derive(Debug)]
+pub struct ToolCallTimedOut {
+    /// The messages of the call's progress notifications, which may hold partial results.
+    pub progress: Vec<String>,
+}
+
+impl fmt::Display for ToolCallTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", RequestError::Timeout)
+    }
+}
+
+impl std::error::Error for ToolCallTimedOut {}
+
+/// A tool call that was cancelled because the server was stopped to be restarted with a new
+/// configuration, after waiting for the call to finish.
+#[derive(Debug)]
+pub struct ToolCallCancelled;
+
+impl fmt::Display for ToolCallCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The tool call was cancelled because the server's configuration changed"
+        )
+    }
+}
+
+impl std::error::Error for ToolCallCancelled {}
+
+/// The progress received for tool calls in flight, keyed by their progress token.
+type ProgressMessages = Arc<Mutex<HashMap<String, ToolCallProgress>>>;
+
+#[derive(Default)]
+struct ToolCallProgress {
+    messages: Vec<String>,
+    /// Where the messages are sent as they arrive, for calls whose caller shows them.
+    listener: Option<mpsc::UnboundedSender<String>>,
+}
+
 pub struct ModelContextProtocol {
     inner: Client,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    tool_call_timeout: Option<Duration>,
+    features: ContextServerFeatures,
+    worktree_roots: Arc<[PathBuf]>,
 }
 
 impl ModelContextProtocol {
-    pub(crate) fn new(inner: Client) -> Self {
-        Self { inner }
+    pub(crate) fn new(
+        inner: Client,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        tool_call_timeout: Option<Duration>,
+        features: ContextServerFeatures,
+        worktree_roots: Arc<[PathBuf]>,
+    ) -> Self {
+        Self {
+            inner,
+            rate_limiter,
+            tool_call_timeout,
+            features,
+            worktree_roots,
+        }
     }
 
     fn supported_protocols() -> Vec<types::ProtocolVersion> {
@@ -41,6 +168,51 @@ impl ModelContextProtocol {
             client_info,
         };
 
+        let progress_messages = ProgressMessages::default();
+        self.inner
+            .on_notification(types::NotificationType::Progress.as_str(), {
+                let progress_messages = progress_messages.clone();
+                move |params, _| {
+                    let Ok(params) = serde_json::from_value::<types::ProgressParams>(params) else {
+                        return;
+                    };
+                    let (types::ProgressToken::String(token), Some(message)) =
+                        (params.progress_token, params.message)
+                    else {
+                        return;
+                    };
+                    if let Some(progress) = progress_messages.lock().get_mut(&token) {
+                        if let Some(listener) = &progress.listener {
+                            listener.unbounded_send(message.clone()).ok();
+                        }
+                        progress.messages.push(message);
+                    }
+                }
+            });
+
+        // Zed doesn't advertise these features, but the server may request them anyway.
+        for (enabled, request, feature) in [
+            (
+                self.features.sampling,
+                types::RequestType::CreateMessage,
+                "Sampling",
+            ),
+            (
+                self.features.elicitation,
+                types::RequestType::Elicit,
+                "Elicitation",
+            ),
+        ] {
+            if !enabled {
+                self.inner.on_request(request.as_str(), move |_| {
+                    Err(ResponseError {
+                        code: METHOD_NOT_FOUND,
+                        message: format!("{feature} is disabled for this server"),
+                    })
+                });
+            }
+        }
+
         let response: types::InitializeResponse = self
             .inner
             .request(types::RequestType::Initialize.as_str(), params)
@@ -59,8 +231,20 @@ impl ModelContextProtocol {
             serde_json::json!({}),
         )?;
 
+        let (in_flight_tool_calls_tx, in_flight_tool_calls_rx) = watch::channel_with(0);
+        let (cancel_tool_calls_tx, cancel_tool_calls_rx) = oneshot::channel();
         let initialized_protocol = InitializedContextServerProtocol {
             inner: self.inner,
+            rate_limiter: self.rate_limiter,
+            tool_call_timeout: self.tool_call_timeout,
+            features: self.features,
+            worktree_roots: self.worktree_roots,
+            progress_messages,
+            next_progress_token: AtomicUsize::new(0),
+            in_flight_tool_calls_tx: Mutex::new(in_flight_tool_calls_tx),
+            in_flight_tool_calls_rx,
+            cancel_tool_calls_tx: Mutex::new(Some(cancel_tool_calls_tx)),
+            tool_calls_cancelled: cancel_tool_calls_rx.shared(),
             initialize: response,
         };
 
@@ -70,9 +254,28 @@ impl ModelContextProtocol {
 
 pub struct InitializedContextServerProtocol {
     inner: Client,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    tool_call_timeout: Option<Duration>,
+    features: ContextServerFeatures,
+    worktree_roots: Arc<[PathBuf]>,
+    progress_messages: ProgressMessages,
+    next_progress_token: AtomicUsize,
+    in_flight_tool_calls_tx: Mutex<watch::Sender<usize>>,
+    in_flight_tool_calls_rx: watch::Receiver<usize>,
+    cancel_tool_calls_tx: Mutex<Option<oneshot::Sender<()>>>,
+    tool_calls_cancelled: Shared<oneshot::Receiver<()>>,
     pub initialize: types::InitializeResponse,
 }
 
+/// The features a server and Zed agreed on when the server was initialized.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ContextServerCapabilities {
+    pub tools: bool,
+    pub prompts: bool,
+    pub resources: bool,
+    pub logging: bool,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ServerCapability {
     Experimental,
@@ -94,6 +297,15 @@ impl InitializedContextServerProtocol {
         }
     }
 
+    pub fn capabilities(&self) -> ContextServerCapabilities {
+        ContextServerCapabilities {
+            tools: self.capable(ServerCapability::Tools),
+            prompts: self.capable(ServerCapability::Prompts),
+            resources: self.capable(ServerCapability::Resources),
+            logging: self.capable(ServerCapability::Logging),
+        }
+    }
+
     fn check_capability(&self, capability: ServerCapability) -> Result<()> {
         anyhow::ensure!(
             self.capable(capability),
@@ -107,8 +319,7 @@ impl InitializedContextServerProtocol {
         self.check_capability(ServerCapability::Prompts)?;
 
         let response: types::PromptsListResponse = self
-            .inner
-            .request(
+            .rate_limited_request(
                 types::RequestType::PromptsList.as_str(),
                 serde_json::json!({}),
             )
@@ -117,13 +328,38 @@ impl InitializedContextServerProtocol {
         Ok(response.prompts)
     }
 
+    /// Waits until the server's rate limit, if it has one, lets another request be sent.
+    async fn wait_for_rate_limit(&self) {
+        if let Some(rate_limiter) = self.rate_limiter.as_ref() {
+            rate_limiter.wait(self.inner.executor()).await;
+        }
+    }
+
+    /// Sends a request once the server's rate limit lets it.
+    async fn rate_limited_request<R: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl serde::Serialize,
+    ) -> Result<R> {
+        self.wait_for_rate_limit().await;
+        self.inner.request(method, params).await
+    }
+
+    fn check_resource_read(&self, uri: &Url) -> Result<()> {
+        if !self.features.resources_outside_worktrees
+            && is_outside_worktrees(uri, &self.worktree_roots)
+        {
+            bail!("Reading {uri} from this server is disabled, since it's outside of the project");
+        }
+        Ok(())
+    }
+
     /// List the MCP resources.
     pub async fn list_resources(&self) -> Result<types::ResourcesListResponse> {
         self.check_capability(ServerCapability::Resources)?;
 
         let response: types::ResourcesListResponse = self
-            .inner
-            .request(
+            .rate_limited_request(
                 types::RequestType::ResourcesList.as_str(),
                 serde_json::json!({}),
             )
@@ -132,6 +368,22 @@ impl InitializedContextServerProtocol {
         Ok(response)
     }
 
+    /// Reads the contents of a resource.
+    ///
+    /// Fails without asking the server for `file://` URIs outside of the project's worktrees,
+    /// when reading those is disabled for the server.
+    pub async fn read_resource(&self, uri: Url) -> Result<types::ResourcesReadResponse> {
+        self.check_capability(ServerCapability::Resources)?;
+        self.check_resource_read(&uri)?;
+
+        let params = types::ResourcesReadParams { uri, meta: None };
+        let response: types::ResourcesReadResponse = self
+            .rate_limited_request(types::RequestType::ResourcesRead.as_str(), params)
+            .await?;
+
+        Ok(response)
+    }
+
     /// Executes a prompt with the given arguments and returns the result.
     pub async fn run_prompt<P: AsRef<str>>(
         &self,
@@ -147,8 +399,7 @@ impl InitializedContextServerProtocol {
         };
 
         let response: types::PromptsGetResponse = self
-            .inner
-            .request(types::RequestType::PromptsGet.as_str(), params)
+            .rate_limited_request(types::RequestType::PromptsGet.as_str(), params)
             .await?;
 
         Ok(response)
@@ -169,8 +420,7 @@ impl InitializedContextServerProtocol {
             meta: None,
         };
         let result: types::CompletionCompleteResponse = self
-            .inner
-            .request(types::RequestType::CompletionComplete.as_str(), params)
+            .rate_limited_request(types::RequestType::CompletionComplete.as_str(), params)
             .await?;
 
         let completion = types::Completion {
@@ -189,42 +439,433 @@ impl InitializedContextServerProtocol {
         self.check_capability(ServerCapability::Tools)?;
 
         let response = self
-            .inner
-            .request::<types::ListToolsResponse>(types::RequestType::ListTools.as_str(), ())
+            .rate_limited_request::<types::ListToolsResponse>(
+                types::RequestType::ListTools.as_str(),
+                (),
+            )
             .await?;
 
         Ok(response)
     }
 
-    /// Executes a tool with the given arguments
+    /// Executes a tool with the given arguments.
+    ///
+    /// When the server has a rate limit, calls over it are queued until they can be sent. When
+    /// the call times out after the server reported progress on it, the error is a
+    /// [`ToolCallTimedOut`] with the progress messages.
+    ///
+    /// The call's timeout is passed to the server in its metadata, as a budget under
+    /// [`TOOL_CALL_TIMEOUT_META_KEY`] and a deadline under [`TOOL_CALL_DEADLINE_META_KEY`], so that
+    /// servers can stop working on calls whose result would be discarded.
     pub async fn run_tool<P: AsRef<str>>(
         &self,
         tool: P,
         arguments: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<types::CallToolResponse> {
+        self.run_tool_with_meta(tool, arguments, HashMap::default())
+            .await
+    }
+
+    /// Executes a tool like [`Self::run_tool`], adding `meta` to the call's metadata. The keys
+    /// Zed sets itself, such as the progress token, take precedence.
+    pub async fn run_tool_with_meta<P: AsRef<str>>(
+        &self,
+        tool: P,
+        arguments: Option<HashMap<String, serde_json::Value>>,
+        meta: HashMap<String, serde_json::Value>,
+    ) -> Result<types::CallToolResponse> {
+        self.run_tool_with_progress(tool, arguments, meta, None)
+            .await
+    }
+
+    /// Executes a tool like [`Self::run_tool_with_meta`], sending the progress messages the
+    /// server reports for the call to `progress` as they arrive.
+    pub async fn run_tool_with_progress<P: AsRef<str>>(
+        &self,
+        tool: P,
+        arguments: Option<HashMap<String, serde_json::Value>>,
+        mut meta: HashMap<String, serde_json::Value>,
+        progress: Option<mpsc::UnboundedSender<String>>,
     ) -> Result<types::CallToolResponse> {
         self.check_capability(ServerCapability::Tools)?;
 
+        self.wait_for_rate_limit().await;
+
+        let progress_token = format!(
+            "tool-call-{}",
+            self.next_progress_token.fetch_add(1, SeqCst)
+        );
+        self.progress_messages.lock().insert(
+            progress_token.clone(),
+            ToolCallProgress {
+                messages: Vec::new(),
+                listener: progress,
+            },
+        );
+        meta.insert("progressToken".to_string(), progress_token.clone().into());
+        // A timeout of zero lets the call take as long as it needs.
+        let timeout = Some(self.tool_call_timeout.unwrap_or(REQUEST_TIMEOUT))
+            .filter(|timeout| !timeout.is_zero());
+        if let Some(timeout) = timeout {
+            // The deadline starts once the call is sent, after any rate limiting.
+            let deadline = SystemTime::now() + timeout;
+            let deadline_ms = deadline
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_millis() as u64);
+            meta.extend([
+                (
+                    TOOL_CALL_TIMEOUT_META_KEY.to_string(),
+                    (timeout.as_millis() as u64).into(),
+                ),
+                (TOOL_CALL_DEADLINE_META_KEY.to_string(), deadline_ms.into()),
+            ]);
+        }
         let params = types::CallToolParams {
             name: tool.as_ref().to_string(),
             arguments,
-            meta: None,
+            meta: Some(meta),
         };
 
-        let response: types::CallToolResponse = self
-            .inner
-            .request(types::RequestType::CallTool.as_str(), params)
-            .await?;
-
-        Ok(response)
+        *self.in_flight_tool_calls_tx.lock().borrow_mut() += 1;
+        let _in_flight = util::defer(|| *self.in_flight_tool_calls_tx.lock().borrow_mut() -= 1);
+        let request = self.inner.request_with_timeout::<types::CallToolResponse>(
+            types::RequestType::CallTool.as_str(),
+            params,
+            timeout,
+        );
+        let response = select! {
+            response = request.fuse() => response,
+            _ = self.tool_calls_cancelled.clone() => Err(ToolCallCancelled.into()),
+        };
+        let progress = self
+            .progress_messages
+            .lock()
+            .remove(&progress_token)
+            .map(|progress| progress.messages)
+            .unwrap_or_default();
+        match response {
+            Err(error)
+                if !progress.is_empty()
+                    && matches!(error.downcast_ref(), Some(RequestError::Timeout)) =>
+            {
+                Err(ToolCallTimedOut { progress }.into())
+            }
+            response => response,
+        }
     }
 }
 
 impl InitializedContextServerProtocol {
+    /// Waits up to `timeout` for the tool calls in flight to finish, then cancels the ones that
+    /// haven't, along with any made afterwards. Returns how many calls were cancelled.
+    pub async fn drain_tool_calls(&self, timeout: Duration) -> usize {
+        let mut in_flight = self.in_flight_tool_calls_rx.clone();
+        let mut timeout = self.inner.executor().timer(timeout).fuse();
+        while *in_flight.borrow() > 0 {
+            select! {
+                changed = in_flight.recv().fuse() => {
+                    if changed.is_none() {
+                        break;
+                    }
+                }
+                _ = timeout => break,
+            }
+        }
+        let cancelled = *in_flight.borrow();
+        if let Some(cancel) = self.cancel_tool_calls_tx.lock().take() {
+            cancel.send(()).ok();
+        }
+        cancelled
+    }
+
+    /// Sends a request for any method, checking it against the server's features like the
+    /// typed methods do.
     pub async fn request<R: serde::de::DeserializeOwned>(
         &self,
         method: &str,
         params: impl serde::Serialize,
     ) -> Result<R> {
-        self.inner.request(method, params).await
+        let params = serde_json::to_value(params)?;
+        if method == types::RequestType::ResourcesRead.as_str() {
+            let uri = params
+                .get("uri")
+                .and_then(|uri| uri.as_str())
+                .context("resources/read requires a uri")?;
+            self.check_resource_read(&Url::parse(uri)?)?;
+        }
+        self.rate_limited_request(method, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContextServerId;
+    use crate::fake_server::FakeContextServer;
+    use gpui::TestAppContext;
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_progress_is_returned_on_timeout(cx: &mut TestAppContext) {
+        let fake = FakeContextServer::new("fake", cx.executor());
+        fake.add_tool(types::Tool {
+            name: "search".into(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            annotations: None,
+        });
+        fake.on_request("tools/call", {
+            let fake = fake.clone();
+            move |params| {
+                fake.send_notification(
+                    "notifications/progress",
+                    json!({
+                        "progressToken": params["_meta"]["progressToken"],
+                        "progress": 1,
+                        "message": "Found src/main.rs",
+                    }),
+                );
+                Ok(json!({ "content": [] }))
+            }
+        });
+        let server = fake.context_server(ContextServerId("fake".into()));
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        fake.set_latency(Duration::from_secs(120));
+        let response = cx.background_spawn(async move { protocol.run_tool("search", None).await });
+        cx.run_until_parked();
+        cx.executor().advance_clock(Duration::from_secs(60));
+        let error = response.await.unwrap_err();
+        let timed_out = error.downcast::<ToolCallTimedOut>().unwrap();
+        assert_eq!(timed_out.progress, ["Found src/main.rs"]);
+    }
+
+    #[gpui::test]
+    async fn test_resource_reads_outside_worktrees(cx: &mut TestAppContext) {
+        let fake = FakeContextServer::new("fake", cx.executor());
+        fake.on_request("resources/read", |_| Ok(json!({ "contents": [] })));
+        let server = Arc::new(
+            crate::ContextServer::new(ContextServerId("fake".into()), Arc::new(fake.clone()))
+                .with_features(
+                    ContextServerFeatures {
+                        resources_outside_worktrees: false,
+                        ..Default::default()
+                    },
+                    vec![PathBuf::from("/projects/app")],
+                ),
+        );
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        protocol
+            .request::<types::ResourcesReadResponse>(
+                "resources/read",
+                json!({ "uri": "file:///projects/app/README.md" }),
+            )
+            .await
+            .unwrap();
+        protocol
+            .request::<types::ResourcesReadResponse>(
+                "resources/read",
+                json!({ "uri": "file:///home/user/.ssh/id_rsa" }),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            fake.requests_for("resources/read"),
+            [json!({ "uri": "file:///projects/app/README.md" })]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_tool_call_timeout(cx: &mut TestAppContext) {
+        let fake = FakeContextServer::new("fake", cx.executor());
+        fake.add_tool(types::Tool {
+            name: "search".into(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            annotations: None,
+        });
+        fake.set_tool_response(
+            "search",
+            types::CallToolResponse {
+                content: Vec::new(),
+                is_error: None,
+                structured_content: None,
+                meta: None,
+            },
+        );
+        let server = Arc::new(
+            crate::ContextServer::new(ContextServerId("fake".into()), Arc::new(fake.clone()))
+                .with_tool_call_timeout(Some(5)),
+        );
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        fake.set_latency(Duration::from_secs(10));
+        let response = cx.background_spawn(async move { protocol.run_tool("search", None).await });
+        cx.run_until_parked();
+        cx.executor().advance_clock(Duration::from_secs(5));
+        let error = response.await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<RequestError>(),
+            Some(RequestError::Timeout)
+        ));
+        let calls = fake.requests_for("tools/call");
+        assert_eq!(calls[0]["_meta"][TOOL_CALL_TIMEOUT_META_KEY], json!(5000));
+        assert!(calls[0]["_meta"][TOOL_CALL_DEADLINE_META_KEY].is_u64());
+    }
+
+    #[gpui::test]
+    async fn test_zero_tool_call_timeout(cx: &mut TestAppContext) {
+        let fake = FakeContextServer::new("fake", cx.executor());
+        fake.add_tool(types::Tool {
+            name: "search".into(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            annotations: None,
+        });
+        fake.set_tool_response(
+            "search",
+            types::CallToolResponse {
+                content: Vec::new(),
+                is_error: None,
+                structured_content: None,
+                meta: None,
+            },
+        );
+        let server = Arc::new(
+            crate::ContextServer::new(ContextServerId("fake".into()), Arc::new(fake.clone()))
+                .with_tool_call_timeout(Some(0)),
+        );
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        fake.set_latency(REQUEST_TIMEOUT * 2);
+        let response = cx.background_spawn(async move { protocol.run_tool("search", None).await });
+        cx.run_until_parked();
+        cx.executor().advance_clock(REQUEST_TIMEOUT * 2);
+        response.await.unwrap();
+        let calls = fake.requests_for("tools/call");
+        assert!(calls[0]["_meta"].get(TOOL_CALL_TIMEOUT_META_KEY).is_none());
+        assert!(calls[0]["_meta"].get(TOOL_CALL_DEADLINE_META_KEY).is_none());
+    }
+
+    #[gpui::test]
+    async fn test_tool_call_meta(cx: &mut TestAppContext) {
+        let fake = FakeContextServer::new("fake", cx.executor());
+        fake.add_tool(types::Tool {
+            name: "query".into(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            annotations: None,
+        });
+        fake.set_tool_response(
+            "query",
+            types::CallToolResponse {
+                content: Vec::new(),
+                is_error: None,
+                structured_content: None,
+                meta: None,
+            },
+        );
+        let server = fake.context_server(ContextServerId("fake".into()));
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        protocol
+            .run_tool_with_meta(
+                "query",
+                None,
+                HashMap::from_iter([
+                    (
+                        TOOL_CALL_ENV_META_KEY.to_string(),
+                        json!({ "READ_ONLY": "1" }),
+                    ),
+                    ("progressToken".to_string(), json!("overridden")),
+                ]),
+            )
+            .await
+            .unwrap();
+        let calls = fake.requests_for("tools/call");
+        assert_eq!(
+            calls[0]["_meta"][TOOL_CALL_ENV_META_KEY],
+            json!({ "READ_ONLY": "1" })
+        );
+        assert_ne!(calls[0]["_meta"]["progressToken"], json!("overridden"));
+    }
+
+    #[gpui::test]
+    async fn test_drain_tool_calls(cx: &mut TestAppContext) {
+        async fn start_server(
+            latency: Duration,
+            cx: &mut TestAppContext,
+        ) -> Arc<InitializedContextServerProtocol> {
+            let fake = FakeContextServer::new("fake", cx.executor());
+            fake.add_tool(types::Tool {
+                name: "search".into(),
+                description: None,
+                input_schema: json!({ "type": "object" }),
+                annotations: None,
+            });
+            fake.set_tool_response(
+                "search",
+                types::CallToolResponse {
+                    content: Vec::new(),
+                    is_error: None,
+                    structured_content: None,
+                    meta: None,
+                },
+            );
+            let server = fake.context_server(ContextServerId("fake".into()));
+            server.clone().start(&cx.to_async()).await.unwrap();
+            fake.set_latency(latency);
+            server.client().unwrap()
+        }
+
+        // Calls that finish before the timeout are waited for.
+        let protocol = start_server(Duration::from_secs(5), cx).await;
+        let call = cx.background_spawn({
+            let protocol = protocol.clone();
+            async move { protocol.run_tool("search", None).await }
+        });
+        cx.run_until_parked();
+        let drain = cx.background_spawn({
+            let protocol = protocol.clone();
+            async move { protocol.drain_tool_calls(Duration::from_secs(10)).await }
+        });
+        cx.executor().advance_clock(Duration::from_secs(5));
+        assert!(call.await.is_ok());
+        assert_eq!(drain.await, 0);
+
+        // Calls that don't are cancelled.
+        let protocol = start_server(Duration::from_secs(30), cx).await;
+        let call = cx.background_spawn({
+            let protocol = protocol.clone();
+            async move { protocol.run_tool("search", None).await }
+        });
+        cx.run_until_parked();
+        let drain = cx.background_spawn({
+            let protocol = protocol.clone();
+            async move { protocol.drain_tool_calls(Duration::from_secs(10)).await }
+        });
+        cx.executor().advance_clock(Duration::from_secs(10));
+        assert_eq!(drain.await, 1);
+        let error = call.await.unwrap_err();
+        assert!(error.downcast_ref::<ToolCallCancelled>().is_some());
+    }
+
+    #[test]
+    fn test_is_outside_worktrees() {
+        let worktree_roots = [PathBuf::from("/projects/app")];
+        let is_outside =
+            |uri: &str| is_outside_worktrees(&Url::parse(uri).unwrap(), &worktree_roots);
+
+        assert!(!is_outside("file:///projects/app/src/main.rs"));
+        assert!(!is_outside("postgres://localhost/users/schema"));
+        assert!(is_outside("file:///home/user/.ssh/id_rsa"));
+        assert!(is_outside("file:///projects/app/../secrets.txt"));
+        assert!(is_outside("file:///projects/application/main.rs"));
     }
 }
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use collections::HashMap;
+use gpui::BackgroundExecutor;
+use parking_lot::Mutex;
+
+use crate::ContextServerId;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// The limiters of the servers with a rate limit. Every instance of a server shares one, so that
+/// restarting a server, or running it for several projects, doesn't reset its limit.
+static RATE_LIMITERS: LazyLock<Mutex<HashMap<ContextServerId, Arc<RateLimiter>>>> =
+    LazyLock::new(Mutex::default);
+
+/// Limits how many requests are sent to a context server in any minute.
+///
+/// Requests over the limit aren't rejected: they wait until the window has room for them. A
+/// request only takes up the limit once it's sent, so requests cancelled while they wait don't.
+pub struct RateLimiter {
+    max_requests_per_minute: usize,
+    state: Mutex<RateLimiterState>,
+}
+
+#[derive(Default)]
+struct RateLimiterState {
+    /// When each request in the current window was sent.
+    sent: VecDeque<Instant>,
+    /// How many requests are waiting for the window to have room.
+    waiting: usize,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests_per_minute: u32) -> Self {
+        Self {
+            max_requests_per_minute: max_requests_per_minute.max(1) as usize,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns the limiter shared by every instance of the server, which is replaced when the
+    /// server's limit changes.
+    pub fn for_server(id: &ContextServerId, max_requests_per_minute: u32) -> Arc<Self> {
+        let mut rate_limiters = RATE_LIMITERS.lock();
+        let rate_limiter = Self::new(max_requests_per_minute);
+        match rate_limiters.get(id) {
+            Some(existing)
+                if existing.max_requests_per_minute == rate_limiter.max_requests_per_minute =>
+            {
+                existing.clone()
+            }
+            _ => {
+                let rate_limiter = Arc::new(rate_limiter);
+                rate_limiters.insert(id.clone(), rate_limiter.clone());
+                rate_limiter
+            }
+        }
+    }
+
+    /// Waits until the window has room for a request, then counts it as sent. Dropping the
+    /// returned future while it waits gives up its place without taking up the limit.
+    pub async fn wait(&self, executor: &BackgroundExecutor) {
+        let Err(mut delay) = self.try_send(executor.now()) else {
+            return;
+        };
+        self.state.lock().waiting += 1;
+        let _waiting = util::defer(|| self.state.lock().waiting -= 1);
+        loop {
+            executor.timer(delay).await;
+            match self.try_send(executor.now()) {
+                Ok(()) => return,
+                Err(next_delay) => delay = next_delay,
+            }
+        }
+    }
+
+    /// Counts a request as sent at `now` if the window has room for it, or returns how long
+    /// until it does.
+    pub fn try_send(&self, now: Instant) -> Result<(), Duration> {
+        let mut state = self.state.lock();
+        let send_at = self.next_slot(&mut state.sent, now);
+        if send_at > now {
+            return Err(send_at - now);
+        }
+        state.sent.push_back(now);
+        Ok(())
+    }
+
+    /// Returns how long a request made at `now` would wait, behind the requests already waiting.
+    pub fn delay(&self, now: Instant) -> Duration {
+        let state = self.state.lock();
+        let mut projected = state.sent.clone();
+        let mut send_at = now;
+        for _ in 0..=state.waiting {
+            send_at = self.next_slot(&mut projected, now);
+            projected.push_back(send_at);
+        }
+        send_at.saturating_duration_since(now)
+    }
+
+    /// Forgets the requests that left the window and returns when the next one can be sent.
+    fn next_slot(&self, sent: &mut VecDeque<Instant>, now: Instant) -> Instant {
+        while sent.front().is_some_and(|sent_at| *sent_at + WINDOW <= now) {
+            sent.pop_front();
+        }
+        if sent.len() < self.max_requests_per_minute {
+            now
+        } else {
+            sent[sent.len() - self.max_requests_per_minute] + WINDOW
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(2);
+        let start = Instant::now();
+        assert_eq!(limiter.try_send(start), Ok(()));
+        assert_eq!(limiter.try_send(start + Duration::from_secs(10)), Ok(()));
+
+        // Requests over the limit wait for the oldest request to leave the window.
+        let now = start + Duration::from_secs(20);
+        assert_eq!(limiter.delay(now), Duration::from_secs(40));
+        assert_eq!(limiter.try_send(now), Err(Duration::from_secs(40)));
+
+        // Once the window has passed, requests are sent right away again.
+        let later = start + Duration::from_secs(200);
+        assert_eq!(limiter.try_send(later), Ok(()));
+    }
+
+    #[test]
+    fn test_rate_limiter_is_shared_per_server() {
+        let id = ContextServerId("rate-limited".into());
+        let limiter = RateLimiter::for_server(&id, 2);
+        assert!(Arc::ptr_eq(&limiter, &RateLimiter::for_server(&id, 2)));
+        assert!(!Arc::ptr_eq(&limiter, &RateLimiter::for_server(&id, 3)));
+        assert!(!Arc::ptr_eq(
+            &limiter,
+            &RateLimiter::for_server(&ContextServerId("other".into()), 2)
+        ));
+    }
+
+    #[gpui::test]
+    async fn test_cancelled_requests_give_up_their_place(cx: &mut TestAppContext) {
+        let executor = cx.executor();
+        let limiter = Arc::new(RateLimiter::new(1));
+        limiter.wait(&executor).await;
+
+        let waiting = cx.background_spawn({
+            let limiter = limiter.clone();
+            let executor = executor.clone();
+            async move { limiter.wait(&executor).await }
+        });
+        cx.run_until_parked();
+        assert_eq!(limiter.delay(executor.now()), Duration::from_secs(120));
+
+        drop(waiting);
+        cx.run_until_parked();
+        assert_eq!(limiter.delay(executor.now()), Duration::from_secs(60));
+
+        executor.advance_clock(Duration::from_secs(60));
+        assert_eq!(limiter.try_send(executor.now()), Ok(()));
+    }
+}
@@ -0,0 +1,69 @@
+//! Recordings of context server tool calls, which can be served back by a
+//! [`ReplayTransport`](crate::transport::ReplayTransport).
+//!
+//! A recording is a JSON Lines file with one [`RecordedToolCall`] per line, so that calls can be
+//! appended as they finish without rewriting the file.
+
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use futures::AsyncWriteExt as _;
+use serde::{Deserialize, Serialize};
+
+use crate::types;
+
+/// A tool call made to a context server, along with the definition of the tool and its outcome.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedToolCall {
+    /// The id of the context server the tool belongs to.
+    pub server: String,
+    /// What identifies the server across renames, which is a digest of what it runs or connects
+    /// to, so that calls recorded under its old id can be told to come from the same server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_identity: Option<String>,
+    pub tool: types::Tool,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+    #[serde(flatten)]
+    pub outcome: RecordedOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedOutcome {
+    /// The `tools/call` result sent by the server.
+    Response(serde_json::Value),
+    /// The error the call failed with.
+    Error(String),
+}
+
+/// Appends a call to the recording at `path`, creating the file if needed.
+pub async fn append_recorded_call(path: &Path, call: &RecordedToolCall) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        smol::fs::create_dir_all(parent).await?;
+    }
+    let mut line = serde_json::to_string(call)?;
+    line.push('\n');
+    let mut file = smol::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("opening tool call recording {path:?}"))?;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+/// Parses the calls in a recording, skipping blank lines.
+pub fn parse_recording(content: &str) -> Result<Vec<RecordedToolCall>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(ix, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("parsing recorded tool call on line {}", ix + 1))
+        })
+        .collect()
+}
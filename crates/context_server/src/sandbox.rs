@@ -0,0 +1,441 @@
+//! Runs locally-spawned context servers inside an OS sandbox.
+//!
+//! On macOS the server is run with `sandbox-exec`, and on Linux with `bwrap` (bubblewrap). In both
+//! cases the server can read the system directories needed to run programs, can read and write the
+//! project's worktrees and a scratch directory of its own, and nothing else.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ContextServerCommand;
+use crate::egress_proxy::EgressProxyAddress;
+
+/// Restrictions on what a context server run as a local process may access.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+pub struct ContextServerSandbox {
+    /// Whether the server may access the network.
+    ///
+    /// Default: true
+    #[serde(default = "default_true")]
+    pub allow_network: bool,
+    /// When not empty, the only hosts the server may connect to, where `*.example.com` allows any
+    /// subdomain of `example.com`.
+    ///
+    /// Connections go through a proxy that Zed runs for the server, which only tunnels HTTPS, so
+    /// the server must honor the `HTTPS_PROXY` environment variable. On Linux, the sandbox reaches
+    /// the proxy with `socat`, which has to be installed.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Paths outside of the project that the server may read, such as the toolchain it runs with.
+    #[serde(default)]
+    pub read_only_paths: Vec<PathBuf>,
+}
+
+impl Default for ContextServerSandbox {
+    fn default() -> Self {
+        Self {
+            allow_network: true,
+            allowed_hosts: Vec::new(),
+            read_only_paths: Vec::new(),
+        }
+    }
+}
+
+impl ContextServerSandbox {
+    /// Returns an error when the sandbox can't be enforced on this platform, so that the server
+    /// isn't started with fewer restrictions than it was configured with.
+    pub fn validate(&self) -> Result<()> {
+        if !cfg!(any(target_os = "macos", target_os = "linux")) {
+            bail!("sandboxing context servers isn't supported on this platform");
+        }
+        if cfg!(target_os = "linux")
+            && self.allow_network
+            && !self.allowed_hosts.is_empty()
+            && which::which("socat").is_err()
+        {
+            bail!(
+                "`allowed_hosts` needs `socat` on Linux, to reach Zed's proxy from the sandbox; \
+                install it, remove `allowed_hosts` to allow all hosts, or set `allow_network` to \
+                false to deny them all"
+            );
+        }
+        Ok(())
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+const MACOS_SYSTEM_PATHS: &[&str] = &[
+    "/bin",
+    "/sbin",
+    "/usr",
+    "/opt",
+    "/System",
+    "/Library",
+    "/private/etc",
+    "/private/var/db",
+    "/dev",
+];
+
+const LINUX_SYSTEM_PATHS: &[&str] = &[
+    "/bin",
+    "/sbin",
+    "/usr",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/opt",
+    "/etc",
+    "/nix/store",
+    "/run/systemd/resolve",
+];
+
+/// Where the egress proxy's socket is bound in a Linux sandbox, and the port inside the sandbox
+/// that's forwarded to it. The sandbox has a network namespace of its own, so the port can't
+/// clash with anything else.
+const LINUX_PROXY_SOCKET: &str = "/run/zed-egress-proxy.sock";
+const LINUX_PROXY_PORT: u16 = 3128;
+
+/// Wraps `command` so that it runs in a sandbox that can only write to `writable_paths` and
+/// `scratch_dir`.
+///
+/// The scratch directory is also used as the server's home and temporary directory, since many
+/// servers write caches there. When the sandbox has an allowlist of hosts, `egress_proxy` is the
+/// address of the [`EgressProxy`](crate::egress_proxy::EgressProxy) the server must connect
+/// through.
+pub fn sandboxed_command(
+    command: &ContextServerCommand,
+    sandbox: &ContextServerSandbox,
+    writable_paths: &[PathBuf],
+    scratch_dir: &Path,
+    egress_proxy: Option<&EgressProxyAddress>,
+) -> Result<ContextServerCommand> {
+    sandbox.validate()?;
+    let network = match egress_proxy {
+        _ if !sandbox.allow_network => Network::Denied,
+        Some(address) if !sandbox.allowed_hosts.is_empty() => Network::Proxied(address),
+        None if !sandbox.allowed_hosts.is_empty() => {
+            bail!("an egress proxy is required to restrict the hosts a context server can reach")
+        }
+        _ => Network::Allowed,
+    };
+
+    let mut readable_paths = sandbox.read_only_paths.clone();
+    // Allow the server to run when its executable lives outside of the system directories.
+    if let Some(parent) = Path::new(&command.path)
+        .parent()
+        .filter(|parent| parent.is_absolute())
+    {
+        readable_paths.push(parent.to_path_buf());
+    }
+    if let Some(cwd) = &command.cwd {
+        readable_paths.push(PathBuf::from(cwd));
+    }
+    let mut writable_paths = writable_paths.to_vec();
+    writable_paths.push(scratch_dir.to_path_buf());
+
+    let mut env = command.env.clone().unwrap_or_default();
+    let scratch_dir = scratch_dir.to_string_lossy().into_owned();
+    env.insert("HOME".into(), scratch_dir.clone());
+    env.insert("TMPDIR".into(), scratch_dir);
+    if let Network::Proxied(address) = network {
+        let port = match address {
+            EgressProxyAddress::Port(port) => *port,
+            EgressProxyAddress::Socket(_) => LINUX_PROXY_PORT,
+        };
+        let proxy_url = format!("http://127.0.0.1:{port}");
+        for name in [
+            "HTTPS_PROXY",
+            "https_proxy",
+            "HTTP_PROXY",
+            "http_proxy",
+            "ALL_PROXY",
+        ] {
+            env.insert(name.into(), proxy_url.clone());
+        }
+        env.remove("NO_PROXY");
+        env.remove("no_proxy");
+    }
+
+    let (path, args) = if cfg!(target_os = "macos") {
+        let profile = sandbox_exec_profile(network, &readable_paths, &writable_paths);
+        let mut args = vec!["-p".into(), profile, command.path.clone()];
+        args.extend(command.args.iter().cloned());
+        ("/usr/bin/sandbox-exec".into(), args)
+    } else if cfg!(target_os = "linux") {
+        let mut args = bubblewrap_args(network, &readable_paths, &writable_paths);
+        if let Network::Proxied(_) = network {
+            args.extend([
+                "/bin/sh".into(),
+                "-c".into(),
+                linux_proxy_forwarding_script(),
+                "sh".into(),
+            ]);
+        }
+        args.push(command.path.clone());
+        args.extend(command.args.iter().cloned());
+        ("bwrap".into(), args)
+    } else {
+        bail!("sandboxing context servers isn't supported on this platform");
+    };
+
+    Ok(ContextServerCommand {
+        path,
+        args,
+        env: Some(env),
+        cwd: command.cwd.clone(),
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Network<'a> {
+    Allowed,
+    Denied,
+    /// Only connections to the egress proxy are allowed.
+    Proxied(&'a EgressProxyAddress),
+}
+
+/// Forwards [`LINUX_PROXY_PORT`] to the proxy's socket in the background, waits for the port to
+/// be listened on, and then runs the server with the script's arguments.
+fn linux_proxy_forwarding_script() -> String {
+    format!(
+        "socat TCP-LISTEN:{LINUX_PROXY_PORT},bind=127.0.0.1,fork,reuseaddr \
+        UNIX-CONNECT:{LINUX_PROXY_SOCKET} & \
+        i=0; while ! grep -qi ':{LINUX_PROXY_PORT:04X} ' /proc/net/tcp && [ $i -lt 100 ]; do \
+        sleep 0.05; i=$((i+1)); done; \
+        exec \"$@\""
+    )
+}
+
+fn sandbox_exec_profile(
+    network: Network,
+    readable_paths: &[PathBuf],
+    writable_paths: &[PathBuf],
+) -> String {
+    let mut profile = String::from(
+        "(version 1)\n\
+         (deny default)\n\
+         (allow process-exec process-fork signal sysctl-read mach-lookup ipc-posix-shm iokit-open pseudo-tty)\n\
+         (allow file-read-metadata file-ioctl)\n\
+         (allow file-write* (literal \"/dev/null\") (literal \"/dev/zero\") (literal \"/dev/tty\"))\n",
+    );
+    let readable_paths = MACOS_SYSTEM_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .chain(readable_paths.iter().cloned());
+    profile.push_str(&format!("(allow file-read*{})\n", subpaths(readable_paths)));
+    profile.push_str(&format!(
+        "(allow file-read* file-write*{})\n",
+        subpaths(writable_paths.iter().cloned())
+    ));
+    match network {
+        Network::Allowed => profile.push_str("(allow network*)\n"),
+        Network::Denied => {}
+        Network::Proxied(EgressProxyAddress::Port(port)) => profile.push_str(&format!(
+            "(allow network-outbound (remote ip \"localhost:{port}\"))\n"
+        )),
+        Network::Proxied(EgressProxyAddress::Socket(socket_path)) => profile.push_str(&format!(
+            "(allow network-outbound (remote unix-socket (path-literal \"{}\")))\n",
+            escape_sandbox_string(&socket_path.to_string_lossy())
+        )),
+    }
+    profile
+}
+
+fn subpaths(paths: impl IntoIterator<Item = PathBuf>) -> String {
+    paths
+        .into_iter()
+        .map(|path| {
+            format!(
+                " (subpath \"{}\")",
+                escape_sandbox_string(&path.to_string_lossy())
+            )
+        })
+        .collect()
+}
+
+fn escape_sandbox_string(path: &str) -> String {
+    path.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn bubblewrap_args(
+    network: Network,
+    readable_paths: &[PathBuf],
+    writable_paths: &[PathBuf],
+) -> Vec<String> {
+    let mut args = vec!["--die-with-parent".into(), "--unshare-all".into()];
+    match network {
+        Network::Allowed => args.push("--share-net".into()),
+        Network::Denied | Network::Proxied(EgressProxyAddress::Port(_)) => {}
+        Network::Proxied(EgressProxyAddress::Socket(socket_path)) => args.extend([
+            "--bind".into(),
+            socket_path.to_string_lossy().into_owned(),
+            LINUX_PROXY_SOCKET.into(),
+        ]),
+    }
+    let system_paths = LINUX_SYSTEM_PATHS.iter().map(PathBuf::from);
+    for path in system_paths.chain(readable_paths.iter().cloned()) {
+        let path = path.to_string_lossy().into_owned();
+        args.extend(["--ro-bind-try".into(), path.clone(), path]);
+    }
+    args.extend([
+        "--dev".into(),
+        "/dev".into(),
+        "--proc".into(),
+        "/proc".into(),
+        "--tmpfs".into(),
+        "/tmp".into(),
+    ]);
+    // Writable paths are bound last, so that they take precedence over read-only parents.
+    for path in writable_paths {
+        let path = path.to_string_lossy().into_owned();
+        args.extend(["--bind".into(), path.clone(), path]);
+    }
+    args.push("--".into());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bubblewrap_args() {
+        let args = bubblewrap_args(
+            Network::Denied,
+            &[PathBuf::from("/home/me/.nvm")],
+            &[PathBuf::from("/home/me/project")],
+        );
+        assert!(!args.contains(&"--share-net".to_string()));
+        let has_mount = |mount: [&str; 3]| args.windows(3).any(|window| *window == mount);
+        assert!(has_mount([
+            "--ro-bind-try",
+            "/home/me/.nvm",
+            "/home/me/.nvm"
+        ]));
+        assert!(has_mount([
+            "--bind",
+            "/home/me/project",
+            "/home/me/project"
+        ]));
+        assert_eq!(args.last().map(String::as_str), Some("--"));
+
+        let args = bubblewrap_args(Network::Allowed, &[], &[]);
+        assert!(args.contains(&"--share-net".to_string()));
+
+        let socket = EgressProxyAddress::Socket(PathBuf::from("/tmp/zed-egress-proxy-1-0.sock"));
+        let args = bubblewrap_args(Network::Proxied(&socket), &[], &[]);
+        assert!(!args.contains(&"--share-net".to_string()));
+        assert!(args.windows(3).any(|window| *window
+            == [
+                "--bind",
+                "/tmp/zed-egress-proxy-1-0.sock",
+                "/run/zed-egress-proxy.sock"
+            ]));
+    }
+
+    #[test]
+    fn test_sandbox_exec_profile() {
+        let profile = sandbox_exec_profile(
+            Network::Allowed,
+            &[],
+            &[PathBuf::from("/Users/me/a \"quoted\" project")],
+        );
+        assert!(profile.contains("(deny default)"));
+        assert!(profile.contains(
+            "(allow file-read* file-write* (subpath \"/Users/me/a \\\"quoted\\\" project\"))"
+        ));
+        assert!(profile.contains("(allow network*)"));
+
+        let profile = sandbox_exec_profile(Network::Denied, &[], &[]);
+        assert!(!profile.contains("network"));
+
+        let profile =
+            sandbox_exec_profile(Network::Proxied(&EgressProxyAddress::Port(4000)), &[], &[]);
+        assert!(profile.contains("(allow network-outbound (remote ip \"localhost:4000\"))"));
+        assert!(!profile.contains("(allow network*)"));
+    }
+
+    #[test]
+    fn test_sandboxed_command_uses_scratch_dir_as_home() {
+        let command = ContextServerCommand {
+            path: "/opt/node/bin/node".into(),
+            args: vec!["server.js".into()],
+            env: None,
+            cwd: None,
+        };
+        let Ok(sandboxed) = sandboxed_command(
+            &command,
+            &ContextServerSandbox::default(),
+            &[PathBuf::from("/project")],
+            Path::new("/scratch"),
+            None,
+        ) else {
+            return;
+        };
+        let env = sandboxed.env.unwrap();
+        assert_eq!(env.get("HOME").map(String::as_str), Some("/scratch"));
+        assert_eq!(
+            &sandboxed.args[sandboxed.args.len() - 2..],
+            &["/opt/node/bin/node".to_string(), "server.js".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_allowed_hosts_require_egress_proxy() {
+        let command = ContextServerCommand {
+            path: "server".into(),
+            args: Vec::new(),
+            env: None,
+            cwd: None,
+        };
+        let sandbox = ContextServerSandbox {
+            allowed_hosts: vec!["api.github.com".into()],
+            ..Default::default()
+        };
+        let scratch_dir = Path::new("/scratch");
+        assert!(sandboxed_command(&command, &sandbox, &[], scratch_dir, None).is_err());
+        assert_eq!(
+            sandbox.validate().is_ok(),
+            cfg!(target_os = "macos")
+                || (cfg!(target_os = "linux") && which::which("socat").is_ok())
+        );
+        let offline = ContextServerSandbox {
+            allow_network: false,
+            ..sandbox.clone()
+        };
+        assert_eq!(
+            offline.validate().is_ok(),
+            cfg!(any(target_os = "macos", target_os = "linux"))
+        );
+        if cfg!(target_os = "macos") {
+            let address = EgressProxyAddress::Port(4000);
+            let sandboxed =
+                sandboxed_command(&command, &sandbox, &[], scratch_dir, Some(&address)).unwrap();
+            let env = sandboxed.env.unwrap();
+            assert_eq!(
+                env.get("HTTPS_PROXY").map(String::as_str),
+                Some("http://127.0.0.1:4000")
+            );
+        }
+        if cfg!(target_os = "linux") && sandbox.validate().is_ok() {
+            let address = EgressProxyAddress::Socket(PathBuf::from("/tmp/proxy.sock"));
+            let sandboxed =
+                sandboxed_command(&command, &sandbox, &[], scratch_dir, Some(&address)).unwrap();
+            let env = sandboxed.env.unwrap();
+            assert_eq!(
+                env.get("HTTPS_PROXY").map(String::as_str),
+                Some("http://127.0.0.1:3128")
+            );
+            let script = sandboxed.args.iter().position(|arg| arg == "-c").unwrap() + 1;
+            assert!(sandboxed.args[script].starts_with("socat TCP-LISTEN:3128"));
+            assert!(sandboxed.args[script].contains(":0C38 "));
+            assert_eq!(&sandboxed.args[script + 1..], ["sh", "server"]);
+        }
+    }
+}
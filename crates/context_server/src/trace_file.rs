@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context as _, Result};
+use serde_json::{Value, json};
+
+use crate::client::IoKind;
+use crate::message_log::redact_message;
+
+/// How large a trace file grows before it's rotated, so that tracing a chatty server for a long
+/// time doesn't fill the disk.
+const MAX_TRACE_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// A file that a context server's JSON-RPC messages are appended to, one JSON object per line,
+/// with sensitive values redacted.
+///
+/// Messages are written on a background thread, so that tracing doesn't slow down the traffic it
+/// traces. Once the file reaches its size limit, it's moved to the same path with a `.1` suffix,
+/// replacing the previous one, and a new file is started.
+pub struct TraceFile {
+    path: PathBuf,
+    messages: mpsc::Sender<TracedMessage>,
+}
+
+struct TracedMessage {
+    direction: &'static str,
+    message: String,
+    time: SystemTime,
+}
+
+impl TraceFile {
+    /// Opens the file at `path` for appending, creating it and its directory if needed.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        Self::open_with_max_bytes(path, MAX_TRACE_FILE_BYTES).map(|(trace_file, _)| trace_file)
+    }
+
+    fn open_with_max_bytes(path: PathBuf, max_bytes: u64) -> Result<(Self, JoinHandle<()>)> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating the directory of {path:?}"))?;
+        }
+        let file = open_for_appending(&path)?;
+        let (messages, receiver) = mpsc::channel();
+        let writer = std::thread::Builder::new()
+            .name("context server trace".into())
+            .spawn({
+                let path = path.clone();
+                move || write_messages(&path, file, max_bytes, receiver)
+            })
+            .context("starting the trace file writer")?;
+        Ok((Self { path, messages }, writer))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a message sent to or received from the server. The server's stderr isn't traced,
+    /// since it isn't part of the protocol.
+    pub fn append(&self, kind: IoKind, message: &str) {
+        let direction = match kind {
+            IoKind::Send => "send",
+            IoKind::Receive => "receive",
+            IoKind::StdErr => return,
+        };
+        // The writer only stops when this is dropped, or after failing to write, which it logged.
+        self.messages
+            .send(TracedMessage {
+                direction,
+                message: message.to_string(),
+                time: SystemTime::now(),
+            })
+            .ok();
+    }
+}
+
+fn open_for_appending(path: &Path) -> Result<File> {
+    File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening trace file {path:?}"))
+}
+
+/// Writes messages to the file until the trace file is dropped, rotating it when it gets too
+/// large.
+fn write_messages(
+    path: &Path,
+    mut file: File,
+    max_bytes: u64,
+    messages: mpsc::Receiver<TracedMessage>,
+) {
+    let result = (|| -> Result<()> {
+        let mut len = file.metadata()?.len();
+        let rotated_path = rotated_trace_file_path(path);
+        for message in messages {
+            let line = format!(
+                "{}\n",
+                trace_line(message.direction, &message.message, message.time)
+            );
+            if len > 0 && len + line.len() as u64 > max_bytes {
+                drop(file);
+                std::fs::rename(path, &rotated_path)
+                    .with_context(|| format!("rotating trace file {path:?}"))?;
+                file = open_for_appending(path)?;
+                len = 0;
+            }
+            file.write_all(line.as_bytes())?;
+            file.flush()?;
+            len += line.len() as u64;
+        }
+        Ok(())
+    })();
+    if let Err(error) = result {
+        log::error!("failed to write trace file {path:?}: {error:#}");
+    }
+}
+
+fn rotated_trace_file_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".1");
+    path.with_file_name(file_name)
+}
+
+fn trace_line(direction: &str, message: &str, time: SystemTime) -> Value {
+    let message = redact_message(message);
+    // Messages that aren't valid JSON are kept as strings, so that malformed frames are traced
+    // as they were sent.
+    let message = serde_json::from_str::<Value>(&message).unwrap_or(Value::String(message));
+    let time_ms = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64);
+    json!({ "time_ms": time_ms, "direction": direction, "message": message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_trace_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("traces").join("github.jsonl");
+        let (trace_file, writer) =
+            TraceFile::open_with_max_bytes(path.clone(), MAX_TRACE_FILE_BYTES).unwrap();
+        trace_file.append(
+            IoKind::Send,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"token":"ghp_123"}}"#,
+        );
+        trace_file.append(IoKind::StdErr, "starting");
+        trace_file.append(IoKind::Receive, "not json");
+        drop(trace_file);
+        writer.join().unwrap();
+
+        let lines = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["direction"], "send");
+        assert_eq!(lines[0]["message"]["params"]["token"], "[redacted]");
+        assert_eq!(lines[1]["direction"], "receive");
+        assert_eq!(lines[1]["message"], "not json");
+
+        assert_eq!(
+            trace_line(
+                "send",
+                "{}",
+                UNIX_EPOCH + Duration::from_millis(1_700_000_000_000)
+            ),
+            json!({ "time_ms": 1_700_000_000_000u64, "direction": "send", "message": {} })
+        );
+    }
+
+    #[test]
+    fn test_trace_file_rotation() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("server.jsonl");
+        let (trace_file, writer) = TraceFile::open_with_max_bytes(path.clone(), 200).unwrap();
+        for id in 0..10 {
+            trace_file.append(
+                IoKind::Send,
+                &format!(r#"{{"jsonrpc":"2.0","id":{id},"method":"ping"}}"#),
+            );
+        }
+        drop(trace_file);
+        writer.join().unwrap();
+
+        let read_ids = |path: &Path| {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap()["message"]["id"].clone())
+                .collect::<Vec<_>>()
+        };
+        let ids = read_ids(&path);
+        let rotated_ids = read_ids(&rotated_trace_file_path(&path));
+        assert!(std::fs::metadata(&path).unwrap().len() <= 200);
+        assert!(!rotated_ids.is_empty());
+        assert_eq!(ids.last(), Some(&json!(9)));
+        assert_eq!(
+            rotated_ids.last().and_then(Value::as_u64).map(|id| id + 1),
+            ids.first().and_then(Value::as_u64)
+        );
+    }
+}
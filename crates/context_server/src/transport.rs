@@ -1,3 +1,7 @@
+mod builtin_transport;
+mod channel_transport;
+mod openapi_transport;
+mod replay_transport;
 mod stdio_transport;
 
 use std::pin::Pin;
@@ -6,6 +10,10 @@ use anyhow::Result;
 use async_trait::async_trait;
 use futures::Stream;
 
+pub use builtin_transport::*;
+pub use channel_transport::*;
+pub use openapi_transport::*;
+pub use replay_transport::*;
 pub use stdio_transport::*;
 
 #[async_trait]
@@ -0,0 +1,200 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::Stream;
+use gpui::{BackgroundExecutor, Task};
+use serde_json::{Value, json};
+
+use crate::ContextServerId;
+use crate::builtin::BuiltinServer;
+use crate::client::{INTERNAL_ERROR, METHOD_NOT_FOUND};
+use crate::transport::{ChannelServerEndpoint, ChannelTransport, Transport};
+use crate::types::{self, RequestType};
+
+/// Serves a server built into Zed in-process, answering its requests without running a
+/// separate program.
+pub struct BuiltinTransport {
+    transport: ChannelTransport,
+    _serve: Task<()>,
+}
+
+impl BuiltinTransport {
+    pub fn new(
+        server_id: ContextServerId,
+        server: Arc<dyn BuiltinServer>,
+        executor: BackgroundExecutor,
+    ) -> Self {
+        let (transport, endpoint) = ChannelTransport::new();
+        let serve = executor.spawn(serve(server_id, server, endpoint, executor.clone()));
+        Self {
+            transport,
+            _serve: serve,
+        }
+    }
+}
+
+/// Answers the requests received on `endpoint` with `server`, until the transport is dropped.
+async fn serve(
+    server_id: ContextServerId,
+    server: Arc<dyn BuiltinServer>,
+    endpoint: ChannelServerEndpoint,
+    executor: BackgroundExecutor,
+) {
+    while let Ok(message) = endpoint.incoming.recv().await {
+        let message = match serde_json::from_str::<Value>(&message) {
+            Ok(message) => message,
+            Err(error) => {
+                log::error!("{server_id}: failed to parse a message: {error}");
+                continue;
+            }
+        };
+        // Notifications don't get a response.
+        let (Some(id), Some(method)) = (message.get("id").cloned(), message["method"].as_str())
+        else {
+            continue;
+        };
+        let method = method.to_string();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        // Requests are answered in the background, so that a slow tool call doesn't hold up the
+        // calls made after it.
+        let server_id = server_id.clone();
+        let server = server.clone();
+        let outgoing = endpoint.outgoing.clone();
+        executor
+            .spawn(async move {
+                let response = match respond(&server_id, server.as_ref(), &method, params).await {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                };
+                outgoing.send(response.to_string()).await.ok();
+            })
+            .detach();
+    }
+}
+
+async fn respond(
+    server_id: &ContextServerId,
+    server: &dyn BuiltinServer,
+    method: &str,
+    params: Value,
+) -> Result<Value, (i32, String)> {
+    let internal_error = |error: anyhow::Error| (INTERNAL_ERROR, format!("{error:#}"));
+    match RequestType::try_from(method) {
+        Ok(RequestType::Initialize) => Ok(json!({
+            "protocolVersion": types::LATEST_PROTOCOL_VERSION,
+            "serverInfo": { "name": server_id.to_string(), "version": env!("CARGO_PKG_VERSION") },
+            "capabilities": { "tools": {} },
+        })),
+        Ok(RequestType::Ping) => Ok(json!({})),
+        Ok(RequestType::ListTools) => {
+            let tools = server.tools().await.map_err(internal_error)?;
+            Ok(json!({ "tools": tools }))
+        }
+        Ok(RequestType::CallTool) => {
+            let name = params["name"].as_str().unwrap_or_default();
+            let arguments = params["arguments"].as_object().cloned().unwrap_or_default();
+            let response = server
+                .call_tool(name, arguments)
+                .await
+                .map_err(internal_error)?;
+            serde_json::to_value(response).map_err(|error| internal_error(error.into()))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+    }
+}
+
+#[async_trait]
+impl Transport for BuiltinTransport {
+    async fn send(&self, message: String) -> Result<()> {
+        self.transport.send(message).await
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        self.transport.receive()
+    }
+
+    fn receive_err(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        self.transport.receive_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContextServer;
+    use crate::builtin::FetchServer;
+    use collections::HashMap;
+    use gpui::TestAppContext;
+    use http_client::{FakeHttpClient, Response};
+
+    #[gpui::test]
+    async fn test_builtin_fetch_server(cx: &mut TestAppContext) {
+        let http_client = FakeHttpClient::create(|request| async move {
+            match request.uri().path() {
+                "/page" => Ok(Response::builder()
+                    .status(200)
+                    .header("Content-Type", "text/html")
+                    .body("<html><body><h1>Hello</h1><p>World</p></body></html>".into())?),
+                "/metadata" => Ok(Response::builder()
+                    .status(302)
+                    .header("Location", "http://169.254.169.254/latest/meta-data")
+                    .body(Default::default())?),
+                _ => Ok(Response::builder().status(404).body("not found".into())?),
+            }
+        });
+
+        let server_id = ContextServerId("fetch".into());
+        let transport = BuiltinTransport::new(
+            server_id.clone(),
+            Arc::new(FetchServer::new(http_client)),
+            cx.executor(),
+        );
+        let server = Arc::new(ContextServer::new(server_id, Arc::new(transport)));
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        let tools = protocol.list_tools().await.unwrap();
+        assert_eq!(tools.tools.len(), 1);
+        assert_eq!(tools.tools[0].name, "fetch_url");
+
+        let arguments = |url: &str| Some(HashMap::from_iter([("url".to_string(), json!(url))]));
+        let response = protocol
+            .run_tool("fetch_url", arguments("https://93.184.216.34/page"))
+            .await
+            .unwrap();
+        assert_eq!(response.is_error, Some(false));
+        assert!(matches!(
+            &response.content[..],
+            [types::ToolResponseContent::Text { text }] if text.contains("# Hello")
+        ));
+
+        let response = protocol
+            .run_tool("fetch_url", arguments("https://93.184.216.34/missing"))
+            .await
+            .unwrap();
+        assert_eq!(response.is_error, Some(true));
+        assert!(matches!(
+            &response.content[..],
+            [types::ToolResponseContent::Text { text }] if text.starts_with("HTTP 404")
+        ));
+
+        for url in ["http://127.0.0.1:8080/", "https://93.184.216.34/metadata"] {
+            let response = protocol
+                .run_tool("fetch_url", arguments(url))
+                .await
+                .unwrap();
+            assert_eq!(response.is_error, Some(true));
+            assert!(matches!(
+                &response.content[..],
+                [types::ToolResponseContent::Text { text }] if text.contains("isn't a public address")
+            ));
+        }
+    }
+}
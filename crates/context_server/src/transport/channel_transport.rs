@@ -0,0 +1,56 @@
+use std::pin::Pin;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures::Stream;
+use smol::channel;
+
+use crate::transport::Transport;
+
+/// Exchanges JSON-RPC messages with a server implemented in Rust through in-memory channels,
+/// so that it runs in-process instead of as a separate program.
+pub struct ChannelTransport {
+    to_server: channel::Sender<String>,
+    from_server: channel::Receiver<String>,
+}
+
+/// The server's end of a [`ChannelTransport`].
+pub struct ChannelServerEndpoint {
+    /// The messages Zed sends to the server.
+    pub incoming: channel::Receiver<String>,
+    /// Sends messages to Zed, such as responses and notifications.
+    pub outgoing: channel::Sender<String>,
+}
+
+impl ChannelTransport {
+    /// Returns a transport along with the endpoint its server reads and writes messages on.
+    pub fn new() -> (Self, ChannelServerEndpoint) {
+        let (to_server, incoming) = channel::unbounded();
+        let (outgoing, from_server) = channel::unbounded();
+        (
+            Self {
+                to_server,
+                from_server,
+            },
+            ChannelServerEndpoint { incoming, outgoing },
+        )
+    }
+}
+
+#[async_trait]
+impl Transport for ChannelTransport {
+    async fn send(&self, message: String) -> Result<()> {
+        self.to_server
+            .send(message)
+            .await
+            .context("the in-process server stopped")
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        Box::pin(self.from_server.clone())
+    }
+
+    fn receive_err(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        Box::pin(futures::stream::empty())
+    }
+}
@@ -0,0 +1,398 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context as _, Result, anyhow};
+use async_trait::async_trait;
+use futures::{AsyncReadExt as _, Stream};
+use gpui::BackgroundExecutor;
+use http_client::{AsyncBody, HttpClient, Request};
+use parking_lot::Mutex;
+use postage::watch;
+use serde_json::{Value, json};
+use smol::channel;
+
+use crate::ContextServerId;
+use crate::client::{INTERNAL_ERROR, METHOD_NOT_FOUND};
+use crate::openapi::{self, ContextServerOpenApi, OpenApiOperation};
+use crate::transport::Transport;
+use crate::types::{self, RequestType};
+
+/// How long tool calls are answered as offline without trying the API again, once a request
+/// to it couldn't be sent.
+const OFFLINE_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Serves the operations of an HTTP API as tools, by making a request to the API for each
+/// `tools/call`.
+pub struct OpenApiTransport {
+    bridge: Arc<OpenApiBridge>,
+    executor: BackgroundExecutor,
+    rx: channel::Receiver<String>,
+    online_rx: watch::Receiver<bool>,
+}
+
+struct OpenApiBridge {
+    server_id: ContextServerId,
+    settings: ContextServerOpenApi,
+    http_client: Arc<dyn HttpClient>,
+    executor: BackgroundExecutor,
+    api: Mutex<Option<Arc<OpenApi>>>,
+    tx: channel::Sender<String>,
+    /// When a request to the API last couldn't be sent, while the network seems unavailable.
+    offline_since: Mutex<Option<Instant>>,
+    online_tx: Mutex<watch::Sender<bool>>,
+}
+
+struct OpenApi {
+    base_url: String,
+    operations: Vec<OpenApiOperation>,
+}
+
+impl OpenApiTransport {
+    pub fn new(
+        server_id: ContextServerId,
+        settings: ContextServerOpenApi,
+        http_client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Self {
+        let (tx, rx) = channel::unbounded();
+        let (online_tx, online_rx) = watch::channel_with(true);
+        Self {
+            bridge: Arc::new(OpenApiBridge {
+                server_id,
+                settings,
+                http_client,
+                executor: executor.clone(),
+                api: Mutex::new(None),
+                tx,
+                offline_since: Mutex::new(None),
+                online_tx: Mutex::new(online_tx),
+            }),
+            executor,
+            rx,
+            online_rx,
+        }
+    }
+
+    /// Returns whether the API could be reached the last time a request was sent to it.
+    pub fn online(&self) -> watch::Receiver<bool> {
+        self.online_rx.clone()
+    }
+}
+
+impl OpenApiBridge {
+    async fn load_api(&self) -> Result<Arc<OpenApi>> {
+        if let Some(api) = self.api.lock().clone() {
+            return Ok(api);
+        }
+        let spec_url = &self.settings.spec_url;
+        let content = if spec_url.starts_with("http://") || spec_url.starts_with("https://") {
+            let (status, body) = self
+                .fetch(Request::get(spec_url).body(AsyncBody::empty())?)
+                .await?;
+            if status >= 400 {
+                return Err(anyhow!("fetching {spec_url} failed with HTTP {status}"));
+            }
+            body
+        } else {
+            smol::fs::read_to_string(spec_url.trim_start_matches("file://"))
+                .await
+                .with_context(|| format!("reading the OpenAPI document {spec_url}"))?
+        };
+        let spec = openapi::parse_spec(&content)
+            .with_context(|| format!("parsing the OpenAPI document {spec_url}"))?;
+        let api = Arc::new(OpenApi {
+            base_url: openapi::base_url(&self.settings, &spec)?,
+            operations: openapi::operations(&spec)?,
+        });
+        *self.api.lock() = Some(api.clone());
+        Ok(api)
+    }
+
+    async fn fetch(&self, request: Request<AsyncBody>) -> Result<(u16, String)> {
+        let response = self.http_client.send(request).await;
+        self.set_online(response.is_ok());
+        let mut response = response?;
+        let mut body = String::new();
+        response
+            .body_mut()
+            .read_to_string(&mut body)
+            .await
+            .context("reading the response")?;
+        Ok((response.status().as_u16(), body))
+    }
+
+    fn set_online(&self, online: bool) {
+        *self.offline_since.lock() = (!online).then(|| self.executor.now());
+        let mut online_tx = self.online_tx.lock();
+        if *online_tx.borrow() != online {
+            *online_tx.borrow_mut() = online;
+        }
+    }
+
+    /// Returns whether calls should be answered as offline without trying the API.
+    fn is_offline(&self) -> bool {
+        self.offline_since.lock().is_some_and(|offline_since| {
+            self.executor.now() - offline_since < OFFLINE_RETRY_INTERVAL
+        })
+    }
+
+    /// A structured result for calls made while the API can't be reached, so that the model
+    /// can tell them apart from the API's own errors.
+    fn offline_response(&self) -> Value {
+        json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "{} is offline, since its API can't be reached. The call wasn't made. \
+                    Try again later, or do without this tool.",
+                    self.server_id
+                ),
+            }],
+            "isError": true,
+            "structuredContent": { "offline": true },
+        })
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value> {
+        if self.is_offline() {
+            return Ok(self.offline_response());
+        }
+        let api = self.load_api().await?;
+        let name = params["name"].as_str().unwrap_or_default();
+        let operation = api
+            .operations
+            .iter()
+            .find(|operation| operation.tool.name == name)
+            .ok_or_else(|| openapi::missing_operation(name))?;
+        let arguments = params["arguments"].as_object().cloned().unwrap_or_default();
+        let request = operation.request(&api.base_url, &self.settings.headers, &arguments)?;
+        log::debug!("{}: {request}", self.server_id);
+
+        let mut builder = Request::builder()
+            .method(request.method.as_str())
+            .uri(request.url.as_str());
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let body = request.body.map_or_else(AsyncBody::empty, AsyncBody::from);
+        match self.fetch(builder.body(body)?).await {
+            Ok((status, body)) => Ok(openapi::tool_response(status, body)),
+            Err(error) if self.is_offline() => {
+                log::warn!("{}: the API can't be reached: {error:#}", self.server_id);
+                Ok(self.offline_response())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn respond(&self, method: &str, params: Value) -> Result<Value, (i32, String)> {
+        let internal_error = |error: anyhow::Error| (INTERNAL_ERROR, format!("{error:#}"));
+        match RequestType::try_from(method) {
+            Ok(RequestType::Initialize) => Ok(json!({
+                "protocolVersion": types::LATEST_PROTOCOL_VERSION,
+                "serverInfo": { "name": self.server_id.to_string(), "version": "1.0.0" },
+                "capabilities": { "tools": {} },
+            })),
+            Ok(RequestType::Ping) => Ok(json!({})),
+            Ok(RequestType::ListTools) => {
+                let api = self.load_api().await.map_err(internal_error)?;
+                let tools = api
+                    .operations
+                    .iter()
+                    .map(|operation| &operation.tool)
+                    .collect::<Vec<_>>();
+                Ok(json!({ "tools": tools }))
+            }
+            Ok(RequestType::CallTool) => self.call_tool(&params).await.map_err(internal_error),
+            _ => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for OpenApiTransport {
+    async fn send(&self, message: String) -> Result<()> {
+        let message = serde_json::from_str::<Value>(&message).context("parsing a message")?;
+        // Notifications don't get a response.
+        let (Some(id), Some(method)) = (message.get("id").cloned(), message["method"].as_str())
+        else {
+            return Ok(());
+        };
+        let method = method.to_string();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        // Requests are answered in the background, so that a slow API call doesn't hold up the
+        // calls made after it.
+        let bridge = self.bridge.clone();
+        self.executor
+            .spawn(async move {
+                let response = match bridge.respond(&method, params).await {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                };
+                bridge.tx.send(response.to_string()).await.ok();
+            })
+            .detach();
+        Ok(())
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        Box::pin(self.rx.clone())
+    }
+
+    fn receive_err(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        Box::pin(futures::stream::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContextServer;
+    use collections::HashMap;
+    use gpui::TestAppContext;
+    use http_client::{FakeHttpClient, Response};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
+
+    #[gpui::test]
+    async fn test_openapi_transport(cx: &mut TestAppContext) {
+        let http_client = FakeHttpClient::create(|request| async move {
+            let body = match (request.method().as_str(), request.uri().path()) {
+                ("GET", "/openapi.json") => json!({
+                    "openapi": "3.0.0",
+                    "servers": [{ "url": "https://api.example.com" }],
+                    "paths": {
+                        "/users/{id}": {
+                            "get": {
+                                "operationId": "getUser",
+                                "parameters": [{ "name": "id", "in": "path" }]
+                            }
+                        }
+                    }
+                })
+                .to_string(),
+                ("GET", "/users/7") => {
+                    assert_eq!(request.headers()["Authorization"], "Bearer secret");
+                    json!({ "id": 7, "name": "Ada" }).to_string()
+                }
+                _ => {
+                    return Ok(Response::builder().status(404).body("not found".into())?);
+                }
+            };
+            Ok(Response::builder().status(200).body(body.into())?)
+        });
+
+        let server_id = ContextServerId("users-api".into());
+        let transport = OpenApiTransport::new(
+            server_id.clone(),
+            ContextServerOpenApi {
+                spec_url: "https://api.example.com/openapi.json".into(),
+                base_url: None,
+                headers: BTreeMap::from_iter([(
+                    "Authorization".to_string(),
+                    "Bearer secret".to_string(),
+                )]),
+            },
+            http_client,
+            cx.executor(),
+        );
+        let server = Arc::new(ContextServer::new(server_id, Arc::new(transport)));
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        let tools = protocol.list_tools().await.unwrap();
+        assert_eq!(tools.tools.len(), 1);
+        assert_eq!(tools.tools[0].name, "getUser");
+
+        let arguments = |id: u32| Some(HashMap::from_iter([("id".to_string(), json!(id))]));
+        let response = protocol.run_tool("getUser", arguments(7)).await.unwrap();
+        assert_eq!(response.is_error, Some(false));
+        assert!(matches!(
+            &response.content[..],
+            [types::ToolResponseContent::Text { text }] if text.contains("Ada")
+        ));
+
+        let response = protocol.run_tool("getUser", arguments(8)).await.unwrap();
+        assert_eq!(response.is_error, Some(true));
+        assert!(matches!(
+            &response.content[..],
+            [types::ToolResponseContent::Text { text }] if text.starts_with("HTTP 404")
+        ));
+    }
+
+    #[gpui::test]
+    async fn test_openapi_transport_offline(cx: &mut TestAppContext) {
+        let network_available = Arc::new(AtomicBool::new(true));
+        let api_requests = Arc::new(AtomicUsize::new(0));
+        let http_client = FakeHttpClient::create({
+            let network_available = network_available.clone();
+            let api_requests = api_requests.clone();
+            move |request| {
+                let network_available = network_available.load(SeqCst);
+                let api_requests = api_requests.clone();
+                async move {
+                    anyhow::ensure!(network_available, "failed to lookup address information");
+                    let body = if request.uri().path() == "/openapi.json" {
+                        json!({
+                            "openapi": "3.0.0",
+                            "servers": [{ "url": "https://api.example.com" }],
+                            "paths": { "/status": { "get": { "operationId": "getStatus" } } }
+                        })
+                    } else {
+                        api_requests.fetch_add(1, SeqCst);
+                        json!({ "status": "ok" })
+                    };
+                    Ok(Response::builder()
+                        .status(200)
+                        .body(body.to_string().into())?)
+                }
+            }
+        });
+
+        let server_id = ContextServerId("status-api".into());
+        let transport = OpenApiTransport::new(
+            server_id.clone(),
+            ContextServerOpenApi {
+                spec_url: "https://api.example.com/openapi.json".into(),
+                base_url: None,
+                headers: BTreeMap::default(),
+            },
+            http_client,
+            cx.executor(),
+        );
+        let online = transport.online();
+        let server = Arc::new(ContextServer::new(server_id, Arc::new(transport)));
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        network_available.store(false, SeqCst);
+        let response = protocol.run_tool("getStatus", None).await.unwrap();
+        assert_eq!(response.is_error, Some(true));
+        assert_eq!(
+            response.structured_content,
+            Some(json!({ "offline": true }))
+        );
+        assert!(!*online.borrow());
+
+        // Calls made soon after are answered without trying the API.
+        network_available.store(true, SeqCst);
+        let response = protocol.run_tool("getStatus", None).await.unwrap();
+        assert_eq!(
+            response.structured_content,
+            Some(json!({ "offline": true }))
+        );
+        assert_eq!(api_requests.load(SeqCst), 0);
+
+        cx.executor().advance_clock(OFFLINE_RETRY_INTERVAL);
+        let response = protocol.run_tool("getStatus", None).await.unwrap();
+        assert_eq!(response.is_error, Some(false));
+        assert_eq!(api_requests.load(SeqCst), 1);
+        assert!(*online.borrow());
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures::Stream;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use smol::channel;
+
+use crate::ContextServerId;
+use crate::client::{INTERNAL_ERROR, METHOD_NOT_FOUND};
+use crate::recording::{RecordedOutcome, RecordedToolCall, parse_recording};
+use crate::transport::Transport;
+use crate::types::{self, RequestType};
+
+/// Serves the tool calls in a recording back instead of running a context server.
+///
+/// The server lists the tools that appear in the recording for its id. Each `tools/call` is
+/// answered with the first unused recorded call to the same tool with the same arguments, or
+/// failing that, to the same tool, so that replays tolerate arguments that vary between runs.
+pub struct ReplayTransport {
+    server_id: ContextServerId,
+    recording_path: PathBuf,
+    calls: Mutex<Option<Vec<(RecordedToolCall, bool)>>>,
+    tx: channel::Sender<String>,
+    rx: channel::Receiver<String>,
+}
+
+impl ReplayTransport {
+    pub fn new(server_id: ContextServerId, recording_path: PathBuf) -> Self {
+        let (tx, rx) = channel::unbounded();
+        Self {
+            server_id,
+            recording_path,
+            calls: Mutex::new(None),
+            tx,
+            rx,
+        }
+    }
+
+    async fn load_recording(&self) -> Result<()> {
+        if self.calls.lock().is_some() {
+            return Ok(());
+        }
+        let content = smol::fs::read_to_string(&self.recording_path)
+            .await
+            .with_context(|| format!("reading tool call recording {:?}", self.recording_path))?;
+        let calls = parse_recording(&content)?
+            .into_iter()
+            .filter(|call| call.server == self.server_id.0.as_ref())
+            .map(|call| (call, false))
+            .collect();
+        self.calls.lock().get_or_insert(calls);
+        Ok(())
+    }
+
+    fn respond(&self, method: &str, params: Value) -> Result<Value, (i32, String)> {
+        let mut calls = self.calls.lock();
+        let calls = calls.get_or_insert_default();
+        match RequestType::try_from(method) {
+            Ok(RequestType::Initialize) => Ok(json!({
+                "protocolVersion": types::LATEST_PROTOCOL_VERSION,
+                "serverInfo": { "name": format!("{} (replay)", self.server_id), "version": "1.0.0" },
+                "capabilities": { "tools": {} },
+            })),
+            Ok(RequestType::Ping) => Ok(json!({})),
+            Ok(RequestType::ListTools) => {
+                let mut tools = Vec::<&types::Tool>::new();
+                for (call, _) in calls.iter() {
+                    if !tools.iter().any(|tool| tool.name == call.tool.name) {
+                        tools.push(&call.tool);
+                    }
+                }
+                Ok(json!({ "tools": tools }))
+            }
+            Ok(RequestType::CallTool) => {
+                let name = params["name"].as_str().unwrap_or_default();
+                let arguments = &params["arguments"];
+                let unused_call_to = |matches_arguments: bool| {
+                    calls.iter().position(|(call, used)| {
+                        !used
+                            && call.tool.name == name
+                            && (!matches_arguments
+                                || normalize(&call.arguments) == normalize(arguments))
+                    })
+                };
+                let ix = unused_call_to(true)
+                    .or_else(|| unused_call_to(false))
+                    .ok_or_else(|| {
+                        (
+                            INTERNAL_ERROR,
+                            format!("no recorded call to {name} left to replay"),
+                        )
+                    })?;
+                let (call, used) = &mut calls[ix];
+                *used = true;
+                match &call.outcome {
+                    RecordedOutcome::Response(response) => Ok(response.clone()),
+                    RecordedOutcome::Error(error) => Err((INTERNAL_ERROR, error.clone())),
+                }
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+        }
+    }
+}
+
+/// Treats missing arguments the same as an empty argument object.
+fn normalize(arguments: &Value) -> Value {
+    match arguments {
+        Value::Null => json!({}),
+        arguments => arguments.clone(),
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, message: String) -> Result<()> {
+        let message = serde_json::from_str::<Value>(&message).context("parsing a message")?;
+        // Notifications don't get a response.
+        let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) else {
+            return Ok(());
+        };
+
+        self.load_recording().await?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let response = match self.respond(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        self.tx
+            .send(response.to_string())
+            .await
+            .context("sending a response")?;
+        Ok(())
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        Box::pin(self.rx.clone())
+    }
+
+    fn receive_err(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
+        Box::pin(futures::stream::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContextServer;
+    use crate::recording::append_recorded_call;
+    use collections::HashMap;
+    use gpui::TestAppContext;
+
+    #[gpui::test]
+    async fn test_replay_transport(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        let dir = tempfile::tempdir().unwrap();
+        let recording_path = dir.path().join("recording.jsonl");
+        let tool = types::Tool {
+            name: "fetch".into(),
+            description: Some("Fetches a URL".into()),
+            input_schema: json!({ "type": "object" }),
+            annotations: None,
+        };
+        for (server, url, outcome) in [
+            (
+                "web",
+                "https://a.com",
+                RecordedOutcome::Response(json!({ "content": [{ "type": "text", "text": "a" }] })),
+            ),
+            (
+                "web",
+                "https://b.com",
+                RecordedOutcome::Error("timed out".into()),
+            ),
+            (
+                "other",
+                "https://c.com",
+                RecordedOutcome::Response(json!({ "content": [] })),
+            ),
+        ] {
+            let call = RecordedToolCall {
+                server: server.into(),
+                server_identity: None,
+                tool: tool.clone(),
+                arguments: json!({ "url": url }),
+                outcome,
+            };
+            append_recorded_call(&recording_path, &call).await.unwrap();
+        }
+
+        let server_id = ContextServerId("web".into());
+        let transport = ReplayTransport::new(server_id.clone(), recording_path);
+        let server = std::sync::Arc::new(ContextServer::new(
+            server_id,
+            std::sync::Arc::new(transport),
+        ));
+        server.clone().start(&cx.to_async()).await.unwrap();
+        let protocol = server.client().unwrap();
+
+        let tools = protocol.list_tools().await.unwrap();
+        assert_eq!(tools.tools.len(), 1);
+        assert_eq!(tools.tools[0].name, "fetch");
+
+        // Calls are matched by their arguments rather than the order they were recorded in.
+        let arguments = |url: &str| HashMap::from_iter([("url".to_string(), json!(url))]);
+        let error = protocol
+            .run_tool("fetch", Some(arguments("https://b.com")))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+        let response = protocol
+            .run_tool("fetch", Some(arguments("https://a.com")))
+            .await
+            .unwrap();
+        assert!(matches!(
+            &response.content[..],
+            [types::ToolResponseContent::Text { text }] if text == "a"
+        ));
+
+        // Calls to other servers aren't replayed, and each call is only replayed once.
+        let error = protocol
+            .run_tool("fetch", Some(arguments("https://c.com")))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("no recorded call"));
+    }
+}
@@ -11,7 +11,9 @@ use smol::channel;
 use smol::process::Child;
 use util::TryFutureExt as _;
 
-use crate::client::ModelContextServerBinary;
+use crate::client::{
+    ModelContextServerBinary, OVERSIZED_MESSAGE_CONTEXT_BYTES, oversized_message_error,
+};
 use crate::transport::Transport;
 
 pub struct StdioTransport {
@@ -22,7 +24,11 @@ pub struct StdioTransport {
 }
 
 impl StdioTransport {
-    pub fn new(binary: ModelContextServerBinary, cx: &AsyncApp) -> Result<Self> {
+    pub fn new(
+        binary: ModelContextServerBinary,
+        max_message_bytes: Option<usize>,
+        cx: &AsyncApp,
+    ) -> Result<Self> {
         let mut command = util::command::new_smol_command(&binary.executable);
         command
             .args(&binary.args)
@@ -31,6 +37,9 @@ impl StdioTransport {
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .kill_on_drop(true);
+        if let Some(cwd) = &binary.cwd {
+            command.current_dir(cwd);
+        }
 
         let mut server = command.spawn().with_context(|| {
             format!(
@@ -50,7 +59,7 @@ impl StdioTransport {
         cx.spawn(async move |_| Self::handle_output(stdin, stdout_receiver).log_err().await)
             .detach();
 
-        cx.spawn(async move |_| Self::handle_input(stdout, stdin_sender).await)
+        cx.spawn(async move |_| Self::handle_input(stdout, stdin_sender, max_message_bytes).await)
             .detach();
 
         cx.spawn(async move |_| Self::handle_err(stderr, stderr_sender).await)
@@ -64,20 +73,67 @@ impl StdioTransport {
         })
     }
 
-    async fn handle_input<Stdout>(stdin: Stdout, inbound_rx: channel::Sender<String>)
-    where
+    /// Forwards the server's messages, one per line. Reading a line larger than
+    /// `max_message_bytes` stops buffering it, and an error response is sent in its place.
+    async fn handle_input<Stdout>(
+        stdin: Stdout,
+        inbound_rx: channel::Sender<String>,
+        max_message_bytes: Option<usize>,
+    ) where
         Stdout: AsyncRead + Unpin + Send + 'static,
     {
         let mut stdin = BufReader::new(stdin);
-        let mut line = String::new();
-        while let Ok(n) = stdin.read_line(&mut line).await {
-            if n == 0 {
+        let mut line = Vec::new();
+        let mut oversized_tail = None::<Vec<u8>>;
+        loop {
+            let Ok(buffer) = stdin.fill_buf().await else {
                 break;
-            }
-            if inbound_rx.send(line.clone()).await.is_err() {
+            };
+            if buffer.is_empty() {
                 break;
             }
+            let (chunk, line_ended) = match buffer.iter().position(|byte| *byte == b'\n') {
+                Some(ix) => (&buffer[..=ix], true),
+                None => (buffer, false),
+            };
+            let chunk_len = chunk.len();
+            if let Some(tail) = oversized_tail.as_mut() {
+                tail.extend_from_slice(chunk);
+                let excess = tail.len().saturating_sub(OVERSIZED_MESSAGE_CONTEXT_BYTES);
+                tail.drain(..excess);
+            } else {
+                line.extend_from_slice(chunk);
+                if let Some(max_bytes) = max_message_bytes.filter(|max| line.len() > *max) {
+                    let tail_start = line.len().saturating_sub(OVERSIZED_MESSAGE_CONTEXT_BYTES);
+                    oversized_tail = Some(line[tail_start..].to_vec());
+                    line.truncate(OVERSIZED_MESSAGE_CONTEXT_BYTES);
+                    log::warn!(
+                        "context server sent a message over its limit of {max_bytes} bytes, \
+                        which is being discarded"
+                    );
+                }
+            }
+            stdin.consume_unpin(chunk_len);
+            if !line_ended {
+                continue;
+            }
+
+            let message = match oversized_tail.take() {
+                Some(tail) => max_message_bytes.and_then(|max_bytes| {
+                    oversized_message_error(
+                        &String::from_utf8_lossy(&line),
+                        &String::from_utf8_lossy(&tail),
+                        max_bytes,
+                    )
+                }),
+                None => Some(String::from_utf8_lossy(&line).into_owned()),
+            };
             line.clear();
+            if let Some(message) = message {
+                if inbound_rx.send(message).await.is_err() {
+                    break;
+                }
+            }
         }
     }
 
@@ -19,6 +19,8 @@ pub enum RequestType {
     ListTools,
     ListResourceTemplates,
     ListRoots,
+    CreateMessage,
+    Elicit,
 }
 
 impl RequestType {
@@ -38,6 +40,8 @@ impl RequestType {
             RequestType::ListTools => "tools/list",
             RequestType::ListResourceTemplates => "resources/templates/list",
             RequestType::ListRoots => "roots/list",
+            RequestType::CreateMessage => "sampling/createMessage",
+            RequestType::Elicit => "elicitation/create",
         }
     }
 }
@@ -61,6 +65,8 @@ impl TryFrom<&str> for RequestType {
             "tools/list" => Ok(RequestType::ListTools),
             "resources/templates/list" => Ok(RequestType::ListResourceTemplates),
             "roots/list" => Ok(RequestType::ListRoots),
+            "sampling/createMessage" => Ok(RequestType::CreateMessage),
+            "elicitation/create" => Ok(RequestType::Elicit),
             _ => Err(()),
         }
     }
@@ -184,6 +190,9 @@ pub struct InitializeResponse {
     pub protocol_version: ProtocolVersion,
     pub capabilities: ServerCapabilities,
     pub server_info: Implementation,
+    /// Guidance on how to use the server, which clients can add to the model's system prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
     #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
     pub meta: Option<HashMap<String, serde_json::Value>>,
 }
@@ -412,13 +421,38 @@ pub struct RootsCapabilities {
     pub list_changed: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Tool {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub input_schema: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Hints describing a tool's behavior. Clients must not rely on these for security,
+/// since they're provided by the (potentially untrusted) server.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+    /// A template for the text Zed shows for calls to the tool, such as
+    /// `"Query {database} for {table}"`, whose placeholders are replaced by the call's arguments.
+    ///
+    /// This isn't part of the protocol, so only servers written with Zed in mind provide it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ui_text_template: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -426,6 +460,20 @@ pub struct Tool {
 pub struct Implementation {
     pub name: String,
     pub version: String,
+    /// A name for the implementation meant for display, where `name` is an identifier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icons: Option<Vec<Icon>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Icon {
+    /// A URL or a `data:` URI of the icon's image.
+    pub src: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -445,6 +493,9 @@ pub struct ResourceContents {
     pub uri: Url,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
+    /// The resource's text, when it's a text resource embedded in a response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -557,13 +608,16 @@ pub enum ProgressToken {
     Number(f64),
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProgressParams {
     pub progress_token: ProgressToken,
     pub progress: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total: Option<f64>,
+    /// A description of the progress, which some servers use to send partial results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
     #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
     pub meta: Option<HashMap<String, serde_json::Value>>,
 }
@@ -589,12 +643,15 @@ pub struct Completion {
     pub total: CompletionTotal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CallToolResponse {
     pub content: Vec<ToolResponseContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
+    /// The result as JSON, for tools whose output is structured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<serde_json::Value>,
     #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
     pub meta: Option<HashMap<String, serde_json::Value>>,
 }
@@ -178,7 +178,7 @@ pub struct Request {
 pub struct Function {
     pub name: String,
     pub description: String,
-    pub parameters: serde_json::Value,
+    pub parameters: Arc<serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -7,7 +7,7 @@ use futures::{
 use http_client::{AsyncBody, HttpClient, Method, Request as HttpRequest};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::convert::TryFrom;
+use std::{convert::TryFrom, sync::Arc};
 
 pub const DEEPSEEK_API_URL: &str = "https://api.deepseek.com";
 
@@ -145,7 +145,7 @@ pub enum ToolDefinition {
 pub struct FunctionDefinition {
     pub name: String,
     pub description: Option<String>,
-    pub parameters: Option<Value>,
+    pub parameters: Option<Arc<Value>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
@@ -111,6 +111,13 @@ pub struct ConfirmCodeAction {
     pub item_ix: Option<usize>,
 }
 
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ApplyCodeActionToAllOccurrences {
+    #[serde(default)]
+    pub item_ix: Option<usize>,
+}
+
 #[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct ToggleComments {
@@ -213,6 +220,7 @@ pub enum UuidVersion {
 impl_actions!(
     editor,
     [
+        ApplyCodeActionToAllOccurrences,
         ComposeCompletion,
         ConfirmCodeAction,
         ConfirmCompletion,
@@ -325,6 +333,7 @@ actions!(
         HalfPageUp,
         Hover,
         Indent,
+        InsertDocComment,
         InsertUuidV4,
         InsertUuidV7,
         JoinLines,
@@ -377,6 +386,7 @@ actions!(
         RestartLanguageServer,
         RevealInFileManager,
         ReverseLines,
+        RevertCollaboratorEdits,
         RevertFile,
         ReloadFile,
         Rewrap,
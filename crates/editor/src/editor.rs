@@ -5633,6 +5633,95 @@ impl Editor {
         }
     }
 
+    /// Previews the selected code action's fix at every diagnostic in the project with the same
+    /// code, in a multibuffer from which the fixes can be applied.
+    pub fn apply_code_action_to_all_occurrences(
+        &mut self,
+        action: &ApplyCodeActionToAllOccurrences,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let CodeContextMenu::CodeActions(actions_menu) = self.hide_context_menu(window, cx)? else {
+            return None;
+        };
+        let action_ix = action.item_ix.unwrap_or(actions_menu.selected_item);
+        let CodeActionsItem::CodeAction { action, .. } = actions_menu.actions.get(action_ix)?
+        else {
+            return None;
+        };
+        let title = action.lsp_action.title().to_string();
+        let project = self.project.clone()?;
+        let workspace = self.workspace()?.downgrade();
+        let find_actions = project.update(cx, |project, cx| {
+            project.code_actions_for_matching_diagnostics(&actions_menu.buffer, &action, cx)
+        });
+
+        Some(cx.spawn_in(window, async move |_, cx| {
+            let actions = find_actions.await?;
+            let action_count = actions.len();
+            let edits = project
+                .update(cx, |project, cx| project.code_action_edits(actions, cx))?
+                .await?;
+            if edits.iter().all(|(_, edits)| edits.is_empty()) {
+                anyhow::bail!("{title:?} doesn't apply to any diagnostics");
+            }
+            let occurrences = match action_count {
+                1 => "1 occurrence".to_string(),
+                count => format!("{count} occurrences"),
+            };
+
+            // The fixes are made to branches of the buffers, so they can be reviewed and then
+            // applied, or discarded, from the preview.
+            cx.update(|window, cx| {
+                let locations = edits
+                    .iter()
+                    .map(|(buffer, edits)| ProposedChangeLocation {
+                        buffer: buffer.clone(),
+                        ranges: edits.iter().map(|(range, _)| range.clone()).collect(),
+                    })
+                    .collect::<Vec<_>>();
+                let preview = cx.new(|cx| {
+                    ProposedChangesEditor::new(
+                        format!("{title} ({occurrences})"),
+                        locations,
+                        Some(project.clone()),
+                        window,
+                        cx,
+                    )
+                });
+                for (buffer, edits) in edits {
+                    let Some(branch) = preview.read(cx).branch_buffer_for_base(&buffer) else {
+                        continue;
+                    };
+                    let snapshot = buffer.read(cx).snapshot();
+                    let mut edits = edits
+                        .into_iter()
+                        .map(|(range, text)| (range.to_offset(&snapshot), text))
+                        .collect::<Vec<_>>();
+                    edits.sort_by_key(|(range, _)| (range.start, range.end));
+                    edits.dedup();
+                    // Fixes for neighbouring diagnostics can overlap, and only the first of
+                    // those is kept.
+                    let mut end = 0;
+                    edits.retain(|(range, _)| {
+                        let keep = range.start >= end;
+                        if keep {
+                            end = range.end;
+                        }
+                        keep
+                    });
+                    branch.update(cx, |branch, cx| branch.edit(edits, None, cx));
+                }
+                workspace.update(cx, |workspace, cx| {
+                    workspace.active_pane().update(cx, |pane, cx| {
+                        pane.add_item(Box::new(preview), true, true, None, window, cx)
+                    })
+                })
+            })??;
+            Ok(())
+        }))
+    }
+
     pub async fn open_project_transaction(
         this: &WeakEntity<Editor>,
         workspace: WeakEntity<Workspace>,
@@ -9540,6 +9629,62 @@ impl Editor {
         }
     }
 
+    /// Reverts the edits collaborators made within the selections, or on the lines of empty
+    /// selections, leaving the user's own edits in place.
+    pub fn revert_collaborator_edits(
+        &mut self,
+        _: &RevertCollaboratorEdits,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.read_only(cx) {
+            return;
+        }
+        self.hide_mouse_cursor(&HideMouseCursorOrigin::TypingAction);
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut ranges_by_buffer = HashMap::<BufferId, Vec<Range<text::Anchor>>>::default();
+        for selection in self.selections.all::<Point>(cx) {
+            let range = if selection.is_empty() {
+                let row = selection.head().row;
+                Point::new(row, 0)..Point::new(row, snapshot.line_len(MultiBufferRow(row)))
+            } else {
+                selection.range()
+            };
+            for (buffer, range, _) in snapshot.range_to_buffer_ranges(range) {
+                ranges_by_buffer
+                    .entry(buffer.remote_id())
+                    .or_default()
+                    .push(buffer.anchor_before(range.start)..buffer.anchor_after(range.end));
+            }
+        }
+
+        let mut transactions = Vec::new();
+        for (buffer_id, ranges) in ranges_by_buffer {
+            let Some(buffer) = self.buffer.read(cx).buffer(buffer_id) else {
+                continue;
+            };
+            let transaction = buffer.update(cx, |buffer, cx| {
+                let transaction_id = buffer.revert_collaborator_edits(ranges, cx)?;
+                buffer.get_transaction(transaction_id).cloned()
+            });
+            transactions.extend(transaction.map(|transaction| (buffer, transaction)));
+        }
+
+        if !transactions.is_empty() {
+            self.buffer.update(cx, |multi_buffer, cx| {
+                if !multi_buffer.is_singleton() {
+                    multi_buffer.push_transaction(
+                        transactions
+                            .iter()
+                            .map(|(buffer, transaction)| (buffer, transaction)),
+                        cx,
+                    );
+                }
+            });
+        }
+    }
+
     pub fn open_active_item_in_terminal(
         &mut self,
         _: &OpenInTerminal,
@@ -17452,6 +17597,40 @@ impl Editor {
         .detach();
     }
 
+    /// Inserts a documentation comment skeleton for the function containing the newest cursor,
+    /// and moves the cursor to where its summary should be written.
+    pub fn insert_doc_comment(
+        &mut self,
+        _: &InsertDocComment,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let cursor = self.selections.newest::<Point>(cx).head();
+        let Some((buffer, buffer_point, excerpt_id)) = snapshot.point_to_buffer_point(cursor)
+        else {
+            return;
+        };
+        let Some(skeleton) = buffer.doc_comment_skeleton_at(buffer_point) else {
+            return;
+        };
+        let Some(position) =
+            snapshot.anchor_in_excerpt(excerpt_id, buffer.anchor_before(skeleton.position))
+        else {
+            return;
+        };
+        let insertion_offset = position.to_offset(&snapshot);
+
+        self.hide_mouse_cursor(&HideMouseCursorOrigin::TypingAction);
+        self.transact(window, cx, |this, window, cx| {
+            this.edit([(insertion_offset..insertion_offset, skeleton.text)], cx);
+            let cursor = insertion_offset + skeleton.cursor_offset;
+            this.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
+                s.select_ranges([cursor..cursor]);
+            });
+        });
+    }
+
     pub fn insert_uuid_v4(
         &mut self,
         _: &InsertUuidV4,
@@ -21,8 +21,8 @@ use indoc::indoc;
 use language::{
     BracketPairConfig,
     Capability::ReadWrite,
-    FakeLspAdapter, LanguageConfig, LanguageConfigOverride, LanguageMatcher, LanguageName,
-    Override, Point,
+    DocCommentConfig, DocCommentPlacement, FakeLspAdapter, LanguageConfig, LanguageConfigOverride,
+    LanguageMatcher, LanguageName, Override, Point,
     language_settings::{
         AllLanguageSettings, AllLanguageSettingsContent, CompletionSettings,
         LanguageSettingsContent, LspInsertMode, PrettierSettings,
@@ -3071,6 +3071,41 @@ async fn test_newline_documentation_comments(cx: &mut TestAppContext) {
     "});
 }
 
+#[gpui::test]
+async fn test_newline_block_comments_in_typescript(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    let language = languages::language(
+        "typescript",
+        tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+    );
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    // Plain block comments continue, not only doc comments.
+    cx.set_state(indoc! {"
+        /*ˇ */
+    "});
+    cx.update_editor(|e, window, cx| e.newline(&Newline, window, cx));
+    cx.assert_editor_state(indoc! {"
+        /*
+         * ˇ
+         */
+    "});
+
+    cx.set_state(indoc! {"
+        /**
+         * Adds two numbers.ˇ
+         */
+    "});
+    cx.update_editor(|e, window, cx| e.newline(&Newline, window, cx));
+    cx.assert_editor_state(indoc! {"
+        /**
+         * Adds two numbers.
+         * ˇ
+         */
+    "});
+}
+
 #[gpui::test]
 fn test_insert_with_old_selections(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -12035,6 +12070,64 @@ async fn test_no_duplicated_completion_requests(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_insert_doc_comment(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(
+        Language::new(
+            LanguageConfig {
+                doc_comment: Some(DocCommentConfig {
+                    start: Some("/**".into()),
+                    end: Some(" */".into()),
+                    prefix: " * ".into(),
+                    parameters_heading: None,
+                    parameter: "@param {name}".into(),
+                    returns: Some("@returns".into()),
+                    ignored_parameters: Vec::new(),
+                    placement: DocCommentPlacement::Above,
+                }),
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        )
+        .with_signature_query(
+            r#"
+            (function_item
+                parameters: (parameters
+                    (parameter pattern: (_) @parameter)*)
+                return_type: (_)? @return_type) @item
+            "#,
+        )
+        .unwrap(),
+    );
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    cx.set_state(indoc! {"
+        mod m {
+            fn add(a: u8, b: u8) -> u8 {
+                aˇ + b
+            }
+        }
+    "});
+    cx.update_editor(|e, window, cx| e.insert_doc_comment(&InsertDocComment, window, cx));
+    cx.assert_editor_state(indoc! {"
+        mod m {
+            /**
+             * ˇ
+             *
+             * @param a
+             * @param b
+             *
+             * @returns
+             */
+            fn add(a: u8, b: u8) -> u8 {
+                a + b
+            }
+        }
+    "});
+}
+
 #[gpui::test]
 async fn test_toggle_comment(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -11,6 +11,7 @@ use instance::{ExampleInstance, JudgeOutput, RunOutput, run_git};
 pub(crate) use tool_metrics::*;
 
 use ::fs::RealFs;
+use agent::HeadlessToolPolicy;
 use clap::Parser;
 use client::{Client, ProxySettings, UserStore};
 use collections::{HashMap, HashSet};
@@ -60,6 +61,13 @@ struct Args {
     /// Maximum number of examples to run concurrently.
     #[arg(long, default_value = "4")]
     concurrency: usize,
+    /// JSON file deciding which tool calls that need confirmation are allowed. Without one, calls
+    /// of Zed's read-only tools are allowed and the others are denied.
+    #[arg(long, value_name = "PATH")]
+    tool_policy: Option<PathBuf>,
+    /// Ask on stdin about tool calls that need confirmation, instead of denying them.
+    #[arg(long)]
+    prompt_tool_confirmations: bool,
 }
 
 fn main() {
@@ -100,13 +108,20 @@ fn main() {
     let zed_branch_name = git_branch_for_path(&root_dir);
     let args = Args::parse();
     let languages: HashSet<String> = args.languages.into_iter().collect();
+    let mut tool_policy = match &args.tool_policy {
+        Some(path) => {
+            HeadlessToolPolicy::from_json(&std::fs::read_to_string(path).unwrap()).unwrap()
+        }
+        None => HeadlessToolPolicy::default(),
+    };
+    tool_policy.prompt_on_stdin = args.prompt_tool_confirmations;
 
     let http_client = Arc::new(ReqwestClient::new());
     let app = Application::headless().with_http_client(http_client.clone());
     let all_threads = examples::all(&examples_dir);
 
     app.run(move |cx| {
-        let app_state = init(cx);
+        let app_state = init(Arc::new(tool_policy), cx);
 
         let telemetry = app_state.client.telemetry();
         telemetry.start(system_id, installation_id, session_id, cx);
@@ -333,9 +348,10 @@ pub struct AgentAppState {
 
     // Additional fields not present in `workspace::AppState`.
     pub prompt_builder: Arc<PromptBuilder>,
+    pub tool_policy: Arc<HeadlessToolPolicy>,
 }
 
-pub fn init(cx: &mut App) -> Arc<AgentAppState> {
+pub fn init(tool_policy: Arc<HeadlessToolPolicy>, cx: &mut App) -> Arc<AgentAppState> {
     release_channel::init(SemanticVersion::default(), cx);
     gpui_tokio::init(cx);
 
@@ -445,6 +461,7 @@ pub fn init(cx: &mut App) -> Arc<AgentAppState> {
         fs,
         node_runtime,
         prompt_builder,
+        tool_policy,
     })
 }
 
@@ -279,11 +279,24 @@ impl ExampleContext {
                 } => {
                     println!("{log_prefix} {ui_text}");
                 }
+                ThreadEvent::ToolCallBudgetExceeded => {
+                    tx.try_send(Err(anyhow!("Exceeded the tool call budget")))
+                        .ok();
+                }
                 ThreadEvent::ToolConfirmationNeeded => {
-                    panic!(
-                        "{}Bug: Tool confirmation should not be required in eval",
-                        log_prefix
-                    );
+                    // The tool policy resolves every confirmation, except those it leaves to the
+                    // user when asked to prompt on stdin.
+                    let prompts = thread
+                        .read(cx)
+                        .headless_tool_policy()
+                        .is_some_and(|policy| policy.prompt_on_stdin);
+                    if !prompts {
+                        panic!(
+                            "{}Bug: Tool confirmation should not be required in eval",
+                            log_prefix
+                        );
+                    }
+                    println!("{log_prefix} tool confirmation needed");
                 }
                 ThreadEvent::StreamedCompletion
                 | ThreadEvent::MessageAdded(_)
@@ -294,6 +307,7 @@ impl ExampleContext {
                 | ThreadEvent::ReceivedTextChunk
                 | ThreadEvent::StreamedToolUse { .. }
                 | ThreadEvent::CheckpointChanged
+                | ThreadEvent::LongToolCallFinished { .. }
                 | ThreadEvent::CancelEditing => {
                     tx.try_send(Ok(())).ok();
                     if std::env::var("ZED_EVAL_DEBUG").is_ok() {
@@ -228,6 +228,7 @@ impl ExampleInstance {
         );
         let meta = self.thread.meta();
         let this = self.clone();
+        let tool_policy = app_state.tool_policy.clone();
 
         cx.spawn(async move |cx| {
             let worktree = worktree.await?;
@@ -321,6 +322,7 @@ impl ExampleInstance {
 
 
             thread.update(cx, |thread, _cx| {
+                thread.set_headless_tool_policy(Some(tool_policy));
                 let mut request_count = 0;
                 let previous_diff = Rc::new(RefCell::new("".to_string()));
                 let example_output_dir = this.run_directory.clone();
@@ -127,6 +127,34 @@ pub trait Extension: Send + Sync + 'static {
         project: Arc<dyn ProjectDelegate>,
     ) -> Result<Option<ContextServerConfiguration>>;
 
+    async fn context_server_tools(
+        &self,
+        context_server_id: Arc<str>,
+    ) -> Result<Vec<ContextServerTool>>;
+
+    async fn run_context_server_tool(
+        &self,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        arguments: serde_json::Value,
+    ) -> Result<ContextServerToolOutput>;
+
+    async fn process_tool_input(
+        &self,
+        middleware_id: Arc<str>,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        arguments: serde_json::Value,
+    ) -> Result<serde_json::Value>;
+
+    async fn process_tool_output(
+        &self,
+        middleware_id: Arc<str>,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        response: serde_json::Value,
+    ) -> Result<serde_json::Value>;
+
     async fn suggest_docs_packages(&self, provider: Arc<str>) -> Result<Vec<String>>;
 
     async fn index_docs(
@@ -8,7 +8,7 @@ use language::{BinaryStatus, LanguageMatcher, LanguageName, LoadedLanguage};
 use lsp::LanguageServerName;
 use parking_lot::RwLock;
 
-use crate::{Extension, SlashCommand};
+use crate::{Extension, SlashCommand, ToolMiddlewareManifestEntry};
 
 #[derive(Default)]
 struct GlobalExtensionHostProxy(Arc<ExtensionHostProxy>);
@@ -370,7 +370,26 @@ pub trait ExtensionContextServerProxy: Send + Sync + 'static {
         cx: &mut App,
     );
 
+    /// Registers a context server whose tools the extension implements itself, so that it runs
+    /// in-process instead of as a separate program.
+    fn register_in_process_context_server(
+        &self,
+        extension: Arc<dyn Extension>,
+        server_id: Arc<str>,
+        cx: &mut App,
+    );
+
     fn unregister_context_server(&self, server_id: Arc<str>, cx: &mut App);
+
+    fn register_tool_middleware(
+        &self,
+        extension: Arc<dyn Extension>,
+        middleware_id: Arc<str>,
+        middleware: ToolMiddlewareManifestEntry,
+        cx: &mut App,
+    );
+
+    fn unregister_tool_middleware(&self, middleware_id: Arc<str>, cx: &mut App);
 }
 
 impl ExtensionContextServerProxy for ExtensionHostProxy {
@@ -387,6 +406,19 @@ impl ExtensionContextServerProxy for ExtensionHostProxy {
         proxy.register_context_server(extension, server_id, cx)
     }
 
+    fn register_in_process_context_server(
+        &self,
+        extension: Arc<dyn Extension>,
+        server_id: Arc<str>,
+        cx: &mut App,
+    ) {
+        let Some(proxy) = self.context_server_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.register_in_process_context_server(extension, server_id, cx)
+    }
+
     fn unregister_context_server(&self, server_id: Arc<str>, cx: &mut App) {
         let Some(proxy) = self.context_server_proxy.read().clone() else {
             return;
@@ -394,6 +426,28 @@ impl ExtensionContextServerProxy for ExtensionHostProxy {
 
         proxy.unregister_context_server(server_id, cx)
     }
+
+    fn register_tool_middleware(
+        &self,
+        extension: Arc<dyn Extension>,
+        middleware_id: Arc<str>,
+        middleware: ToolMiddlewareManifestEntry,
+        cx: &mut App,
+    ) {
+        let Some(proxy) = self.context_server_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.register_tool_middleware(extension, middleware_id, middleware, cx)
+    }
+
+    fn unregister_tool_middleware(&self, middleware_id: Arc<str>, cx: &mut App) {
+        let Some(proxy) = self.context_server_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.unregister_tool_middleware(middleware_id, cx)
+    }
 }
 
 pub trait ExtensionIndexedDocsProviderProxy: Send + Sync + 'static {
@@ -80,6 +80,8 @@ pub struct ExtensionManifest {
     #[serde(default)]
     pub context_servers: BTreeMap<Arc<str>, ContextServerManifestEntry>,
     #[serde(default)]
+    pub tool_middleware: BTreeMap<Arc<str>, ToolMiddlewareManifestEntry>,
+    #[serde(default)]
     pub slash_commands: BTreeMap<Arc<str>, SlashCommandManifestEntry>,
     #[serde(default)]
     pub indexed_docs_providers: BTreeMap<Arc<str>, IndexedDocsProviderEntry>,
@@ -196,8 +198,46 @@ impl LanguageServerManifestEntry {
     }
 }
 
+#[derive(Clone, Default, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct ContextServerManifestEntry {
+    /// What the server offers, such as `tools`, `prompts` or `resources`, shown before it's
+    /// installed.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// The secrets the server needs, which the user is asked for when installing it.
+    #[serde(default)]
+    pub secrets: Vec<ContextServerSecretEntry>,
+    /// Whether the agent should ask before calling the server's tools, unless the user says
+    /// otherwise.
+    #[serde(default)]
+    pub confirm_tool_calls: Option<bool>,
+    /// Whether the extension implements the server itself, with its `context_server_tools` and
+    /// `run_context_server_tool` functions, rather than returning a command to run.
+    #[serde(default)]
+    pub in_process: bool,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
-pub struct ContextServerManifestEntry {}
+pub struct ContextServerSecretEntry {
+    /// The key the secret is stored under in the server's settings.
+    pub name: String,
+    /// What the secret is, and where to get it.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Middleware that runs around every context server tool call, with the extension's
+/// `process_tool_input` and `process_tool_output` functions.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+pub struct ToolMiddlewareManifestEntry {
+    /// Whether the extension implements `process_tool_input`. Calls into the extension are only
+    /// made for the functions it declares, since each one serializes the call.
+    #[serde(default)]
+    pub process_input: bool,
+    /// Whether the extension implements `process_tool_output`.
+    #[serde(default)]
+    pub process_output: bool,
+}
 
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct SlashCommandManifestEntry {
@@ -272,6 +312,7 @@ fn manifest_from_old_manifest(
             .collect(),
         language_servers: Default::default(),
         context_servers: BTreeMap::default(),
+        tool_middleware: BTreeMap::default(),
         slash_commands: BTreeMap::default(),
         indexed_docs_providers: BTreeMap::default(),
         snippets: None,
@@ -300,6 +341,7 @@ mod tests {
             grammars: BTreeMap::default(),
             language_servers: BTreeMap::default(),
             context_servers: BTreeMap::default(),
+            tool_middleware: BTreeMap::default(),
             slash_commands: BTreeMap::default(),
             indexed_docs_providers: BTreeMap::default(),
             snippets: None,
@@ -308,6 +350,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_context_server_manifest_entry() {
+        let manifest: ExtensionManifest = toml::from_str(
+            r#"
+            id = "github"
+            name = "GitHub"
+            version = "1.0.0"
+            schema_version = 1
+
+            [context_servers.github]
+
+            [context_servers.github-issues]
+            capabilities = ["tools", "resources"]
+            confirm_tool_calls = false
+            in_process = true
+            secrets = [
+                { name = "github_token", description = "A personal access token" },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.context_servers["github"],
+            ContextServerManifestEntry::default()
+        );
+        assert_eq!(
+            manifest.context_servers["github-issues"],
+            ContextServerManifestEntry {
+                capabilities: vec!["tools".into(), "resources".into()],
+                secrets: vec![ContextServerSecretEntry {
+                    name: "github_token".into(),
+                    description: Some("A personal access token".into()),
+                }],
+                confirm_tool_calls: Some(false),
+                in_process: true,
+            }
+        );
+    }
+
     #[test]
     fn test_allow_exact_match() {
         let manifest = ExtensionManifest {
@@ -8,3 +8,31 @@ pub struct ContextServerConfiguration {
     /// Default settings template.
     pub default_settings: String,
 }
+
+/// A tool of a context server that an extension implements itself.
+#[derive(Debug, Clone)]
+pub struct ContextServerTool {
+    pub name: String,
+    pub description: Option<String>,
+    /// The JSON schema of the tool's input.
+    pub input_schema: serde_json::Value,
+    pub annotations: Option<ContextServerToolAnnotations>,
+}
+
+/// Hints about how a tool of a context server that an extension implements behaves.
+#[derive(Debug, Clone, Default)]
+pub struct ContextServerToolAnnotations {
+    pub title: Option<String>,
+    pub read_only_hint: Option<bool>,
+    pub destructive_hint: Option<bool>,
+    pub idempotent_hint: Option<bool>,
+    pub open_world_hint: Option<bool>,
+}
+
+/// The result of running a tool of a context server that an extension implements itself.
+#[derive(Debug, Clone)]
+pub struct ContextServerToolOutput {
+    pub text: String,
+    /// Whether the call failed in a way the model can act on.
+    pub is_error: bool,
+}
@@ -18,7 +18,10 @@ pub use wit::{
     CodeLabel, CodeLabelSpan, CodeLabelSpanLiteral, Command, DownloadedFileType, EnvVars,
     KeyValueStore, LanguageServerInstallationStatus, Project, Range, Worktree, download_file,
     make_file_executable,
-    zed::extension::context_server::ContextServerConfiguration,
+    zed::extension::context_server::{
+        ContextServerConfiguration, ContextServerTool, ContextServerToolAnnotations,
+        ContextServerToolOutput,
+    },
     zed::extension::dap::{
         DebugAdapterBinary, DebugTaskDefinition, StartDebuggingRequestArguments,
         StartDebuggingRequestArgumentsRequest, TcpArguments, TcpArgumentsTemplate,
@@ -174,6 +177,51 @@ pub trait Extension: Send + Sync {
         Ok(None)
     }
 
+    /// Returns the tools of a context server that the extension implements itself, rather than
+    /// returning a command to run it. Such servers are declared with `in_process = true` in
+    /// `extension.toml`.
+    fn context_server_tools(
+        &mut self,
+        _context_server_id: &ContextServerId,
+    ) -> Result<Vec<ContextServerTool>> {
+        Err("`context_server_tools` not implemented".to_string())
+    }
+
+    /// Runs a tool of a context server that the extension implements itself, with the tool's
+    /// input as `arguments`.
+    fn run_context_server_tool(
+        &mut self,
+        _context_server_id: &ContextServerId,
+        _tool_name: &str,
+        _arguments: serde_json::Value,
+    ) -> Result<ContextServerToolOutput> {
+        Err("`run_context_server_tool` not implemented".to_string())
+    }
+
+    /// Rewrites the input of a context server tool call before it's sent to the server. Runs
+    /// for the tool middleware declared in `extension.toml`, around the calls to every server.
+    fn process_tool_input(
+        &mut self,
+        _middleware_id: &str,
+        _context_server_id: &ContextServerId,
+        _tool_name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        Ok(arguments)
+    }
+
+    /// Rewrites the response to a context server tool call, which is the server's `tools/call`
+    /// result, before the model sees it.
+    fn process_tool_output(
+        &mut self,
+        _middleware_id: &str,
+        _context_server_id: &ContextServerId,
+        _tool_name: &str,
+        response: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        Ok(response)
+    }
+
     /// Returns a list of package names as suggestions to be included in the
     /// search results of the `/docs` slash command.
     ///
@@ -380,6 +428,55 @@ impl wit::Guest for Component {
         extension().context_server_configuration(&context_server_id, project)
     }
 
+    fn context_server_tools(context_server_id: String) -> Result<Vec<ContextServerTool>, String> {
+        let context_server_id = ContextServerId(context_server_id);
+        extension().context_server_tools(&context_server_id)
+    }
+
+    fn run_context_server_tool(
+        context_server_id: String,
+        tool_name: String,
+        arguments: String,
+    ) -> Result<ContextServerToolOutput, String> {
+        let context_server_id = ContextServerId(context_server_id);
+        let arguments = serde_json::from_str(&arguments).map_err(|error| error.to_string())?;
+        extension().run_context_server_tool(&context_server_id, &tool_name, arguments)
+    }
+
+    fn process_tool_input(
+        middleware_id: String,
+        context_server_id: String,
+        tool_name: String,
+        arguments: String,
+    ) -> Result<String, String> {
+        let context_server_id = ContextServerId(context_server_id);
+        let arguments = serde_json::from_str(&arguments).map_err(|error| error.to_string())?;
+        let arguments = extension().process_tool_input(
+            &middleware_id,
+            &context_server_id,
+            &tool_name,
+            arguments,
+        )?;
+        Ok(arguments.to_string())
+    }
+
+    fn process_tool_output(
+        middleware_id: String,
+        context_server_id: String,
+        tool_name: String,
+        response: String,
+    ) -> Result<String, String> {
+        let context_server_id = ContextServerId(context_server_id);
+        let response = serde_json::from_str(&response).map_err(|error| error.to_string())?;
+        let response = extension().process_tool_output(
+            &middleware_id,
+            &context_server_id,
+            &tool_name,
+            response,
+        )?;
+        Ok(response.to_string())
+    }
+
     fn suggest_docs_packages(provider: String) -> Result<Vec<String>, String> {
         extension().suggest_docs_packages(provider)
     }
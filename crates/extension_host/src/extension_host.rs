@@ -1135,6 +1135,11 @@ impl ExtensionStore {
             for (server_id, _) in extension.manifest.context_servers.iter() {
                 self.proxy.unregister_context_server(server_id.clone(), cx);
             }
+
+            for (middleware_id, _) in extension.manifest.tool_middleware.iter() {
+                self.proxy
+                    .unregister_tool_middleware(middleware_id.clone(), cx);
+            }
         }
 
         self.wasm_extensions
@@ -1320,9 +1325,29 @@ impl ExtensionStore {
                         );
                     }
 
-                    for (id, _context_server_entry) in &manifest.context_servers {
-                        this.proxy
-                            .register_context_server(extension.clone(), id.clone(), cx);
+                    for (id, context_server_entry) in &manifest.context_servers {
+                        if context_server_entry.in_process {
+                            this.proxy.register_in_process_context_server(
+                                extension.clone(),
+                                id.clone(),
+                                cx,
+                            );
+                        } else {
+                            this.proxy
+                                .register_context_server(extension.clone(), id.clone(), cx);
+                        }
+                    }
+
+                    for (middleware_id, middleware) in &manifest.tool_middleware {
+                        if !middleware.process_input && !middleware.process_output {
+                            continue;
+                        }
+                        this.proxy.register_tool_middleware(
+                            extension.clone(),
+                            middleware_id.clone(),
+                            middleware.clone(),
+                            cx,
+                        );
                     }
 
                     for (provider_id, _provider) in &manifest.indexed_docs_providers {
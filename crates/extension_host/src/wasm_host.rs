@@ -4,9 +4,10 @@ use crate::ExtensionManifest;
 use anyhow::{Context as _, Result, anyhow, bail};
 use async_trait::async_trait;
 use extension::{
-    CodeLabel, Command, Completion, ContextServerConfiguration, DebugAdapterBinary,
-    DebugTaskDefinition, ExtensionHostProxy, KeyValueStoreDelegate, ProjectDelegate, SlashCommand,
-    SlashCommandArgumentCompletion, SlashCommandOutput, Symbol, WorktreeDelegate,
+    CodeLabel, Command, Completion, ContextServerConfiguration, ContextServerTool,
+    ContextServerToolOutput, DebugAdapterBinary, DebugTaskDefinition, ExtensionHostProxy,
+    KeyValueStoreDelegate, ProjectDelegate, SlashCommand, SlashCommandArgumentCompletion,
+    SlashCommandOutput, Symbol, WorktreeDelegate,
 };
 use fs::{Fs, normalize_path};
 use futures::future::LocalBoxFuture;
@@ -291,6 +292,58 @@ impl extension::Extension for WasmExtension {
         .await
     }
 
+    async fn process_tool_input(
+        &self,
+        middleware_id: Arc<str>,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        arguments: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        self.call(|extension, store| {
+            async move {
+                let arguments = extension
+                    .call_process_tool_input(
+                        store,
+                        middleware_id,
+                        context_server_id,
+                        tool_name,
+                        arguments.to_string(),
+                    )
+                    .await?
+                    .map_err(|err| anyhow!("{err}"))?;
+                serde_json::from_str(&arguments).context("parsing the rewritten tool input")
+            }
+            .boxed()
+        })
+        .await
+    }
+
+    async fn process_tool_output(
+        &self,
+        middleware_id: Arc<str>,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        response: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        self.call(|extension, store| {
+            async move {
+                let response = extension
+                    .call_process_tool_output(
+                        store,
+                        middleware_id,
+                        context_server_id,
+                        tool_name,
+                        response.to_string(),
+                    )
+                    .await?
+                    .map_err(|err| anyhow!("{err}"))?;
+                serde_json::from_str(&response).context("parsing the rewritten tool output")
+            }
+            .boxed()
+        })
+        .await
+    }
+
     async fn context_server_command(
         &self,
         context_server_id: Arc<str>,
@@ -337,6 +390,47 @@ impl extension::Extension for WasmExtension {
         .await
     }
 
+    async fn context_server_tools(
+        &self,
+        context_server_id: Arc<str>,
+    ) -> Result<Vec<ContextServerTool>> {
+        self.call(|extension, store| {
+            async move {
+                let tools = extension
+                    .call_context_server_tools(store, context_server_id)
+                    .await?
+                    .map_err(|err| anyhow!("{err}"))?;
+                tools.into_iter().map(TryInto::try_into).collect()
+            }
+            .boxed()
+        })
+        .await
+    }
+
+    async fn run_context_server_tool(
+        &self,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        arguments: serde_json::Value,
+    ) -> Result<ContextServerToolOutput> {
+        self.call(|extension, store| {
+            async move {
+                let output = extension
+                    .call_run_context_server_tool(
+                        store,
+                        context_server_id,
+                        tool_name,
+                        arguments.to_string(),
+                    )
+                    .await?
+                    .map_err(|err| anyhow!("{err}"))?;
+                Ok(output.into())
+            }
+            .boxed()
+        })
+        .await
+    }
+
     async fn suggest_docs_packages(&self, provider: Arc<str>) -> Result<Vec<String>> {
         self.call(|extension, store| {
             async move {
@@ -26,7 +26,9 @@ use wasmtime::{
 pub use latest::CodeLabelSpanLiteral;
 pub use latest::{
     CodeLabel, CodeLabelSpan, Command, DebugAdapterBinary, ExtensionProject, Range, SlashCommand,
-    zed::extension::context_server::ContextServerConfiguration,
+    zed::extension::context_server::{
+        ContextServerConfiguration, ContextServerTool, ContextServerToolOutput,
+    },
     zed::extension::lsp::{
         Completion, CompletionKind, CompletionLabelDetails, InsertTextFormat, Symbol, SymbolKind,
     },
@@ -841,6 +843,82 @@ impl Extension {
         }
     }
 
+    pub async fn call_context_server_tools(
+        &self,
+        store: &mut Store<WasmState>,
+        context_server_id: Arc<str>,
+    ) -> Result<Result<Vec<ContextServerTool>, String>> {
+        match self {
+            Extension::V0_6_0(ext) => {
+                ext.call_context_server_tools(store, &context_server_id)
+                    .await
+            }
+            _ => anyhow::bail!("`context_server_tools` not available prior to v0.6.0"),
+        }
+    }
+
+    pub async fn call_run_context_server_tool(
+        &self,
+        store: &mut Store<WasmState>,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        arguments: String,
+    ) -> Result<Result<ContextServerToolOutput, String>> {
+        match self {
+            Extension::V0_6_0(ext) => {
+                ext.call_run_context_server_tool(store, &context_server_id, &tool_name, &arguments)
+                    .await
+            }
+            _ => anyhow::bail!("`run_context_server_tool` not available prior to v0.6.0"),
+        }
+    }
+
+    pub async fn call_process_tool_input(
+        &self,
+        store: &mut Store<WasmState>,
+        middleware_id: Arc<str>,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        arguments: String,
+    ) -> Result<Result<String, String>> {
+        match self {
+            Extension::V0_6_0(ext) => {
+                ext.call_process_tool_input(
+                    store,
+                    &middleware_id,
+                    &context_server_id,
+                    &tool_name,
+                    &arguments,
+                )
+                .await
+            }
+            _ => anyhow::bail!("`process_tool_input` not available prior to v0.6.0"),
+        }
+    }
+
+    pub async fn call_process_tool_output(
+        &self,
+        store: &mut Store<WasmState>,
+        middleware_id: Arc<str>,
+        context_server_id: Arc<str>,
+        tool_name: Arc<str>,
+        response: String,
+    ) -> Result<Result<String, String>> {
+        match self {
+            Extension::V0_6_0(ext) => {
+                ext.call_process_tool_output(
+                    store,
+                    &middleware_id,
+                    &context_server_id,
+                    &tool_name,
+                    &response,
+                )
+                .await
+            }
+            _ => anyhow::bail!("`process_tool_output` not available prior to v0.6.0"),
+        }
+    }
+
     pub async fn call_suggest_docs_packages(
         &self,
         store: &mut Store<WasmState>,
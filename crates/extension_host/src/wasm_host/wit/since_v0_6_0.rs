@@ -334,6 +334,43 @@ impl TryFrom<ContextServerConfiguration> for extension::ContextServerConfigurati
     }
 }
 
+impl TryFrom<ContextServerTool> for extension::ContextServerTool {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ContextServerTool) -> Result<Self, Self::Error> {
+        let input_schema = serde_json::from_str(&value.input_schema)
+            .with_context(|| format!("Failed to parse the input schema of {}", value.name))?;
+
+        Ok(Self {
+            name: value.name,
+            description: value.description,
+            input_schema,
+            annotations: value.annotations.map(Into::into),
+        })
+    }
+}
+
+impl From<ContextServerToolAnnotations> for extension::ContextServerToolAnnotations {
+    fn from(value: ContextServerToolAnnotations) -> Self {
+        Self {
+            title: value.title,
+            read_only_hint: value.read_only_hint,
+            destructive_hint: value.destructive_hint,
+            idempotent_hint: value.idempotent_hint,
+            open_world_hint: value.open_world_hint,
+        }
+    }
+}
+
+impl From<ContextServerToolOutput> for extension::ContextServerToolOutput {
+    fn from(value: ContextServerToolOutput) -> Self {
+        Self {
+            text: value.text,
+            is_error: value.is_error,
+        }
+    }
+}
+
 impl HostKeyValueStore for WasmState {
     async fn insert(
         &mut self,
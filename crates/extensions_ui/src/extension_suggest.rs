@@ -5,6 +5,8 @@ use std::sync::{Arc, OnceLock};
 use db::kvp::KEY_VALUE_STORE;
 use editor::Editor;
 use extension_host::ExtensionStore;
+use fs::Fs;
+use futures::StreamExt as _;
 use gpui::{AppContext as _, Context, Entity, SharedString, Window};
 use language::Buffer;
 use ui::prelude::*;
@@ -75,6 +77,65 @@ const SUGGESTIONS_BY_EXTENSION_ID: &[(&str, &[&str])] = &[
     ("zig", &["zig"]),
 ];
 
+/// Zed extensions that provide equivalent functionality to popular VS Code extensions, keyed by
+/// the VS Code extension identifier (`publisher.name`, lowercased).
+const SUGGESTIONS_BY_VSCODE_EXTENSION_ID: &[(&str, &str)] = &[
+    ("astro-build.astro-vscode", "astro"),
+    ("bmewburn.vscode-intelephense-client", "php"),
+    ("dart-code.dart-code", "dart"),
+    ("elixir-lsp.elixir-ls", "elixir"),
+    ("fwcd.kotlin", "kotlin"),
+    ("gleam.gleam", "gleam"),
+    ("graphql.vscode-graphql", "graphql"),
+    ("graphql.vscode-graphql-syntax", "graphql"),
+    ("hashicorp.terraform", "terraform"),
+    ("haskell.haskell", "haskell"),
+    ("jakebecker.elixir-ls", "elixir"),
+    ("jnoortheen.nix-ide", "nix"),
+    ("ms-azuretools.vscode-docker", "dockerfile"),
+    ("ms-dotnettools.csharp", "csharp"),
+    ("ocamllabs.ocaml-platform", "ocaml"),
+    ("prisma.prisma", "prisma"),
+    ("redhat.java", "java"),
+    ("shopify.ruby-lsp", "ruby"),
+    ("sumneko.lua", "lua"),
+    ("svelte.svelte-vscode", "svelte"),
+    ("tamasfe.even-better-toml", "toml"),
+    ("vue.volar", "vue"),
+    ("ziglang.vscode-zig", "zig"),
+];
+
+/// Returns the Zed extensions equivalent to the given VS Code extension identifiers, without
+/// duplicates.
+fn suggested_extensions_for_vscode<'a>(
+    vscode_extension_ids: impl IntoIterator<Item = &'a str>,
+) -> Vec<Arc<str>> {
+    let mut suggestions = Vec::new();
+    for vscode_extension_id in vscode_extension_ids {
+        let vscode_extension_id = vscode_extension_id.to_lowercase();
+        let Some((_, extension_id)) = SUGGESTIONS_BY_VSCODE_EXTENSION_ID
+            .iter()
+            .find(|(id, _)| *id == vscode_extension_id)
+        else {
+            continue;
+        };
+        let extension_id = Arc::<str>::from(*extension_id);
+        if !suggestions.contains(&extension_id) {
+            suggestions.push(extension_id);
+        }
+    }
+    suggestions
+}
+
+/// Extracts the extension identifier from the name of a directory in the VS Code extensions
+/// directory, which has the form `publisher.name-version`.
+fn vscode_extension_id(dir_name: &str) -> Option<&str> {
+    let (id, version) = dir_name.rsplit_once('-')?;
+    version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then_some(id)
+}
+
 fn suggested_extensions() -> &'static HashMap<&'static str, Arc<str>> {
     static SUGGESTIONS_BY_PATH_SUFFIX: OnceLock<HashMap<&str, Arc<str>>> = OnceLock::new();
     SUGGESTIONS_BY_PATH_SUFFIX.get_or_init(|| {
@@ -204,6 +265,75 @@ pub(crate) fn suggest(buffer: Entity<Buffer>, window: &mut Window, cx: &mut Cont
     })
 }
 
+/// Suggests installing the Zed equivalents of the extensions installed in VS Code.
+pub(crate) fn suggest_from_vscode(window: &mut Window, cx: &mut Context<Workspace>) {
+    let fs = <dyn Fs>::global(cx);
+    cx.spawn_in(window, async move |workspace, cx| {
+        let mut entries = fs.read_dir(paths::vscode_extensions_dir()).await?;
+        let mut vscode_extension_ids = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let Some(dir_name) = entry?
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(str::to_owned)
+            else {
+                continue;
+            };
+            if let Some(id) = vscode_extension_id(&dir_name) {
+                vscode_extension_ids.push(id.to_owned());
+            }
+        }
+
+        let extension_store = cx.update(|_, cx| ExtensionStore::global(cx))?;
+        let suggestions = extension_store.read_with(cx, |store, _| {
+            suggested_extensions_for_vscode(vscode_extension_ids.iter().map(String::as_str))
+                .into_iter()
+                .filter(|extension_id| !store.installed_extensions().contains_key(extension_id))
+                .collect::<Vec<_>>()
+        })?;
+        if suggestions.is_empty() {
+            return anyhow::Ok(());
+        }
+
+        workspace.update(cx, |workspace, cx| {
+            struct VsCodeExtensionSuggestionNotification;
+
+            workspace.show_notification(
+                NotificationId::unique::<VsCodeExtensionSuggestionNotification>(),
+                cx,
+                |cx| {
+                    cx.new(move |cx| {
+                        MessageNotification::new(
+                            format!(
+                                "Zed has equivalents for some of your VS Code extensions: {}. \
+                                Do you want to install them?",
+                                suggestions.join(", ")
+                            ),
+                            cx,
+                        )
+                        .primary_message("Yes, install extensions")
+                        .primary_icon(IconName::Check)
+                        .primary_icon_color(Color::Success)
+                        .primary_on_click(move |_window, cx| {
+                            let suggestions = suggestions.clone();
+                            ExtensionStore::global(cx).update(cx, move |store, cx| {
+                                for extension_id in suggestions {
+                                    store.install_latest_extension(extension_id, cx);
+                                }
+                            });
+                        })
+                        .secondary_message("No, don't install them")
+                        .secondary_icon(IconName::Close)
+                        .secondary_icon_color(Color::Error)
+                        .secondary_on_click(|_window, _cx| {})
+                    })
+                },
+            );
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,4 +376,23 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    pub fn test_suggested_extensions_for_vscode() {
+        assert_eq!(
+            vscode_extension_id("svelte.svelte-vscode-109.5.2"),
+            Some("svelte.svelte-vscode")
+        );
+        assert_eq!(vscode_extension_id("extensions.json"), None);
+
+        assert_eq!(
+            suggested_extensions_for_vscode([
+                "Svelte.svelte-vscode",
+                "rust-lang.rust-analyzer",
+                "graphql.vscode-graphql",
+                "graphql.vscode-graphql-syntax",
+            ]),
+            vec![Arc::<str>::from("svelte"), Arc::<str>::from("graphql")]
+        );
+    }
 }
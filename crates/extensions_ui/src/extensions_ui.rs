@@ -93,6 +93,9 @@ pub fn init(cx: &mut App) {
                     }
                 },
             )
+            .register_action(|_, _: &zed_actions::SuggestVsCodeExtensions, window, cx| {
+                extension_suggest::suggest_from_vscode(window, cx);
+            })
             .register_action(move |workspace, _: &InstallDevExtension, window, cx| {
                 let store = ExtensionStore::global(cx);
                 let prompt = workspace.prompt_for_open_path(
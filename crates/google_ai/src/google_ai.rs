@@ -1,4 +1,5 @@
 use std::mem;
+use std::sync::Arc;
 
 use anyhow::{Result, anyhow, bail};
 use futures::{AsyncBufReadExt, AsyncReadExt, StreamExt, io::BufReader, stream::BoxStream};
@@ -416,7 +417,7 @@ pub enum FunctionCallingMode {
 pub struct FunctionDeclaration {
     pub name: String,
     pub description: String,
-    pub parameters: serde_json::Value,
+    pub parameters: Arc<serde_json::Value>,
 }
 
 #[derive(Debug, Default)]
@@ -1,12 +1,6 @@
-pub use crate::{
-    Grammar, Language, LanguageRegistry,
-    diagnostic_set::DiagnosticSet,
-    highlight_map::{HighlightId, HighlightMap},
-    proto,
-};
 use crate::{
-    LanguageScope, Outline, OutlineConfig, RunnableCapture, RunnableTag, TextObject,
-    TreeSitterOptions,
+    DocCommentPlacement, LanguageScope, Outline, OutlineConfig, RunnableCapture, RunnableTag,
+    TextObject, TreeSitterOptions,
     diagnostic_set::{DiagnosticEntry, DiagnosticGroup},
     language_settings::{LanguageSettings, language_settings},
     outline::OutlineItem,
@@ -17,6 +11,12 @@ use crate::{
     task_context::RunnableRange,
     text_diff::text_diff,
 };
+pub use crate::{
+    Grammar, Language, LanguageRegistry,
+    diagnostic_set::DiagnosticSet,
+    highlight_map::{HighlightId, HighlightMap},
+    proto,
+};
 use anyhow::{Context as _, Result};
 use async_watch as watch;
 pub use clock::ReplicaId;
@@ -766,6 +766,16 @@ pub struct BracketMatch {
     pub newline_only: bool,
 }
 
+/// A documentation comment skeleton for a function, ready to be inserted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocCommentSkeleton {
+    /// The start of the line where the comment should be inserted.
+    pub position: Point,
+    pub text: String,
+    /// Where the summary should be written, relative to the start of `text`.
+    pub cursor_offset: usize,
+}
+
 impl Buffer {
     /// Create a new buffer with the given base text.
     pub fn local<T: Into<String>>(base_text: T, cx: &Context<Self>) -> Self {
@@ -2639,6 +2649,22 @@ impl Buffer {
         self.did_edit(&old_version, was_dirty, cx);
     }
 
+    /// Undoes the edits collaborators made in the given ranges, as a transaction in this
+    /// replica's undo history.
+    pub fn revert_collaborator_edits<T: ToOffset>(
+        &mut self,
+        ranges: impl IntoIterator<Item = Range<T>>,
+        cx: &mut Context<Self>,
+    ) -> Option<TransactionId> {
+        let was_dirty = self.is_dirty();
+        let old_version = self.version.clone();
+
+        let (transaction_id, operation) = self.text.revert_collaborator_edits_in_ranges(ranges)?;
+        self.send_operation(Operation::Buffer(operation), true, cx);
+        self.did_edit(&old_version, was_dirty, cx);
+        Some(transaction_id)
+    }
+
     /// Manually redoes a specific transaction in the buffer's redo history.
     pub fn redo(&mut self, cx: &mut Context<Self>) -> Option<TransactionId> {
         let was_dirty = self.is_dirty();
@@ -3790,6 +3816,75 @@ impl BufferSnapshot {
             .filter(|pair| !pair.newline_only)
     }
 
+    /// Builds a documentation comment skeleton for the innermost function containing `position`,
+    /// using the language's `signatures.scm` query and `doc_comment` configuration.
+    pub fn doc_comment_skeleton_at<T: ToOffset>(&self, position: T) -> Option<DocCommentSkeleton> {
+        let offset = position.to_offset(self);
+        let mut matches = self.syntax.matches(offset..offset, &self.text, |grammar| {
+            grammar.signature_config.as_ref().map(|c| &c.query)
+        });
+        let configs = matches
+            .grammars()
+            .iter()
+            .map(|grammar| grammar.signature_config.as_ref().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut innermost: Option<(Range<usize>, Vec<String>, bool, Option<Range<usize>>)> = None;
+        while let Some(mat) = matches.peek() {
+            let config = configs[mat.grammar_index];
+            let mut item_range = None;
+            let mut parameters = Vec::new();
+            let mut has_return_type = false;
+            let mut body_range = None;
+            for capture in mat.captures {
+                let range = capture.node.byte_range();
+                if capture.index == config.item_capture_ix {
+                    item_range = Some(range);
+                } else if Some(capture.index) == config.parameter_capture_ix {
+                    parameters.push(self.text_for_range(range).collect::<String>());
+                } else if Some(capture.index) == config.return_type_capture_ix {
+                    has_return_type = true;
+                } else if Some(capture.index) == config.body_capture_ix {
+                    body_range = Some(range);
+                }
+            }
+            matches.advance();
+
+            let Some(item_range) = item_range else {
+                continue;
+            };
+            let is_innermost = innermost.as_ref().map_or(true, |(innermost_range, ..)| {
+                item_range.len() < innermost_range.len()
+            });
+            if item_range.start <= offset && offset <= item_range.end && is_innermost {
+                innermost = Some((item_range, parameters, has_return_type, body_range));
+            }
+        }
+
+        let (item_range, parameters, has_return_type, body_range) = innermost?;
+        let language = self.language_at(item_range.start)?;
+        let config = language.config.doc_comment.as_ref()?;
+        let item_row = self.offset_to_point(item_range.start).row;
+        let row = match config.placement {
+            DocCommentPlacement::Above => item_row,
+            DocCommentPlacement::Body => {
+                let body_row = self.offset_to_point(body_range?.start).row;
+                // A body on the same line as the signature has nowhere to put the comment.
+                if body_row == item_row {
+                    return None;
+                }
+                body_row
+            }
+        };
+        let indent = self.indent_size_for_line(row).chars().collect::<String>();
+        let (text, cursor_offset) = config.skeleton(&indent, &parameters, has_return_type);
+        Some(DocCommentSkeleton {
+            position: Point::new(row, 0),
+            text,
+            cursor_offset,
+        })
+    }
+
     pub fn text_object_ranges<T: ToOffset>(
         &self,
         range: Range<T>,
@@ -969,6 +969,65 @@ fn test_text_objects(cx: &mut App) {
     )
 }
 
+#[gpui::test]
+fn test_doc_comment_skeleton(cx: &mut App) {
+    let language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            doc_comment: Some(DocCommentConfig {
+                start: None,
+                end: None,
+                prefix: "/// ".into(),
+                parameters_heading: Some("# Arguments".into()),
+                parameter: "* `{name}` - ".into(),
+                returns: Some("# Returns".into()),
+                ignored_parameters: Vec::new(),
+                placement: DocCommentPlacement::Above,
+            }),
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::LANGUAGE.into()),
+    )
+    .with_signature_query(
+        r#"
+        (function_item
+            parameters: (parameters
+                (parameter pattern: (_) @parameter)*)
+            return_type: (_)? @return_type) @item
+        "#,
+    )
+    .unwrap();
+
+    let text = indoc! {"
+        impl Hello {
+            fn say(&self, name: &str, times: u8) -> String {
+                let f = |x: u8| x;
+            }
+        }
+    "};
+    let buffer = cx.new(|cx| Buffer::local(text, cx).with_language(Arc::new(language), cx));
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+
+    let position = text.find("let f").unwrap();
+    let skeleton = snapshot.doc_comment_skeleton_at(position).unwrap();
+    assert_eq!(skeleton.position, Point::new(1, 0));
+    assert_eq!(
+        skeleton.text,
+        concat!(
+            "    /// \n",
+            "    ///\n",
+            "    /// # Arguments\n",
+            "    /// * `name` -\n",
+            "    /// * `times` -\n",
+            "    ///\n",
+            "    /// # Returns\n",
+        )
+    );
+    assert_eq!(&skeleton.text[..skeleton.cursor_offset], "    /// ");
+
+    assert_eq!(snapshot.doc_comment_skeleton_at(0), None);
+}
+
 #[gpui::test]
 fn test_enclosing_bracket_ranges(cx: &mut App) {
     let mut assert = |selection_text, range_markers| {
@@ -44,7 +44,7 @@ use schemars::{
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 use serde_json::Value;
-use settings::WorktreeId;
+use settings::{LanguageServerSettingsSchema, WorktreeId};
 use smol::future::FutureExt as _;
 use std::{
     any::Any,
@@ -545,6 +545,16 @@ pub trait LspAdapter: 'static + Send + Sync {
         Ok(serde_json::json!({}))
     }
 
+    /// Returns JSON schemas for the `initialization_options` and `settings` this server accepts,
+    /// which are used to assist editing them in settings files.
+    async fn settings_schema(
+        self: Arc<Self>,
+        _: &dyn Fs,
+        _: &Arc<dyn LspAdapterDelegate>,
+    ) -> Result<Option<LanguageServerSettingsSchema>> {
+        Ok(None)
+    }
+
     async fn additional_initialization_options(
         self: Arc<Self>,
         _target_language_server_id: LanguageServerName,
@@ -759,6 +769,10 @@ pub struct LanguageConfig {
     /// auto adding prefix on new line, adjusting the indenting , etc.
     #[serde(default)]
     pub documentation: Option<DocumentationConfig>,
+    /// How to write a documentation comment skeleton for a function, based on the parameters and
+    /// return type captured by the language's `signatures.scm` query.
+    #[serde(default)]
+    pub doc_comment: Option<DocCommentConfig>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default, JsonSchema)]
@@ -822,6 +836,112 @@ pub struct DocumentationConfig {
     pub tab_size: NonZeroU32,
 }
 
+/// The template for documentation comment skeletons in a language.
+///
+/// Each template may span several lines, all of which are prefixed with `prefix`.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct DocCommentConfig {
+    /// A line inserted before the comment, e.g. `/**`.
+    #[serde(default)]
+    pub start: Option<Arc<str>>,
+    /// A line inserted after the comment, e.g. ` */`.
+    #[serde(default)]
+    pub end: Option<Arc<str>>,
+    /// The prefix of every line within the comment, e.g. `/// ` or ` * `.
+    #[serde(default)]
+    pub prefix: Arc<str>,
+    /// A line introducing the parameters, e.g. `# Arguments`.
+    #[serde(default)]
+    pub parameters_heading: Option<Arc<str>>,
+    /// The line describing each parameter, where `{name}` is replaced with the parameter's name.
+    pub parameter: Arc<str>,
+    /// The line describing the return value, which is only added when the function declares a
+    /// return type.
+    #[serde(default)]
+    pub returns: Option<Arc<str>>,
+    /// Parameter names to leave out of the comment, e.g. `self`.
+    #[serde(default)]
+    pub ignored_parameters: Vec<String>,
+    /// Where the comment is inserted relative to the function.
+    #[serde(default)]
+    pub placement: DocCommentPlacement,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DocCommentPlacement {
+    /// On the lines before the function.
+    #[default]
+    Above,
+    /// At the start of the function's body, like Python docstrings.
+    Body,
+}
+
+impl DocCommentConfig {
+    /// Builds the text of a comment skeleton indented by `indent`, returning the text and the
+    /// offset within it where the summary should be written.
+    pub fn skeleton(
+        &self,
+        indent: &str,
+        parameters: &[String],
+        has_return_type: bool,
+    ) -> (String, usize) {
+        let mut sections = Vec::new();
+        let parameters = parameters
+            .iter()
+            .filter(|name| !self.ignored_parameters.contains(name))
+            .collect::<Vec<_>>();
+        if !parameters.is_empty() {
+            sections.push(
+                self.parameters_heading
+                    .iter()
+                    .map(|heading| heading.to_string())
+                    .chain(
+                        parameters
+                            .iter()
+                            .map(|name| self.parameter.replace("{name}", name)),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+        if has_return_type {
+            if let Some(returns) = &self.returns {
+                sections.push(returns.to_string());
+            }
+        }
+
+        let mut text = String::new();
+        if let Some(start) = &self.start {
+            text.push_str(indent);
+            text.push_str(start);
+            text.push('\n');
+        }
+        text.push_str(indent);
+        text.push_str(&self.prefix);
+        let cursor_offset = text.len();
+        text.push('\n');
+        for section in sections {
+            self.push_line(&mut text, indent, "");
+            for line in section.lines() {
+                self.push_line(&mut text, indent, line);
+            }
+        }
+        if let Some(end) = &self.end {
+            text.push_str(indent);
+            text.push_str(end);
+            text.push('\n');
+        }
+        (text, cursor_offset)
+    }
+
+    fn push_line(&self, text: &mut String, indent: &str, line: &str) {
+        let line = format!("{indent}{}{line}", self.prefix);
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+}
+
 /// Represents a language for the given range. Some languages (e.g. HTML)
 /// interleave several languages together, thus a single buffer might actually contain
 /// several nested scopes.
@@ -901,6 +1021,7 @@ impl Default for LanguageConfig {
             debuggers: Default::default(),
             significant_indentation: Default::default(),
             documentation: None,
+            doc_comment: None,
         }
     }
 }
@@ -1064,6 +1185,7 @@ pub struct Grammar {
     pub(crate) indents_config: Option<IndentConfig>,
     pub outline_config: Option<OutlineConfig>,
     pub text_object_config: Option<TextObjectConfig>,
+    pub(crate) signature_config: Option<SignatureConfig>,
     pub embedding_config: Option<EmbeddingConfig>,
     pub(crate) injection_config: Option<InjectionConfig>,
     pub(crate) override_config: Option<OverrideConfig>,
@@ -1127,6 +1249,14 @@ pub struct TextObjectConfig {
     pub text_objects_by_capture_ix: Vec<(u32, TextObject)>,
 }
 
+pub(crate) struct SignatureConfig {
+    pub query: Query,
+    pub item_capture_ix: u32,
+    pub parameter_capture_ix: Option<u32>,
+    pub return_type_capture_ix: Option<u32>,
+    pub body_capture_ix: Option<u32>,
+}
+
 #[derive(Debug)]
 pub struct EmbeddingConfig {
     pub query: Query,
@@ -1215,6 +1345,7 @@ impl Language {
                     brackets_config: None,
                     outline_config: None,
                     text_object_config: None,
+                    signature_config: None,
                     embedding_config: None,
                     indents_config: None,
                     injection_config: None,
@@ -1292,6 +1423,11 @@ impl Language {
                 .with_text_object_query(query.as_ref())
                 .context("Error loading textobject query")?;
         }
+        if let Some(query) = queries.signatures {
+            self = self
+                .with_signature_query(query.as_ref())
+                .context("Error loading signatures query")?;
+        }
         Ok(self)
     }
 
@@ -1379,6 +1515,34 @@ impl Language {
         Ok(self)
     }
 
+    pub fn with_signature_query(mut self, source: &str) -> Result<Self> {
+        let grammar = self.grammar_mut().context("cannot mutate grammar")?;
+        let query = Query::new(&grammar.ts_language, source)?;
+        let mut item_capture_ix = None;
+        let mut parameter_capture_ix = None;
+        let mut return_type_capture_ix = None;
+        let mut body_capture_ix = None;
+        get_capture_indices(
+            &query,
+            &mut [
+                ("item", &mut item_capture_ix),
+                ("parameter", &mut parameter_capture_ix),
+                ("return_type", &mut return_type_capture_ix),
+                ("body", &mut body_capture_ix),
+            ],
+        );
+        if let Some(item_capture_ix) = item_capture_ix {
+            grammar.signature_config = Some(SignatureConfig {
+                query,
+                item_capture_ix,
+                parameter_capture_ix,
+                return_type_capture_ix,
+                body_capture_ix,
+            });
+        }
+        Ok(self)
+    }
+
     pub fn with_embedding_query(mut self, source: &str) -> Result<Self> {
         let grammar = self.grammar_mut().context("cannot mutate grammar")?;
         let query = Query::new(&grammar.ts_language, source)?;
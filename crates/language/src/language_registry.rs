@@ -8,7 +8,7 @@ use crate::{
     with_parser,
 };
 use anyhow::{Context as _, Result, anyhow};
-use collections::{FxHashMap, HashMap, HashSet, hash_map};
+use collections::{BTreeMap, FxHashMap, HashMap, HashSet, hash_map};
 
 use futures::{
     Future,
@@ -23,6 +23,7 @@ use parking_lot::{Mutex, RwLock};
 use postage::watch;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use settings::LanguageServerSettingsSchema;
 use smallvec::SmallVec;
 use std::{
     borrow::{Borrow, Cow},
@@ -127,6 +128,7 @@ struct LanguageRegistryState {
     theme: Option<Arc<Theme>>,
     version: usize,
     reload_count: usize,
+    language_server_settings_schemas: BTreeMap<String, LanguageServerSettingsSchema>,
 
     #[cfg(any(test, feature = "test-support"))]
     fake_server_entries: HashMap<LanguageServerName, FakeLanguageServerEntry>,
@@ -222,6 +224,7 @@ pub const QUERY_FILENAME_PREFIXES: &[(
     ("runnables", |q| &mut q.runnables),
     ("debug_variables", |q| &mut q.debug_variables),
     ("textobjects", |q| &mut q.text_objects),
+    ("signatures", |q| &mut q.signatures),
 ];
 
 /// Tree-sitter language queries for a given language.
@@ -238,6 +241,7 @@ pub struct LanguageQueries {
     pub runnables: Option<Cow<'static, str>>,
     pub text_objects: Option<Cow<'static, str>>,
     pub debug_variables: Option<Cow<'static, str>>,
+    pub signatures: Option<Cow<'static, str>>,
 }
 
 #[derive(Clone, Default)]
@@ -269,6 +273,7 @@ impl LanguageRegistry {
                 theme: Default::default(),
                 version: 0,
                 reload_count: 0,
+                language_server_settings_schemas: BTreeMap::default(),
 
                 #[cfg(any(test, feature = "test-support"))]
                 fake_server_entries: Default::default(),
@@ -571,6 +576,26 @@ impl LanguageRegistry {
         result
     }
 
+    /// Records the configuration schema that a language server publishes, returning whether it
+    /// differs from the one recorded before.
+    pub fn set_language_server_settings_schema(
+        &self,
+        name: &LanguageServerName,
+        schema: LanguageServerSettingsSchema,
+    ) -> bool {
+        let mut state = self.state.write();
+        let previous = state
+            .language_server_settings_schemas
+            .insert(name.0.to_string(), schema.clone());
+        previous.as_ref() != Some(&schema)
+    }
+
+    pub fn language_server_settings_schemas(
+        &self,
+    ) -> BTreeMap<String, LanguageServerSettingsSchema> {
+        self.state.read().language_server_settings_schemas.clone()
+    }
+
     pub fn grammar_names(&self) -> Vec<Arc<str>> {
         let state = self.state.read();
         let mut result = state.grammars.keys().cloned().collect::<Vec<_>>();
@@ -90,6 +90,8 @@ pub struct LanguageSettings {
     pub indent_guides: IndentGuideSettings,
     /// Whether or not to perform a buffer format before saving.
     pub format_on_save: FormatOnSave,
+    /// Which parts of a buffer to format when saving it.
+    pub format_on_save_scope: FormatOnSaveScope,
     /// Whether or not to remove any trailing whitespace from lines of a buffer
     /// before saving it.
     pub remove_trailing_whitespace_on_save: bool,
@@ -426,6 +428,11 @@ pub struct LanguageSettingsContent {
     /// Default: on
     #[serde(default)]
     pub format_on_save: Option<FormatOnSave>,
+    /// Which parts of a buffer to format when saving it.
+    ///
+    /// Default: file
+    #[serde(default)]
+    pub format_on_save_scope: Option<FormatOnSaveScope>,
     /// Whether or not to remove any trailing whitespace from lines of a buffer
     /// before saving it.
     ///
@@ -633,6 +640,22 @@ pub enum SoftWrap {
     Bounded,
 }
 
+/// Controls which parts of a file are formatted when it is saved.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatOnSaveScope {
+    /// Format the whole file.
+    #[default]
+    File,
+    /// Only format the lines that differ from the file's contents in the last
+    /// git commit, so that saving doesn't reformat code you haven't touched.
+    ///
+    /// Language servers are asked to format each modified range, and the changes
+    /// made by other formatters are discarded outside of the modified lines.
+    /// Files that aren't tracked by git are formatted in full.
+    ModifiedLines,
+}
+
 /// Controls the behavior of formatting files when they are saved.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FormatOnSave {
@@ -1384,103 +1407,17 @@ impl settings::Settings for AllLanguageSettings {
     }
 
     fn import_from_vscode(vscode: &settings::VsCodeSettings, current: &mut Self::FileContent) {
-        let d = &mut current.defaults;
-        if let Some(size) = vscode
-            .read_value("editor.tabSize")
-            .and_then(|v| v.as_u64())
-            .and_then(|n| NonZeroU32::new(n as u32))
-        {
-            d.tab_size = Some(size);
-        }
-        if let Some(v) = vscode.read_bool("editor.insertSpaces") {
-            d.hard_tabs = Some(!v);
-        }
-
-        vscode.enum_setting("editor.wordWrap", &mut d.soft_wrap, |s| match s {
-            "on" => Some(SoftWrap::EditorWidth),
-            "wordWrapColumn" => Some(SoftWrap::PreferLine),
-            "bounded" => Some(SoftWrap::Bounded),
-            "off" => Some(SoftWrap::None),
-            _ => None,
-        });
-        vscode.u32_setting("editor.wordWrapColumn", &mut d.preferred_line_length);
-
-        if let Some(arr) = vscode
-            .read_value("editor.rulers")
-            .and_then(|v| v.as_array())
-            .map(|v| v.iter().map(|n| n.as_u64().map(|n| n as usize)).collect())
-        {
-            d.wrap_guides = arr;
-        }
-        if let Some(b) = vscode.read_bool("editor.guides.indentation") {
-            if let Some(guide_settings) = d.indent_guides.as_mut() {
-                guide_settings.enabled = b;
-            } else {
-                d.indent_guides = Some(IndentGuideSettings {
-                    enabled: b,
-                    ..Default::default()
-                });
-            }
-        }
-
-        if let Some(b) = vscode.read_bool("editor.guides.formatOnSave") {
-            d.format_on_save = Some(if b {
-                FormatOnSave::On
-            } else {
-                FormatOnSave::Off
-            });
-        }
-        vscode.bool_setting(
-            "editor.trimAutoWhitespace",
-            &mut d.remove_trailing_whitespace_on_save,
-        );
-        vscode.bool_setting(
-            "files.insertFinalNewline",
-            &mut d.ensure_final_newline_on_save,
-        );
-        vscode.bool_setting("editor.inlineSuggest.enabled", &mut d.show_edit_predictions);
-        vscode.enum_setting("editor.renderWhitespace", &mut d.show_whitespaces, |s| {
-            Some(match s {
-                "boundary" | "trailing" => ShowWhitespaceSetting::Boundary,
-                "selection" => ShowWhitespaceSetting::Selection,
-                "all" => ShowWhitespaceSetting::All,
-                _ => ShowWhitespaceSetting::None,
-            })
-        });
-        vscode.enum_setting(
-            "editor.autoSurround",
-            &mut d.use_auto_surround,
-            |s| match s {
-                "languageDefined" | "quotes" | "brackets" => Some(true),
-                "never" => Some(false),
-                _ => None,
-            },
-        );
-        vscode.bool_setting("editor.formatOnType", &mut d.use_on_type_format);
-        vscode.bool_setting("editor.linkedEditing", &mut d.linked_edits);
-        vscode.bool_setting("editor.formatOnPaste", &mut d.auto_indent_on_paste);
-        vscode.bool_setting(
-            "editor.suggestOnTriggerCharacters",
-            &mut d.show_completions_on_input,
-        );
-        if let Some(b) = vscode.read_bool("editor.suggest.showWords") {
-            let mode = if b {
-                WordsCompletionMode::Enabled
-            } else {
-                WordsCompletionMode::Disabled
+        import_language_settings_from_vscode(vscode, &mut current.defaults);
+        for (language_id, overrides) in vscode.language_overrides() {
+            let Some(language_name) = vscode_language_name(language_id) else {
+                continue;
             };
-            if let Some(completion_settings) = d.completions.as_mut() {
-                completion_settings.words = mode;
-            } else {
-                d.completions = Some(CompletionSettings {
-                    words: mode,
-                    lsp: true,
-                    lsp_fetch_timeout_ms: 0,
-                    lsp_insert_mode: LspInsertMode::ReplaceSuffix,
-                });
-            }
+            let language = current
+                .languages
+                .entry(LanguageName::new(language_name))
+                .or_default();
+            import_language_settings_from_vscode(&overrides, language);
         }
-        // TODO: pull ^ out into helper and reuse for per-language settings
 
         // vscodes file association map is inverted from ours, so we flip the mapping before merging
         let mut associations: HashMap<Arc<str>, Vec<String>> = HashMap::default();
@@ -1498,6 +1435,147 @@ impl settings::Settings for AllLanguageSettings {
     }
 }
 
+fn import_language_settings_from_vscode(
+    vscode: &settings::VsCodeSettings,
+    d: &mut LanguageSettingsContent,
+) {
+    if let Some(size) = vscode
+        .read_value("editor.tabSize")
+        .and_then(|v| v.as_u64())
+        .and_then(|n| NonZeroU32::new(n as u32))
+    {
+        d.tab_size = Some(size);
+    }
+    if let Some(v) = vscode.read_bool("editor.insertSpaces") {
+        d.hard_tabs = Some(!v);
+    }
+
+    vscode.enum_setting("editor.wordWrap", &mut d.soft_wrap, |s| match s {
+        "on" => Some(SoftWrap::EditorWidth),
+        "wordWrapColumn" => Some(SoftWrap::PreferLine),
+        "bounded" => Some(SoftWrap::Bounded),
+        "off" => Some(SoftWrap::None),
+        _ => None,
+    });
+    vscode.u32_setting("editor.wordWrapColumn", &mut d.preferred_line_length);
+
+    if let Some(arr) = vscode
+        .read_value("editor.rulers")
+        .and_then(|v| v.as_array())
+        .map(|v| v.iter().map(|n| n.as_u64().map(|n| n as usize)).collect())
+    {
+        d.wrap_guides = arr;
+    }
+    if let Some(b) = vscode.read_bool("editor.guides.indentation") {
+        if let Some(guide_settings) = d.indent_guides.as_mut() {
+            guide_settings.enabled = b;
+        } else {
+            d.indent_guides = Some(IndentGuideSettings {
+                enabled: b,
+                ..Default::default()
+            });
+        }
+    }
+
+    if let Some(b) = vscode.read_bool("editor.formatOnSave") {
+        d.format_on_save = Some(if b {
+            FormatOnSave::On
+        } else {
+            FormatOnSave::Off
+        });
+    }
+    vscode.enum_setting(
+        "editor.formatOnSaveMode",
+        &mut d.format_on_save_scope,
+        |s| match s {
+            "file" => Some(FormatOnSaveScope::File),
+            "modifications" | "modificationsIfAvailable" => Some(FormatOnSaveScope::ModifiedLines),
+            _ => None,
+        },
+    );
+    vscode.bool_setting(
+        "editor.trimAutoWhitespace",
+        &mut d.remove_trailing_whitespace_on_save,
+    );
+    vscode.bool_setting(
+        "files.insertFinalNewline",
+        &mut d.ensure_final_newline_on_save,
+    );
+    vscode.bool_setting("editor.inlineSuggest.enabled", &mut d.show_edit_predictions);
+    vscode.enum_setting("editor.renderWhitespace", &mut d.show_whitespaces, |s| {
+        Some(match s {
+            "boundary" | "trailing" => ShowWhitespaceSetting::Boundary,
+            "selection" => ShowWhitespaceSetting::Selection,
+            "all" => ShowWhitespaceSetting::All,
+            _ => ShowWhitespaceSetting::None,
+        })
+    });
+    vscode.enum_setting(
+        "editor.autoSurround",
+        &mut d.use_auto_surround,
+        |s| match s {
+            "languageDefined" | "quotes" | "brackets" => Some(true),
+            "never" => Some(false),
+            _ => None,
+        },
+    );
+    vscode.bool_setting("editor.formatOnType", &mut d.use_on_type_format);
+    vscode.bool_setting("editor.linkedEditing", &mut d.linked_edits);
+    vscode.bool_setting("editor.formatOnPaste", &mut d.auto_indent_on_paste);
+    vscode.bool_setting(
+        "editor.suggestOnTriggerCharacters",
+        &mut d.show_completions_on_input,
+    );
+    if let Some(b) = vscode.read_bool("editor.suggest.showWords") {
+        let mode = if b {
+            WordsCompletionMode::Enabled
+        } else {
+            WordsCompletionMode::Disabled
+        };
+        if let Some(completion_settings) = d.completions.as_mut() {
+            completion_settings.words = mode;
+        } else {
+            d.completions = Some(CompletionSettings {
+                words: mode,
+                lsp: true,
+                lsp_fetch_timeout_ms: 0,
+                lsp_insert_mode: LspInsertMode::ReplaceSuffix,
+            });
+        }
+    }
+}
+
+/// Maps a VS Code language identifier to the name of the corresponding Zed language.
+fn vscode_language_name(language_id: &str) -> Option<&'static str> {
+    Some(match language_id {
+        "c" => "C",
+        "cpp" => "C++",
+        "css" => "CSS",
+        "diff" => "Diff",
+        "dockerfile" => "Dockerfile",
+        "elixir" => "Elixir",
+        "go" => "Go",
+        "gomod" => "Go Mod",
+        "html" => "HTML",
+        "javascript" | "javascriptreact" => "JavaScript",
+        "json" => "JSON",
+        "jsonc" => "JSONC",
+        "lua" => "Lua",
+        "markdown" => "Markdown",
+        "php" => "PHP",
+        "python" => "Python",
+        "ruby" => "Ruby",
+        "rust" => "Rust",
+        "shellscript" => "Shell Script",
+        "toml" => "TOML",
+        "typescript" => "TypeScript",
+        "typescriptreact" => "TSX",
+        "yaml" => "YAML",
+        "zig" => "Zig",
+        _ => return None,
+    })
+}
+
 fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent) {
     fn merge<T>(target: &mut T, value: Option<T>) {
         if let Some(value) = value {
@@ -1541,6 +1619,7 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
         src.jsx_tag_auto_close.clone(),
     );
     merge(&mut settings.format_on_save, src.format_on_save.clone());
+    merge(&mut settings.format_on_save_scope, src.format_on_save_scope);
     merge(
         &mut settings.remove_trailing_whitespace_on_save,
         src.remove_trailing_whitespace_on_save,
@@ -1864,4 +1943,40 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_import_from_vscode() {
+        let vscode = settings::VsCodeSettings::from_str(
+            r#"{
+                "editor.tabSize": 2,
+                "editor.formatOnSave": true,
+                "[rust]": {
+                    "editor.tabSize": 4
+                },
+                "[typescript][typescriptreact]": {
+                    "editor.formatOnSave": false
+                },
+                "[unknown-language]": {
+                    "editor.tabSize": 8
+                }
+            }"#,
+        )
+        .unwrap();
+        let mut content = AllLanguageSettingsContent::default();
+        AllLanguageSettings::import_from_vscode(&vscode, &mut content);
+
+        assert_eq!(content.defaults.tab_size, NonZeroU32::new(2));
+        assert_eq!(content.defaults.format_on_save, Some(FormatOnSave::On));
+        assert_eq!(
+            content.languages[&LanguageName::new("Rust")].tab_size,
+            NonZeroU32::new(4)
+        );
+        for language in ["TypeScript", "TSX"] {
+            assert_eq!(
+                content.languages[&LanguageName::new(language)].format_on_save,
+                Some(FormatOnSave::Off)
+            );
+        }
+        assert_eq!(content.languages.len(), 3);
+    }
 }
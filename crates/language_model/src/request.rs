@@ -4,7 +4,7 @@ use std::sync::Arc;
 use crate::role::Role;
 use crate::{LanguageModelToolUse, LanguageModelToolUseId};
 use anyhow::Result;
-use base64::write::EncoderWriter;
+use base64::{Engine as _, write::EncoderWriter};
 use gpui::{
     App, AppContext as _, DevicePixels, Image, ImageFormat, ObjectFit, SharedString, Size, Task,
     point, px, size,
@@ -52,59 +52,126 @@ impl LanguageModelImage {
     }
 
     pub fn from_image(data: Arc<Image>, cx: &mut App) -> Task<Option<Self>> {
+        Self::from_image_with_max_dimension(data, ANTHROPIC_SIZE_LIMT as u32, cx)
+    }
+
+    /// Like [`Self::from_image`], but also downscales the image so that neither of its
+    /// dimensions exceeds `max_dimension` pixels.
+    pub fn from_image_with_max_dimension(
+        data: Arc<Image>,
+        max_dimension: u32,
+        cx: &mut App,
+    ) -> Task<Option<Self>> {
+        let max_dimension = (max_dimension as f32).min(ANTHROPIC_SIZE_LIMT).max(1.);
+        cx.background_spawn(async move { Self::encode(data, max_dimension) })
+    }
+
+    /// Like [`Self::from_image_with_max_dimension`], but for an image that's already
+    /// base64-encoded. PNGs that are small enough are sent as they are, sharing `data` rather
+    /// than decoding and re-encoding it.
+    pub fn from_base64_with_max_dimension(
+        data: SharedString,
+        format: ImageFormat,
+        max_dimension: u32,
+        cx: &mut App,
+    ) -> Task<Option<Self>> {
+        let max_dimension = (max_dimension as f32).min(ANTHROPIC_SIZE_LIMT).max(1.);
         cx.background_spawn(async move {
-            let image_bytes = Cursor::new(data.bytes());
-            let dynamic_image = match data.format() {
-                ImageFormat::Png => image::codecs::png::PngDecoder::new(image_bytes)
-                    .and_then(image::DynamicImage::from_decoder),
-                ImageFormat::Jpeg => image::codecs::jpeg::JpegDecoder::new(image_bytes)
-                    .and_then(image::DynamicImage::from_decoder),
-                ImageFormat::Webp => image::codecs::webp::WebPDecoder::new(image_bytes)
-                    .and_then(image::DynamicImage::from_decoder),
-                ImageFormat::Gif => image::codecs::gif::GifDecoder::new(image_bytes)
-                    .and_then(image::DynamicImage::from_decoder),
-                _ => return None,
-            }
-            .log_err()?;
-
-            let width = dynamic_image.width();
-            let height = dynamic_image.height();
-            let image_size = size(DevicePixels(width as i32), DevicePixels(height as i32));
-
-            let base64_image = {
-                if image_size.width.0 > ANTHROPIC_SIZE_LIMT as i32
-                    || image_size.height.0 > ANTHROPIC_SIZE_LIMT as i32
-                {
-                    let new_bounds = ObjectFit::ScaleDown.get_bounds(
-                        gpui::Bounds {
-                            origin: point(px(0.0), px(0.0)),
-                            size: size(px(ANTHROPIC_SIZE_LIMT), px(ANTHROPIC_SIZE_LIMT)),
-                        },
-                        image_size,
-                    );
-                    let resized_image = dynamic_image.resize(
-                        new_bounds.size.width.0 as u32,
-                        new_bounds.size.height.0 as u32,
-                        image::imageops::FilterType::Triangle,
-                    );
-
-                    encode_as_base64(data, resized_image)
-                } else {
-                    encode_as_base64(data, dynamic_image)
+            if format == ImageFormat::Png {
+                if let Some((width, height)) = base64_png_dimensions(&data) {
+                    if width.max(height) as f32 <= max_dimension {
+                        return Some(LanguageModelImage {
+                            size: size(DevicePixels(width as i32), DevicePixels(height as i32)),
+                            source: data,
+                        });
+                    }
                 }
             }
-            .log_err()?;
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(data.as_bytes())
+                .log_err()?;
+            drop(data);
+            Self::encode(Arc::new(Image::from_bytes(format, bytes)), max_dimension)
+        })
+    }
+
+    /// Encodes the image as a base64 PNG no larger than `max_dimension` in either dimension.
+    fn encode(data: Arc<Image>, max_dimension: f32) -> Option<Self> {
+        // PNGs that are small enough are sent as they are, so only their header is read
+        // rather than decoding every pixel.
+        if data.format() == ImageFormat::Png {
+            let (width, height) =
+                image::ImageReader::with_format(Cursor::new(data.bytes()), image::ImageFormat::Png)
+                    .into_dimensions()
+                    .log_err()?;
+            if width.max(height) as f32 <= max_dimension {
+                let source = base64::engine::general_purpose::STANDARD.encode(data.bytes());
+                return Some(LanguageModelImage {
+                    size: size(DevicePixels(width as i32), DevicePixels(height as i32)),
+                    source: source.into(),
+                });
+            }
+        }
+
+        let image_bytes = Cursor::new(data.bytes());
+        let dynamic_image = match data.format() {
+            ImageFormat::Png => image::codecs::png::PngDecoder::new(image_bytes)
+                .and_then(image::DynamicImage::from_decoder),
+            ImageFormat::Jpeg => image::codecs::jpeg::JpegDecoder::new(image_bytes)
+                .and_then(image::DynamicImage::from_decoder),
+            ImageFormat::Webp => image::codecs::webp::WebPDecoder::new(image_bytes)
+                .and_then(image::DynamicImage::from_decoder),
+            ImageFormat::Gif => image::codecs::gif::GifDecoder::new(image_bytes)
+                .and_then(image::DynamicImage::from_decoder),
+            _ => return None,
+        }
+        .log_err()?;
+
+        let width = dynamic_image.width();
+        let height = dynamic_image.height();
+        let image_size = size(DevicePixels(width as i32), DevicePixels(height as i32));
+
+        let (image_size, base64_image) = {
+            if image_size.width.0 > max_dimension as i32
+                || image_size.height.0 > max_dimension as i32
+            {
+                let new_bounds = ObjectFit::ScaleDown.get_bounds(
+                    gpui::Bounds {
+                        origin: point(px(0.0), px(0.0)),
+                        size: size(px(max_dimension), px(max_dimension)),
+                    },
+                    image_size,
+                );
+                let resized_image = dynamic_image.resize(
+                    new_bounds.size.width.0 as u32,
+                    new_bounds.size.height.0 as u32,
+                    image::imageops::FilterType::Triangle,
+                );
+                let resized_size = size(
+                    DevicePixels(resized_image.width() as i32),
+                    DevicePixels(resized_image.height() as i32),
+                );
+
+                (resized_size, encode_as_base64(resized_image))
+            } else {
+                (image_size, encode_as_base64(dynamic_image))
+            }
+        };
+        let base64_image = base64_image.log_err()?;
 
-            // SAFETY: The base64 encoder should not produce non-UTF8.
-            let source = unsafe { String::from_utf8_unchecked(base64_image) };
+        // SAFETY: The base64 encoder should not produce non-UTF8.
+        let source = unsafe { String::from_utf8_unchecked(base64_image) };
 
-            Some(LanguageModelImage {
-                size: image_size,
-                source: source.into(),
-            })
+        Some(LanguageModelImage {
+            size: image_size,
+            source: source.into(),
         })
     }
 
+    pub fn size(&self) -> Size<DevicePixels> {
+        self.size
+    }
+
     pub fn estimate_tokens(&self) -> usize {
         let width = self.size.width.0.unsigned_abs() as usize;
         let height = self.size.height.0.unsigned_abs() as usize;
@@ -120,21 +187,34 @@ impl LanguageModelImage {
     }
 }
 
-fn encode_as_base64(data: Arc<Image>, image: image::DynamicImage) -> Result<Vec<u8>> {
+/// Reads the dimensions of a base64-encoded PNG from its header, without decoding the rest.
+fn base64_png_dimensions(data: &str) -> Option<(u32, u32)> {
+    // The signature and the header chunk, up to the height, take up 24 bytes, or 32 characters.
+    let header = base64::engine::general_purpose::STANDARD
+        .decode(data.get(..32)?)
+        .ok()?;
+    if header[..8] != *b"\x89PNG\r\n\x1a\n" || header[12..16] != *b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(header[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(header[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// Encodes the image as a base64 PNG.
+fn encode_as_base64(image: image::DynamicImage) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    image.write_with_encoder(PngEncoder::new(&mut png))?;
+    // The decoded pixels are usually much larger than the PNG, so free them before encoding.
+    drop(image);
+
     let mut base64_image = Vec::new();
     {
         let mut base64_encoder = EncoderWriter::new(
             Cursor::new(&mut base64_image),
             &base64::engine::general_purpose::STANDARD,
         );
-        if data.format() == ImageFormat::Png {
-            base64_encoder.write_all(data.bytes())?;
-        } else {
-            let mut png = Vec::new();
-            image.write_with_encoder(PngEncoder::new(&mut png))?;
-
-            base64_encoder.write_all(png.as_slice())?;
-        }
+        base64_encoder.write_all(png.as_slice())?;
     }
     Ok(base64_image)
 }
@@ -179,6 +259,16 @@ impl LanguageModelToolResultContent {
             Self::Image(_) => false,
         }
     }
+
+    /// A rough estimate of the tokens this content takes up in a request.
+    pub fn estimate_tokens(&self) -> usize {
+        match self {
+            Self::Text(text) | Self::WrappedText(WrappedTextContent { text, .. }) => {
+                estimate_text_tokens(text)
+            }
+            Self::Image(image) => image.estimate_tokens(),
+        }
+    }
 }
 
 impl From<&str> for LanguageModelToolResultContent {
@@ -267,7 +357,24 @@ impl LanguageModelRequestMessage {
 pub struct LanguageModelRequestTool {
     pub name: String,
     pub description: String,
-    pub input_schema: serde_json::Value,
+    /// Shared, because tools keep their schemas around and can be offered in every request.
+    pub input_schema: Arc<serde_json::Value>,
+}
+
+impl LanguageModelRequestTool {
+    /// A rough estimate of the tokens this tool's definition takes up in a request.
+    pub fn estimate_tokens(&self) -> usize {
+        estimate_text_tokens(&self.name)
+            + estimate_text_tokens(&self.description)
+            + estimate_text_tokens(&self.input_schema.to_string())
+    }
+}
+
+/// Estimates the tokens in `text` without a tokenizer, which is only good enough to compare the
+/// parts of a request with each other. Tokenizers average about four bytes of English text or
+/// code per token.
+pub fn estimate_text_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
 }
 
 #[derive(Debug, PartialEq, Hash, Clone, Serialize, Deserialize)]
@@ -275,6 +382,8 @@ pub enum LanguageModelToolChoice {
     Auto,
     Any,
     None,
+    /// The model must call the tool with this name.
+    Tool(String),
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
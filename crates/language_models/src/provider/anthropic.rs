@@ -441,7 +441,8 @@ impl LanguageModel for AnthropicModel {
         match choice {
             LanguageModelToolChoice::Auto
             | LanguageModelToolChoice::Any
-            | LanguageModelToolChoice::None => true,
+            | LanguageModelToolChoice::None
+            | LanguageModelToolChoice::Tool(_) => true,
         }
     }
 
@@ -663,6 +664,7 @@ pub fn into_anthropic(
             LanguageModelToolChoice::Auto => anthropic::ToolChoice::Auto,
             LanguageModelToolChoice::Any => anthropic::ToolChoice::Any,
             LanguageModelToolChoice::None => anthropic::ToolChoice::None,
+            LanguageModelToolChoice::Tool(name) => anthropic::ToolChoice::Tool { name },
         }),
         metadata: None,
         stop_sequences: Vec::new(),
@@ -16,10 +16,11 @@ use bedrock::bedrock_client::types::{
 };
 use bedrock::{
     BedrockAnyToolChoice, BedrockAutoToolChoice, BedrockBlob, BedrockError, BedrockInnerContent,
-    BedrockMessage, BedrockModelMode, BedrockStreamingResponse, BedrockThinkingBlock,
-    BedrockThinkingTextBlock, BedrockTool, BedrockToolChoice, BedrockToolConfig,
-    BedrockToolInputSchema, BedrockToolResultBlock, BedrockToolResultContentBlock,
-    BedrockToolResultStatus, BedrockToolSpec, BedrockToolUseBlock, Model, value_to_aws_document,
+    BedrockMessage, BedrockModelMode, BedrockSpecificToolChoice, BedrockStreamingResponse,
+    BedrockThinkingBlock, BedrockThinkingTextBlock, BedrockTool, BedrockToolChoice,
+    BedrockToolConfig, BedrockToolInputSchema, BedrockToolResultBlock,
+    BedrockToolResultContentBlock, BedrockToolResultStatus, BedrockToolSpec, BedrockToolUseBlock,
+    Model, value_to_aws_document,
 };
 use collections::{BTreeMap, HashMap};
 use credentials_provider::CredentialsProvider;
@@ -495,6 +496,7 @@ impl LanguageModel for BedrockModel {
             LanguageModelToolChoice::Auto | LanguageModelToolChoice::Any => {
                 self.model.supports_tool_use()
             }
+            LanguageModelToolChoice::Tool(_) => self.model.supports_tool_use(),
             LanguageModelToolChoice::None => false,
         }
     }
@@ -717,6 +719,9 @@ pub fn into_bedrock(
         Some(LanguageModelToolChoice::Any) => {
             BedrockToolChoice::Any(BedrockAnyToolChoice::builder().build())
         }
+        Some(LanguageModelToolChoice::Tool(name)) => {
+            BedrockToolChoice::Tool(BedrockSpecificToolChoice::builder().name(name).build()?)
+        }
         Some(LanguageModelToolChoice::None) => {
             anyhow::bail!("LanguageModelToolChoice::None is not supported");
         }
@@ -690,7 +690,8 @@ impl LanguageModel for CloudLanguageModel {
         match choice {
             LanguageModelToolChoice::Auto
             | LanguageModelToolChoice::Any
-            | LanguageModelToolChoice::None => true,
+            | LanguageModelToolChoice::None
+            | LanguageModelToolChoice::Tool(_) => true,
         }
     }
 
@@ -219,6 +219,7 @@ impl LanguageModel for CopilotChatLanguageModel {
             LanguageModelToolChoice::Auto
             | LanguageModelToolChoice::Any
             | LanguageModelToolChoice::None => self.supports_tools(),
+            LanguageModelToolChoice::Tool(_) => false,
         }
     }
 
@@ -587,9 +588,9 @@ fn into_copilot_chat(
             function: copilot::copilot_chat::Function {
                 name: "noop".to_string(),
                 description: "No operation".to_string(),
-                parameters: serde_json::json!({
+                parameters: Arc::new(serde_json::json!({
                     "type": "object"
-                }),
+                })),
             },
         });
     }
@@ -606,6 +607,8 @@ fn into_copilot_chat(
             LanguageModelToolChoice::Auto => copilot::copilot_chat::ToolChoice::Auto,
             LanguageModelToolChoice::Any => copilot::copilot_chat::ToolChoice::Any,
             LanguageModelToolChoice::None => copilot::copilot_chat::ToolChoice::None,
+            // Copilot can't be made to call a specific tool, so it has to call one of them.
+            LanguageModelToolChoice::Tool(_) => copilot::copilot_chat::ToolChoice::Any,
         }),
     })
 }
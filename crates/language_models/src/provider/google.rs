@@ -321,7 +321,8 @@ impl LanguageModel for GoogleLanguageModel {
         match choice {
             LanguageModelToolChoice::Auto
             | LanguageModelToolChoice::Any
-            | LanguageModelToolChoice::None => true,
+            | LanguageModelToolChoice::None
+            | LanguageModelToolChoice::Tool(_) => true,
         }
     }
 
@@ -525,13 +526,23 @@ pub fn into_google(
             }]
         }),
         tool_config: request.tool_choice.map(|choice| google_ai::ToolConfig {
-            function_calling_config: google_ai::FunctionCallingConfig {
-                mode: match choice {
-                    LanguageModelToolChoice::Auto => google_ai::FunctionCallingMode::Auto,
-                    LanguageModelToolChoice::Any => google_ai::FunctionCallingMode::Any,
-                    LanguageModelToolChoice::None => google_ai::FunctionCallingMode::None,
+            function_calling_config: match choice {
+                LanguageModelToolChoice::Auto => google_ai::FunctionCallingConfig {
+                    mode: google_ai::FunctionCallingMode::Auto,
+                    allowed_function_names: None,
+                },
+                LanguageModelToolChoice::Any => google_ai::FunctionCallingConfig {
+                    mode: google_ai::FunctionCallingMode::Any,
+                    allowed_function_names: None,
+                },
+                LanguageModelToolChoice::None => google_ai::FunctionCallingConfig {
+                    mode: google_ai::FunctionCallingMode::None,
+                    allowed_function_names: None,
+                },
+                LanguageModelToolChoice::Tool(name) => google_ai::FunctionCallingConfig {
+                    mode: google_ai::FunctionCallingMode::Any,
+                    allowed_function_names: Some(vec![name]),
                 },
-                allowed_function_names: None,
             },
         }),
     }
@@ -463,6 +463,15 @@ pub fn into_mistral(
                 Some(mistral::ToolChoice::Any)
             }
             Some(LanguageModelToolChoice::None) => Some(mistral::ToolChoice::None),
+            Some(LanguageModelToolChoice::Tool(name)) if !request.tools.is_empty() => Some(
+                mistral::ToolChoice::Function(mistral::ToolDefinition::Function {
+                    function: mistral::FunctionDefinition {
+                        name,
+                        description: None,
+                        parameters: None,
+                    },
+                }),
+            ),
             _ if !request.tools.is_empty() => Some(mistral::ToolChoice::Auto),
             _ => None,
         },
@@ -851,4 +860,31 @@ mod tests {
             _ => panic!("Expected User message"),
         }
     }
+
+    #[test]
+    fn test_into_mistral_forced_tool() {
+        let request = language_model::LanguageModelRequest {
+            messages: Vec::new(),
+            temperature: None,
+            tools: vec![language_model::LanguageModelRequestTool {
+                name: "search".to_string(),
+                description: "Searches the docs".to_string(),
+                input_schema: Arc::new(serde_json::json!({ "type": "object" })),
+            }],
+            tool_choice: Some(LanguageModelToolChoice::Tool("search".to_string())),
+            thread_id: None,
+            prompt_id: None,
+            mode: None,
+            stop: Vec::new(),
+        };
+
+        let mistral_request = into_mistral(request, "mistral-medium-latest".to_string(), None);
+
+        assert!(matches!(
+            mistral_request.tool_choice,
+            Some(mistral::ToolChoice::Function(mistral::ToolDefinition::Function {
+                function: mistral::FunctionDefinition { name, .. }
+            })) if name == "search"
+        ));
+    }
 }
@@ -334,6 +334,7 @@ impl LanguageModel for OllamaLanguageModel {
             LanguageModelToolChoice::Auto => false,
             LanguageModelToolChoice::Any => false,
             LanguageModelToolChoice::None => false,
+            LanguageModelToolChoice::Tool(_) => false,
         }
     }
 
@@ -305,6 +305,7 @@ impl LanguageModel for OpenAiLanguageModel {
             LanguageModelToolChoice::Auto => true,
             LanguageModelToolChoice::Any => true,
             LanguageModelToolChoice::None => true,
+            LanguageModelToolChoice::Tool(_) => true,
         }
     }
 
@@ -463,6 +464,15 @@ pub fn into_open_ai(
             LanguageModelToolChoice::Auto => open_ai::ToolChoice::Auto,
             LanguageModelToolChoice::Any => open_ai::ToolChoice::Required,
             LanguageModelToolChoice::None => open_ai::ToolChoice::None,
+            LanguageModelToolChoice::Tool(name) => {
+                open_ai::ToolChoice::Other(open_ai::ToolDefinition::Function {
+                    function: open_ai::FunctionDefinition {
+                        name,
+                        description: None,
+                        parameters: None,
+                    },
+                })
+            }
         }),
     }
 }
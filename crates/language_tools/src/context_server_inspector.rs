@@ -0,0 +1,542 @@
+use collections::HashMap;
+use context_server::{
+    ContextServerId,
+    client::IoKind,
+    protocol::{InitializedContextServerProtocol, ServerCapability},
+    types,
+};
+use editor::Editor;
+use gpui::{
+    App, AppContext as _, Context, Entity, EventEmitter, FocusHandle, Focusable, IntoElement,
+    ParentElement, Render, SharedString, Styled, Subscription, Task, Window, actions, div,
+    prelude::FluentBuilder as _,
+};
+use project::context_server_store::{self, ContextServerStatus, ContextServerStore};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use theme::ActiveTheme;
+use ui::{
+    Button, ButtonCommon, ButtonStyle, Clickable, Color, InteractiveElement, Label, LabelCommon,
+    LabelSize, StatefulInteractiveElement, Toggleable, h_flex, v_flex,
+};
+use util::ResultExt as _;
+use workspace::{Item, SplitDirection, Workspace};
+use zed_actions::dev::OpenContextServerInspector;
+
+actions!(dev, [ToggleContextServerTrace]);
+
+/// Only the most recent messages are rendered, and each is truncated, to keep the view responsive.
+const MAX_RENDERED_MESSAGES: usize = 200;
+const MAX_RENDERED_MESSAGE_LEN: usize = 2000;
+
+pub fn init(cx: &mut App) {
+    cx.observe_new(|workspace: &mut Workspace, _, _| {
+        workspace.register_action(|workspace, _: &OpenContextServerInspector, window, cx| {
+            let context_server_store = workspace.project().read(cx).context_server_store();
+            let inspector =
+                cx.new(|cx| ContextServerInspector::new(context_server_store, window, cx));
+            workspace.split_item(SplitDirection::Right, Box::new(inspector), window, cx)
+        });
+    })
+    .detach();
+}
+
+pub struct ContextServerInspector {
+    context_server_store: Entity<ContextServerStore>,
+    selected_server: Option<ContextServerId>,
+    tools: Option<Result<Vec<types::Tool>, SharedString>>,
+    selected_tool: Option<String>,
+    arguments_editor: Entity<Editor>,
+    tool_result: Option<Result<String, SharedString>>,
+    focus_handle: FocusHandle,
+    _load_tools: Task<()>,
+    _run_tool: Task<()>,
+    _watch_messages: Task<()>,
+    _subscription: Subscription,
+}
+
+impl ContextServerInspector {
+    pub fn new(
+        context_server_store: Entity<ContextServerStore>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let subscription = cx.subscribe(&context_server_store, |this, _, event, cx| {
+            let context_server_store::Event::ServerStatusChanged { server_id, status } = event
+            else {
+                return;
+            };
+            if this.selected_server.as_ref() == Some(server_id) {
+                if *status == ContextServerStatus::Running {
+                    this.load_server(cx);
+                }
+            } else if this.selected_server.is_none() {
+                this.select_server(server_id.clone(), cx);
+            }
+            cx.notify();
+        });
+        let arguments_editor = cx.new(|cx| {
+            let mut editor = Editor::auto_height(12, window, cx);
+            editor.set_placeholder_text("Tool arguments as a JSON object", cx);
+            editor
+        });
+
+        let mut this = Self {
+            context_server_store: context_server_store.clone(),
+            selected_server: None,
+            tools: None,
+            selected_tool: None,
+            arguments_editor,
+            tool_result: None,
+            focus_handle: cx.focus_handle(),
+            _load_tools: Task::ready(()),
+            _run_tool: Task::ready(()),
+            _watch_messages: Task::ready(()),
+            _subscription: subscription,
+        };
+        if let Some(server_id) = context_server_store
+            .read(cx)
+            .all_server_ids()
+            .into_iter()
+            .next()
+        {
+            this.select_server(server_id, cx);
+        }
+        this
+    }
+
+    fn select_server(&mut self, server_id: ContextServerId, cx: &mut Context<Self>) {
+        self.selected_server = Some(server_id);
+        self.selected_tool = None;
+        self.tool_result = None;
+        self.load_server(cx);
+        cx.notify();
+    }
+
+    fn protocol(&self, cx: &App) -> Option<Arc<InitializedContextServerProtocol>> {
+        self.context_server_store
+            .read(cx)
+            .get_running_server(self.selected_server.as_ref()?)?
+            .client()
+    }
+
+    /// Fetches the selected server's tools, and starts re-rendering as its traffic is logged.
+    fn load_server(&mut self, cx: &mut Context<Self>) {
+        self.tools = None;
+        let Some(server) = self
+            .selected_server
+            .as_ref()
+            .and_then(|id| self.context_server_store.read(cx).get_server(id))
+        else {
+            return;
+        };
+
+        let mut messages_changed = server.message_log().lock().subscribe();
+        self._watch_messages = cx.spawn(async move |this, cx| {
+            while messages_changed.recv().await.is_some() {
+                if this.update(cx, |_, cx| cx.notify()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let Some(protocol) = server.client() else {
+            return;
+        };
+        if !protocol.capable(ServerCapability::Tools) {
+            self.tools = Some(Ok(Vec::new()));
+            return;
+        }
+        self._load_tools = cx.spawn(async move |this, cx| {
+            let response = protocol.list_tools().await;
+            this.update(cx, |this, cx| {
+                this.tools = Some(
+                    response
+                        .map(|response| response.tools)
+                        .map_err(|error| error.to_string().into()),
+                );
+                cx.notify();
+            })
+            .ok();
+        });
+    }
+
+    fn select_tool(&mut self, tool_name: String, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(Ok(tools)) = &self.tools else {
+            return;
+        };
+        let Some(tool) = tools.iter().find(|tool| tool.name == tool_name) else {
+            return;
+        };
+        let arguments = arguments_skeleton(&tool.input_schema);
+        self.selected_tool = Some(tool_name);
+        self.tool_result = None;
+        self.arguments_editor.update(cx, |editor, cx| {
+            editor.set_text(arguments, window, cx);
+        });
+        cx.notify();
+    }
+
+    fn run_tool(&mut self, cx: &mut Context<Self>) {
+        let Some(tool_name) = self.selected_tool.clone() else {
+            return;
+        };
+        let Some(protocol) = self.protocol(cx) else {
+            self.tool_result = Some(Err("The server is not running".into()));
+            cx.notify();
+            return;
+        };
+        let arguments = self.arguments_editor.read(cx).text(cx);
+        let arguments = if arguments.trim().is_empty() {
+            None
+        } else {
+            match serde_json::from_str::<HashMap<String, Value>>(&arguments) {
+                Ok(arguments) => Some(arguments),
+                Err(error) => {
+                    self.tool_result = Some(Err(format!("Invalid arguments: {error}").into()));
+                    cx.notify();
+                    return;
+                }
+            }
+        };
+
+        self.tool_result = Some(Ok("Running…".to_string()));
+        self._run_tool = cx.spawn(async move |this, cx| {
+            let response = protocol.run_tool(&tool_name, arguments).await;
+            this.update(cx, |this, cx| {
+                this.tool_result = Some(
+                    response
+                        .and_then(|response| Ok(serde_json::to_string_pretty(&response)?))
+                        .map_err(|error| error.to_string().into()),
+                );
+                cx.notify();
+            })
+            .ok();
+        });
+        cx.notify();
+    }
+
+    fn clear_messages(&mut self, cx: &mut Context<Self>) {
+        if let Some(server) = self
+            .selected_server
+            .as_ref()
+            .and_then(|id| self.context_server_store.read(cx).get_server(id))
+        {
+            server.message_log().lock().clear();
+        }
+    }
+
+    /// Starts or stops appending the selected server's traffic to a file.
+    fn toggle_trace(
+        &mut self,
+        _: &ToggleContextServerTrace,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(server_id) = self.selected_server.clone() else {
+            return;
+        };
+        self.context_server_store
+            .update(cx, |store, cx| store.toggle_trace_file(&server_id, cx))
+            .log_err();
+        cx.notify();
+    }
+
+    fn render_servers(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let store = self.context_server_store.read(cx);
+        let mut server_ids = store.all_server_ids();
+        server_ids.sort_by(|a, b| a.0.cmp(&b.0));
+        h_flex()
+            .flex_wrap()
+            .gap_1()
+            .when(server_ids.is_empty(), |this| {
+                this.child(Label::new("No context servers are configured").color(Color::Muted))
+            })
+            .children(server_ids.into_iter().map(|server_id| {
+                let status = match store.status_for_server(&server_id) {
+                    Some(ContextServerStatus::Installing) => "installing",
+                    Some(ContextServerStatus::Starting) => "starting",
+                    Some(ContextServerStatus::Running) => "running",
+                    Some(ContextServerStatus::Stopped) | None => "stopped",
+                    Some(ContextServerStatus::Error(_)) => "error",
+                };
+                Button::new(
+                    SharedString::from(format!("server-{server_id}")),
+                    format!("{server_id} ({status})"),
+                )
+                .style(ButtonStyle::Subtle)
+                .toggle_state(self.selected_server.as_ref() == Some(&server_id))
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    this.select_server(server_id.clone(), cx);
+                }))
+            }))
+    }
+
+    fn render_capabilities(&self, cx: &App) -> Option<impl IntoElement> {
+        let protocol = self.protocol(cx)?;
+        let initialize = &protocol.initialize;
+        let capabilities = serde_json::to_string_pretty(&initialize.capabilities).ok()?;
+        Some(
+            v_flex()
+                .gap_1()
+                .child(section_header("Capabilities"))
+                .child(Label::new(format!(
+                    "{} {} (protocol {})",
+                    initialize.server_info.name,
+                    initialize.server_info.version,
+                    initialize.protocol_version.0
+                )))
+                .child(code_block(capabilities, cx)),
+        )
+    }
+
+    fn render_tools(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let content = match &self.tools {
+            None => Label::new("Loading tools…")
+                .color(Color::Muted)
+                .into_any_element(),
+            Some(Err(error)) => Label::new(error.clone())
+                .color(Color::Error)
+                .into_any_element(),
+            Some(Ok(tools)) if tools.is_empty() => Label::new("This server has no tools")
+                .color(Color::Muted)
+                .into_any_element(),
+            Some(Ok(tools)) => v_flex()
+                .gap_2()
+                .children(tools.iter().enumerate().map(|(ix, tool)| {
+                    let is_selected = self.selected_tool.as_ref() == Some(&tool.name);
+                    let schema =
+                        serde_json::to_string_pretty(&tool.input_schema).unwrap_or_default();
+                    let tool_name = tool.name.clone();
+                    v_flex()
+                        .gap_1()
+                        .child(
+                            h_flex()
+                                .gap_2()
+                                .child(Label::new(tool.name.clone()).buffer_font(cx))
+                                .child(
+                                    Button::new(("invoke-tool", ix), "Invoke")
+                                        .style(ButtonStyle::Filled)
+                                        .label_size(LabelSize::Small)
+                                        .toggle_state(is_selected)
+                                        .on_click(cx.listener(move |this, _, window, cx| {
+                                            this.select_tool(tool_name.clone(), window, cx);
+                                        })),
+                                ),
+                        )
+                        .when_some(tool.description.clone(), |this, description| {
+                            this.child(
+                                Label::new(description)
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted),
+                            )
+                        })
+                        .when(is_selected, |this| this.child(code_block(schema, cx)))
+                }))
+                .into_any_element(),
+        };
+        v_flex()
+            .gap_1()
+            .child(section_header("Tools"))
+            .child(content)
+    }
+
+    fn render_invocation(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let tool_name = self.selected_tool.as_ref()?;
+        let colors = cx.theme().colors();
+        Some(
+            v_flex()
+                .gap_1()
+                .child(section_header(format!("Invoke {tool_name}")))
+                .child(
+                    div()
+                        .p_1()
+                        .border_1()
+                        .border_color(colors.border_variant)
+                        .rounded_sm()
+                        .child(self.arguments_editor.clone()),
+                )
+                .child(
+                    h_flex().child(
+                        Button::new("run-tool", "Run")
+                            .style(ButtonStyle::Filled)
+                            .on_click(cx.listener(|this, _, _, cx| this.run_tool(cx))),
+                    ),
+                )
+                .when_some(self.tool_result.as_ref(), |this, result| match result {
+                    Ok(output) => this.child(code_block(output.clone(), cx)),
+                    Err(error) => this.child(Label::new(error.clone()).color(Color::Error)),
+                }),
+        )
+    }
+
+    fn render_traffic(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let server = self
+            .selected_server
+            .as_ref()
+            .and_then(|id| self.context_server_store.read(cx).get_server(id));
+        let trace_file = server.as_ref().and_then(|server| server.trace_file_path());
+        let messages = server
+            .map(|server| {
+                let message_log = server.message_log();
+                let message_log = message_log.lock();
+                let mut messages = message_log
+                    .messages()
+                    .rev()
+                    .take(MAX_RENDERED_MESSAGES)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                messages.reverse();
+                messages
+            })
+            .unwrap_or_default();
+
+        v_flex()
+            .gap_1()
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(section_header("Traffic"))
+                    .child(
+                        Button::new("clear-traffic", "Clear")
+                            .label_size(LabelSize::Small)
+                            .on_click(cx.listener(|this, _, _, cx| this.clear_messages(cx))),
+                    )
+                    .child(
+                        Button::new("toggle-trace", "Trace to File")
+                            .label_size(LabelSize::Small)
+                            .toggle_state(trace_file.is_some())
+                            .on_click(cx.listener(|this, _, window, cx| {
+                                this.toggle_trace(&ToggleContextServerTrace, window, cx)
+                            })),
+                    ),
+            )
+            .when_some(trace_file, |this, trace_file| {
+                this.child(
+                    Label::new(format!("Tracing to {}", trace_file.display()))
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+            })
+            .when(messages.is_empty(), |this| {
+                this.child(Label::new("No messages yet").color(Color::Muted))
+            })
+            .children(messages.into_iter().map(|message| {
+                let (direction, color) = match message.kind {
+                    IoKind::Send => ("→", Color::Accent),
+                    IoKind::Receive => ("←", Color::Success),
+                    IoKind::StdErr => ("stderr", Color::Warning),
+                };
+                let text = util::truncate_and_trailoff(&message.message, MAX_RENDERED_MESSAGE_LEN);
+                h_flex()
+                    .gap_2()
+                    .items_start()
+                    .child(Label::new(direction).size(LabelSize::Small).color(color))
+                    .child(Label::new(text).size(LabelSize::Small).buffer_font(cx))
+            }))
+    }
+}
+
+/// Builds a JSON object with a `null` placeholder for every property in a tool's input schema.
+fn arguments_skeleton(input_schema: &Value) -> String {
+    let arguments = input_schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .keys()
+                .map(|name| (name.clone(), Value::Null))
+                .collect::<Map<_, _>>()
+        })
+        .unwrap_or_default();
+    serde_json::to_string_pretty(&Value::Object(arguments)).unwrap_or_else(|_| "{}".into())
+}
+
+fn section_header(title: impl Into<SharedString>) -> Label {
+    Label::new(title).size(LabelSize::Large)
+}
+
+fn code_block(text: String, cx: &App) -> impl IntoElement {
+    div()
+        .p_1()
+        .rounded_sm()
+        .bg(cx.theme().colors().editor_background)
+        .child(Label::new(text).size(LabelSize::Small).buffer_font(cx))
+}
+
+impl Render for ContextServerInspector {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .id("context-server-inspector")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::toggle_trace))
+            .size_full()
+            .overflow_y_scroll()
+            .p_4()
+            .gap_4()
+            .bg(cx.theme().colors().panel_background)
+            .child(Label::new("Context Servers").size(LabelSize::Large))
+            .child(self.render_servers(cx))
+            .when(self.selected_server.is_some(), |this| {
+                this.children(self.render_capabilities(cx))
+                    .child(self.render_tools(cx))
+                    .children(self.render_invocation(cx))
+                    .child(self.render_traffic(cx))
+            })
+    }
+}
+
+impl EventEmitter<()> for ContextServerInspector {}
+
+impl Focusable for ContextServerInspector {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Item for ContextServerInspector {
+    type Event = ();
+
+    fn to_item_events(_: &Self::Event, _: impl FnMut(workspace::item::ItemEvent)) {}
+
+    fn tab_content_text(&self, _detail: usize, _cx: &App) -> SharedString {
+        "Context Server Inspector".into()
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn clone_on_split(
+        &self,
+        _workspace_id: Option<workspace::WorkspaceId>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<Entity<Self>>
+    where
+        Self: Sized,
+    {
+        Some(cx.new(|cx| Self::new(self.context_server_store.clone(), window, cx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_arguments_skeleton() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "recursive": { "type": "boolean" },
+            },
+        });
+        let skeleton = serde_json::from_str::<Value>(&arguments_skeleton(&schema)).unwrap();
+        assert_eq!(skeleton, json!({ "path": null, "recursive": null }));
+
+        assert_eq!(arguments_skeleton(&json!({ "type": "object" })), "{}");
+    }
+}
@@ -1,3 +1,4 @@
+mod context_server_inspector;
 mod key_context_view;
 mod lsp_log;
 mod syntax_tree_view;
@@ -7,11 +8,13 @@ mod lsp_log_tests;
 
 use gpui::App;
 
+pub use context_server_inspector::ContextServerInspector;
 pub use lsp_log::{LogStore, LspLogToolbarItemView, LspLogView};
 pub use syntax_tree_view::{SyntaxTreeToolbarItemView, SyntaxTreeView};
 
 pub fn init(cx: &mut App) {
     lsp_log::init(cx);
+    context_server_inspector::init(cx);
     syntax_tree_view::init(cx);
     key_context_view::init(cx);
 }
@@ -1,15 +1,20 @@
-use editor::{Anchor, Editor, ExcerptId, scroll::Autoscroll};
+use editor::{Anchor, Editor, EditorEvent, ExcerptId, scroll::Autoscroll};
 use gpui::{
-    App, AppContext as _, Context, Div, Entity, EventEmitter, FocusHandle, Focusable, Hsla,
-    InteractiveElement, IntoElement, MouseButton, MouseDownEvent, MouseMoveEvent, ParentElement,
-    Render, ScrollStrategy, SharedString, Styled, UniformListScrollHandle, WeakEntity, Window,
-    actions, div, rems, uniform_list,
+    AnyElement, App, AppContext as _, Context, Div, Entity, EventEmitter, FocusHandle, Focusable,
+    Hsla, InteractiveElement, IntoElement, MouseButton, MouseDownEvent, MouseMoveEvent,
+    ParentElement, Render, ScrollStrategy, SharedString, StatefulInteractiveElement, Styled,
+    Subscription, UniformListScrollHandle, WeakEntity, Window, actions, div,
+    prelude::FluentBuilder as _, rems, uniform_list,
 };
-use language::{Buffer, OwnedSyntaxLayer};
+use language::{Buffer, Language, OwnedSyntaxLayer};
 use std::{mem, ops::Range};
+use streaming_iterator::StreamingIterator as _;
 use theme::ActiveTheme;
-use tree_sitter::{Node, TreeCursor};
-use ui::{ButtonLike, Color, ContextMenu, Label, LabelCommon, PopoverMenu, h_flex};
+use tree_sitter::{Node, Query, QueryCursor, TreeCursor};
+use ui::{
+    Button, ButtonLike, Clickable, Color, ContextMenu, Label, LabelCommon, LabelSize, PopoverMenu,
+    Toggleable, h_flex, v_flex,
+};
 use workspace::{
     SplitDirection, ToolbarItemEvent, ToolbarItemLocation, ToolbarItemView, Workspace,
     item::{Item, ItemHandle},
@@ -42,8 +47,71 @@ pub struct SyntaxTreeView {
     selected_descendant_ix: Option<usize>,
     hovered_descendant_ix: Option<usize>,
     focus_handle: FocusHandle,
+    query_editor: Entity<Editor>,
+    query_kind: QueryKind,
+    query_result: Option<Result<QueryResult, SharedString>>,
+    _query_subscription: Subscription,
+}
+
+/// The kinds of queries a language extension provides, which the playground can evaluate
+/// against the active syntax layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QueryKind {
+    Highlights,
+    Outline,
+    Injections,
 }
 
+impl QueryKind {
+    const ALL: [Self; 3] = [Self::Highlights, Self::Outline, Self::Injections];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Highlights => "highlights.scm",
+            Self::Outline => "outline.scm",
+            Self::Injections => "injections.scm",
+        }
+    }
+
+    /// Returns the captures that Zed requires in this kind of query, and that are missing from
+    /// `capture_names`.
+    fn missing_captures(self, capture_names: &[&str]) -> Vec<&'static str> {
+        let has = |name| capture_names.contains(&name);
+        match self {
+            Self::Highlights => Vec::new(),
+            Self::Outline => ["item", "name"]
+                .into_iter()
+                .filter(|name| !has(*name))
+                .collect(),
+            Self::Injections => {
+                if has("injection.content") || has("content") {
+                    Vec::new()
+                } else {
+                    vec!["injection.content"]
+                }
+            }
+        }
+    }
+}
+
+/// Large queries can capture thousands of nodes, so only this many are listed.
+const MAX_LISTED_CAPTURES: usize = 500;
+
+struct QueryResult {
+    captures: Vec<QueryCapture>,
+    capture_count: usize,
+    missing_captures: Vec<&'static str>,
+}
+
+struct QueryCapture {
+    name: SharedString,
+    range: Range<usize>,
+    text: SharedString,
+}
+
+/// Background highlights for the nodes captured by the query being evaluated.
+struct QueryCaptureHighlight;
+
 pub struct SyntaxTreeToolbarItemView {
     tree_view: Option<Entity<SyntaxTreeView>>,
     subscription: Option<gpui::Subscription>,
@@ -69,6 +137,16 @@ impl SyntaxTreeView {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) -> Self {
+        let query_editor = cx.new(|cx| {
+            let mut editor = Editor::auto_height(8, window, cx);
+            editor.set_placeholder_text("Tree-sitter query, e.g. (identifier) @variable", cx);
+            editor
+        });
+        let query_subscription = cx.subscribe(&query_editor, |this, _, event, cx| {
+            if let EditorEvent::BufferEdited = event {
+                this.evaluate_query(cx);
+            }
+        });
         let mut this = Self {
             workspace_handle: workspace_handle.clone(),
             list_scroll_handle: UniformListScrollHandle::new(),
@@ -76,6 +154,10 @@ impl SyntaxTreeView {
             hovered_descendant_ix: None,
             selected_descendant_ix: None,
             focus_handle: cx.focus_handle(),
+            query_editor,
+            query_kind: QueryKind::Highlights,
+            query_result: None,
+            _query_subscription: query_subscription,
         };
 
         this.workspace_updated(active_item, window, cx);
@@ -111,8 +193,9 @@ impl SyntaxTreeView {
             if state.editor == editor {
                 return;
             }
-            editor.update(cx, |editor, cx| {
-                editor.clear_background_highlights::<Self>(cx)
+            state.editor.update(cx, |editor, cx| {
+                editor.clear_background_highlights::<Self>(cx);
+                editor.clear_background_highlights::<QueryCaptureHighlight>(cx);
             });
         }
 
@@ -220,10 +303,88 @@ impl SyntaxTreeView {
         self.list_scroll_handle
             .scroll_to_item(descendant_ix, ScrollStrategy::Center);
 
+        if did_reparse {
+            self.evaluate_query(cx);
+        }
+
         cx.notify();
         Some(())
     }
 
+    /// Runs the query in the query editor against the active syntax layer, and highlights the
+    /// captured nodes in the editor.
+    fn evaluate_query(&mut self, cx: &mut Context<Self>) {
+        let source = self.query_editor.read(cx).text(cx);
+        let active_buffer = self
+            .editor
+            .as_ref()
+            .and_then(|editor| editor.active_buffer.as_ref());
+        self.query_result = active_buffer.and_then(|buffer_state| {
+            let layer = buffer_state.active_layer.as_ref()?;
+            if source.trim().is_empty() {
+                return None;
+            }
+            let text = buffer_state.buffer.read(cx).text();
+            Some(run_query(
+                self.query_kind,
+                &source,
+                &layer.language,
+                layer.node(),
+                &text,
+            ))
+        });
+
+        let highlighted_ranges = match &self.query_result {
+            Some(Ok(result)) => self.anchor_ranges(
+                result.captures.iter().map(|capture| capture.range.clone()),
+                cx,
+            ),
+            _ => None,
+        };
+        if let Some(editor_state) = &self.editor {
+            editor_state.editor.update(cx, |editor, cx| {
+                if let Some(ranges) = highlighted_ranges {
+                    editor.highlight_background::<QueryCaptureHighlight>(
+                        &ranges,
+                        |colors| colors.editor_document_highlight_read_background,
+                        cx,
+                    );
+                } else {
+                    editor.clear_background_highlights::<QueryCaptureHighlight>(cx);
+                }
+            });
+        }
+        cx.notify();
+    }
+
+    fn set_query_kind(&mut self, kind: QueryKind, cx: &mut Context<Self>) {
+        self.query_kind = kind;
+        self.evaluate_query(cx);
+    }
+
+    /// Converts byte ranges in the active buffer into anchor ranges in the editor.
+    fn anchor_ranges(
+        &self,
+        ranges: impl IntoIterator<Item = Range<usize>>,
+        cx: &App,
+    ) -> Option<Vec<Range<Anchor>>> {
+        let editor_state = self.editor.as_ref()?;
+        let buffer_state = editor_state.active_buffer.as_ref()?;
+        let buffer = buffer_state.buffer.read(cx);
+        let multibuffer = editor_state.editor.read(cx).buffer().read(cx).snapshot(cx);
+        let excerpt_id = buffer_state.excerpt_id;
+        ranges
+            .into_iter()
+            .map(|range| {
+                let start =
+                    multibuffer.anchor_in_excerpt(excerpt_id, buffer.anchor_before(range.start))?;
+                let end =
+                    multibuffer.anchor_in_excerpt(excerpt_id, buffer.anchor_after(range.end))?;
+                Some(start..end)
+            })
+            .collect()
+    }
+
     fn update_editor_with_range_for_descendant_ix(
         &self,
         descendant_ix: usize,
@@ -238,31 +399,147 @@ impl SyntaxTreeView {
         // Find the node.
         let mut cursor = layer.node().walk();
         cursor.goto_descendant(descendant_ix);
-        let node = cursor.node();
-        let range = node.byte_range();
-
-        // Build a text anchor range.
-        let buffer = buffer_state.buffer.read(cx);
-        let range = buffer.anchor_before(range.start)..buffer.anchor_after(range.end);
-
-        // Build a multibuffer anchor range.
-        let multibuffer = editor_state.editor.read(cx).buffer();
-        let multibuffer = multibuffer.read(cx).snapshot(cx);
-        let excerpt_id = buffer_state.excerpt_id;
-        let range = multibuffer
-            .anchor_in_excerpt(excerpt_id, range.start)
-            .unwrap()
-            ..multibuffer
-                .anchor_in_excerpt(excerpt_id, range.end)
-                .unwrap();
+        let range = cursor.node().byte_range();
 
         // Update the editor with the anchor range.
+        let range = self.anchor_ranges([range], cx)?.pop()?;
         editor_state.editor.update(cx, |editor, cx| {
             f(editor, range, window, cx);
         });
         Some(())
     }
 
+    fn select_byte_range(
+        &self,
+        range: Range<usize>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<()> {
+        let editor_state = self.editor.as_ref()?;
+        let range = self.anchor_ranges([range], cx)?.pop()?;
+        editor_state.editor.update(cx, |editor, cx| {
+            editor.change_selections(Some(Autoscroll::newest()), window, cx, |selections| {
+                selections.select_ranges(vec![range]);
+            });
+        });
+        Some(())
+    }
+
+    fn render_query_panel(&self, cx: &mut Context<Self>) -> Div {
+        let colors = cx.theme().colors();
+        v_flex()
+            .gap_1()
+            .p_1()
+            .border_t_1()
+            .border_color(colors.border)
+            .child(
+                h_flex()
+                    .gap_1()
+                    .children(QueryKind::ALL.into_iter().map(|kind| {
+                        Button::new(kind.file_name(), kind.file_name())
+                            .label_size(LabelSize::Small)
+                            .toggle_state(kind == self.query_kind)
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                this.set_query_kind(kind, cx);
+                            }))
+                    })),
+            )
+            .child(
+                div()
+                    .p_1()
+                    .border_1()
+                    .border_color(colors.border_variant)
+                    .rounded_sm()
+                    .child(self.query_editor.clone()),
+            )
+            .children(self.render_query_result(cx))
+    }
+
+    fn render_query_result(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let result = match self.query_result.as_ref()? {
+            Ok(result) => result,
+            Err(error) => {
+                return Some(
+                    Label::new(error.clone())
+                        .size(LabelSize::Small)
+                        .color(Color::Error)
+                        .into_any_element(),
+                );
+            }
+        };
+
+        let summary = if result.capture_count > result.captures.len() {
+            format!(
+                "{} captures, showing the first {}",
+                result.capture_count,
+                result.captures.len()
+            )
+        } else {
+            format!("{} captures", result.capture_count)
+        };
+        let syntax_theme = cx.theme().syntax().clone();
+        let hover_color = cx.theme().colors().element_hover;
+        Some(
+            v_flex()
+                .gap_1()
+                .when(!result.missing_captures.is_empty(), |this| {
+                    this.child(
+                        Label::new(format!(
+                            "Zed requires these captures in {}: {}",
+                            self.query_kind.file_name(),
+                            result
+                                .missing_captures
+                                .iter()
+                                .map(|name| format!("@{name}"))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ))
+                        .size(LabelSize::Small)
+                        .color(Color::Warning),
+                    )
+                })
+                .child(
+                    Label::new(summary)
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+                .child(
+                    v_flex()
+                        .id("query-captures")
+                        .max_h(rems(16.))
+                        .overflow_y_scroll()
+                        .children(result.captures.iter().enumerate().map(|(ix, capture)| {
+                            let name_color = if self.query_kind == QueryKind::Highlights {
+                                Color::Custom(syntax_theme.color(&capture.name))
+                            } else {
+                                Color::Accent
+                            };
+                            let range = capture.range.clone();
+                            h_flex()
+                                .id(("query-capture", ix))
+                                .gap_1()
+                                .cursor_pointer()
+                                .hover(|style| style.bg(hover_color))
+                                .child(
+                                    Label::new(format!("@{}", capture.name))
+                                        .size(LabelSize::Small)
+                                        .color(name_color),
+                                )
+                                .child(
+                                    Label::new(capture.text.clone())
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted)
+                                        .single_line(),
+                                )
+                                .on_click(cx.listener(move |this, _, window, cx| {
+                                    this.select_byte_range(range.clone(), window, cx);
+                                }))
+                        })),
+                )
+                .into_any_element(),
+        )
+    }
+
     fn render_node(cursor: &TreeCursor, depth: u32, selected: bool, cx: &App) -> Div {
         let colors = cx.theme().colors();
         let mut row = h_flex();
@@ -293,7 +570,7 @@ impl SyntaxTreeView {
 
 impl Render for SyntaxTreeView {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let mut rendered = div().flex_1().bg(cx.theme().colors().editor_background);
+        let mut rendered = v_flex().flex_1().bg(cx.theme().colors().editor_background);
 
         if let Some(layer) = self
             .editor
@@ -379,12 +656,13 @@ impl Render for SyntaxTreeView {
                     items
                 },
             )
-            .size_full()
+            .flex_1()
+            .w_full()
             .track_scroll(self.list_scroll_handle.clone())
             .text_bg(cx.theme().colors().background).into_any_element());
         }
 
-        rendered
+        rendered.child(self.render_query_panel(cx))
     }
 }
 
@@ -418,8 +696,13 @@ impl Item for SyntaxTreeView {
     where
         Self: Sized,
     {
+        let query = self.query_editor.read(cx).text(cx);
         Some(cx.new(|cx| {
             let mut clone = Self::new(self.workspace_handle.clone(), None, window, cx);
+            clone.query_kind = self.query_kind;
+            clone
+                .query_editor
+                .update(cx, |editor, cx| editor.set_text(query, window, cx));
             if let Some(editor) = &self.editor {
                 clone.set_editor(editor.editor.clone(), window, cx)
             }
@@ -491,6 +774,7 @@ impl SyntaxTreeToolbarItemView {
             let layer = snapshot.syntax_layers().nth(layer_ix)?;
             buffer_state.active_layer = Some(layer.to_owned());
             view.selected_descendant_ix = None;
+            view.evaluate_query(cx);
             cx.notify();
             view.focus_handle.focus(window);
             Some(())
@@ -544,3 +828,47 @@ impl ToolbarItemView for SyntaxTreeToolbarItemView {
         ToolbarItemLocation::Hidden
     }
 }
+
+/// Evaluates a query against a syntax layer, collecting its captures in the order they appear.
+fn run_query(
+    kind: QueryKind,
+    source: &str,
+    language: &Language,
+    root: Node,
+    text: &str,
+) -> Result<QueryResult, SharedString> {
+    let grammar = language
+        .grammar()
+        .ok_or_else(|| SharedString::from(format!("{} has no grammar", language.name())))?;
+    let query = Query::new(&grammar.ts_language, source)
+        .map_err(|error| SharedString::from(error.to_string()))?;
+    let capture_names = query.capture_names();
+
+    let mut captures = Vec::new();
+    let mut capture_count = 0;
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, root, text.as_bytes());
+    while let Some(query_match) = matches.next() {
+        for capture in query_match.captures {
+            capture_count += 1;
+            if captures.len() == MAX_LISTED_CAPTURES {
+                continue;
+            }
+            let range = capture.node.byte_range();
+            let captured_text = text.get(range.clone()).unwrap_or_default();
+            let first_line = captured_text.lines().next().unwrap_or_default();
+            captures.push(QueryCapture {
+                name: capture_names[capture.index as usize].to_string().into(),
+                text: first_line.to_string().into(),
+                range,
+            });
+        }
+    }
+    captures.sort_by_key(|capture| (capture.range.start, capture.range.end));
+
+    Ok(QueryResult {
+        captures,
+        capture_count,
+        missing_captures: kind.missing_captures(capture_names),
+    })
+}
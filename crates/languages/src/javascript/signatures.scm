@@ -0,0 +1,20 @@
+([
+    (function_declaration
+        parameters: (formal_parameters
+            [
+                (identifier) @parameter
+                (assignment_pattern left: (identifier) @parameter)
+            ]*))
+    (generator_function_declaration
+        parameters: (formal_parameters
+            [
+                (identifier) @parameter
+                (assignment_pattern left: (identifier) @parameter)
+            ]*))
+    (method_definition
+        parameters: (formal_parameters
+            [
+                (identifier) @parameter
+                (assignment_pattern left: (identifier) @parameter)
+            ]*))
+]) @item
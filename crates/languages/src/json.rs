@@ -2,7 +2,7 @@ use anyhow::{Context as _, Result, bail};
 use async_compression::futures::bufread::GzipDecoder;
 use async_tar::Archive;
 use async_trait::async_trait;
-use collections::HashMap;
+use collections::{BTreeMap, HashMap};
 use dap::DapRegistry;
 use futures::StreamExt;
 use gpui::{App, AsyncApp};
@@ -12,7 +12,7 @@ use lsp::{LanguageServerBinary, LanguageServerName};
 use node_runtime::NodeRuntime;
 use project::{ContextProviderWithTasks, Fs, lsp_store::language_server_settings};
 use serde_json::{Value, json};
-use settings::{KeymapFile, SettingsJsonSchemaParams, SettingsStore};
+use settings::{KeymapFile, LanguageServerSettingsSchema, SettingsJsonSchemaParams, SettingsStore};
 use smol::{
     fs::{self},
     io::BufReader,
@@ -78,6 +78,7 @@ impl JsonLspAdapter {
 
     fn get_workspace_config(
         language_names: Vec<String>,
+        language_server_schemas: BTreeMap<String, LanguageServerSettingsSchema>,
         adapter_schemas: AdapterSchemas,
         cx: &mut App,
     ) -> Value {
@@ -87,6 +88,7 @@ impl JsonLspAdapter {
             &SettingsJsonSchemaParams {
                 language_names: &language_names,
                 font_names,
+                language_server_schemas: &language_server_schemas,
             },
             cx,
         );
@@ -187,7 +189,12 @@ impl JsonLspAdapter {
             .await;
 
         let config = cx.update(|cx| {
-            Self::get_workspace_config(self.languages.language_names().clone(), adapter_schemas, cx)
+            Self::get_workspace_config(
+                self.languages.language_names(),
+                self.languages.language_server_settings_schemas(),
+                adapter_schemas,
+                cx,
+            )
         })?;
         writer.replace(config.clone());
         return Ok(config);
@@ -403,3 +403,29 @@ fn load_queries(name: &str) -> LanguageQueries {
     }
     result
 }
+
+/// Reads the settings schema from the `package.json` of an npm package that a language server
+/// was installed from.
+pub(crate) async fn npm_package_settings_schema(
+    fs: &dyn project::Fs,
+    delegate: &Arc<dyn LspAdapterDelegate>,
+    server_name: &LanguageServerName,
+    package_name: &str,
+) -> anyhow::Result<Option<settings::LanguageServerSettingsSchema>> {
+    let Some(container_dir) = delegate.language_server_download_dir(server_name).await else {
+        return Ok(None);
+    };
+    let package_json_path = container_dir
+        .join("node_modules")
+        .join(package_name)
+        .join("package.json");
+    if !fs.is_file(&package_json_path).await {
+        return Ok(None);
+    }
+    let package_json = fs.load(&package_json_path).await?;
+    let package_json = serde_json::from_str(&package_json)
+        .with_context(|| format!("parsing {package_json_path:?}"))?;
+    Ok(settings::LanguageServerSettingsSchema::from_package_json(
+        &package_json,
+    ))
+}
@@ -0,0 +1,10 @@
+(function_definition
+    parameters: (parameters
+        [
+            (identifier) @parameter
+            (default_parameter name: (identifier) @parameter)
+            (typed_parameter . (identifier) @parameter)
+            (typed_default_parameter name: (identifier) @parameter)
+        ]*)
+    return_type: (_)? @return_type
+    body: (block) @body) @item
@@ -0,0 +1,9 @@
+(function_item
+    parameters: (parameters
+        (parameter pattern: (_) @parameter)*)
+    return_type: (_)? @return_type) @item
+
+(function_signature_item
+    parameters: (parameters
+        (parameter pattern: (_) @parameter)*)
+    return_type: (_)? @return_type) @item
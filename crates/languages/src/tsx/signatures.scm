@@ -0,0 +1,30 @@
+([
+    (function_declaration
+        parameters: (formal_parameters
+            [
+                (required_parameter pattern: (identifier) @parameter)
+                (optional_parameter pattern: (identifier) @parameter)
+            ]*)
+        return_type: (_)? @return_type)
+    (function_signature
+        parameters: (formal_parameters
+            [
+                (required_parameter pattern: (identifier) @parameter)
+                (optional_parameter pattern: (identifier) @parameter)
+            ]*)
+        return_type: (_)? @return_type)
+    (method_definition
+        parameters: (formal_parameters
+            [
+                (required_parameter pattern: (identifier) @parameter)
+                (optional_parameter pattern: (identifier) @parameter)
+            ]*)
+        return_type: (_)? @return_type)
+    (method_signature
+        parameters: (formal_parameters
+            [
+                (required_parameter pattern: (identifier) @parameter)
+                (optional_parameter pattern: (identifier) @parameter)
+            ]*)
+        return_type: (_)? @return_type)
+]) @item
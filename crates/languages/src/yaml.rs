@@ -9,7 +9,7 @@ use lsp::{LanguageServerBinary, LanguageServerName};
 use node_runtime::NodeRuntime;
 use project::{Fs, lsp_store::language_server_settings};
 use serde_json::Value;
-use settings::{Settings, SettingsLocation};
+use settings::{LanguageServerSettingsSchema, Settings, SettingsLocation};
 use smol::fs;
 use std::{
     any::Any,
@@ -126,6 +126,15 @@ impl LspAdapter for YamlLspAdapter {
         get_cached_server_binary(container_dir, &self.node).await
     }
 
+    async fn settings_schema(
+        self: Arc<Self>,
+        fs: &dyn Fs,
+        delegate: &Arc<dyn LspAdapterDelegate>,
+    ) -> Result<Option<LanguageServerSettingsSchema>> {
+        crate::npm_package_settings_schema(fs, delegate, &Self::SERVER_NAME, Self::PACKAGE_NAME)
+            .await
+    }
+
     async fn workspace_configuration(
         self: Arc<Self>,
         _: &dyn Fs,
@@ -3,7 +3,7 @@ use futures::{AsyncBufReadExt, AsyncReadExt, StreamExt, io::BufReader, stream::B
 use http_client::{AsyncBody, HttpClient, Method, Request as HttpRequest};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::convert::TryFrom;
+use std::{convert::TryFrom, sync::Arc};
 use strum::EnumIter;
 
 pub const MISTRAL_API_URL: &str = "https://api.mistral.ai/v1";
@@ -191,7 +191,7 @@ pub enum ToolDefinition {
 pub struct FunctionDefinition {
     pub name: String,
     pub description: Option<String>,
-    pub parameters: Option<Value>,
+    pub parameters: Option<Arc<Value>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
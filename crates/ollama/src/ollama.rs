@@ -123,7 +123,7 @@ pub struct OllamaFunctionCall {
 pub struct OllamaFunctionTool {
     pub name: String,
     pub description: Option<String>,
-    pub parameters: Option<Value>,
+    pub parameters: Option<Arc<Value>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
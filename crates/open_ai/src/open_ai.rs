@@ -10,6 +10,7 @@ use serde_json::Value;
 use std::{
     convert::TryFrom,
     future::{self, Future},
+    sync::Arc,
 };
 use strum::EnumIter;
 
@@ -271,7 +272,7 @@ pub enum ToolDefinition {
 pub struct FunctionDefinition {
     pub name: String,
     pub description: Option<String>,
-    pub parameters: Option<Value>,
+    pub parameters: Option<Arc<Value>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
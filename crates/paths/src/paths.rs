@@ -417,6 +417,16 @@ pub fn local_vscode_launch_file_relative_path() -> &'static Path {
     Path::new(".vscode/launch.json")
 }
 
+/// Returns the relative path to a `.vscode/mcp.json` file within a project.
+pub fn local_vscode_mcp_file_relative_path() -> &'static Path {
+    Path::new(".vscode/mcp.json")
+}
+
+/// Returns the relative path to a `.cursor/mcp.json` file within a project.
+pub fn local_cursor_mcp_file_relative_path() -> &'static Path {
+    Path::new(".cursor/mcp.json")
+}
+
 pub fn user_ssh_config_file() -> PathBuf {
     home_dir().join(".ssh/config")
 }
@@ -425,6 +435,12 @@ pub fn global_ssh_config_file() -> &'static Path {
     Path::new("/etc/ssh/ssh_config")
 }
 
+/// Returns the path to the directory containing installed vscode extensions
+pub fn vscode_extensions_dir() -> &'static PathBuf {
+    static VSCODE_EXTENSIONS_DIR: OnceLock<PathBuf> = OnceLock::new();
+    VSCODE_EXTENSIONS_DIR.get_or_init(|| home_dir().join(".vscode/extensions"))
+}
+
 /// Returns the path to the vscode user settings file
 pub fn vscode_settings_file() -> &'static PathBuf {
     static LOGS_DIR: OnceLock<PathBuf> = OnceLock::new();
@@ -439,3 +455,41 @@ pub fn vscode_settings_file() -> &'static PathBuf {
         }
     })
 }
+
+/// Returns the path to the Claude Desktop configuration file, which lists its MCP servers.
+pub fn claude_desktop_config_file() -> &'static PathBuf {
+    static CLAUDE_DESKTOP_CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+    let rel_path = "Claude/claude_desktop_config.json";
+    CLAUDE_DESKTOP_CONFIG_FILE.get_or_init(|| {
+        if cfg!(target_os = "macos") {
+            home_dir()
+                .join("Library/Application Support")
+                .join(rel_path)
+        } else {
+            dirs::config_dir()
+                .unwrap_or_else(|| home_dir().join(".config"))
+                .join(rel_path)
+        }
+    })
+}
+
+/// Returns the path to the file listing Cursor's user-wide MCP servers.
+pub fn cursor_mcp_file() -> &'static PathBuf {
+    static CURSOR_MCP_FILE: OnceLock<PathBuf> = OnceLock::new();
+    CURSOR_MCP_FILE.get_or_init(|| home_dir().join(".cursor/mcp.json"))
+}
+
+/// Returns the path to the vscode user keybindings file
+pub fn vscode_keybindings_file() -> &'static PathBuf {
+    static KEYBINDINGS_FILE: OnceLock<PathBuf> = OnceLock::new();
+    let rel_path = "Code/User/keybindings.json";
+    KEYBINDINGS_FILE.get_or_init(|| {
+        if cfg!(target_os = "macos") {
+            home_dir()
+                .join("Library/Application Support")
+                .join(rel_path)
+        } else {
+            config_dir().join(rel_path)
+        }
+    })
+}
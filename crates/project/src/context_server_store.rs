@@ -1,19 +1,50 @@
 pub mod extension;
+pub mod package;
+pub mod pool;
 pub mod registry;
 
-use std::{path::Path, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
-use anyhow::{Context as _, Result};
+use anyhow::{Context as _, Result, anyhow};
 use collections::{HashMap, HashSet};
-use context_server::{ContextServer, ContextServerId};
+use context_server::{
+    ContextServer, ContextServerCommand, ContextServerId,
+    builtin::{BuiltinContextServer, BuiltinServer, FetchServer, FilesystemServer},
+    egress_proxy::EgressProxy,
+    message_log::is_sensitive_key,
+    middleware::ToolMiddleware,
+    openapi::ContextServerOpenApi,
+    protocol::ContextServerCapabilities,
+    sandbox::{ContextServerSandbox, sandboxed_command},
+    transport::{BuiltinTransport, OpenApiTransport, ReplayTransport},
+};
+use db::kvp::KEY_VALUE_STORE;
+use futures::{
+    FutureExt as _, StreamExt as _, channel::oneshot, future::Shared, select_biased,
+    stream::FuturesUnordered,
+};
 use gpui::{App, AsyncApp, Context, Entity, EventEmitter, Subscription, Task, WeakEntity, actions};
+use node_runtime::NodeRuntime;
+use package::InstalledPackage;
+use pool::ContextServerPool;
+use postage::stream::Stream as _;
+use postage::watch;
 use registry::ContextServerDescriptorRegistry;
-use settings::{Settings as _, SettingsStore};
+use serde::Deserialize;
+use settings::{Settings as _, SettingsStore, parse_json_with_comments};
+use sha2::{Digest as _, Sha256};
 use util::ResultExt as _;
+use worktree::{Worktree, WorktreeId};
 
 use crate::{
-    project_settings::{ContextServerConfiguration, ProjectSettings},
-    worktree_store::WorktreeStore,
+    ProjectEnvironment,
+    project_settings::{ContextServerConfiguration, PackageRegistry, ProjectSettings},
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
 };
 
 pub fn init(cx: &mut App) {
@@ -24,6 +55,7 @@ actions!(context_server, [Restart]);
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ContextServerStatus {
+    Installing,
     Starting,
     Running,
     Stopped,
@@ -81,16 +113,611 @@ impl ContextServerState {
     }
 }
 
+/// The files, relative to a worktree's root, in which a project can declare context servers.
+/// When both exist, the first one is used.
+const PROJECT_SERVERS_FILES: [&str; 2] = [".zed/mcp.json", ".mcp.json"];
+
+/// How long tool calls in flight are waited for before a server whose configuration changed is
+/// restarted, after which they're cancelled.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long settings have to stay unchanged before servers are updated for them, so that a burst
+/// of edits, such as from settings sync, restarts each server once.
+const SETTINGS_UPDATE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+fn is_allowed_trace_file(
+    path: &Path,
+    user_trace_file: Option<&Path>,
+    zed_directories: &[&Path],
+) -> bool {
+    if path
+        .components()
+        .any(|component| component == std::path::Component::ParentDir)
+    {
+        return false;
+    }
+    user_trace_file == Some(path)
+        || zed_directories
+            .iter()
+            .any(|directory| path.starts_with(directory))
+}
+
+/// How maintenance stops using a server.
+#[derive(Clone, Copy)]
+enum ServerShutdown {
+    /// The server is restarted, or isn't needed anymore.
+    Stop,
+    /// The server isn't active, and may be kept running in the pool.
+    Park,
+    /// The server was removed from the configuration.
+    Remove,
+}
+
+/// Context servers declared in a project's `.zed/mcp.json` or `.mcp.json` file.
+///
+/// They only run once the user trusts the file, since opening a repository shouldn't run the
+/// commands in it. User settings take precedence over servers with the same name.
+#[derive(Debug, Clone)]
+pub struct ProjectContextServers {
+    pub abs_path: Arc<Path>,
+    pub servers: HashMap<Arc<str>, ContextServerConfiguration>,
+    /// A digest of the file's contents, so that trust can be given to what the user reviewed.
+    pub digest: Arc<str>,
+    pub trusted: bool,
+}
+
+/// The settings that decided whether a server's tool calls are confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationSource {
+    /// No settings say, so calls are confirmed.
+    Default,
+    /// The user's settings.
+    User,
+    /// The `.mcp.json` file of the trusted project that declares the server.
+    ProjectServers,
+    /// The `.zed/settings.json` file at the root of the project's first worktree.
+    WorktreeSettings,
+}
+
+/// Whether calls to a server's tools are confirmed before they run, and why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolConfirmation {
+    pub confirm: bool,
+    pub source: ConfirmationSource,
+}
+
+/// Resolves a server's `confirm_tool_calls` from the settings that set it.
+///
+/// The user's settings take precedence over the declarations of a project's `.mcp.json`, which
+/// the user reviewed when trusting them. A worktree's settings weren't reviewed, so they can only
+/// require confirmation, never turn it off.
+fn resolve_tool_confirmation(
+    user: Option<bool>,
+    project_servers: Option<bool>,
+    worktree_settings: Option<bool>,
+) -> ToolConfirmation {
+    let mut confirmation = match (user, project_servers) {
+        (Some(confirm), _) => ToolConfirmation {
+            confirm,
+            source: ConfirmationSource::User,
+        },
+        (None, Some(confirm)) => ToolConfirmation {
+            confirm,
+            source: ConfirmationSource::ProjectServers,
+        },
+        (None, None) => ToolConfirmation {
+            confirm: true,
+            source: ConfirmationSource::Default,
+        },
+    };
+    if worktree_settings == Some(true) && !confirmation.confirm {
+        confirmation = ToolConfirmation {
+            confirm: true,
+            source: ConfirmationSource::WorktreeSettings,
+        };
+    }
+    confirmation
+}
+
+/// The format used by `.mcp.json` files, which other MCP clients read too.
+#[derive(Deserialize)]
+struct ProjectServersFile {
+    #[serde(default, rename = "mcpServers")]
+    mcp_servers: HashMap<Arc<str>, ProjectServer>,
+}
+
+#[derive(Deserialize)]
+struct ProjectServer {
+    #[serde(rename = "type")]
+    transport: Option<String>,
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    env: Option<HashMap<String, String>>,
+    cwd: Option<String>,
+}
+
+fn parse_project_servers(content: &str) -> Result<HashMap<Arc<str>, ContextServerConfiguration>> {
+    let file: ProjectServersFile = settings::parse_json_with_comments(content)?;
+    Ok(file
+        .mcp_servers
+        .into_iter()
+        .filter_map(|(name, server)| {
+            if let Some(transport) = server.transport.filter(|transport| transport != "stdio") {
+                log::warn!(
+                    "skipping context server {name}: the {transport} transport isn't supported"
+                );
+                return None;
+            }
+            let Some(path) = server.command else {
+                log::warn!("skipping context server {name}: it has no command");
+                return None;
+            };
+            let configuration = ContextServerConfiguration {
+                command: Some(ContextServerCommand {
+                    path,
+                    args: server.args,
+                    env: server.env,
+                    cwd: server.cwd,
+                }),
+                settings: Some(serde_json::json!({})),
+                ..Default::default()
+            };
+            Some((name, configuration))
+        })
+        .collect())
+}
+
+/// Looks `command` up on the `PATH` of the shell environment and runs it with that environment,
+/// so that the programs it runs are found too.
+fn resolve_in_shell_environment(
+    command: &mut ContextServerCommand,
+    shell_environment: &HashMap<String, String>,
+    directory: &Path,
+) {
+    let search_path = shell_environment.get("PATH");
+    if let Ok(path) = which::which_in(&command.path, search_path, directory) {
+        command.path = path.to_string_lossy().into_owned();
+    }
+    let mut env = shell_environment.clone();
+    env.extend(command.env.take().unwrap_or_default());
+    command.env = Some(env);
+}
+
+/// A context server that isn't run until the user trusts how it's launched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UntrustedContextServer {
+    /// A digest of how the server is launched, so that trust can be given to what the user
+    /// reviewed.
+    pub digest: Arc<str>,
+    /// A description of how the server is launched, for the user to review.
+    pub summary: String,
+}
+
+/// Returns a digest of what a server runs or connects to, which identifies it across renames.
+///
+/// Environment variables and headers are left out, so that rotating a secret doesn't change it.
+fn launch_digest(configuration: &ContextServerConfiguration) -> Arc<str> {
+    let launch = serde_json::json!({
+        "command": configuration.command.as_ref().map(|command| {
+            serde_json::json!([command.path, command.args, command.cwd])
+        }),
+        "package": configuration.package.as_ref().map(|package| {
+            serde_json::json!([package.registry, package.name, package.version, package.bin, package.args])
+        }),
+        "openapi": configuration.openapi.as_ref().map(|openapi| {
+            serde_json::json!([openapi.spec_url, openapi.base_url])
+        }),
+        "replay": configuration.replay,
+        "builtin": configuration.builtin,
+    });
+    format!("{:x}", Sha256::digest(launch.to_string().as_bytes())).into()
+}
+
+/// Returns the directory a sandboxed server uses as its home. It's named after a hash of the
+/// server's ID, since the ID comes from settings and may contain `/` or `..`.
+fn sandbox_scratch_dir(id: &ContextServerId) -> PathBuf {
+    paths::data_dir()
+        .join("context_server_sandboxes")
+        .join(format!("{:x}", Sha256::digest(id.0.as_bytes())))
+}
+
+/// Returns the environment variables a server is started with.
+fn server_env(configuration: &ContextServerConfiguration) -> Option<&HashMap<String, String>> {
+    configuration
+        .command
+        .as_ref()
+        .and_then(|command| command.env.as_ref())
+        .or_else(|| {
+            configuration
+                .package
+                .as_ref()
+                .and_then(|package| package.env.as_ref())
+        })
+}
+
+/// Environment variables that make the programs a server runs load other code, or find other
+/// programs, so that their values decide what runs.
+const CODE_LOADING_ENV_VARS: &[&str] = &[
+    "BASH_ENV",
+    "DYLD_INSERT_LIBRARIES",
+    "DYLD_LIBRARY_PATH",
+    "ENV",
+    "JAVA_TOOL_OPTIONS",
+    "LD_LIBRARY_PATH",
+    "LD_PRELOAD",
+    "NODE_OPTIONS",
+    "NODE_PATH",
+    "PATH",
+    "PERL5LIB",
+    "PERL5OPT",
+    "PYTHONPATH",
+    "PYTHONSTARTUP",
+    "RUBYLIB",
+    "RUBYOPT",
+    "_JAVA_OPTIONS",
+];
+
+fn is_code_loading_env_var(name: &str) -> bool {
+    CODE_LOADING_ENV_VARS
+        .iter()
+        .any(|var| var.eq_ignore_ascii_case(name))
+}
+
+/// Returns the environment variables a server is started with, sorted by name, with the values of
+/// the ones that load code.
+fn trusted_env(configuration: &ContextServerConfiguration) -> BTreeMap<&str, Option<&str>> {
+    server_env(configuration)
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| {
+            let value = is_code_loading_env_var(name).then_some(value.as_str());
+            (name.as_str(), value)
+        })
+        .collect()
+}
+
+/// Returns a digest of how a server is launched and what it's allowed to do, which is what the
+/// user reviews before trusting it.
+///
+/// The names of environment variables and headers are included, but only the values of the
+/// variables that load code, such as `LD_PRELOAD`, so that rotating a secret doesn't require
+/// trusting the server again.
+fn trust_digest(configuration: &ContextServerConfiguration) -> Arc<str> {
+    let trust = serde_json::json!({
+        "launch": launch_digest(configuration),
+        "env": trusted_env(configuration),
+        "headers": configuration
+            .openapi
+            .as_ref()
+            .map(|openapi| openapi.headers.keys().collect::<Vec<_>>()),
+        "sandbox": configuration.sandbox,
+        "confirm_tool_calls": configuration.confirm_tool_calls,
+    });
+    format!("{:x}", Sha256::digest(trust.to_string().as_bytes())).into()
+}
+
+/// Describes everything about how a server is launched, including the values of its environment
+/// variables and headers, so that servers are only the same when they'd run the same.
+fn full_launch(configuration: &ContextServerConfiguration) -> String {
+    let env = server_env(configuration).map(|env| env.iter().collect::<BTreeMap<_, _>>());
+    serde_json::json!({
+        "launch": launch_digest(configuration),
+        "env": env,
+        "headers": configuration.openapi.as_ref().map(|openapi| &openapi.headers),
+        "sandbox": configuration.sandbox,
+    })
+    .to_string()
+}
+
+/// Identifies the program a server runs, ignoring the arguments and environment that configure
+/// it, so that several instances of the same server can be told apart.
+///
+/// For commands, that's the executable and its first argument that isn't a flag, which is the
+/// script or package for launchers like `npx` and `uvx`.
+fn server_program(configuration: &ContextServerConfiguration) -> Option<String> {
+    if let Some(command) = &configuration.command {
+        let program = command.args.iter().find(|arg| !arg.starts_with('-'));
+        Some(match program {
+            Some(program) => format!("{} {program}", command.path),
+            None => command.path.clone(),
+        })
+    } else if let Some(package) = &configuration.package {
+        Some(package.name.clone())
+    } else if let Some(builtin) = configuration.builtin {
+        Some(format!("builtin {}", builtin.id()))
+    } else {
+        configuration
+            .openapi
+            .as_ref()
+            .map(|openapi| openapi.spec_url.clone())
+    }
+}
+
+/// Parses the command, or the URL of an OpenAPI document, of a server to run without saving it
+/// to the settings. Returns a name for the server along with its configuration.
+pub fn temporary_server_configuration(
+    command_or_url: &str,
+) -> Result<(String, ContextServerConfiguration)> {
+    let command_or_url = command_or_url.trim();
+    if let Some(location) = command_or_url
+        .strip_prefix("https://")
+        .or_else(|| command_or_url.strip_prefix("http://"))
+    {
+        let host = location.split(['/', ':', '?']).next().unwrap_or_default();
+        anyhow::ensure!(!host.is_empty(), "{command_or_url:?} isn't a valid URL");
+        let configuration = ContextServerConfiguration {
+            openapi: Some(ContextServerOpenApi {
+                spec_url: command_or_url.to_string(),
+                base_url: None,
+                headers: Default::default(),
+            }),
+            ..Default::default()
+        };
+        return Ok((host.to_string(), configuration));
+    }
+
+    let mut args = shlex::split(command_or_url)
+        .with_context(|| format!("couldn't parse the command {command_or_url:?}"))?;
+    anyhow::ensure!(!args.is_empty(), "no command was given");
+    let path = args.remove(0);
+    let configuration = ContextServerConfiguration {
+        command: Some(ContextServerCommand {
+            path,
+            args,
+            env: None,
+            cwd: None,
+        }),
+        ..Default::default()
+    };
+    let program = server_program(&configuration).unwrap_or_default();
+    let name = program
+        .rsplit([' ', '/', '\\'])
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("server")
+        .to_string();
+    Ok((name, configuration))
+}
+
+/// Whether a configuration says how to launch its server, rather than only adjusting a server
+/// that an extension or Zed provides.
+fn has_launch(configuration: &ContextServerConfiguration) -> bool {
+    configuration.command.is_some()
+        || configuration.replay.is_some()
+        || configuration.openapi.is_some()
+        || configuration.package.is_some()
+        || configuration.builtin.is_some()
+}
+
+/// Removes the servers that launch the same thing as another one, with the same environment,
+/// headers and sandbox, so that it only runs once and its tools' names don't collide, and returns
+/// the server each removed one duplicates.
+///
+/// The server kept is the one with the lowest `rank`, which is where it's configured, and then
+/// the one whose id sorts first.
+fn deduplicate_servers(
+    servers: &mut HashMap<Arc<str>, ContextServerConfiguration>,
+    rank: impl Fn(&Arc<str>) -> usize,
+) -> HashMap<ContextServerId, ContextServerId> {
+    let mut servers_by_launch = HashMap::<String, Vec<Arc<str>>>::default();
+    for (id, configuration) in servers.iter() {
+        if has_launch(configuration) {
+            servers_by_launch
+                .entry(full_launch(configuration))
+                .or_default()
+                .push(id.clone());
+        }
+    }
+    let mut duplicates = HashMap::default();
+    for mut ids in servers_by_launch.into_values() {
+        ids.sort_by(|a, b| rank(a).cmp(&rank(b)).then_with(|| a.cmp(b)));
+        let Some((kept, duplicate_ids)) = ids.split_first() else {
+            continue;
+        };
+        for duplicate in duplicate_ids {
+            servers.remove(duplicate);
+            duplicates.insert(
+                ContextServerId(duplicate.clone()),
+                ContextServerId(kept.clone()),
+            );
+        }
+    }
+    duplicates
+}
+
+/// Returns the servers that went away and the new servers that launch the same thing, as pairs
+/// of the old and new ids, which is what renaming a server in the settings looks like.
+///
+/// `previous` maps the ids of the servers that were running to their launch digests.
+fn renamed_servers(
+    previous: &HashMap<Arc<str>, Arc<str>>,
+    servers: &HashMap<Arc<str>, ContextServerConfiguration>,
+) -> Vec<(ContextServerId, ContextServerId)> {
+    let mut removed_by_launch = HashMap::<Arc<str>, Vec<Arc<str>>>::default();
+    for (id, digest) in previous {
+        if !servers.contains_key(id) {
+            removed_by_launch
+                .entry(digest.clone())
+                .or_default()
+                .push(id.clone());
+        }
+    }
+    let mut added_by_launch = HashMap::<Arc<str>, Vec<Arc<str>>>::default();
+    for (id, configuration) in servers {
+        if !previous.contains_key(id) && has_launch(configuration) {
+            added_by_launch
+                .entry(launch_digest(configuration))
+                .or_default()
+                .push(id.clone());
+        }
+    }
+    // When several servers launch the same thing, there's no telling which became which.
+    let mut renamed = added_by_launch
+        .into_iter()
+        .filter_map(|(digest, added)| {
+            let removed = removed_by_launch.get(&digest)?;
+            match (removed.as_slice(), added.as_slice()) {
+                ([old_id], [new_id]) => Some((
+                    ContextServerId(old_id.clone()),
+                    ContextServerId(new_id.clone()),
+                )),
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    renamed.sort_by(|a, b| a.0.0.cmp(&b.0.0));
+    renamed
+}
+
+/// Describes what a server runs or connects to, and what it's allowed to do.
+pub fn launch_summary(configuration: &ContextServerConfiguration) -> String {
+    let mut summary = Vec::new();
+    if let Some(command) = &configuration.command {
+        let command_line = std::iter::once(command.path.as_str())
+            .chain(command.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        summary.push(match &command.cwd {
+            Some(cwd) => format!("Runs `{command_line}` in {cwd}"),
+            None => format!("Runs `{command_line}`"),
+        });
+    } else if let Some(package) = &configuration.package {
+        let registry = match package.registry {
+            PackageRegistry::Npm => "npm",
+            PackageRegistry::Pypi => "PyPI",
+        };
+        let version = package.version.as_deref().unwrap_or("latest");
+        summary.push(format!(
+            "Installs and runs {}@{version} from {registry}",
+            package.name
+        ));
+    } else if let Some(openapi) = &configuration.openapi {
+        summary.push(format!(
+            "Calls the REST API described by {}",
+            openapi.spec_url
+        ));
+    } else if let Some(replay) = &configuration.replay {
+        summary.push(format!("Replays the recording at {}", replay.display()));
+    } else if let Some(builtin) = configuration.builtin {
+        summary.push(format!("Runs Zed's built-in {} server", builtin.id()));
+    }
+    if let Some(env) = server_env(configuration).filter(|env| !env.is_empty()) {
+        let env = env
+            .iter()
+            .map(|(name, value)| {
+                // Code-loading variables are shown even when their name looks like a secret,
+                // since what they load is what's being trusted.
+                if is_sensitive_key(name) && !is_code_loading_env_var(name) {
+                    format!("{name}=[redacted]")
+                } else {
+                    format!("{name}={value}")
+                }
+            })
+            .collect::<BTreeSet<_>>();
+        summary.push(format!(
+            "Sets the environment variables {}",
+            env.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if let Some(openapi) = &configuration.openapi {
+        if !openapi.headers.is_empty() {
+            summary.push(format!(
+                "Sends the headers {}",
+                openapi
+                    .headers
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+    if let Some(sandbox) = &configuration.sandbox {
+        summary.push(if !sandbox.allow_network {
+            "Runs in a sandbox without network access".to_string()
+        } else if sandbox.allowed_hosts.is_empty() {
+            "Runs in a sandbox with network access".to_string()
+        } else {
+            format!(
+                "Runs in a sandbox that can only connect to {}",
+                sandbox.allowed_hosts.join(", ")
+            )
+        });
+    }
+    if configuration.confirm_tool_calls == Some(false) {
+        summary.push("Its tools run without asking for confirmation".to_string());
+    }
+    summary.join("\n")
+}
+
 pub type ContextServerFactory =
     Box<dyn Fn(ContextServerId, Arc<ContextServerConfiguration>) -> Arc<ContextServer>>;
 
 pub struct ContextServerStore {
     servers: HashMap<ContextServerId, ContextServerState>,
     worktree_store: Entity<WorktreeStore>,
+    environment: Entity<ProjectEnvironment>,
+    node: Option<NodeRuntime>,
     registry: Entity<ContextServerDescriptorRegistry>,
     update_servers_task: Option<Task<Result<()>>>,
+    /// How long to wait for settings to settle before updating servers for them.
+    settings_update_debounce: Duration,
+    /// Waits for the settings to settle, and is replaced each time they change.
+    settings_update_task: Option<Task<()>>,
+    /// The servers in the settings the last time they changed, to ignore changes to other
+    /// settings.
+    configured_servers: HashMap<Arc<str>, ContextServerConfiguration>,
     context_server_factory: Option<ContextServerFactory>,
     needs_server_update: bool,
+    running_server_listeners:
+        HashMap<ContextServerId, Vec<oneshot::Sender<Result<Arc<ContextServer>>>>>,
+    egress_proxies: HashMap<ContextServerId, EgressProxy>,
+    project_servers: HashMap<WorktreeId, ProjectContextServers>,
+    /// The status of the packages being installed for servers, until the servers are created.
+    package_installs: HashMap<ContextServerId, ContextServerStatus>,
+    installed_packages: HashMap<ContextServerId, InstalledPackage>,
+    /// Servers that aren't run because their sandbox can't be enforced on this platform, with the
+    /// reason.
+    invalid_sandboxes: HashMap<ContextServerId, Arc<str>>,
+    /// Whether servers only run once the user trusts how they're launched.
+    require_server_trust: bool,
+    /// The launch digests the user trusted for each server.
+    trusted_servers: HashMap<ContextServerId, Arc<str>>,
+    untrusted_servers: HashMap<ContextServerId, UntrustedContextServer>,
+    /// Trace files turned on or off while Zed is running, which take precedence over the
+    /// servers' `trace_file` settings.
+    trace_file_overrides: HashMap<ContextServerId, Option<PathBuf>>,
+    /// The servers that may run, as chosen by the agent's profile, or `None` for all of them.
+    active_servers: Option<HashSet<Arc<str>>>,
+    /// Servers that aren't running because they aren't active, which are started once they are.
+    inactive_servers: HashSet<ContextServerId>,
+    /// Servers the user turned off for the project, which don't run whatever the profile says.
+    disabled_servers: HashSet<ContextServerId>,
+    /// Whether the disabled servers are remembered for the project across restarts.
+    remember_disabled_servers: bool,
+    /// The key the disabled servers were loaded from, which they're saved under.
+    disabled_servers_key: Option<String>,
+    /// Servers attached to a single agent thread, which aren't saved to the settings.
+    temporary_servers: HashMap<ContextServerId, ContextServerConfiguration>,
+    /// The built-in servers that run without being configured.
+    builtin_servers: &'static [BuiltinContextServer],
+    /// Servers backed by an HTTP API that couldn't be reached the last time they were called.
+    offline_servers: HashSet<ContextServerId>,
+    /// Watch whether the APIs of servers backed by one can be reached.
+    network_status_tasks: HashMap<ContextServerId, Task<()>>,
+    /// Servers that aren't run because another server is configured to launch the same thing,
+    /// and the servers that run in their place.
+    duplicate_servers: HashMap<ContextServerId, ContextServerId>,
+    /// The calls reported running that haven't finished yet, which tools report their progress
+    /// under.
+    running_tool_calls: HashSet<Arc<str>>,
+    /// Where to send the user's request to cancel a running tool call.
+    tool_call_cancellations: HashMap<Arc<str>, oneshot::Sender<()>>,
+    /// Servers waiting for their tool calls to finish before they're stopped, which maintenance
+    /// leaves alone until they are.
+    draining_servers: HashMap<ContextServerId, Task<()>>,
+    /// The worktree paths that went into launching each server whose launch depends on them.
+    launch_worktrees: HashMap<ContextServerId, Vec<PathBuf>>,
     _subscriptions: Vec<Subscription>,
 }
 
@@ -99,17 +726,79 @@ pub enum Event {
         server_id: ContextServerId,
         status: ContextServerStatus,
     },
+    /// A worktree declares context servers that the user hasn't trusted yet.
+    UntrustedProjectServers { worktree_id: WorktreeId },
+    /// Servers appeared, or changed how they're launched, and won't run until the user trusts
+    /// them.
+    UntrustedServers,
+    /// A newer version of a server's package than the installed one is available.
+    PackageUpdateAvailable {
+        server_id: ContextServerId,
+        version: String,
+    },
+    /// The API of a server backed by one couldn't be reached, or can be again.
+    ServerOfflineChanged {
+        server_id: ContextServerId,
+        offline: bool,
+    },
+    /// A call to one of a server's tools moved on to another step.
+    ToolCall {
+        server_id: ContextServerId,
+        tool_name: Arc<str>,
+        /// Identifies the call among the others to the same tool.
+        call_id: Arc<str>,
+        event: ToolCallEvent,
+    },
+    /// A server was renamed in the settings, which is a server going away and another one that
+    /// launches the same thing appearing in its place.
+    ServerRenamed {
+        old_id: ContextServerId,
+        new_id: ContextServerId,
+    },
+}
+
+/// A step in the life of a call to a server's tool, which the agent reports so that the UI and
+/// telemetry can follow tool activity.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolCallEvent {
+    /// The model asked for the call.
+    Requested,
+    /// The call waits for the user to allow it.
+    AwaitingConfirmation,
+    /// The call is running, and the server reported this progress on it, if any.
+    Running {
+        progress: Option<String>,
+    },
+    /// The call returned a result that's `bytes` long.
+    Finished {
+        duration: Duration,
+        bytes: usize,
+    },
+    Failed {
+        error: String,
+    },
 }
 
 impl EventEmitter<Event> for ContextServerStore {}
 
 impl ContextServerStore {
-    pub fn new(worktree_store: Entity<WorktreeStore>, cx: &mut Context<Self>) -> Self {
+    pub fn new(
+        worktree_store: Entity<WorktreeStore>,
+        environment: Entity<ProjectEnvironment>,
+        node: Option<NodeRuntime>,
+        cx: &mut Context<Self>,
+    ) -> Self {
         Self::new_internal(
             true,
+            true,
+            true,
+            SETTINGS_UPDATE_DEBOUNCE,
+            BuiltinContextServer::DEFAULT,
             None,
             ContextServerDescriptorRegistry::default_global(cx),
             worktree_store,
+            environment,
+            node,
             cx,
         )
     }
@@ -120,7 +809,20 @@ impl ContextServerStore {
         worktree_store: Entity<WorktreeStore>,
         cx: &mut Context<Self>,
     ) -> Self {
-        Self::new_internal(false, None, registry, worktree_store, cx)
+        let environment = cx.new(|_| ProjectEnvironment::new(None));
+        Self::new_internal(
+            false,
+            false,
+            false,
+            Duration::ZERO,
+            &[],
+            None,
+            registry,
+            worktree_store,
+            environment,
+            None,
+            cx,
+        )
     }
 
     #[cfg(any(test, feature = "test-support"))]
@@ -130,20 +832,33 @@ impl ContextServerStore {
         worktree_store: Entity<WorktreeStore>,
         cx: &mut Context<Self>,
     ) -> Self {
+        let environment = cx.new(|_| ProjectEnvironment::new(None));
         Self::new_internal(
             true,
+            false,
+            false,
+            Duration::ZERO,
+            &[],
             Some(context_server_factory),
             registry,
             worktree_store,
+            environment,
+            None,
             cx,
         )
     }
 
     fn new_internal(
         maintain_server_loop: bool,
+        require_server_trust: bool,
+        remember_disabled_servers: bool,
+        settings_update_debounce: Duration,
+        builtin_servers: &'static [BuiltinContextServer],
         context_server_factory: Option<ContextServerFactory>,
         registry: Entity<ContextServerDescriptorRegistry>,
         worktree_store: Entity<WorktreeStore>,
+        environment: Entity<ProjectEnvironment>,
+        node: Option<NodeRuntime>,
         cx: &mut Context<Self>,
     ) -> Self {
         let subscriptions = if maintain_server_loop {
@@ -152,638 +867,3405 @@ impl ContextServerStore {
                     this.available_context_servers_changed(cx);
                 }),
                 cx.observe_global::<SettingsStore>(|this, cx| {
-                    this.available_context_servers_changed(cx);
+                    let configured_servers = this.configured_servers(cx);
+                    if configured_servers != this.configured_servers {
+                        this.configured_servers = configured_servers;
+                        this.settings_changed(cx);
+                    }
                 }),
+                cx.subscribe(&worktree_store, Self::on_worktree_store_event),
             ]
         } else {
             Vec::new()
         };
+        // Closing a project keeps the servers that are kept warm running, for the next project
+        // that uses them.
+        cx.on_release(|this, cx| {
+            for (id, state) in &this.servers {
+                if let ContextServerState::Running {
+                    server,
+                    configuration,
+                } = state
+                {
+                    if let Some((key, keep_warm)) = this.pool_key(id, configuration, cx) {
+                        ContextServerPool::park(key, server.clone(), keep_warm, cx);
+                    }
+                }
+            }
+        })
+        .detach();
 
         let mut this = Self {
             _subscriptions: subscriptions,
             worktree_store,
+            environment,
+            node,
             registry,
             needs_server_update: false,
             servers: HashMap::default(),
+            running_server_listeners: HashMap::default(),
+            egress_proxies: HashMap::default(),
+            project_servers: HashMap::default(),
+            package_installs: HashMap::default(),
+            installed_packages: HashMap::default(),
+            invalid_sandboxes: HashMap::default(),
+            require_server_trust,
+            trusted_servers: HashMap::default(),
+            untrusted_servers: HashMap::default(),
+            trace_file_overrides: HashMap::default(),
+            active_servers: None,
+            inactive_servers: HashSet::default(),
+            disabled_servers: HashSet::default(),
+            remember_disabled_servers,
+            disabled_servers_key: None,
+            temporary_servers: HashMap::default(),
+            builtin_servers,
+            offline_servers: HashSet::default(),
+            network_status_tasks: HashMap::default(),
+            duplicate_servers: HashMap::default(),
+            running_tool_calls: HashSet::default(),
+            tool_call_cancellations: HashMap::default(),
+            draining_servers: HashMap::default(),
+            launch_worktrees: HashMap::default(),
             update_servers_task: None,
+            settings_update_debounce,
+            settings_update_task: None,
+            configured_servers: HashMap::default(),
             context_server_factory,
         };
+        this.configured_servers = this.configured_servers(cx);
         if maintain_server_loop {
-            this.available_context_servers_changed(cx);
+            let worktrees = this
+                .worktree_store
+                .read(cx)
+                .visible_worktrees(cx)
+                .collect::<Vec<_>>();
+            let has_worktrees = !worktrees.is_empty();
+            for worktree in worktrees {
+                this.load_project_servers(worktree, cx);
+            }
+            // A project's folders are added right after it's created, and the servers the user
+            // turned off for it are only known once they are, so give them a moment to be.
+            if has_worktrees || !remember_disabled_servers {
+                this.available_context_servers_changed(cx);
+            } else {
+                this.settings_changed(cx);
+            }
         }
         this
     }
 
-    pub fn get_server(&self, id: &ContextServerId) -> Option<Arc<ContextServer>> {
-        self.servers.get(id).map(|state| state.server())
+    /// Returns the context servers declared by the given worktree, if any.
+    pub fn project_servers(&self, worktree_id: WorktreeId) -> Option<&ProjectContextServers> {
+        self.project_servers.get(&worktree_id)
     }
 
-    pub fn get_running_server(&self, id: &ContextServerId) -> Option<Arc<ContextServer>> {
-        if let Some(ContextServerState::Running { server, .. }) = self.servers.get(id) {
-            Some(server.clone())
-        } else {
-            None
-        }
+    /// Returns the worktrees that declare context servers the user hasn't trusted yet.
+    pub fn untrusted_project_servers(&self) -> Vec<WorktreeId> {
+        self.project_servers
+            .iter()
+            .filter(|(_, servers)| !servers.trusted)
+            .map(|(worktree_id, _)| *worktree_id)
+            .collect()
     }
 
-    pub fn status_for_server(&self, id: &ContextServerId) -> Option<ContextServerStatus> {
-        self.servers.get(id).map(ContextServerStatus::from_state)
+    /// Runs the context servers declared by the given worktree, if its file still has the
+    /// contents the user reviewed.
+    pub fn trust_project_servers(
+        &mut self,
+        worktree_id: WorktreeId,
+        digest: &str,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(servers) = self.project_servers.get_mut(&worktree_id) {
+            if servers.digest.as_ref() == digest && !servers.trusted {
+                servers.trusted = true;
+                self.available_context_servers_changed(cx);
+            }
+        }
     }
 
-    pub fn all_server_ids(&self) -> Vec<ContextServerId> {
-        self.servers.keys().cloned().collect()
+    /// Returns the servers that won't run until the user trusts how they're launched.
+    pub fn untrusted_servers(&self) -> &HashMap<ContextServerId, UntrustedContextServer> {
+        &self.untrusted_servers
     }
 
-    pub fn running_servers(&self) -> Vec<Arc<ContextServer>> {
-        self.servers
-            .values()
-            .filter_map(|state| {
-                if let ContextServerState::Running { server, .. } = state {
-                    Some(server.clone())
-                } else {
-                    None
-                }
-            })
-            .collect()
+    /// Runs the server, if it's still launched the way the user reviewed.
+    pub fn trust_server(&mut self, id: &ContextServerId, digest: &str, cx: &mut Context<Self>) {
+        if self
+            .untrusted_servers
+            .get(id)
+            .is_some_and(|server| server.digest.as_ref() == digest)
+        {
+            self.trusted_servers.insert(id.clone(), digest.into());
+            self.available_context_servers_changed(cx);
+        }
     }
 
-    pub fn start_server(
+    /// Removes the servers the user hasn't trusted to run as they're configured, and lets the
+    /// user know about the ones that are new.
+    fn retain_trusted_servers(
         &mut self,
-        server: Arc<ContextServer>,
+        servers: &mut HashMap<Arc<str>, ContextServerConfiguration>,
+        reviewed_servers: &HashSet<Arc<str>>,
         cx: &mut Context<Self>,
-    ) -> Result<()> {
-        let location = self
-            .worktree_store
-            .read(cx)
-            .visible_worktrees(cx)
-            .next()
-            .map(|worktree| settings::SettingsLocation {
-                worktree_id: worktree.read(cx).id(),
-                path: Path::new(""),
-            });
-        let settings = ProjectSettings::get(location, cx);
-        let configuration = settings
-            .context_servers
-            .get(&server.id().0)
-            .context("Failed to load context server configuration from settings")?
-            .clone();
-
-        self.run_server(server, Arc::new(configuration), cx);
-        Ok(())
+    ) {
+        if !self.require_server_trust {
+            return;
+        }
+        let mut untrusted_servers = HashMap::default();
+        servers.retain(|id, configuration| {
+            if reviewed_servers.contains(id) {
+                return true;
+            }
+            let id = ContextServerId(id.clone());
+            let digest = trust_digest(configuration);
+            if self.trusted_servers.get(&id) == Some(&digest) {
+                return true;
+            }
+            let summary = launch_summary(configuration);
+            untrusted_servers.insert(id, UntrustedContextServer { digest, summary });
+            false
+        });
+        let has_new_servers = untrusted_servers
+            .iter()
+            .any(|(id, server)| self.untrusted_servers.get(id) != Some(server));
+        self.untrusted_servers = untrusted_servers;
+        if has_new_servers {
+            cx.emit(Event::UntrustedServers);
+        }
     }
 
-    pub fn stop_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) -> Result<()> {
-        let state = self
-            .servers
-            .remove(id)
-            .context("Context server not found")?;
+    fn on_worktree_store_event(
+        &mut self,
+        _: Entity<WorktreeStore>,
+        event: &WorktreeStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            WorktreeStoreEvent::WorktreeAdded(worktree) => {
+                self.load_project_servers(worktree.clone(), cx);
+                if self.disabled_servers_key(cx) != self.disabled_servers_key
+                    || self.launch_worktrees_changed(cx)
+                {
+                    self.available_context_servers_changed(cx);
+                }
+            }
+            WorktreeStoreEvent::WorktreeRemoved(_, worktree_id) => {
+                if self.project_servers.remove(worktree_id).is_some()
+                    || self.disabled_servers_key(cx) != self.disabled_servers_key
+                    || self.launch_worktrees_changed(cx)
+                {
+                    self.available_context_servers_changed(cx);
+                }
+            }
+            WorktreeStoreEvent::WorktreeUpdatedEntries(worktree_id, changes) => {
+                let changed = changes.iter().any(|(path, _, _)| {
+                    PROJECT_SERVERS_FILES
+                        .iter()
+                        .any(|file| path.as_ref() == Path::new(file))
+                });
+                if changed {
+                    if let Some(worktree) = self
+                        .worktree_store
+                        .read(cx)
+                        .worktree_for_id(*worktree_id, cx)
+                    {
+                        self.load_project_servers(worktree, cx);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 
-        let server = state.server();
-        let configuration = state.configuration();
-        let mut result = Ok(());
-        if let ContextServerState::Running { server, .. } = &state {
-            result = server.stop();
+    fn load_project_servers(&mut self, worktree: Entity<Worktree>, cx: &mut Context<Self>) {
+        if !worktree.read(cx).is_visible() {
+            return;
         }
-        drop(state);
+        let worktree_id = worktree.read(cx).id();
+        let loads = PROJECT_SERVERS_FILES.map(|file| {
+            worktree.update(cx, |worktree, cx| worktree.load_file(Path::new(file), cx))
+        });
+        let worktree_root = worktree.read(cx).abs_path();
+        cx.spawn(async move |this, cx| {
+            let mut loaded = None;
+            for (file, load) in PROJECT_SERVERS_FILES.iter().zip(loads) {
+                if let Ok(file_contents) = load.await {
+                    loaded = Some((worktree_root.join(file), file_contents.text));
+                    break;
+                }
+            }
 
-        self.update_server_state(
-            id.clone(),
-            ContextServerState::Stopped {
-                configuration,
-                server,
-                error: None,
-            },
-            cx,
-        );
+            let project_servers = match loaded {
+                Some((abs_path, content)) => match parse_project_servers(&content) {
+                    Ok(servers) if !servers.is_empty() => Some(ProjectContextServers {
+                        abs_path: abs_path.into(),
+                        servers,
+                        digest: format!("{:x}", Sha256::digest(content.as_bytes())).into(),
+                        trusted: false,
+                    }),
+                    Ok(_) => None,
+                    Err(error) => {
+                        log::error!("failed to parse {abs_path:?}: {error}");
+                        None
+                    }
+                },
+                None => None,
+            };
 
-        result
+            this.update(cx, |this, cx| {
+                let previous = match project_servers {
+                    Some(mut project_servers) => {
+                        let previous = this.project_servers.get(&worktree_id);
+                        project_servers.trusted = previous.is_some_and(|previous| {
+                            previous.trusted && previous.digest == project_servers.digest
+                        });
+                        let trusted = project_servers.trusted;
+                        let previous = this.project_servers.insert(worktree_id, project_servers);
+                        if !trusted {
+                            cx.emit(Event::UntrustedProjectServers { worktree_id });
+                        }
+                        previous
+                    }
+                    None => this.project_servers.remove(&worktree_id),
+                };
+                if previous.is_some_and(|previous| previous.trusted) {
+                    this.available_context_servers_changed(cx);
+                }
+            })
+        })
+        .detach_and_log_err(cx);
     }
 
-    pub fn restart_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) -> Result<()> {
-        if let Some(state) = self.servers.get(&id) {
-            let configuration = state.configuration();
+    pub fn get_server(&self, id: &ContextServerId) -> Option<Arc<ContextServer>> {
+        self.servers.get(id).map(|state| state.server())
+    }
 
-            self.stop_server(&state.server().id(), cx)?;
-            let new_server = self.create_context_server(id.clone(), configuration.clone())?;
-            self.run_server(new_server, configuration, cx);
+    pub fn configuration_for_server(
+        &self,
+        id: &ContextServerId,
+    ) -> Option<Arc<ContextServerConfiguration>> {
+        self.servers.get(id).map(|state| state.configuration())
+    }
+
+    /// Returns the directory the server resolves relative paths against, which is the one its
+    /// command starts in, or Zed's own working directory for commands that don't set one.
+    pub fn server_working_directory(&self, id: &ContextServerId, cx: &App) -> Option<PathBuf> {
+        let configuration = self.configuration_for_server(id)?;
+        if configuration.builtin == Some(BuiltinContextServer::Filesystem) {
+            return self.worktree_root(cx).map(|root| root.to_path_buf());
+        }
+        let command = configuration.command.as_ref().or_else(|| {
+            self.installed_packages
+                .get(id)
+                .map(|package| &package.command)
+        });
+        match command.map(|command| command.working_directory(self.worktree_root(cx).as_deref())) {
+            Some(Ok(Some(directory))) => Some(directory),
+            Some(Err(_)) => None,
+            Some(Ok(None)) | None => std::env::current_dir().ok(),
         }
-        Ok(())
     }
 
-    fn run_server(
-        &mut self,
-        server: Arc<ContextServer>,
-        configuration: Arc<ContextServerConfiguration>,
-        cx: &mut Context<Self>,
-    ) {
-        let id = server.id();
-        if matches!(
-            self.servers.get(&id),
-            Some(ContextServerState::Starting { .. } | ContextServerState::Running { .. })
-        ) {
-            self.stop_server(&id, cx).log_err();
-        }
-
-        let task = cx.spawn({
-            let id = server.id();
-            let server = server.clone();
-            let configuration = configuration.clone();
-            async move |this, cx| {
-                match server.clone().start(&cx).await {
-                    Ok(_) => {
-                        log::info!("Started {} context server", id);
-                        debug_assert!(server.client().is_some());
-
-                        this.update(cx, |this, cx| {
-                            this.update_server_state(
-                                id.clone(),
-                                ContextServerState::Running {
-                                    server,
-                                    configuration,
-                                },
-                                cx,
-                            )
-                        })
-                        .log_err()
-                    }
-                    Err(err) => {
-                        log::error!("{} context server failed to start: {}", id, err);
-                        this.update(cx, |this, cx| {
-                            this.update_server_state(
-                                id.clone(),
-                                ContextServerState::Stopped {
-                                    configuration,
-                                    server,
-                                    error: Some(err.to_string().into()),
-                                },
-                                cx,
-                            )
-                        })
-                        .log_err()
-                    }
-                };
-            }
-        });
-
-        self.update_server_state(
-            id.clone(),
-            ContextServerState::Starting {
-                configuration,
-                _task: task,
-                server,
-            },
-            cx,
-        );
-    }
-
-    fn remove_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) -> Result<()> {
-        let state = self
-            .servers
-            .remove(id)
-            .context("Context server not found")?;
-        drop(state);
-        cx.emit(Event::ServerStatusChanged {
-            server_id: id.clone(),
-            status: ContextServerStatus::Stopped,
-        });
-        Ok(())
-    }
-
-    fn is_configuration_valid(&self, configuration: &ContextServerConfiguration) -> bool {
-        // Command must be some when we are running in stdio mode.
-        self.context_server_factory.as_ref().is_some() || configuration.command.is_some()
+    /// Returns what identifies the server across renames, which is a digest of what it runs or
+    /// connects to, or `None` for servers that an extension or Zed provides under a fixed id.
+    pub fn server_identity(&self, id: &ContextServerId) -> Option<Arc<str>> {
+        let configuration = self.configuration_for_server(id)?;
+        has_launch(&configuration).then(|| launch_digest(&configuration))
     }
 
-    fn create_context_server(
-        &self,
-        id: ContextServerId,
-        configuration: Arc<ContextServerConfiguration>,
-    ) -> Result<Arc<ContextServer>> {
-        if let Some(factory) = self.context_server_factory.as_ref() {
-            Ok(factory(id, configuration))
+    pub fn get_running_server(&self, id: &ContextServerId) -> Option<Arc<ContextServer>> {
+        if let Some(ContextServerState::Running { server, .. }) = self.servers.get(id) {
+            Some(server.clone())
         } else {
-            let command = configuration
-                .command
-                .clone()
-                .context("Missing command to run context server")?;
-            Ok(Arc::new(ContextServer::stdio(id, command)))
+            None
         }
     }
 
-    fn update_server_state(
+    /// Lets whoever follows tool activity know that a call to one of the server's tools moved
+    /// on to another step.
+    pub fn report_tool_call(
         &mut self,
-        id: ContextServerId,
-        state: ContextServerState,
+        server_id: &ContextServerId,
+        tool_name: Arc<str>,
+        call_id: Arc<str>,
+        event: ToolCallEvent,
         cx: &mut Context<Self>,
     ) {
-        let status = ContextServerStatus::from_state(&state);
-        self.servers.insert(id.clone(), state);
-        cx.emit(Event::ServerStatusChanged {
-            server_id: id,
-            status,
+        match &event {
+            ToolCallEvent::Running { progress: None } => {
+                self.running_tool_calls.insert(call_id.clone());
+            }
+            ToolCallEvent::Finished { .. } | ToolCallEvent::Failed { .. } => {
+                self.running_tool_calls.remove(&call_id);
+                self.tool_call_cancellations.remove(&call_id);
+            }
+            ToolCallEvent::Requested
+            | ToolCallEvent::AwaitingConfirmation
+            | ToolCallEvent::Running { .. } => {}
+        }
+        cx.emit(Event::ToolCall {
+            server_id: server_id.clone(),
+            tool_name,
+            call_id,
+            event,
         });
     }
 
-    fn available_context_servers_changed(&mut self, cx: &mut Context<Self>) {
-        if self.update_servers_task.is_some() {
-            self.needs_server_update = true;
-        } else {
-            self.needs_server_update = false;
-            self.update_servers_task = Some(cx.spawn(async move |this, cx| {
-                if let Err(err) = Self::maintain_servers(this.clone(), cx).await {
-                    log::error!("Error maintaining context servers: {}", err);
-                }
+    /// Returns whether the call was reported running and hasn't been reported finished, so that
+    /// whoever follows tool activity knows about it.
+    pub fn is_tool_call_running(&self, call_id: &str) -> bool {
+        self.running_tool_calls.contains(call_id)
+    }
 
-                this.update(cx, |this, cx| {
-                    this.update_servers_task.take();
-                    if this.needs_server_update {
-                        this.available_context_servers_changed(cx);
-                    }
-                })?;
+    /// Returns a receiver that's notified when the user cancels the tool call.
+    pub fn tool_call_cancellation(&mut self, call_id: Arc<str>) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tool_call_cancellations.insert(call_id, tx);
+        rx
+    }
 
-                Ok(())
-            }));
+    /// Cancels a running tool call, if the tool that runs it can be cancelled.
+    pub fn cancel_tool_call(&mut self, call_id: &str) {
+        if let Some(tx) = self.tool_call_cancellations.remove(call_id) {
+            tx.send(()).ok();
         }
     }
 
-    async fn maintain_servers(this: WeakEntity<Self>, cx: &mut AsyncApp) -> Result<()> {
-        let mut desired_servers = HashMap::default();
-
-        let (registry, worktree_store) = this.update(cx, |this, cx| {
-            let location = this
-                .worktree_store
-                .read(cx)
-                .visible_worktrees(cx)
-                .next()
-                .map(|worktree| settings::SettingsLocation {
-                    worktree_id: worktree.read(cx).id(),
-                    path: Path::new(""),
-                });
-            let settings = ProjectSettings::get(location, cx);
-            desired_servers = settings.context_servers.clone();
+    /// Returns the features the server and Zed agreed on, once the server is running.
+    pub fn capabilities(&self, id: &ContextServerId) -> Option<ContextServerCapabilities> {
+        Some(self.get_running_server(id)?.client()?.capabilities())
+    }
 
-            (this.registry.clone(), this.worktree_store.clone())
-        })?;
+    /// Returns the server once it is running. If the server is still starting, this waits
+    /// up to `timeout` for it to finish initializing.
+    pub fn wait_for_running_server(
+        &mut self,
+        id: &ContextServerId,
+        timeout: Duration,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Arc<ContextServer>>> {
+        match self.servers.get(id) {
+            Some(ContextServerState::Running { server, .. }) => Task::ready(Ok(server.clone())),
+            Some(ContextServerState::Starting { .. }) => {
+                let (tx, rx) = oneshot::channel();
+                let listeners = self.running_server_listeners.entry(id.clone()).or_default();
+                // Drop the listeners of callers that timed out or stopped waiting.
+                listeners.retain(|listener| !listener.is_canceled());
+                listeners.push(tx);
 
-        for (id, descriptor) in
-            registry.read_with(cx, |registry, _| registry.context_server_descriptors())?
-        {
-            let config = desired_servers.entry(id.clone()).or_default();
-            if config.command.is_none() {
-                if let Some(extension_command) = descriptor
-                    .command(worktree_store.clone(), &cx)
-                    .await
-                    .log_err()
-                {
-                    config.command = Some(extension_command);
-                }
+                let id = id.clone();
+                let timer = cx.background_executor().timer(timeout);
+                cx.background_spawn(async move {
+                    select_biased! {
+                        result = rx.fuse() => result.unwrap_or_else(|_| {
+                            Err(anyhow!("Context server {id} was stopped"))
+                        }),
+                        _ = timer.fuse() => {
+                            Err(anyhow!("Timed out waiting for context server {id} to start"))
+                        }
+                    }
+                })
+            }
+            Some(ContextServerState::Stopped { .. }) | None => {
+                Task::ready(Err(anyhow!("Context server not found")))
             }
         }
+    }
 
-        this.update(cx, |this, _| {
-            // Filter out configurations without commands, the user uninstalled an extension.
-            desired_servers.retain(|_, configuration| this.is_configuration_valid(configuration));
-        })?;
-
-        let mut servers_to_start = Vec::new();
-        let mut servers_to_remove = HashSet::default();
-        let mut servers_to_stop = HashSet::default();
+    pub fn status_for_server(&self, id: &ContextServerId) -> Option<ContextServerStatus> {
+        if let Some(error) = self.invalid_sandboxes.get(id) {
+            return Some(ContextServerStatus::Error(error.clone()));
+        }
+        self.package_installs
+            .get(id)
+            .cloned()
+            .or_else(|| self.servers.get(id).map(ContextServerStatus::from_state))
+    }
 
-        this.update(cx, |this, _cx| {
-            for server_id in this.servers.keys() {
-                // All servers that are not in desired_servers should be removed from the store.
-                // E.g. this can happen if the user removed a server from the configuration,
-                // or the user uninstalled an extension.
-                if !desired_servers.contains_key(&server_id.0) {
-                    servers_to_remove.insert(server_id.clone());
-                }
-            }
+    /// Returns whether the server is backed by an HTTP API that couldn't be reached the last time
+    /// it was called. Calls to its tools are answered as offline until it can be again.
+    pub fn is_server_offline(&self, id: &ContextServerId) -> bool {
+        self.offline_servers.contains(id)
+    }
 
-            for (id, config) in desired_servers {
-                let id = ContextServerId(id.clone());
+    pub fn offline_servers(&self) -> impl Iterator<Item = &ContextServerId> {
+        self.offline_servers.iter()
+    }
 
-                let existing_config = this.servers.get(&id).map(|state| state.configuration());
-                if existing_config.as_deref() != Some(&config) {
-                    let config = Arc::new(config);
-                    if let Some(server) = this
-                        .create_context_server(id.clone(), config.clone())
-                        .log_err()
-                    {
-                        servers_to_start.push((server, config));
-                        if this.servers.contains_key(&id) {
-                            servers_to_stop.insert(id);
+    fn watch_network_status(
+        &mut self,
+        id: ContextServerId,
+        mut online: watch::Receiver<bool>,
+        cx: &mut Context<Self>,
+    ) {
+        let task = cx.spawn({
+            let id = id.clone();
+            async move |this, cx| {
+                while let Some(online) = online.recv().await {
+                    let updated = this.update(cx, |this, cx| {
+                        let changed = if online {
+                            this.offline_servers.remove(&id)
+                        } else {
+                            this.offline_servers.insert(id.clone())
+                        };
+                        if changed {
+                            cx.emit(Event::ServerOfflineChanged {
+                                server_id: id.clone(),
+                                offline: !online,
+                            });
+                            cx.notify();
                         }
+                    });
+                    if updated.is_err() {
+                        break;
                     }
                 }
             }
-        })?;
+        });
+        self.network_status_tasks.insert(id, task);
+    }
 
-        for id in servers_to_stop {
-            this.update(cx, |this, cx| this.stop_server(&id, cx).ok())?;
-        }
+    pub fn all_server_ids(&self) -> Vec<ContextServerId> {
+        self.servers
+            .keys()
+            .chain(
+                self.package_installs
+                    .keys()
+                    .filter(|id| !self.servers.contains_key(id)),
+            )
+            .cloned()
+            .collect()
+    }
 
-        for id in servers_to_remove {
-            this.update(cx, |this, cx| this.remove_server(&id, cx).ok())?;
-        }
+    pub fn running_servers(&self) -> Vec<Arc<ContextServer>> {
+        self.servers
+            .values()
+            .filter_map(|state| {
+                if let ContextServerState::Running { server, .. } = state {
+                    Some(server.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
 
-        for (server, config) in servers_to_start {
-            this.update(cx, |this, cx| this.run_server(server, config, cx))
-                .log_err();
-        }
+    pub fn start_server(
+        &mut self,
+        server: Arc<ContextServer>,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        let configuration = self
+            .configured_servers(cx)
+            .remove(&server.id().0)
+            .context("Failed to load context server configuration from settings")?;
 
+        self.run_server(server, Arc::new(configuration), cx);
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{FakeFs, Project, project_settings::ProjectSettings};
-    use context_server::{
-        transport::Transport,
-        types::{
-            self, Implementation, InitializeResponse, ProtocolVersion, RequestType,
-            ServerCapabilities,
-        },
-    };
-    use futures::{Stream, StreamExt as _, lock::Mutex};
-    use gpui::{AppContext, BackgroundExecutor, TestAppContext, UpdateGlobal as _};
-    use serde_json::json;
-    use std::{cell::RefCell, pin::Pin, rc::Rc};
-    use util::path;
+    /// Returns the package installed for the server, if it runs one.
+    pub fn installed_package(&self, id: &ContextServerId) -> Option<&InstalledPackage> {
+        self.installed_packages.get(id)
+    }
 
-    #[gpui::test]
-    async fn test_context_server_status(cx: &mut TestAppContext) {
-        const SERVER_1_ID: &'static str = "mcp-1";
-        const SERVER_2_ID: &'static str = "mcp-2";
+    /// Updates the server's package to its latest version and restarts the server.
+    pub fn update_package(
+        &mut self,
+        id: &ContextServerId,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let Some(package) = self
+            .servers
+            .get(id)
+            .and_then(|state| state.configuration().package.clone())
+        else {
+            return Task::ready(Err(anyhow!("context server {id} doesn't run a package")));
+        };
+        self.package_installs
+            .insert(id.clone(), ContextServerStatus::Installing);
+        cx.emit(Event::ServerStatusChanged {
+            server_id: id.clone(),
+            status: ContextServerStatus::Installing,
+        });
 
-        let (_fs, project) = setup_context_server_test(
-            cx,
-            json!({"code.rs": ""}),
-            vec![
-                (SERVER_1_ID.into(), ContextServerConfiguration::default()),
-                (SERVER_2_ID.into(), ContextServerConfiguration::default()),
-            ],
-        )
-        .await;
+        let id = id.clone();
+        let node = self.node.clone();
+        cx.spawn(async move |this, cx| {
+            let installed = package::install_package(&id, &package, true, node).await;
+            this.update(cx, |this, cx| {
+                this.package_installed(id.clone(), installed, cx)?;
+                this.restart_server(&id, cx)
+            })?
+        })
+    }
 
+    fn package_installed(
+        &mut self,
+        id: ContextServerId,
+        installed: Result<InstalledPackage>,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        self.package_installs.remove(&id);
+        match installed {
+            Ok(installed) => {
+                if let Some(version) = installed.latest_version.clone() {
+                    cx.emit(Event::PackageUpdateAvailable {
+                        server_id: id.clone(),
+                        version,
+                    });
+                }
+                self.installed_packages.insert(id, installed);
+                Ok(())
+            }
+            Err(error) => {
+                let error = error.context(format!("installing the package of context server {id}"));
+                let status = ContextServerStatus::Error(format!("{error:#}").into());
+                self.package_installs.insert(id.clone(), status.clone());
+                cx.emit(Event::ServerStatusChanged {
+                    server_id: id,
+                    status,
+                });
+                Err(error)
+            }
+        }
+    }
+
+    pub fn stop_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) -> Result<()> {
+        let state = self
+            .servers
+            .remove(id)
+            .context("Context server not found")?;
+
+        let server = state.server();
+        let configuration = state.configuration();
+        let mut result = Ok(());
+        if let ContextServerState::Running { server, .. } = &state {
+            result = server.stop();
+        }
+        drop(state);
+
+        self.update_server_state(
+            id.clone(),
+            ContextServerState::Stopped {
+                configuration,
+                server,
+                error: None,
+            },
+            cx,
+        );
+
+        result
+    }
+
+    /// Stops using a server, leaving it running in the pool when it's kept warm.
+    fn park_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) -> Result<()> {
+        let Some(ContextServerState::Running {
+            server,
+            configuration,
+        }) = self.servers.get(id)
+        else {
+            return self.stop_server(id, cx);
+        };
+        let Some((key, keep_warm)) = self.pool_key(id, configuration, cx) else {
+            return self.stop_server(id, cx);
+        };
+        let server = server.clone();
+        let configuration = configuration.clone();
+        ContextServerPool::park(key, server.clone(), keep_warm, cx);
+        self.update_server_state(
+            id.clone(),
+            ContextServerState::Stopped {
+                server,
+                configuration,
+                error: None,
+            },
+            cx,
+        );
+        Ok(())
+    }
+
+    /// Returns the key a server is pooled under once it's no longer used, and how long it's kept
+    /// running there, for servers that are kept warm.
+    fn pool_key(
+        &self,
+        id: &ContextServerId,
+        configuration: &ContextServerConfiguration,
+        cx: &App,
+    ) -> Option<(Arc<str>, Duration)> {
+        let keep_warm = Duration::from_secs(configuration.keep_warm_seconds?);
+        // Sandboxed servers depend on the project's worktrees and egress proxy, and the others
+        // don't run a process.
+        if configuration.sandbox.is_some()
+            || configuration.replay.is_some()
+            || configuration.openapi.is_some()
+            || configuration.builtin.is_some()
+        {
+            return None;
+        }
+        let working_directory = configuration.command.as_ref().and_then(|command| {
+            command
+                .working_directory(self.worktree_root(cx).as_deref())
+                .ok()
+                .flatten()
+        });
+        // Servers confined to the project's worktrees can only be reused by the same project.
+        let worktree_roots = (!configuration.features.resources_outside_worktrees).then(|| {
+            self.worktree_store
+                .read(cx)
+                .visible_worktrees(cx)
+                .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+                .collect::<Vec<_>>()
+        });
+        let launch = serde_json::json!([id.0, configuration, working_directory, worktree_roots]);
+        let key = format!("{:x}", Sha256::digest(launch.to_string().as_bytes()));
+        Some((key.into(), keep_warm))
+    }
+
+    /// Takes a server that's kept warm and was launched the way `configuration` would launch it,
+    /// if the pool has one.
+    fn take_pooled_server(
+        &self,
+        id: &ContextServerId,
+        configuration: &ContextServerConfiguration,
+        cx: &mut App,
+    ) -> Option<Arc<ContextServer>> {
+        let (key, _) = self.pool_key(id, configuration, cx)?;
+        let server = ContextServerPool::take(&key, cx)?;
+        log::info!("reusing context server {id}, which was kept warm");
+        server
+            .set_trace_file(self.trace_file(id, configuration, cx))
+            .log_err();
+        Some(server)
+    }
+
+    pub fn restart_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) -> Result<()> {
+        if let Some(state) = self.servers.get(&id) {
+            let configuration = state.configuration();
+
+            self.stop_server(&state.server().id(), cx)?;
+            let new_server = self.create_context_server(id.clone(), configuration.clone(), cx)?;
+            self.run_server(new_server, configuration, cx);
+        }
+        Ok(())
+    }
+
+    /// Only runs the servers in `active_servers`, stopping the others, or runs all of them when
+    /// it's `None`.
+    pub fn set_active_servers(
+        &mut self,
+        active_servers: Option<HashSet<Arc<str>>>,
+        cx: &mut Context<Self>,
+    ) {
+        if self.active_servers != active_servers {
+            self.active_servers = active_servers;
+            self.available_context_servers_changed(cx);
+        }
+    }
+
+    /// Stops the server and keeps it from running until it's enabled again, or lets it run again.
+    pub fn set_server_enabled(
+        &mut self,
+        id: &ContextServerId,
+        enabled: bool,
+        cx: &mut Context<Self>,
+    ) {
+        let changed = if enabled {
+            self.disabled_servers.remove(id)
+        } else {
+            self.disabled_servers.insert(id.clone())
+        };
+        if changed {
+            self.save_disabled_servers(cx);
+            self.available_context_servers_changed(cx);
+        }
+    }
+
+    /// The servers turned off in a project are remembered by its first folder, which stays the
+    /// same as other folders are added and removed.
+    fn disabled_servers_key(&self, cx: &App) -> Option<String> {
+        if !self.remember_disabled_servers {
+            return None;
+        }
+        let worktree = self.worktree_store.read(cx).visible_worktrees(cx).next()?;
+        Some(format!(
+            "disabled_context_servers:{}",
+            worktree.read(cx).abs_path().to_string_lossy()
+        ))
+    }
+
+    fn save_disabled_servers(&self, cx: &mut Context<Self>) {
+        let Some(key) = self.disabled_servers_key.clone() else {
+            return;
+        };
+        let mut disabled_servers = self
+            .disabled_servers
+            .iter()
+            .map(|id| id.0.clone())
+            .collect::<Vec<_>>();
+        disabled_servers.sort();
+        cx.background_spawn(async move {
+            if disabled_servers.is_empty() {
+                KEY_VALUE_STORE.delete_kvp(key).await
+            } else {
+                KEY_VALUE_STORE
+                    .write_kvp(key, serde_json::to_string(&disabled_servers)?)
+                    .await
+            }
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn disabled_servers(&self) -> &HashSet<ContextServerId> {
+        &self.disabled_servers
+    }
+
+    pub fn is_server_disabled(&self, id: &ContextServerId) -> bool {
+        self.disabled_servers.contains(id)
+    }
+
+    fn is_server_active(&self, id: &ContextServerId) -> bool {
+        self.temporary_servers.contains_key(id)
+            || (!self.disabled_servers.contains(id)
+                && self
+                    .active_servers
+                    .as_ref()
+                    .map_or(true, |active_servers| active_servers.contains(&id.0)))
+    }
+
+    /// Runs a server that isn't in the settings until it's removed, under an id derived from
+    /// `name` that no other server uses.
+    pub fn add_temporary_server(
+        &mut self,
+        name: &str,
+        configuration: ContextServerConfiguration,
+        cx: &mut Context<Self>,
+    ) -> ContextServerId {
+        let configured_servers = ProjectSettings::get_global(cx).context_servers.clone();
+        let is_taken = |id: &ContextServerId| {
+            self.servers.contains_key(id)
+                || self.temporary_servers.contains_key(id)
+                || configured_servers.contains_key(&id.0)
+        };
+        let mut id = ContextServerId(format!("{name}-temporary").into());
+        let mut suffix = 2;
+        while is_taken(&id) {
+            id = ContextServerId(format!("{name}-temporary-{suffix}").into());
+            suffix += 1;
+        }
+        self.temporary_servers.insert(id.clone(), configuration);
+        self.available_context_servers_changed(cx);
+        id
+    }
+
+    pub fn remove_temporary_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) {
+        if self.temporary_servers.remove(id).is_some() {
+            self.available_context_servers_changed(cx);
+        }
+    }
+
+    /// Returns whether calls to a server's tools are confirmed before they run, and which
+    /// settings decided it.
+    ///
+    /// The settings of the servers that aren't run because they duplicate this one count as
+    /// this server's, after its own. In each layer of settings, the first of them that says
+    /// whether to confirm calls decides.
+    pub fn tool_confirmation(&self, id: &ContextServerId, cx: &App) -> ToolConfirmation {
+        let mut ids = vec![id];
+        ids.extend(self.duplicates_of(id));
+        let confirm_tool_calls = |settings: &serde_json::Value| -> Option<bool> {
+            let servers = settings.get("context_servers")?;
+            ids.iter().find_map(|id| {
+                servers
+                    .get(id.0.as_ref())?
+                    .get("confirm_tool_calls")?
+                    .as_bool()
+            })
+        };
+
+        let settings_store = cx.global::<SettingsStore>();
+        let user_settings = settings_store.raw_user_settings();
+        let user_configured = user_settings
+            .get("context_servers")
+            .is_some_and(|servers| ids.iter().any(|id| servers.get(id.0.as_ref()).is_some()));
+        // The project's declaration of a server is only used when the user didn't configure it.
+        let project_servers = if user_configured {
+            None
+        } else {
+            ids.iter().find_map(|id| {
+                self.project_servers
+                    .values()
+                    .filter(|project_servers| project_servers.trusted)
+                    .find_map(|project_servers| project_servers.servers.get(&id.0))
+                    .and_then(|configuration| configuration.confirm_tool_calls)
+            })
+        };
+        // Since worktrees can only require confirmation, any of them requiring it is enough.
+        let worktree_confirm_tool_calls = self
+            .worktree_store
+            .read(cx)
+            .visible_worktrees(cx)
+            .filter_map(|worktree| {
+                let (_, content) = settings_store
+                    .local_settings(worktree.read(cx).id())
+                    .find(|(path, _)| path.as_os_str().is_empty())?;
+                let settings = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+                confirm_tool_calls(&settings)
+            })
+            .max();
+
+        resolve_tool_confirmation(
+            confirm_tool_calls(user_settings),
+            project_servers,
+            worktree_confirm_tool_calls,
+        )
+    }
+
+    /// Returns the servers that aren't run because they launch the same thing as `id`, in the
+    /// order of their ids.
+    pub fn duplicates_of(&self, id: &ContextServerId) -> Vec<&ContextServerId> {
+        let mut duplicates = self
+            .duplicate_servers
+            .iter()
+            .filter(|(_, kept)| *kept == id)
+            .map(|(duplicate, _)| duplicate)
+            .collect::<Vec<_>>();
+        duplicates.sort_by(|a, b| a.0.cmp(&b.0));
+        duplicates
+    }
+
+    /// Returns the ids of the servers in the user's settings.
+    fn user_server_ids(&self, cx: &App) -> HashSet<Arc<str>> {
+        cx.global::<SettingsStore>()
+            .raw_user_settings()
+            .get("context_servers")
+            .and_then(|servers| servers.as_object())
+            .map(|servers| servers.keys().map(|id| id.as_str().into()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_temporary_server(&self, id: &ContextServerId) -> bool {
+        self.temporary_servers.contains_key(id)
+    }
+
+    pub fn temporary_server_ids(&self) -> impl Iterator<Item = &ContextServerId> {
+        self.temporary_servers.keys()
+    }
+
+    /// Returns the prefix of the names a server's tools are offered to the model under, with the
+    /// characters that tool names can't contain replaced.
+    ///
+    /// Only servers configured with a prefix have one, so that configuring other servers never
+    /// renames a server's tools, which profiles and permissions refer to by name.
+    pub fn tool_name_prefix(&self, id: &ContextServerId) -> Option<String> {
+        let prefix = self
+            .configuration_for_server(id)?
+            .tool_name_prefix
+            .as_ref()?
+            .chars()
+            .map(|char| {
+                if char.is_ascii_alphanumeric() || char == '-' || char == '_' {
+                    char
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        (!prefix.is_empty()).then_some(prefix)
+    }
+
+    /// Returns the instructions that a running server gave when it was initialized, if any.
+    pub fn server_instructions(&self, id: &ContextServerId) -> Option<String> {
+        self.get_running_server(id)?
+            .client()?
+            .initialize
+            .instructions
+            .clone()
+            .filter(|instructions| !instructions.trim().is_empty())
+    }
+
+    /// Returns the user's guidance on using a server's tools, from its configuration.
+    pub fn configured_instructions(&self, id: &ContextServerId) -> Option<String> {
+        self.configuration_for_server(id)?
+            .instructions
+            .clone()
+            .filter(|instructions| !instructions.trim().is_empty())
+    }
+
+    /// Whether the instructions a server gives itself go into the agent's system prompt. They're
+    /// the server's own claim, so they're left out unless the user opts in.
+    pub fn uses_server_instructions(&self, id: &ContextServerId) -> bool {
+        self.configuration_for_server(id)
+            .and_then(|configuration| configuration.use_server_instructions)
+            .unwrap_or(false)
+    }
+
+    /// The middleware to run around tool calls, in the order it runs in.
+    pub fn tool_middleware(&self, cx: &App) -> Vec<Arc<dyn ToolMiddleware>> {
+        self.registry.read(cx).tool_middleware()
+    }
+
+    /// Starts appending a server's JSON-RPC messages to a file, or stops if they already are,
+    /// until Zed restarts. Returns the file, when tracing was started.
+    pub fn toggle_trace_file(&mut self, id: &ContextServerId, cx: &App) -> Result<Option<PathBuf>> {
+        let server = self
+            .get_server(id)
+            .with_context(|| format!("context server {id} isn't configured"))?;
+        let path = if server.trace_file_path().is_some() {
+            None
+        } else {
+            Some(
+                self.configuration_for_server(id)
+                    .and_then(|configuration| configuration.trace_file.clone())
+                    .filter(|path| self.is_allowed_trace_file(id, path, cx))
+                    .unwrap_or_else(|| {
+                        paths::logs_dir()
+                            .join("context_servers")
+                            .join(format!("{id}.jsonl"))
+                    }),
+            )
+        };
+        server.set_trace_file(path.clone())?;
+        self.trace_file_overrides.insert(id.clone(), path.clone());
+        Ok(path)
+    }
+
+    fn trace_file(
+        &self,
+        id: &ContextServerId,
+        configuration: &ContextServerConfiguration,
+        cx: &App,
+    ) -> Option<PathBuf> {
+        let path = match self.trace_file_overrides.get(id) {
+            Some(path) => path.clone(),
+            None => configuration.trace_file.clone(),
+        }?;
+        if self.is_allowed_trace_file(id, &path, cx) {
+            Some(path)
+        } else {
+            log::warn!(
+                "not tracing context server {id} to {path:?}: only the user's settings can \
+                trace servers to files outside of Zed's data and logs directories"
+            );
+            None
+        }
+    }
+
+    /// Whether a server may be traced to `path`. A project's settings could otherwise have Zed
+    /// append to any file the user can write, so files outside of Zed's own directories have to
+    /// come from the user's settings.
+    fn is_allowed_trace_file(&self, id: &ContextServerId, path: &Path, cx: &App) -> bool {
+        let user_trace_file = cx
+            .global::<SettingsStore>()
+            .raw_user_settings()
+            .get("context_servers")
+            .and_then(|servers| servers.get(id.0.as_ref())?.get("trace_file")?.as_str())
+            .map(PathBuf::from);
+        is_allowed_trace_file(
+            path,
+            user_trace_file.as_deref(),
+            &[paths::data_dir().as_path(), paths::logs_dir().as_path()],
+        )
+    }
+
+    fn run_server(
+        &mut self,
+        server: Arc<ContextServer>,
+        configuration: Arc<ContextServerConfiguration>,
+        cx: &mut Context<Self>,
+    ) {
+        let id = server.id();
+        if matches!(
+            self.servers.get(&id),
+            Some(ContextServerState::Starting { .. } | ContextServerState::Running { .. })
+        ) {
+            // Calls waiting on the previous instance can be served by the new one.
+            let listeners = self.running_server_listeners.remove(&id);
+            self.stop_server(&id, cx).log_err();
+            if let Some(listeners) = listeners {
+                self.running_server_listeners.insert(id.clone(), listeners);
+            }
+        }
+
+        // Servers taken from the pool are already running.
+        if server.client().is_some() {
+            self.update_server_state(
+                id,
+                ContextServerState::Running {
+                    server,
+                    configuration,
+                },
+                cx,
+            );
+            return;
+        }
+
+        let scratch_dir = configuration
+            .sandbox
+            .is_some()
+            .then(|| sandbox_scratch_dir(&id));
+        let fs = self.worktree_store.read(cx).fs();
+        let task = cx.spawn({
+            let id = server.id();
+            let server = server.clone();
+            let configuration = configuration.clone();
+            async move |this, cx| {
+                let started = async {
+                    if let Some(scratch_dir) = scratch_dir {
+                        fs.context("sandboxed context servers only run in local projects")?
+                            .create_dir(&scratch_dir)
+                            .await
+                            .with_context(|| {
+                                format!("creating scratch directory for context server {id}")
+                            })?;
+                    }
+                    server.clone().start(&cx).await
+                };
+                match started.await {
+                    Ok(_) => {
+                        log::info!("Started {} context server", id);
+                        debug_assert!(server.client().is_some());
+
+                        this.update(cx, |this, cx| {
+                            this.update_server_state(
+                                id.clone(),
+                                ContextServerState::Running {
+                                    server,
+                                    configuration,
+                                },
+                                cx,
+                            )
+                        })
+                        .log_err()
+                    }
+                    Err(err) => {
+                        log::error!("{} context server failed to start: {}", id, err);
+                        this.update(cx, |this, cx| {
+                            this.update_server_state(
+                                id.clone(),
+                                ContextServerState::Stopped {
+                                    configuration,
+                                    server,
+                                    error: Some(err.to_string().into()),
+                                },
+                                cx,
+                            )
+                        })
+                        .log_err()
+                    }
+                };
+            }
+        });
+
+        self.update_server_state(
+            id.clone(),
+            ContextServerState::Starting {
+                configuration,
+                _task: task,
+                server,
+            },
+            cx,
+        );
+    }
+
+    fn remove_server(&mut self, id: &ContextServerId, cx: &mut Context<Self>) -> Result<()> {
+        let state = self
+            .servers
+            .remove(id)
+            .context("Context server not found")?;
+        drop(state);
+        self.running_server_listeners.remove(id);
+        self.egress_proxies.remove(id);
+        self.installed_packages.remove(id);
+        self.launch_worktrees.remove(id);
+        self.network_status_tasks.remove(id);
+        self.offline_servers.remove(id);
+        cx.emit(Event::ServerStatusChanged {
+            server_id: id.clone(),
+            status: ContextServerStatus::Stopped,
+        });
+        Ok(())
+    }
+
+    /// Stops using a server that maintenance let go of, starting its replacement when it's
+    /// restarted.
+    fn shut_down_server(
+        &mut self,
+        id: &ContextServerId,
+        shutdown: ServerShutdown,
+        restart: Option<(Arc<ContextServer>, Arc<ContextServerConfiguration>)>,
+        cx: &mut Context<Self>,
+    ) {
+        match shutdown {
+            ServerShutdown::Stop => self.stop_server(id, cx),
+            ServerShutdown::Park => self.park_server(id, cx),
+            ServerShutdown::Remove => self.remove_server(id, cx),
+        }
+        .ok();
+        if let Some((server, configuration)) = restart {
+            self.run_server(server, configuration, cx);
+        }
+    }
+
+    fn is_configuration_valid(&self, configuration: &ContextServerConfiguration) -> bool {
+        // Command must be some when we are running in stdio mode.
+        self.context_server_factory.as_ref().is_some() || has_launch(configuration)
+    }
+
+    /// Returns the native server registered under `id`, unless the configuration launches
+    /// another server in its place.
+    fn native_server(
+        &self,
+        id: &ContextServerId,
+        configuration: &ContextServerConfiguration,
+        cx: &App,
+    ) -> Option<Arc<dyn BuiltinServer>> {
+        if has_launch(configuration) {
+            return None;
+        }
+        self.registry.read(cx).native_context_server(&id.0)
+    }
+
+    /// Returns the worktree paths that launching a server with `configuration` depends on, which
+    /// are all of them for servers confined to the worktrees, and the first one for servers that
+    /// start in it.
+    fn worktree_launch(
+        &self,
+        configuration: &ContextServerConfiguration,
+        cx: &App,
+    ) -> Option<Vec<PathBuf>> {
+        if configuration.sandbox.is_some()
+            || configuration.builtin == Some(BuiltinContextServer::Filesystem)
+            || !configuration.features.resources_outside_worktrees
+        {
+            Some(
+                self.worktree_store
+                    .read(cx)
+                    .visible_worktrees(cx)
+                    .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+                    .collect(),
+            )
+        } else if configuration
+            .command
+            .as_ref()
+            .is_some_and(ContextServerCommand::uses_worktree_root)
+        {
+            Some(
+                self.worktree_root(cx)
+                    .map(|root| root.to_path_buf())
+                    .into_iter()
+                    .collect(),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether any server was launched with worktrees other than the project's current
+    /// ones, so that it has to be restarted.
+    fn launch_worktrees_changed(&self, cx: &App) -> bool {
+        self.servers.iter().any(|(id, state)| {
+            self.launch_worktrees.get(id)
+                != self.worktree_launch(&state.configuration(), cx).as_ref()
+        })
+    }
+
+    fn worktree_root(&self, cx: &App) -> Option<Arc<Path>> {
+        self.worktree_store
+            .read(cx)
+            .visible_worktrees(cx)
+            .next()
+            .map(|worktree| worktree.read(cx).abs_path())
+    }
+
+    /// Returns the environment of the user's login shell in the directory the server's command
+    /// starts in, along with that directory, for servers whose command is run in it.
+    fn shell_environment(
+        &mut self,
+        configuration: &ContextServerConfiguration,
+        cx: &mut Context<Self>,
+    ) -> Option<(Arc<Path>, Shared<Task<Option<HashMap<String, String>>>>)> {
+        if self.context_server_factory.is_some()
+            || configuration.replay.is_some()
+            || configuration.openapi.is_some()
+            || configuration.builtin.is_some()
+            || !configuration.resolve_command_in_shell.unwrap_or(true)
+        {
+            return None;
+        }
+        let command = configuration.command.as_ref()?;
+        let worktree_root = self
+            .worktree_store
+            .read(cx)
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx))
+            .find(|worktree| worktree.is_local())
+            .map(|worktree| worktree.abs_path());
+        let directory = command
+            .working_directory(worktree_root.as_deref())
+            .ok()
+            .flatten()
+            .map(Arc::from)
+            .or(worktree_root)
+            .unwrap_or_else(|| paths::home_dir().as_path().into());
+        let environment = self.environment.update(cx, |environment, cx| {
+            environment.get_directory_environment(directory.clone(), cx)
+        });
+        Some((directory, environment))
+    }
+
+    fn create_context_server(
+        &mut self,
+        id: ContextServerId,
+        configuration: Arc<ContextServerConfiguration>,
+        cx: &mut Context<Self>,
+    ) -> Result<Arc<ContextServer>> {
+        let worktree_paths = self
+            .worktree_store
+            .read(cx)
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+            .collect::<Vec<_>>();
+        let trace_file = self.trace_file(&id, &configuration, cx);
+        if let Some(factory) = self.context_server_factory.as_ref() {
+            Ok(factory(id, configuration))
+        } else if let Some(recording_path) = configuration.replay.clone() {
+            let transport = ReplayTransport::new(id.clone(), recording_path);
+            Ok(Arc::new(
+                ContextServer::new(id, Arc::new(transport))
+                    .with_max_requests_per_minute(configuration.max_requests_per_minute)
+                    .with_max_result_bytes(configuration.max_result_bytes)
+                    .with_tool_call_timeout(configuration.tool_call_timeout_seconds)
+                    .with_features(configuration.features, worktree_paths)
+                    .with_trace_file(trace_file),
+            ))
+        } else if let Some(builtin) = configuration.builtin {
+            let server: Arc<dyn BuiltinServer> = match builtin {
+                BuiltinContextServer::Fetch => Arc::new(FetchServer::new(cx.http_client())),
+                BuiltinContextServer::Filesystem => {
+                    let fs = self
+                        .worktree_store
+                        .read(cx)
+                        .fs()
+                        .context("the filesystem server only runs in local projects")?;
+                    Arc::new(FilesystemServer::new(fs, worktree_paths.clone().into()))
+                }
+            };
+            let transport =
+                BuiltinTransport::new(id.clone(), server, cx.background_executor().clone());
+            Ok(Arc::new(
+                ContextServer::new(id, Arc::new(transport))
+                    .with_max_requests_per_minute(configuration.max_requests_per_minute)
+                    .with_max_result_bytes(configuration.max_result_bytes)
+                    .with_tool_call_timeout(configuration.tool_call_timeout_seconds)
+                    .with_features(configuration.features, worktree_paths)
+                    .with_trace_file(trace_file),
+            ))
+        } else if let Some(server) = self.native_server(&id, &configuration, cx) {
+            let transport =
+                BuiltinTransport::new(id.clone(), server, cx.background_executor().clone());
+            Ok(Arc::new(
+                ContextServer::new(id, Arc::new(transport))
+                    .with_max_requests_per_minute(configuration.max_requests_per_minute)
+                    .with_max_result_bytes(configuration.max_result_bytes)
+                    .with_tool_call_timeout(configuration.tool_call_timeout_seconds)
+                    .with_features(configuration.features, worktree_paths)
+                    .with_trace_file(trace_file),
+            ))
+        } else if let Some(openapi) = configuration.openapi.clone() {
+            let transport = OpenApiTransport::new(
+                id.clone(),
+                openapi,
+                cx.http_client(),
+                cx.background_executor().clone(),
+            );
+            self.watch_network_status(id.clone(), transport.online(), cx);
+            Ok(Arc::new(
+                ContextServer::new(id, Arc::new(transport))
+                    .with_max_requests_per_minute(configuration.max_requests_per_minute)
+                    .with_max_result_bytes(configuration.max_result_bytes)
+                    .with_tool_call_timeout(configuration.tool_call_timeout_seconds)
+                    .with_features(configuration.features, worktree_paths)
+                    .with_trace_file(trace_file),
+            ))
+        } else {
+            let mut command = configuration
+                .command
+                .clone()
+                .or_else(|| {
+                    self.installed_packages
+                        .get(&id)
+                        .map(|package| package.command.clone())
+                })
+                .context("Missing command to run context server")?;
+            command
+                .expand_worktree_root(self.worktree_root(cx).as_deref())
+                .with_context(|| format!("resolving the command of context server {id}"))?;
+            if let Some((directory, environment)) = self.shell_environment(&configuration, cx) {
+                // The environment is loaded by `maintain_servers` before servers are created.
+                match environment.peek() {
+                    Some(Some(environment)) => {
+                        resolve_in_shell_environment(&mut command, environment, &directory)
+                    }
+                    Some(None) => {}
+                    None => log::warn!(
+                        "the shell environment isn't loaded yet, so context server {id} will be \
+                        started without it"
+                    ),
+                }
+            }
+            command.expand_env_variables();
+            if let Some(sandbox) = configuration.sandbox.as_ref() {
+                let scratch_dir = sandbox_scratch_dir(&id);
+                let egress_proxy = if sandbox.allow_network && !sandbox.allowed_hosts.is_empty() {
+                    Some(EgressProxy::start(sandbox.allowed_hosts.clone(), cx)?)
+                } else {
+                    None
+                };
+                command = sandboxed_command(
+                    &command,
+                    sandbox,
+                    &worktree_paths,
+                    &scratch_dir,
+                    egress_proxy.as_ref().map(EgressProxy::address),
+                )?;
+                if let Some(egress_proxy) = egress_proxy {
+                    self.egress_proxies.insert(id.clone(), egress_proxy);
+                }
+            }
+            Ok(Arc::new(
+                ContextServer::stdio(id, command)
+                    .with_max_requests_per_minute(configuration.max_requests_per_minute)
+                    .with_max_result_bytes(configuration.max_result_bytes)
+                    .with_tool_call_timeout(configuration.tool_call_timeout_seconds)
+                    .with_features(configuration.features, worktree_paths)
+                    .with_trace_file(trace_file),
+            ))
+        }
+    }
+
+    fn update_server_state(
+        &mut self,
+        id: ContextServerId,
+        state: ContextServerState,
+        cx: &mut Context<Self>,
+    ) {
+        let status = ContextServerStatus::from_state(&state);
+        match &state {
+            ContextServerState::Starting { .. } => {}
+            ContextServerState::Running { server, .. } => {
+                for listener in self
+                    .running_server_listeners
+                    .remove(&id)
+                    .unwrap_or_default()
+                {
+                    listener.send(Ok(server.clone())).ok();
+                }
+            }
+            ContextServerState::Stopped { error, .. } => {
+                for listener in self
+                    .running_server_listeners
+                    .remove(&id)
+                    .unwrap_or_default()
+                {
+                    let error = match error {
+                        Some(error) => anyhow!("Context server {id} failed to start: {error}"),
+                        None => anyhow!("Context server {id} was stopped"),
+                    };
+                    listener.send(Err(error)).ok();
+                }
+            }
+        }
+        self.servers.insert(id.clone(), state);
+        cx.emit(Event::ServerStatusChanged {
+            server_id: id,
+            status,
+        });
+    }
+
+    /// Returns the servers configured in the settings of the project's first worktree.
+    fn configured_servers(&self, cx: &App) -> HashMap<Arc<str>, ContextServerConfiguration> {
+        let worktree = self.worktree_store.read(cx).visible_worktrees(cx).next();
+        let location = worktree
+            .as_ref()
+            .map(|worktree| settings::SettingsLocation {
+                worktree_id: worktree.read(cx).id(),
+                path: Path::new(""),
+            });
+        let mut servers = ProjectSettings::get(location, cx).context_servers.clone();
+
+        // Relative icon paths are relative to the directory of the settings file that sets them,
+        // and the worktree's settings take precedence over the user's.
+        let worktree_settings = worktree.and_then(|worktree| {
+            let worktree = worktree.read(cx);
+            let (_, content) = cx
+                .global::<SettingsStore>()
+                .local_settings(worktree.id())
+                .find(|(path, _)| path.as_os_str().is_empty())?;
+            let settings = parse_json_with_comments::<serde_json::Value>(&content).log_err()?;
+            Some((worktree.abs_path().join(".zed"), settings))
+        });
+        for (id, configuration) in &mut servers {
+            let Some(icon) = configuration.icon.as_mut() else {
+                continue;
+            };
+            if icon.is_absolute() {
+                continue;
+            }
+            let settings_dir = worktree_settings
+                .as_ref()
+                .filter(|(_, settings)| {
+                    settings["context_servers"][id.as_ref()]
+                        .get("icon")
+                        .is_some()
+                })
+                .map(|(settings_dir, _)| settings_dir.as_path())
+                .or_else(|| paths::settings_file().parent());
+            if let Some(settings_dir) = settings_dir {
+                *icon = settings_dir.join(&*icon);
+            }
+        }
+        servers
+    }
+
+    /// Updates the servers once the settings stop changing.
+    fn settings_changed(&mut self, cx: &mut Context<Self>) {
+        if self.settings_update_debounce.is_zero() {
+            self.available_context_servers_changed(cx);
+            return;
+        }
+        let debounce = self.settings_update_debounce;
+        self.settings_update_task = Some(cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(debounce).await;
+            this.update(cx, |this, cx| {
+                this.settings_update_task.take();
+                this.available_context_servers_changed(cx);
+            })
+            .ok();
+        }));
+    }
+
+    fn available_context_servers_changed(&mut self, cx: &mut Context<Self>) {
+        if self.update_servers_task.is_some() {
+            self.needs_server_update = true;
+        } else {
+            self.needs_server_update = false;
+            self.update_servers_task = Some(cx.spawn(async move |this, cx| {
+                if let Err(err) = Self::maintain_servers(this.clone(), cx).await {
+                    log::error!("Error maintaining context servers: {}", err);
+                }
+
+                this.update(cx, |this, cx| {
+                    this.update_servers_task.take();
+                    if this.needs_server_update {
+                        this.available_context_servers_changed(cx);
+                    }
+                })?;
+
+                Ok(())
+            }));
+        }
+    }
+
+    async fn maintain_servers(this: WeakEntity<Self>, cx: &mut AsyncApp) -> Result<()> {
+        // The servers the user turned off for the project are loaded before any server starts.
+        let disabled_servers_key = this.update(cx, |this, cx| {
+            let key = this.disabled_servers_key(cx);
+            (key != this.disabled_servers_key).then_some(key)
+        })?;
+        if let Some(key) = disabled_servers_key {
+            let disabled_servers = match key.clone() {
+                Some(key) => Some(
+                    cx.background_spawn(async move { KEY_VALUE_STORE.read_kvp(&key) })
+                        .await
+                        .log_err()
+                        .flatten()
+                        .and_then(|json| serde_json::from_str::<Vec<Arc<str>>>(&json).log_err())
+                        .unwrap_or_default(),
+                ),
+                None => None,
+            };
+            this.update(cx, |this, _| {
+                this.disabled_servers_key = key;
+                if let Some(disabled_servers) = disabled_servers {
+                    this.disabled_servers =
+                        disabled_servers.into_iter().map(ContextServerId).collect();
+                }
+            })?;
+        }
+
+        let mut desired_servers = HashMap::default();
+        // Servers from project files the user trusted, who reviewed their commands there.
+        let mut reviewed_servers = HashSet::default();
+
+        let (registry, worktree_store) = this.update(cx, |this, cx| {
+            desired_servers = this.configured_servers(cx);
+            let user_server_ids = this.user_server_ids(cx);
+            let worktree_server_ids = desired_servers.keys().cloned().collect::<HashSet<_>>();
+            for project_servers in this.project_servers.values() {
+                if project_servers.trusted {
+                    for (id, configuration) in &project_servers.servers {
+                        if !desired_servers.contains_key(id) {
+                            desired_servers.insert(id.clone(), configuration.clone());
+                            reviewed_servers.insert(id.clone());
+                        }
+                    }
+                }
+            }
+            this.duplicate_servers = deduplicate_servers(&mut desired_servers, |id| {
+                if user_server_ids.contains(id) {
+                    0
+                } else if worktree_server_ids.contains(id) {
+                    1
+                } else {
+                    2
+                }
+            });
+            for (duplicate, kept) in &this.duplicate_servers {
+                log::info!("not running context server {duplicate}, since {kept} runs the same");
+            }
+            let previous_servers = this
+                .servers
+                .iter()
+                .filter_map(|(id, state)| {
+                    let configuration = state.configuration();
+                    has_launch(&configuration)
+                        .then(|| (id.0.clone(), launch_digest(&configuration)))
+                })
+                .collect();
+            for (old_id, new_id) in renamed_servers(&previous_servers, &desired_servers) {
+                log::info!("context server {old_id} was renamed to {new_id}");
+                if let Some(digest) = this.trusted_servers.remove(&old_id) {
+                    this.trusted_servers.insert(new_id.clone(), digest);
+                }
+                if let Some(trace_file) = this.trace_file_overrides.remove(&old_id) {
+                    this.trace_file_overrides.insert(new_id.clone(), trace_file);
+                }
+                if this.disabled_servers.remove(&old_id) {
+                    this.disabled_servers.insert(new_id.clone());
+                    this.save_disabled_servers(cx);
+                }
+                cx.emit(Event::ServerRenamed { old_id, new_id });
+            }
+            // The user typed these in themselves, so they don't need to be trusted.
+            for (id, configuration) in &this.temporary_servers {
+                desired_servers.insert(id.0.clone(), configuration.clone());
+                reviewed_servers.insert(id.0.clone());
+            }
+
+            (this.registry.clone(), this.worktree_store.clone())
+        })?;
+
+        for (id, descriptor) in
+            registry.read_with(cx, |registry, _| registry.context_server_descriptors())?
+        {
+            let config = desired_servers.entry(id.clone()).or_default();
+            if config.command.is_none() {
+                if let Some(extension_command) = descriptor
+                    .command(worktree_store.clone(), &cx)
+                    .await
+                    .log_err()
+                {
+                    config.command = Some(extension_command);
+                }
+            }
+        }
+
+        let native_servers =
+            registry.read_with(cx, |registry, _| registry.native_context_servers())?;
+
+        let (packages_to_install, node) = this.update(cx, |this, cx| {
+            // Built-in servers run unless the user configured a server under the same id, and a
+            // configuration that doesn't say how to launch a server only adjusts the built-in one.
+            for builtin in this.builtin_servers {
+                let configuration = desired_servers.entry(builtin.id().into()).or_default();
+                if !has_launch(configuration) {
+                    configuration.builtin = Some(*builtin);
+                }
+            }
+            // Built-in servers are part of Zed, so they don't need to be trusted.
+            for (id, configuration) in &desired_servers {
+                if configuration.builtin.is_some() {
+                    reviewed_servers.insert(id.clone());
+                }
+            }
+            // Native servers run in-process, and were trusted along with the extension that
+            // registered them.
+            let mut native_server_ids = HashSet::default();
+            for (id, _) in &native_servers {
+                let configuration = desired_servers.entry(id.clone()).or_default();
+                if !has_launch(configuration) {
+                    reviewed_servers.insert(id.clone());
+                    native_server_ids.insert(id.clone());
+                }
+            }
+            // Filter out configurations without commands, the user uninstalled an extension.
+            desired_servers.retain(|id, configuration| {
+                this.is_configuration_valid(configuration) || native_server_ids.contains(id)
+            });
+            this.retain_trusted_servers(&mut desired_servers, &reviewed_servers, cx);
+            // Starting the server would fail anyway, but this way the reason is shown next to it.
+            let mut invalid_sandboxes = HashMap::default();
+            desired_servers.retain(|id, configuration| {
+                let Some(Err(error)) = configuration
+                    .sandbox
+                    .as_ref()
+                    .map(ContextServerSandbox::validate)
+                else {
+                    return true;
+                };
+                let error: Arc<str> = format!("invalid sandbox settings: {error:#}").into();
+                invalid_sandboxes.insert(ContextServerId(id.clone()), error);
+                false
+            });
+            for (id, error) in &invalid_sandboxes {
+                if this.invalid_sandboxes.get(id) != Some(error) {
+                    cx.emit(Event::ServerStatusChanged {
+                        server_id: id.clone(),
+                        status: ContextServerStatus::Error(error.clone()),
+                    });
+                }
+            }
+            this.invalid_sandboxes = invalid_sandboxes;
+            this.package_installs
+                .retain(|id, _| desired_servers.contains_key(&id.0));
+
+            let mut packages_to_install = Vec::new();
+            for (id, configuration) in &desired_servers {
+                let Some(package) = configuration.package.clone() else {
+                    continue;
+                };
+                let id = ContextServerId(id.clone());
+                let existing_config = this.servers.get(&id).map(|state| state.configuration());
+                if configuration.command.is_none()
+                    && existing_config.as_deref() != Some(configuration)
+                {
+                    this.package_installs
+                        .insert(id.clone(), ContextServerStatus::Installing);
+                    cx.emit(Event::ServerStatusChanged {
+                        server_id: id.clone(),
+                        status: ContextServerStatus::Installing,
+                    });
+                    packages_to_install.push((id, package));
+                }
+            }
+            (packages_to_install, this.node.clone())
+        })?;
+
+        let mut installs = packages_to_install
+            .into_iter()
+            .map(|(id, package)| {
+                let node = node.clone();
+                async move {
+                    let installed = package::install_package(&id, &package, false, node).await;
+                    (id, installed)
+                }
+            })
+            .collect::<FuturesUnordered<_>>();
+        while let Some((id, installed)) = installs.next().await {
+            this.update(cx, |this, cx| {
+                this.package_installed(id, installed, cx).log_err()
+            })?;
+        }
+
+        let shell_environments = this.update(cx, |this, cx| {
+            desired_servers
+                .values()
+                .filter_map(|configuration| this.shell_environment(configuration, cx))
+                .map(|(_, environment)| environment)
+                .collect::<Vec<_>>()
+        })?;
+        futures::future::join_all(shell_environments).await;
+
+        let mut servers_to_start = Vec::new();
+        let mut servers_to_remove = HashSet::default();
+        let mut servers_to_stop = HashSet::default();
+        let mut servers_to_park = HashSet::default();
+
+        this.update(cx, |this, cx| {
+            for server_id in this.servers.keys() {
+                if this.draining_servers.contains_key(server_id) {
+                    continue;
+                }
+                // All servers that are not in desired_servers should be removed from the store.
+                // E.g. this can happen if the user removed a server from the configuration,
+                // or the user uninstalled an extension.
+                if !desired_servers.contains_key(&server_id.0) {
+                    servers_to_remove.insert(server_id.clone());
+                }
+            }
+
+            for (id, config) in desired_servers {
+                let id = ContextServerId(id.clone());
+                if this.draining_servers.contains_key(&id) {
+                    continue;
+                }
+
+                if !this.is_server_active(&id) {
+                    if matches!(
+                        this.servers.get(&id),
+                        Some(
+                            ContextServerState::Starting { .. }
+                                | ContextServerState::Running { .. }
+                        )
+                    ) {
+                        servers_to_park.insert(id.clone());
+                    }
+                    this.inactive_servers.insert(id);
+                    continue;
+                }
+
+                let was_inactive = this.inactive_servers.remove(&id);
+                let existing_config = this.servers.get(&id).map(|state| state.configuration());
+                // Servers confined to the worktrees, or started in one, are launched for the
+                // worktrees the project had at the time.
+                let worktree_launch = this.worktree_launch(&config, cx);
+                if was_inactive
+                    || existing_config.as_deref() != Some(&config)
+                    || this.launch_worktrees.get(&id) != worktree_launch.as_ref()
+                {
+                    let config = Arc::new(config);
+                    let server = this.take_pooled_server(&id, &config, cx).or_else(|| {
+                        this.create_context_server(id.clone(), config.clone(), cx)
+                            .log_err()
+                    });
+                    if let Some(server) = server {
+                        match worktree_launch {
+                            Some(worktrees) => {
+                                this.launch_worktrees.insert(id.clone(), worktrees);
+                            }
+                            None => {
+                                this.launch_worktrees.remove(&id);
+                            }
+                        }
+                        this.package_installs.remove(&id);
+                        servers_to_start.push((server, config));
+                        if this.servers.contains_key(&id) {
+                            servers_to_stop.insert(id);
+                        }
+                    }
+                }
+            }
+        })?;
+
+        this.update(cx, |this, cx| {
+            let mut restarts = servers_to_start
+                .into_iter()
+                .map(|(server, config)| (server.id(), (server, config)))
+                .collect::<HashMap<_, _>>();
+            let shutdowns = servers_to_stop
+                .into_iter()
+                .map(|id| (id, ServerShutdown::Stop))
+                .chain(servers_to_park.into_iter().map(|id| (id, ServerShutdown::Park)))
+                .chain(
+                    servers_to_remove
+                        .into_iter()
+                        .map(|id| (id, ServerShutdown::Remove)),
+                );
+            for (id, shutdown) in shutdowns {
+                let restart = restarts.remove(&id);
+                let Some((server, protocol)) = this
+                    .get_running_server(&id)
+                    .and_then(|server| Some((server.clone(), server.client()?)))
+                else {
+                    this.shut_down_server(&id, shutdown, restart, cx);
+                    continue;
+                };
+                // Servers that are restarted or removed finish the tool calls they're running
+                // first, each in its own task so that a slow call only holds up its own server.
+                let drain = cx.spawn({
+                    let id = id.clone();
+                    async move |this, cx| {
+                        let cancelled = protocol.drain_tool_calls(DRAIN_TIMEOUT).await;
+                        if cancelled > 0 {
+                            log::warn!(
+                                "cancelled {cancelled} tool calls to context server {id} to restart it"
+                            );
+                        }
+                        this.update(cx, |this, cx| {
+                            this.draining_servers.remove(&id);
+                            // The user may have stopped or restarted the server in the meantime.
+                            if this
+                                .get_running_server(&id)
+                                .is_some_and(|running| Arc::ptr_eq(&running, &server))
+                            {
+                                this.shut_down_server(&id, shutdown, restart, cx);
+                            }
+                            // The settings may have changed again while the server drained.
+                            this.available_context_servers_changed(cx);
+                        })
+                        .ok();
+                    }
+                });
+                this.draining_servers.insert(id, drain);
+            }
+            for (server, config) in restarts.into_values() {
+                this.run_server(server, config, cx);
+            }
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FakeFs, Project, project_settings::ProjectSettings};
+    use context_server::{fake_server::FakeContextServer, protocol::ContextServerFeatures};
+    use gpui::{AppContext, TestAppContext, UpdateGlobal as _};
+    use serde_json::json;
+    use std::{cell::RefCell, rc::Rc};
+    use util::path;
+
+    #[gpui::test]
+    async fn test_context_server_status(cx: &mut TestAppContext) {
+        const SERVER_1_ID: &'static str = "mcp-1";
+        const SERVER_2_ID: &'static str = "mcp-2";
+
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![
+                (SERVER_1_ID.into(), ContextServerConfiguration::default()),
+                (SERVER_2_ID.into(), ContextServerConfiguration::default()),
+            ],
+        )
+        .await;
+
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
+
+        let server_1_id = ContextServerId("mcp-1".into());
+        let server_2_id = ContextServerId("mcp-2".into());
+
+        let server_1 =
+            FakeContextServer::new(SERVER_1_ID, cx.executor()).context_server(server_1_id.clone());
+        let server_2 =
+            FakeContextServer::new(SERVER_2_ID, cx.executor()).context_server(server_2_id.clone());
+
+        store
+            .update(cx, |store, cx| store.start_server(server_1, cx))
+            .unwrap();
+
+        cx.run_until_parked();
+
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_1_id),
+                Some(ContextServerStatus::Running)
+            );
+            assert_eq!(store.read(cx).status_for_server(&server_2_id), None);
+        });
+
+        store
+            .update(cx, |store, cx| store.start_server(server_2.clone(), cx))
+            .unwrap();
+
+        cx.run_until_parked();
+
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_1_id),
+                Some(ContextServerStatus::Running)
+            );
+            assert_eq!(
+                store.read(cx).status_for_server(&server_2_id),
+                Some(ContextServerStatus::Running)
+            );
+        });
+
+        store
+            .update(cx, |store, cx| store.stop_server(&server_2_id, cx))
+            .unwrap();
+
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_1_id),
+                Some(ContextServerStatus::Running)
+            );
+            assert_eq!(
+                store.read(cx).status_for_server(&server_2_id),
+                Some(ContextServerStatus::Stopped)
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_context_server_status_events(cx: &mut TestAppContext) {
+        const SERVER_1_ID: &'static str = "mcp-1";
+        const SERVER_2_ID: &'static str = "mcp-2";
+
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![
+                (SERVER_1_ID.into(), ContextServerConfiguration::default()),
+                (SERVER_2_ID.into(), ContextServerConfiguration::default()),
+            ],
+        )
+        .await;
+
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
+
+        let server_1_id = ContextServerId("mcp-1".into());
+        let server_2_id = ContextServerId("mcp-2".into());
+
+        let server_1 =
+            FakeContextServer::new(SERVER_1_ID, cx.executor()).context_server(server_1_id.clone());
+        let server_2 =
+            FakeContextServer::new(SERVER_2_ID, cx.executor()).context_server(server_2_id.clone());
+
+        let _server_events = assert_server_events(
+            &store,
+            vec![
+                (server_1_id.clone(), ContextServerStatus::Starting),
+                (server_1_id.clone(), ContextServerStatus::Running),
+                (server_2_id.clone(), ContextServerStatus::Starting),
+                (server_2_id.clone(), ContextServerStatus::Running),
+                (server_2_id.clone(), ContextServerStatus::Stopped),
+            ],
+            cx,
+        );
+
+        store
+            .update(cx, |store, cx| store.start_server(server_1, cx))
+            .unwrap();
+
+        cx.run_until_parked();
+
+        store
+            .update(cx, |store, cx| store.start_server(server_2.clone(), cx))
+            .unwrap();
+
+        cx.run_until_parked();
+
+        store
+            .update(cx, |store, cx| store.stop_server(&server_2_id, cx))
+            .unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_disabled_servers_key(cx: &mut TestAppContext) {
+        let (fs, project) = setup_context_server_test(cx, json!({"code.rs": ""}), vec![]).await;
+        fs.insert_tree(path!("/other"), json!({})).await;
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
+        let expected_key = Some(format!("disabled_context_servers:{}", path!("/test")));
+
+        store.read_with(cx, |store, cx| {
+            assert_eq!(store.disabled_servers_key(cx), None)
+        });
+        store.update(cx, |store, _| store.remember_disabled_servers = true);
+        store.read_with(cx, |store, cx| {
+            assert_eq!(store.disabled_servers_key(cx), expected_key)
+        });
+
+        // Adding a folder keeps the servers turned off for the project.
+        project
+            .update(cx, |project, cx| {
+                project.find_or_create_worktree(path!("/other"), true, cx)
+            })
+            .await
+            .unwrap();
+        store.read_with(cx, |store, cx| {
+            assert_eq!(store.disabled_servers_key(cx), expected_key)
+        });
+    }
+
+    #[gpui::test]
+    async fn test_report_tool_call(cx: &mut TestAppContext) {
+        let (_fs, project) = setup_context_server_test(cx, json!({"code.rs": ""}), vec![]).await;
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let _subscription = cx.update(|cx| {
+            cx.subscribe(&store, {
+                let events = events.clone();
+                move |_, event, _| {
+                    if let Event::ToolCall { call_id, event, .. } = event {
+                        events.borrow_mut().push((call_id.clone(), event.clone()));
+                    }
+                }
+            })
+        });
+
+        let server_id = ContextServerId("mcp-1".into());
+        store.update(cx, |store, cx| {
+            for (call_id, event) in [
+                ("call-1", ToolCallEvent::Requested),
+                ("call-1", ToolCallEvent::Running { progress: None }),
+            ] {
+                store.report_tool_call(&server_id, "search".into(), call_id.into(), event, cx);
+            }
+            assert!(store.is_tool_call_running("call-1"));
+            assert!(!store.is_tool_call_running("call-2"));
+
+            for (call_id, event) in [
+                ("call-2", ToolCallEvent::Running { progress: None }),
+                (
+                    "call-2",
+                    ToolCallEvent::Failed {
+                        error: "Server stopped".into(),
+                    },
+                ),
+            ] {
+                store.report_tool_call(&server_id, "search".into(), call_id.into(), event, cx);
+            }
+            assert!(store.is_tool_call_running("call-1"));
+            assert!(!store.is_tool_call_running("call-2"));
+        });
+
+        assert_eq!(
+            events.borrow().as_slice(),
+            [
+                (Arc::from("call-1"), ToolCallEvent::Requested),
+                (
+                    Arc::from("call-1"),
+                    ToolCallEvent::Running { progress: None }
+                ),
+                (
+                    Arc::from("call-2"),
+                    ToolCallEvent::Running { progress: None }
+                ),
+                (
+                    Arc::from("call-2"),
+                    ToolCallEvent::Failed {
+                        error: "Server stopped".into(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[gpui::test(iterations = 25)]
+    async fn test_context_server_concurrent_starts(cx: &mut TestAppContext) {
+        const SERVER_1_ID: &'static str = "mcp-1";
+
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![(SERVER_1_ID.into(), ContextServerConfiguration::default())],
+        )
+        .await;
+
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
+
+        let server_id = ContextServerId(SERVER_1_ID.into());
+
+        let server_with_same_id_1 =
+            FakeContextServer::new(SERVER_1_ID, cx.executor()).context_server(server_id.clone());
+        let server_with_same_id_2 =
+            FakeContextServer::new(SERVER_1_ID, cx.executor()).context_server(server_id.clone());
+
+        // If we start another server with the same id, we should report that we stopped the previous one
+        let _server_events = assert_server_events(
+            &store,
+            vec![
+                (server_id.clone(), ContextServerStatus::Starting),
+                (server_id.clone(), ContextServerStatus::Stopped),
+                (server_id.clone(), ContextServerStatus::Starting),
+                (server_id.clone(), ContextServerStatus::Running),
+            ],
+            cx,
+        );
+
+        store
+            .update(cx, |store, cx| {
+                store.start_server(server_with_same_id_1.clone(), cx)
+            })
+            .unwrap();
+        store
+            .update(cx, |store, cx| {
+                store.start_server(server_with_same_id_2.clone(), cx)
+            })
+            .unwrap();
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_id),
+                Some(ContextServerStatus::Starting)
+            );
+        });
+
+        cx.run_until_parked();
+
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_id),
+                Some(ContextServerStatus::Running)
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_wait_for_running_server(cx: &mut TestAppContext) {
+        const SERVER_1_ID: &'static str = "mcp-1";
+
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![(SERVER_1_ID.into(), ContextServerConfiguration::default())],
+        )
+        .await;
+
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
+
+        let server_id = ContextServerId(SERVER_1_ID.into());
+        let server =
+            FakeContextServer::new(SERVER_1_ID, cx.executor()).context_server(server_id.clone());
+
+        let unknown_server = store.update(cx, |store, cx| {
+            store.wait_for_running_server(&server_id, Duration::from_secs(10), cx)
+        });
+        assert!(unknown_server.await.is_err());
+
+        store
+            .update(cx, |store, cx| store.start_server(server.clone(), cx))
+            .unwrap();
+        let running_server = store.update(cx, |store, cx| {
+            assert_eq!(
+                store.status_for_server(&server_id),
+                Some(ContextServerStatus::Starting)
+            );
+            store.wait_for_running_server(&server_id, Duration::from_secs(10), cx)
+        });
+
+        cx.run_until_parked();
+        let running_server = running_server.await.unwrap();
+        assert_eq!(running_server.id(), server_id);
+        assert!(running_server.client().is_some());
+    }
+
+    #[test]
+    fn test_is_allowed_trace_file() {
+        let zed_directories = [Path::new("/home/me/.local/share/zed")];
+        let in_zed_directory = Path::new("/home/me/.local/share/zed/traces/github.jsonl");
+        let elsewhere = Path::new("/home/me/.bashrc");
+
+        assert!(is_allowed_trace_file(
+            in_zed_directory,
+            None,
+            &zed_directories
+        ));
+        assert!(!is_allowed_trace_file(elsewhere, None, &zed_directories));
+        assert!(is_allowed_trace_file(
+            elsewhere,
+            Some(elsewhere),
+            &zed_directories
+        ));
+        assert!(!is_allowed_trace_file(
+            Path::new("/home/me/.local/share/zed/../../../.bashrc"),
+            None,
+            &zed_directories
+        ));
+    }
+
+    #[gpui::test]
+    async fn test_instructions_for_server(cx: &mut TestAppContext) {
+        const SERVER_1_ID: &'static str = "mcp-1";
+        const SERVER_2_ID: &'static str = "mcp-2";
+
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![
+                (
+                    SERVER_1_ID.into(),
+                    ContextServerConfiguration {
+                        instructions: Some("Prefer small queries.".into()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    SERVER_2_ID.into(),
+                    ContextServerConfiguration {
+                        use_server_instructions: Some(true),
+                        ..Default::default()
+                    },
+                ),
+            ],
+        )
+        .await;
+
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
+
+        let server_1_id = ContextServerId(SERVER_1_ID.into());
+        let server_2_id = ContextServerId(SERVER_2_ID.into());
+        let fake_server_1 = FakeContextServer::new(SERVER_1_ID, cx.executor());
+        fake_server_1.set_instructions("Call `describe` before `query`.");
+        let fake_server_2 = FakeContextServer::new(SERVER_2_ID, cx.executor());
+        fake_server_2.set_instructions("Use the `search` tool first.");
+
+        store.update(cx, |store, cx| {
+            store
+                .start_server(fake_server_1.context_server(server_1_id.clone()), cx)
+                .unwrap();
+            store
+                .start_server(fake_server_2.context_server(server_2_id.clone()), cx)
+                .unwrap();
+        });
+        cx.run_until_parked();
+
+        store.read_with(cx, |store, _| {
+            assert_eq!(
+                store.server_instructions(&server_1_id).as_deref(),
+                Some("Call `describe` before `query`.")
+            );
+            assert_eq!(
+                store.configured_instructions(&server_1_id).as_deref(),
+                Some("Prefer small queries.")
+            );
+            assert!(!store.uses_server_instructions(&server_1_id));
+            assert_eq!(
+                store.server_instructions(&server_2_id).as_deref(),
+                Some("Use the `search` tool first.")
+            );
+            assert_eq!(store.configured_instructions(&server_2_id), None);
+            assert!(store.uses_server_instructions(&server_2_id));
+        });
+    }
+
+    #[gpui::test]
+    async fn test_context_server_maintain_servers_loop(cx: &mut TestAppContext) {
+        const SERVER_1_ID: &'static str = "mcp-1";
+        const SERVER_2_ID: &'static str = "mcp-2";
+
+        let server_1_id = ContextServerId(SERVER_1_ID.into());
+        let server_2_id = ContextServerId(SERVER_2_ID.into());
+
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![(
+                SERVER_1_ID.into(),
+                ContextServerConfiguration {
+                    command: None,
+                    settings: Some(json!({
+                        "somevalue": true
+                    })),
+                    replay: None,
+                    sandbox: None,
+                    confine_paths_to_worktrees: false,
+                    max_requests_per_minute: None,
+                    max_result_bytes: None,
+                    tool_call_timeout_seconds: None,
+                    keep_warm_seconds: None,
+                    tool_ui_text: Default::default(),
+                    tool_examples: Default::default(),
+                    tool_models: Default::default(),
+                    tool_env: Default::default(),
+                    display_name: None,
+                    icon: None,
+                    openapi: None,
+                    builtin: None,
+                    resolve_command_in_shell: None,
+                    package: None,
+                    confirm_tool_calls: None,
+                    features: Default::default(),
+                    trace_file: None,
+                    tool_name_prefix: None,
+                    instructions: None,
+                    use_server_instructions: None,
+                },
+            )],
+        )
+        .await;
+
+        let executor = cx.executor();
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test_maintain_server_loop(
+                Box::new(move |id, _| {
+                    FakeContextServer::new(id.0.to_string(), executor.clone())
+                        .context_server(id.clone())
+                }),
+                registry.clone(),
+                project.read(cx).worktree_store(),
+                cx,
+            )
+        });
+
+        // Ensure that mcp-1 starts up
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![
+                    (server_1_id.clone(), ContextServerStatus::Starting),
+                    (server_1_id.clone(), ContextServerStatus::Running),
+                ],
+                cx,
+            );
+            cx.run_until_parked();
+        }
+
+        // Ensure that mcp-1 is restarted when the configuration was changed
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![
+                    (server_1_id.clone(), ContextServerStatus::Stopped),
+                    (server_1_id.clone(), ContextServerStatus::Starting),
+                    (server_1_id.clone(), ContextServerStatus::Running),
+                ],
+                cx,
+            );
+            set_context_server_configuration(
+                vec![(
+                    server_1_id.0.clone(),
+                    ContextServerConfiguration {
+                        command: None,
+                        settings: Some(json!({
+                            "somevalue": false
+                        })),
+                        replay: None,
+                        sandbox: None,
+                        confine_paths_to_worktrees: false,
+                        max_requests_per_minute: None,
+                        max_result_bytes: None,
+                        tool_call_timeout_seconds: None,
+                        keep_warm_seconds: None,
+                        tool_ui_text: Default::default(),
+                        tool_examples: Default::default(),
+                        tool_models: Default::default(),
+                        tool_env: Default::default(),
+                        display_name: None,
+                        icon: None,
+                        openapi: None,
+                        builtin: None,
+                        resolve_command_in_shell: None,
+                        package: None,
+                        confirm_tool_calls: None,
+                        features: Default::default(),
+                        trace_file: None,
+                        tool_name_prefix: None,
+                        instructions: None,
+                        use_server_instructions: None,
+                    },
+                )],
+                cx,
+            );
+
+            cx.run_until_parked();
+        }
+
+        // Ensure that mcp-1 is not restarted when the configuration was not changed
+        {
+            let _server_events = assert_server_events(&store, vec![], cx);
+            set_context_server_configuration(
+                vec![(
+                    server_1_id.0.clone(),
+                    ContextServerConfiguration {
+                        command: None,
+                        settings: Some(json!({
+                            "somevalue": false
+                        })),
+                        replay: None,
+                        sandbox: None,
+                        confine_paths_to_worktrees: false,
+                        max_requests_per_minute: None,
+                        max_result_bytes: None,
+                        tool_call_timeout_seconds: None,
+                        keep_warm_seconds: None,
+                        tool_ui_text: Default::default(),
+                        tool_examples: Default::default(),
+                        tool_models: Default::default(),
+                        tool_env: Default::default(),
+                        display_name: None,
+                        icon: None,
+                        openapi: None,
+                        builtin: None,
+                        resolve_command_in_shell: None,
+                        package: None,
+                        confirm_tool_calls: None,
+                        features: Default::default(),
+                        trace_file: None,
+                        tool_name_prefix: None,
+                        instructions: None,
+                        use_server_instructions: None,
+                    },
+                )],
+                cx,
+            );
+
+            cx.run_until_parked();
+        }
+
+        // Ensure that mcp-2 is started once it is added to the settings
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![
+                    (server_2_id.clone(), ContextServerStatus::Starting),
+                    (server_2_id.clone(), ContextServerStatus::Running),
+                ],
+                cx,
+            );
+            set_context_server_configuration(
+                vec![
+                    (
+                        server_1_id.0.clone(),
+                        ContextServerConfiguration {
+                            command: None,
+                            settings: Some(json!({
+                                "somevalue": false
+                            })),
+                            replay: None,
+                            sandbox: None,
+                            confine_paths_to_worktrees: false,
+                            max_requests_per_minute: None,
+                            max_result_bytes: None,
+                            tool_call_timeout_seconds: None,
+                            keep_warm_seconds: None,
+                            tool_ui_text: Default::default(),
+                            tool_examples: Default::default(),
+                            tool_models: Default::default(),
+                            tool_env: Default::default(),
+                            display_name: None,
+                            icon: None,
+                            openapi: None,
+                            builtin: None,
+                            resolve_command_in_shell: None,
+                            package: None,
+                            confirm_tool_calls: None,
+                            features: Default::default(),
+                            trace_file: None,
+                            tool_name_prefix: None,
+                            instructions: None,
+                            use_server_instructions: None,
+                        },
+                    ),
+                    (
+                        server_2_id.0.clone(),
+                        ContextServerConfiguration {
+                            command: None,
+                            settings: Some(json!({
+                                "somevalue": true
+                            })),
+                            replay: None,
+                            sandbox: None,
+                            confine_paths_to_worktrees: false,
+                            max_requests_per_minute: None,
+                            max_result_bytes: None,
+                            tool_call_timeout_seconds: None,
+                            keep_warm_seconds: None,
+                            tool_ui_text: Default::default(),
+                            tool_examples: Default::default(),
+                            tool_models: Default::default(),
+                            tool_env: Default::default(),
+                            display_name: None,
+                            icon: None,
+                            openapi: None,
+                            builtin: None,
+                            resolve_command_in_shell: None,
+                            package: None,
+                            confirm_tool_calls: None,
+                            features: Default::default(),
+                            trace_file: None,
+                            tool_name_prefix: None,
+                            instructions: None,
+                            use_server_instructions: None,
+                        },
+                    ),
+                ],
+                cx,
+            );
+
+            cx.run_until_parked();
+        }
+
+        // Ensure that mcp-2 is removed once it is removed from the settings
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![(server_2_id.clone(), ContextServerStatus::Stopped)],
+                cx,
+            );
+            set_context_server_configuration(
+                vec![(
+                    server_1_id.0.clone(),
+                    ContextServerConfiguration {
+                        command: None,
+                        settings: Some(json!({
+                            "somevalue": false
+                        })),
+                        replay: None,
+                        sandbox: None,
+                        confine_paths_to_worktrees: false,
+                        max_requests_per_minute: None,
+                        max_result_bytes: None,
+                        tool_call_timeout_seconds: None,
+                        keep_warm_seconds: None,
+                        tool_ui_text: Default::default(),
+                        tool_examples: Default::default(),
+                        tool_models: Default::default(),
+                        tool_env: Default::default(),
+                        display_name: None,
+                        icon: None,
+                        openapi: None,
+                        builtin: None,
+                        resolve_command_in_shell: None,
+                        package: None,
+                        confirm_tool_calls: None,
+                        features: Default::default(),
+                        trace_file: None,
+                        tool_name_prefix: None,
+                        instructions: None,
+                        use_server_instructions: None,
+                    },
+                )],
+                cx,
+            );
+
+            cx.run_until_parked();
+
+            cx.update(|cx| {
+                assert_eq!(store.read(cx).status_for_server(&server_2_id), None);
+            });
+        }
+
+        // Ensure that mcp-1 is stopped while it isn't active, and started again once it is
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![(server_1_id.clone(), ContextServerStatus::Stopped)],
+                cx,
+            );
+            store.update(cx, |store, cx| {
+                store.set_active_servers(Some(HashSet::default()), cx)
+            });
+
+            cx.run_until_parked();
+        }
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![
+                    (server_1_id.clone(), ContextServerStatus::Starting),
+                    (server_1_id.clone(), ContextServerStatus::Running),
+                ],
+                cx,
+            );
+            store.update(cx, |store, cx| store.set_active_servers(None, cx));
+
+            cx.run_until_parked();
+        }
+
+        // Ensure that mcp-1 is stopped while it's disabled, and started again once it's enabled
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![(server_1_id.clone(), ContextServerStatus::Stopped)],
+                cx,
+            );
+            store.update(cx, |store, cx| {
+                store.set_server_enabled(&server_1_id, false, cx)
+            });
+
+            cx.run_until_parked();
+        }
+        {
+            let _server_events = assert_server_events(
+                &store,
+                vec![
+                    (server_1_id.clone(), ContextServerStatus::Starting),
+                    (server_1_id.clone(), ContextServerStatus::Running),
+                ],
+                cx,
+            );
+            store.update(cx, |store, cx| {
+                store.set_server_enabled(&server_1_id, true, cx)
+            });
+
+            cx.run_until_parked();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_in_shell_environment() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let dir = util::test::TempTree::new(json!({ "bin": { "npx": "" } }));
+        let npx = dir.path().join("bin/npx");
+        std::fs::set_permissions(&npx, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let shell_environment = HashMap::from_iter([
+            (
+                "PATH".to_string(),
+                dir.path().join("bin").to_string_lossy().into_owned(),
+            ),
+            ("NVM_DIR".to_string(), "/home/user/.nvm".to_string()),
+        ]);
+
+        let mut command = ContextServerCommand {
+            path: "npx".into(),
+            args: vec!["-y".into(), "@example/mcp-server".into()],
+            env: Some(HashMap::from_iter([(
+                "NVM_DIR".to_string(),
+                "/opt/nvm".to_string(),
+            )])),
+            cwd: None,
+        };
+        resolve_in_shell_environment(&mut command, &shell_environment, dir.path());
+        assert_eq!(command.path, npx.to_string_lossy());
+        let env = command.env.unwrap();
+        assert_eq!(env["PATH"], shell_environment["PATH"]);
+        assert_eq!(env["NVM_DIR"], "/opt/nvm");
+
+        let mut command = ContextServerCommand {
+            path: "uvx".into(),
+            args: Vec::new(),
+            env: None,
+            cwd: None,
+        };
+        resolve_in_shell_environment(&mut command, &shell_environment, dir.path());
+        assert_eq!(command.path, "uvx");
+    }
+
+    #[test]
+    fn test_trust_digest_of_env() {
+        let configuration = |env: &[(&str, &str)]| ContextServerConfiguration {
+            command: Some(ContextServerCommand {
+                path: "mcp-server".into(),
+                args: Vec::new(),
+                env: Some(HashMap::from_iter(
+                    env.iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string())),
+                )),
+                cwd: None,
+            }),
+            ..Default::default()
+        };
+        let trusted = trust_digest(&configuration(&[
+            ("TOKEN", "old"),
+            ("NODE_OPTIONS", "--max-old-space-size=4096"),
+        ]));
+        assert_eq!(
+            trust_digest(&configuration(&[
+                ("TOKEN", "new"),
+                ("NODE_OPTIONS", "--max-old-space-size=4096"),
+            ])),
+            trusted
+        );
+        assert_ne!(
+            trust_digest(&configuration(&[
+                ("TOKEN", "old"),
+                ("NODE_OPTIONS", "--require /tmp/hook.js"),
+            ])),
+            trusted
+        );
+        assert_ne!(
+            trust_digest(&configuration(&[
+                ("TOKEN", "old"),
+                ("node_options", "--max-old-space-size=4096"),
+            ])),
+            trusted
+        );
+    }
+
+    #[test]
+    fn test_launch_summary() {
+        let configuration = serde_json::from_value::<ContextServerConfiguration>(json!({
+            "package": {
+                "registry": "npm",
+                "name": "@example/github",
+                "version": "1.2.3",
+                "env": {
+                    "GITHUB_TOKEN": "ghp_123",
+                    "LOG_LEVEL": "debug",
+                    "NODE_OPTIONS": "--require /tmp/hook.js",
+                },
+            },
+            "sandbox": { "allow_network": false },
+        }))
+        .unwrap();
+        assert_eq!(
+            launch_summary(&configuration),
+            "Installs and runs @example/github@1.2.3 from npm\n\
+            Sets the environment variables GITHUB_TOKEN=[redacted], LOG_LEVEL=debug, \
+            NODE_OPTIONS=--require /tmp/hook.js\n\
+            Runs in a sandbox without network access"
+        );
+    }
+
+    #[test]
+    fn test_server_program() {
+        let command = |path: &str, args: &[&str]| ContextServerConfiguration {
+            command: Some(ContextServerCommand {
+                path: path.into(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                env: None,
+                cwd: None,
+            }),
+            ..Default::default()
+        };
+        let staging = command("npx", &["-y", "@example/postgres", "postgres://staging"]);
+        let production = command("npx", &["-y", "@example/postgres", "postgres://production"]);
+        let github = command("npx", &["-y", "@example/github"]);
+        assert_eq!(
+            server_program(&staging).as_deref(),
+            Some("npx @example/postgres")
+        );
+        assert_eq!(server_program(&staging), server_program(&production));
+        assert_ne!(server_program(&staging), server_program(&github));
+        assert_eq!(
+            server_program(&command("mcp-server", &[])).as_deref(),
+            Some("mcp-server")
+        );
+        assert_eq!(server_program(&ContextServerConfiguration::default()), None);
+    }
+
+    #[test]
+    fn test_sandbox_scratch_dir() {
+        let sandboxes = paths::data_dir().join("context_server_sandboxes");
+        for id in ["github", "../../..", "a/b"] {
+            let scratch_dir = sandbox_scratch_dir(&ContextServerId(id.into()));
+            assert_eq!(scratch_dir.parent(), Some(sandboxes.as_path()));
+        }
+        assert_ne!(
+            sandbox_scratch_dir(&ContextServerId("a/b".into())),
+            sandbox_scratch_dir(&ContextServerId("a_b".into()))
+        );
+    }
+
+    #[test]
+    fn test_resolve_tool_confirmation() {
+        let confirmation = |confirm, source| ToolConfirmation { confirm, source };
+        assert_eq!(
+            resolve_tool_confirmation(None, None, None),
+            confirmation(true, ConfirmationSource::Default)
+        );
+        assert_eq!(
+            resolve_tool_confirmation(Some(false), Some(true), None),
+            confirmation(false, ConfirmationSource::User)
+        );
+        assert_eq!(
+            resolve_tool_confirmation(None, Some(false), None),
+            confirmation(false, ConfirmationSource::ProjectServers)
+        );
+        assert_eq!(
+            resolve_tool_confirmation(Some(false), None, Some(true)),
+            confirmation(true, ConfirmationSource::WorktreeSettings)
+        );
+        assert_eq!(
+            resolve_tool_confirmation(Some(true), None, Some(false)),
+            confirmation(true, ConfirmationSource::User)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_tool_confirmation_of_duplicates(cx: &mut TestAppContext) {
+        let (_fs, project) = setup_context_server_test(cx, json!({"code.rs": ""}), vec![]).await;
         let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
         let store = cx.new(|cx| {
             ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
         });
-
-        let server_1_id = ContextServerId("mcp-1".into());
-        let server_2_id = ContextServerId("mcp-2".into());
-
-        let transport_1 =
-            Arc::new(FakeTransport::new(
-                cx.executor(),
-                |_, request_type, _| match request_type {
-                    Some(RequestType::Initialize) => {
-                        Some(create_initialize_response("mcp-1".to_string()))
-                    }
-                    _ => None,
-                },
-            ));
-
-        let transport_2 =
-            Arc::new(FakeTransport::new(
-                cx.executor(),
-                |_, request_type, _| match request_type {
-                    Some(RequestType::Initialize) => {
-                        Some(create_initialize_response("mcp-2".to_string()))
-                    }
-                    _ => None,
-                },
-            ));
-
-        let server_1 = Arc::new(ContextServer::new(server_1_id.clone(), transport_1.clone()));
-        let server_2 = Arc::new(ContextServer::new(server_2_id.clone(), transport_2.clone()));
-
-        store
-            .update(cx, |store, cx| store.start_server(server_1, cx))
-            .unwrap();
-
-        cx.run_until_parked();
-
         cx.update(|cx| {
-            assert_eq!(
-                store.read(cx).status_for_server(&server_1_id),
-                Some(ContextServerStatus::Running)
-            );
-            assert_eq!(store.read(cx).status_for_server(&server_2_id), None);
+            SettingsStore::update_global(cx, |store, cx| {
+                store
+                    .set_user_settings(
+                        &serde_json::to_string(&json!({
+                            "context_servers": {
+                                "mcp-a": { "command": { "path": "server", "args": [] } },
+                                "mcp-b": {
+                                    "command": { "path": "server", "args": [] },
+                                    "confirm_tool_calls": false,
+                                },
+                            },
+                        }))
+                        .unwrap(),
+                        cx,
+                    )
+                    .unwrap();
+            })
+        });
+        let kept = ContextServerId("mcp-a".into());
+        store.update(cx, |store, _| {
+            store
+                .duplicate_servers
+                .insert(ContextServerId("mcp-b".into()), kept.clone());
         });
 
-        store
-            .update(cx, |store, cx| store.start_server(server_2.clone(), cx))
-            .unwrap();
-
-        cx.run_until_parked();
-
-        cx.update(|cx| {
+        // The duplicate's setting applies, since the kept server doesn't say.
+        store.read_with(cx, |store, cx| {
             assert_eq!(
-                store.read(cx).status_for_server(&server_1_id),
-                Some(ContextServerStatus::Running)
+                store.duplicates_of(&kept),
+                [&ContextServerId("mcp-b".into())]
             );
             assert_eq!(
-                store.read(cx).status_for_server(&server_2_id),
-                Some(ContextServerStatus::Running)
+                store.tool_confirmation(&kept, cx),
+                ToolConfirmation {
+                    confirm: false,
+                    source: ConfirmationSource::User,
+                }
             );
         });
+    }
 
-        store
-            .update(cx, |store, cx| store.stop_server(&server_2_id, cx))
+    #[gpui::test]
+    async fn test_tool_confirmation_of_every_worktree(cx: &mut TestAppContext) {
+        let (fs, project) = setup_context_server_test(cx, json!({"code.rs": ""}), vec![]).await;
+        fs.insert_tree(
+            path!("/other"),
+            json!({
+                ".zed": {
+                    "settings.json": r#"{ "context_servers": { "mcp-a": { "confirm_tool_calls": true } } }"#,
+                },
+            }),
+        )
+        .await;
+        project
+            .update(cx, |project, cx| {
+                project.find_or_create_worktree(path!("/other"), true, cx)
+            })
+            .await
             .unwrap();
-
+        cx.run_until_parked();
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+        });
         cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store
+                    .set_user_settings(
+                        &serde_json::to_string(&json!({
+                            "context_servers": {
+                                "mcp-a": {
+                                    "command": { "path": "server", "args": [] },
+                                    "confirm_tool_calls": false,
+                                },
+                            },
+                        }))
+                        .unwrap(),
+                        cx,
+                    )
+                    .unwrap();
+            })
+        });
+
+        // The second worktree's settings require confirmation, even though the first's don't
+        // say anything.
+        store.read_with(cx, |store, cx| {
             assert_eq!(
-                store.read(cx).status_for_server(&server_1_id),
-                Some(ContextServerStatus::Running)
-            );
-            assert_eq!(
-                store.read(cx).status_for_server(&server_2_id),
-                Some(ContextServerStatus::Stopped)
+                store.tool_confirmation(&ContextServerId("mcp-a".into()), cx),
+                ToolConfirmation {
+                    confirm: true,
+                    source: ConfirmationSource::WorktreeSettings,
+                }
             );
         });
     }
 
     #[gpui::test]
-    async fn test_context_server_status_events(cx: &mut TestAppContext) {
-        const SERVER_1_ID: &'static str = "mcp-1";
-        const SERVER_2_ID: &'static str = "mcp-2";
-
+    async fn test_icon_paths_are_relative_to_their_settings_file(cx: &mut TestAppContext) {
         let (_fs, project) = setup_context_server_test(
             cx,
-            json!({"code.rs": ""}),
-            vec![
-                (SERVER_1_ID.into(), ContextServerConfiguration::default()),
-                (SERVER_2_ID.into(), ContextServerConfiguration::default()),
-            ],
+            json!({
+                ".zed": {
+                    "settings.json": r#"{ "context_servers": { "mcp-b": { "icon": "icons/b.png" } } }"#,
+                },
+            }),
+            vec![],
         )
         .await;
-
+        cx.run_until_parked();
         let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
         let store = cx.new(|cx| {
             ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
         });
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store
+                    .set_user_settings(
+                        &serde_json::to_string(&json!({
+                            "context_servers": {
+                                "mcp-a": {
+                                    "command": { "path": "server", "args": [] },
+                                    "icon": "icons/a.png",
+                                },
+                                "mcp-b": {
+                                    "command": { "path": "server", "args": ["b"] },
+                                    "icon": "icons/user-b.png",
+                                },
+                                "mcp-c": {
+                                    "command": { "path": "server", "args": ["c"] },
+                                    "icon": path!("/icons/c.png"),
+                                },
+                            },
+                        }))
+                        .unwrap(),
+                        cx,
+                    )
+                    .unwrap();
+            })
+        });
 
-        let server_1_id = ContextServerId("mcp-1".into());
-        let server_2_id = ContextServerId("mcp-2".into());
+        store.read_with(cx, |store, cx| {
+            let servers = store.configured_servers(cx);
+            let icon = |id: &str| servers[id].icon.clone().unwrap();
+            assert_eq!(
+                icon("mcp-a"),
+                paths::settings_file().parent().unwrap().join("icons/a.png")
+            );
+            assert_eq!(
+                icon("mcp-b"),
+                Path::new(path!("/test/.zed")).join("icons/b.png")
+            );
+            assert_eq!(icon("mcp-c"), Path::new(path!("/icons/c.png")));
+        });
+    }
 
-        let transport_1 =
-            Arc::new(FakeTransport::new(
-                cx.executor(),
-                |_, request_type, _| match request_type {
-                    Some(RequestType::Initialize) => {
-                        Some(create_initialize_response("mcp-1".to_string()))
-                    }
-                    _ => None,
-                },
-            ));
+    #[test]
+    fn test_deduplicate_servers() {
+        let command = |path: &str| ContextServerConfiguration {
+            command: Some(ContextServerCommand {
+                path: path.into(),
+                args: vec!["--stdio".into()],
+                env: None,
+                cwd: None,
+            }),
+            ..Default::default()
+        };
+        let mut servers = HashMap::from_iter([
+            (Arc::from("github"), command("github-mcp")),
+            (Arc::from("gh"), command("github-mcp")),
+            (Arc::from("gh-project"), command("github-mcp")),
+            (Arc::from("postgres"), command("postgres-mcp")),
+            (
+                Arc::from("settings-only"),
+                ContextServerConfiguration::default(),
+            ),
+        ]);
+        // The same server with another account's token runs separately.
+        let mut github_work = command("github-mcp");
+        if let Some(command) = github_work.command.as_mut() {
+            command.env = Some(HashMap::from_iter([(
+                "GITHUB_TOKEN".into(),
+                "work-token".into(),
+            )]));
+        }
+        servers.insert(Arc::from("github-work"), github_work);
+        let duplicates = deduplicate_servers(&mut servers, |id| match id.as_ref() {
+            "github" => 0,
+            "gh-project" => 2,
+            _ => 1,
+        });
 
-        let transport_2 =
-            Arc::new(FakeTransport::new(
-                cx.executor(),
-                |_, request_type, _| match request_type {
-                    Some(RequestType::Initialize) => {
-                        Some(create_initialize_response("mcp-2".to_string()))
-                    }
-                    _ => None,
-                },
-            ));
+        let mut ids = servers.keys().map(|id| id.as_ref()).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, ["github", "github-work", "postgres", "settings-only"]);
+        assert_eq!(duplicates.len(), 2);
+        for duplicate in ["gh", "gh-project"] {
+            assert_eq!(
+                duplicates.get(&ContextServerId(duplicate.into())),
+                Some(&ContextServerId("github".into()))
+            );
+        }
+    }
 
-        let server_1 = Arc::new(ContextServer::new(server_1_id.clone(), transport_1.clone()));
-        let server_2 = Arc::new(ContextServer::new(server_2_id.clone(), transport_2.clone()));
+    #[test]
+    fn test_renamed_servers() {
+        let command = |path: &str| ContextServerConfiguration {
+            command: Some(ContextServerCommand {
+                path: path.into(),
+                args: Vec::new(),
+                env: None,
+                cwd: None,
+            }),
+            ..Default::default()
+        };
+        let previous = HashMap::from_iter([
+            (Arc::from("github"), launch_digest(&command("github-mcp"))),
+            (
+                Arc::from("postgres"),
+                launch_digest(&command("postgres-mcp")),
+            ),
+            (Arc::from("sqlite"), launch_digest(&command("sqlite-mcp"))),
+            (
+                Arc::from("sqlite-copy"),
+                launch_digest(&command("sqlite-mcp")),
+            ),
+        ]);
+        let servers = HashMap::from_iter([
+            (Arc::from("gh"), command("github-mcp")),
+            (Arc::from("postgres"), command("postgres-mcp")),
+            (Arc::from("pg"), command("postgres-mcp")),
+            (Arc::from("db"), command("sqlite-mcp")),
+            (Arc::from("fetch"), command("fetch-mcp")),
+        ]);
+        assert_eq!(
+            renamed_servers(&previous, &servers),
+            [(
+                ContextServerId("github".into()),
+                ContextServerId("gh".into())
+            )]
+        );
+    }
 
-        let _server_events = assert_server_events(
-            &store,
-            vec![
-                (server_1_id.clone(), ContextServerStatus::Starting),
-                (server_1_id.clone(), ContextServerStatus::Running),
-                (server_2_id.clone(), ContextServerStatus::Starting),
-                (server_2_id.clone(), ContextServerStatus::Running),
-                (server_2_id.clone(), ContextServerStatus::Stopped),
-            ],
-            cx,
+    #[test]
+    fn test_temporary_server_configuration() {
+        let (name, configuration) =
+            temporary_server_configuration("npx -y @example/postgres-mcp 'postgres://db/app'")
+                .unwrap();
+        assert_eq!(name, "postgres-mcp");
+        let command = configuration.command.unwrap();
+        assert_eq!(command.path, "npx");
+        assert_eq!(
+            command.args,
+            ["-y", "@example/postgres-mcp", "postgres://db/app"]
         );
 
-        store
-            .update(cx, |store, cx| store.start_server(server_1, cx))
-            .unwrap();
+        let (name, configuration) =
+            temporary_server_configuration("https://api.example.com/openapi.json").unwrap();
+        assert_eq!(name, "api.example.com");
+        assert_eq!(
+            configuration.openapi.unwrap().spec_url,
+            "https://api.example.com/openapi.json"
+        );
+
+        assert!(temporary_server_configuration("  ").is_err());
+        assert!(temporary_server_configuration("https://").is_err());
+    }
+
+    #[gpui::test]
+    async fn test_project_servers_require_trust(cx: &mut TestAppContext) {
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({
+                ".mcp.json": r#"{
+                    "mcpServers": {
+                        "project-mcp": { "command": "project-mcp", "args": ["--stdio"] },
+                        "shared-mcp": { "command": "project-shared-mcp" },
+                        "remote-mcp": { "type": "http", "url": "https://example.com/mcp" }
+                    }
+                }"#,
+            }),
+            vec![(
+                "shared-mcp".into(),
+                ContextServerConfiguration {
+                    settings: Some(json!({ "from": "user" })),
+                    ..Default::default()
+                },
+            )],
+        )
+        .await;
 
+        let executor = cx.executor();
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            ContextServerStore::test_maintain_server_loop(
+                Box::new(move |id, _| {
+                    FakeContextServer::new(id.0.to_string(), executor.clone())
+                        .context_server(id.clone())
+                }),
+                registry.clone(),
+                project.read(cx).worktree_store(),
+                cx,
+            )
+        });
         cx.run_until_parked();
 
-        store
-            .update(cx, |store, cx| store.start_server(server_2.clone(), cx))
-            .unwrap();
+        let project_server_id = ContextServerId("project-mcp".into());
+        let shared_server_id = ContextServerId("shared-mcp".into());
+        let (worktree_id, digest) = store.read_with(cx, |store, _| {
+            let untrusted = store.untrusted_project_servers();
+            assert_eq!(untrusted.len(), 1);
+            let project_servers = store.project_servers(untrusted[0]).unwrap();
+            let mut names = project_servers.servers.keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(names, [Arc::from("project-mcp"), Arc::from("shared-mcp")]);
+            assert_eq!(store.status_for_server(&project_server_id), None);
+            (untrusted[0], project_servers.digest.clone())
+        });
 
+        // Trusting a different version of the file doesn't run its servers.
+        store.update(cx, |store, cx| {
+            store.trust_project_servers(worktree_id, "stale", cx)
+        });
         cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert_eq!(store.status_for_server(&project_server_id), None);
+        });
 
-        store
-            .update(cx, |store, cx| store.stop_server(&server_2_id, cx))
-            .unwrap();
+        store.update(cx, |store, cx| {
+            store.trust_project_servers(worktree_id, &digest, cx)
+        });
+        cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert!(store.untrusted_project_servers().is_empty());
+            assert_eq!(
+                store.status_for_server(&project_server_id),
+                Some(ContextServerStatus::Running)
+            );
+            // User settings take precedence over the project's servers.
+            let shared_configuration = store.configuration_for_server(&shared_server_id).unwrap();
+            assert_eq!(
+                shared_configuration.settings,
+                Some(json!({ "from": "user" }))
+            );
+        });
     }
 
-    #[gpui::test(iterations = 25)]
-    async fn test_context_server_concurrent_starts(cx: &mut TestAppContext) {
-        const SERVER_1_ID: &'static str = "mcp-1";
-
+    #[gpui::test]
+    async fn test_context_server_kept_warm(cx: &mut TestAppContext) {
+        let server_id = ContextServerId("mcp-1".into());
         let (_fs, project) = setup_context_server_test(
             cx,
             json!({"code.rs": ""}),
-            vec![(SERVER_1_ID.into(), ContextServerConfiguration::default())],
+            vec![(
+                server_id.0.clone(),
+                ContextServerConfiguration {
+                    keep_warm_seconds: Some(60),
+                    ..Default::default()
+                },
+            )],
         )
         .await;
 
+        let executor = cx.executor();
+        let created_servers = Rc::new(RefCell::new(0));
         let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
         let store = cx.new(|cx| {
-            ContextServerStore::test(registry.clone(), project.read(cx).worktree_store(), cx)
+            let created_servers = created_servers.clone();
+            ContextServerStore::test_maintain_server_loop(
+                Box::new(move |id, _| {
+                    *created_servers.borrow_mut() += 1;
+                    FakeContextServer::new(id.0.to_string(), executor.clone())
+                        .context_server(id.clone())
+                }),
+                registry.clone(),
+                project.read(cx).worktree_store(),
+                cx,
+            )
         });
+        cx.run_until_parked();
+        assert_eq!(*created_servers.borrow(), 1);
 
-        let server_id = ContextServerId(SERVER_1_ID.into());
-
-        let transport_1 =
-            Arc::new(FakeTransport::new(
-                cx.executor(),
-                |_, request_type, _| match request_type {
-                    Some(RequestType::Initialize) => {
-                        Some(create_initialize_response(SERVER_1_ID.to_string()))
-                    }
-                    _ => None,
-                },
-            ));
-
-        let transport_2 =
-            Arc::new(FakeTransport::new(
-                cx.executor(),
-                |_, request_type, _| match request_type {
-                    Some(RequestType::Initialize) => {
-                        Some(create_initialize_response(SERVER_1_ID.to_string()))
-                    }
-                    _ => None,
-                },
-            ));
+        // Switching to a profile without the server and back reuses the running server.
+        store.update(cx, |store, cx| {
+            store.set_active_servers(Some(HashSet::default()), cx)
+        });
+        cx.run_until_parked();
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_id),
+                Some(ContextServerStatus::Stopped)
+            );
+        });
+        store.update(cx, |store, cx| store.set_active_servers(None, cx));
+        cx.run_until_parked();
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_id),
+                Some(ContextServerStatus::Running)
+            );
+        });
+        assert_eq!(*created_servers.borrow(), 1);
 
-        let server_with_same_id_1 = Arc::new(ContextServer::new(server_id.clone(), transport_1));
-        let server_with_same_id_2 = Arc::new(ContextServer::new(server_id.clone(), transport_2));
+        // Once it's been unused for longer than it's kept warm, it's started again.
+        store.update(cx, |store, cx| {
+            store.set_active_servers(Some(HashSet::default()), cx)
+        });
+        cx.run_until_parked();
+        cx.executor().advance_clock(Duration::from_secs(61));
+        cx.run_until_parked();
+        store.update(cx, |store, cx| store.set_active_servers(None, cx));
+        cx.run_until_parked();
+        cx.update(|cx| {
+            assert_eq!(
+                store.read(cx).status_for_server(&server_id),
+                Some(ContextServerStatus::Running)
+            );
+        });
+        assert_eq!(*created_servers.borrow(), 2);
+    }
 
-        // If we start another server with the same id, we should report that we stopped the previous one
-        let _server_events = assert_server_events(
-            &store,
+    #[gpui::test]
+    async fn test_servers_confined_to_worktrees_restart_when_worktrees_change(
+        cx: &mut TestAppContext,
+    ) {
+        let confined_id = ContextServerId("confined".into());
+        let other_id = ContextServerId("other".into());
+        let (fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
             vec![
-                (server_id.clone(), ContextServerStatus::Starting),
-                (server_id.clone(), ContextServerStatus::Stopped),
-                (server_id.clone(), ContextServerStatus::Starting),
-                (server_id.clone(), ContextServerStatus::Running),
+                (
+                    confined_id.0.clone(),
+                    ContextServerConfiguration {
+                        features: ContextServerFeatures {
+                            resources_outside_worktrees: false,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                ),
+                (other_id.0.clone(), ContextServerConfiguration::default()),
             ],
-            cx,
-        );
+        )
+        .await;
+        fs.insert_tree(path!("/other"), json!({})).await;
+
+        let executor = cx.executor();
+        let created_servers = Rc::new(RefCell::new(Vec::new()));
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            let created_servers = created_servers.clone();
+            ContextServerStore::test_maintain_server_loop(
+                Box::new(move |id, _| {
+                    created_servers.borrow_mut().push(id.clone());
+                    FakeContextServer::new(id.0.to_string(), executor.clone())
+                        .context_server(id.clone())
+                }),
+                registry.clone(),
+                project.read(cx).worktree_store(),
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        created_servers.borrow_mut().clear();
 
-        store
-            .update(cx, |store, cx| {
-                store.start_server(server_with_same_id_1.clone(), cx)
-            })
-            .unwrap();
-        store
-            .update(cx, |store, cx| {
-                store.start_server(server_with_same_id_2.clone(), cx)
+        project
+            .update(cx, |project, cx| {
+                project.find_or_create_worktree(path!("/other"), true, cx)
             })
+            .await
             .unwrap();
-        cx.update(|cx| {
+        cx.run_until_parked();
+        assert_eq!(*created_servers.borrow(), [confined_id.clone()]);
+        store.read_with(cx, |store, _| {
             assert_eq!(
-                store.read(cx).status_for_server(&server_id),
-                Some(ContextServerStatus::Starting)
+                store.status_for_server(&confined_id),
+                Some(ContextServerStatus::Running)
+            );
+            assert_eq!(
+                store.launch_worktrees[&confined_id],
+                [
+                    PathBuf::from(path!("/test")),
+                    PathBuf::from(path!("/other"))
+                ]
             );
         });
+    }
+
+    #[gpui::test]
+    async fn test_settings_changes_are_debounced(cx: &mut TestAppContext) {
+        let server_id = ContextServerId("mcp-1".into());
+        let configuration = |value: i32| ContextServerConfiguration {
+            settings: Some(json!({ "value": value })),
+            ..Default::default()
+        };
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![(server_id.0.clone(), configuration(0))],
+        )
+        .await;
 
+        let executor = cx.executor();
+        let created_servers = Rc::new(RefCell::new(0));
+        let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
+        let store = cx.new(|cx| {
+            let created_servers = created_servers.clone();
+            ContextServerStore::test_maintain_server_loop(
+                Box::new(move |id, _| {
+                    *created_servers.borrow_mut() += 1;
+                    FakeContextServer::new(id.0.to_string(), executor.clone())
+                        .context_server(id.clone())
+                }),
+                registry.clone(),
+                project.read(cx).worktree_store(),
+                cx,
+            )
+        });
         cx.run_until_parked();
+        assert_eq!(*created_servers.borrow(), 1);
+        store.update(cx, |store, _| {
+            store.settings_update_debounce = SETTINGS_UPDATE_DEBOUNCE
+        });
 
+        // A burst of edits restarts the server once, after the settings settle.
+        for value in 1..=3 {
+            set_context_server_configuration(vec![(server_id.0.clone(), configuration(value))], cx);
+            cx.executor().advance_clock(SETTINGS_UPDATE_DEBOUNCE / 2);
+            cx.run_until_parked();
+        }
+        assert_eq!(*created_servers.borrow(), 1);
+        cx.executor().advance_clock(SETTINGS_UPDATE_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(*created_servers.borrow(), 2);
         cx.update(|cx| {
             assert_eq!(
-                store.read(cx).status_for_server(&server_id),
-                Some(ContextServerStatus::Running)
+                store
+                    .read(cx)
+                    .configuration_for_server(&server_id)
+                    .as_deref(),
+                Some(&configuration(3))
             );
         });
+
+        // Changing other settings doesn't update the servers.
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store
+                    .set_user_settings(
+                        &serde_json::to_string(&json!({
+                            "context_servers": { "mcp-1": configuration(3) },
+                            "load_direnv": "shell_hook",
+                        }))
+                        .unwrap(),
+                        cx,
+                    )
+                    .unwrap();
+            })
+        });
+        assert!(store.read_with(cx, |store, _| store.settings_update_task.is_none()));
     }
 
     #[gpui::test]
-    async fn test_context_server_maintain_servers_loop(cx: &mut TestAppContext) {
-        const SERVER_1_ID: &'static str = "mcp-1";
-        const SERVER_2_ID: &'static str = "mcp-2";
-
-        let server_1_id = ContextServerId(SERVER_1_ID.into());
-        let server_2_id = ContextServerId(SERVER_2_ID.into());
-
+    async fn test_servers_require_trust(cx: &mut TestAppContext) {
+        let command = |path: &str| ContextServerCommand {
+            path: path.into(),
+            args: vec!["--stdio".into()],
+            env: None,
+            cwd: None,
+        };
+        let command_with_env = |env: &[(&str, &str)]| ContextServerCommand {
+            env: Some(HashMap::from_iter(
+                env.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string())),
+            )),
+            ..command("mcp-server")
+        };
         let (_fs, project) = setup_context_server_test(
             cx,
             json!({"code.rs": ""}),
             vec![(
-                SERVER_1_ID.into(),
+                "mcp-1".into(),
                 ContextServerConfiguration {
-                    command: None,
-                    settings: Some(json!({
-                        "somevalue": true
-                    })),
+                    command: Some(command_with_env(&[("TOKEN", "old")])),
+                    ..Default::default()
                 },
             )],
         )
@@ -792,147 +4274,198 @@ mod tests {
         let executor = cx.executor();
         let registry = cx.new(|_| ContextServerDescriptorRegistry::new());
         let store = cx.new(|cx| {
-            ContextServerStore::test_maintain_server_loop(
+            let mut store = ContextServerStore::test_maintain_server_loop(
                 Box::new(move |id, _| {
-                    let transport = FakeTransport::new(executor.clone(), {
-                        let id = id.0.clone();
-                        move |_, request_type, _| match request_type {
-                            Some(RequestType::Initialize) => {
-                                Some(create_initialize_response(id.clone().to_string()))
-                            }
-                            _ => None,
-                        }
-                    });
-                    Arc::new(ContextServer::new(id.clone(), Arc::new(transport)))
+                    FakeContextServer::new(id.0.to_string(), executor.clone())
+                        .context_server(id.clone())
                 }),
                 registry.clone(),
                 project.read(cx).worktree_store(),
                 cx,
-            )
+            );
+            store.require_server_trust = true;
+            store
         });
+        cx.run_until_parked();
 
-        // Ensure that mcp-1 starts up
-        {
-            let _server_events = assert_server_events(
-                &store,
-                vec![
-                    (server_1_id.clone(), ContextServerStatus::Starting),
-                    (server_1_id.clone(), ContextServerStatus::Running),
-                ],
-                cx,
+        let server_id = ContextServerId("mcp-1".into());
+        let digest = store.read_with(cx, |store, _| {
+            assert_eq!(store.status_for_server(&server_id), None);
+            let server = &store.untrusted_servers()[&server_id];
+            assert_eq!(
+                server.summary,
+                "Runs `mcp-server --stdio`\nSets the environment variables TOKEN=[redacted]"
             );
-            cx.run_until_parked();
-        }
+            server.digest.clone()
+        });
 
-        // Ensure that mcp-1 is restarted when the configuration was changed
-        {
-            let _server_events = assert_server_events(
-                &store,
-                vec![
-                    (server_1_id.clone(), ContextServerStatus::Stopped),
-                    (server_1_id.clone(), ContextServerStatus::Starting),
-                    (server_1_id.clone(), ContextServerStatus::Running),
-                ],
-                cx,
+        // Trusting a different command doesn't run the server.
+        store.update(cx, |store, cx| store.trust_server(&server_id, "stale", cx));
+        cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert_eq!(store.status_for_server(&server_id), None);
+        });
+
+        store.update(cx, |store, cx| store.trust_server(&server_id, &digest, cx));
+        cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert!(store.untrusted_servers().is_empty());
+            assert_eq!(
+                store.status_for_server(&server_id),
+                Some(ContextServerStatus::Running)
             );
-            set_context_server_configuration(
-                vec![(
-                    server_1_id.0.clone(),
-                    ContextServerConfiguration {
-                        command: None,
-                        settings: Some(json!({
-                            "somevalue": false
-                        })),
-                    },
-                )],
-                cx,
+        });
+
+        // Changing the server's settings, or rotating a secret, keeps it running.
+        set_context_server_configuration(
+            vec![(
+                server_id.0.clone(),
+                ContextServerConfiguration {
+                    command: Some(command_with_env(&[("TOKEN", "new")])),
+                    settings: Some(json!({ "somevalue": true })),
+                    ..Default::default()
+                },
+            )],
+            cx,
+        );
+        cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert_eq!(
+                store.status_for_server(&server_id),
+                Some(ContextServerStatus::Running)
             );
+        });
 
+        // Setting another variable, or turning off confirmations, stops it until it's trusted
+        // again.
+        let untrusted_configurations = [
+            ContextServerConfiguration {
+                command: Some(command_with_env(&[
+                    ("TOKEN", "new"),
+                    ("LD_PRELOAD", "/tmp/hook.so"),
+                ])),
+                ..Default::default()
+            },
+            ContextServerConfiguration {
+                command: Some(command_with_env(&[("TOKEN", "new")])),
+                confirm_tool_calls: Some(false),
+                ..Default::default()
+            },
+        ];
+        for configuration in untrusted_configurations {
+            set_context_server_configuration(vec![(server_id.0.clone(), configuration)], cx);
             cx.run_until_parked();
+            store.read_with(cx, |store, _| {
+                assert_eq!(store.status_for_server(&server_id), None);
+                assert_ne!(store.untrusted_servers()[&server_id].digest, digest);
+            });
         }
 
-        // Ensure that mcp-1 is not restarted when the configuration was not changed
-        {
-            let _server_events = assert_server_events(&store, vec![], cx);
-            set_context_server_configuration(
-                vec![(
-                    server_1_id.0.clone(),
-                    ContextServerConfiguration {
-                        command: None,
-                        settings: Some(json!({
-                            "somevalue": false
-                        })),
-                    },
-                )],
-                cx,
-            );
+        // Changing its command stops it until it's trusted again.
+        set_context_server_configuration(
+            vec![(
+                server_id.0.clone(),
+                ContextServerConfiguration {
+                    command: Some(command("other-server")),
+                    ..Default::default()
+                },
+            )],
+            cx,
+        );
+        cx.run_until_parked();
+        store.read_with(cx, |store, _| {
+            assert_eq!(store.status_for_server(&server_id), None);
+            assert_ne!(store.untrusted_servers()[&server_id].digest, digest);
+        });
+    }
 
-            cx.run_until_parked();
-        }
+    #[gpui::test]
+    async fn test_native_context_server(cx: &mut TestAppContext) {
+        struct EchoServer;
 
-        // Ensure that mcp-2 is started once it is added to the settings
-        {
-            let _server_events = assert_server_events(
-                &store,
-                vec![
-                    (server_2_id.clone(), ContextServerStatus::Starting),
-                    (server_2_id.clone(), ContextServerStatus::Running),
-                ],
-                cx,
-            );
-            set_context_server_configuration(
-                vec![
-                    (
-                        server_1_id.0.clone(),
-                        ContextServerConfiguration {
-                            command: None,
-                            settings: Some(json!({
-                                "somevalue": false
-                            })),
-                        },
-                    ),
-                    (
-                        server_2_id.0.clone(),
-                        ContextServerConfiguration {
-                            command: None,
-                            settings: Some(json!({
-                                "somevalue": true
-                            })),
-                        },
-                    ),
-                ],
-                cx,
-            );
+        #[async_trait::async_trait]
+        impl BuiltinServer for EchoServer {
+            async fn tools(&self) -> Result<Vec<context_server::types::Tool>> {
+                Ok(vec![context_server::types::Tool {
+                    name: "echo".into(),
+                    description: None,
+                    input_schema: json!({ "type": "object" }),
+                    annotations: None,
+                }])
+            }
 
-            cx.run_until_parked();
+            async fn call_tool(
+                &self,
+                _name: &str,
+                arguments: serde_json::Map<String, serde_json::Value>,
+            ) -> Result<context_server::types::CallToolResponse> {
+                Ok(context_server::types::CallToolResponse {
+                    content: vec![context_server::types::ToolResponseContent::Text {
+                        text: serde_json::Value::Object(arguments).to_string(),
+                    }],
+                    is_error: None,
+                    structured_content: None,
+                    meta: None,
+                })
+            }
         }
 
-        // Ensure that mcp-2 is removed once it is removed from the settings
-        {
-            let _server_events = assert_server_events(
-                &store,
-                vec![(server_2_id.clone(), ContextServerStatus::Stopped)],
-                cx,
-            );
-            set_context_server_configuration(
-                vec![(
-                    server_1_id.0.clone(),
-                    ContextServerConfiguration {
-                        command: None,
-                        settings: Some(json!({
-                            "somevalue": false
-                        })),
-                    },
-                )],
+        let server_id = ContextServerId("echo".into());
+        // Settings that don't launch a server only adjust the native one.
+        let (_fs, project) = setup_context_server_test(
+            cx,
+            json!({"code.rs": ""}),
+            vec![(
+                server_id.0.clone(),
+                ContextServerConfiguration {
+                    confirm_tool_calls: Some(false),
+                    ..Default::default()
+                },
+            )],
+        )
+        .await;
+
+        let registry = cx.new(|_| {
+            let mut registry = ContextServerDescriptorRegistry::new();
+            registry.register_native_context_server(server_id.0.clone(), Arc::new(EchoServer));
+            registry
+        });
+        let store = cx.new(|cx| {
+            let mut store = ContextServerStore::test_maintain_server_loop(
+                Box::new(|_, _| unreachable!("native servers aren't created by the factory")),
+                registry.clone(),
+                project.read(cx).worktree_store(),
                 cx,
             );
+            store.context_server_factory = None;
+            store.require_server_trust = true;
+            store
+        });
+        cx.run_until_parked();
 
-            cx.run_until_parked();
-
-            cx.update(|cx| {
-                assert_eq!(store.read(cx).status_for_server(&server_2_id), None);
-            });
-        }
+        let server = store.read_with(cx, |store, _| {
+            assert!(store.untrusted_servers().is_empty());
+            assert_eq!(
+                store.status_for_server(&server_id),
+                Some(ContextServerStatus::Running)
+            );
+            store.get_running_server(&server_id).unwrap()
+        });
+        let protocol = server.client().unwrap();
+        let tools = protocol.list_tools().await.unwrap();
+        assert_eq!(tools.tools[0].name, "echo");
+        let response = protocol
+            .run_tool(
+                "echo",
+                Some(HashMap::from_iter([("text".into(), json!("hello"))])),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            &response.content[..],
+            [context_server::types::ToolResponseContent::Text { text }]
+                if text == r#"{"text":"hello"}"#
+        ));
     }
 
     fn set_context_server_configuration(
@@ -1001,6 +4534,12 @@ mod tests {
                         ix += 1;
                         *received_event_count.borrow_mut() += 1;
                     }
+                    Event::UntrustedProjectServers { .. }
+                    | Event::UntrustedServers
+                    | Event::PackageUpdateAvailable { .. }
+                    | Event::ServerOfflineChanged { .. }
+                    | Event::ServerRenamed { .. }
+                    | Event::ToolCall { .. } => {}
                 }
             });
             ServerEvents {
@@ -1033,99 +4572,4 @@ mod tests {
 
         (fs, project)
     }
-
-    fn create_initialize_response(server_name: String) -> serde_json::Value {
-        serde_json::to_value(&InitializeResponse {
-            protocol_version: ProtocolVersion(types::LATEST_PROTOCOL_VERSION.to_string()),
-            server_info: Implementation {
-                name: server_name,
-                version: "1.0.0".to_string(),
-            },
-            capabilities: ServerCapabilities::default(),
-            meta: None,
-        })
-        .unwrap()
-    }
-
-    struct FakeTransport {
-        on_request: Arc<
-            dyn Fn(u64, Option<RequestType>, serde_json::Value) -> Option<serde_json::Value>
-                + Send
-                + Sync,
-        >,
-        tx: futures::channel::mpsc::UnboundedSender<String>,
-        rx: Arc<Mutex<futures::channel::mpsc::UnboundedReceiver<String>>>,
-        executor: BackgroundExecutor,
-    }
-
-    impl FakeTransport {
-        fn new(
-            executor: BackgroundExecutor,
-            on_request: impl Fn(
-                u64,
-                Option<RequestType>,
-                serde_json::Value,
-            ) -> Option<serde_json::Value>
-            + 'static
-            + Send
-            + Sync,
-        ) -> Self {
-            let (tx, rx) = futures::channel::mpsc::unbounded();
-            Self {
-                on_request: Arc::new(on_request),
-                tx,
-                rx: Arc::new(Mutex::new(rx)),
-                executor,
-            }
-        }
-    }
-
-    #[async_trait::async_trait]
-    impl Transport for FakeTransport {
-        async fn send(&self, message: String) -> Result<()> {
-            if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&message) {
-                let id = msg.get("id").and_then(|id| id.as_u64()).unwrap_or(0);
-
-                if let Some(method) = msg.get("method") {
-                    let request_type = method
-                        .as_str()
-                        .and_then(|method| types::RequestType::try_from(method).ok());
-                    if let Some(payload) = (self.on_request.as_ref())(id, request_type, msg) {
-                        let response = serde_json::json!({
-                            "jsonrpc": "2.0",
-                            "id": id,
-                            "result": payload
-                        });
-
-                        self.tx
-                            .unbounded_send(response.to_string())
-                            .context("sending a message")?;
-                    }
-                }
-            }
-            Ok(())
-        }
-
-        fn receive(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
-            let rx = self.rx.clone();
-            let executor = self.executor.clone();
-            Box::pin(futures::stream::unfold(rx, move |rx| {
-                let executor = executor.clone();
-                async move {
-                    let mut rx_guard = rx.lock().await;
-                    executor.simulate_random_delay().await;
-                    if let Some(message) = rx_guard.next().await {
-                        drop(rx_guard);
-                        Some((message, rx))
-                    } else {
-                        None
-                    }
-                }
-            }))
-        }
-
-        fn receive_err(&self) -> Pin<Box<dyn Stream<Item = String> + Send>> {
-            Box::pin(futures::stream::empty())
-        }
-    }
 }
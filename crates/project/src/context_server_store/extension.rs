@@ -1,12 +1,17 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
 use context_server::ContextServerCommand;
+use context_server::builtin::{BuiltinServer, text_response};
+use context_server::middleware::{ToolCall, ToolMiddleware};
+use context_server::types;
 use extension::{
     ContextServerConfiguration, Extension, ExtensionContextServerProxy, ExtensionHostProxy,
-    ProjectDelegate,
+    ProjectDelegate, ToolMiddlewareManifestEntry,
 };
 use gpui::{App, AsyncApp, Entity, Task};
+use serde_json::{Map, Value};
 
 use crate::worktree_store::WorktreeStore;
 
@@ -72,6 +77,7 @@ impl registry::ContextServerDescriptor for ContextServerDescriptor {
                 path: command.command,
                 args: command.args,
                 env: Some(command.env.into_iter().collect()),
+                cwd: None,
             })
         })
     }
@@ -96,6 +102,103 @@ impl registry::ContextServerDescriptor for ContextServerDescriptor {
     }
 }
 
+/// A context server whose tools are implemented by the extension's Wasm module.
+struct InProcessContextServer {
+    id: Arc<str>,
+    extension: Arc<dyn Extension>,
+}
+
+#[async_trait]
+impl BuiltinServer for InProcessContextServer {
+    async fn tools(&self) -> Result<Vec<types::Tool>> {
+        let tools = self.extension.context_server_tools(self.id.clone()).await?;
+        Ok(tools
+            .into_iter()
+            .map(|tool| types::Tool {
+                name: tool.name,
+                description: tool.description,
+                input_schema: tool.input_schema,
+                annotations: tool.annotations.map(|annotations| types::ToolAnnotations {
+                    title: annotations.title,
+                    read_only_hint: annotations.read_only_hint,
+                    destructive_hint: annotations.destructive_hint,
+                    idempotent_hint: annotations.idempotent_hint,
+                    open_world_hint: annotations.open_world_hint,
+                    ui_text_template: None,
+                }),
+            })
+            .collect())
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Map<String, Value>,
+    ) -> Result<types::CallToolResponse> {
+        let output = self
+            .extension
+            .run_context_server_tool(self.id.clone(), name.into(), Value::Object(arguments))
+            .await?;
+        Ok(text_response(output.text, output.is_error))
+    }
+}
+
+/// Tool middleware implemented by the extension's Wasm module.
+struct ExtensionToolMiddleware {
+    id: Arc<str>,
+    extension: Arc<dyn Extension>,
+    manifest_entry: ToolMiddlewareManifestEntry,
+}
+
+#[async_trait]
+impl ToolMiddleware for ExtensionToolMiddleware {
+    async fn process_input(
+        &self,
+        call: ToolCall<'_>,
+        arguments: &mut Map<String, Value>,
+    ) -> Result<()> {
+        if !self.manifest_entry.process_input {
+            return Ok(());
+        }
+        let processed = self
+            .extension
+            .process_tool_input(
+                self.id.clone(),
+                call.server_id.0.clone(),
+                call.tool_name.into(),
+                Value::Object(std::mem::take(arguments)),
+            )
+            .await?;
+        let Value::Object(processed) = processed else {
+            anyhow::bail!("tool middleware {} didn't return an object", self.id);
+        };
+        *arguments = processed;
+        Ok(())
+    }
+
+    async fn process_output(
+        &self,
+        call: ToolCall<'_>,
+        response: &mut types::CallToolResponse,
+    ) -> Result<()> {
+        if !self.manifest_entry.process_output {
+            return Ok(());
+        }
+        let processed = self
+            .extension
+            .process_tool_output(
+                self.id.clone(),
+                call.server_id.0.clone(),
+                call.tool_name.into(),
+                serde_json::to_value(&*response)?,
+            )
+            .await?;
+        *response = serde_json::from_value(processed)
+            .with_context(|| format!("tool middleware {} returned an invalid response", self.id))?;
+        Ok(())
+    }
+}
+
 struct ContextServerDescriptorRegistryProxy {
     context_server_factory_registry: Entity<ContextServerDescriptorRegistry>,
 }
@@ -112,10 +215,288 @@ impl ExtensionContextServerProxy for ContextServerDescriptorRegistryProxy {
             });
     }
 
+    fn register_in_process_context_server(
+        &self,
+        extension: Arc<dyn Extension>,
+        id: Arc<str>,
+        cx: &mut App,
+    ) {
+        self.context_server_factory_registry
+            .update(cx, |registry, _| {
+                registry.register_native_context_server(
+                    id.clone(),
+                    Arc::new(InProcessContextServer { id, extension }),
+                )
+            });
+    }
+
     fn unregister_context_server(&self, server_id: Arc<str>, cx: &mut App) {
         self.context_server_factory_registry
             .update(cx, |registry, _| {
-                registry.unregister_context_server_descriptor_by_id(&server_id)
+                registry.unregister_context_server_descriptor_by_id(&server_id);
+                registry.unregister_native_context_server(&server_id);
+            });
+    }
+
+    fn register_tool_middleware(
+        &self,
+        extension: Arc<dyn Extension>,
+        id: Arc<str>,
+        manifest_entry: ToolMiddlewareManifestEntry,
+        cx: &mut App,
+    ) {
+        self.context_server_factory_registry
+            .update(cx, |registry, _| {
+                registry.register_tool_middleware(
+                    id.clone(),
+                    Arc::new(ExtensionToolMiddleware {
+                        id,
+                        extension,
+                        manifest_entry,
+                    }),
+                )
+            });
+    }
+
+    fn unregister_tool_middleware(&self, middleware_id: Arc<str>, cx: &mut App) {
+        self.context_server_factory_registry
+            .update(cx, |registry, _| {
+                registry.unregister_tool_middleware(&middleware_id)
             });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use extension::{
+        CodeLabel, Command, Completion, ContextServerTool, ContextServerToolAnnotations,
+        ContextServerToolOutput, DebugAdapterBinary, DebugTaskDefinition, ExtensionManifest,
+        KeyValueStoreDelegate, SlashCommand, SlashCommandArgumentCompletion, SlashCommandOutput,
+        Symbol, WorktreeDelegate,
+    };
+    use language::LanguageName;
+    use lsp::LanguageServerName;
+    use serde_json::json;
+
+    use super::*;
+
+    /// An extension that implements a context server with a tool that counts words.
+    struct WordCountExtension;
+
+    #[async_trait]
+    impl Extension for WordCountExtension {
+        fn manifest(&self) -> Arc<ExtensionManifest> {
+            unimplemented!()
+        }
+
+        fn work_dir(&self) -> Arc<Path> {
+            unimplemented!()
+        }
+
+        async fn language_server_command(
+            &self,
+            _: LanguageServerName,
+            _: LanguageName,
+            _: Arc<dyn WorktreeDelegate>,
+        ) -> Result<Command> {
+            unimplemented!()
+        }
+
+        async fn language_server_initialization_options(
+            &self,
+            _: LanguageServerName,
+            _: LanguageName,
+            _: Arc<dyn WorktreeDelegate>,
+        ) -> Result<Option<String>> {
+            unimplemented!()
+        }
+
+        async fn language_server_workspace_configuration(
+            &self,
+            _: LanguageServerName,
+            _: Arc<dyn WorktreeDelegate>,
+        ) -> Result<Option<String>> {
+            unimplemented!()
+        }
+
+        async fn language_server_additional_initialization_options(
+            &self,
+            _: LanguageServerName,
+            _: LanguageServerName,
+            _: Arc<dyn WorktreeDelegate>,
+        ) -> Result<Option<String>> {
+            unimplemented!()
+        }
+
+        async fn language_server_additional_workspace_configuration(
+            &self,
+            _: LanguageServerName,
+            _: LanguageServerName,
+            _: Arc<dyn WorktreeDelegate>,
+        ) -> Result<Option<String>> {
+            unimplemented!()
+        }
+
+        async fn labels_for_completions(
+            &self,
+            _: LanguageServerName,
+            _: Vec<Completion>,
+        ) -> Result<Vec<Option<CodeLabel>>> {
+            unimplemented!()
+        }
+
+        async fn labels_for_symbols(
+            &self,
+            _: LanguageServerName,
+            _: Vec<Symbol>,
+        ) -> Result<Vec<Option<CodeLabel>>> {
+            unimplemented!()
+        }
+
+        async fn complete_slash_command_argument(
+            &self,
+            _: SlashCommand,
+            _: Vec<String>,
+        ) -> Result<Vec<SlashCommandArgumentCompletion>> {
+            unimplemented!()
+        }
+
+        async fn run_slash_command(
+            &self,
+            _: SlashCommand,
+            _: Vec<String>,
+            _: Option<Arc<dyn WorktreeDelegate>>,
+        ) -> Result<SlashCommandOutput> {
+            unimplemented!()
+        }
+
+        async fn context_server_command(
+            &self,
+            _: Arc<str>,
+            _: Arc<dyn ProjectDelegate>,
+        ) -> Result<Command> {
+            unimplemented!()
+        }
+
+        async fn context_server_configuration(
+            &self,
+            _: Arc<str>,
+            _: Arc<dyn ProjectDelegate>,
+        ) -> Result<Option<ContextServerConfiguration>> {
+            unimplemented!()
+        }
+
+        async fn context_server_tools(&self, _: Arc<str>) -> Result<Vec<ContextServerTool>> {
+            Ok(vec![ContextServerTool {
+                name: "word_count".into(),
+                description: Some("Counts the words in a text.".into()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": { "text": { "type": "string" } },
+                }),
+                annotations: Some(ContextServerToolAnnotations {
+                    read_only_hint: Some(true),
+                    ..Default::default()
+                }),
+            }])
+        }
+
+        async fn run_context_server_tool(
+            &self,
+            _: Arc<str>,
+            _: Arc<str>,
+            arguments: Value,
+        ) -> Result<ContextServerToolOutput> {
+            let Some(text) = arguments.get("text").and_then(Value::as_str) else {
+                return Ok(ContextServerToolOutput {
+                    text: "`text` is required".into(),
+                    is_error: true,
+                });
+            };
+            Ok(ContextServerToolOutput {
+                text: text.split_whitespace().count().to_string(),
+                is_error: false,
+            })
+        }
+
+        async fn process_tool_input(
+            &self,
+            _: Arc<str>,
+            _: Arc<str>,
+            _: Arc<str>,
+            _: Value,
+        ) -> Result<Value> {
+            unimplemented!()
+        }
+
+        async fn process_tool_output(
+            &self,
+            _: Arc<str>,
+            _: Arc<str>,
+            _: Arc<str>,
+            _: Value,
+        ) -> Result<Value> {
+            unimplemented!()
+        }
+
+        async fn suggest_docs_packages(&self, _: Arc<str>) -> Result<Vec<String>> {
+            unimplemented!()
+        }
+
+        async fn index_docs(
+            &self,
+            _: Arc<str>,
+            _: Arc<str>,
+            _: Arc<dyn KeyValueStoreDelegate>,
+        ) -> Result<()> {
+            unimplemented!()
+        }
+
+        async fn get_dap_binary(
+            &self,
+            _: Arc<str>,
+            _: DebugTaskDefinition,
+            _: Option<PathBuf>,
+            _: Arc<dyn WorktreeDelegate>,
+        ) -> Result<DebugAdapterBinary> {
+            unimplemented!()
+        }
+
+        async fn get_dap_schema(&self) -> Result<Value> {
+            unimplemented!()
+        }
+    }
+
+    #[gpui::test]
+    async fn test_in_process_context_server() {
+        let server = InProcessContextServer {
+            id: "words".into(),
+            extension: Arc::new(WordCountExtension),
+        };
+
+        let tools = server.tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "word_count");
+        assert_eq!(
+            tools[0]
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.read_only_hint),
+            Some(true)
+        );
+
+        let arguments = Map::from_iter([("text".to_string(), json!("one two three"))]);
+        let response = server.call_tool("word_count", arguments).await.unwrap();
+        assert_eq!(response.is_error, Some(false));
+        assert!(matches!(
+            response.content.as_slice(),
+            [types::ToolResponseContent::Text { text }] if text == "3"
+        ));
+
+        // Errors the model can act on are returned as responses rather than failing the call.
+        let response = server.call_tool("word_count", Map::new()).await.unwrap();
+        assert_eq!(response.is_error, Some(true));
+    }
+}
@@ -0,0 +1,321 @@
+use std::path::Path;
+
+use anyhow::{Context as _, Result, bail};
+use context_server::{ContextServerCommand, ContextServerId};
+use node_runtime::NodeRuntime;
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+
+use crate::project_settings::{ContextServerPackage, PackageRegistry};
+
+/// A package installed for a context server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledPackage {
+    /// The command that runs the package.
+    pub command: ContextServerCommand,
+    /// The version that's installed.
+    pub version: String,
+    /// A newer version than the installed one, for packages that aren't pinned to a version.
+    pub latest_version: Option<String>,
+}
+
+/// Installs `package` in a directory of the server's own and returns the command that runs it.
+///
+/// Packages that aren't pinned to a version are installed at their latest version, and are only
+/// updated to a newer one when `update` is true, since a server's tools can change between
+/// versions.
+pub async fn install_package(
+    id: &ContextServerId,
+    package: &ContextServerPackage,
+    update: bool,
+    node: Option<NodeRuntime>,
+) -> Result<InstalledPackage> {
+    let directory = paths::data_dir()
+        .join("context_server_packages")
+        .join(format!("{:x}", Sha256::digest(id.0.as_bytes())));
+    smol::fs::create_dir_all(&directory)
+        .await
+        .with_context(|| format!("creating {directory:?}"))?;
+    match package.registry {
+        PackageRegistry::Npm => {
+            let node = node.context("Node.js isn't available to install npm packages with")?;
+            install_npm_package(package, update, &node, &directory).await
+        }
+        PackageRegistry::Pypi => install_pypi_package(package, update, &directory).await,
+    }
+}
+
+/// Returns the version of a package that isn't pinned to install, given the installed and latest
+/// versions.
+fn version_to_install(
+    installed_version: Option<&str>,
+    latest_version: Option<&str>,
+    update: bool,
+) -> Option<String> {
+    match installed_version {
+        Some(installed_version) if !update => Some(installed_version.to_string()),
+        // Keep using the installed version when the registry can't be reached.
+        installed_version => latest_version.or(installed_version).map(str::to_string),
+    }
+}
+
+async fn install_npm_package(
+    package: &ContextServerPackage,
+    update: bool,
+    node: &NodeRuntime,
+    directory: &Path,
+) -> Result<InstalledPackage> {
+    let installed_version = node
+        .npm_package_installed_version(directory, &package.name)
+        .await?;
+    let latest_version = if package.version.is_none() {
+        node.npm_package_latest_version(&package.name)
+            .await
+            .map_err(|error| {
+                log::warn!("failed to check for updates to {}: {error:#}", package.name)
+            })
+            .ok()
+    } else {
+        None
+    };
+    let version = match &package.version {
+        Some(version) => version.clone(),
+        None => version_to_install(
+            installed_version.as_deref(),
+            latest_version.as_deref(),
+            update,
+        )
+        .with_context(|| format!("failed to find the latest version of {}", package.name))?,
+    };
+    if installed_version.as_deref() != Some(version.as_str()) {
+        log::info!("installing {}@{version} in {directory:?}", package.name);
+        node.npm_install_packages(directory, &[(&package.name, &version)])
+            .await?;
+    }
+
+    let package_directory = directory.join("node_modules").join(&package.name);
+    let manifest = smol::fs::read_to_string(package_directory.join("package.json"))
+        .await
+        .with_context(|| format!("reading the package.json of {}", package.name))?;
+    let manifest = serde_json::from_str::<Value>(&manifest)?;
+    let script = npm_executable(&manifest, &package.name, package.bin.as_deref())?;
+
+    let script = package_directory.join(script);
+    let mut args = vec![script.to_string_lossy().into_owned()];
+    args.extend(package.args.iter().cloned());
+    Ok(InstalledPackage {
+        command: ContextServerCommand {
+            path: node.binary_path().await?.to_string_lossy().into_owned(),
+            args,
+            env: package.env.clone(),
+            cwd: None,
+        },
+        latest_version: latest_version.filter(|latest_version| *latest_version != version),
+        version,
+    })
+}
+
+/// Returns the script of the executable named `bin` in an npm package's manifest, or of the
+/// package's only executable when `bin` isn't given.
+fn npm_executable<'a>(manifest: &'a Value, name: &str, bin: Option<&str>) -> Result<&'a str> {
+    let script = match &manifest["bin"] {
+        Value::String(script) => return Ok(script),
+        Value::Object(executables) => match bin {
+            Some(bin) => executables.get(bin),
+            None if executables.len() == 1 => executables.values().next(),
+            None => {
+                let unscoped_name = name.rsplit('/').next().unwrap_or(name);
+                executables.get(unscoped_name)
+            }
+        },
+        _ => bail!("{name} has no executables"),
+    };
+    script.and_then(Value::as_str).with_context(|| match bin {
+        Some(bin) => format!("{name} has no executable named {bin}"),
+        None => format!("{name} has several executables, so `bin` must name the one to run"),
+    })
+}
+
+async fn install_pypi_package(
+    package: &ContextServerPackage,
+    update: bool,
+    directory: &Path,
+) -> Result<InstalledPackage> {
+    let venv = directory.join("venv");
+    let bin_directory = if cfg!(windows) {
+        venv.join("Scripts")
+    } else {
+        venv.join("bin")
+    };
+    if smol::fs::metadata(&bin_directory).await.is_err() {
+        let python = if cfg!(windows) { "python" } else { "python3" };
+        let mut command = util::command::new_smol_command(python);
+        command.args(["-m", "venv"]).arg(&venv);
+        run(command)
+            .await
+            .context("creating a Python virtual environment")?;
+    }
+    let pip = bin_directory.join("pip");
+    let pip_list = async |args: &[&str]| -> Result<Value> {
+        let mut command = util::command::new_smol_command(&pip);
+        command
+            .args(["list", "--format=json", "--disable-pip-version-check"])
+            .args(args);
+        Ok(serde_json::from_str(&run(command).await?)?)
+    };
+
+    let installed_version = pypi_version(&pip_list(&[]).await?, &package.name, "version");
+    let requirement = match &package.version {
+        Some(version) if installed_version.as_ref() != Some(version) => {
+            Some(format!("{}=={version}", package.name))
+        }
+        None if installed_version.is_none() || update => Some(package.name.clone()),
+        _ => None,
+    };
+    if let Some(requirement) = requirement {
+        log::info!("installing {requirement} in {venv:?}");
+        let mut command = util::command::new_smol_command(&pip);
+        command
+            .args(["install", "--upgrade", "--quiet"])
+            .arg("--disable-pip-version-check")
+            .arg(&requirement);
+        let installed = run(command)
+            .await
+            .with_context(|| format!("installing {requirement}"));
+        // Keep using the installed version when the package index can't be reached.
+        match (installed, &installed_version) {
+            (Err(error), None) => return Err(error),
+            (Err(error), Some(_)) => log::warn!("failed to update {}: {error:#}", package.name),
+            (Ok(_), _) => {}
+        }
+    }
+
+    let version = pypi_version(&pip_list(&[]).await?, &package.name, "version")
+        .with_context(|| format!("{} isn't installed", package.name))?;
+    let latest_version = if package.version.is_none() {
+        match pip_list(&["--outdated"]).await {
+            Ok(outdated) => pypi_version(&outdated, &package.name, "latest_version"),
+            Err(error) => {
+                log::warn!("failed to check for updates to {}: {error:#}", package.name);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let bin = package.bin.as_deref().unwrap_or(&package.name);
+    let executable = bin_directory.join(format!("{bin}{}", std::env::consts::EXE_SUFFIX));
+    Ok(InstalledPackage {
+        command: ContextServerCommand {
+            path: executable.to_string_lossy().into_owned(),
+            args: package.args.clone(),
+            env: package.env.clone(),
+            cwd: None,
+        },
+        version,
+        latest_version,
+    })
+}
+
+/// Returns a field of a package in the output of `pip list --format=json`.
+fn pypi_version(packages: &Value, name: &str, field: &str) -> Option<String> {
+    // Package names are compared the way PyPI normalizes them.
+    let normalize = |name: &str| {
+        name.to_lowercase()
+            .split(['-', '_', '.'])
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+    };
+    let name = normalize(name);
+    packages
+        .as_array()?
+        .iter()
+        .find(|package| package["name"].as_str().map(normalize).as_ref() == Some(&name))?
+        .get(field)?
+        .as_str()
+        .map(str::to_string)
+}
+
+async fn run(mut command: smol::process::Command) -> Result<String> {
+    let output = command.output().await?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_npm_executable() {
+        let manifest = json!({ "bin": "dist/index.js" });
+        assert_eq!(
+            npm_executable(&manifest, "@example/server", None).unwrap(),
+            "dist/index.js"
+        );
+
+        let manifest = json!({ "bin": { "mcp-server-github": "dist/index.js" } });
+        assert_eq!(
+            npm_executable(&manifest, "@example/server-github", None).unwrap(),
+            "dist/index.js"
+        );
+
+        let manifest = json!({
+            "bin": { "server": "dist/server.js", "server-cli": "dist/cli.js" }
+        });
+        assert_eq!(
+            npm_executable(&manifest, "@example/server", None).unwrap(),
+            "dist/server.js"
+        );
+        assert_eq!(
+            npm_executable(&manifest, "@example/server", Some("server-cli")).unwrap(),
+            "dist/cli.js"
+        );
+        assert!(npm_executable(&manifest, "@example/tools", None).is_err());
+        assert!(npm_executable(&manifest, "@example/server", Some("other")).is_err());
+        assert!(npm_executable(&json!({}), "@example/server", None).is_err());
+    }
+
+    #[test]
+    fn test_version_to_install() {
+        assert_eq!(
+            version_to_install(Some("1.0.0"), Some("1.1.0"), false).as_deref(),
+            Some("1.0.0")
+        );
+        assert_eq!(
+            version_to_install(Some("1.0.0"), Some("1.1.0"), true).as_deref(),
+            Some("1.1.0")
+        );
+        assert_eq!(
+            version_to_install(Some("1.0.0"), None, true).as_deref(),
+            Some("1.0.0")
+        );
+        assert_eq!(
+            version_to_install(None, Some("1.1.0"), false).as_deref(),
+            Some("1.1.0")
+        );
+        assert_eq!(version_to_install(None, None, false), None);
+    }
+
+    #[test]
+    fn test_pypi_version() {
+        let packages = json!([
+            { "name": "pip", "version": "24.0" },
+            { "name": "mcp_server.Fetch", "version": "2025.1.17", "latest_version": "2025.4.7" }
+        ]);
+        assert_eq!(
+            pypi_version(&packages, "mcp-server-fetch", "version").as_deref(),
+            Some("2025.1.17")
+        );
+        assert_eq!(
+            pypi_version(&packages, "mcp-server-fetch", "latest_version").as_deref(),
+            Some("2025.4.7")
+        );
+        assert_eq!(pypi_version(&packages, "mcp-server-git", "version"), None);
+    }
+}
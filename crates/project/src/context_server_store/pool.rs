@@ -0,0 +1,80 @@
+use std::{sync::Arc, time::Duration};
+
+use collections::HashMap;
+use context_server::ContextServer;
+use gpui::{App, Global, Task};
+use util::ResultExt as _;
+
+/// Servers that no project or profile uses anymore, kept running for a while so that switching
+/// back to them doesn't wait for them to start again.
+///
+/// Servers are keyed by a digest of how they're launched, and are only reused by a store that
+/// would launch them the same way.
+#[derive(Default)]
+pub struct ContextServerPool {
+    servers: HashMap<Arc<str>, Vec<PooledServer>>,
+}
+
+struct PooledServer {
+    server: Arc<ContextServer>,
+    _expire: Task<()>,
+}
+
+impl Global for ContextServerPool {}
+
+impl ContextServerPool {
+    /// Keeps `server` running for `keep_warm`, after which it's stopped unless it's been taken.
+    pub fn park(key: Arc<str>, server: Arc<ContextServer>, keep_warm: Duration, cx: &mut App) {
+        if server.client().is_none() {
+            return;
+        }
+        let expire = cx.spawn({
+            let key = key.clone();
+            let server = Arc::downgrade(&server);
+            async move |cx| {
+                cx.background_executor().timer(keep_warm).await;
+                cx.update(|cx| {
+                    let Some(server) = server.upgrade() else {
+                        return;
+                    };
+                    let pool = cx.default_global::<ContextServerPool>();
+                    if let Some(servers) = pool.servers.get_mut(&key) {
+                        servers.retain(|pooled| !Arc::ptr_eq(&pooled.server, &server));
+                        if servers.is_empty() {
+                            pool.servers.remove(&key);
+                        }
+                    }
+                    log::info!("stopping idle context server {}", server.id());
+                    server.stop().log_err();
+                })
+                .ok();
+            }
+        });
+        cx.default_global::<ContextServerPool>()
+            .servers
+            .entry(key)
+            .or_default()
+            .push(PooledServer {
+                server,
+                _expire: expire,
+            });
+    }
+
+    /// Takes a running server that was launched the way `key` describes, if there's one.
+    pub fn take(key: &str, cx: &mut App) -> Option<Arc<ContextServer>> {
+        let pool = cx.try_global::<ContextServerPool>()?;
+        if !pool.servers.contains_key(key) {
+            return None;
+        }
+        let pool = cx.global_mut::<ContextServerPool>();
+        let servers = pool.servers.get_mut(key)?;
+        // Servers that exited while they were pooled can't be reused.
+        let server = std::iter::from_fn(|| servers.pop())
+            .find(|pooled| pooled.server.client().is_some())
+            .map(|pooled| pooled.server);
+        if servers.is_empty() {
+            pool.servers.remove(key);
+        }
+        server
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use collections::HashMap;
-use context_server::ContextServerCommand;
+use context_server::{ContextServerCommand, builtin::BuiltinServer, middleware::ToolMiddleware};
 use extension::ContextServerConfiguration;
 use gpui::{App, AppContext as _, AsyncApp, Entity, Global, Task};
 
@@ -28,6 +28,9 @@ impl Global for GlobalContextServerDescriptorRegistry {}
 #[derive(Default)]
 pub struct ContextServerDescriptorRegistry {
     context_servers: HashMap<Arc<str>, Arc<dyn ContextServerDescriptor>>,
+    native_context_servers: HashMap<Arc<str>, Arc<dyn BuiltinServer>>,
+    /// Kept in registration order, which is the order the middleware runs in.
+    tool_middleware: Vec<(Arc<str>, Arc<dyn ToolMiddleware>)>,
 }
 
 impl ContextServerDescriptorRegistry {
@@ -47,6 +50,8 @@ impl ContextServerDescriptorRegistry {
     pub fn new() -> Self {
         Self {
             context_servers: HashMap::default(),
+            native_context_servers: HashMap::default(),
+            tool_middleware: Vec::new(),
         }
     }
 
@@ -74,4 +79,53 @@ impl ContextServerDescriptorRegistry {
     pub fn unregister_context_server_descriptor_by_id(&mut self, server_id: &str) {
         self.context_servers.remove(server_id);
     }
+
+    pub fn native_context_servers(&self) -> Vec<(Arc<str>, Arc<dyn BuiltinServer>)> {
+        self.native_context_servers
+            .iter()
+            .map(|(id, server)| (id.clone(), server.clone()))
+            .collect()
+    }
+
+    pub fn native_context_server(&self, id: &str) -> Option<Arc<dyn BuiltinServer>> {
+        self.native_context_servers.get(id).cloned()
+    }
+
+    /// Registers a server implemented in Rust, which runs in-process and is otherwise treated
+    /// like the servers that run a command.
+    pub fn register_native_context_server(&mut self, id: Arc<str>, server: Arc<dyn BuiltinServer>) {
+        self.native_context_servers.insert(id, server);
+    }
+
+    /// Unregisters the native server with the given ID.
+    pub fn unregister_native_context_server(&mut self, server_id: &str) {
+        self.native_context_servers.remove(server_id);
+    }
+
+    /// The middleware that runs around every tool call, in the order it runs in.
+    pub fn tool_middleware(&self) -> Vec<Arc<dyn ToolMiddleware>> {
+        self.tool_middleware
+            .iter()
+            .map(|(_, middleware)| middleware.clone())
+            .collect()
+    }
+
+    /// Registers middleware to run around every tool call, after the middleware registered
+    /// before it. Registering it again under the same ID replaces it in place.
+    pub fn register_tool_middleware(&mut self, id: Arc<str>, middleware: Arc<dyn ToolMiddleware>) {
+        match self
+            .tool_middleware
+            .iter_mut()
+            .find(|(existing_id, _)| *existing_id == id)
+        {
+            Some((_, existing)) => *existing = middleware,
+            None => self.tool_middleware.push((id, middleware)),
+        }
+    }
+
+    /// Unregisters the tool middleware with the given ID.
+    pub fn unregister_tool_middleware(&mut self, id: &str) {
+        self.tool_middleware
+            .retain(|(existing_id, _)| existing_id.as_ref() != id);
+    }
 }
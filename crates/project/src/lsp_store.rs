@@ -67,7 +67,7 @@ use rpc::{
     proto::{FromProto, ToProto},
 };
 use serde::Serialize;
-use settings::{Settings, SettingsLocation, SettingsStore};
+use settings::{LanguageServerSettingsSchema, Settings, SettingsLocation, SettingsStore};
 use sha2::{Digest, Sha256};
 use smol::channel::Sender;
 use snippet::Snippet;
@@ -271,6 +271,13 @@ impl LocalLspStore {
                         &delegate,
                     )
                     .await?;
+                    let settings_schema = adapter
+                        .adapter
+                        .clone()
+                        .settings_schema(fs.as_ref(), &delegate)
+                        .await
+                        .log_err()
+                        .flatten();
 
                     match (&mut initialization_options, override_options) {
                         (Some(initialization_options), Some(override_options)) => {
@@ -322,13 +329,20 @@ impl LocalLspStore {
                         )
                         .ok();
 
-                    anyhow::Ok(language_server)
+                    anyhow::Ok((language_server, settings_schema))
                 }
                 .await;
 
                 match result {
-                    Ok(server) => {
+                    Ok((server, settings_schema)) => {
                         this.update(cx, |this, mut cx| {
+                            if let Some(settings_schema) = settings_schema {
+                                this.set_language_server_settings_schema(
+                                    &adapter.name,
+                                    settings_schema,
+                                    cx,
+                                );
+                            }
                             this.insert_newly_running_language_server(
                                 adapter,
                                 server.clone(),
@@ -1297,6 +1311,32 @@ impl LocalLspStore {
             })?
         }
 
+        /// Drops the edits in `diff` outside of the ranges being formatted on save. Only
+        /// language servers can be asked to format ranges, so the other formatters' changes
+        /// are filtered instead.
+        fn restrict_diff_to_ranges(
+            buffer: &FormattableBuffer,
+            trigger: FormatTrigger,
+            mut diff: Diff,
+            cx: &mut AsyncApp,
+        ) -> anyhow::Result<Diff> {
+            let (FormatTrigger::Save, Some(ranges)) = (trigger, buffer.ranges.as_ref()) else {
+                return Ok(diff);
+            };
+            let ranges = buffer.handle.read_with(cx, |buffer, _| {
+                ranges
+                    .iter()
+                    .map(|range| range.start.to_offset(buffer)..range.end.to_offset(buffer))
+                    .collect::<Vec<_>>()
+            })?;
+            diff.edits.retain(|(edit_range, _)| {
+                ranges
+                    .iter()
+                    .any(|range| edit_range.start <= range.end && range.start <= edit_range.end)
+            });
+            Ok(diff)
+        }
+
         // handle whitespace formatting
         if settings.remove_trailing_whitespace_on_save {
             zlog::trace!(logger => "removing trailing whitespace");
@@ -1304,6 +1344,7 @@ impl LocalLspStore {
                 .handle
                 .read_with(cx, |buffer, cx| buffer.remove_trailing_whitespace(cx))?
                 .await;
+            let diff = restrict_diff_to_ranges(buffer, trigger, diff, cx)?;
             extend_formatting_transaction(buffer, formatting_transaction_id, cx, |buffer, cx| {
                 buffer.apply_diff(diff, cx);
             })?;
@@ -1374,6 +1415,7 @@ impl LocalLspStore {
                         zlog::trace!(logger => "No changes");
                         continue;
                     };
+                    let diff = restrict_diff_to_ranges(buffer, trigger, diff, cx)?;
 
                     extend_formatting_transaction(
                         buffer,
@@ -1403,6 +1445,7 @@ impl LocalLspStore {
                         zlog::trace!(logger => "No changes");
                         continue;
                     };
+                    let diff = restrict_diff_to_ranges(buffer, trigger, diff, cx)?;
 
                     extend_formatting_transaction(
                         buffer,
@@ -3879,6 +3922,26 @@ impl LspStore {
             | extension::Event::ConfigureExtensionRequested(_) => return,
             extension::Event::ExtensionsInstalledChanged => {}
         }
+        self.reload_zed_json_schemas(cx);
+    }
+
+    /// Records a schema published by a language server, and refreshes the schemas of Zed's
+    /// settings files so that its `lsp` settings can be completed and validated.
+    fn set_language_server_settings_schema(
+        &mut self,
+        name: &LanguageServerName,
+        schema: LanguageServerSettingsSchema,
+        cx: &mut Context<Self>,
+    ) {
+        if self
+            .languages
+            .set_language_server_settings_schema(name, schema)
+        {
+            self.reload_zed_json_schemas(cx);
+        }
+    }
+
+    fn reload_zed_json_schemas(&mut self, cx: &mut Context<Self>) {
         if self.as_local().is_none() {
             return;
         }
@@ -4659,6 +4722,106 @@ impl LspStore {
         }
     }
 
+    /// Returns the edits that a code action would make to its own buffer, without making them.
+    /// Actions whose effects can't be known in advance, because they run a command or change
+    /// other files, are reported as errors.
+    pub fn code_action_edits(
+        &self,
+        buffer: Entity<Buffer>,
+        mut action: CodeAction,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<(Range<Anchor>, Arc<str>)>>> {
+        if !self.mode.is_local() {
+            return Task::ready(Err(anyhow!(
+                "code actions can only be previewed in local projects"
+            )));
+        }
+        let server_id = action.server_id;
+        let Some(lang_server) = buffer.update(cx, |buffer, cx| {
+            self.language_server_for_local_buffer(buffer, server_id, cx)
+                .map(|(_, server)| server.clone())
+        }) else {
+            return Task::ready(Err(anyhow!("no language server for code action")));
+        };
+        let Some(abs_path) = File::from_dyn(buffer.read(cx).file()).map(|file| file.abs_path(cx))
+        else {
+            return Task::ready(Err(anyhow!("buffer has no file")));
+        };
+        cx.spawn(async move |this, cx| {
+            let uri = lsp::Url::from_file_path(&abs_path)
+                .map_err(|()| anyhow!("failed to convert abs path to uri"))?;
+            LocalLspStore::try_resolve_code_action(&lang_server, &mut action)
+                .await
+                .context("resolving a code action")?;
+            let title = action.lsp_action.title().to_string();
+            if action.lsp_action.command().is_some() {
+                anyhow::bail!("{title:?} runs a command, so it can't be previewed");
+            }
+
+            let edit = action.lsp_action.edit().cloned().unwrap_or_default();
+            let mut document_edits = Vec::new();
+            if let Some(document_changes) = edit.document_changes {
+                match document_changes {
+                    lsp::DocumentChanges::Edits(edits) => document_edits = edits,
+                    lsp::DocumentChanges::Operations(operations) => {
+                        for operation in operations {
+                            match operation {
+                                lsp::DocumentChangeOperation::Edit(edit) => {
+                                    document_edits.push(edit)
+                                }
+                                lsp::DocumentChangeOperation::Op(_) => anyhow::bail!(
+                                    "{title:?} creates, renames or deletes files, so it can't be previewed"
+                                ),
+                            }
+                        }
+                    }
+                }
+            } else if let Some(changes) = edit.changes {
+                document_edits.extend(changes.into_iter().map(|(uri, edits)| {
+                    lsp::TextDocumentEdit {
+                        text_document: lsp::OptionalVersionedTextDocumentIdentifier {
+                            uri,
+                            version: None,
+                        },
+                        edits: edits.into_iter().map(Edit::Plain).collect(),
+                    }
+                }));
+            }
+
+            let mut edits = Vec::new();
+            for document_edit in document_edits {
+                if document_edit.text_document.uri != uri {
+                    anyhow::bail!("{title:?} edits other files, so it can't be previewed");
+                }
+                let lsp_edits = document_edit
+                    .edits
+                    .into_iter()
+                    .map(|edit| match edit {
+                        Edit::Plain(edit) => edit,
+                        Edit::Annotated(edit) => edit.text_edit,
+                        Edit::Snippet(edit) => TextEdit {
+                            range: edit.range,
+                            new_text: Snippet::parse(&edit.snippet.value)
+                                .map(|snippet| snippet.text)
+                                .unwrap_or(edit.snippet.value),
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                let buffer_edits = this.update(cx, |this, cx| {
+                    anyhow::Ok(this.as_local_mut().context("not a local project")?.edits_from_lsp(
+                        &buffer,
+                        lsp_edits,
+                        server_id,
+                        document_edit.text_document.version,
+                        cx,
+                    ))
+                })??;
+                edits.extend(buffer_edits.await?);
+            }
+            Ok(edits)
+        })
+    }
+
     pub fn apply_code_action_kind(
         &mut self,
         buffers: HashSet<Entity<Buffer>>,
@@ -8649,6 +8812,93 @@ impl LspStore {
         }
     }
 
+    /// Restarts the language servers with the given names in a worktree, so that they're started
+    /// again with the worktree's current settings.
+    pub fn restart_language_servers_in_worktree(
+        &mut self,
+        worktree_id: WorktreeId,
+        server_names: HashSet<LanguageServerName>,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(local) = self.as_local() else {
+            // Remote servers can only be restarted along with every other server of a buffer.
+            let buffers = self
+                .buffer_store
+                .read(cx)
+                .buffers()
+                .filter(|buffer| {
+                    let buffer = buffer.read(cx);
+                    File::from_dyn(buffer.file())
+                        .is_some_and(|file| file.worktree_id(cx) == worktree_id)
+                        && buffer.language().is_some_and(|language| {
+                            self.languages
+                                .lsp_adapters(&language.name())
+                                .iter()
+                                .any(|adapter| server_names.contains(&adapter.name))
+                        })
+                })
+                .collect::<Vec<_>>();
+            self.restart_language_servers_for_buffers(buffers, cx);
+            return;
+        };
+        let servers_to_stop = local
+            .language_server_ids
+            .iter()
+            .filter(|((id, name), _)| *id == worktree_id && server_names.contains(name))
+            .flat_map(|((_, name), server_ids)| {
+                server_ids
+                    .iter()
+                    .map(|server_id| (*server_id, name.clone()))
+            })
+            .collect::<Vec<_>>();
+        // Only the buffers that were open in the stopped servers are opened in them again, since
+        // other buffers, like those that were only searched, were never registered with them.
+        let buffers = self
+            .buffer_store
+            .read(cx)
+            .buffers()
+            .filter(|buffer| {
+                let buffer_id = buffer.read(cx).remote_id();
+                local.registered_buffers.contains_key(&buffer_id)
+                    && local
+                        .buffer_snapshots
+                        .get(&buffer_id)
+                        .is_some_and(|snapshots| {
+                            servers_to_stop
+                                .iter()
+                                .any(|(server_id, _)| snapshots.contains_key(server_id))
+                        })
+            })
+            .collect::<Vec<_>>();
+
+        let Some(local) = self.as_local_mut() else {
+            return;
+        };
+        local.lsp_tree.update(cx, |lsp_tree, _| {
+            lsp_tree.remove_nodes(
+                &servers_to_stop
+                    .iter()
+                    .map(|(server_id, _)| *server_id)
+                    .collect::<BTreeSet<_>>(),
+            );
+        });
+        let stop_tasks = servers_to_stop
+            .into_iter()
+            .map(|(server_id, name)| self.stop_local_language_server(server_id, name, cx))
+            .collect::<Vec<_>>();
+
+        cx.spawn(async move |this, cx| {
+            futures::future::join_all(stop_tasks).await;
+            this.update(cx, |this, cx| {
+                for buffer in buffers {
+                    this.register_buffer_with_language_servers(&buffer, true, cx);
+                }
+            })
+            .ok()
+        })
+        .detach();
+    }
+
     pub fn stop_language_servers_for_buffers(
         &mut self,
         buffers: Vec<Entity<Buffer>>,
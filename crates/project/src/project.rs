@@ -11,6 +11,7 @@ pub mod lsp_store;
 mod manifest_tree;
 pub mod prettier_store;
 pub mod project_settings;
+mod replace_on_disk;
 pub mod search;
 mod task_inventory;
 pub mod task_store;
@@ -23,7 +24,7 @@ mod project_tests;
 
 mod direnv;
 mod environment;
-use buffer_diff::BufferDiff;
+use buffer_diff::{BufferDiff, BufferDiffSnapshot};
 use context_server_store::ContextServerStore;
 pub use environment::{EnvironmentErrorMessage, ProjectEnvironmentEvent};
 use git_store::{Repository, RepositoryId};
@@ -45,7 +46,7 @@ use clock::ReplicaId;
 
 use dap::{DapRegistry, client::DebugAdapterClient};
 
-use collections::{BTreeSet, HashMap, HashSet};
+use collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use debounced_delay::DebouncedDelay;
 pub use debugger::breakpoint_store::BreakpointWithPosition;
 use debugger::{
@@ -73,7 +74,9 @@ use itertools::Itertools;
 use language::{
     Buffer, BufferEvent, Capability, CodeLabel, CursorShape, Language, LanguageName,
     LanguageRegistry, PointUtf16, ToOffset, ToPointUtf16, Toolchain, ToolchainList, Transaction,
-    Unclipped, language_settings::InlayHintKind, proto::split_operations,
+    Unclipped,
+    language_settings::{FormatOnSaveScope, InlayHintKind, language_settings},
+    proto::split_operations,
 };
 use lsp::{
     CodeActionKind, CompletionContext, CompletionItemKind, DocumentHighlightKind, InsertTextMode,
@@ -87,13 +90,18 @@ use parking_lot::Mutex;
 pub use prettier_store::PrettierStore;
 use project_settings::{ProjectSettings, SettingsObserver, SettingsObserverEvent};
 use remote::{SshConnectionOptions, SshRemoteClient};
+pub use replace_on_disk::ReplaceOnDiskSummary;
+use replace_on_disk::{FileReplacement, replace_in_file};
 use rpc::{
     AnyProtoClient, ErrorCode,
     proto::{FromProto, LanguageServerPromptResponse, SSH_PROJECT_ID, ToProto},
 };
 use search::{SearchInputKind, SearchQuery, SearchResult};
 use search_history::SearchHistory;
-use settings::{InvalidSettingsError, Settings, SettingsLocation, SettingsStore};
+use settings::{
+    InvalidSettingsError, LocalSettingsKind, Settings, SettingsLocation, SettingsStore,
+    parse_json_with_comments,
+};
 use smol::channel::Receiver;
 use snippet::Snippet;
 use snippet_provider::SnippetProvider;
@@ -140,6 +148,9 @@ pub use toolchain_store::ToolchainStore;
 const MAX_PROJECT_SEARCH_HISTORY_SIZE: usize = 500;
 const MAX_SEARCH_RESULT_FILES: usize = 5_000;
 const MAX_SEARCH_RESULT_RANGES: usize = 10_000;
+/// How many code actions are requested at once when looking for a fix at every occurrence of a
+/// diagnostic, so that large projects don't flood the language server.
+const CODE_ACTION_BATCH_SIZE: usize = 16;
 
 pub trait ProjectItem: 'static {
     fn try_open(
@@ -870,10 +881,16 @@ impl Project {
             cx.subscribe(&worktree_store, Self::on_worktree_store_event)
                 .detach();
 
-            let context_server_store =
-                cx.new(|cx| ContextServerStore::new(worktree_store.clone(), cx));
-
             let environment = cx.new(|_| ProjectEnvironment::new(env));
+            let context_server_store = cx.new(|cx| {
+                ContextServerStore::new(
+                    worktree_store.clone(),
+                    environment.clone(),
+                    Some(node.clone()),
+                    cx,
+                )
+            });
+
             let toolchain_store = cx.new(|cx| {
                 ToolchainStore::local(
                     languages.clone(),
@@ -1033,8 +1050,15 @@ impl Project {
             cx.subscribe(&worktree_store, Self::on_worktree_store_event)
                 .detach();
 
-            let context_server_store =
-                cx.new(|cx| ContextServerStore::new(worktree_store.clone(), cx));
+            let environment = cx.new(|_| ProjectEnvironment::new(None));
+            let context_server_store = cx.new(|cx| {
+                ContextServerStore::new(
+                    worktree_store.clone(),
+                    environment.clone(),
+                    Some(node.clone()),
+                    cx,
+                )
+            });
 
             let buffer_store = cx.new(|cx| {
                 BufferStore::remote(
@@ -1078,8 +1102,6 @@ impl Project {
             cx.subscribe(&settings_observer, Self::on_settings_observer_event)
                 .detach();
 
-            let environment = cx.new(|_| ProjectEnvironment::new(None));
-
             let lsp_store = cx.new(|cx| {
                 LspStore::new_remote(
                     buffer_store.clone(),
@@ -1283,10 +1305,10 @@ impl Project {
         let image_store = cx.new(|cx| {
             ImageStore::remote(worktree_store.clone(), client.clone().into(), remote_id, cx)
         })?;
-        let context_server_store =
-            cx.new(|cx| ContextServerStore::new(worktree_store.clone(), cx))?;
-
         let environment = cx.new(|_| ProjectEnvironment::new(None))?;
+        let context_server_store = cx.new(|cx| {
+            ContextServerStore::new(worktree_store.clone(), environment.clone(), None, cx)
+        })?;
 
         let breakpoint_store =
             cx.new(|_| BreakpointStore::remote(remote_id, client.clone().into()))?;
@@ -3042,6 +3064,67 @@ impl Project {
         })
     }
 
+    /// Saves a project settings file and restarts the language servers listed in its `lsp`
+    /// section, so that their `initialization_options` and `settings` can be tried out without
+    /// editing global settings.
+    pub fn restart_language_servers_for_settings_file(
+        &mut self,
+        buffer: Entity<Buffer>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let Some(file) = File::from_dyn(buffer.read(cx).file()) else {
+            return Task::ready(Err(anyhow!("buffer is not a project settings file")));
+        };
+        let settings_file_path = paths::local_settings_file_relative_path();
+        let settings_dir = file
+            .path
+            .ends_with(settings_file_path)
+            .then(|| {
+                file.path
+                    .ancestors()
+                    .nth(settings_file_path.components().count())
+            })
+            .flatten();
+        let Some(settings_dir) = settings_dir.map(Arc::<Path>::from) else {
+            return Task::ready(Err(anyhow!("buffer is not a project settings file")));
+        };
+        let worktree = file.worktree.clone();
+        let worktree_id = file.worktree_id(cx);
+
+        let content = buffer.read(cx).text();
+        let server_names = match parse_json_with_comments::<serde_json::Value>(&content) {
+            Ok(settings) => settings
+                .get("lsp")
+                .and_then(|lsp| lsp.as_object())
+                .map(|lsp| {
+                    lsp.keys()
+                        .map(|name| LanguageServerName::from(name.as_str()))
+                        .collect::<HashSet<_>>()
+                })
+                .unwrap_or_default(),
+            Err(error) => return Task::ready(Err(error.context("parsing project settings"))),
+        };
+
+        let save = self.save_buffer(buffer, cx);
+        cx.spawn(async move |this, cx| {
+            save.await?;
+            this.update(cx, |this, cx| {
+                // Apply the saved settings right away rather than waiting for the file system to
+                // report the change, so that the servers start with them.
+                this.settings_observer.update(cx, |settings_observer, cx| {
+                    settings_observer.update_settings(
+                        worktree,
+                        [(settings_dir, LocalSettingsKind::Settings, Some(content))],
+                        cx,
+                    )
+                });
+                this.lsp_store.update(cx, |lsp_store, cx| {
+                    lsp_store.restart_language_servers_in_worktree(worktree_id, server_names, cx)
+                });
+            })
+        })
+    }
+
     pub fn stop_language_servers_for_buffers(
         &mut self,
         buffers: Vec<Entity<Buffer>>,
@@ -3183,11 +3266,102 @@ impl Project {
         trigger: lsp_store::FormatTrigger,
         cx: &mut Context<Project>,
     ) -> Task<anyhow::Result<ProjectTransaction>> {
+        let buffers = if trigger == lsp_store::FormatTrigger::Save
+            && matches!(target, LspFormatTarget::Buffers)
+            && self.is_local()
+        {
+            let (modified_lines_buffers, buffers): (HashSet<_>, HashSet<_>) =
+                buffers.into_iter().partition(|buffer| {
+                    let buffer = buffer.read(cx);
+                    language_settings(buffer.language().map(|l| l.name()), buffer.file(), cx)
+                        .format_on_save_scope
+                        == FormatOnSaveScope::ModifiedLines
+                });
+            if !modified_lines_buffers.is_empty() {
+                return self.format_modified_lines(
+                    buffers,
+                    modified_lines_buffers,
+                    push_to_history,
+                    cx,
+                );
+            }
+            buffers
+        } else {
+            buffers
+        };
+
         self.lsp_store.update(cx, |lsp_store, cx| {
             lsp_store.format(buffers, target, push_to_history, trigger, cx)
         })
     }
 
+    /// Formats `buffers` in full, and only the lines of `modified_lines_buffers` that differ from
+    /// the last commit.
+    fn format_modified_lines(
+        &mut self,
+        buffers: HashSet<Entity<Buffer>>,
+        modified_lines_buffers: HashSet<Entity<Buffer>>,
+        push_to_history: bool,
+        cx: &mut Context<Project>,
+    ) -> Task<anyhow::Result<ProjectTransaction>> {
+        let diffs = modified_lines_buffers
+            .into_iter()
+            .map(|buffer| {
+                let diff = self.open_uncommitted_diff(buffer.clone(), cx);
+                (buffer, diff)
+            })
+            .collect::<Vec<_>>();
+        cx.spawn(async move |this, cx| {
+            let mut full_buffers = buffers;
+            let mut ranged_buffers = HashSet::default();
+            let mut ranges = BTreeMap::new();
+            for (buffer, diff) in diffs {
+                let modified_ranges = match diff.await {
+                    Ok(diff) => modified_line_ranges(&buffer, &diff, cx).await?,
+                    Err(error) => {
+                        log::debug!(
+                            "formatting whole buffer, since its diff is unavailable: {error:#}"
+                        );
+                        full_buffers.insert(buffer);
+                        continue;
+                    }
+                };
+                if !modified_ranges.is_empty() {
+                    ranges.insert(
+                        buffer.read_with(cx, |buffer, _| buffer.remote_id())?,
+                        modified_ranges,
+                    );
+                    ranged_buffers.insert(buffer);
+                }
+            }
+
+            let (format_buffers, format_ranges) = this.update(cx, |this, cx| {
+                this.lsp_store.update(cx, |lsp_store, cx| {
+                    let trigger = lsp_store::FormatTrigger::Save;
+                    (
+                        lsp_store.format(
+                            full_buffers,
+                            LspFormatTarget::Buffers,
+                            push_to_history,
+                            trigger,
+                            cx,
+                        ),
+                        lsp_store.format(
+                            ranged_buffers,
+                            LspFormatTarget::Ranges(ranges),
+                            push_to_history,
+                            trigger,
+                            cx,
+                        ),
+                    )
+                })
+            })?;
+            let mut project_transaction = format_buffers.await?;
+            project_transaction.0.extend(format_ranges.await?.0);
+            Ok(project_transaction)
+        })
+    }
+
     #[inline(never)]
     fn definition_impl(
         &mut self,
@@ -3502,6 +3676,129 @@ impl Project {
         })
     }
 
+    /// Finds the primary diagnostics across the project that have the same code as the one
+    /// `action` fixes, and returns the same fix for each of them. Fixes are the same when they
+    /// come from the same language server and have the same title and kind.
+    pub fn code_actions_for_matching_diagnostics(
+        &mut self,
+        buffer: &Entity<Buffer>,
+        action: &CodeAction,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<(Entity<Buffer>, CodeAction)>>> {
+        let server_id = action.server_id;
+        let Some((source, code)) = diagnostic_code_fixed_by(&buffer.read(cx).snapshot(), action)
+        else {
+            return Task::ready(Err(anyhow!(
+                "{:?} doesn't fix a diagnostic with a code",
+                action.lsp_action.title()
+            )));
+        };
+        let title = action.lsp_action.title().to_string();
+        let kind = action.lsp_action.action_kind();
+
+        let open_buffers = self
+            .diagnostic_summaries(false, cx)
+            .filter(|(_, diagnostics_server_id, _)| *diagnostics_server_id == server_id)
+            .map(|(path, _, _)| path)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|path| self.open_buffer_with_lsp(path, cx))
+            .collect::<Vec<_>>();
+        cx.spawn(async move |project, cx| {
+            let mut occurrences = Vec::new();
+            // Buffers need to stay registered with the language server until it has been asked
+            // for their code actions.
+            let mut lsp_handles = Vec::new();
+            for buffer in join_all(open_buffers).await {
+                let Some((buffer, lsp_handle)) = buffer.log_err() else {
+                    continue;
+                };
+                lsp_handles.push(lsp_handle);
+                let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot())?;
+                for (_, group) in snapshot.diagnostic_groups(Some(server_id)) {
+                    let entry = &group.entries[group.primary_ix];
+                    if entry.diagnostic.code.as_ref() == Some(&code)
+                        && entry.diagnostic.source == source
+                    {
+                        occurrences.push((buffer.clone(), entry.range.clone()));
+                    }
+                }
+            }
+
+            let mut actions = Vec::new();
+            for batch in occurrences.chunks(CODE_ACTION_BATCH_SIZE) {
+                let requests = project.update(cx, |project, cx| {
+                    batch
+                        .iter()
+                        .map(|(buffer, range)| {
+                            project.code_actions(
+                                buffer,
+                                range.clone(),
+                                kind.clone().map(|kind| vec![kind]),
+                                cx,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })?;
+                for ((buffer, _), buffer_actions) in batch.iter().zip(join_all(requests).await) {
+                    let Some(buffer_actions) = buffer_actions.log_err() else {
+                        continue;
+                    };
+                    actions.extend(
+                        buffer_actions
+                            .into_iter()
+                            .find(|action| {
+                                action.server_id == server_id
+                                    && action.lsp_action.title() == title
+                                    && action.lsp_action.action_kind() == kind
+                            })
+                            .map(|action| (buffer.clone(), action)),
+                    );
+                }
+            }
+            drop(lsp_handles);
+            Ok(actions)
+        })
+    }
+
+    /// Computes the edits that code actions would make, without making them, so that they can
+    /// be previewed. Edits are grouped by buffer, and actions that can't be previewed are skipped.
+    pub fn code_action_edits(
+        &mut self,
+        actions: Vec<(Entity<Buffer>, CodeAction)>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<(Entity<Buffer>, Vec<(Range<Anchor>, Arc<str>)>)>>> {
+        cx.spawn(async move |project, cx| {
+            let mut edits_by_buffer: Vec<(Entity<Buffer>, Vec<(Range<Anchor>, Arc<str>)>)> =
+                Vec::new();
+            for batch in actions.chunks(CODE_ACTION_BATCH_SIZE) {
+                let requests = project.update(cx, |project, cx| {
+                    project.lsp_store.update(cx, |lsp_store, cx| {
+                        batch
+                            .iter()
+                            .map(|(buffer, action)| {
+                                lsp_store.code_action_edits(buffer.clone(), action.clone(), cx)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })?;
+                for ((buffer, _), edits) in batch.iter().zip(join_all(requests).await) {
+                    let Some(edits) = edits.log_err() else {
+                        continue;
+                    };
+                    match edits_by_buffer
+                        .iter_mut()
+                        .find(|(existing, _)| existing == buffer)
+                    {
+                        Some((_, buffer_edits)) => buffer_edits.extend(edits),
+                        None => edits_by_buffer.push((buffer.clone(), edits)),
+                    }
+                }
+            }
+            Ok(edits_by_buffer)
+        })
+    }
+
     fn prepare_rename_impl(
         &mut self,
         buffer: Entity<Buffer>,
@@ -3708,6 +4005,128 @@ impl Project {
         result_rx
     }
 
+    /// Replaces every match of `query` across the project without opening buffers for files that
+    /// aren't already open.
+    ///
+    /// Open buffers are edited in memory and left unsaved. Other files are rewritten on disk in
+    /// their original encoding, after their contents have been copied to a backup directory.
+    pub fn replace_on_disk(
+        &mut self,
+        query: SearchQuery,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<ReplaceOnDiskSummary>> {
+        if !self.is_local() {
+            return Task::ready(Err(anyhow!(
+                "replacing on disk is only supported in local projects"
+            )));
+        }
+        if query.replacement().is_none() {
+            return Task::ready(Err(anyhow!("no replacement was provided")));
+        }
+
+        let mut open_entries = HashSet::default();
+        let mut open_buffers = Vec::new();
+        for buffer in self.buffer_store.read(cx).buffers() {
+            let Some(file) = File::from_dyn(buffer.read(cx).file()) else {
+                continue;
+            };
+            if let Some(entry_id) = file.entry_id {
+                open_entries.insert(entry_id);
+            }
+            if self.worktree_store.read(cx).path_matches_query(
+                &query,
+                file.worktree_id(cx),
+                &file.path,
+                cx,
+            ) {
+                open_buffers.push(buffer);
+            }
+        }
+        let paths = self
+            .worktree_store
+            .read(cx)
+            .unopened_search_paths(&query, &open_entries, cx);
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        // Replacements started within the same second must not share a backup directory.
+        let backup_dir = paths::data_dir()
+            .join("replace_backups")
+            .join(format!("{timestamp}-{:08x}", rand::random::<u32>()));
+        let fs = self.fs.clone();
+
+        cx.spawn(async move |_, cx| {
+            let mut summary = ReplaceOnDiskSummary {
+                backup_dir: backup_dir.clone(),
+                ..Default::default()
+            };
+
+            for buffer in open_buffers {
+                let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot())?;
+                let edits = query
+                    .search(&snapshot, None)
+                    .await
+                    .into_iter()
+                    .filter_map(|range| {
+                        let text = snapshot.text_for_range(range.clone()).collect::<String>();
+                        let replacement = query.replacement_for(&text)?.into_owned();
+                        Some((
+                            snapshot.anchor_before(range.start)..snapshot.anchor_after(range.end),
+                            replacement,
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+                if edits.is_empty() {
+                    continue;
+                }
+                summary.replacements += edits.len();
+                summary.buffers_changed += 1;
+                buffer.update(cx, |buffer, cx| buffer.edit(edits, None, cx))?;
+            }
+
+            const MAX_CONCURRENT_FILE_REPLACEMENTS: usize = 16;
+            let outcomes = cx
+                .background_spawn(async move {
+                    futures::stream::iter(paths)
+                        .map(|(abs_path, backup_path)| {
+                            let fs = &fs;
+                            let query = &query;
+                            let backup_path = backup_dir.join(backup_path);
+                            async move {
+                                let outcome =
+                                    replace_in_file(fs, &abs_path, query, &backup_path).await;
+                                (abs_path, outcome)
+                            }
+                        })
+                        .buffer_unordered(MAX_CONCURRENT_FILE_REPLACEMENTS)
+                        .collect::<Vec<_>>()
+                        .await
+                })
+                .await;
+
+            for (abs_path, outcome) in outcomes {
+                match outcome {
+                    Ok(FileReplacement::Unchanged) => {}
+                    Ok(FileReplacement::Replaced(replacements)) => {
+                        summary.files_changed += 1;
+                        summary.replacements += replacements;
+                    }
+                    Ok(FileReplacement::UnsupportedEncoding) => {
+                        summary.skipped_files.push(abs_path);
+                    }
+                    Err(error) => {
+                        log::error!("failed to replace matches in {abs_path:?}: {error:#}");
+                        summary.skipped_files.push(abs_path);
+                    }
+                }
+            }
+            summary.skipped_files.sort();
+
+            Ok(summary)
+        })
+    }
+
     fn find_search_candidate_buffers(
         &mut self,
         query: &SearchQuery,
@@ -5244,6 +5663,30 @@ pub fn sort_worktree_entries(entries: &mut [impl AsRef<Entry>]) {
     });
 }
 
+/// Returns the source and code of the diagnostic a code action fixes, preferring the diagnostics
+/// the language server attached to the action over those overlapping its range.
+fn diagnostic_code_fixed_by(
+    buffer: &language::BufferSnapshot,
+    action: &CodeAction,
+) -> Option<(Option<String>, lsp::NumberOrString)> {
+    if let LspAction::Action(lsp_action) = &action.lsp_action {
+        let attached = lsp_action
+            .diagnostics
+            .iter()
+            .flatten()
+            .find_map(|diagnostic| Some((diagnostic.source.clone(), diagnostic.code.clone()?)));
+        if attached.is_some() {
+            return attached;
+        }
+    }
+
+    let range = action.range.start.to_offset(buffer)..action.range.end.to_offset(buffer);
+    buffer
+        .diagnostics_in_range::<_, usize>(range, false)
+        .filter(|entry| entry.diagnostic.is_primary)
+        .find_map(|entry| Some((entry.diagnostic.source, entry.diagnostic.code?)))
+}
+
 fn proto_to_prompt(level: proto::language_server_prompt_request::Level) -> gpui::PromptLevel {
     match level {
         proto::language_server_prompt_request::Level::Info(_) => gpui::PromptLevel::Info,
@@ -5251,3 +5694,31 @@ fn proto_to_prompt(level: proto::language_server_prompt_request::Level) -> gpui:
         proto::language_server_prompt_request::Level::Critical(_) => gpui::PromptLevel::Critical,
     }
 }
+
+/// Returns the ranges of `buffer` that were added or changed since the base text of `diff`.
+///
+/// The diff is recomputed rather than read, since the diff a buffer keeps is updated after a
+/// delay and may not include the latest edits yet.
+async fn modified_line_ranges(
+    buffer: &Entity<Buffer>,
+    diff: &Entity<BufferDiff>,
+    cx: &mut AsyncApp,
+) -> Result<Vec<Range<Anchor>>> {
+    let (buffer_snapshot, diff_snapshot) = cx.update(|cx| {
+        let buffer_snapshot = buffer.read(cx).text_snapshot();
+        let diff = diff.read(cx);
+        let diff_snapshot = BufferDiffSnapshot::new_with_base_buffer(
+            buffer_snapshot.clone(),
+            diff.base_text_string().map(Arc::new),
+            diff.base_text().clone(),
+            cx,
+        );
+        (buffer_snapshot, diff_snapshot)
+    })?;
+    let diff_snapshot = diff_snapshot.await;
+    Ok(diff_snapshot
+        .hunks_intersecting_range(Anchor::MIN..Anchor::MAX, &buffer_snapshot)
+        .filter(|hunk| !hunk.range.is_empty())
+        .map(|hunk| hunk.buffer_range)
+        .collect())
+}
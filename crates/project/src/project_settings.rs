@@ -1,6 +1,9 @@
 use anyhow::Context as _;
 use collections::HashMap;
-use context_server::ContextServerCommand;
+use context_server::{
+    ContextServerCommand, builtin::BuiltinContextServer, openapi::ContextServerOpenApi,
+    protocol::ContextServerFeatures, sandbox::ContextServerSandbox,
+};
 use dap::adapters::DebugAdapterName;
 use fs::Fs;
 use futures::StreamExt as _;
@@ -15,11 +18,18 @@ use rpc::{
     AnyProtoClient, TypedEnvelope,
     proto::{self, FromProto, ToProto},
 };
-use schemars::JsonSchema;
+use schemars::{
+    JsonSchema,
+    r#gen::SchemaGenerator,
+    schema::{
+        InstanceType, ObjectValidation, RootSchema, Schema, SchemaObject, SubschemaValidation,
+    },
+};
 use serde::{Deserialize, Serialize};
 use settings::{
-    InvalidSettingsError, LocalSettingsKind, Settings, SettingsLocation, SettingsSources,
-    SettingsStore, parse_json_with_comments, watch_config_file,
+    InvalidSettingsError, LocalSettingsKind, Settings, SettingsJsonSchemaParams, SettingsLocation,
+    SettingsSources, SettingsStore, add_references_to_properties, parse_json_with_comments,
+    watch_config_file,
 };
 use std::{
     path::{Path, PathBuf},
@@ -95,6 +105,158 @@ pub struct ContextServerConfiguration {
     /// Consult the documentation for the context server to see what settings
     /// are supported.
     pub settings: Option<serde_json::Value>,
+    /// A file of tool calls recorded with the agent's `record_tool_calls` setting.
+    ///
+    /// When set, the recorded responses are served back instead of running the server, so that
+    /// a thread's tool calls can be reproduced deterministically.
+    pub replay: Option<PathBuf>,
+    /// Runs the server's command in a sandbox that can only write to the project's worktrees and
+    /// a scratch directory of its own.
+    ///
+    /// Supported on macOS, and on Linux when bubblewrap is installed.
+    pub sandbox: Option<ContextServerSandbox>,
+    /// Whether to refuse tool calls whose path arguments point outside of the project's
+    /// worktrees.
+    ///
+    /// Arguments are treated as paths when their name looks like one, such as `path`, `paths`
+    /// or `source_file`, or when their description mentions a path.
+    #[serde(default)]
+    pub confine_paths_to_worktrees: bool,
+    /// The most requests, such as tool calls, to send to this server per minute.
+    ///
+    /// Requests over the limit are queued until they can be sent, rather than failing.
+    pub max_requests_per_minute: Option<u32>,
+    /// The largest message this server may send, in bytes.
+    ///
+    /// Larger responses are discarded as they're read, and the tool call fails with an error
+    /// asking the model for less data.
+    pub max_result_bytes: Option<u64>,
+    /// How many seconds a call to one of this server's tools may take before it fails.
+    ///
+    /// The timeout is also passed to the server in the call's metadata, so that servers that
+    /// support it can stop early. Defaults to 60 seconds, and 0 means calls may take as long as
+    /// they need.
+    pub tool_call_timeout_seconds: Option<u64>,
+    /// How many seconds to keep this server running once no open project or agent profile uses
+    /// it, so that switching back doesn't wait for it to start again.
+    ///
+    /// Useful for servers that are slow to start, such as ones written in Python or Node.js. A
+    /// server is only reused when it would be launched the same way, and servers that run in a
+    /// sandbox are never kept running.
+    pub keep_warm_seconds: Option<u64>,
+    /// Templates for the text shown for calls to this server's tools, keyed by tool name.
+    ///
+    /// Placeholders like `{table}` or `{query.limit}` are replaced by the call's arguments, such
+    /// as in `"Query {database} for {table}"`.
+    #[serde(default)]
+    pub tool_ui_text: HashMap<String, String>,
+    /// Examples of how to use this server's tools, keyed by tool name, which are added to the
+    /// tools' descriptions for the model.
+    ///
+    /// Helps the model pick the right tool when the server's descriptions are terse, as in
+    /// `"Find open bugs: {\"query\": \"is:open label:bug\"}"`.
+    #[serde(default)]
+    pub tool_examples: HashMap<String, Vec<String>>,
+    /// The only models this server's tools are offered to, keyed by tool name, or by `*` for
+    /// all of the server's tools.
+    ///
+    /// Models are given as `provider/model`, where either part may be `*` or end with `*`, as
+    /// in `"anthropic/*"` or `"openai/gpt-4*"`. Tools that aren't listed are offered to every
+    /// model.
+    #[serde(default)]
+    pub tool_models: HashMap<String, Vec<String>>,
+    /// Environment variables to pass to calls to this server's tools, keyed by tool name, or by
+    /// `*` for all of the server's tools.
+    ///
+    /// They're sent in the call's `_meta` under `dev.zed/env`, so they only take effect with
+    /// servers that read them from there, as in `{"query": {"READ_ONLY": "1"}}`.
+    #[serde(default)]
+    pub tool_env: HashMap<String, HashMap<String, String>>,
+    /// The name shown for this server on the cards of its tool calls.
+    ///
+    /// Defaults to the name the server reports, or to the server's key in the settings.
+    pub display_name: Option<String>,
+    /// An image shown for this server on the cards of its tool calls.
+    ///
+    /// Relative paths are resolved against the directory of the settings file that sets it.
+    /// Defaults to the icon the server reports, if any.
+    pub icon: Option<PathBuf>,
+    /// An HTTP API to offer as tools, one for each operation in its OpenAPI document.
+    ///
+    /// When set, Zed makes the API's requests itself instead of running `command`.
+    pub openapi: Option<ContextServerOpenApi>,
+    /// A server built into Zed to run, in place of `command`.
+    ///
+    /// Built-in servers only run when a server configured with this names them.
+    pub builtin: Option<BuiltinContextServer>,
+    /// Whether to find and run `command` with the environment of your login shell, as if it
+    /// were run from a terminal in the directory the server starts in.
+    ///
+    /// This lets commands installed by version managers like nvm or asdf, such as `npx`, be found
+    /// when Zed wasn't started from a shell. Defaults to true.
+    pub resolve_command_in_shell: Option<bool>,
+    /// A package to install and run, in place of `command`.
+    ///
+    /// Zed installs the package in a directory of its own.
+    pub package: Option<ContextServerPackage>,
+    /// Whether the agent asks before calling this server's tools.
+    ///
+    /// Defaults to true. The agent's `always_allow_tool_actions` setting skips confirmation for
+    /// every server.
+    pub confirm_tool_calls: Option<bool>,
+    /// Protocol features to turn off for this server, even if it supports them.
+    #[serde(default)]
+    pub features: ContextServerFeatures,
+    /// A file to append every JSON-RPC message sent to and received from this server to, with
+    /// sensitive values redacted, for debugging servers that don't behave as expected.
+    /// Project settings can only trace to files in Zed's data and logs directories.
+    pub trace_file: Option<PathBuf>,
+    /// A prefix for the names of this server's tools, such as `staging_`, so that the model can
+    /// tell them apart from the tools of another server with the same names.
+    ///
+    /// Default: no prefix
+    pub tool_name_prefix: Option<String>,
+    /// Guidance on how to use this server's tools, which is added to the agent's system prompt
+    /// while they're available, along with any instructions the server gives itself.
+    pub instructions: Option<String>,
+    /// Whether to add the instructions the server gives when it starts to the agent's system
+    /// prompt, along with `instructions`. Only turn this on for servers you trust, since the
+    /// instructions steer the model in every request that offers the server's tools.
+    ///
+    /// Defaults to false.
+    pub use_server_instructions: Option<bool>,
+}
+
+/// A context server distributed as an npm or PyPI package.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+pub struct ContextServerPackage {
+    /// The registry to install the package from.
+    pub registry: PackageRegistry,
+    /// The name of the package, such as `@modelcontextprotocol/server-github`.
+    pub name: String,
+    /// The exact version to install, which pins the package to it.
+    ///
+    /// Defaults to the latest version. Newer versions are then offered as updates, rather than
+    /// installed automatically, since the server's tools can change between versions.
+    pub version: Option<String>,
+    /// The package's executable to run.
+    ///
+    /// Defaults to the package's only executable, or to the one named after the package.
+    pub bin: Option<String>,
+    /// The arguments to run the executable with.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The environment variables to run the executable with.
+    pub env: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageRegistry {
+    /// Installed with Zed's Node.js runtime.
+    Npm,
+    /// Installed in a Python virtual environment, which needs `python3` to be installed.
+    Pypi,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -384,6 +546,67 @@ impl Settings for ProjectSettings {
         sources.json_merge()
     }
 
+    fn json_schema(
+        generator: &mut SchemaGenerator,
+        params: &SettingsJsonSchemaParams,
+        _: &App,
+    ) -> RootSchema {
+        let mut root_schema = generator.root_schema_for::<Self::FileContent>();
+        if params.language_server_schemas.is_empty() {
+            return root_schema;
+        }
+
+        // Describe the `initialization_options` and `settings` of the servers that published a
+        // schema for them, while other servers still accept any `LspSettings`.
+        let lsp_settings_schema = Schema::new_ref("#/definitions/LspSettings".into());
+        let servers_schema = SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(ObjectValidation {
+                properties: params
+                    .language_server_schemas
+                    .iter()
+                    .map(|(name, schema)| {
+                        let properties = [
+                            ("initialization_options", &schema.initialization_options),
+                            ("settings", &schema.settings),
+                        ]
+                        .into_iter()
+                        .filter_map(|(property, schema)| {
+                            let schema = serde_json::from_value::<Schema>(schema.clone()?)
+                                .with_context(|| format!("invalid {property} schema for {name}"))
+                                .log_err()?;
+                            Some((property.to_string(), schema))
+                        })
+                        .collect();
+                        let server_schema = SchemaObject {
+                            subschemas: Some(Box::new(SubschemaValidation {
+                                all_of: Some(vec![lsp_settings_schema.clone()]),
+                                ..Default::default()
+                            })),
+                            object: Some(Box::new(ObjectValidation {
+                                properties,
+                                ..Default::default()
+                            })),
+                            ..Default::default()
+                        };
+                        (name.clone(), server_schema.into())
+                    })
+                    .collect(),
+                additional_properties: Some(Box::new(lsp_settings_schema.clone())),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        root_schema
+            .definitions
+            .insert("LanguageServers".into(), servers_schema.into());
+        add_references_to_properties(
+            &mut root_schema,
+            &[("lsp", "#/definitions/LanguageServers")],
+        );
+        root_schema
+    }
+
     fn import_from_vscode(vscode: &settings::VsCodeSettings, current: &mut Self::FileContent) {
         // this just sets the binary name instead of a full path so it relies on path lookup
         // resolving to the one you want
@@ -420,6 +643,7 @@ impl Settings for ProjectSettings {
                     path: cmd.command,
                     args: cmd.args.unwrap_or_default(),
                     env: cmd.env,
+                    cwd: None,
                 }
             }
         }
@@ -436,6 +660,29 @@ impl Settings for ProjectSettings {
                                     .into(),
                             ),
                             settings: None,
+                            replay: None,
+                            sandbox: None,
+                            confine_paths_to_worktrees: false,
+                            max_requests_per_minute: None,
+                            max_result_bytes: None,
+                            tool_call_timeout_seconds: None,
+                            keep_warm_seconds: None,
+                            tool_ui_text: Default::default(),
+                            tool_examples: Default::default(),
+                            tool_models: Default::default(),
+                            tool_env: Default::default(),
+                            display_name: None,
+                            icon: None,
+                            openapi: None,
+                            builtin: None,
+                            resolve_command_in_shell: None,
+                            package: None,
+                            confirm_tool_calls: None,
+                            features: Default::default(),
+                            trace_file: None,
+                            tool_name_prefix: None,
+                            instructions: None,
+                            use_server_instructions: None,
                         },
                     ))
                 }));
@@ -777,7 +1024,7 @@ impl SettingsObserver {
         .detach();
     }
 
-    fn update_settings(
+    pub(crate) fn update_settings(
         &mut self,
         worktree: Entity<Worktree>,
         settings_contents: impl IntoIterator<Item = (Arc<Path>, LocalSettingsKind, Option<String>)>,
@@ -20,7 +20,10 @@ use http_client::Url;
 use language::{
     Diagnostic, DiagnosticEntry, DiagnosticSet, DiskState, FakeLspAdapter, LanguageConfig,
     LanguageMatcher, LanguageName, LineEnding, OffsetRangeExt, Point, ToPoint,
-    language_settings::{AllLanguageSettings, LanguageSettingsContent, language_settings},
+    language_settings::{
+        AllLanguageSettings, FormatOnSave, FormatOnSaveScope, LanguageSettingsContent,
+        language_settings,
+    },
     tree_sitter_rust, tree_sitter_typescript,
 };
 use lsp::{
@@ -1683,6 +1686,107 @@ async fn test_restarting_server_with_diagnostics_running(cx: &mut gpui::TestAppC
     });
 }
 
+#[gpui::test]
+async fn test_restarting_language_servers_for_settings_file(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".zed": {
+                "settings.json": r#"{ "lsp": { "the-language-server": { "initialization_options": { "a": 1 } } } }"#,
+            },
+            "a.rs": "",
+            "b.rs": "",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            name: "the-language-server",
+            ..Default::default()
+        },
+    );
+    let worktree_id = project.update(cx, |p, cx| p.worktrees(cx).next().unwrap().read(cx).id());
+
+    let (_buffer, _handle) = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/a.rs"), cx)
+        })
+        .await
+        .unwrap();
+    let _fake_server = fake_servers.next().await.unwrap();
+    // A buffer that isn't open in the server, which restarting it shouldn't open.
+    let _other_buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let settings_buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/.zed/settings.json"), cx)
+        })
+        .await
+        .unwrap();
+    let new_settings =
+        r#"{ "lsp": { "the-language-server": { "initialization_options": { "a": 2 } } } }"#;
+    settings_buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..buffer.len(), new_settings)], None, cx)
+    });
+    project
+        .update(cx, |project, cx| {
+            project.restart_language_servers_for_settings_file(settings_buffer, cx)
+        })
+        .await
+        .unwrap();
+
+    // The server is started again, with the edited settings saved and applied, and only the
+    // buffers that were open in it before are opened again.
+    let mut fake_server = fake_servers.next().await.unwrap();
+    let opened = fake_server
+        .receive_notification::<lsp::notification::DidOpenTextDocument>()
+        .await;
+    assert_eq!(
+        opened.text_document.uri,
+        Url::from_file_path(path!("/dir/a.rs")).unwrap()
+    );
+    cx.executor().run_until_parked();
+    assert!(
+        futures::FutureExt::now_or_never(
+            fake_server.try_receive_notification::<lsp::notification::DidOpenTextDocument>()
+        )
+        .is_none()
+    );
+    assert_eq!(
+        fs.load(path!("/dir/.zed/settings.json").as_ref())
+            .await
+            .unwrap(),
+        new_settings
+    );
+    cx.update(|cx| {
+        let settings = crate::project_settings::ProjectSettings::get(
+            Some(settings::SettingsLocation {
+                worktree_id,
+                path: Path::new("a.rs"),
+            }),
+            cx,
+        );
+        assert_eq!(
+            settings.lsp[&LanguageServerName::new_static("the-language-server")]
+                .initialization_options,
+            Some(json!({ "a": 2 }))
+        );
+    });
+}
+
 #[gpui::test]
 async fn test_restarting_server_with_diagnostics_published(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -3452,6 +3556,139 @@ async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_applying_code_action_to_matching_diagnostics(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a.ts": "let a = 1;\nlet b = 2;\n",
+            "b.ts": "let c = 3;\nlet d = 4;\n",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(typescript_lang());
+    let mut fake_language_servers = language_registry.register_fake_lsp(
+        "TypeScript",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                code_action_provider: Some(lsp::CodeActionProviderCapability::Simple(true)),
+                ..lsp::ServerCapabilities::default()
+            },
+            ..FakeLspAdapter::default()
+        },
+    );
+
+    let (buffer_a, _handle) = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
+        })
+        .await
+        .unwrap();
+    let fake_server = fake_language_servers.next().await.unwrap();
+
+    let diagnostic = |line, code: &str| lsp::Diagnostic {
+        range: lsp::Range::new(lsp::Position::new(line, 4), lsp::Position::new(line, 5)),
+        severity: Some(lsp::DiagnosticSeverity::WARNING),
+        code: Some(lsp::NumberOrString::String(code.into())),
+        source: Some("ts".into()),
+        message: "unused variable".into(),
+        ..Default::default()
+    };
+    fake_server.notify::<lsp::notification::PublishDiagnostics>(&lsp::PublishDiagnosticsParams {
+        uri: Url::from_file_path(path!("/dir/a.ts")).unwrap(),
+        version: None,
+        diagnostics: vec![diagnostic(0, "unused"), diagnostic(1, "unused")],
+    });
+    fake_server.notify::<lsp::notification::PublishDiagnostics>(&lsp::PublishDiagnosticsParams {
+        uri: Url::from_file_path(path!("/dir/b.ts")).unwrap(),
+        version: None,
+        diagnostics: vec![diagnostic(0, "unused"), diagnostic(1, "other")],
+    });
+    cx.executor().run_until_parked();
+
+    // The fix prefixes the variable in the requested range with an underscore.
+    fake_server.set_request_handler::<lsp::request::CodeActionRequest, _, _>(
+        |params, _| async move {
+            Ok(Some(vec![lsp::CodeActionOrCommand::CodeAction(
+                lsp::CodeAction {
+                    title: "Prefix with an underscore".into(),
+                    kind: Some(lsp::CodeActionKind::QUICKFIX),
+                    edit: Some(lsp::WorkspaceEdit {
+                        changes: Some(
+                            [(
+                                params.text_document.uri,
+                                vec![lsp::TextEdit {
+                                    range: lsp::Range::new(params.range.start, params.range.start),
+                                    new_text: "_".into(),
+                                }],
+                            )]
+                            .into_iter()
+                            .collect(),
+                        ),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )]))
+        },
+    );
+
+    let action = project
+        .update(cx, |project, cx| {
+            project.code_actions(&buffer_a, 4..5, None, cx)
+        })
+        .await
+        .unwrap()
+        .remove(0);
+    let actions = project
+        .update(cx, |project, cx| {
+            project.code_actions_for_matching_diagnostics(&buffer_a, &action, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(actions.len(), 3);
+
+    // The fixes are only computed, so that they can be previewed before being applied.
+    let edits = project
+        .update(cx, |project, cx| project.code_action_edits(actions, cx))
+        .await
+        .unwrap();
+    let edits = edits
+        .into_iter()
+        .map(|(buffer, edits)| {
+            buffer.read_with(cx, |buffer, _| {
+                (
+                    buffer.file().unwrap().path().to_string_lossy().to_string(),
+                    edits
+                        .into_iter()
+                        .map(|(range, text)| (range.to_offset(buffer), text))
+                        .collect::<Vec<_>>(),
+                )
+            })
+        })
+        .collect::<HashMap<_, _>>();
+    assert_eq!(
+        edits,
+        HashMap::from_iter([
+            (
+                "a.ts".to_string(),
+                vec![(4..4, "_".into()), (16..16, "_".into())]
+            ),
+            ("b.ts".to_string(), vec![(4..4, "_".into())]),
+        ])
+    );
+    assert_eq!(
+        buffer_a.read_with(cx, |buffer, _| buffer.text()),
+        "let a = 1;\nlet b = 2;\n"
+    );
+}
+
 #[gpui::test(iterations = 10)]
 async fn test_save_file(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -4874,6 +5111,168 @@ async fn test_search(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_replace_on_disk(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE + one::ONE;",
+            "four.rs": "const FOUR: usize = one::ONE;",
+            "none.rs": "const NONE: usize = 0;",
+        }),
+    )
+    .await;
+    let utf16: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain("ONE".encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    fs.insert_file(path!("/dir/three.rs"), utf16).await;
+    fs.insert_file(path!("/dir/latin1.rs"), b"ONE = \xE9;".to_vec())
+        .await;
+    let utf32: Vec<u8> = [0xFF, 0xFE, 0x00, 0x00]
+        .into_iter()
+        .chain("ONE".chars().flat_map(|c| (c as u32).to_le_bytes()))
+        .collect();
+    fs.insert_file(path!("/dir/utf32.rs"), utf32.clone()).await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    let buffer_4 = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/four.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let query = SearchQuery::text(
+        "ONE",
+        true,
+        true,
+        false,
+        Default::default(),
+        Default::default(),
+        false,
+        None,
+    )
+    .unwrap()
+    .with_replacement("UNO".to_string());
+    let summary = project
+        .update(cx, |project, cx| project.replace_on_disk(query, cx))
+        .await
+        .unwrap();
+
+    assert_eq!(summary.replacements, 5);
+    assert_eq!(summary.files_changed, 3);
+    assert_eq!(summary.buffers_changed, 1);
+    assert_eq!(
+        summary.skipped_files,
+        vec![
+            PathBuf::from(path!("/dir/latin1.rs")),
+            PathBuf::from(path!("/dir/utf32.rs"))
+        ]
+    );
+    assert_eq!(
+        fs.load_bytes(path!("/dir/utf32.rs").as_ref())
+            .await
+            .unwrap(),
+        utf32
+    );
+
+    assert_eq!(
+        fs.load(path!("/dir/one.rs").as_ref()).await.unwrap(),
+        "const UNO: usize = 1;"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/two.rs").as_ref()).await.unwrap(),
+        "const TWO: usize = one::UNO + one::UNO;"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/none.rs").as_ref()).await.unwrap(),
+        "const NONE: usize = 0;"
+    );
+    let utf16: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain("UNO".encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    assert_eq!(
+        fs.load_bytes(path!("/dir/three.rs").as_ref())
+            .await
+            .unwrap(),
+        utf16
+    );
+
+    // Open buffers are edited in memory, and left for the user to save.
+    buffer_4.update(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "const FOUR: usize = one::UNO;")
+    });
+    assert_eq!(
+        fs.load(path!("/dir/four.rs").as_ref()).await.unwrap(),
+        "const FOUR: usize = one::ONE;"
+    );
+
+    assert_eq!(
+        fs.load(&summary.backup_dir.join("dir").join("one.rs"))
+            .await
+            .unwrap(),
+        "const ONE: usize = 1;"
+    );
+}
+
+#[gpui::test]
+async fn test_replace_on_disk_in_worktrees_with_the_same_name(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/a"),
+        json!({ "dir": { "one.rs": "const ONE: usize = 1;" } }),
+    )
+    .await;
+    fs.insert_tree(
+        path!("/b"),
+        json!({ "dir": { "one.rs": "const ONE: usize = 2;" } }),
+    )
+    .await;
+    let project = Project::test(
+        fs.clone(),
+        [path!("/a/dir").as_ref(), path!("/b/dir").as_ref()],
+        cx,
+    )
+    .await;
+
+    let query = SearchQuery::text(
+        "ONE",
+        true,
+        true,
+        false,
+        Default::default(),
+        Default::default(),
+        false,
+        None,
+    )
+    .unwrap()
+    .with_replacement("UNO".to_string());
+    let summary = project
+        .update(cx, |project, cx| project.replace_on_disk(query, cx))
+        .await
+        .unwrap();
+    assert_eq!(summary.files_changed, 2);
+
+    let mut backups = [
+        fs.load(&summary.backup_dir.join("dir").join("one.rs"))
+            .await
+            .unwrap(),
+        fs.load(&summary.backup_dir.join("dir-2").join("one.rs"))
+            .await
+            .unwrap(),
+    ];
+    backups.sort();
+    assert_eq!(backups, ["const ONE: usize = 1;", "const ONE: usize = 2;"]);
+}
+
 #[gpui::test]
 async fn test_search_with_inclusions(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -6539,6 +6938,88 @@ async fn test_uncommitted_diff_for_buffer(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_formatting_modified_lines_on_save(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.defaults.format_on_save = Some(FormatOnSave::Off);
+                settings.defaults.remove_trailing_whitespace_on_save = Some(true);
+                settings.defaults.format_on_save_scope = Some(FormatOnSaveScope::ModifiedLines);
+            });
+        });
+    });
+
+    let committed_contents = "one  \ntwo\nthree  \n";
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".git": {},
+            "a.txt": committed_contents,
+        }),
+    )
+    .await;
+    fs.set_head_for_repo(
+        Path::new(path!("/dir/.git")),
+        &[("a.txt".into(), committed_contents.into())],
+    );
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
+        })
+        .await
+        .unwrap();
+    cx.run_until_parked();
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(Point::new(1, 3)..Point::new(1, 3), "!  ")], None, cx)
+    });
+
+    project
+        .update(cx, |project, cx| {
+            project.format(
+                HashSet::from_iter([buffer.clone()]),
+                LspFormatTarget::Buffers,
+                true,
+                lsp_store::FormatTrigger::Save,
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    // Only the trailing whitespace on the edited line is removed.
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "one  \ntwo!\nthree  \n");
+    });
+
+    // Files are formatted in full when the scope is the whole file.
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.defaults.format_on_save_scope = Some(FormatOnSaveScope::File);
+            });
+        });
+    });
+    project
+        .update(cx, |project, cx| {
+            project.format(
+                HashSet::from_iter([buffer.clone()]),
+                LspFormatTarget::Buffers,
+                true,
+                lsp_store::FormatTrigger::Save,
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "one\ntwo!\nthree\n");
+    });
+}
+
 #[gpui::test]
 async fn test_staging_hunks(cx: &mut gpui::TestAppContext) {
     use DiffHunkSecondaryStatus::*;
@@ -0,0 +1,178 @@
+use std::io::{BufRead as _, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use fs::Fs;
+
+use crate::search::SearchQuery;
+
+/// The outcome of replacing matches across a project without opening buffers for unopened files.
+#[derive(Debug, Default)]
+pub struct ReplaceOnDiskSummary {
+    pub replacements: usize,
+    pub files_changed: usize,
+    /// Open buffers that were edited in memory and still need to be saved.
+    pub buffers_changed: usize,
+    /// Files that couldn't be decoded or rewritten, and were left untouched.
+    pub skipped_files: Vec<PathBuf>,
+    /// Where the original contents of every changed file were copied to.
+    pub backup_dir: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum FileReplacement {
+    Unchanged,
+    Replaced(usize),
+    UnsupportedEncoding,
+}
+
+/// The encodings that files can be rewritten in without changing their bytes outside of matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+const UTF32_LE_BOM: &[u8] = &[0xFF, 0xFE, 0x00, 0x00];
+const UTF32_BE_BOM: &[u8] = &[0x00, 0x00, 0xFE, 0xFF];
+
+impl TextEncoding {
+    /// Returns `None` for the encodings that are recognized but can't be rewritten.
+    fn detect(prefix: &[u8]) -> Option<Self> {
+        // The UTF-32 little-endian BOM starts with the UTF-16 one, and its text would decode as
+        // UTF-16 full of NULs.
+        if prefix.starts_with(UTF32_LE_BOM) || prefix.starts_with(UTF32_BE_BOM) {
+            None
+        } else if prefix.starts_with(UTF8_BOM) {
+            Some(Self::Utf8Bom)
+        } else if prefix.starts_with(UTF16_LE_BOM) {
+            Some(Self::Utf16Le)
+        } else if prefix.starts_with(UTF16_BE_BOM) {
+            Some(Self::Utf16Be)
+        } else {
+            Some(Self::Utf8)
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            Self::Utf8Bom => String::from_utf8(bytes.get(UTF8_BOM.len()..)?.to_vec()).ok(),
+            Self::Utf16Le | Self::Utf16Be => {
+                let bytes = bytes.get(UTF16_LE_BOM.len()..)?;
+                if bytes.len() % 2 != 0 {
+                    return None;
+                }
+                let units = bytes
+                    .chunks_exact(2)
+                    .map(|pair| {
+                        let pair = [pair[0], pair[1]];
+                        if self == Self::Utf16Le {
+                            u16::from_le_bytes(pair)
+                        } else {
+                            u16::from_be_bytes(pair)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                String::from_utf16(&units).ok()
+            }
+        }
+    }
+
+    fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => text.as_bytes().to_vec(),
+            Self::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            Self::Utf16Le => UTF16_LE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+            Self::Utf16Be => UTF16_BE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect(),
+        }
+    }
+}
+
+/// Replaces every match of `query` in the file at `abs_path`, copying its original contents to
+/// `backup_path` first.
+///
+/// UTF-8 files are scanned as a stream and only read in full when they contain a match, so that
+/// replacing across a large project doesn't load every file into memory.
+pub(crate) async fn replace_in_file(
+    fs: &Arc<dyn Fs>,
+    abs_path: &Path,
+    query: &SearchQuery,
+    backup_path: &Path,
+) -> Result<FileReplacement> {
+    let file = fs.open_sync(abs_path).await?;
+    let mut reader = BufReader::new(file);
+    let prefix = reader.fill_buf()?;
+    let Some(encoding) = TextEncoding::detect(prefix) else {
+        return Ok(FileReplacement::UnsupportedEncoding);
+    };
+    if encoding == TextEncoding::Utf8 {
+        // Like project search, ignore files that look binary rather than reporting them.
+        if prefix.contains(&0) {
+            return Ok(FileReplacement::Unchanged);
+        }
+        // Files in other encodings fail to scan, and are only reported if they might contain a
+        // match.
+        if !query.detect(reader).unwrap_or(true) {
+            return Ok(FileReplacement::Unchanged);
+        }
+    }
+
+    let bytes = fs.load_bytes(abs_path).await?;
+    let Some(text) = encoding.decode(&bytes) else {
+        return Ok(FileReplacement::UnsupportedEncoding);
+    };
+    let Some((new_text, replacements)) = query.replace_all_in_text(&text) else {
+        return Ok(FileReplacement::Unchanged);
+    };
+
+    if let Some(backup_parent) = backup_path.parent() {
+        fs.create_dir(backup_parent).await?;
+    }
+    fs.write(backup_path, &bytes)
+        .await
+        .with_context(|| format!("backing up {abs_path:?}"))?;
+    fs.write(abs_path, &encoding.encode(&new_text))
+        .await
+        .with_context(|| format!("writing {abs_path:?}"))?;
+    Ok(FileReplacement::Replaced(replacements))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_encoding_round_trip() {
+        let text = "héllo\r\nwörld";
+        for encoding in [
+            TextEncoding::Utf8,
+            TextEncoding::Utf8Bom,
+            TextEncoding::Utf16Le,
+            TextEncoding::Utf16Be,
+        ] {
+            let bytes = encoding.encode(text);
+            assert_eq!(TextEncoding::detect(&bytes), Some(encoding));
+            assert_eq!(encoding.decode(&bytes).as_deref(), Some(text));
+        }
+
+        assert_eq!(TextEncoding::detect(&[0xFF, 0xFE, 0x00, 0x00, 0x41]), None);
+        assert_eq!(TextEncoding::detect(&[0x00, 0x00, 0xFE, 0xFF]), None);
+        assert_eq!(TextEncoding::Utf16Le.decode(&[0xFF, 0xFE, 0x41]), None);
+        assert_eq!(TextEncoding::Utf8.decode(&[0x66, 0xE9, 0x65]), None);
+    }
+}
@@ -322,6 +322,60 @@ impl SearchQuery {
         }
     }
 
+    /// Replaces every match of this query in `text`, returning the new text and the number of
+    /// replacements, or `None` if nothing matched or no replacement is set.
+    pub fn replace_all_in_text(&self, text: &str) -> Option<(String, usize)> {
+        if self.as_str().is_empty() || self.replacement().is_none() {
+            return None;
+        }
+
+        let mut ranges = Vec::new();
+        match self {
+            Self::Text {
+                search, whole_word, ..
+            } => {
+                for mat in search.find_iter(text) {
+                    if *whole_word && !is_whole_word(text, mat.range()) {
+                        continue;
+                    }
+                    ranges.push(mat.range());
+                }
+            }
+            Self::Regex {
+                regex, multiline, ..
+            } => {
+                if *multiline {
+                    for mat in regex.find_iter(text).flatten() {
+                        ranges.push(mat.range());
+                    }
+                } else {
+                    let mut line_offset = 0;
+                    for line in text.split('\n') {
+                        // Buffers normalize line endings, so `$` must match before a `\r\n` too.
+                        let line_text = line.strip_suffix('\r').unwrap_or(line);
+                        for mat in regex.find_iter(line_text).flatten() {
+                            ranges.push(line_offset + mat.start()..line_offset + mat.end());
+                        }
+                        line_offset += line.len() + 1;
+                    }
+                }
+            }
+        }
+        if ranges.is_empty() {
+            return None;
+        }
+
+        let mut new_text = String::with_capacity(text.len());
+        let mut last_end = 0;
+        for range in &ranges {
+            new_text.push_str(&text[last_end..range.start]);
+            new_text.push_str(&self.replacement_for(&text[range.clone()])?);
+            last_end = range.end;
+        }
+        new_text.push_str(&text[last_end..]);
+        Some((new_text, ranges.len()))
+    }
+
     pub async fn search(
         &self,
         buffer: &BufferSnapshot,
@@ -517,6 +571,19 @@ impl SearchQuery {
     }
 }
 
+fn is_whole_word(text: &str, range: Range<usize>) -> bool {
+    fn is_word(c: char) -> bool {
+        c.is_alphanumeric() || c == '_'
+    }
+
+    let mat = &text[range.clone()];
+    let starts_mid_word = text[..range.start].chars().next_back().is_some_and(is_word)
+        && mat.chars().next().is_some_and(is_word);
+    let ends_mid_word = text[range.end..].chars().next().is_some_and(is_word)
+        && mat.chars().next_back().is_some_and(is_word);
+    !starts_mid_word && !ends_mid_word
+}
+
 pub fn deserialize_path_matches(glob_set: &str) -> anyhow::Result<PathMatcher> {
     let globs = glob_set
         .split(',')
@@ -572,4 +639,43 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn replace_all_in_text() {
+        let query = SearchQuery::text(
+            "foo",
+            true,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            false,
+            None,
+        )
+        .unwrap()
+        .with_replacement("bar".to_string());
+        assert_eq!(
+            query.replace_all_in_text("foo food Foo foo\r\nfoo"),
+            Some(("bar food Foo bar\r\nbar".to_string(), 3))
+        );
+        assert_eq!(query.replace_all_in_text("food"), None);
+
+        let query = SearchQuery::regex(
+            r"(\w+)$",
+            false,
+            true,
+            false,
+            false,
+            Default::default(),
+            Default::default(),
+            false,
+            None,
+        )
+        .unwrap()
+        .with_replacement("<$1>".to_string());
+        assert_eq!(
+            query.replace_all_in_text("one two\r\nthree four\n"),
+            Some(("one <two>\r\nthree <four>\n".to_string(), 2))
+        );
+    }
 }
@@ -115,6 +115,14 @@ impl WorktreeStore {
         }
     }
 
+    /// The filesystem of the worktrees, which only local projects have access to.
+    pub fn fs(&self) -> Option<Arc<dyn Fs>> {
+        match &self.state {
+            WorktreeStoreState::Local { fs } => Some(fs.clone()),
+            WorktreeStoreState::Remote { .. } => None,
+        }
+    }
+
     /// Iterates through all worktrees, including ones that don't appear in the project panel
     pub fn worktrees(&self) -> impl '_ + DoubleEndedIterator<Item = Entity<Worktree>> {
         self.worktrees
@@ -768,17 +776,8 @@ impl WorktreeStore {
             results.sort_by(|(a_path, _), (b_path, _)| a_path.cmp(b_path));
             for (path, is_file) in results {
                 if is_file {
-                    if query.filters_path() {
-                        let matched_path = if query.match_full_paths() {
-                            let mut full_path = PathBuf::from(snapshot.root_name());
-                            full_path.push(&path);
-                            query.match_path(&full_path)
-                        } else {
-                            query.match_path(&path)
-                        };
-                        if !matched_path {
-                            continue;
-                        }
+                    if !Self::matches_path_filters(query, snapshot, &path) {
+                        continue;
                     }
                     let (tx, rx) = oneshot::channel();
                     output_tx.send(rx).await?;
@@ -831,17 +830,8 @@ impl WorktreeStore {
                     continue;
                 }
 
-                if query.filters_path() {
-                    let matched_path = if query.match_full_paths() {
-                        let mut full_path = PathBuf::from(snapshot.root_name());
-                        full_path.push(&entry.path);
-                        query.match_path(&full_path)
-                    } else {
-                        query.match_path(&entry.path)
-                    };
-                    if !matched_path {
-                        continue;
-                    }
+                if !Self::matches_path_filters(&query, &snapshot, &entry.path) {
+                    continue;
                 }
 
                 let (mut tx, rx) = oneshot::channel();
@@ -871,6 +861,83 @@ impl WorktreeStore {
         Ok(())
     }
 
+    fn matches_path_filters(
+        query: &SearchQuery,
+        snapshot: &worktree::Snapshot,
+        path: &Path,
+    ) -> bool {
+        if !query.filters_path() {
+            return true;
+        }
+        if query.match_full_paths() {
+            let mut full_path = PathBuf::from(snapshot.root_name());
+            full_path.push(path);
+            query.match_path(&full_path)
+        } else {
+            query.match_path(path)
+        }
+    }
+
+    /// Returns the files in visible local worktrees that `query` applies to and that aren't open
+    /// in buffers, as pairs of absolute and worktree-relative paths.
+    ///
+    /// Ignored directories are only included as far as the worktrees have already scanned them.
+    pub fn unopened_search_paths(
+        &self,
+        query: &SearchQuery,
+        open_entries: &HashSet<ProjectEntryId>,
+        cx: &App,
+    ) -> Vec<(PathBuf, PathBuf)> {
+        let mut paths = Vec::new();
+        let mut backup_names = HashSet::default();
+        for worktree in self.visible_worktrees(cx) {
+            let worktree = worktree.read(cx);
+            if worktree.as_local().is_none() {
+                continue;
+            }
+            let snapshot = worktree.snapshot();
+            // Worktrees in different directories can have the same name, and their backups
+            // mustn't overwrite each other.
+            let root_name = snapshot.root_name();
+            let mut backup_name = root_name.to_string();
+            let mut suffix = 1;
+            while !backup_names.insert(backup_name.clone()) {
+                suffix += 1;
+                backup_name = format!("{root_name}-{suffix}");
+            }
+            for entry in snapshot.entries(query.include_ignored(), 0) {
+                if entry.is_fifo
+                    || !entry.is_file()
+                    || open_entries.contains(&entry.id)
+                    || !Self::matches_path_filters(query, &snapshot, &entry.path)
+                {
+                    continue;
+                }
+                paths.push((
+                    snapshot.abs_path().join(&entry.path),
+                    Path::new(&backup_name).join(&entry.path),
+                ));
+            }
+        }
+        paths
+    }
+
+    /// Returns whether `path` in `worktree_id` passes the include and exclude filters of `query`.
+    pub fn path_matches_query(
+        &self,
+        query: &SearchQuery,
+        worktree_id: WorktreeId,
+        path: &Path,
+        cx: &App,
+    ) -> bool {
+        self.worktree_for_id(worktree_id, cx)
+            .is_some_and(|worktree| {
+                let worktree = worktree.read(cx);
+                worktree.is_visible()
+                    && Self::matches_path_filters(query, &worktree.snapshot(), path)
+            })
+    }
+
     async fn filter_paths(
         fs: &Arc<dyn Fs>,
         input: Receiver<MatchingEntry>,
@@ -51,6 +51,16 @@ impl ProjectContext {
 #[derive(Debug, Clone, Serialize)]
 pub struct ModelContext {
     pub available_tools: Vec<String>,
+    /// The guidance of the MCP servers whose tools are available, which the prompt builder
+    /// deduplicates before rendering.
+    #[serde(skip)]
+    pub context_server_instructions: Vec<ContextServerInstructions>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextServerInstructions {
+    pub server_name: String,
+    pub instructions: String,
 }
 
 #[derive(Serialize)]
@@ -62,6 +72,9 @@ struct PromptTemplateContext {
     model: ModelContext,
 
     has_tools: bool,
+    context_server_instructions: Vec<ContextServerInstructions>,
+    /// `!context_server_instructions.is_empty()` - provided as a field because handlebars can't do this.
+    has_context_server_instructions: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -327,10 +340,14 @@ impl PromptBuilder {
         context: &ProjectContext,
         model_context: &ModelContext,
     ) -> Result<String, RenderError> {
+        let context_server_instructions =
+            Self::dedup_context_server_instructions(&model_context.context_server_instructions);
         let template_context = PromptTemplateContext {
             project: context.clone(),
             model: model_context.clone(),
             has_tools: !model_context.available_tools.is_empty(),
+            has_context_server_instructions: !context_server_instructions.is_empty(),
+            context_server_instructions,
         };
 
         self.handlebars
@@ -338,6 +355,39 @@ impl PromptBuilder {
             .render("assistant_system_prompt", &template_context)
     }
 
+    /// Merges the instructions that several servers share, such as servers running the same
+    /// program, so that they're only in the prompt once, under the names of all of them.
+    fn dedup_context_server_instructions(
+        instructions: &[ContextServerInstructions],
+    ) -> Vec<ContextServerInstructions> {
+        let mut deduped: Vec<ContextServerInstructions> = Vec::new();
+        for entry in instructions {
+            let text = entry.instructions.trim();
+            if text.is_empty() {
+                continue;
+            }
+            if let Some(existing) = deduped
+                .iter_mut()
+                .find(|existing| existing.instructions == text)
+            {
+                let already_named = existing
+                    .server_name
+                    .split(", ")
+                    .any(|name| name == entry.server_name);
+                if !already_named {
+                    existing.server_name.push_str(", ");
+                    existing.server_name.push_str(&entry.server_name);
+                }
+            } else {
+                deduped.push(ContextServerInstructions {
+                    server_name: entry.server_name.clone(),
+                    instructions: text.to_string(),
+                });
+            }
+        }
+        deduped
+    }
+
     pub fn generate_inline_transformation_prompt(
         &self,
         user_prompt: String,
@@ -469,6 +519,7 @@ mod test {
         let project_context = ProjectContext::new(worktrees, default_user_rules);
         let model_context = ModelContext {
             available_tools: ["grep".into()].to_vec(),
+            context_server_instructions: Vec::new(),
         };
         let prompt = PromptBuilder::new(None)
             .unwrap()
@@ -493,6 +544,7 @@ mod test {
         // When the `grep` tool is enabled, it should be mentioned in the prompt
         let model_context = ModelContext {
             available_tools: ["grep".into()].to_vec(),
+            context_server_instructions: Vec::new(),
         };
         let prompt_with_grep = prompt_builder
             .generate_assistant_system_prompt(&project_context, &model_context)
@@ -505,6 +557,7 @@ mod test {
         // When the `grep` tool is disabled, it should not be mentioned in the prompt
         let model_context = ModelContext {
             available_tools: [].to_vec(),
+            context_server_instructions: Vec::new(),
         };
         let prompt_without_grep = prompt_builder
             .generate_assistant_system_prompt(&project_context, &model_context)
@@ -515,6 +568,42 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_context_server_instructions_are_deduplicated() {
+        let project_context = ProjectContext::new(Vec::new(), Vec::new());
+        let instructions = |server_name: &str, instructions: &str| ContextServerInstructions {
+            server_name: server_name.into(),
+            instructions: instructions.into(),
+        };
+        let model_context = ModelContext {
+            available_tools: ["query".into()].to_vec(),
+            context_server_instructions: vec![
+                instructions("postgres-staging", "Use LIMIT in every query."),
+                instructions("postgres-prod", "Use LIMIT in every query.\n"),
+                instructions("postgres-prod", "Never write to production."),
+                instructions("blank-server", "  "),
+            ],
+        };
+        let prompt = PromptBuilder::new(None)
+            .unwrap()
+            .generate_assistant_system_prompt(&project_context, &model_context)
+            .unwrap();
+        assert_eq!(prompt.matches("Use LIMIT in every query.").count(), 1);
+        assert!(prompt.contains("postgres-staging, postgres-prod"));
+        assert!(prompt.contains("Never write to production."));
+        assert!(!prompt.contains("blank-server"));
+
+        let model_context = ModelContext {
+            available_tools: Vec::new(),
+            ..model_context
+        };
+        let prompt = PromptBuilder::new(None)
+            .unwrap()
+            .generate_assistant_system_prompt(&project_context, &model_context)
+            .unwrap();
+        assert!(!prompt.contains("Use LIMIT in every query."));
+    }
+
     #[test]
     fn test_has_tool_helper() {
         let mut handlebars = Handlebars::new();
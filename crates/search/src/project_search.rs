@@ -39,15 +39,22 @@ use ui::{
 };
 use util::{ResultExt as _, paths::PathMatcher};
 use workspace::{
-    DeploySearch, ItemNavHistory, NewSearch, ToolbarItemEvent, ToolbarItemLocation,
+    DeploySearch, ItemNavHistory, NewSearch, Toast, ToolbarItemEvent, ToolbarItemLocation,
     ToolbarItemView, Workspace, WorkspaceId,
     item::{BreadcrumbText, Item, ItemEvent, ItemHandle},
+    notifications::NotificationId,
     searchable::{Direction, SearchableItem, SearchableItemHandle},
 };
 
 actions!(
     project_search,
-    [SearchInNew, ToggleFocus, NextField, ToggleFilters]
+    [
+        SearchInNew,
+        ToggleFocus,
+        NextField,
+        ToggleFilters,
+        ReplaceAllOnDisk
+    ]
 );
 
 #[derive(Default)]
@@ -70,6 +77,16 @@ pub fn init(cx: &mut App) {
                 search_bar.toggle_filters(window, cx);
             },
         );
+        register_workspace_action(
+            workspace,
+            move |search_bar, action: &ReplaceAllOnDisk, window, cx| {
+                if let Some(search) = search_bar.active_project_search.as_ref() {
+                    search.update(cx, |search, cx| {
+                        search.replace_all_on_disk(action, window, cx);
+                    })
+                }
+            },
+        );
         register_workspace_action(
             workspace,
             move |search_bar, _: &ToggleCaseSensitive, window, cx| {
@@ -717,6 +734,62 @@ impl ProjectSearchView {
         });
     }
 
+    /// Replaces every match in the project, rewriting files that aren't open directly on disk
+    /// rather than loading them into buffers.
+    fn replace_all_on_disk(
+        &mut self,
+        _: &ReplaceAllOnDisk,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(query) = self.build_search_query(cx) else {
+            return;
+        };
+        if query.is_opened_only() {
+            self.replace_all(&ReplaceAll, window, cx);
+            return;
+        }
+        let query = query.with_replacement(self.replacement(cx));
+
+        let project = self.entity.read(cx).project.clone();
+        let replace = project.update(cx, |project, cx| project.replace_on_disk(query, cx));
+        let workspace = self.workspace.clone();
+        cx.spawn_in(window, async move |this, cx| {
+            let message = match replace.await {
+                Ok(summary) => {
+                    let mut message = format!(
+                        "Replaced {} matches in {} files on disk and {} open buffers. \
+                        Original files were backed up to {}.",
+                        summary.replacements,
+                        summary.files_changed,
+                        summary.buffers_changed,
+                        summary.backup_dir.display()
+                    );
+                    if !summary.skipped_files.is_empty() {
+                        message.push_str(&format!(
+                            " Skipped {} files that couldn't be decoded or written.",
+                            summary.skipped_files.len()
+                        ));
+                    }
+                    message
+                }
+                Err(error) => format!("Failed to replace matches: {error}"),
+            };
+
+            this.update(cx, |this, cx| this.search(cx))?;
+            workspace.update(cx, |workspace, cx| {
+                struct ReplaceAllOnDiskToast;
+
+                workspace.show_toast(
+                    Toast::new(NotificationId::unique::<ReplaceAllOnDiskToast>(), message),
+                    cx,
+                )
+            })?;
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
     pub fn new(
         workspace: WeakEntity<Workspace>,
         entity: Entity<ProjectSearch>,
@@ -1,3 +1,4 @@
+use collections::BTreeMap;
 use schemars::schema::{
     ArrayValidation, InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec,
 };
@@ -6,6 +7,65 @@ use serde_json::Value;
 pub struct SettingsJsonSchemaParams<'a> {
     pub language_names: &'a [String],
     pub font_names: &'a [String],
+    /// The configuration schemas published by language servers, keyed by server name.
+    pub language_server_schemas: &'a BTreeMap<String, LanguageServerSettingsSchema>,
+}
+
+/// JSON schemas for the `initialization_options` and `settings` that a language server accepts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LanguageServerSettingsSchema {
+    pub initialization_options: Option<Value>,
+    pub settings: Option<Value>,
+}
+
+impl LanguageServerSettingsSchema {
+    /// Builds a schema for a server's `settings` from the `contributes.configuration` section of
+    /// its `package.json`, as published by servers that are also packaged as VS Code extensions.
+    ///
+    /// Configuration properties are declared with dotted keys (`"yaml.format.enable"`), which are
+    /// expanded into nested objects to match the settings that the server requests.
+    pub fn from_package_json(package_json: &Value) -> Option<Self> {
+        let configurations = match &package_json["contributes"]["configuration"] {
+            Value::Array(configurations) => configurations.iter().collect::<Vec<_>>(),
+            configuration @ Value::Object(_) => vec![configuration],
+            _ => return None,
+        };
+
+        let mut settings = serde_json::json!({ "type": "object" });
+        let mut has_properties = false;
+        for configuration in configurations {
+            let Some(properties) = configuration["properties"].as_object() else {
+                continue;
+            };
+            for (key, schema) in properties {
+                let mut object = &mut settings;
+                for segment in key.split('.') {
+                    object = object
+                        .as_object_mut()
+                        .and_then(|object| {
+                            object
+                                .entry("properties")
+                                .or_insert_with(|| Value::Object(Default::default()))
+                                .as_object_mut()
+                        })
+                        .map(|properties| {
+                            properties
+                                .entry(segment)
+                                .or_insert_with(|| serde_json::json!({ "type": "object" }))
+                        })?;
+                }
+                if let (Some(object), Some(schema)) = (object.as_object_mut(), schema.as_object()) {
+                    object.extend(schema.clone());
+                    has_properties = true;
+                }
+            }
+        }
+
+        has_properties.then(|| Self {
+            initialization_options: None,
+            settings: Some(settings),
+        })
+    }
 }
 
 impl SettingsJsonSchemaParams<'_> {
@@ -73,3 +133,51 @@ pub fn add_references_to_properties(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_language_server_settings_schema_from_package_json() {
+        let package_json = json!({
+            "name": "yaml-language-server",
+            "contributes": {
+                "configuration": [{
+                    "properties": {
+                        "yaml.format.enable": { "type": "boolean", "default": true },
+                        "yaml.schemas": { "type": "object" },
+                    },
+                }],
+            },
+        });
+        let schema = LanguageServerSettingsSchema::from_package_json(&package_json).unwrap();
+        assert_eq!(schema.initialization_options, None);
+        assert_eq!(
+            schema.settings,
+            Some(json!({
+                "type": "object",
+                "properties": {
+                    "yaml": {
+                        "type": "object",
+                        "properties": {
+                            "format": {
+                                "type": "object",
+                                "properties": {
+                                    "enable": { "type": "boolean", "default": true },
+                                },
+                            },
+                            "schemas": { "type": "object" },
+                        },
+                    },
+                },
+            }))
+        );
+
+        assert_eq!(
+            LanguageServerSettingsSchema::from_package_json(&json!({ "name": "server" })),
+            None
+        );
+    }
+}
@@ -0,0 +1,912 @@
+use std::fmt;
+
+use anyhow::{Context as _, Result};
+use collections::IndexMap;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::settings_store::parse_json_with_comments;
+
+/// The editor a keymap is being imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeymapImportSource {
+    VsCode,
+    JetBrains,
+}
+
+impl fmt::Display for KeymapImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapImportSource::VsCode => write!(f, "VS Code"),
+            KeymapImportSource::JetBrains => write!(f, "JetBrains"),
+        }
+    }
+}
+
+/// A binding that was translated to a Zed action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedBinding {
+    pub keystrokes: String,
+    pub action: &'static str,
+    pub context: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UntranslatedReason {
+    /// There is no known Zed action equivalent to the command.
+    UnknownCommand,
+    /// The keystroke uses a key or modifier that Zed can't express.
+    UnsupportedKeystroke,
+    /// The binding is only active under a condition Zed has no equivalent for.
+    UnsupportedCondition(String),
+    /// The binding removes a default binding, which Zed keymaps can't express by command name.
+    Removal,
+}
+
+impl fmt::Display for UntranslatedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UntranslatedReason::UnknownCommand => write!(f, "no equivalent Zed action"),
+            UntranslatedReason::UnsupportedKeystroke => write!(f, "unsupported keystroke"),
+            UntranslatedReason::UnsupportedCondition(condition) => {
+                write!(f, "unsupported condition `{condition}`")
+            }
+            UntranslatedReason::Removal => write!(f, "binding removals are not supported"),
+        }
+    }
+}
+
+/// A binding from the source keymap that could not be imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UntranslatedBinding {
+    pub keystrokes: String,
+    pub command: String,
+    pub reason: UntranslatedReason,
+}
+
+/// The result of translating another editor's keymap into Zed bindings.
+#[derive(Debug)]
+pub struct ImportedKeymap {
+    pub source: KeymapImportSource,
+    pub bindings: Vec<ImportedBinding>,
+    pub untranslated: Vec<UntranslatedBinding>,
+    /// Bindings that were left out because the keymap already has them, such as when importing
+    /// the same keymap again.
+    pub already_bound: Vec<ImportedBinding>,
+}
+
+#[derive(Deserialize)]
+struct VsCodeKeybinding {
+    key: String,
+    command: String,
+    #[serde(default)]
+    when: Option<String>,
+}
+
+const VSCODE_COMMANDS: &[(&str, &str)] = &[
+    ("workbench.action.showCommands", "command_palette::Toggle"),
+    ("workbench.action.quickOpen", "file_finder::Toggle"),
+    ("workbench.action.files.save", "workspace::Save"),
+    ("workbench.action.files.saveAll", "workspace::SaveAll"),
+    (
+        "workbench.action.files.newUntitledFile",
+        "workspace::NewFile",
+    ),
+    ("workbench.action.newWindow", "workspace::NewWindow"),
+    (
+        "workbench.action.closeActiveEditor",
+        "pane::CloseActiveItem",
+    ),
+    (
+        "workbench.action.reopenClosedEditor",
+        "pane::ReopenClosedItem",
+    ),
+    ("workbench.action.nextEditor", "pane::ActivateNextItem"),
+    (
+        "workbench.action.previousEditor",
+        "pane::ActivatePreviousItem",
+    ),
+    ("workbench.action.splitEditor", "pane::SplitRight"),
+    ("workbench.action.navigateBack", "pane::GoBack"),
+    ("workbench.action.navigateForward", "pane::GoForward"),
+    ("workbench.action.findInFiles", "pane::DeploySearch"),
+    (
+        "workbench.action.toggleSidebarVisibility",
+        "workspace::ToggleLeftDock",
+    ),
+    (
+        "workbench.action.toggleAuxiliaryBar",
+        "workspace::ToggleRightDock",
+    ),
+    (
+        "workbench.action.togglePanel",
+        "workspace::ToggleBottomDock",
+    ),
+    (
+        "workbench.action.terminal.toggleTerminal",
+        "terminal_panel::ToggleFocus",
+    ),
+    ("workbench.view.explorer", "project_panel::ToggleFocus"),
+    ("workbench.action.gotoLine", "go_to_line::Toggle"),
+    ("workbench.action.gotoSymbol", "outline::Toggle"),
+    ("workbench.action.showAllSymbols", "project_symbols::Toggle"),
+    ("workbench.action.openRecent", "projects::OpenRecent"),
+    ("workbench.action.openSettings", "zed::OpenSettings"),
+    ("workbench.action.zoomIn", "zed::IncreaseBufferFontSize"),
+    ("workbench.action.zoomOut", "zed::DecreaseBufferFontSize"),
+    ("actions.find", "buffer_search::Deploy"),
+    (
+        "editor.action.startFindReplaceAction",
+        "buffer_search::DeployReplace",
+    ),
+    ("editor.action.commentLine", "editor::ToggleComments"),
+    ("editor.action.formatDocument", "editor::Format"),
+    ("editor.action.organizeImports", "editor::OrganizeImports"),
+    ("editor.action.rename", "editor::Rename"),
+    ("editor.action.revealDefinition", "editor::GoToDefinition"),
+    (
+        "editor.action.revealDefinitionAside",
+        "editor::GoToDefinitionSplit",
+    ),
+    (
+        "editor.action.goToImplementation",
+        "editor::GoToImplementation",
+    ),
+    (
+        "editor.action.goToTypeDefinition",
+        "editor::GoToTypeDefinition",
+    ),
+    ("editor.action.goToReferences", "editor::FindAllReferences"),
+    ("editor.action.quickFix", "editor::ToggleCodeActions"),
+    ("editor.action.showHover", "editor::Hover"),
+    ("editor.action.triggerSuggest", "editor::ShowCompletions"),
+    (
+        "editor.action.triggerParameterHints",
+        "editor::ShowSignatureHelp",
+    ),
+    ("editor.action.marker.next", "editor::GoToDiagnostic"),
+    (
+        "editor.action.marker.prev",
+        "editor::GoToPreviousDiagnostic",
+    ),
+    (
+        "editor.action.copyLinesDownAction",
+        "editor::DuplicateLineDown",
+    ),
+    ("editor.action.copyLinesUpAction", "editor::DuplicateLineUp"),
+    ("editor.action.moveLinesUpAction", "editor::MoveLineUp"),
+    ("editor.action.moveLinesDownAction", "editor::MoveLineDown"),
+    ("editor.action.deleteLines", "editor::DeleteLine"),
+    ("editor.action.joinLines", "editor::JoinLines"),
+    (
+        "editor.action.insertCursorAbove",
+        "editor::AddSelectionAbove",
+    ),
+    (
+        "editor.action.insertCursorBelow",
+        "editor::AddSelectionBelow",
+    ),
+    (
+        "editor.action.addSelectionToNextFindMatch",
+        "editor::SelectNext",
+    ),
+    ("editor.action.selectHighlights", "editor::SelectAllMatches"),
+    (
+        "editor.action.smartSelect.expand",
+        "editor::SelectLargerSyntaxNode",
+    ),
+    (
+        "editor.action.smartSelect.shrink",
+        "editor::SelectSmallerSyntaxNode",
+    ),
+    ("editor.action.insertLineBefore", "editor::NewlineAbove"),
+    ("editor.action.insertLineAfter", "editor::NewlineBelow"),
+    (
+        "editor.debug.action.toggleBreakpoint",
+        "editor::ToggleBreakpoint",
+    ),
+    ("editor.fold", "editor::Fold"),
+    ("editor.unfold", "editor::UnfoldLines"),
+    ("expandLineSelection", "editor::SelectLine"),
+    ("undo", "editor::Undo"),
+    ("redo", "editor::Redo"),
+    ("editor.action.clipboardCutAction", "editor::Cut"),
+    ("editor.action.clipboardCopyAction", "editor::Copy"),
+    ("editor.action.clipboardPasteAction", "editor::Paste"),
+    ("editor.action.selectAll", "editor::SelectAll"),
+];
+
+const JETBRAINS_ACTIONS: &[(&str, &str)] = &[
+    ("GotoAction", "command_palette::Toggle"),
+    ("GotoFile", "file_finder::Toggle"),
+    ("GotoSymbol", "project_symbols::Toggle"),
+    ("GotoLine", "go_to_line::Toggle"),
+    ("FileStructurePopup", "outline::Toggle"),
+    ("RecentFiles", "tab_switcher::Toggle"),
+    ("ManageRecentProjects", "projects::OpenRecent"),
+    ("ShowSettings", "zed::OpenSettings"),
+    ("SaveAll", "workspace::SaveAll"),
+    ("FindInPath", "pane::DeploySearch"),
+    ("CloseContent", "pane::CloseActiveItem"),
+    ("ReopenClosedTab", "pane::ReopenClosedItem"),
+    ("NextTab", "pane::ActivateNextItem"),
+    ("PreviousTab", "pane::ActivatePreviousItem"),
+    ("Back", "pane::GoBack"),
+    ("Forward", "pane::GoForward"),
+    ("ActivateProjectToolWindow", "project_panel::ToggleFocus"),
+    ("ActivateTerminalToolWindow", "terminal_panel::ToggleFocus"),
+    ("Find", "buffer_search::Deploy"),
+    ("Replace", "buffer_search::DeployReplace"),
+    ("CommentByLineComment", "editor::ToggleComments"),
+    ("ReformatCode", "editor::Format"),
+    ("OptimizeImports", "editor::OrganizeImports"),
+    ("RenameElement", "editor::Rename"),
+    ("GotoDeclaration", "editor::GoToDefinition"),
+    ("GotoImplementation", "editor::GoToImplementation"),
+    ("GotoTypeDeclaration", "editor::GoToTypeDefinition"),
+    ("FindUsages", "editor::FindAllReferences"),
+    ("ShowIntentionActions", "editor::ToggleCodeActions"),
+    ("QuickJavaDoc", "editor::Hover"),
+    ("CodeCompletion", "editor::ShowCompletions"),
+    ("ParameterInfo", "editor::ShowSignatureHelp"),
+    ("GotoNextError", "editor::GoToDiagnostic"),
+    ("GotoPreviousError", "editor::GoToPreviousDiagnostic"),
+    ("EditorDuplicate", "editor::DuplicateSelection"),
+    ("EditorDeleteLine", "editor::DeleteLine"),
+    ("EditorJoinLines", "editor::JoinLines"),
+    ("EditorSelectWord", "editor::SelectLargerSyntaxNode"),
+    ("EditorUnSelectWord", "editor::SelectSmallerSyntaxNode"),
+    ("EditorStartNewLineBefore", "editor::NewlineAbove"),
+    ("EditorStartNewLine", "editor::NewlineBelow"),
+    ("MoveLineUp", "editor::MoveLineUp"),
+    ("MoveLineDown", "editor::MoveLineDown"),
+    ("SelectNextOccurrence", "editor::SelectNext"),
+    ("SelectAllOccurrences", "editor::SelectAllMatches"),
+    ("CollapseRegion", "editor::Fold"),
+    ("ExpandRegion", "editor::UnfoldLines"),
+    ("ToggleLineBreakpoint", "editor::ToggleBreakpoint"),
+    ("$Undo", "editor::Undo"),
+    ("$Redo", "editor::Redo"),
+    ("$Cut", "editor::Cut"),
+    ("$Copy", "editor::Copy"),
+    ("$Paste", "editor::Paste"),
+    ("$SelectAll", "editor::SelectAll"),
+];
+
+const VSCODE_CONDITIONS: &[(&str, &str)] = &[
+    ("editorTextFocus", "Editor"),
+    ("editorFocus", "Editor"),
+    ("terminalFocus", "Terminal"),
+    ("filesExplorerFocus", "ProjectPanel"),
+    ("explorerViewletFocus", "ProjectPanel"),
+];
+
+fn lookup(table: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find_map(|(from, to)| (*from == key).then_some(*to))
+}
+
+/// Editor actions only make sense while an editor is focused, so scope them to
+/// the `Editor` context rather than binding them globally.
+fn default_context_for_action(action: &str) -> &'static str {
+    if action.starts_with("editor::") {
+        "Editor"
+    } else {
+        ""
+    }
+}
+
+fn platform_modifier() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "cmd"
+    } else {
+        "super"
+    }
+}
+
+impl ImportedKeymap {
+    /// Translates the contents of a VS Code `keybindings.json` file.
+    pub fn from_vscode_keybindings(content: &str) -> Result<Self> {
+        let keybindings: Vec<VsCodeKeybinding> =
+            parse_json_with_comments(content).context("Failed to parse VS Code keybindings")?;
+
+        let mut imported = Self {
+            source: KeymapImportSource::VsCode,
+            bindings: Vec::new(),
+            untranslated: Vec::new(),
+            already_bound: Vec::new(),
+        };
+        for keybinding in keybindings {
+            let untranslated = |reason| UntranslatedBinding {
+                keystrokes: keybinding.key.clone(),
+                command: keybinding.command.clone(),
+                reason,
+            };
+
+            if keybinding.command.starts_with('-') {
+                imported
+                    .untranslated
+                    .push(untranslated(UntranslatedReason::Removal));
+                continue;
+            }
+            let Some(action) = lookup(VSCODE_COMMANDS, &keybinding.command) else {
+                imported
+                    .untranslated
+                    .push(untranslated(UntranslatedReason::UnknownCommand));
+                continue;
+            };
+            let Some(keystrokes) = vscode_keystrokes(&keybinding.key) else {
+                imported
+                    .untranslated
+                    .push(untranslated(UntranslatedReason::UnsupportedKeystroke));
+                continue;
+            };
+            let context = match keybinding.when.as_deref().map(str::trim) {
+                None | Some("") => default_context_for_action(action),
+                Some(condition) => match lookup(VSCODE_CONDITIONS, condition) {
+                    Some(context) => context,
+                    None => {
+                        imported.untranslated.push(untranslated(
+                            UntranslatedReason::UnsupportedCondition(condition.to_string()),
+                        ));
+                        continue;
+                    }
+                },
+            };
+
+            imported.bindings.push(ImportedBinding {
+                keystrokes,
+                action,
+                context,
+            });
+        }
+        Ok(imported)
+    }
+
+    /// Translates the contents of a JetBrains keymap XML file, as found in the IDE's
+    /// `keymaps` config directory or produced by exporting a keymap.
+    pub fn from_jetbrains_keymap(content: &str) -> Result<Self> {
+        anyhow::ensure!(
+            content.contains("<keymap"),
+            "File does not look like a JetBrains keymap"
+        );
+
+        let mut imported = Self {
+            source: KeymapImportSource::JetBrains,
+            bindings: Vec::new(),
+            untranslated: Vec::new(),
+            already_bound: Vec::new(),
+        };
+        let mut current_action: Option<String> = None;
+        for tag in xml_tags(content) {
+            if tag.starts_with("/action") {
+                current_action = None;
+                continue;
+            }
+            if tag.starts_with("action") {
+                current_action = xml_attribute(tag, "id");
+                if tag.ends_with('/') {
+                    current_action = None;
+                }
+                continue;
+            }
+            if !tag.starts_with("keyboard-shortcut") {
+                continue;
+            }
+            let Some(command) = current_action.clone() else {
+                continue;
+            };
+            let Some(first) = xml_attribute(tag, "first-keystroke") else {
+                continue;
+            };
+            let second = xml_attribute(tag, "second-keystroke");
+            let source_keystrokes = match &second {
+                Some(second) => format!("{first}, {second}"),
+                None => first.clone(),
+            };
+            let untranslated = |reason| UntranslatedBinding {
+                keystrokes: source_keystrokes.clone(),
+                command: command.clone(),
+                reason,
+            };
+
+            let Some(action) = lookup(JETBRAINS_ACTIONS, &command) else {
+                imported
+                    .untranslated
+                    .push(untranslated(UntranslatedReason::UnknownCommand));
+                continue;
+            };
+            let keystrokes = std::iter::once(first.as_str())
+                .chain(second.as_deref())
+                .map(jetbrains_keystroke)
+                .collect::<Option<Vec<_>>>();
+            let Some(keystrokes) = keystrokes else {
+                imported
+                    .untranslated
+                    .push(untranslated(UntranslatedReason::UnsupportedKeystroke));
+                continue;
+            };
+
+            imported.bindings.push(ImportedBinding {
+                keystrokes: keystrokes.join(" "),
+                action,
+                context: default_context_for_action(action),
+            });
+        }
+        Ok(imported)
+    }
+
+    /// Returns the imported bindings as keymap sections, grouped by context in the
+    /// order the contexts were first encountered.
+    pub fn to_keymap_sections(&self) -> Vec<Value> {
+        keymap_sections(&self.bindings)
+    }
+
+    /// Moves the bindings that the keymap file already has to `already_bound`.
+    pub fn skip_bound_bindings(&mut self, keymap_text: &str) -> Result<()> {
+        let existing: Vec<Value> =
+            parse_json_with_comments(keymap_text).context("Failed to parse existing keymap")?;
+        let (already_bound, bindings) = self
+            .bindings
+            .drain(..)
+            .partition(|binding| is_bound(&existing, binding));
+        self.bindings = bindings;
+        self.already_bound.extend(already_bound);
+        Ok(())
+    }
+
+    /// Appends the imported bindings to the end of an existing keymap file. Later sections
+    /// take precedence, so imported bindings override the user's existing ones. Bindings the
+    /// file already has aren't appended again.
+    pub fn append_to_keymap_text(&self, old_text: &str) -> Result<String> {
+        let existing: Vec<Value> =
+            parse_json_with_comments(old_text).context("Failed to parse existing keymap")?;
+        let sections = keymap_sections(
+            self.bindings
+                .iter()
+                .filter(|binding| !is_bound(&existing, binding)),
+        );
+        if sections.is_empty() {
+            return Ok(old_text.to_string());
+        }
+
+        // Edit the text instead of re-serializing the whole file, so that the user's
+        // comments and formatting are preserved.
+        let close_ix =
+            closing_bracket_ix(old_text).context("Existing keymap is not a JSON array")?;
+        let head = old_text[..close_ix].trim_end();
+        let needs_comma = !existing.is_empty() && !head.ends_with(',');
+
+        let mut new_text = String::with_capacity(old_text.len());
+        new_text.push_str(head);
+        if needs_comma {
+            new_text.push(',');
+        }
+        new_text.push_str(&format!("\n  // Imported from {}\n", self.source));
+        for (ix, section) in sections.iter().enumerate() {
+            let section = serde_json::to_string_pretty(section)?;
+            for line in section.lines() {
+                new_text.push_str("  ");
+                new_text.push_str(line);
+                new_text.push('\n');
+            }
+            if ix + 1 < sections.len() {
+                new_text.pop();
+                new_text.push_str(",\n");
+            }
+        }
+        new_text.push_str(&old_text[close_ix..]);
+        Ok(new_text)
+    }
+
+    /// Returns a markdown report listing the bindings that could not be imported.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "# Keymap import from {}\n\nImported {} binding(s).",
+            self.source,
+            self.bindings.len()
+        );
+        if !self.already_bound.is_empty() {
+            report.push_str(&format!(
+                " Skipped {} binding(s) that were already in your keymap.",
+                self.already_bound.len()
+            ));
+        }
+        if self.untranslated.is_empty() {
+            report.push('\n');
+            return report;
+        }
+        report.push_str(&format!(
+            " The following {} binding(s) could not be imported:\n\n",
+            self.untranslated.len()
+        ));
+        for binding in &self.untranslated {
+            report.push_str(&format!(
+                "- `{}` → `{}`: {}\n",
+                binding.keystrokes, binding.command, binding.reason
+            ));
+        }
+        report
+    }
+}
+
+fn keymap_sections<'a>(bindings: impl IntoIterator<Item = &'a ImportedBinding>) -> Vec<Value> {
+    let mut sections = IndexMap::<&str, serde_json::Map<String, Value>>::default();
+    for binding in bindings {
+        sections.entry(binding.context).or_default().insert(
+            binding.keystrokes.clone(),
+            Value::String(binding.action.to_string()),
+        );
+    }
+    sections
+        .into_iter()
+        .map(|(context, bindings)| {
+            let mut section = serde_json::Map::new();
+            if !context.is_empty() {
+                section.insert("context".into(), Value::String(context.into()));
+            }
+            section.insert("bindings".into(), Value::Object(bindings));
+            Value::Object(section)
+        })
+        .collect()
+}
+
+/// Whether the keymap sections bind the binding's keystrokes to its action in its context. Only
+/// the last section binding the keystrokes counts, since it takes precedence over the others.
+fn is_bound(sections: &[Value], binding: &ImportedBinding) -> bool {
+    sections
+        .iter()
+        .rev()
+        .filter(|section| {
+            section.get("context").and_then(Value::as_str).unwrap_or("") == binding.context
+        })
+        .find_map(|section| section.get("bindings")?.get(&binding.keystrokes))
+        .is_some_and(|action| action.as_str() == Some(binding.action))
+}
+
+fn vscode_keystrokes(source: &str) -> Option<String> {
+    source
+        .split_whitespace()
+        .map(vscode_keystroke)
+        .collect::<Option<Vec<_>>>()
+        .map(|keystrokes| keystrokes.join(" "))
+}
+
+fn vscode_keystroke(source: &str) -> Option<String> {
+    let source = source.to_lowercase();
+    // `+` is both the separator and a valid key, as in `ctrl++`.
+    let (modifiers, key) = match source.strip_suffix("++") {
+        Some(modifiers) => (modifiers, "+"),
+        None => match source.rsplit_once('+') {
+            Some((modifiers, key)) => (modifiers, key),
+            None => ("", source.as_str()),
+        },
+    };
+
+    let mut components = Vec::new();
+    for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+        components.push(match modifier {
+            "ctrl" | "shift" | "alt" | "cmd" => modifier,
+            "meta" | "win" => platform_modifier(),
+            _ => return None,
+        });
+    }
+    let key = match key {
+        "escape" | "enter" | "tab" | "backspace" | "delete" | "insert" | "home" | "end"
+        | "pageup" | "pagedown" | "up" | "down" | "left" | "right" | "space" => key,
+        _ if key.len() > 1 && key.starts_with('f') && key[1..].parse::<u8>().is_ok() => key,
+        _ if key.chars().count() == 1 => key,
+        _ => return None,
+    };
+    components.push(key);
+    Some(components.join("-"))
+}
+
+fn jetbrains_keystroke(source: &str) -> Option<String> {
+    let mut components = Vec::new();
+    let mut key = None;
+    for part in source.split_whitespace() {
+        match part {
+            "ctrl" | "shift" | "alt" => components.push(part.to_string()),
+            "meta" => components.push(platform_modifier().to_string()),
+            _ if key.is_none() => key = Some(jetbrains_key(part)?),
+            _ => return None,
+        }
+    }
+    components.push(key?.to_string());
+    Some(components.join("-"))
+}
+
+fn jetbrains_key(key: &str) -> Option<String> {
+    let key = match key {
+        "ENTER" => "enter",
+        "ESCAPE" => "escape",
+        "BACK_SPACE" => "backspace",
+        "DELETE" => "delete",
+        "INSERT" => "insert",
+        "TAB" => "tab",
+        "SPACE" => "space",
+        "UP" => "up",
+        "DOWN" => "down",
+        "LEFT" => "left",
+        "RIGHT" => "right",
+        "HOME" => "home",
+        "END" => "end",
+        "PAGE_UP" => "pageup",
+        "PAGE_DOWN" => "pagedown",
+        "OPEN_BRACKET" => "[",
+        "CLOSE_BRACKET" => "]",
+        "SEMICOLON" => ";",
+        "QUOTE" => "'",
+        "COMMA" => ",",
+        "PERIOD" => ".",
+        "SLASH" => "/",
+        "BACK_SLASH" => "\\",
+        "MINUS" => "-",
+        "EQUALS" => "=",
+        "BACK_QUOTE" => "`",
+        _ if key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            return Some(key.to_ascii_lowercase());
+        }
+        _ if key.len() > 1 && key.starts_with('F') && key[1..].parse::<u8>().is_ok() => {
+            return Some(key.to_ascii_lowercase());
+        }
+        _ => return None,
+    };
+    Some(key.to_string())
+}
+
+/// Yields the contents of each tag in an XML document, without the surrounding angle brackets.
+///
+/// JetBrains keymaps are flat and attribute-only, so this avoids pulling in a full XML parser.
+fn xml_tags(content: &str) -> impl Iterator<Item = &str> {
+    content.split('<').skip(1).filter_map(|chunk| {
+        let (tag, _) = chunk.split_once('>')?;
+        let tag = tag.trim();
+        (!tag.starts_with('?') && !tag.starts_with('!')).then_some(tag)
+    })
+}
+
+fn xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!("{name}=\"");
+    let mut search_ix = 0;
+    while let Some(ix) = tag[search_ix..].find(&pattern) {
+        let start = search_ix + ix;
+        let preceded_by_space = tag[..start]
+            .chars()
+            .next_back()
+            .map_or(false, char::is_whitespace);
+        let value_start = start + pattern.len();
+        if preceded_by_space {
+            let value_len = tag[value_start..].find('"')?;
+            return Some(unescape_xml(&tag[value_start..value_start + value_len]));
+        }
+        search_ix = value_start;
+    }
+    None
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Returns the offset of the `]` that closes the top-level array, which can be followed by
+/// comments that contain brackets of their own.
+fn closing_bracket_ix(text: &str) -> Option<usize> {
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&tree_sitter_json::LANGUAGE.into())
+        .ok()?;
+    let tree = parser.parse(text, None)?;
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let array = root
+        .named_children(&mut cursor)
+        .find(|node| node.kind() == "array")?;
+    let close_ix = array.end_byte().checked_sub(1)?;
+    (text.as_bytes().get(close_ix) == Some(&b']')).then_some(close_ix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_import_vscode_keybindings() {
+        let imported = ImportedKeymap::from_vscode_keybindings(
+            r#"[
+                // A comment
+                { "key": "ctrl+shift+p", "command": "workbench.action.showCommands" },
+                { "key": "ctrl+k ctrl+c", "command": "editor.action.commentLine", "when": "editorTextFocus" },
+                { "key": "ctrl+d", "command": "editor.action.addSelectionToNextFindMatch" },
+                { "key": "ctrl+alt+x", "command": "someExtension.doThing" },
+                { "key": "ctrl+numpad_add", "command": "workbench.action.zoomIn" },
+                { "key": "ctrl+j", "command": "editor.action.joinLines", "when": "suggestWidgetVisible" },
+                { "key": "ctrl+w", "command": "-workbench.action.closeActiveEditor" },
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            imported.bindings,
+            vec![
+                ImportedBinding {
+                    keystrokes: "ctrl-shift-p".into(),
+                    action: "command_palette::Toggle",
+                    context: "",
+                },
+                ImportedBinding {
+                    keystrokes: "ctrl-k ctrl-c".into(),
+                    action: "editor::ToggleComments",
+                    context: "Editor",
+                },
+                ImportedBinding {
+                    keystrokes: "ctrl-d".into(),
+                    action: "editor::SelectNext",
+                    context: "Editor",
+                },
+            ]
+        );
+        assert_eq!(
+            imported
+                .untranslated
+                .iter()
+                .map(|binding| binding.reason.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                UntranslatedReason::UnknownCommand,
+                UntranslatedReason::UnsupportedKeystroke,
+                UntranslatedReason::UnsupportedCondition("suggestWidgetVisible".into()),
+                UntranslatedReason::Removal,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_jetbrains_keymap() {
+        let imported = ImportedKeymap::from_jetbrains_keymap(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <keymap version="1" name="Custom" parent="$default">
+              <action id="EditorDuplicate">
+                <keyboard-shortcut first-keystroke="ctrl D" />
+              </action>
+              <action id="GotoAction">
+                <keyboard-shortcut first-keystroke="ctrl shift A" />
+                <keyboard-shortcut first-keystroke="ctrl K" second-keystroke="ctrl OPEN_BRACKET" />
+              </action>
+              <action id="SomePluginAction">
+                <keyboard-shortcut first-keystroke="alt P" />
+              </action>
+              <action id="GotoDeclaration">
+                <keyboard-shortcut first-keystroke="ctrl NUMPAD1" />
+                <mouse-shortcut keystroke="ctrl button1" />
+              </action>
+              <action id="Unbound" />
+            </keymap>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            imported.bindings,
+            vec![
+                ImportedBinding {
+                    keystrokes: "ctrl-d".into(),
+                    action: "editor::DuplicateSelection",
+                    context: "Editor",
+                },
+                ImportedBinding {
+                    keystrokes: "ctrl-shift-a".into(),
+                    action: "command_palette::Toggle",
+                    context: "",
+                },
+                ImportedBinding {
+                    keystrokes: "ctrl-k ctrl-[".into(),
+                    action: "command_palette::Toggle",
+                    context: "",
+                },
+            ]
+        );
+        assert_eq!(
+            imported.untranslated,
+            vec![
+                UntranslatedBinding {
+                    keystrokes: "alt P".into(),
+                    command: "SomePluginAction".into(),
+                    reason: UntranslatedReason::UnknownCommand,
+                },
+                UntranslatedBinding {
+                    keystrokes: "ctrl NUMPAD1".into(),
+                    command: "GotoDeclaration".into(),
+                    reason: UntranslatedReason::UnsupportedKeystroke,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_append_imported_bindings_to_keymap() {
+        let imported = ImportedKeymap::from_vscode_keybindings(
+            r#"[
+                { "key": "ctrl+shift+p", "command": "workbench.action.showCommands" },
+                { "key": "ctrl+/", "command": "editor.action.commentLine" },
+            ]"#,
+        )
+        .unwrap();
+
+        let old_text = "[\n  // My bindings\n  {\n    \"bindings\": {}\n  },\n]\n";
+        assert_eq!(
+            imported.append_to_keymap_text(old_text).unwrap(),
+            concat!(
+                "[\n",
+                "  // My bindings\n",
+                "  {\n",
+                "    \"bindings\": {}\n",
+                "  },\n",
+                "  // Imported from VS Code\n",
+                "  {\n",
+                "    \"bindings\": {\n",
+                "      \"ctrl-shift-p\": \"command_palette::Toggle\"\n",
+                "    }\n",
+                "  },\n",
+                "  {\n",
+                "    \"context\": \"Editor\",\n",
+                "    \"bindings\": {\n",
+                "      \"ctrl-/\": \"editor::ToggleComments\"\n",
+                "    }\n",
+                "  }\n",
+                "]\n",
+            )
+        );
+
+        let new_text = imported.append_to_keymap_text("[]").unwrap();
+        assert!(crate::KeymapFile::parse(&new_text).is_ok());
+
+        let old_text =
+            "[\n  { \"bindings\": { \"ctrl-]\": \"pane::GoForward\" } }\n]\n// Use [ and ]\n";
+        let new_text = imported.append_to_keymap_text(old_text).unwrap();
+        assert!(crate::KeymapFile::parse(&new_text).is_ok());
+        assert!(new_text.ends_with("  }\n]\n// Use [ and ]\n"));
+    }
+
+    #[test]
+    fn test_reimporting_skips_existing_bindings() {
+        let content = r#"[
+            { "key": "ctrl+shift+p", "command": "workbench.action.showCommands" },
+            { "key": "ctrl+/", "command": "editor.action.commentLine" },
+        ]"#;
+        let imported = ImportedKeymap::from_vscode_keybindings(content).unwrap();
+        let old_text = imported.append_to_keymap_text("[]").unwrap();
+
+        let mut reimported = ImportedKeymap::from_vscode_keybindings(content).unwrap();
+        assert_eq!(
+            reimported.append_to_keymap_text(&old_text).unwrap(),
+            old_text
+        );
+        reimported.skip_bound_bindings(&old_text).unwrap();
+        assert!(reimported.bindings.is_empty());
+        assert_eq!(reimported.already_bound, imported.bindings);
+
+        // A binding that was since changed is imported again, to take precedence over the change.
+        let changed_text = format!(
+            "{}, {{ \"context\": \"Editor\", \"bindings\": {{ \"ctrl-/\": \"editor::Cancel\" }} }}]",
+            old_text.trim_end().trim_end_matches(']')
+        );
+        let mut reimported = ImportedKeymap::from_vscode_keybindings(content).unwrap();
+        reimported.skip_bound_bindings(&changed_text).unwrap();
+        assert_eq!(
+            reimported.bindings,
+            vec![ImportedBinding {
+                keystrokes: "ctrl-/".into(),
+                action: "editor::ToggleComments",
+                context: "Editor",
+            }]
+        );
+    }
+}
@@ -2,6 +2,7 @@ mod editable_setting_control;
 mod json_schema;
 mod key_equivalents;
 mod keymap_file;
+mod keymap_import;
 mod settings_file;
 mod settings_store;
 mod vscode_import;
@@ -17,6 +18,9 @@ pub use key_equivalents::*;
 pub use keymap_file::{
     KeyBindingValidator, KeyBindingValidatorRegistration, KeymapFile, KeymapFileLoadResult,
 };
+pub use keymap_import::{
+    ImportedBinding, ImportedKeymap, KeymapImportSource, UntranslatedBinding, UntranslatedReason,
+};
 pub use settings_file::*;
 pub use settings_store::{
     InvalidSettingsError, LocalSettingsKind, Settings, SettingsLocation, SettingsSources,
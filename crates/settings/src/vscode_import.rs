@@ -27,10 +27,32 @@ impl VsCodeSettings {
             return Some(value);
         }
         // TODO: maybe check if it's in [platform] settings for current platform as a fallback
-        // TODO: deal with language specific settings
         None
     }
 
+    /// Returns the language-specific overrides (e.g. `"[rust]"` or `"[javascript][typescript]"`)
+    /// as `(vscode_language_id, settings)` pairs.
+    pub fn language_overrides(&self) -> Vec<(&str, VsCodeSettings)> {
+        let mut overrides = Vec::new();
+        for (key, value) in &self.content {
+            let Some(ids) = key.strip_prefix('[').and_then(|key| key.strip_suffix(']')) else {
+                continue;
+            };
+            let Some(content) = value.as_object() else {
+                continue;
+            };
+            for id in ids.split("][") {
+                overrides.push((
+                    id,
+                    VsCodeSettings {
+                        content: content.clone(),
+                    },
+                ));
+            }
+        }
+        overrides
+    }
+
     pub fn read_string(&self, setting: &str) -> Option<&str> {
         self.read_value(setting).and_then(|v| v.as_str())
     }
@@ -1,18 +1,20 @@
 mod appearance_settings_controls;
 
 use std::any::TypeId;
+use std::sync::Arc;
 
+use anyhow::Context as _;
 use command_palette_hooks::CommandPaletteFilter;
-use editor::EditorSettingsControls;
+use editor::{Editor, EditorSettingsControls, MultiBuffer};
 use feature_flags::{FeatureFlag, FeatureFlagViewExt};
 use fs::Fs;
 use gpui::{
-    App, AsyncWindowContext, Entity, EventEmitter, FocusHandle, Focusable, Task, actions,
-    impl_actions,
+    App, AsyncWindowContext, Entity, EventEmitter, FocusHandle, Focusable, PathPromptOptions, Task,
+    WeakEntity, actions, impl_actions,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
-use settings::SettingsStore;
+use settings::{ImportedKeymap, KeymapFile, KeymapImportSource, SettingsStore};
 use ui::prelude::*;
 use workspace::Workspace;
 use workspace::item::{Item, ItemEvent};
@@ -32,7 +34,14 @@ pub struct ImportVsCodeSettings {
 }
 
 impl_actions!(zed, [ImportVsCodeSettings]);
-actions!(zed, [OpenSettingsEditor]);
+actions!(
+    zed,
+    [
+        OpenSettingsEditor,
+        ImportVsCodeKeymap,
+        ImportJetBrainsKeymap
+    ]
+);
 
 pub fn init(cx: &mut App) {
     cx.observe_new(|workspace: &mut Workspace, window, cx| {
@@ -98,16 +107,25 @@ pub fn init(cx: &mut App) {
                         return;
                     }
 
-                    cx.update(|_, cx| {
+                    cx.update(|window, cx| {
                         cx.global::<SettingsStore>()
                             .import_vscode_settings(fs, vscode);
                         log::info!("Imported settings from VsCode");
+                        window.dispatch_action(Box::new(zed_actions::SuggestVsCodeExtensions), cx);
                     })
                     .ok();
                 })
                 .detach();
         });
 
+        workspace.register_action(|_workspace, _: &ImportVsCodeKeymap, window, cx| {
+            import_keymap(KeymapImportSource::VsCode, cx.weak_entity(), window, cx);
+        });
+
+        workspace.register_action(|_workspace, _: &ImportJetBrainsKeymap, window, cx| {
+            import_keymap(KeymapImportSource::JetBrains, cx.weak_entity(), window, cx);
+        });
+
         let settings_ui_actions = [TypeId::of::<OpenSettingsEditor>()];
 
         CommandPaletteFilter::update_global(cx, |filter, _cx| {
@@ -133,6 +151,146 @@ pub fn init(cx: &mut App) {
     .detach();
 }
 
+fn import_keymap(
+    source: KeymapImportSource,
+    workspace: WeakEntity<Workspace>,
+    window: &mut Window,
+    cx: &mut App,
+) {
+    let fs = <dyn Fs>::global(cx);
+    let jetbrains_path = match source {
+        KeymapImportSource::VsCode => None,
+        KeymapImportSource::JetBrains => Some(cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        })),
+    };
+
+    window
+        .spawn(cx, async move |cx: &mut AsyncWindowContext| {
+            let source_path = match jetbrains_path {
+                None => paths::vscode_keybindings_file().clone(),
+                Some(paths) => match paths.await.ok().and_then(|paths| paths.ok().flatten()) {
+                    Some(mut paths) if !paths.is_empty() => paths.remove(0),
+                    _ => return,
+                },
+            };
+
+            let imported = fs
+                .load(&source_path)
+                .await
+                .and_then(|content| match source {
+                    KeymapImportSource::VsCode => ImportedKeymap::from_vscode_keybindings(&content),
+                    KeymapImportSource::JetBrains => {
+                        ImportedKeymap::from_jetbrains_keymap(&content)
+                    }
+                });
+            let mut imported = match imported {
+                Ok(imported) => imported,
+                Err(err) => {
+                    log::error!(
+                        "Failed to load {source} keymap from path {source_path:?}: {err:?}"
+                    );
+                    let _ = cx.prompt(
+                        gpui::PromptLevel::Info,
+                        &format!("Could not find or load a {source} keymap"),
+                        None,
+                        &["Ok"],
+                    );
+                    return;
+                }
+            };
+
+            let old_text = match KeymapFile::load_keymap_file(&fs).await.and_then(|old_text| {
+                imported.skip_bound_bindings(&old_text)?;
+                Ok(old_text)
+            }) {
+                Ok(old_text) => old_text,
+                Err(err) => {
+                    log::error!("Failed to import {source} keymap: {err:?}");
+                    return;
+                }
+            };
+            if imported.bindings.is_empty() && !imported.already_bound.is_empty() {
+                let _ = cx.prompt(
+                    gpui::PromptLevel::Info,
+                    &format!("Your keymap already has the bindings from {source}"),
+                    None,
+                    &["Ok"],
+                );
+                return;
+            }
+
+            let prompt = cx.prompt(
+                gpui::PromptLevel::Warning,
+                &format!(
+                    "Import {} binding(s) from {source}? They will take precedence over your existing bindings.",
+                    imported.bindings.len()
+                ),
+                None,
+                &["Ok", "Cancel"],
+            );
+            if prompt.await.ok() != Some(0) {
+                return;
+            }
+
+            if let Err(err) = write_imported_keymap(&fs, &imported, &old_text).await {
+                log::error!("Failed to import {source} keymap: {err:?}");
+                return;
+            }
+            log::info!("Imported keymap from {source}");
+
+            let report = imported.report();
+            workspace
+                .update_in(cx, |workspace, window, cx| {
+                    open_keymap_import_report(workspace, report, window, cx)
+                })
+                .ok();
+        })
+        .detach();
+}
+
+async fn write_imported_keymap(
+    fs: &Arc<dyn Fs>,
+    imported: &ImportedKeymap,
+    old_text: &str,
+) -> anyhow::Result<()> {
+    let new_text = imported.append_to_keymap_text(old_text)?;
+    let keymap_path = paths::keymap_file().as_path();
+    let keymap_path = if fs.is_file(keymap_path).await {
+        fs.canonicalize(keymap_path)
+            .await
+            .with_context(|| format!("Failed to canonicalize keymap path {:?}", keymap_path))?
+    } else {
+        keymap_path.to_path_buf()
+    };
+    fs.atomic_write(keymap_path.clone(), new_text)
+        .await
+        .with_context(|| format!("Failed to write keymap to file {:?}", keymap_path))
+}
+
+fn open_keymap_import_report(
+    workspace: &mut Workspace,
+    report: String,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let title = "Keymap Import Report";
+    let project = workspace.project().clone();
+    let buffer = project.update(cx, |project, cx| {
+        project.create_local_buffer(&report, None, cx)
+    });
+    let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title.into()));
+    let editor = cx.new(|cx| {
+        let mut editor = Editor::for_multibuffer(buffer, Some(project), window, cx);
+        editor.set_read_only(true);
+        editor.set_breadcrumb_header(title.into());
+        editor
+    });
+    workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx);
+}
+
 pub struct SettingsPage {
     focus_handle: FocusHandle,
 }
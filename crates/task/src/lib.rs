@@ -22,7 +22,7 @@ pub use debug_format::{
     Request, TcpArgumentsTemplate, ZedDebugConfig,
 };
 pub use task_template::{
-    DebugArgsRequest, HideStrategy, RevealStrategy, TaskTemplate, TaskTemplates,
+    DebugArgsRequest, HideStrategy, McpToolCall, RevealStrategy, TaskTemplate, TaskTemplates,
     substitute_variables_in_map, substitute_variables_in_str,
 };
 pub use vscode_debug_format::VsCodeDebugTaskFile;
@@ -119,6 +119,8 @@ pub struct ResolvedTask {
     pub resolved_label: String,
     /// Variables that were substituted during the task template resolution.
     substituted_variables: HashSet<VariableName>,
+    /// The MCP tool to call instead of spawning a command, with all task variables resolved.
+    pub mcp_tool: Option<McpToolCall>,
     /// Further actions that need to take place after the resolved task is spawned,
     /// with all task variables resolved.
     pub resolved: SpawnInTerminal,
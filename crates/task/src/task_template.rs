@@ -72,6 +72,22 @@ pub struct TaskTemplate {
     /// Whether to show the command line in the task output.
     #[serde(default = "default_true")]
     pub show_command: bool,
+    /// An MCP tool to call instead of running `command`, on a server configured in the settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp_tool: Option<McpToolCall>,
+}
+
+/// A call to a tool of a configured MCP server, which a task makes instead of running a command.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct McpToolCall {
+    /// The id of the server in the `context_servers` settings.
+    pub server: String,
+    /// The name of the tool to call.
+    pub tool: String,
+    /// The arguments to call the tool with, whose strings may use task variables.
+    #[serde(default)]
+    pub arguments: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Eq, PartialEq, Clone, Debug)]
@@ -133,7 +149,9 @@ impl TaskTemplate {
     /// Every [`ResolvedTask`] gets a [`TaskId`], based on the `id_base` (to avoid collision with various task sources),
     /// and hashes of its template and [`TaskContext`], see [`ResolvedTask`] fields' documentation for more details.
     pub fn resolve_task(&self, id_base: &str, cx: &TaskContext) -> Option<ResolvedTask> {
-        if self.label.trim().is_empty() || self.command.trim().is_empty() {
+        if self.label.trim().is_empty()
+            || (self.command.trim().is_empty() && self.mcp_tool.is_none())
+        {
             return None;
         }
 
@@ -209,6 +227,20 @@ impl TaskTemplate {
             &mut substituted_variables,
         )?;
 
+        let mcp_tool = match &self.mcp_tool {
+            Some(mcp_tool) => Some(McpToolCall {
+                server: mcp_tool.server.clone(),
+                tool: mcp_tool.tool.clone(),
+                arguments: substitute_all_template_variables_in_json_map(
+                    &mcp_tool.arguments,
+                    &task_variables,
+                    &variable_names,
+                    &mut substituted_variables,
+                )?,
+            }),
+            None => None,
+        };
+
         let task_hash = to_hex_hash(self)
             .context("hashing task template")
             .log_err()?;
@@ -242,6 +274,7 @@ impl TaskTemplate {
             substituted_variables,
             original_task: self.clone(),
             resolved_label: full_label.clone(),
+            mcp_tool,
             resolved: SpawnInTerminal {
                 id,
                 cwd,
@@ -371,6 +404,65 @@ fn substitute_all_template_variables_in_vec(
     Some(expanded)
 }
 
+fn substitute_all_template_variables_in_json_map(
+    map: &serde_json::Map<String, serde_json::Value>,
+    task_variables: &HashMap<String, &str>,
+    variable_names: &HashMap<String, VariableName>,
+    substituted_variables: &mut HashSet<VariableName>,
+) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let mut expanded = serde_json::Map::with_capacity(map.len());
+    for (key, value) in map {
+        let new_value = substitute_all_template_variables_in_json(
+            value,
+            task_variables,
+            variable_names,
+            substituted_variables,
+        )?;
+        expanded.insert(key.clone(), new_value);
+    }
+    Some(expanded)
+}
+
+fn substitute_all_template_variables_in_json(
+    value: &serde_json::Value,
+    task_variables: &HashMap<String, &str>,
+    variable_names: &HashMap<String, VariableName>,
+    substituted_variables: &mut HashSet<VariableName>,
+) -> Option<serde_json::Value> {
+    Some(match value {
+        serde_json::Value::String(string) => {
+            serde_json::Value::String(substitute_all_template_variables_in_str(
+                string,
+                task_variables,
+                variable_names,
+                substituted_variables,
+            )?)
+        }
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items
+                .iter()
+                .map(|item| {
+                    substitute_all_template_variables_in_json(
+                        item,
+                        task_variables,
+                        variable_names,
+                        substituted_variables,
+                    )
+                })
+                .collect::<Option<_>>()?,
+        ),
+        serde_json::Value::Object(map) => {
+            serde_json::Value::Object(substitute_all_template_variables_in_json_map(
+                map,
+                task_variables,
+                variable_names,
+                substituted_variables,
+            )?)
+        }
+        value => value.clone(),
+    })
+}
+
 pub fn substitute_variables_in_map(
     keys_and_values: &HashMap<String, String>,
     context: &TaskContext,
@@ -715,6 +807,70 @@ mod tests {
         assert_eq!(resolved.args, task.args);
     }
 
+    #[test]
+    fn test_resolving_mcp_tool_calls() {
+        let task = TaskTemplate {
+            label: "Deploy".into(),
+            mcp_tool: Some(McpToolCall {
+                server: "deployer".into(),
+                tool: "deploy".into(),
+                arguments: serde_json::json!({
+                    "file": "$ZED_FILE",
+                    "paths": ["${ZED_WORKTREE_ROOT}/dist"],
+                    "dry_run": false,
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            }),
+            ..TaskTemplate::default()
+        };
+        let resolved_task = task
+            .resolve_task(
+                TEST_ID_BASE,
+                &TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([
+                        (VariableName::File, "/project/main.rs".to_string()),
+                        (VariableName::WorktreeRoot, "/project".to_string()),
+                    ]),
+                    project_env: HashMap::default(),
+                },
+            )
+            .unwrap();
+        assert_substituted_variables(
+            &resolved_task,
+            vec![VariableName::File, VariableName::WorktreeRoot],
+        );
+        let mcp_tool = resolved_task.mcp_tool.unwrap();
+        assert_eq!(mcp_tool.server, "deployer");
+        assert_eq!(mcp_tool.tool, "deploy");
+        assert_eq!(
+            serde_json::Value::Object(mcp_tool.arguments),
+            serde_json::json!({
+                "file": "/project/main.rs",
+                "paths": ["/project/dist"],
+                "dry_run": false,
+            })
+        );
+
+        let task_without_tool_variable = TaskTemplate {
+            mcp_tool: Some(McpToolCall {
+                server: "deployer".into(),
+                tool: "deploy".into(),
+                arguments: serde_json::json!({ "symbol": "$ZED_SYMBOL" })
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            }),
+            ..task
+        };
+        assert_eq!(
+            task_without_tool_variable.resolve_task(TEST_ID_BASE, &TaskContext::default()),
+            None
+        );
+    }
+
     #[test]
     fn test_errors_on_missing_zed_variable() {
         let task = TaskTemplate {
@@ -724,6 +724,52 @@ fn test_concurrent_edits() {
     assert_eq!(buffer3.text(), "a12c34e56");
 }
 
+#[test]
+fn test_reverting_collaborator_edits() {
+    let mut buffer1 = Buffer::new(1, BufferId::new(1).unwrap(), "abcdef".into());
+    let mut buffer2 = Buffer::new(2, BufferId::new(1).unwrap(), "abcdef".into());
+    buffer1.set_group_interval(Duration::from_secs(0));
+
+    let local_op = buffer1.edit([(1..2, "12")]);
+    let remote_ops = vec![buffer2.edit([(4..5, "")]), buffer2.edit([(0..0, "XY")])];
+    buffer1.apply_ops(remote_ops);
+    buffer2.apply_ops([local_op]);
+    assert_eq!(buffer1.text(), "XYa12cdf");
+
+    // Undo only reverts this replica's own edits.
+    let mut ops = Vec::new();
+    ops.extend(buffer1.undo().map(|(_, op)| op));
+    assert_eq!(buffer1.text(), "XYabcdf");
+    ops.extend(buffer1.redo().map(|(_, op)| op));
+    assert_eq!(buffer1.text(), "XYa12cdf");
+
+    // Only the collaborator edits within the range are reverted.
+    ops.extend(
+        buffer1
+            .revert_collaborator_edits_in_ranges([5..8])
+            .map(|(_, op)| op),
+    );
+    assert_eq!(buffer1.text(), "XYa12cdef");
+    ops.extend(
+        buffer1
+            .revert_collaborator_edits_in_ranges([0..buffer1.len()])
+            .map(|(_, op)| op),
+    );
+    assert_eq!(buffer1.text(), "a12cdef");
+    assert!(
+        buffer1
+            .revert_collaborator_edits_in_ranges([0..buffer1.len()])
+            .is_none()
+    );
+
+    // Reverts are undone like any other transaction.
+    ops.extend(buffer1.undo().map(|(_, op)| op));
+    assert_eq!(buffer1.text(), "XYa12cdef");
+
+    buffer2.apply_ops(ops);
+    assert_eq!(buffer2.text(), "XYa12cdef");
+}
+
 #[gpui::test(iterations = 100)]
 fn test_random_concurrent_edits(mut rng: StdRng) {
     let peers = env::var("PEERS")
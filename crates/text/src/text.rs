@@ -1490,6 +1490,88 @@ impl Buffer {
         Operation::Undo(undo)
     }
 
+    /// Returns the edits made by other replicas that inserted or deleted text in the given ranges
+    /// and that are still in effect.
+    pub fn collaborator_edit_ids_in_ranges<T: ToOffset>(
+        &self,
+        ranges: impl IntoIterator<Item = Range<T>>,
+    ) -> Vec<clock::Lamport> {
+        // The base text isn't an operation, so it never counts as a collaborator's edit.
+        let is_collaborator_edit = |timestamp: &clock::Lamport| {
+            timestamp.replica_id != self.replica_id
+                && !self.undo_map.is_undone(*timestamp)
+                && matches!(
+                    self.history.operations.get(timestamp),
+                    Some(Operation::Edit(_))
+                )
+        };
+
+        let mut edit_ids = Vec::new();
+        let mut cursor = self.fragments.cursor::<FragmentTextSummary>(&None);
+        for range in ranges {
+            let start = range.start.to_offset(self);
+            let end = range.end.to_offset(self);
+            cursor.seek(&start, Bias::Left, &None);
+            while let Some(fragment) = cursor.item() {
+                let fragment_start = cursor.start().visible;
+                if fragment_start > end {
+                    break;
+                }
+
+                if fragment.visible {
+                    if fragment_start < end
+                        && fragment_start + fragment.len > start
+                        && is_collaborator_edit(&fragment.timestamp)
+                    {
+                        edit_ids.push(fragment.timestamp);
+                    }
+                } else if !self.undo_map.is_undone(fragment.timestamp)
+                    && !is_collaborator_edit(&fragment.timestamp)
+                {
+                    // Undoing deletions doesn't bring the text back if this replica deleted it too.
+                    let (collaborator_deletions, own_deletions) = fragment
+                        .deletions
+                        .iter()
+                        .filter(|deletion| !self.undo_map.is_undone(**deletion))
+                        .partition::<Vec<_>, _>(|deletion| is_collaborator_edit(deletion));
+                    if own_deletions.is_empty() {
+                        edit_ids.extend(collaborator_deletions);
+                    }
+                }
+                cursor.next(&None);
+            }
+        }
+        edit_ids.sort_unstable();
+        edit_ids.dedup();
+        edit_ids
+    }
+
+    /// Undoes the edits other replicas made in the given ranges, leaving this replica's own edits
+    /// in place. Edits are undone as a whole, including any of their parts outside the ranges.
+    ///
+    /// The revert is pushed onto this replica's undo stack, so undoing it reapplies the
+    /// collaborators' edits.
+    pub fn revert_collaborator_edits_in_ranges<T: ToOffset>(
+        &mut self,
+        ranges: impl IntoIterator<Item = Range<T>>,
+    ) -> Option<(TransactionId, Operation)> {
+        let edit_ids = self.collaborator_edit_ids_in_ranges(ranges);
+        if edit_ids.is_empty() {
+            return None;
+        }
+
+        let transaction = Transaction {
+            id: self.lamport_clock.tick(),
+            edit_ids,
+            start: self.version(),
+        };
+        let transaction_id = transaction.id;
+        let operation = self.undo_or_redo(transaction.clone());
+        self.history.push_transaction(transaction, Instant::now());
+        self.history.finalize_last_transaction();
+        Some((transaction_id, operation))
+    }
+
     pub fn push_transaction(&mut self, transaction: Transaction, now: Instant) {
         self.history.push_transaction(transaction, now);
     }
@@ -1011,8 +1011,34 @@ impl settings::Settings for ThemeSettings {
 
     fn import_from_vscode(vscode: &settings::VsCodeSettings, current: &mut Self::FileContent) {
         vscode.f32_setting("editor.fontWeight", &mut current.buffer_font_weight);
+        // VS Code also accepts the font weight as a string, e.g. `"600"`.
+        if let Some(weight) =
+            vscode
+                .read_string("editor.fontWeight")
+                .and_then(|weight| match weight {
+                    "normal" => Some(FontWeight::NORMAL.0),
+                    "bold" => Some(FontWeight::BOLD.0),
+                    weight => weight.parse().ok(),
+                })
+        {
+            current.buffer_font_weight = Some(weight);
+        }
         vscode.f32_setting("editor.fontSize", &mut current.buffer_font_size);
-        vscode.string_setting("editor.font", &mut current.buffer_font_family);
+        // `editor.fontFamily` is a CSS-style, comma-separated list of font families.
+        if let Some(families) = vscode.read_string("editor.fontFamily") {
+            let mut families = families
+                .split(',')
+                .map(|family| family.trim().trim_matches(|c| c == '\'' || c == '"'))
+                .filter(|family| !family.is_empty() && *family != "monospace")
+                .map(str::to_owned);
+            if let Some(family) = families.next() {
+                current.buffer_font_family = Some(family);
+                let fallbacks = families.collect::<Vec<_>>();
+                if !fallbacks.is_empty() {
+                    current.buffer_font_fallbacks = Some(fallbacks);
+                }
+            }
+        }
         // TODO: possibly map editor.fontLigatures to buffer_font_features?
     }
 }
@@ -31,7 +31,10 @@ use ui::ActiveTheme;
 use util::ResultExt;
 use workspace::notifications::DetachAndPromptErr;
 use workspace::{Item, SaveIntent, notifications::NotifyResultExt};
-use zed_actions::{OpenDocs, RevealTarget};
+use zed_actions::{
+    OpenDocs, RevealTarget,
+    agent::{DisableMcpServer, EnableMcpServer},
+};
 
 use crate::{
     ToggleMarksView, ToggleRegistersView, Vim,
@@ -966,6 +969,22 @@ fn generate_commands(_: &App) -> Vec<VimCommand> {
         VimCommand::str(("No", "tifications"), "notification_panel::ToggleFocus"),
         VimCommand::str(("A", "I"), "agent::ToggleFocus"),
         VimCommand::str(("G", "it"), "git_panel::ToggleFocus"),
+        VimCommand::new(("McpE", "nable"), EnableMcpServer::default()).args(|_, args| {
+            Some(
+                EnableMcpServer {
+                    server_id: Some(args),
+                }
+                .boxed_clone(),
+            )
+        }),
+        VimCommand::new(("McpD", "isable"), DisableMcpServer::default()).args(|_, args| {
+            Some(
+                DisableMcpServer {
+                    server_id: Some(args),
+                }
+                .boxed_clone(),
+            )
+        }),
         VimCommand::new(("noh", "lsearch"), search::buffer_search::Dismiss),
         VimCommand::new(("$", ""), EndOfDocument),
         VimCommand::new(("%", ""), EndOfDocument),
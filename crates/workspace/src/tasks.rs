@@ -55,6 +55,7 @@ impl Workspace {
         cx: &mut Context<Workspace>,
     ) {
         let spawn_in_terminal = resolved_task.resolved.clone();
+        let mcp_tool = resolved_task.mcp_tool.clone();
         if !omit_history {
             self.project().update(cx, |project, cx| {
                 if let Some(task_inventory) =
@@ -67,7 +68,20 @@ impl Workspace {
             });
         }
 
-        if let Some(terminal_provider) = self.terminal_provider.as_ref() {
+        if let Some(mcp_tool) = mcp_tool {
+            if let Some(mcp_tool_provider) = self.mcp_tool_provider.as_ref() {
+                let call =
+                    mcp_tool_provider.call_tool(spawn_in_terminal.full_label, mcp_tool, window, cx);
+                cx.background_spawn(async move {
+                    if let Err(e) = call.await {
+                        log::error!("Task MCP tool call failed: {e:#}");
+                    }
+                })
+                .detach();
+            } else {
+                log::warn!("Cannot call MCP tools for tasks without the agent");
+            }
+        } else if let Some(terminal_provider) = self.terminal_provider.as_ref() {
             let task_status = terminal_provider.spawn(spawn_in_terminal, window, cx);
             cx.background_spawn(async move {
                 match task_status.await {
@@ -96,7 +96,7 @@ use std::{
     sync::{Arc, LazyLock, Weak, atomic::AtomicUsize},
     time::Duration,
 };
-use task::{DebugScenario, SpawnInTerminal, TaskContext};
+use task::{DebugScenario, McpToolCall, SpawnInTerminal, TaskContext};
 use theme::{ActiveTheme, SystemAppearance, ThemeSettings};
 pub use toolbar::{Toolbar, ToolbarItemEvent, ToolbarItemLocation, ToolbarItemView};
 pub use ui;
@@ -139,6 +139,17 @@ pub trait TerminalProvider {
     ) -> Task<Option<Result<ExitStatus>>>;
 }
 
+pub trait McpToolProvider {
+    /// Calls the tool for the task labeled `label`, once the user allows it if they need to.
+    fn call_tool(
+        &self,
+        label: String,
+        call: McpToolCall,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<()>>;
+}
+
 pub trait DebuggerProvider {
     // `active_buffer` is used to resolve build task's name against language-specific tasks.
     fn start_session(
@@ -948,6 +959,7 @@ pub struct Workspace {
     on_prompt_for_open_path: Option<PromptForOpenPath>,
     terminal_provider: Option<Box<dyn TerminalProvider>>,
     debugger_provider: Option<Box<dyn DebuggerProvider>>,
+    mcp_tool_provider: Option<Box<dyn McpToolProvider>>,
     serializable_items_tx: UnboundedSender<Box<dyn SerializableItemHandle>>,
     serialized_ssh_project: Option<SerializedSshProject>,
     _items_serializer: Task<Result<()>>,
@@ -1280,6 +1292,7 @@ impl Workspace {
             on_prompt_for_open_path: None,
             terminal_provider: None,
             debugger_provider: None,
+            mcp_tool_provider: None,
             serializable_items_tx,
             _items_serializer,
             session_id: Some(session_id),
@@ -1831,6 +1844,10 @@ impl Workspace {
         self.debugger_provider = Some(Box::new(provider));
     }
 
+    pub fn set_mcp_tool_provider(&mut self, provider: impl McpToolProvider + 'static) {
+        self.mcp_tool_provider = Some(Box::new(provider));
+    }
+
     pub fn serialized_ssh_project(&self) -> Option<SerializedSshProject> {
         self.serialized_ssh_project.clone()
     }
@@ -109,15 +109,23 @@ impl Settings for WorktreeSettings {
                 current.file_scan_inclusions = Some(inclusions)
             }
         }
-        if let Some(exclusions) = vscode
-            .read_value("files.watcherExclude")
-            .and_then(|v| v.as_array())
-            .and_then(|v| v.iter().map(|n| n.as_str().map(str::to_owned)).collect())
-        {
-            if let Some(old) = current.file_scan_exclusions.as_mut() {
-                old.extend(exclusions)
-            } else {
-                current.file_scan_exclusions = Some(exclusions)
+        // VS Code stores exclusions as a map from glob to whether the glob is enabled.
+        let exclusions = ["files.exclude", "files.watcherExclude"]
+            .into_iter()
+            .filter_map(|key| vscode.read_value(key).and_then(|v| v.as_object()))
+            .flat_map(|globs| {
+                globs
+                    .iter()
+                    .filter(|(_, enabled)| enabled.as_bool() == Some(true))
+                    .map(|(glob, _)| glob.clone())
+            })
+            .collect::<Vec<_>>();
+        if !exclusions.is_empty() {
+            let old = current.file_scan_exclusions.get_or_insert_default();
+            for exclusion in exclusions {
+                if !old.contains(&exclusion) {
+                    old.push(exclusion);
+                }
             }
         }
     }
@@ -90,6 +90,7 @@ actions!(
         OpenTasks,
         OpenDebugTasks,
         ResetDatabase,
+        RestartLanguageServersWithProjectSettings,
         ShowAll,
         ToggleFullScreen,
         Zoom,
@@ -222,6 +223,9 @@ pub fn initialize_workspace(
         });
 
         let search_button = cx.new(|_| search::search_status_button::SearchButton::new());
+        let read_only_tools_button = cx.new(agent::ReadOnlyToolsButton::new);
+        let context_server_activity_indicator =
+            cx.new(|cx| agent::ContextServerActivityIndicator::new(workspace, cx));
         let diagnostic_summary =
             cx.new(|cx| diagnostics::items::DiagnosticIndicator::new(workspace, cx));
         let activity_indicator = activity_indicator::ActivityIndicator::new(
@@ -242,6 +246,8 @@ pub fn initialize_workspace(
             status_bar.add_left_item(search_button, window, cx);
             status_bar.add_left_item(diagnostic_summary, window, cx);
             status_bar.add_left_item(activity_indicator, window, cx);
+            status_bar.add_right_item(context_server_activity_indicator, window, cx);
+            status_bar.add_right_item(read_only_tools_button, window, cx);
             status_bar.add_right_item(inline_completion_button, window, cx);
             status_bar.add_right_item(active_buffer_language, window, cx);
             status_bar.add_right_item(active_toolchain_language, window, cx);
@@ -743,6 +749,7 @@ fn register_actions(
         .register_action(open_project_settings_file)
         .register_action(open_project_tasks_file)
         .register_action(open_project_debug_tasks_file)
+        .register_action(restart_language_servers_with_project_settings)
         .register_action(
             move |workspace, _: &zed_actions::OpenDefaultKeymap, window, cx| {
                 open_bundled_file(
@@ -1518,6 +1525,31 @@ fn open_project_debug_tasks_file(
     )
 }
 
+fn restart_language_servers_with_project_settings(
+    workspace: &mut Workspace,
+    _: &RestartLanguageServersWithProjectSettings,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let Some(buffer) = workspace
+        .active_item_as::<Editor>(cx)
+        .and_then(|editor| editor.read(cx).buffer().read(cx).as_singleton())
+    else {
+        return;
+    };
+    workspace
+        .project()
+        .update(cx, |project, cx| {
+            project.restart_language_servers_for_settings_file(buffer, cx)
+        })
+        .detach_and_prompt_err(
+            "Failed to restart language servers",
+            window,
+            cx,
+            |_, _, _| None,
+        );
+}
+
 fn open_local_file(
     workspace: &mut Workspace,
     settings_relative_path: &'static Path,
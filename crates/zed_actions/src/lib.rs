@@ -38,6 +38,8 @@ actions!(
         OpenDocs,
         OpenLicenses,
         OpenTelemetryLog,
+        SuggestVsCodeExtensions,
+        ShowEffectiveToolPermissions,
     ]
 );
 
@@ -114,7 +116,7 @@ impl_actions!(
 pub mod dev {
     use gpui::actions;
 
-    actions!(dev, [ToggleInspector]);
+    actions!(dev, [ToggleInspector, OpenContextServerInspector]);
 }
 
 pub mod workspace {
@@ -197,12 +199,34 @@ pub mod icon_theme_selector {
 }
 
 pub mod agent {
-    use gpui::actions;
+    use gpui::{actions, impl_actions};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
 
     actions!(
         agent,
         [OpenConfiguration, OpenOnboardingModal, ResetOnboarding]
     );
+
+    /// Lets an MCP server run in the current project again.
+    #[derive(PartialEq, Clone, Default, Debug, Deserialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    pub struct EnableMcpServer {
+        /// The server to enable, or `None` to pick one.
+        #[serde(default)]
+        pub server_id: Option<String>,
+    }
+
+    /// Stops an MCP server and keeps it from running in the current project.
+    #[derive(PartialEq, Clone, Default, Debug, Deserialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    pub struct DisableMcpServer {
+        /// The server to disable, or `None` to pick one.
+        #[serde(default)]
+        pub server_id: Option<String>,
+    }
+
+    impl_actions!(agent, [EnableMcpServer, DisableMcpServer]);
 }
 
 pub mod assistant {